@@ -1,19 +1,23 @@
-#![feature(file_buffered)]
-#![feature(trim_prefix_suffix)]
-
-use anyhow::anyhow;
 use copy_dir::copy_dir;
 use glob::glob;
+use paths::GamePath;
 use std::{
     collections::HashMap,
     fs::{self, File, OpenOptions},
-    io::{self, Read},
+    io::{self, ErrorKind, Read},
     path::Path,
 };
 use thiserror::Error;
-use typed_path::{CheckedPathError, Utf8PlatformPath, Utf8PlatformPathBuf};
+use typed_path::{CheckedPathError, StripPrefixError, Utf8PlatformPath, Utf8PlatformPathBuf};
 use vpk::VPK;
 
+pub mod packaging;
+pub mod relocation;
+pub mod removal;
+pub mod thumbnail;
+pub mod unextracted;
+pub mod validation;
+
 #[derive(Debug)]
 pub struct Info {
     pub name: String,
@@ -38,12 +42,29 @@ pub struct Addon {
     // pub relative_material_files: HashMap<String, Material>,
     /// A map of absolute PCF paths to decoded PCFs, provided by the addon
     pub particle_files: HashMap<Utf8PlatformPathBuf, pcf::new::Pcf>,
+
+    /// Parts of the addon's PCFs which were skipped while decoding them, because they were invalid
+    pub particle_warnings: Vec<(Utf8PlatformPathBuf, pcf::new::DecodeWarning)>,
+
+    /// The absolute path to the addon's preview image, if it has one. See [`thumbnail::find_thumbnail`]
+    pub thumbnail: Option<Utf8PlatformPathBuf>,
+
+    /// Whether the addon's content is in [`Addon::content_path`]. An addon read with [`Addon::parse_from_vpk`] isn't
+    /// extracted until [`Addon::extract`] is called, so its PCF & thumbnail paths point to where they will be.
+    pub extracted: bool,
 }
 
 impl Addon {
     pub fn name(&self) -> &str {
         self.source_path.file_name().unwrap()
     }
+
+    /// Shares the symbols of every PCF in this addon through `pool`, see [`pcf::symbol`].
+    pub fn intern_symbols(&mut self, pool: &pcf::SymbolPool) {
+        for pcf in self.particle_files.values_mut() {
+            pcf.intern_symbols(pool);
+        }
+    }
 }
 
 #[derive(Debug, Clone)]
@@ -67,19 +88,104 @@ pub struct Material {
     pub normal_map_2: Option<String>,
 }
 
+#[derive(Debug, Error)]
+pub enum MaterialError {
+    #[error(transparent)]
+    KeyValues(#[from] keyvalues_parser::error::Error),
+
+    #[error("expected a shader block")]
+    MissingShaderBlock,
+}
+
+impl Material {
+    /// Parses the VMT contents in `vmt`, for the material at `relative_path`.
+    ///
+    /// # Errors
+    ///
+    /// See [`MaterialError`].
+    pub fn parse(relative_path: Utf8PlatformPathBuf, vmt: &str) -> Result<Self, MaterialError> {
+        fn value_to_texture_name(cow: &str) -> String {
+            let owned = cow.to_owned();
+            if owned.eq_ignore_ascii_case(".vtf") {
+                owned
+            } else {
+                owned + ".vtf"
+            }
+        }
+
+        let root = keyvalues_parser::parse(vmt)?;
+
+        // vtf parameters will always be keys on the first value
+        let keyvalues_parser::Value::Obj(values) = root.value else {
+            return Err(MaterialError::MissingShaderBlock);
+        };
+
+        let mut material = Material {
+            relative_path,
+            base_texture: None,
+            detail: None,
+            ramp_texture: None,
+            normal_map: None,
+            normal_map_2: None,
+        };
+
+        for (key, values) in values.iter() {
+            let Some(keyvalues_parser::Value::Str(value)) = values.first() else {
+                continue;
+            };
+
+            match key as &str {
+                "$basetexture" => material.base_texture = Some(value_to_texture_name(value)),
+                "$detail" => material.detail = Some(value_to_texture_name(value)),
+                "$ramptexture" => material.ramp_texture = Some(value_to_texture_name(value)),
+                "$normalmap" => material.normal_map = Some(value_to_texture_name(value)),
+                "$normalmap2" => material.normal_map_2 = Some(value_to_texture_name(value)),
+                _ => {}
+            }
+        }
+
+        Ok(material)
+    }
+
+    /// Every texture this material references, relative to `{path_to_game}/materials/`.
+    pub fn textures(&self) -> impl Iterator<Item = &str> {
+        [
+            &self.base_texture,
+            &self.detail,
+            &self.ramp_texture,
+            &self.normal_map,
+            &self.normal_map_2,
+        ]
+        .into_iter()
+        .flatten()
+        .map(String::as_str)
+    }
+}
+
 #[derive(Debug)]
 pub struct Extracted {
     source_path: Utf8PlatformPathBuf,
     content_path: Utf8PlatformPathBuf,
 }
 
+/// Why one of an addon's PCFs couldn't be read.
 #[derive(Debug, Error)]
-pub enum ParseError {
+pub enum ParticleError {
     #[error(transparent)]
-    Dmx(#[from] dmx::dmx::Error),
+    Dmx(#[from] dmx::dmx::DecodeError),
 
     #[error(transparent)]
     Pcf(#[from] pcf::new::Error),
+}
+
+#[derive(Debug, Error)]
+pub enum ParseError {
+    #[error("corrupt PCF '{path}'")]
+    Particle {
+        path: Utf8PlatformPathBuf,
+        #[source]
+        source: ParticleError,
+    },
 
     #[error(transparent)]
     Glob(#[from] glob::GlobError),
@@ -90,6 +196,22 @@ pub enum ParseError {
     #[error(transparent)]
     CheckedPath(#[from] CheckedPathError),
 
+    #[error(transparent)]
+    StripPrefix(#[from] StripPrefixError),
+
+    #[error("couldn't get last component from addon path: {0}")]
+    MissingFileName(Utf8PlatformPathBuf),
+
+    #[error(transparent)]
+    Vpk(#[from] vpk::Error),
+
+    #[error("malformed VMT '{path}'")]
+    Material {
+        path: Utf8PlatformPathBuf,
+        #[source]
+        source: MaterialError,
+    },
+
     #[error(transparent)]
     Io(#[from] io::Error),
 }
@@ -103,16 +225,7 @@ impl Extracted {
         &self.source_path
     }
 
-    fn get_material_files(materials_path: &Utf8PlatformPath) -> anyhow::Result<HashMap<String, Material>> {
-        fn value_to_texture_name(cow: &str) -> String {
-            let owned = cow.to_owned();
-            if owned.eq_ignore_ascii_case(".vtf") {
-                owned
-            } else {
-                owned + ".vtf"
-            }
-        }
-
+    fn get_material_files(materials_path: &Utf8PlatformPath) -> Result<HashMap<String, Material>, ParseError> {
         let mut relative_material_files = HashMap::new();
         for path in glob(&format!("{materials_path}/**/*.vmt"))? {
             let path = path?;
@@ -122,41 +235,37 @@ impl Extracted {
             let mut vmt_buf = String::new();
             File::open(&path)?.read_to_string(&mut vmt_buf)?;
 
-            let root = keyvalues_parser::parse(&vmt_buf)?;
+            let material = Material::parse(relative_path.clone(), &vmt_buf)
+                .map_err(|source| ParseError::Material { path, source })?;
 
-            // vtf parameters will always be keys on the first value
-            let keyvalues_parser::Value::Obj(values) = root.value else {
-                return Err(anyhow!("malformed VMT '{}'", &path));
-            };
+            relative_material_files.insert(relative_path.into_string(), material);
+        }
 
-            let mut material = Material {
-                relative_path: relative_path.clone(),
-                base_texture: None,
-                detail: None,
-                ramp_texture: None,
-                normal_map: None,
-                normal_map_2: None,
-            };
+        Ok(relative_material_files)
+    }
 
-            for (key, values) in values.iter() {
-                let Some(keyvalues_parser::Value::Str(value)) = values.first() else {
-                    continue;
-                };
-
-                match key as &str {
-                    "$basetexture" => material.base_texture = Some(value_to_texture_name(value)),
-                    "$detail" => material.detail = Some(value_to_texture_name(value)),
-                    "$ramptexture" => material.ramp_texture = Some(value_to_texture_name(value)),
-                    "$normalmap" => material.normal_map = Some(value_to_texture_name(value)),
-                    "$normalmap2" => material.normal_map_2 = Some(value_to_texture_name(value)),
-                    _ => {}
-                }
+    /// Every PCF directly in the addon's `particles` folder, sorted. The engine finds them regardless of case, so neither
+    /// the folder's nor the PCFs' names have to be lowercase.
+    fn particle_paths(content_path: &Utf8PlatformPath) -> io::Result<Vec<Utf8PlatformPathBuf>> {
+        let mut pcf_paths = Vec::new();
+        for entry in fs::read_dir(content_path)? {
+            let entry = entry?;
+            let is_particles_dir = GamePath::new(&entry.file_name().to_string_lossy()).as_str() == "particles";
+            if !is_particles_dir || !entry.file_type()?.is_dir() {
+                continue;
             }
 
-            relative_material_files.insert(relative_path.into_string(), material.clone());
+            for entry in fs::read_dir(entry.path())? {
+                let entry = entry?;
+                let is_pcf = GamePath::new(&entry.file_name().to_string_lossy()).extension() == Some("pcf");
+                if is_pcf && entry.file_type()?.is_file() {
+                    pcf_paths.push(paths::std_buf_to_typed(entry.path()));
+                }
+            }
         }
 
-        Ok(relative_material_files)
+        pcf_paths.sort();
+        Ok(pcf_paths)
     }
 
     /// parses the contents of an extracted addon into an [`Addon`].
@@ -167,20 +276,19 @@ impl Extracted {
     ///
     /// - iterating over extracted files fails
     /// - some [`std::io::Error`] when opening or reading files
-    /// - the addon contains invalid or inoperable parts, such as a corrupted PCF.
+    /// - the addon contains invalid or inoperable parts, such as a corrupted PCF. Invalid parts of an otherwise
+    ///   readable PCF are skipped, and recorded in [`Addon::particle_warnings`] instead.
     pub fn parse_content(self) -> Result<Addon, ParseError> {
         let mut particle_files = HashMap::new();
-        let particles_path = self.content_path.join_checked("particles")?;
-        for path in glob(&format!("{particles_path}/*.pcf"))? {
-            let path = path?;
-            let path = paths::to_typed(&path);
-
-            let mut file = File::open_buffered(path.as_ref())?;
-            let dmx = dmx::decode(&mut file)?;
-            let pcf = pcf::new::Pcf::try_from(dmx)?;
-            particle_files.insert(path.into_owned(), pcf);
+        let mut particle_warnings = Vec::new();
+        for path in Self::particle_paths(&self.content_path)? {
+            let (pcf, warnings) = read_particle(&path, dmx::decode_bytes(fs::read(&path)?.into()))?;
+            particle_warnings.extend(warnings.into_iter().map(|warning| (path.clone(), warning)));
+            particle_files.insert(path, pcf);
         }
 
+        let thumbnail = thumbnail::find_thumbnail(&self.content_path)?;
+
         // let materials_path = self.content_path.join_checked("materials")?;
         // let relative_material_files = Self::get_material_files(&materials_path)?;
 
@@ -198,10 +306,31 @@ impl Extracted {
             // texture_files,
             // relative_material_files,
             particle_files,
+            particle_warnings,
+            thumbnail,
+            extracted: true,
         })
     }
 }
 
+/// Reads the PCF at `path` from the result of decoding it.
+///
+/// # Errors
+///
+/// Returns [`ParseError::Particle`] if the PCF couldn't be decoded, or isn't a valid PCF.
+pub(crate) fn read_particle(
+    path: &Utf8PlatformPath,
+    decoded: Result<dmx::Dmx, dmx::dmx::DecodeError>,
+) -> Result<(pcf::new::Pcf, Vec<pcf::new::DecodeWarning>), ParseError> {
+    decoded
+        .map_err(ParticleError::from)
+        .and_then(|dmx| Ok(pcf::new::Pcf::try_from_dmx_lenient(dmx)?))
+        .map_err(|source| ParseError::Particle {
+            path: path.to_owned(),
+            source,
+        })
+}
+
 #[derive(Debug)]
 /// A collection of all sources read with [`Sources::read_dir`].
 pub struct Sources {
@@ -221,7 +350,15 @@ impl Sources {
         let mut failures = Vec::new();
         for entry in addons_dir.as_ref().read_dir()? {
             let entry = entry?;
-            let path = paths::std_buf_to_typed(entry.path());
+            let path = match paths::try_buf_to_typed(entry.path()) {
+                Ok(path) => path,
+                Err(err) => {
+                    // the lossy path is only used to show the user which entry failed
+                    failures.push((paths::std_buf_to_typed(entry.path()), err.into()));
+                    continue;
+                }
+            };
+
             match Source::from_path(&path) {
                 Ok(source) => sources.push(source),
                 Err(err) => failures.push((path, err)),
@@ -274,6 +411,29 @@ pub enum Error {
 
     #[error(transparent)]
     Utf8(#[from] std::str::Utf8Error),
+
+    #[error(transparent)]
+    Path(#[from] paths::PathError),
+}
+
+/// Appended to an extracted addon's folder name to name the file recording the hash of the source it was extracted
+/// from, see [`ExtractionStrategy::ReuseIfHashMatches`].
+pub const SOURCE_HASH_SUFFIX: &str = ".source_hash";
+
+/// What [`Source::extract_as_subfolder_in`] does when the destination subfolder already exists.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ExtractionStrategy<'a> {
+    /// Fails with [`ExtractionError::ExtractionDestinationAlreadyExists`].
+    Error,
+
+    /// Replaces the existing subfolder once the source has been extracted, so that the destination always has either
+    /// the old or the new content, never part of either.
+    ReplaceAtomically,
+
+    /// Keeps the existing subfolder if it was extracted from a source with this hash, and otherwise replaces it like
+    /// [`ExtractionStrategy::ReplaceAtomically`]. The hash is recorded next to the subfolder, with
+    /// [`SOURCE_HASH_SUFFIX`], to be compared against the next time.
+    ReuseIfHashMatches(&'a str),
 }
 
 #[derive(Debug, Error)]
@@ -293,6 +453,21 @@ pub enum ExtractionError {
     #[error("expected to copy {0} bytes, instead copied {1}, when copying from {2} to {3}")]
     UnexpectedCopyResult(u64, u64, String, String),
 
+    #[error("not enough free space to extract to '{destination}': {required} bytes required, {available} available")]
+    InsufficientSpace {
+        destination: Utf8PlatformPathBuf,
+        required: u64,
+        available: u64,
+    },
+
+    #[error("the entry '{entry}' in '{source_vpk}' would be extracted outside of the addon")]
+    UnsafeEntryPath {
+        source_vpk: Utf8PlatformPathBuf,
+        entry: String,
+        #[source]
+        source: CheckedPathError,
+    },
+
     #[error(transparent)]
     Vpk(#[from] vpk::Error),
 
@@ -347,6 +522,10 @@ impl Source {
     ///
     /// For example, if the Source points to a file `/path/to/addon.vpk` then the subfolder will be `{parent}/addon.vpk/`.
     ///
+    /// The source is extracted into a temporary folder next to the subfolder, which is renamed once the extraction is
+    /// complete, so an extraction which fails never leaves part of the source behind. `strategy` decides what's done
+    /// when the subfolder already exists.
+    ///
     /// Returns [`Extracted`] pointing to the extracted contents.
     ///
     /// ## Errors
@@ -356,9 +535,14 @@ impl Source {
     /// - the source is missing a file or directory name
     /// - a valid subfolder path couldn't be formed
     /// - `parent` doesn't exist
-    /// - the destination subfolder already exists
+    /// - the destination subfolder already exists, and `strategy` is [`ExtractionStrategy::Error`]
+    /// - there isn't enough free space at `parent` for the source's contents
     /// - there was an error extracting the source's contents, e.g. not enough permissions to write to the folder
-    pub fn extract_as_subfolder_in(&self, parent: &Utf8PlatformPath) -> Result<Extracted, ExtractionError> {
+    pub fn extract_as_subfolder_in(
+        &self,
+        parent: &Utf8PlatformPath,
+        strategy: ExtractionStrategy,
+    ) -> Result<Extracted, ExtractionError> {
         let source_path = match self {
             Source::Folder(source_path) | Source::Vpk(source_path) => source_path,
         };
@@ -368,44 +552,152 @@ impl Source {
             .ok_or_else(|| ExtractionError::CouldntGetAddonFileName(source_path.to_owned()))?;
 
         let destination = parent.join_checked(last_part)?;
+        let hash_path = parent.join_checked(format!("{last_part}{SOURCE_HASH_SUFFIX}"))?;
 
         if !fs::exists(parent)? {
             return Err(ExtractionError::MissingAddonParentPath(parent.to_owned()));
         }
 
-        if fs::exists(&destination)? {
-            return Err(ExtractionError::ExtractionDestinationAlreadyExists(
-                destination.to_owned(),
-            ));
+        let extracted = Extracted {
+            source_path: source_path.clone(),
+            content_path: destination.clone(),
+        };
+
+        let exists = fs::exists(&destination)?;
+        match strategy {
+            ExtractionStrategy::Error if exists => {
+                return Err(ExtractionError::ExtractionDestinationAlreadyExists(destination));
+            }
+            ExtractionStrategy::ReuseIfHashMatches(hash)
+                if exists && fs::read_to_string(&hash_path).is_ok_and(|recorded| recorded == hash) =>
+            {
+                return Ok(extracted);
+            }
+            _ => {}
         }
 
-        match self {
+        let required = self.content_size()?;
+        let available = paths::available_space(parent)?;
+        if required > available {
+            return Err(ExtractionError::InsufficientSpace {
+                destination,
+                required,
+                available,
+            });
+        }
+
+        // a temporary folder left behind by an extraction which was interrupted is of no use
+        let temporary = parent.join_checked(format!(".{last_part}.extracting"))?;
+        remove_dir_if_exists(&temporary)?;
+
+        let result = match self {
             Source::Folder(source_path) => {
-                let errors = copy_dir(source_path, &destination)?;
-                if !errors.is_empty() {
-                    return Err(ExtractionError::CopyFailed(errors));
+                let errors = copy_dir(&*paths::long_path(source_path), &*paths::long_path(&temporary))?;
+                if errors.is_empty() {
+                    Ok(())
+                } else {
+                    Err(ExtractionError::CopyFailed(errors))
                 }
             }
-            Source::Vpk(source_path) => Self::extract_vpk(source_path, &destination)?,
+            Source::Vpk(source_path) => Self::extract_vpk(source_path, &temporary),
+        };
+
+        if let Err(err) = result {
+            remove_dir_if_exists(&temporary)?;
+            return Err(err);
         }
 
-        Ok(Extracted {
-            source_path: source_path.clone(),
-            content_path: destination,
-        })
+        // the old content has to be moved aside first, since a folder can't be renamed over another on every platform
+        let replaced = parent.join_checked(format!(".{last_part}.replaced"))?;
+        if exists {
+            remove_dir_if_exists(&replaced)?;
+            fs::rename(&destination, &replaced)?;
+        }
+
+        if let Err(err) = fs::rename(&temporary, &destination) {
+            // the old content is put back, so that the destination isn't left empty
+            if exists {
+                fs::rename(&replaced, &destination)?;
+            }
+
+            return Err(err.into());
+        }
+
+        if exists {
+            remove_dir_if_exists(&replaced)?;
+        }
+
+        // a hash recorded before describes the content which was just replaced
+        match strategy {
+            ExtractionStrategy::ReuseIfHashMatches(hash) => fs::write(&hash_path, hash)?,
+            _ => match fs::remove_file(&hash_path) {
+                Err(err) if err.kind() == ErrorKind::NotFound => {}
+                result => result?,
+            },
+        }
+
+        Ok(extracted)
+    }
+
+    /// Returns the number of bytes the source's contents will take up once extracted.
+    ///
+    /// ## Errors
+    ///
+    /// Errors if the source's files or the VPK's directory couldn't be read.
+    pub fn content_size(&self) -> Result<u64, ExtractionError> {
+        fn dir_size(dir: impl AsRef<Path>) -> io::Result<u64> {
+            let mut size = 0;
+            for entry in fs::read_dir(dir)? {
+                let entry = entry?;
+                let metadata = entry.metadata()?;
+                if metadata.is_dir() {
+                    size += dir_size(entry.path())?;
+                } else {
+                    size += metadata.len();
+                }
+            }
+
+            Ok(size)
+        }
+
+        match self {
+            Source::Folder(source_path) => Ok(dir_size(source_path)?),
+            Source::Vpk(source_path) => {
+                let vpk = VPK::read(source_path)?;
+                Ok(vpk
+                    .tree
+                    .values()
+                    .map(|entry| u64::from(entry.dir_entry.file_length) + u64::from(entry.dir_entry.preload_length))
+                    .sum())
+            }
+        }
     }
 
     /// Extracts the entire file tree from a vpk at `source_vpk` to a target directory `to_dir`.
-    fn extract_vpk(source_vpk: impl AsRef<Path>, to_dir: impl AsRef<Path>) -> Result<(), ExtractionError> {
-        let vpk = VPK::read(&source_vpk)?;
+    ///
+    /// Nothing is extracted if any entry's path would escape `to_dir`, e.g. with `../` components or an absolute path.
+    fn extract_vpk(source_vpk: &Utf8PlatformPath, to_dir: &Utf8PlatformPath) -> Result<(), ExtractionError> {
+        let vpk = VPK::read(source_vpk)?;
+
+        let entries = vpk
+            .tree
+            .into_iter()
+            .map(|(entry_path, entry)| {
+                let file_path =
+                    checked_entry_path(to_dir, &entry_path).map_err(|source| ExtractionError::UnsafeEntryPath {
+                        source_vpk: source_vpk.to_owned(),
+                        entry: entry_path.clone(),
+                        source,
+                    })?;
+
+                Ok((entry_path, file_path, entry))
+            })
+            .collect::<Result<Vec<_>, ExtractionError>>()?;
 
         // TODO: make vpk extraction asynchronous/threaded
-        for (entry_path, entry) in vpk.tree {
+        for (entry_path, file_path, entry) in entries {
             let mut file_in_vpk = entry.reader()?;
 
-            let entry_path = entry_path.trim_prefix('/');
-            let file_path = to_dir.as_ref().join(entry_path);
-
             if let Some(parent) = file_path.parent() {
                 fs::create_dir_all(parent)?;
             }
@@ -418,8 +710,8 @@ impl Source {
                 return Err(ExtractionError::UnexpectedCopyResult(
                     entry_size,
                     copied,
-                    format!("{}/{entry_path}", source_vpk.as_ref().display()),
-                    file_path.to_string_lossy().into_owned(),
+                    format!("{source_vpk}/{}", entry_path.strip_prefix('/').unwrap_or(&entry_path)),
+                    file_path.into_string(),
                 ));
             }
         }
@@ -427,3 +719,90 @@ impl Source {
         Ok(())
     }
 }
+
+fn remove_dir_if_exists(dir: &Utf8PlatformPath) -> io::Result<()> {
+    match fs::remove_dir_all(&*paths::long_path(dir)) {
+        Err(err) if err.kind() == ErrorKind::NotFound => Ok(()),
+        result => result,
+    }
+}
+
+/// Joins a VPK entry path onto `to_dir`, the same way [`Source::extract_vpk`] would extract it.
+///
+/// # Errors
+///
+/// Returns [`CheckedPathError`] if the resulting path would be outside of `to_dir`, or if the entry contains a `\` or
+/// `:`.
+pub(crate) fn checked_entry_path(
+    to_dir: &Utf8PlatformPath,
+    entry_path: &str,
+) -> Result<Utf8PlatformPathBuf, CheckedPathError> {
+    // a `\` is a separator & a `C:` a drive prefix on windows, but not elsewhere; so they're rejected on every platform,
+    // rather than an entry only escaping `to_dir` on windows
+    if entry_path.contains(['\\', ':']) {
+        return Err(CheckedPathError::InvalidFilename);
+    }
+
+    // VPK entries are rooted, so the leading separator is dropped to make them relative to `to_dir`
+    to_dir.join_checked(entry_path.strip_prefix('/').unwrap_or(entry_path))
+}
+
+#[cfg(test)]
+mod entry_path_tests {
+    use std::{env, fs, process};
+
+    use typed_path::{Utf8PlatformPath, Utf8PlatformPathBuf};
+    use writevpk::pack::{self, GeneratedFile};
+
+    use crate::{Source, checked_entry_path};
+
+    fn temp_dir(name: &str) -> Utf8PlatformPathBuf {
+        let path = env::temp_dir().join(format!("addon-{name}-{}", process::id()));
+        let _ = fs::remove_dir_all(&path);
+        fs::create_dir_all(&path).unwrap();
+        Utf8PlatformPathBuf::from(path.to_str().unwrap())
+    }
+
+    #[test]
+    fn entries_inside_the_dir_are_joined() {
+        let to_dir = Utf8PlatformPath::new("addon");
+        for entry_path in ["materials/x.vmt", "/materials/x.vmt"] {
+            assert!(
+                checked_entry_path(to_dir, entry_path).is_ok(),
+                "{entry_path} should be allowed"
+            );
+        }
+    }
+
+    #[test]
+    fn entries_outside_the_dir_are_rejected() {
+        let to_dir = Utf8PlatformPath::new("addon");
+        for entry_path in ["../x", "a/../../x", "//etc/x", "C:/x", "..\\x", "a\\..\\..\\x"] {
+            assert!(
+                checked_entry_path(to_dir, entry_path).is_err(),
+                "{entry_path} should be rejected"
+            );
+        }
+    }
+
+    #[test]
+    fn nothing_is_extracted_from_a_vpk_with_a_bad_entry() {
+        let dir = temp_dir("bad-entry");
+        fs::create_dir_all(dir.join("source/materials")).unwrap();
+        fs::write(dir.join("source/materials/x.vmt"), "\"UnlitGeneric\" {}").unwrap();
+
+        let generated = GeneratedFile {
+            path: "../escaped.txt",
+            generate: Box::new(|_| b"escaped".to_vec()),
+        };
+        pack::pack_directory(&dir.join("source"), &dir, "addon", u32::MAX, Some(generated)).unwrap();
+
+        let to_dir = dir.join("extracted");
+        fs::create_dir_all(&to_dir).unwrap();
+        assert!(Source::extract_vpk(&dir.join("addon.vpk"), &to_dir).is_err());
+
+        assert_eq!(fs::read_dir(&to_dir).unwrap().count(), 0);
+        assert!(!fs::exists(dir.join("escaped.txt")).unwrap());
+        fs::remove_dir_all(&dir).unwrap();
+    }
+}
@@ -0,0 +1,266 @@
+//! Packages a mod folder into a single VPK which is ready to share. The folder's layout is checked, junk & source files
+//! which the game never loads are left out, and PCFs can optionally be stripped before they're packed.
+
+use std::{
+    fs::{self, File},
+    io::{self, BufReader, BufWriter, Write},
+};
+
+use pcf::Pcf;
+use thiserror::Error;
+use typed_path::{Utf8PlatformPath, Utf8PlatformPathBuf};
+
+/// The folders TF2 loads custom content from. Anything else at the top of a mod folder is left out.
+pub const CONTENT_DIRS: [&str; 9] = [
+    "cfg",
+    "maps",
+    "materials",
+    "media",
+    "models",
+    "particles",
+    "resource",
+    "scripts",
+    "sound",
+];
+
+/// Files created by operating systems & version control, which are never part of a mod.
+const JUNK_FILE_NAMES: [&str; 5] = ["thumbs.db", "desktop.ini", ".ds_store", ".gitignore", ".gitattributes"];
+
+/// The extensions of source files for image, model & audio editors, which the game can't load.
+const SOURCE_EXTENSIONS: [&str; 12] = [
+    "psd", "xcf", "kra", "pdn", "ai", "blend", "blend1", "max", "ma", "mb", "bak", "tmp",
+];
+
+#[derive(Default)]
+pub struct PackOptions<'a> {
+    /// Applied to every PCF before it's packed, e.g. to strip its default attribute values.
+    pub strip_pcf: Option<&'a dyn Fn(Pcf) -> Pcf>,
+}
+
+#[derive(Debug, Clone, Default)]
+pub struct PackReport {
+    pub packed_files: usize,
+    pub packed_bytes: u64,
+
+    /// the files & folders which were left out, relative to the mod folder, along with why
+    pub skipped: Vec<(String, &'static str)>,
+
+    /// the number of bytes saved by [`PackOptions::strip_pcf`]
+    pub stripped_bytes: u64,
+}
+
+#[derive(Debug, Error)]
+pub enum PackError {
+    #[error("'{0}' isn't a folder")]
+    NotAFolder(Utf8PlatformPathBuf),
+
+    #[error("'{0}' has none of the folders TF2 loads content from, like materials/, models/, or particles/")]
+    NoContent(Utf8PlatformPathBuf),
+
+    #[error("the output path '{0}' must be a .vpk file")]
+    InvalidOutput(Utf8PlatformPathBuf),
+
+    #[error("the PCF '{path}' couldn't be decoded")]
+    Pcf {
+        path: String,
+        #[source]
+        source: pcf::DecodeError,
+    },
+
+    #[error("the PCF '{path}' couldn't be encoded")]
+    PcfEncode {
+        path: String,
+        #[source]
+        source: dmx::dmx::EncodeError,
+    },
+
+    #[error(transparent)]
+    Vpk(#[from] writevpk::pack::Error),
+
+    #[error(transparent)]
+    Io(#[from] io::Error),
+}
+
+/// Packs the content of the mod folder `source` into a VPK at `output`. A copy of the content is staged next to
+/// `output` while packing, and removed afterwards.
+///
+/// # Errors
+///
+/// Returns [`Err`] if `source` isn't a folder with any content, if `output` isn't a `.vpk` path, or if any file
+/// couldn't be read or written.
+pub fn pack_folder(
+    source: &Utf8PlatformPath,
+    output: &Utf8PlatformPath,
+    options: &PackOptions,
+) -> Result<PackReport, PackError> {
+    if !fs::metadata(source)?.is_dir() {
+        return Err(PackError::NotAFolder(source.to_path_buf()));
+    }
+
+    let (Some(dest), Some(vpk_name)) = (output.parent(), output.file_stem()) else {
+        return Err(PackError::InvalidOutput(output.to_path_buf()));
+    };
+
+    if !output
+        .extension()
+        .is_some_and(|extension| extension.eq_ignore_ascii_case("vpk"))
+    {
+        return Err(PackError::InvalidOutput(output.to_path_buf()));
+    }
+
+    // N.B. a bare file name has an empty parent
+    let dest = if dest.as_str().is_empty() {
+        Utf8PlatformPath::new(".")
+    } else {
+        dest
+    };
+
+    let staging = dest.join(format!(".{vpk_name}.staging"));
+    if let Err(err) = fs::remove_dir_all(&staging)
+        && err.kind() != io::ErrorKind::NotFound
+    {
+        return Err(err.into());
+    }
+
+    fs::create_dir_all(&staging)?;
+    let result = stage_and_pack(source, &staging, dest, vpk_name, options);
+    let cleanup = fs::remove_dir_all(&staging);
+
+    let report = result?;
+    cleanup?;
+    Ok(report)
+}
+
+fn stage_and_pack(
+    source: &Utf8PlatformPath,
+    staging: &Utf8PlatformPath,
+    dest: &Utf8PlatformPath,
+    vpk_name: &str,
+    options: &PackOptions,
+) -> Result<PackReport, PackError> {
+    let mut report = PackReport::default();
+    let mut has_content = false;
+
+    for entry in fs::read_dir(source)? {
+        let entry = entry?;
+        let name = entry.file_name().to_string_lossy().into_owned();
+        if !entry.file_type()?.is_dir() {
+            report
+                .skipped
+                .push((name, "files outside of a content folder aren't loaded"));
+            continue;
+        }
+
+        // the game's filesystem is case-insensitive, but the packed paths are compared as-is
+        let content_dir = name.to_ascii_lowercase();
+        if !CONTENT_DIRS.contains(&content_dir.as_str()) {
+            report.skipped.push((name, "TF2 doesn't load content from this folder"));
+            continue;
+        }
+
+        has_content = true;
+        stage_dir(
+            &source.join(&name),
+            &staging.join(&content_dir),
+            &content_dir,
+            options,
+            &mut report,
+        )?;
+    }
+
+    if !has_content {
+        return Err(PackError::NoContent(source.to_path_buf()));
+    }
+
+    // a shared addon should be a single file, so the content is never split into numbered archives
+    writevpk::pack::pack_directory(staging, dest, vpk_name, u32::MAX, None)?;
+
+    Ok(report)
+}
+
+/// Copies the files in `source` to `staging`, leaving out junk & source files. `relative` is the path of `source`
+/// relative to the mod folder.
+fn stage_dir(
+    source: &Utf8PlatformPath,
+    staging: &Utf8PlatformPath,
+    relative: &str,
+    options: &PackOptions,
+    report: &mut PackReport,
+) -> Result<(), PackError> {
+    fs::create_dir_all(staging)?;
+
+    for entry in fs::read_dir(source)? {
+        let entry = entry?;
+        let name = entry.file_name().to_string_lossy().into_owned();
+        let relative = format!("{relative}/{name}");
+        let file_type = entry.file_type()?;
+
+        if file_type.is_dir() {
+            if name.starts_with('.') {
+                report.skipped.push((relative, "hidden folders aren't part of a mod"));
+                continue;
+            }
+
+            stage_dir(&source.join(&name), &staging.join(&name), &relative, options, report)?;
+            continue;
+        }
+
+        if let Some(reason) = skip_reason(&name) {
+            report.skipped.push((relative, reason));
+            continue;
+        }
+
+        let source_path = source.join(&name);
+        let staged_path = staging.join(&name);
+        let is_pcf = source_path
+            .extension()
+            .is_some_and(|extension| extension.eq_ignore_ascii_case("pcf"));
+        match options.strip_pcf {
+            Some(strip_pcf) if is_pcf => {
+                let original_size = fs::metadata(&source_path)?.len();
+                let mut reader = BufReader::new(File::open(&source_path)?);
+                let pcf = pcf::decode(&mut reader).map_err(|source| PackError::Pcf {
+                    path: relative.clone(),
+                    source,
+                })?;
+
+                let pcf = strip_pcf(pcf);
+                let size = pcf.encoded_size() as u64;
+
+                let mut writer = BufWriter::new(File::create(&staged_path)?);
+                pcf::encode(pcf, &mut writer).map_err(|source| PackError::PcfEncode {
+                    path: relative.clone(),
+                    source,
+                })?;
+                writer.flush()?;
+
+                report.stripped_bytes += original_size.saturating_sub(size);
+                report.packed_bytes += size;
+            }
+            _ => report.packed_bytes += fs::copy(&source_path, &staged_path)?,
+        }
+
+        report.packed_files += 1;
+    }
+
+    Ok(())
+}
+
+/// Why the file named `name` shouldn't be packed, if it shouldn't be.
+fn skip_reason(name: &str) -> Option<&'static str> {
+    let lowercase = name.to_ascii_lowercase();
+    if JUNK_FILE_NAMES.contains(&lowercase.as_str()) || lowercase.starts_with("._") {
+        return Some("operating system & version control files aren't part of a mod");
+    }
+
+    if lowercase.ends_with('~') {
+        return Some("backup files aren't part of a mod");
+    }
+
+    let extension = lowercase.rsplit_once('.').map(|(_, extension)| extension)?;
+    if SOURCE_EXTENSIONS.contains(&extension) {
+        return Some("source files can't be loaded by the game");
+    }
+
+    None
+}
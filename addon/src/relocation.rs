@@ -0,0 +1,275 @@
+//! Moves an addon's materials & textures to new paths, rewriting every VMT and PCF which references them so the addon
+//! still renders the same.
+//!
+//! Source references materials & textures by their path relative to `materials/`, usually without an extension, so a
+//! [`Relocation`] maps those extensionless paths. Relocating `effects/fire` moves both `effects/fire.vmt` and
+//! `effects/fire.vtf`.
+
+use std::{collections::HashMap, fs, io, ops::Range};
+
+use glob::glob;
+use thiserror::Error;
+use typed_path::{CheckedPathError, Utf8PlatformPathBuf};
+
+use crate::Addon;
+
+/// A map of old material & texture paths to new ones, relative to `{path_to_game}/materials/` and without extensions.
+/// Paths are matched ignoring case & slash direction, like the game does.
+#[derive(Debug, Clone, Default)]
+pub struct Relocation {
+    paths: HashMap<String, String>,
+}
+
+#[derive(Debug, Error)]
+pub enum RelocationError {
+    #[error("can't move '{from}' to '{to}', since a file already exists there")]
+    Occupied {
+        from: Utf8PlatformPathBuf,
+        to: Utf8PlatformPathBuf,
+    },
+
+    #[error("the VMT '{0}' isn't valid UTF-8")]
+    NonUtf8Vmt(Utf8PlatformPathBuf),
+
+    #[error(transparent)]
+    Glob(#[from] glob::GlobError),
+
+    #[error(transparent)]
+    GlobPattern(#[from] glob::PatternError),
+
+    #[error(transparent)]
+    CheckedPath(#[from] CheckedPathError),
+
+    #[error(transparent)]
+    Path(#[from] paths::PathError),
+
+    #[error(transparent)]
+    Io(#[from] io::Error),
+}
+
+impl Relocation {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Relocates the material or texture at `from` to `to`. Both may include a `.vmt` or `.vtf` extension, which is
+    /// ignored.
+    pub fn insert(&mut self, from: &str, to: &str) {
+        let to = to.replace('\\', "/");
+        let to = strip_extension(to.trim_start_matches('/'));
+        self.paths.insert(normalize(from), to.to_string());
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.paths.is_empty()
+    }
+
+    /// The new path for the material or texture referenced by `reference`, in the same form as `reference`: if it has
+    /// an extension, or a leading `materials/`, then so will the new path.
+    pub fn relocated(&self, reference: &str) -> Option<String> {
+        let reference = reference.replace('\\', "/");
+        let (prefix, path) = match reference.get(..10) {
+            Some(prefix) if prefix.eq_ignore_ascii_case("materials/") => (prefix, &reference[10..]),
+            _ => ("", reference.as_str()),
+        };
+
+        let new_path = self.paths.get(&normalize(path))?;
+        let extension = &path[strip_extension(path).len()..];
+
+        Some(format!("{prefix}{new_path}{extension}"))
+    }
+}
+
+impl Addon {
+    /// Moves this addon's materials & textures according to `relocation`, then rewrites every VMT in the addon and
+    /// every PCF in [`Addon::particle_files`] to reference the new paths.
+    ///
+    /// References to materials or textures the addon doesn't provide are rewritten too, so relocating a vanilla path
+    /// redirects the addon to a replacement packed elsewhere. The PCFs are only rewritten in memory.
+    ///
+    /// # Errors
+    ///
+    /// Returns [`Err`] if a file couldn't be moved or rewritten, or if a relocated file would overwrite another file.
+    /// Files may have been moved already.
+    pub fn relocate_materials(&mut self, relocation: &Relocation) -> Result<(), RelocationError> {
+        if relocation.is_empty() {
+            return Ok(());
+        }
+
+        let materials_path = self.content_path.join_checked("materials")?;
+        let mut moves = Vec::new();
+        for path in glob(&format!("{materials_path}/**/*"))? {
+            let path = paths::try_buf_to_typed(path?)?;
+            let is_material = path.extension().is_some_and(|extension| {
+                extension.eq_ignore_ascii_case("vmt") || extension.eq_ignore_ascii_case("vtf")
+            });
+
+            if !is_material || !fs::metadata(&path)?.is_file() {
+                continue;
+            }
+
+            let Ok(relative_path) = path.strip_prefix(&materials_path) else {
+                continue;
+            };
+
+            if let Some(new_relative_path) = relocation.relocated(relative_path.as_str()) {
+                let new_path = materials_path.join_checked(new_relative_path)?;
+                moves.push((path, new_path));
+            }
+        }
+
+        for (from, to) in moves {
+            if fs::exists(&to)? {
+                return Err(RelocationError::Occupied { from, to });
+            }
+
+            if let Some(parent) = to.parent() {
+                fs::create_dir_all(parent)?;
+            }
+
+            fs::rename(&from, &to)?;
+        }
+
+        for path in glob(&format!("{materials_path}/**/*.vmt"))? {
+            let path = paths::try_buf_to_typed(path?)?;
+            let vmt = fs::read(&path)?;
+            let Ok(vmt) = String::from_utf8(vmt) else {
+                return Err(RelocationError::NonUtf8Vmt(path));
+            };
+
+            if let Some(rewritten) = rewrite_vmt(&vmt, relocation) {
+                fs::write(&path, rewritten)?;
+            }
+        }
+
+        self.particle_files = self
+            .particle_files
+            .drain()
+            .map(|(path, pcf)| (path, pcf.materials_renamed(|material| relocation.relocated(material))))
+            .collect();
+
+        Ok(())
+    }
+}
+
+/// Rewrites every texture parameter (e.g. `$basetexture`) and `include` in `vmt` which references a relocated path.
+/// Returns [`None`] if nothing in `vmt` was relocated.
+///
+/// Only the referencing values are replaced, so the rest of the VMT's formatting & comments are preserved.
+fn rewrite_vmt(vmt: &str, relocation: &Relocation) -> Option<String> {
+    let mut rewritten = String::with_capacity(vmt.len());
+    let mut end_of_last_replacement = 0;
+
+    let mut key: Option<&str> = None;
+    for token in tokens(vmt) {
+        let Token::String { span, value } = token else {
+            key = None;
+            continue;
+        };
+
+        // conditionals like [$WIN32] can follow any key or value
+        if value.starts_with("[$") || value.starts_with("[!$") {
+            continue;
+        }
+
+        let Some(key) = key.take() else {
+            key = Some(value);
+            continue;
+        };
+
+        let references_material = key.starts_with('$') || key.starts_with('%') || key.eq_ignore_ascii_case("include");
+        if !references_material {
+            continue;
+        }
+
+        if let Some(relocated) = relocation.relocated(value) {
+            rewritten += &vmt[end_of_last_replacement..span.start];
+            rewritten += &format!("\"{relocated}\"");
+            end_of_last_replacement = span.end;
+        }
+    }
+
+    if end_of_last_replacement == 0 {
+        return None;
+    }
+
+    rewritten += &vmt[end_of_last_replacement..];
+    Some(rewritten)
+}
+
+#[derive(Debug)]
+enum Token<'a> {
+    /// A quoted or unquoted string. `span` includes the quotes, and `value` doesn't.
+    String {
+        span: Range<usize>,
+        value: &'a str,
+    },
+    OpenBrace,
+    CloseBrace,
+}
+
+/// Splits KeyValues text into tokens, skipping whitespace & `//` comments.
+fn tokens(text: &str) -> impl Iterator<Item = Token<'_>> {
+    let mut offset = 0;
+    std::iter::from_fn(move || {
+        loop {
+            let rest = &text[offset..];
+            let trimmed = rest.trim_start();
+            offset += rest.len() - trimmed.len();
+
+            if trimmed.starts_with("//") {
+                offset += trimmed.find('\n').unwrap_or(trimmed.len());
+                continue;
+            }
+
+            let start = offset;
+            return match trimmed.chars().next()? {
+                '{' => {
+                    offset += 1;
+                    Some(Token::OpenBrace)
+                }
+                '}' => {
+                    offset += 1;
+                    Some(Token::CloseBrace)
+                }
+                '"' => {
+                    // an unterminated string runs to the end of the text
+                    let (value, len) = match trimmed[1..].find('"') {
+                        Some(len) => (&trimmed[1..1 + len], len + 2),
+                        None => (&trimmed[1..], trimmed.len()),
+                    };
+
+                    offset += len;
+                    Some(Token::String {
+                        span: start..offset,
+                        value,
+                    })
+                }
+                _ => {
+                    let len = trimmed
+                        .find(|char: char| char.is_whitespace() || matches!(char, '"' | '{' | '}'))
+                        .unwrap_or(trimmed.len());
+                    offset += len;
+                    Some(Token::String {
+                        span: start..offset,
+                        value: &trimmed[..len],
+                    })
+                }
+            };
+        }
+    })
+}
+
+fn strip_extension(path: &str) -> &str {
+    match path.len().checked_sub(4).and_then(|idx| path.get(idx..)) {
+        Some(extension) if extension.eq_ignore_ascii_case(".vmt") || extension.eq_ignore_ascii_case(".vtf") => {
+            &path[..path.len() - 4]
+        }
+        _ => path,
+    }
+}
+
+fn normalize(path: &str) -> String {
+    let path = path.replace('\\', "/");
+    strip_extension(path.trim_start_matches('/')).to_ascii_lowercase()
+}
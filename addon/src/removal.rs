@@ -0,0 +1,193 @@
+//! Removes addons from dazzle's data dir. Every path an [`Addon`] points at is checked against the dirs dazzle manages
+//! before anything is deleted, so that a bad path can never take an arbitrary folder with it.
+//!
+//! An addon's source can be moved to a trash dir rather than deleted, so the removal can be undone with
+//! [`Trashed::restore`] until the trash is emptied by [`empty_trash`].
+
+use std::{
+    fs, io,
+    time::{Duration, SystemTime, UNIX_EPOCH},
+};
+
+use thiserror::Error;
+use typed_path::{Utf8PlatformPath, Utf8PlatformPathBuf};
+
+use crate::Addon;
+
+/// The dirs an addon's files may be removed from.
+#[derive(Debug, Clone, Copy)]
+pub struct ManagedDirs<'a> {
+    /// where addon sources are kept, see [`Addon::source_path`]
+    pub addons: &'a Utf8PlatformPath,
+
+    /// where addons are extracted to, see [`Addon::content_path`]
+    pub extracted_content: &'a Utf8PlatformPath,
+
+    /// where thumbnails are cached, see [`Addon::cache_thumbnail`]
+    pub thumbnails: &'a Utf8PlatformPath,
+}
+
+#[derive(Debug, Error)]
+pub enum RemovalError {
+    #[error("'{path}' isn't directly inside '{dir}', so dazzle won't delete it")]
+    Unmanaged {
+        path: Utf8PlatformPathBuf,
+        dir: Utf8PlatformPathBuf,
+    },
+
+    #[error("can't restore '{0}', since something else is already there")]
+    RestoreConflict(Utf8PlatformPathBuf),
+
+    #[error(transparent)]
+    Io(#[from] io::Error),
+}
+
+/// An addon source which was moved to the trash by [`remove`].
+#[derive(Debug, Clone)]
+pub struct Trashed {
+    /// where the source was before it was removed, and where [`Trashed::restore`] moves it back to
+    pub source_path: Utf8PlatformPathBuf,
+
+    /// where the source is in the trash
+    pub trash_path: Utf8PlatformPathBuf,
+}
+
+impl Trashed {
+    /// Moves the source back to where it was removed from. The addon's content & thumbnail aren't restored, so it
+    /// must be added again to be extracted.
+    ///
+    /// # Errors
+    ///
+    /// Returns [`RemovalError::RestoreConflict`] if another addon has since been added with the same name, or
+    /// [`RemovalError::Io`] if the source couldn't be moved, e.g. because the trash was emptied.
+    pub fn restore(&self) -> Result<(), RemovalError> {
+        if fs::symlink_metadata(&self.source_path).is_ok() {
+            return Err(RemovalError::RestoreConflict(self.source_path.clone()));
+        }
+
+        fs::rename(&self.trash_path, &self.source_path)?;
+        Ok(())
+    }
+}
+
+/// Removes `addon`'s extracted content & cached thumbnail, and either moves its source into `trash_dir` or, if there
+/// is no trash, deletes it.
+///
+/// Returns where the source was trashed, if it was.
+///
+/// # Errors
+///
+/// Returns [`RemovalError::Unmanaged`] if one of the addon's paths isn't directly inside the matching dir in `dirs`,
+/// in which case nothing is removed. Otherwise returns [`RemovalError::Io`] if a file couldn't be removed.
+pub fn remove(
+    addon: &Addon,
+    dirs: ManagedDirs,
+    trash_dir: Option<&Utf8PlatformPath>,
+) -> Result<Option<Trashed>, RemovalError> {
+    check_managed(&addon.source_path, dirs.addons)?;
+    check_managed(&addon.content_path, dirs.extracted_content)?;
+
+    // a thumbnail which was never cached is somewhere inside the content, so it goes along with it
+    let cached_thumbnail = addon
+        .thumbnail
+        .as_deref()
+        .filter(|thumbnail| check_managed(thumbnail, dirs.thumbnails).is_ok());
+
+    // an addon which was never installed may never have been extracted
+    match fs::remove_dir_all(&addon.content_path) {
+        Err(err) if err.kind() == io::ErrorKind::NotFound => {}
+        result => result?,
+    }
+
+    if let Some(thumbnail) = cached_thumbnail {
+        let _ = fs::remove_file(thumbnail);
+    }
+
+    let Some(trash_dir) = trash_dir else {
+        delete(&addon.source_path)?;
+        return Ok(None);
+    };
+
+    fs::create_dir_all(trash_dir)?;
+    let trash_path = trash_dir.join(format!("{}-{}", unix_secs(SystemTime::now()), addon.name()));
+    fs::rename(&addon.source_path, &trash_path)?;
+
+    Ok(Some(Trashed {
+        source_path: addon.source_path.clone(),
+        trash_path,
+    }))
+}
+
+/// Deletes everything in `trash_dir` which was trashed more than `max_age` ago. Returns how many were deleted.
+///
+/// # Errors
+///
+/// Returns [`Err`] if the trash couldn't be read, or an old entry couldn't be deleted. A missing trash is empty.
+pub fn empty_trash(trash_dir: &Utf8PlatformPath, max_age: Duration) -> io::Result<usize> {
+    let entries = match fs::read_dir(trash_dir) {
+        Ok(entries) => entries,
+        Err(err) if err.kind() == io::ErrorKind::NotFound => return Ok(0),
+        Err(err) => return Err(err),
+    };
+
+    let cutoff = unix_secs(SystemTime::now()).saturating_sub(max_age.as_secs());
+    let mut deleted = 0;
+    for entry in entries {
+        let entry = entry?;
+
+        // entries which weren't trashed by `remove` are left alone
+        let Some(trashed_at) = entry
+            .file_name()
+            .to_str()
+            .and_then(|name| name.split_once('-'))
+            .and_then(|(secs, _)| secs.parse::<u64>().ok())
+        else {
+            continue;
+        };
+
+        if trashed_at < cutoff {
+            let path = entry.path();
+            if entry.file_type()?.is_dir() {
+                fs::remove_dir_all(path)?;
+            } else {
+                fs::remove_file(path)?;
+            }
+            deleted += 1;
+        }
+    }
+
+    Ok(deleted)
+}
+
+/// Checks that `path` names an entry directly inside `dir`, after resolving any links in either.
+fn check_managed(path: &Utf8PlatformPath, dir: &Utf8PlatformPath) -> Result<(), RemovalError> {
+    let unmanaged = || RemovalError::Unmanaged {
+        path: path.to_path_buf(),
+        dir: dir.to_path_buf(),
+    };
+
+    // only the parent is resolved, so that a linked addon is removed without touching what it links to
+    let (Some(parent), Some(_)) = (path.parent(), path.file_name()) else {
+        return Err(unmanaged());
+    };
+
+    if fs::canonicalize(parent)? == fs::canonicalize(dir)? {
+        Ok(())
+    } else {
+        Err(unmanaged())
+    }
+}
+
+fn delete(path: &Utf8PlatformPath) -> io::Result<()> {
+    if fs::symlink_metadata(path)?.is_dir() {
+        fs::remove_dir_all(path)
+    } else {
+        fs::remove_file(path)
+    }
+}
+
+fn unix_secs(time: SystemTime) -> u64 {
+    time.duration_since(UNIX_EPOCH)
+        .map(|duration| duration.as_secs())
+        .unwrap_or_default()
+}
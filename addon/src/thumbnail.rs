@@ -0,0 +1,124 @@
+//! Preview images which addons ship alongside their content, e.g. the `addonimage.jpg` that many VPK addons have at
+//! their root.
+
+use std::{fs, io};
+
+use typed_path::{Utf8PlatformPath, Utf8PlatformPathBuf};
+
+use crate::Addon;
+
+/// The names, without an extension, that an addon's preview image can have.
+const THUMBNAIL_STEMS: [&str; 3] = ["addonimage", "thumbnail", "preview"];
+
+/// The image formats a thumbnail can be in. VTFs aren't included, since they can't be shown without converting them.
+const THUMBNAIL_EXTENSIONS: [&str; 3] = ["jpg", "jpeg", "png"];
+
+/// The directories, relative to an addon's content, that are searched for a thumbnail - in order of preference.
+const THUMBNAIL_DIRS: [&str; 2] = ["", "materials/vgui"];
+
+/// Searches `content_path` for the addon's thumbnail. Names & extensions are matched case-insensitively.
+///
+/// # Errors
+///
+/// Returns [`Err`] if one of the searched directories exists but can't be read.
+pub fn find_thumbnail(content_path: &Utf8PlatformPath) -> io::Result<Option<Utf8PlatformPathBuf>> {
+    for dir in THUMBNAIL_DIRS {
+        let dir = content_path.join(dir);
+        let entries = match fs::read_dir(&dir) {
+            Ok(entries) => entries,
+            Err(err) if err.kind() == io::ErrorKind::NotFound => continue,
+            Err(err) => return Err(err),
+        };
+
+        let mut candidates = Vec::new();
+        for entry in entries {
+            let entry = entry?;
+            let Ok(name) = entry.file_name().into_string() else {
+                continue;
+            };
+
+            if entry.file_type()?.is_file()
+                && let Some(rank) = thumbnail_rank(&name)
+            {
+                candidates.push((rank, name));
+            }
+        }
+
+        // the sort makes the choice stable when an addon ships more than one candidate
+        candidates.sort();
+        if let Some((_, name)) = candidates.into_iter().next() {
+            return Ok(Some(dir.join(name)));
+        }
+    }
+
+    Ok(None)
+}
+
+/// Searches the entries of a VPK addon for its thumbnail, in the same directories as [`find_thumbnail`]. Returns the
+/// path of the thumbnail's entry.
+pub fn find_thumbnail_entry<'a>(entry_paths: impl IntoIterator<Item = &'a str>) -> Option<&'a str> {
+    let entry_paths: Vec<_> = entry_paths.into_iter().collect();
+    for dir in THUMBNAIL_DIRS {
+        let mut candidates = Vec::new();
+        for entry_path in &entry_paths {
+            // VPK entries are rooted, and their directories can be in any case
+            let relative_path = entry_path.strip_prefix('/').unwrap_or(entry_path);
+            let (entry_dir, name) = relative_path.rsplit_once('/').unwrap_or(("", relative_path));
+            if entry_dir.eq_ignore_ascii_case(dir)
+                && let Some(rank) = thumbnail_rank(name)
+            {
+                candidates.push((rank, *entry_path));
+            }
+        }
+
+        candidates.sort();
+        if let Some((_, entry_path)) = candidates.into_iter().next() {
+            return Some(entry_path);
+        }
+    }
+
+    None
+}
+
+/// How preferable `file_name` is as a thumbnail - lower is better - or [`None`] if it can't be one.
+fn thumbnail_rank(file_name: &str) -> Option<usize> {
+    let (stem, extension) = file_name.rsplit_once('.')?;
+    let stem = stem.to_ascii_lowercase();
+    let extension = extension.to_ascii_lowercase();
+
+    let stem_rank = THUMBNAIL_STEMS.iter().position(|candidate| *candidate == stem)?;
+    let extension_rank = THUMBNAIL_EXTENSIONS
+        .iter()
+        .position(|candidate| *candidate == extension)?;
+
+    Some(stem_rank * THUMBNAIL_EXTENSIONS.len() + extension_rank)
+}
+
+impl Addon {
+    /// Copies the addon's thumbnail into `cache_dir`, named after the addon, and points [`Addon::thumbnail`] at the
+    /// copy. This lets the thumbnail outlive the addon's extracted content. Does nothing if the addon has no thumbnail.
+    ///
+    /// The thumbnail of an addon which hasn't been extracted yet is read straight from its VPK.
+    ///
+    /// # Errors
+    ///
+    /// Returns [`Err`] if the thumbnail couldn't be copied, in which case [`Addon::thumbnail`] is left unchanged.
+    pub fn cache_thumbnail(&mut self, cache_dir: &Utf8PlatformPath) -> io::Result<()> {
+        let Some(thumbnail) = &self.thumbnail else {
+            return Ok(());
+        };
+
+        let extension = thumbnail.extension().unwrap_or_default().to_ascii_lowercase();
+        let cached = cache_dir.join(format!("{}.{extension}", self.name()));
+
+        fs::create_dir_all(cache_dir)?;
+        if self.extracted {
+            fs::copy(thumbnail, &cached)?;
+        } else {
+            fs::write(&cached, self.read_unextracted(thumbnail).map_err(io::Error::other)?)?;
+        }
+
+        self.thumbnail = Some(cached);
+        Ok(())
+    }
+}
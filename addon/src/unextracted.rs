@@ -0,0 +1,147 @@
+//! Addons read straight from their VPK, without extracting them first. Reading an addon only needs its PCFs & thumbnail,
+//! so the rest of its content is only extracted once it's installed.
+
+use std::{
+    collections::HashMap,
+    fs,
+    io::{self, BufReader, Read},
+};
+
+use paths::GamePath;
+use typed_path::Utf8PlatformPath;
+use vpk::VPK;
+
+use crate::{
+    Addon, ExtractionError, ExtractionStrategy, ParseError, Source, checked_entry_path, read_particle, thumbnail,
+};
+
+impl Addon {
+    /// Reads the addon in the VPK at `source_vpk`, as it will be once it's extracted into a subfolder of `parent`, like
+    /// [`Source::extract_as_subfolder_in`] does. PCFs are decoded straight from their entries, and nothing is written
+    /// to `parent` until [`Addon::extract`] is called.
+    ///
+    /// # Errors
+    ///
+    /// May return [`Err`] if:
+    ///
+    /// - `source_vpk` has no file name, or one of its PCFs would be extracted outside of the addon
+    /// - the VPK or one of its entries couldn't be read
+    /// - one of the addon's PCFs is corrupt. Like [`crate::Extracted::parse_content`], invalid parts of an otherwise
+    ///   readable PCF are skipped, and recorded in [`Addon::particle_warnings`] instead.
+    pub fn parse_from_vpk(source_vpk: &Utf8PlatformPath, parent: &Utf8PlatformPath) -> Result<Addon, ParseError> {
+        let file_name = source_vpk
+            .file_name()
+            .ok_or_else(|| ParseError::MissingFileName(source_vpk.to_owned()))?;
+        let content_path = parent.join_checked(file_name)?;
+        let vpk = VPK::read(source_vpk)?;
+
+        // like an extracted addon's, only PCFs directly in `particles` are read, in order
+        let mut particle_entries: Vec<_> = vpk
+            .tree
+            .iter()
+            .filter(|(entry_path, _)| {
+                let game_path = GamePath::new(entry_path);
+                game_path.directory() == Some("particles") && game_path.extension() == Some("pcf")
+            })
+            .collect();
+        particle_entries.sort_by_key(|(entry_path, _)| *entry_path);
+
+        let mut particle_files = HashMap::new();
+        let mut particle_warnings = Vec::new();
+        for (entry_path, entry) in particle_entries {
+            let path = checked_entry_path(&content_path, entry_path)?;
+            let mut reader = BufReader::new(entry.reader()?);
+            let (pcf, warnings) = read_particle(&path, dmx::decode(&mut reader))?;
+            particle_warnings.extend(warnings.into_iter().map(|warning| (path.clone(), warning)));
+            particle_files.insert(path, pcf);
+        }
+
+        let thumbnail = thumbnail::find_thumbnail_entry(vpk.tree.keys().map(String::as_str))
+            .map(|entry_path| checked_entry_path(&content_path, entry_path))
+            .transpose()?;
+
+        Ok(Addon {
+            content_path,
+            source_path: source_vpk.to_owned(),
+            particle_files,
+            particle_warnings,
+            thumbnail,
+            extracted: false,
+        })
+    }
+
+    /// Extracts the addon's VPK into [`Addon::content_path`], if it was read with [`Addon::parse_from_vpk`] and hasn't
+    /// been extracted yet. Anything already at [`Addon::content_path`], e.g. an older version of the addon, is replaced.
+    ///
+    /// # Errors
+    ///
+    /// See [`Source::extract_as_subfolder_in`].
+    pub fn extract(&mut self) -> Result<(), ExtractionError> {
+        if self.extracted {
+            return Ok(());
+        }
+
+        let parent = self
+            .content_path
+            .parent()
+            .ok_or_else(|| ExtractionError::MissingAddonParentPath(self.content_path.clone()))?;
+        Source::Vpk(self.source_path.clone()).extract_as_subfolder_in(parent, ExtractionStrategy::ReplaceAtomically)?;
+        self.extracted = true;
+        Ok(())
+    }
+
+    /// The game path of every file in the addon's content, sorted. The content of an addon which hasn't been extracted
+    /// yet is listed from its VPK.
+    ///
+    /// # Errors
+    ///
+    /// Returns [`Err`] if the extracted content or the VPK couldn't be read.
+    pub fn content_files(&self) -> Result<Vec<GamePath>, ParseError> {
+        fn visit(root: &Utf8PlatformPath, dir: &Utf8PlatformPath, files: &mut Vec<GamePath>) -> Result<(), ParseError> {
+            for entry in fs::read_dir(dir)? {
+                let entry = entry?;
+                let path = paths::std_buf_to_typed(entry.path());
+                let file_type = entry.file_type()?;
+                if file_type.is_dir() {
+                    visit(root, &path, files)?;
+                } else if file_type.is_file() {
+                    files.push(GamePath::relative_to(&path, root)?);
+                }
+            }
+
+            Ok(())
+        }
+
+        let mut files = Vec::new();
+        if self.extracted {
+            visit(&self.content_path, &self.content_path, &mut files)?;
+        } else {
+            let vpk = VPK::read(&self.source_path)?;
+            files.extend(vpk.tree.keys().map(|entry_path| GamePath::new(entry_path)));
+        }
+
+        // paths differing only in case are the same file to the game
+        files.sort();
+        files.dedup();
+        Ok(files)
+    }
+
+    /// Reads the file which will be extracted to `path`, from the VPK of an addon which hasn't been extracted yet.
+    ///
+    /// # Errors
+    ///
+    /// Returns [`Err`] if the VPK couldn't be read, or has no entry which would be extracted to `path`.
+    pub(crate) fn read_unextracted(&self, path: &Utf8PlatformPath) -> Result<Vec<u8>, ParseError> {
+        let vpk = VPK::read(&self.source_path)?;
+        for (entry_path, entry) in &vpk.tree {
+            if checked_entry_path(&self.content_path, entry_path).is_ok_and(|entry_path| entry_path == path) {
+                let mut data = Vec::new();
+                entry.reader()?.read_to_end(&mut data)?;
+                return Ok(data);
+            }
+        }
+
+        let message = format!("'{path}' isn't in '{}'", self.source_path);
+        Err(io::Error::new(io::ErrorKind::NotFound, message).into())
+    }
+}
@@ -0,0 +1,280 @@
+//! A validation pass over an [`Addon`] which runs before it's installed. Each check produces [`Diagnostic`]s in a
+//! [`ValidationReport`]; errors block the install, warnings are only shown to the user.
+
+use std::{
+    collections::HashSet,
+    fmt,
+    fs::{self, File},
+    io::{self, Read},
+};
+
+use glob::glob;
+use paths::GamePath;
+use thiserror::Error;
+use typed_path::{Utf8PlatformPath, Utf8PlatformPathBuf};
+use vpk::VPK;
+
+use crate::{Addon, Material, checked_entry_path};
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord)]
+pub enum Severity {
+    Warning,
+    Error,
+}
+
+#[derive(Debug, Clone)]
+pub struct Diagnostic {
+    pub severity: Severity,
+
+    /// the file this diagnostic is about, if any
+    pub path: Option<Utf8PlatformPathBuf>,
+
+    pub message: String,
+}
+
+impl fmt::Display for Diagnostic {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let severity = match self.severity {
+            Severity::Warning => "warning",
+            Severity::Error => "error",
+        };
+
+        match &self.path {
+            Some(path) => write!(f, "{severity}: {path}: {}", self.message),
+            None => write!(f, "{severity}: {}", self.message),
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy)]
+pub struct Limits {
+    /// the largest size, in bytes, of any one file in the addon
+    pub max_file_size: u64,
+
+    /// the largest size, in bytes, of all files in the addon combined
+    pub max_total_size: Option<u64>,
+}
+
+impl Default for Limits {
+    fn default() -> Self {
+        // the engine's filesystem uses signed 32 bit file sizes, so anything 2 GiB or larger can't be read
+        Self {
+            max_file_size: (2 << 30) - 1,
+            max_total_size: None,
+        }
+    }
+}
+
+#[derive(Debug, Clone, Default)]
+pub struct ValidationReport {
+    pub diagnostics: Vec<Diagnostic>,
+
+    /// the size, in bytes, of every file in the addon's content
+    pub content_size: u64,
+
+    /// the encoded size, in bytes, of every PCF in the addon
+    pub particle_size: u64,
+}
+
+impl ValidationReport {
+    /// Returns true if any diagnostic is an error, meaning the addon shouldn't be installed.
+    pub fn is_blocking(&self) -> bool {
+        self.errors().next().is_some()
+    }
+
+    pub fn errors(&self) -> impl Iterator<Item = &Diagnostic> {
+        self.diagnostics
+            .iter()
+            .filter(|diagnostic| diagnostic.severity == Severity::Error)
+    }
+
+    pub fn warnings(&self) -> impl Iterator<Item = &Diagnostic> {
+        self.diagnostics
+            .iter()
+            .filter(|diagnostic| diagnostic.severity == Severity::Warning)
+    }
+
+    fn push(&mut self, severity: Severity, path: Option<&Utf8PlatformPath>, message: impl Into<String>) {
+        self.diagnostics.push(Diagnostic {
+            severity,
+            path: path.map(Utf8PlatformPath::to_path_buf),
+            message: message.into(),
+        });
+    }
+}
+
+#[derive(Debug, Error)]
+pub enum ValidationError {
+    #[error(transparent)]
+    Vpk(#[from] vpk::Error),
+
+    #[error(transparent)]
+    Glob(#[from] glob::GlobError),
+
+    #[error(transparent)]
+    GlobPattern(#[from] glob::PatternError),
+
+    #[error(transparent)]
+    Io(#[from] io::Error),
+}
+
+impl Addon {
+    /// Checks the addon's source, content, particles & materials for problems which would break the install or the
+    /// game.
+    ///
+    /// # Errors
+    ///
+    /// Returns [`Err`] if the addon's files couldn't be read. Problems with the addon itself are reported as
+    /// [`Diagnostic`]s in the [`ValidationReport`] instead.
+    pub fn validate(&self, limits: &Limits) -> Result<ValidationReport, ValidationError> {
+        let mut report = ValidationReport::default();
+
+        if fs::metadata(&self.source_path)?.is_file() {
+            self.validate_vpk_entries(&mut report)?;
+        }
+
+        self.validate_content(limits, &mut report)?;
+        self.validate_particles(&mut report);
+        self.validate_materials(&mut report)?;
+
+        Ok(report)
+    }
+
+    fn validate_vpk_entries(&self, report: &mut ValidationReport) -> Result<(), ValidationError> {
+        let vpk = VPK::read(&self.source_path)?;
+        for entry_path in vpk.tree.keys() {
+            if checked_entry_path(&self.content_path, entry_path).is_err() {
+                report.push(
+                    Severity::Error,
+                    Some(&self.source_path),
+                    format!("the entry '{entry_path}' would be extracted outside of the addon"),
+                );
+            }
+        }
+
+        Ok(())
+    }
+
+    fn validate_content(&self, limits: &Limits, report: &mut ValidationReport) -> Result<(), ValidationError> {
+        let content_root = fs::canonicalize(&self.content_path)?;
+        for path in glob(&format!("{}/**/*", self.content_path))? {
+            let path = paths::std_buf_to_typed(path?);
+
+            let metadata = fs::symlink_metadata(&path)?;
+            if metadata.is_symlink() {
+                let target = fs::canonicalize(&path)?;
+                if !target.starts_with(&content_root) {
+                    report.push(Severity::Error, Some(&path), "the symlink points outside of the addon");
+                }
+
+                continue;
+            }
+
+            if !metadata.is_file() {
+                continue;
+            }
+
+            if metadata.len() > limits.max_file_size {
+                report.push(
+                    Severity::Error,
+                    Some(&path),
+                    format!(
+                        "the file is {} bytes, which is larger than the limit of {} bytes",
+                        metadata.len(),
+                        limits.max_file_size
+                    ),
+                );
+            }
+
+            report.content_size += metadata.len();
+        }
+
+        if let Some(max_total_size) = limits.max_total_size
+            && report.content_size > max_total_size
+        {
+            report.push(
+                Severity::Error,
+                None,
+                format!(
+                    "the addon is {} bytes, which is larger than the limit of {max_total_size} bytes",
+                    report.content_size
+                ),
+            );
+        }
+
+        Ok(())
+    }
+
+    fn validate_particles(&self, report: &mut ValidationReport) {
+        for (path, warning) in &self.particle_warnings {
+            report.push(Severity::Warning, Some(path), warning.to_string());
+        }
+
+        for (path, pcf) in &self.particle_files {
+            report.particle_size += pcf.encoded_size() as u64;
+
+            if pcf.particle_systems().is_empty() {
+                report.push(Severity::Warning, Some(path), "the PCF contains no particle systems");
+                continue;
+            }
+
+            if let Err(err) = pcf.validate_signatures() {
+                report.push(Severity::Warning, Some(path), err.to_string());
+            }
+
+            if let Err(err) = pcf.clone().topologically_sorted(true) {
+                report.push(Severity::Error, Some(path), err.to_string());
+            }
+        }
+    }
+
+    fn validate_materials(&self, report: &mut ValidationReport) -> Result<(), ValidationError> {
+        let materials_path = self.content_path.join("materials");
+
+        let mut textures = HashSet::new();
+        for path in glob(&format!("{materials_path}/**/*.vtf"))? {
+            let path = paths::std_buf_to_typed(path?);
+            if let Ok(relative_path) = path.strip_prefix(&materials_path) {
+                textures.insert(normalize_texture_name(relative_path.as_str()));
+            }
+        }
+
+        for path in glob(&format!("{materials_path}/**/*.vmt"))? {
+            let path = paths::std_buf_to_typed(path?);
+            let Ok(relative_path) = path.strip_prefix(&materials_path) else {
+                continue;
+            };
+
+            let mut vmt_buf = String::new();
+            File::open(&path)?.read_to_string(&mut vmt_buf)?;
+
+            let material = match Material::parse(relative_path.to_path_buf(), &vmt_buf) {
+                Ok(material) => material,
+                Err(err) => {
+                    report.push(Severity::Warning, Some(&path), format!("malformed VMT: {err}"));
+                    continue;
+                }
+            };
+
+            for texture in material.textures() {
+                if !textures.contains(&normalize_texture_name(texture)) {
+                    report.push(
+                        Severity::Warning,
+                        Some(&path),
+                        format!("the texture '{texture}' isn't provided by the addon, so it must exist in the game"),
+                    );
+                }
+            }
+        }
+
+        Ok(())
+    }
+}
+
+fn normalize_texture_name(texture: &str) -> String {
+    let texture = GamePath::new(texture).into_string();
+    match texture.strip_suffix(".vtf") {
+        Some(texture) => texture.to_string(),
+        None => texture,
+    }
+}
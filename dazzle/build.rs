@@ -3,7 +3,6 @@
 #![feature(seek_stream_len)]
 
 use std::{
-    collections::HashMap,
     env,
     fs::{self, File, OpenOptions},
     io::{BufWriter, Seek, Write},
@@ -14,6 +13,11 @@ use dmx::Dmx;
 use pcf::Pcf;
 use typed_path::Utf8PlatformPathBuf;
 
+use crate::strip_defaults::{get_default_operator_map, get_particle_system_defaults};
+
+#[path = "src/strip_defaults.rs"]
+mod strip_defaults;
+
 struct VanillaPcf {
     name: String,
     size: u64,
@@ -22,6 +26,7 @@ struct VanillaPcf {
 
 fn main() -> anyhow::Result<()> {
     println!("cargo:rerun-if-changed=vanilla/particles/");
+    println!("cargo:rerun-if-changed=src/strip_defaults.rs");
     let manifest = keyvalues_parser::parse(include_str!("vanilla/particles/particles_manifest.txt"))?;
     assert_eq!("particles_manifest", manifest.key);
 
@@ -104,63 +109,6 @@ fn main() -> anyhow::Result<()> {
     Ok(())
 }
 
-pub(crate) fn get_default_operator_map() -> HashMap<&'static str, pcf::Attribute> {
-    HashMap::from([
-        ("operator start fadein", 0.0.into()),
-        ("operator end fadein", 0.0.into()),
-        ("operator start fadeout", 0.0.into()),
-        ("operator end fadeout", 0.0.into()),
-        ("Visibility Proxy Input Control Point Number", (-1).into()),
-        ("Visibility Proxy Radius", 1.0.into()),
-        ("Visibility input minimum", 0.0.into()),
-        ("Visibility input maximum", 1.0.into()),
-        ("Visibility Alpha Scale minimum", 0.0.into()),
-        ("Visibility Alpha Scale maximum", 1.0.into()),
-        ("Visibility Radius Scale minimum", 1.0.into()),
-        ("Visibility Radius Scale maximum", 1.0.into()),
-        ("Visibility Camera Depth Bias", 0.0.into()),
-    ])
-}
-
-pub(crate) fn get_particle_system_defaults() -> HashMap<&'static str, pcf::Attribute> {
-    HashMap::from([
-        // ("batch particle systems", false.into()),
-        (
-            "bounding_box_min",
-            dmx::Vector3((-10.0).into(), (-10.0).into(), (-10.0).into()).into(),
-        ),
-        (
-            "bounding_box_max",
-            dmx::Vector3(10.0.into(), 10.0.into(), 10.0.into()).into(),
-        ),
-        ("color", dmx::Color(255, 255, 255, 255).into()),
-        ("control point to disable rendering if it is the camera", (-1).into()),
-        ("cull_control_point", 0.into()),
-        ("cull_cost", 1.0.into()),
-        ("cull_radius", 0.0.into()),
-        ("cull_replacement_definition", String::new().into()),
-        ("group id", 0.into()),
-        ("initial_particles", 0i32.into()),
-        ("max_particles", 1000i32.into()),
-        ("material", "vgui/white".to_string().into()),
-        ("max_particles", 1000.into()),
-        ("maximum draw distance", 100_000.0.into()),
-        ("maximum sim tick rate", 0.0.into()),
-        ("maximum time step", 0.1.into()),
-        ("minimum rendered frames", 0.into()),
-        ("minimum sim tick rate", 0.0.into()),
-        ("preventNameBasedLookup", false.into()),
-        ("radius", 5.0.into()),
-        ("rotation", 0.0.into()),
-        ("rotation_speed", 0.0.into()),
-        ("sequence_number", 0.into()),
-        ("sequence_number1", 0.into()),
-        ("Sort particles", true.into()),
-        ("time to sleep when not drawn", 8.0.into()),
-        ("view model effect", false.into()),
-    ])
-}
-
 fn write_bins(writer: &mut BufWriter<File>, pcfs: &[VanillaPcf]) -> anyhow::Result<()> {
     writeln!(writer, "pub fn bins() -> Box<[pcfpack::Bin]> {{")?;
     writeln!(writer, "  use dmx::dmx::Version;")?;
@@ -1,45 +1,67 @@
 use std::{
-    collections::HashSet,
+    cmp::Reverse,
+    collections::{BTreeMap, HashMap, HashSet},
     fs::{self, OpenOptions},
     io::{self, ErrorKind, Read, Seek, Write},
-    thread::{self, JoinHandle},
+    thread,
     time::Duration,
 };
 
 use anyhow::anyhow;
-use bytes::{Buf, BufMut, BytesMut};
 use dmx::Dmx;
-use eframe::egui::{self, Align2, Layout, Vec2b, Window};
+use eframe::egui::{self, Align2, CollapsingHeader, Grid, Image, Layout, ScrollArea, Sense, Vec2, Vec2b, Window};
 use egui_extras::{Column, Size, StripBuilder, TableBuilder};
 
-use addon::{Addon, Sources};
+use addon::{
+    Addon, ExtractionStrategy, Source, Sources,
+    removal::{self, ManagedDirs, Trashed},
+};
 use itertools::Itertools;
-use pcfpack::BinPack;
+use ordermap::OrderMap;
+use pcf::{Pcf, stats::StripStats};
+use pcfpack::Packer;
 use rayon::iter::{IntoParallelIterator, ParallelIterator};
 use typed_path::{Utf8PlatformPath, Utf8PlatformPathBuf};
-use vpk::VPK;
 use walkdir::WalkDir;
-use writevpk::patch::PatchVpkExt;
 
 use crate::{
     app::{
-        Paths,
-        config::{self, AddonConfig, Config},
-        initial_load::LoadError,
-        process::{ProcessState, ProcessView},
+        Paths, SYMBOL_POOL,
+        budget::{self, ParticleBudget},
+        config::{self, AddonConfig, AttributeOverride, Config, ParticleSelection},
+        download,
+        install_report::InstallReport,
+        load_problems::LoadProblem,
+        orphans,
+        particle_merge::{self, AddonParticles},
+        patches::{self, Patch},
+        preview,
+        process::ProcessState,
+        provenance::{self, Manifest, PatchedParticle},
+        search::{AddonSearch, SearchMatches},
+        staging::{self, Staging},
+        strip_stage::StripStage,
+        task::Task,
+        vanilla_source::VanillaSource,
     },
     particles_manifest,
 };
 
-const SPLIT_BY_2GB: u32 = 2 << 30;
-
 #[derive(Debug)]
 pub struct AddonState {
     pub enabled: bool,
     pub addon: Addon,
+    pub particles: ParticleSelection,
+    pub overrides: Vec<AttributeOverride>,
 }
 
-pub fn addons_manager(ui: &mut egui::Ui, addons: &mut [AddonState]) -> Response {
+/// Shows the addon manager. When `read_only`, every action which [modifies files](Action::modifies_files) is disabled.
+pub fn addons_manager(
+    ui: &mut egui::Ui,
+    addons: &mut [AddonState],
+    search: &mut AddonSearch,
+    read_only: bool,
+) -> Response {
     let mut action = None;
 
     let desired_size = ui.available_size() - (100.0, 160.0).into();
@@ -56,15 +78,16 @@ pub fn addons_manager(ui: &mut egui::Ui, addons: &mut [AddonState]) -> Response
                 .vertical(|mut strip| {
                     strip.cell(|ui| {
                         ui.group(|ui| {
-                            if let Some(delete_idx) = addons_table(ui, addons) {
-                                action = Some(Action::DeleteAddon(delete_idx));
+                            search.search_box(ui);
+                            if let Some(inner) = addons_table(ui, addons, search.matches(), read_only) {
+                                action = Some(inner);
                             }
                         });
                     });
 
                     strip.cell(|ui| {
                         ui.group(|ui| {
-                            if let Some(inner) = actions(ui) {
+                            if let Some(inner) = actions(ui, read_only) {
                                 action = Some(inner);
                             }
                         });
@@ -75,12 +98,17 @@ pub fn addons_manager(ui: &mut egui::Ui, addons: &mut [AddonState]) -> Response
     Response { action }
 }
 
-fn addons_table(ui: &mut egui::Ui, addons: &mut [AddonState]) -> Option<usize> {
+fn addons_table(
+    ui: &mut egui::Ui,
+    addons: &mut [AddonState],
+    matches: &SearchMatches,
+    read_only: bool,
+) -> Option<Action> {
+    let mut action = None;
     let mut move_addon_up = None;
     let mut move_addon_top = None;
     let mut move_addon_down = None;
     let mut move_addon_bottom = None;
-    let mut delete_addon = None;
 
     TableBuilder::new(ui)
         .striped(true)
@@ -111,16 +139,25 @@ fn addons_table(ui: &mut egui::Ui, addons: &mut [AddonState]) -> Option<usize> {
         .body(|body| {
             // TODO: how do we get/store configuration for each addon? such as their priority and whether or not to disable/enable them
             let row_count = addons.len();
-            body.rows(20.0, row_count, |mut row| {
-                let row_index = row.index();
-                let AddonState { enabled, addon } = addons.get_mut(row_index).unwrap();
+
+            // rows are still indexed by their addon's place in the full list, so that reordering a filtered addon
+            // moves it past its hidden neighbours too
+            let shown_rows: Vec<_> = (0..row_count)
+                .filter(|&idx| matches.is_addon_shown(addons[idx].addon.name()))
+                .collect();
+            body.rows(20.0, shown_rows.len(), |mut row| {
+                let row_index = shown_rows[row.index()];
+                let AddonState { enabled, addon, .. } = addons.get_mut(row_index).unwrap();
 
                 row.col(|ui| {
                     if *enabled {
                         ui.label("✔");
                     }
                 });
-                row.col(|ui| { ui.label(addon.name()); });
+                row.col(|ui| {
+                    thumbnail(ui, addon, 18.0);
+                    ui.label(addon.name());
+                });
                 row.col(|ui| { ui.label(""); });
                 row.col(|ui| { ui.label(""); });
                 row.col(|ui| {
@@ -170,8 +207,42 @@ fn addons_table(ui: &mut egui::Ui, addons: &mut [AddonState]) -> Option<usize> {
 
                     ui.separator();
 
-                    if ui.button("delete").on_hover_text("Permanently deletes the addon's files from the addons folder").clicked() {
-                        delete_addon = Some(row_index);
+                    let particles_button = ui.add_enabled_ui(!addon.particle_files.is_empty(), |ui| {
+                        ui.button("particles").on_hover_text("Choose which of the addon's particles get installed")
+                    }).inner;
+
+                    if particles_button.clicked() {
+                        action = Some(Action::SelectParticles(row_index));
+                    }
+
+                    let tweak_button = ui.add_enabled_ui(!addon.particle_files.is_empty(), |ui| {
+                        ui.button("tweak").on_hover_text("Change the colors, sizes, and other values of the addon's particles")
+                    }).inner;
+
+                    if tweak_button.clicked() {
+                        action = Some(Action::TweakParticles(row_index));
+                    }
+
+                    let stripping_button = ui.add_enabled_ui(!addon.particle_files.is_empty(), |ui| {
+                        ui.button("stripping").on_hover_text("Shows how much the addon's particles shrink when they're stripped to fit the particle budget")
+                    }).inner;
+
+                    if stripping_button.clicked() {
+                        action = Some(Action::ShowStripStats(row_index));
+                    }
+
+                    let preview_button = ui.add_enabled_ui(!read_only && *enabled && !addon.particle_files.is_empty(), |ui| {
+                        ui.button("preview").on_hover_text("Exports just this addon's particles to tf/custom, to quickly try them in game without installing")
+                    }).inner;
+
+                    if preview_button.clicked() {
+                        action = Some(Action::PreviewParticles(row_index));
+                    }
+
+                    ui.separator();
+
+                    if ui.add_enabled(!read_only, egui::Button::new("delete")).on_hover_text("Permanently deletes the addon's files from the addons folder").clicked() {
+                        action = Some(Action::DeleteAddon(row_index));
                     }
                 });
 
@@ -208,34 +279,240 @@ fn addons_table(ui: &mut egui::Ui, addons: &mut [AddonState]) -> Option<usize> {
         addons.swap(idx, addons.len() - 1);
     }
 
-    delete_addon
+    action
+}
+
+/// Shows the addon's system name filters, then lists every PCF in the addon, and the root particle systems in each of
+/// them, with a checkbox to enable or disable each one. Only the systems in `matches` are listed, along with their PCFs.
+pub fn particle_selection(ui: &mut egui::Ui, addon_state: &mut AddonState, matches: &SearchMatches) {
+    let AddonState { addon, particles, .. } = addon_state;
+
+    Grid::new("particle system filters")
+        .num_columns(2)
+        .spacing([16.0, 8.0])
+        .show(ui, |ui| {
+            ui.label("Only install").on_hover_text(
+                "When there are any patterns, only the particle systems matching one of them are installed. One glob \
+                 pattern per line, e.g. explosion_*",
+            );
+            system_filter(ui, &mut particles.include_systems);
+            ui.end_row();
+
+            ui.label("Never install").on_hover_text(
+                "Particle systems matching any of these patterns aren't installed, so the vanilla system - or another \
+                 addon's - is kept instead. One glob pattern per line, e.g. rockettrail*",
+            );
+            system_filter(ui, &mut particles.exclude_systems);
+            ui.end_row();
+        });
+    ui.add_space(8.0);
+
+    let is_system_shown = |pcf_path: &str, name: &str| matches.is_system_shown(addon.name(), pcf_path, name);
+    let mut particle_files: Vec<_> = addon
+        .particle_files
+        .iter()
+        .map(|(path, pcf)| (relative_pcf_path(addon, path), pcf))
+        .filter(|(pcf_path, pcf)| {
+            pcf.root_systems()
+                .unwrap_or_default()
+                .into_iter()
+                .any(|system_idx| is_system_shown(pcf_path, &pcf.particle_systems()[system_idx].name))
+        })
+        .collect();
+    particle_files.sort_by(|(a, _), (b, _)| a.cmp(b));
+
+    ScrollArea::vertical().max_height(400.0).show(ui, |ui| {
+        for (pcf_path, pcf) in particle_files {
+            let mut pcf_enabled = particles.is_pcf_enabled(&pcf_path);
+            if ui.checkbox(&mut pcf_enabled, &pcf_path).changed() {
+                particles.set_pcf_enabled(&pcf_path, pcf_enabled);
+            }
+
+            ui.add_enabled_ui(pcf_enabled, |ui| {
+                CollapsingHeader::new("Particle systems")
+                    .id_salt(&pcf_path)
+                    .show(ui, |ui| {
+                        for system_idx in pcf.root_systems().unwrap_or_default() {
+                            let name = &pcf.particle_systems()[system_idx].name;
+                            if !is_system_shown(&pcf_path, name) {
+                                continue;
+                            }
+
+                            let filtered_out = particles.is_system_filtered_out(name);
+                            let mut system_enabled = particles.is_system_enabled(&pcf_path, name);
+                            let checkbox = ui
+                                .add_enabled(!filtered_out, egui::Checkbox::new(&mut system_enabled, name))
+                                .on_disabled_hover_text("Left out by the filters above");
+                            if checkbox.changed() {
+                                particles.set_system_enabled(&pcf_path, name, system_enabled);
+                            }
+                        }
+                    });
+            });
+        }
+    });
 }
 
-fn actions(ui: &mut egui::Ui) -> Option<Action> {
+/// Edits `patterns`, one per line, and points out any which aren't valid glob patterns.
+fn system_filter(ui: &mut egui::Ui, patterns: &mut Vec<String>) {
+    ui.vertical(|ui| {
+        // the lines are kept as they're typed, even blank ones, so that the text round-trips while it's being edited
+        let mut text = patterns.join("\n");
+        let edit = egui::TextEdit::multiline(&mut text)
+            .desired_rows(2)
+            .desired_width(300.0)
+            .hint_text("no patterns");
+        if ui.add(edit).changed() {
+            *patterns = text.split('\n').map(str::to_string).collect();
+        }
+
+        for pattern in patterns.iter().map(|pattern| pattern.trim()) {
+            if !pattern.is_empty()
+                && let Err(err) = glob::Pattern::new(pattern)
+            {
+                ui.colored_label(
+                    ui.visuals().warn_fg_color,
+                    format!("'{pattern}' isn't a valid pattern, so it never matches: {}", err.msg),
+                );
+            }
+        }
+    });
+}
+
+/// What stripping each of the addon's enabled PCFs as much as the installer can would remove from it, by the PCF's path
+/// relative to the addon's content. See [`StripStage`].
+pub fn strip_stats(addon_state: &AddonState) -> Vec<(String, StripStats)> {
+    let AddonState { addon, particles, .. } = addon_state;
+
+    let mut stats: Vec<_> = addon
+        .particle_files
+        .iter()
+        .map(|(path, pcf)| (relative_pcf_path(addon, path), pcf))
+        .filter(|(pcf_path, _)| particles.is_pcf_enabled(pcf_path))
+        .map(|(pcf_path, pcf)| {
+            // the last stage applies every stage before it
+            let (_, stats) = StripStage::EditorMetadata.apply_with_stats(pcf.clone());
+            (pcf_path, stats)
+        })
+        .collect();
+    stats.sort_by(|(a, _), (b, _)| a.cmp(b));
+
+    stats
+}
+
+/// Shows `stats` with a row for each PCF, followed by their total.
+pub fn strip_stats_table(ui: &mut egui::Ui, stats: &[(String, StripStats)]) {
+    fn row(ui: &mut egui::Ui, name: &str, stats: &StripStats) {
+        ui.label(name);
+        let attributes = ui.label(format!(
+            "{} → {} (-{})",
+            stats.before.attributes,
+            stats.after.attributes,
+            stats.attributes_removed()
+        ));
+        if !stats.removed_attributes.is_empty() {
+            let removed = stats
+                .removed_attributes
+                .iter()
+                .sorted_by_key(|(_, count)| Reverse(**count))
+                .map(|(name, count)| format!("{name}: {count}"))
+                .join("\n");
+            attributes.on_hover_text(format!("Removed attributes:\n{removed}"));
+        }
+        ui.label(format!(
+            "{} → {} (-{})",
+            stats.before.symbols,
+            stats.after.symbols,
+            stats.symbols_removed()
+        ));
+        ui.label(format!(
+            "{} → {} KB",
+            stats.before.encoded_size.div_ceil(1024),
+            stats.after.encoded_size.div_ceil(1024)
+        ));
+        ui.end_row();
+    }
+
+    ScrollArea::vertical().max_height(400.0).show(ui, |ui| {
+        Grid::new("Strip Stats").striped(true).num_columns(4).show(ui, |ui| {
+            ui.strong("PCF");
+            ui.strong("Attributes");
+            ui.strong("Symbols");
+            ui.strong("Size");
+            ui.end_row();
+
+            let mut total = StripStats::default();
+            for (pcf_path, pcf_stats) in stats {
+                row(ui, pcf_path, pcf_stats);
+                total.add(pcf_stats);
+            }
+
+            row(ui, "Total", &total);
+        });
+    });
+}
+
+/// The `file://` URI egui loads a thumbnail from.
+fn thumbnail_uri(path: &Utf8PlatformPath) -> String {
+    format!("file://{path}")
+}
+
+/// Shows `addon`'s thumbnail, scaled to fit in a `size` by `size` square. Addons without a thumbnail leave the square
+/// empty, so that they line up with those that have one.
+pub fn thumbnail(ui: &mut egui::Ui, addon: &Addon, size: f32) -> egui::Response {
+    match &addon.thumbnail {
+        Some(path) => ui.add(
+            Image::new(thumbnail_uri(path))
+                .fit_to_exact_size(Vec2::splat(size))
+                .maintain_aspect_ratio(true),
+        ),
+        None => ui.allocate_response(Vec2::splat(size), Sense::hover()),
+    }
+}
+
+/// The path of the PCF at `path`, relative to `addon`'s content. This is how a [`ParticleSelection`] refers to it.
+pub fn relative_pcf_path(addon: &Addon, path: &Utf8PlatformPath) -> String {
+    match path.strip_prefix(&addon.content_path) {
+        Ok(relative_path) => relative_path.as_str().replace('\\', "/"),
+        Err(_) => path.to_string(),
+    }
+}
+
+fn actions(ui: &mut egui::Ui, read_only: bool) -> Option<Action> {
     let mut response = None;
     StripBuilder::new(ui)
         .cell_layout(Layout::left_to_right(egui::Align::Center))
-        .size(Size::relative(0.225))
-        .size(Size::relative(0.225))
+        .size(Size::relative(0.18))
+        .size(Size::relative(0.18))
+        .size(Size::relative(0.18))
+        .size(Size::relative(0.1))
         .size(Size::remainder())
         .size(Size::remainder())
         .horizontal(|mut strip| {
             strip.cell(|ui| {
                 ui.vertical_centered_justified(|ui| {
                     if ui
-                        .button("Add Addon - From Vpk")
+                        .add_enabled(!read_only, egui::Button::new("Add Addon - From Vpk"))
                         .on_hover_text("open a dialogue to select an archive files (vpk, zip, tarball, etc) to install")
                         .clicked()
                     {
                         response = Some(Action::AddAddonFiles);
                     }
                     if ui
-                        .button("Add Addon - From Folder")
+                        .add_enabled(!read_only, egui::Button::new("Add Addon - From Folder"))
                         .on_hover_text("open a dialogue to select addon folders to install")
                         .clicked()
                     {
                         response = Some(Action::AddAddonFolders);
                     }
+                    if download::IS_AVAILABLE
+                        && ui
+                            .add_enabled(!read_only, egui::Button::new("Add Addon - From URL"))
+                            .on_hover_text("downloads addons from GameBanana or the Steam Workshop")
+                            .clicked()
+                    {
+                        response = Some(Action::AddAddonUrls);
+                    }
                 });
             });
             strip.cell(|ui| {
@@ -256,10 +533,46 @@ fn actions(ui: &mut egui::Ui) -> Option<Action> {
                     }
                 });
             });
+            strip.cell(|ui| {
+                ui.vertical_centered_justified(|ui| {
+                    if ui
+                        .button("Export Profile")
+                        .on_hover_text("saves which addons are enabled & their order, to share with others")
+                        .clicked()
+                    {
+                        response = Some(Action::ExportProfile);
+                    }
+                    if ui
+                        .button("Import Profile")
+                        .on_hover_text("enables & orders your addons to match a profile someone shared with you")
+                        .clicked()
+                    {
+                        response = Some(Action::ImportProfile);
+                    }
+                });
+            });
+            strip.cell(|ui| {
+                ui.vertical_centered_justified(|ui| {
+                    if ui
+                        .add_enabled(!read_only, egui::Button::new("Appearance"))
+                        .on_hover_text("changes dazzle's theme, accent color, and scale")
+                        .clicked()
+                    {
+                        response = Some(Action::EditAppearance);
+                    }
+                    if ui
+                        .add_enabled(!read_only, egui::Button::new("Remove Preview"))
+                        .on_hover_text("removes the particle preview exported from an addon")
+                        .clicked()
+                    {
+                        response = Some(Action::RemovePreview);
+                    }
+                });
+            });
             strip.cell(|ui| {
                 ui.centered_and_justified(|ui| {
                     if ui
-                        .button("Install Addons")
+                        .add_enabled(!read_only, egui::Button::new("Install Addons"))
                         .on_hover_text("installs selected addons into your tf directory")
                         .clicked()
                     {
@@ -270,7 +583,7 @@ fn actions(ui: &mut egui::Ui) -> Option<Action> {
             strip.cell(|ui| {
                 ui.centered_and_justified(|ui| {
                     if ui
-                        .button("Uninstall Addons")
+                        .add_enabled(!read_only, egui::Button::new("Uninstall Addons"))
                         .on_hover_text(
                             "removes any Dazzle customizations from your tf directory, resetting them back to vanilla",
                         )
@@ -291,77 +604,168 @@ pub struct Response {
 
 pub enum Action {
     DeleteAddon(usize),
+    SelectParticles(usize),
+    TweakParticles(usize),
+    ShowStripStats(usize),
+    PreviewParticles(usize),
     OpenAddonsFolder,
     OpenTfFolder,
     AddAddonFiles,
     AddAddonFolders,
+    AddAddonUrls,
     InstallAddons,
     UninstallAddons,
+    EditAppearance,
+    ExportProfile,
+    ImportProfile,
+    RemovePreview,
+}
+
+impl Action {
+    /// Whether the action changes the game, the addons folder, or the config. Tweaks, particle selections & imported
+    /// profiles are only kept in memory until an install, so they don't.
+    pub fn modifies_files(&self) -> bool {
+        matches!(
+            self,
+            Self::DeleteAddon(_)
+                | Self::PreviewParticles(_)
+                | Self::AddAddonFiles
+                | Self::AddAddonFolders
+                | Self::AddAddonUrls
+                | Self::InstallAddons
+                | Self::UninstallAddons
+                | Self::EditAppearance
+                | Self::RemovePreview
+        )
+    }
 }
 
-pub type RemovingAddonJob = JoinHandle<Result<(), io::Error>>;
+/// The config without the removed addon, and where the addon's source was trashed.
+pub type RemovingAddonJob = Task<anyhow::Result<(Config, Option<Trashed>)>>;
 
-pub fn start_addon_removal(ctx: &egui::Context, addon: Addon) -> (ProcessView, RemovingAddonJob) {
-    let (state, view) = ProcessState::with_spinner(ctx);
-    let handle = thread::spawn(move || -> Result<(), io::Error> {
+/// Removes `addon` from the data dir and from `config`, which is saved once it's removed. The addon's source is moved
+/// to the trash, so the removal can be undone until the trash is next emptied.
+pub fn start_addon_removal(ctx: &egui::Context, paths: &Paths, config: &Config, addon: Addon) -> RemovingAddonJob {
+    // an addon added later with the same name would otherwise show this one's thumbnail
+    if let Some(thumbnail) = &addon.thumbnail {
+        ctx.forget_image(&thumbnail_uri(thumbnail));
+    }
+
+    let addons_dir = paths.addons.clone();
+    let extracted_content_dir = paths.extracted_content.clone();
+    let thumbnails_dir = paths.thumbnails.clone();
+    let trash_dir = paths.trash.clone();
+    let config_path = paths.config.clone();
+    let mut config = config.clone();
+    Task::spawn_with_spinner(ctx, move |state| -> anyhow::Result<(Config, Option<Trashed>)> {
         state.push_status(format!("Removing '{}'", addon.name()));
 
         // for small addons, this job ends up running too fast - theres no good feedback for the user. So we sleep a bit
         thread::sleep(Duration::from_millis(500));
 
-        fs::remove_dir_all(&addon.content_path)?;
-        let result = if let Err(err) = fs::remove_dir_all(&addon.source_path) {
-            if err.kind() == ErrorKind::NotADirectory {
-                fs::remove_file(&addon.source_path)
-            } else {
-                Err(err)
-            }
-        } else {
-            Ok(())
+        let dirs = ManagedDirs {
+            addons: &addons_dir,
+            extracted_content: &extracted_content_dir,
+            thumbnails: &thumbnails_dir,
         };
+        let trashed = removal::remove(&addon, dirs, Some(trash_dir.as_path()))?;
+
+        state.push_status("Saving updated config");
+        config.addons.remove(addon.name());
+        config::write_config(&config_path, &config)?;
 
         state.push_status("Done!");
         thread::sleep(Duration::from_millis(500));
 
-        result
-    });
+        Ok((config, trashed))
+    })
+}
 
-    (view, handle)
+/// Shows the last removed addon in the bottom of the window, with the option to undo its removal.
+pub fn removal_notification(ctx: &egui::Context, trashed: &Trashed) -> Option<RemovalAction> {
+    let mut action = None;
+    Window::new("🗑 Addon removed")
+        .collapsible(false)
+        .resizable(false)
+        .title_bar(false)
+        .anchor(Align2::CENTER_BOTTOM, (0.0, -16.0))
+        .show(ctx, |ui| {
+            ui.horizontal(|ui| {
+                let name = trashed.source_path.file_name().unwrap_or_default();
+                ui.label(format!("Removed '{name}'."));
+                if ui
+                    .button("Undo")
+                    .on_hover_text("puts the addon back, with the default settings")
+                    .clicked()
+                {
+                    action = Some(RemovalAction::Undo);
+                }
+
+                if ui.button("Dismiss").clicked() {
+                    action = Some(RemovalAction::Dismiss);
+                }
+            });
+        });
+
+    action
 }
 
-pub type AddingAddonsJob = JoinHandle<(Vec<AddonState>, Vec<(Utf8PlatformPathBuf, LoadError)>)>;
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum RemovalAction {
+    Undo,
+    Dismiss,
+}
+
+/// The addons, the problems loading any of them, and the names of the addons which were updated.
+pub type AddingAddonsJob = Task<(Vec<AddonState>, Vec<LoadProblem>, Vec<String>)>;
 
 pub fn start_addon_add(
     ctx: &egui::Context,
     paths: &Paths,
     mut addons: Vec<AddonState>,
     files: Vec<Utf8PlatformPathBuf>,
-) -> (ProcessView, AddingAddonsJob) {
+) -> AddingAddonsJob {
     assert!(!files.is_empty());
 
     let steps = (files.len() * 3) + 1;
     let addons_dir = paths.addons.clone();
     let extracted_content_dir = paths.extracted_content.clone();
-    let (state, view) = ProcessState::with_progress_bar(ctx, steps.try_into().unwrap());
-    let handle = thread::spawn(move || -> (Vec<AddonState>, Vec<(Utf8PlatformPathBuf, LoadError)>) {
+    let thumbnails_dir = paths.thumbnails.clone();
+    Task::spawn_with_progress_bar(ctx, steps.try_into().unwrap(), move |state| {
+        // the index in `addons` of the addon each update replaces, by the update's file name
+        let mut updates: HashMap<String, usize> = HashMap::new();
+
         let original_count = files.len();
         let files: Vec<_> = files
             .into_iter()
             .filter(|file| {
                 let name = file.file_name().unwrap();
+                let Some(existing_idx) = addons
+                    .iter()
+                    .position(|state| state.addon.name().eq_ignore_ascii_case(name))
+                else {
+                    return true;
+                };
+
+                if is_same_source(file, &addons[existing_idx].addon.source_path) {
+                    state.push_status(format!("'{name}' has already been added, and hasn't changed"));
+                    return false;
+                }
 
-                if addons.iter().any(|state| state.addon.name().eq_ignore_ascii_case(name)) {
-                    eprintln!(
-                        "Confirming: 'An addon with the name '{name}' has already been added. What do you want to do?'"
-                    );
-                    let choice = state.confirm(
-                        format!("An addon with the name '{name}' has already been added. What do you want to do?"),
-                        ["Skip", "Replace Existing"],
-                    );
-
-                    choice == 1
-                } else {
+                eprintln!("Confirming: 'A different version of '{name}' has already been added. Update it?'");
+                let choice = state.confirm(
+                    format!(
+                        "A different version of '{name}' has already been added. Do you want to update it? It keeps its \
+                         place in your addons, whether it's enabled, and your particle choices & tweaks."
+                    ),
+                    ["Skip", "Update"],
+                );
+
+                if choice == 1 {
+                    updates.insert(name.to_string(), existing_idx);
                     true
+                } else {
+                    false
                 }
             })
             .collect();
@@ -372,7 +776,7 @@ pub fn start_addon_add(
         }
 
         if files.is_empty() {
-            return (addons, Vec::new());
+            return (addons, Vec::new(), Vec::new());
         }
 
         let files: Vec<_> = files
@@ -383,7 +787,14 @@ pub fn start_addon_add(
                     state.push_status(format!("Copying {file} to addons folder"));
 
                     let target = addons_dir.join(file.file_name().unwrap());
-                    fs::copy(&file, &target).map_err(|err| (file, err))?;
+                    if let Some(&existing_idx) = updates.get(file.file_name().unwrap()) {
+                        replace_addon_source(&file, &addons[existing_idx].addon, &target)
+                            .map_err(|err| (file.clone(), err))?;
+                    } else if file != target {
+                        // an addon retried after it failed to load is already in the addons folder, and whatever was
+                        // extracted the last time is replaced when it's extracted again
+                        fs::copy(&*paths::long_path(&file), &*paths::long_path(&target)).map_err(|err| (file, err))?;
+                    }
 
                     state.increment_progress();
 
@@ -394,11 +805,11 @@ pub fn start_addon_add(
 
         let (files, mut errors): (Vec<_>, Vec<_>) = files.into_iter().partition_map(|file| match file {
             Ok(file) => itertools::Either::Left(file),
-            Err((path, err)) => itertools::Either::Right((path, LoadError::Sources(addon::Error::Io(err)))),
+            Err((path, err)) => itertools::Either::Right(LoadProblem::new(path, addon::Error::Io(err))),
         });
 
         if files.is_empty() {
-            return (addons, errors);
+            return (addons, errors, Vec::new());
         }
 
         eprintln!("Reading sources");
@@ -406,160 +817,358 @@ pub fn start_addon_add(
 
         let sources = Sources::read_paths(files.iter());
 
-        if !sources.failures.is_empty() {
-            // TODO: we should present information about addons that failed to load to the user
-            eprintln!("There were some errors reading some or all addon sources:");
-            for (path, error) in sources.failures {
-                eprintln!("  {path}: {error}");
-                errors.push((path, error.into()));
-            }
-        }
+        errors.extend(
+            sources
+                .failures
+                .into_iter()
+                .map(|(path, error)| LoadProblem::new(path, error)),
+        );
 
         state.increment_progress();
 
+        let mut updated = Vec::new();
+
+        // VPKs are read without extracting them, which is left until the addon is installed
         let extracted_addons: Vec<_> = sources
             .sources
             .into_par_iter()
             .map(|source| {
-                state.push_status(format!("Extracting addon {}", source.name().unwrap_or_default()));
-
-                let extracted = source.extract_as_subfolder_in(&extracted_content_dir);
+                let extracted = match source {
+                    Source::Vpk(source_path) => {
+                        state.push_status(format!("Reading addon {}", source_path.file_name().unwrap_or_default()));
+                        Addon::parse_from_vpk(&source_path, &extracted_content_dir)
+                            .map(itertools::Either::Right)
+                            .map_err(|err| LoadProblem::new(source_path, err))
+                    }
+                    source @ Source::Folder(_) => {
+                        state.push_status(format!("Extracting addon {}", source.name().unwrap_or_default()));
+                        source
+                            .extract_as_subfolder_in(&extracted_content_dir, ExtractionStrategy::ReplaceAtomically)
+                            .map(itertools::Either::Left)
+                            .map_err(|err| LoadProblem::new(source.into_inner(), err))
+                    }
+                };
 
                 state.increment_progress();
 
-                extracted.map_err(|err| (source.into_inner(), err))
+                extracted
             })
             .collect();
 
-        let (extracted_addons, mut errors): (Vec<_>, Vec<_>) =
+        let (extracted_addons, extraction_errors): (Vec<_>, Vec<_>) =
             extracted_addons.into_iter().partition_map(|addon| match addon {
                 Ok(addon) => itertools::Either::Left(addon),
-                Err((path, err)) => itertools::Either::Right((path, err.into())),
+                Err(problem) => itertools::Either::Right(problem),
             });
+        errors.extend(extraction_errors);
 
         for addon in extracted_addons {
-            state.push_status(format!("Parsing contents of {}", addon.name().unwrap_or_default()));
-
-            let source_path = addon.source_path().to_owned();
-            let addon = match addon.parse_content() {
-                Ok(parsed_content) => parsed_content,
-                Err(err) => {
-                    errors.push((source_path, err.into()));
-                    continue;
+            let mut addon = match addon {
+                itertools::Either::Left(addon) => {
+                    state.push_status(format!("Parsing contents of {}", addon.name().unwrap_or_default()));
+
+                    let source_path = addon.source_path().to_owned();
+                    match addon.parse_content() {
+                        Ok(parsed_content) => parsed_content,
+                        Err(err) => {
+                            errors.push(LoadProblem::new(source_path, err));
+                            continue;
+                        }
+                    }
                 }
+                itertools::Either::Right(addon) => addon,
             };
 
-            addons.push(AddonState { enabled: true, addon });
+            if let Err(err) = addon.cache_thumbnail(&thumbnails_dir) {
+                eprintln!("Couldn't cache the thumbnail of '{}': {err}", addon.name());
+            }
+
+            addon.intern_symbols(&SYMBOL_POOL);
+
+            if let Some(existing_idx) = updates.remove(addon.name()) {
+                // everything the user chose for the addon is kept, but it may not apply to the new version
+                state.push_status(format!("Updated {}", addon.name()));
+                updated.push(addon.name().to_string());
+                addons[existing_idx].addon = addon;
+            } else {
+                addons.push(AddonState {
+                    enabled: true,
+                    addon,
+                    particles: ParticleSelection::default(),
+                    overrides: Vec::new(),
+                });
+            }
 
             state.increment_progress();
         }
 
+        // an update which failed may have already removed the old version's files, so the old version is dropped too.
+        // The failure is in `errors`, so it can be retried
+        let failed_updates: HashSet<_> = updates.into_values().collect();
+        let mut idx = 0;
+        addons.retain(|_| {
+            idx += 1;
+            !failed_updates.contains(&(idx - 1))
+        });
+
         state.push_status("Done!");
 
         // for small addons, this job ends up running too fast - theres no good feedback for the user. So we sleep a bit
         thread::sleep(Duration::from_millis(500));
 
-        (addons, errors)
-    });
+        (addons, errors, updated)
+    })
+}
 
-    (view, handle)
+/// Whether the addon sources at `a` & `b` have the same contents. Sources which can't be read are assumed to differ.
+fn is_same_source(a: &Utf8PlatformPath, b: &Utf8PlatformPath) -> bool {
+    match (provenance::source_hash(a), provenance::source_hash(b)) {
+        (Ok(a), Ok(b)) => a == b,
+        _ => false,
+    }
 }
 
-pub type AddonInstallJob = JoinHandle<anyhow::Result<Vec<AddonState>>>;
+/// Replaces `existing`'s source with a copy of `source` at `target`. The content extracted from the old source is
+/// replaced when the new source is extracted.
+fn replace_addon_source(source: &Utf8PlatformPath, existing: &Addon, target: &Utf8PlatformPath) -> io::Result<()> {
+    if fs::metadata(&existing.source_path)?.is_dir() {
+        fs::remove_dir_all(&*paths::long_path(&existing.source_path))?;
+    } else if existing.source_path.as_path() != target {
+        fs::remove_file(&*paths::long_path(&existing.source_path))?;
+    }
+
+    if fs::metadata(source)?.is_dir() {
+        let mut errors = copy_dir::copy_dir(&*paths::long_path(source), &*paths::long_path(target))?;
+        if !errors.is_empty() {
+            return Err(errors.swap_remove(0));
+        }
+    } else {
+        fs::copy(&*paths::long_path(source), &*paths::long_path(target))?;
+    }
+
+    Ok(())
+}
+
+/// The addons, handed back for the addon manager, and the install's report.
+pub type AddonInstallJob = Task<anyhow::Result<(Vec<AddonState>, InstallReport)>>;
 
 pub fn start_addon_install(
     ctx: &egui::Context,
     paths: &Paths,
     config: &Config,
-    addons: Vec<AddonState>,
-) -> (ProcessView, AddonInstallJob) {
-    const TF2_VPK_NAME: &str = "tf2_misc_dir.vpk";
-
-    let (state, view) = ProcessState::with_spinner(ctx);
-
+    mut addons: Vec<AddonState>,
+) -> AddonInstallJob {
     let working_vpk_dir = paths.working_vpk.clone();
+    let staging_dir = paths.data.join("staging");
+    let patches_dir = paths.patches.clone();
 
     let tf_custom_dir = config.tf_dir.join("custom");
-    let vpk_path = config.tf_dir.join(TF2_VPK_NAME);
     let game_info_path = config.tf_dir.join("gameinfo.txt");
     let config_path = paths.config.clone();
     let mut config = config.clone();
 
-    let handle = thread::spawn(move || -> anyhow::Result<Vec<AddonState>> {
+    Task::spawn_with_spinner(ctx, move |state| -> anyhow::Result<(Vec<AddonState>, InstallReport)> {
         state.push_status("Saving updated config");
         update_config_addon_states(&addons, &mut config);
         config::write_config(&config_path, &config)?;
 
-        state.push_status("Loading particle graph from manifest");
-        let mut bins = particles_manifest::bins();
-        let vanilla_graphs = particles_manifest::graphs();
+        // addons read straight from their VPK are only extracted once they're installed
+        let unextracted: Vec<_> = addons
+            .iter_mut()
+            .filter(|addon_state| addon_state.enabled && !addon_state.addon.extracted)
+            .collect();
+        if !unextracted.is_empty() {
+            state.begin_stage("Extracting addons", unextracted.len(), 0);
+            for addon_state in unextracted {
+                state.push_status(format!("Extracting addon {}", addon_state.addon.name()));
+                addon_state.addon.extract()?;
+                state.advance_stage(1, 0);
+            }
+        }
 
-        let mut packed_system_names = HashSet::new();
         // N.B. addons that come first in the array need to have priority
-        for addon_state in addons.iter().rev() {
-            if !addon_state.enabled {
-                continue;
-            }
+        let enabled_addon_states: Vec<_> = addons.iter().rev().filter(|addon_state| addon_state.enabled).collect();
+        let enabled_addons: Vec<_> = enabled_addon_states
+            .iter()
+            .map(|addon_state| &addon_state.addon)
+            .collect();
 
-            for pcf in addon_state.addon.particle_files.values() {
-                packed_system_names.extend(pcf.particle_systems().iter().map(|system| system.name.clone()));
-            }
+        let bins = config.bins.select(particles_manifest::bins())?;
+        let content_size = validate_addons(&state, &enabled_addons, &bins)?;
+        ensure_install_space(&state, &working_vpk_dir, &tf_custom_dir, content_size)?;
 
-            process_addon(&state, &working_vpk_dir, &mut bins, &addon_state.addon)?;
+        state.begin_stage("Copying addon content", enabled_addons.len(), content_size);
+        for addon in &enabled_addons {
+            process_addon(&state, &working_vpk_dir, addon)?;
+            state.advance_stage(1, 0);
         }
 
-        let mut tf2_misc_vpk = VPK::read(vpk_path)?;
+        let mut vanilla = VanillaSource::open(&config.tf_dir)?;
 
         // the vgui cache is necessary to enable custom skyboxes and warpaints
         state.push_status("Enabling VGUI caching");
-        ensure_vgui_cache_in_hud(&working_vpk_dir, &tf2_misc_vpk)?;
+        ensure_vgui_cache_in_hud(&working_vpk_dir, &vanilla)?;
 
         // some vtf customizations - like warpaints - require a VMT to be present in tf/custom/.
         state.push_status("Generating VMTs for VTF customizations");
-        ensure_all_vtfs_have_matching_vmts(&working_vpk_dir, &tf2_misc_vpk)?;
+        ensure_all_vtfs_have_matching_vmts(&working_vpk_dir, &vanilla)?;
 
-        // the bins don't contain any of the necessary particle systems by default, since they're supposed to be a blank
-        // slate for our addons; so, we pack every vanilla particle system not present in the bins.
-        for (name, graphs) in &vanilla_graphs {
-            state.push_status(format!("Bin-packing missing vanilla particle systems from {name}."));
+        state.push_status("Loading particle graph from manifest");
+        let vanilla_graphs = particles_manifest::graphs();
 
-            for graph in graphs {
-                if graph
-                    .particle_systems()
-                    .iter()
-                    .any(|system| !packed_system_names.contains(&system.name))
-                {
-                    let mut pcf = graph.clone();
-                    bins.pack(&mut pcf).unwrap();
+        state.push_status("Reading particle patches");
+        let mut warnings = Vec::new();
+        let mut patches = Vec::new();
+        for patch in patches::read_dir(&patches_dir)? {
+            match patch {
+                Ok(patch) => patches.push(patch),
+                Err((file_name, err)) => warnings.push(format!(
+                    "Skipping the patch {file_name}, since it couldn't be read: {:#}",
+                    anyhow::Error::from(err)
+                )),
+            }
+        }
+
+        let previous_bins = if config.stable_packing {
+            state.push_status("Reading the previous install's particle bins");
+            match provenance::read_installed(&tf_custom_dir, &config.vpk_name) {
+                Ok(manifest) => manifest.map(|manifest| manifest.bin_assignments).unwrap_or_default(),
+                Err(err) => {
+                    warnings.push(format!(
+                        "Packing the particles from scratch, since the previous install couldn't be read: {err:#}"
+                    ));
+                    BTreeMap::new()
                 }
             }
+        } else {
+            BTreeMap::new()
+        };
+
+        let (packer, stage) = pack_particles(
+            &state,
+            &enabled_addon_states,
+            &bins,
+            &vanilla_graphs,
+            &patches,
+            &previous_bins,
+        )?;
+        if stage != StripStage::None {
+            warnings.push(format!("Particles only fit the vanilla budget after {stage}"));
         }
 
-        // TODO: create quickprecache assets for props & pack them into _dazzle_qpc.vpk
+        warnings.extend(check_particle_budget(&state, &packer)?);
+        for warning in &warnings {
+            state.push_status(warning);
+        }
 
-        state.push_status("Restoring tf2_misc.vpk");
-        restore_tf2_misc_vpk(&mut tf2_misc_vpk)?;
+        let mut report = InstallReport::new(&enabled_addon_states, stage);
+        report.bins = packer.report().bins.into_iter().map(Into::into).collect();
+        report.warnings = warnings;
+        report.content_bytes = content_size;
 
-        state.push_status("Removing old _dazzle_addons.vpk");
-        remove_old_dazzle_vpks(&tf_custom_dir)?;
+        // TODO: create quickprecache assets for props & pack them into _dazzle_qpc.vpk
 
+        // everything is generated & packed into the staging area first, so that a failure while generating or packing
+        // leaves the game as it was. See staging.rs.
+        let mut staging = Staging::create(&staging_dir, &tf_custom_dir, &working_vpk_dir)?;
+        let bins = packer.into_bins();
+        let bin_assignments = bin_assignments(&bins);
+        let mut patched_particles = Vec::with_capacity(bins.len());
+        state.begin_stage("Generating particles", bins.len(), 0);
         for bin in bins {
             let (name, pcf) = bin.into_inner();
-            state.push_status(format!("Writing tf2_misc.vpk/{name}"));
+            state.push_status(format!("Generating {name}"));
+
+            // the engine expects parent systems to come before their children
+            let pcf = pcf.topologically_sorted(true)?;
+            let size = pcf.encoded_size();
             let dmx: Dmx = pcf.into();
 
-            let mut writer = BytesMut::new().writer();
-            dmx.encode(&mut writer)?;
+            let mut buffer = Vec::with_capacity(size);
+            dmx.encode_sized(&mut buffer, size)?;
+            staging.stage_particle(&name, &buffer)?;
+
+            // particles are patched in place, so where they'll be is already known
+            if let Some(tf2_misc_vpk) = vanilla.vpk() {
+                patched_particles.extend(PatchedParticle::new(tf2_misc_vpk, &name, &buffer));
+            }
 
-            let buffer = writer.into_inner();
-            let size = buffer.len() as u64;
-            let mut reader = buffer.reader();
-            tf2_misc_vpk.patch_file(&name, size, &mut reader)?;
+            state.advance_stage(1, 0);
         }
 
-        // we can finally generate our _dazzle_addons VPKs from our addon contents.
-        state.push_status("Packing addons into _dazzle_addons.vpk");
-        writevpk::pack::pack_directory(&working_vpk_dir, &tf_custom_dir, "_dazzle_addons", SPLIT_BY_2GB)?;
+        orphans::write_marker(&working_vpk_dir)?;
+        let mut manifest = Manifest::new(&enabled_addons, stage, patched_particles)?;
+        manifest.bin_assignments = bin_assignments;
+
+        staging.write_manifest()?;
+
+        // we can finally generate our _dazzle_addons VPKs from our addon contents. files shared by several addons are
+        // only packed once, and the manifest is packed last so it can record them. the VPK is packed into the staging
+        // area, and only moved into tf/custom once it's finished
+        let manifest = Some(manifest.into_packed());
+        state.begin_stage("Packing addons", 0, 0);
+        state.push_status(format!("Packing addons into {}.vpk", config.vpk_name));
+        let stats = if config.incremental_builds {
+            staging.link_previous_vpk(&tf_custom_dir, &config.vpk_name)?;
+            let stats = writevpk::pack::pack_directory_incremental(
+                &working_vpk_dir,
+                staging.vpk_dir(),
+                &config.vpk_name,
+                config.vpk_split_size,
+                manifest,
+            )?;
+
+            state.push_status(format!(
+                "Reused {} unchanged files ({} MB), and wrote {} new or changed files ({} MB)",
+                stats.reused_files,
+                stats.reused_bytes.div_ceil(1024 * 1024),
+                stats.written_files,
+                stats.written_bytes.div_ceil(1024 * 1024),
+            ));
+
+            stats
+        } else {
+            writevpk::pack::pack_directory(
+                &working_vpk_dir,
+                staging.vpk_dir(),
+                &config.vpk_name,
+                config.vpk_split_size,
+                manifest,
+            )?
+        };
+
+        // everything has been generated & packed, so it's committed into the game from here on
+        state.push_status("Checking the staged particles");
+        let staged_particles = staging.read_particles()?;
+
+        state.push_status("Restoring the vanilla particles");
+        vanilla.restore()?;
+
+        state.begin_stage("Writing particles", 1, 0);
+        state.push_status(format!("Writing {} particles", staged_particles.len()));
+        vanilla.write_all(&staged_particles)?;
+        report
+            .patched_files
+            .extend(staged_particles.iter().map(|(name, _)| vanilla.describe(name)));
+        state.advance_stage(1, 0);
+
+        state.push_status(format!("Moving {}.vpk into tf/custom", config.vpk_name));
+        let committed_vpk = staging.commit_vpk(&tf_custom_dir)?;
+
+        state.push_status("Removing old VPKs & caches");
+        remove_old_dazzle_vpks(&tf_custom_dir, &committed_vpk)?;
+
+        if stats.deduplicated_files > 0 {
+            state.push_status(format!(
+                "Packed {} duplicate files only once, saving {} MB",
+                stats.deduplicated_files,
+                stats.deduplicated_bytes.div_ceil(1024 * 1024),
+            ));
+        }
+
+        report.deduplicated_files = stats.deduplicated_files;
+        report.deduplicated_bytes = stats.deduplicated_bytes;
+        report.packed_bytes = stats.written_bytes + stats.reused_bytes;
 
         // NOTE(dress) after packing everything, cueki does a full-scan of every VPK & file in tf/custom for $ignorez 1 then
         //             replaces each with spaces. This isn't necessary at all, so we just don't do it; anyone can bypass her
@@ -571,7 +1180,8 @@ pub fn start_addon_install(
         state.push_status("Writing gameinfo.txt");
         let gameinfo = fs::read_to_string(&game_info_path)?;
         let gameinfo = gameinfo.replace("type multiplayer_only", "type singleplayer_only");
-        fs::write(&game_info_path, gameinfo)?;
+        staging::write_synced(&game_info_path, gameinfo)?;
+        report.patched_files.push("gameinfo.txt".to_string());
 
         // we delete & re-create the working vpk dir to ensure that its empty before copying addons over. If we dont do
         // this, then the contents of the addons from the previous install will still be present.
@@ -583,17 +1193,16 @@ pub fn start_addon_install(
         }
 
         fs::create_dir(&working_vpk_dir)?;
+        staging.remove()?;
 
         state.push_status("Done!");
         thread::sleep(Duration::from_millis(500));
 
-        Ok(addons)
-    });
-
-    (view, handle)
+        Ok((addons, report))
+    })
 }
 
-fn ensure_all_vtfs_have_matching_vmts(working_vpk_dir: &Utf8PlatformPath, tf2_misc_vpk: &VPK) -> Result<(), anyhow::Error> {
+fn ensure_all_vtfs_have_matching_vmts(working_vpk_dir: &Utf8PlatformPath, vanilla: &VanillaSource) -> Result<(), anyhow::Error> {
     let working_materials_dir = working_vpk_dir.join("materials");
     for entry in WalkDir::new(&working_materials_dir) {
         let entry = entry?;
@@ -618,11 +1227,9 @@ fn ensure_all_vtfs_have_matching_vmts(working_vpk_dir: &Utf8PlatformPath, tf2_mi
         // if the customizations didn't provide their own VMT, then we need to create our own. By default, we just copy
         // whatever VMT vanilla tf2 provides for that VTF. If there is no matching VMT in vanilla tf2, then we just
         // output a very simple default VMT.
-        let vmt_path_in_vpk = vmt_path.strip_prefix(working_vpk_dir)?;
-        if let Some(vpk_vmt_entry) = tf2_misc_vpk.tree.get(vmt_path_in_vpk.as_str()) {
-            let mut entry_reader = vpk_vmt_entry.reader()?;
-
-            io::copy(&mut entry_reader, &mut vmt_file)?;
+        let vmt_path_in_vpk = paths::GamePath::relative_to(&vmt_path, working_vpk_dir)?;
+        if let Some(vanilla_vmt) = vanilla.read(vmt_path_in_vpk.as_str())? {
+            vmt_file.write_all(&vanilla_vmt)?;
         } else {
             let vtf_materials_path = vtf_path.strip_prefix(&working_materials_dir)?;
             let vmt_contents = format!("\"LightmappedGeneric\"
@@ -638,7 +1245,7 @@ fn ensure_all_vtfs_have_matching_vmts(working_vpk_dir: &Utf8PlatformPath, tf2_mi
     Ok(())
 }
 
-fn ensure_vgui_cache_in_hud(working_vpk_dir: &Utf8PlatformPath, tf2_misc_vpk: &VPK) -> Result<(), anyhow::Error> {
+fn ensure_vgui_cache_in_hud(working_vpk_dir: &Utf8PlatformPath, vanilla: &VanillaSource) -> Result<(), anyhow::Error> {
     // TODO: we should generate dazzlevguicache.res based on what warpaints & skyboxes have been customized by the user
     const DAZZLE_VGUI_CACHE_RES: &[u8] = include_bytes!("../static/dazzlevguicache.res");
 
@@ -661,17 +1268,15 @@ fn ensure_vgui_cache_in_hud(working_vpk_dir: &Utf8PlatformPath, tf2_misc_vpk: &V
             // no custom mainmenuoverride.res. We'll assume that the user is using the vanilla
             // mainmenuoverride.res, so we'll extract the vanilla file and prepend
             // `#base "dazzlevguicache.res"` to it.
-            let entry = tf2_misc_vpk
-                .tree
-                .get("resource/ui/mainmenuoverride.res")
-                .ok_or(anyhow!("tf2_misc.vpk is missing mainmenuoverride.res"))?;
+            let vanilla_res = vanilla
+                .read("resource/ui/mainmenuoverride.res")?
+                .ok_or(anyhow!("The vanilla mainmenuoverride.res is missing"))?;
 
             fs::create_dir_all(dest.parent().unwrap())?;
 
-            let mut reader = entry.reader()?;
             let mut file = OpenOptions::new().write(true).create_new(true).open(&dest)?;
             file.write_all(b"#base \"dazzlevguicache.res\"\n")?;
-            io::copy(&mut reader, &mut file)?;
+            file.write_all(&vanilla_res)?;
         }
         Err(err) => Err(err)?,
     }
@@ -683,21 +1288,366 @@ fn ensure_vgui_cache_in_hud(working_vpk_dir: &Utf8PlatformPath, tf2_misc_vpk: &V
     Ok(())
 }
 
-fn process_addon(
+/// Packs `pcf`, preferring the bin named `preferred`, and carrying on if it doesn't fit so that the final
+/// [`pcfpack::PackReport`] describes every item that didn't fit, rather than just the first.
+fn pack_or_record_failure(
+    packer: &mut Packer,
+    item: String,
+    pcf: &mut Pcf,
+    preferred: Option<&str>,
+) -> Result<(), pcfpack::Error> {
+    match packer.pack_preferring(item, pcf, preferred) {
+        Ok(()) | Err(pcfpack::Error::NoFit { .. }) => Ok(()),
+        Err(err) => Err(err),
+    }
+}
+
+/// Runs the validation pass on every addon in `addons`, reporting any warnings as status messages. Reports whether the
+/// particles will need stripping to fit into `bins`.
+///
+/// Returns the combined size of every addon's content, or [`Err`] describing every error if any addon failed
+/// validation, since installing it could break the game.
+fn validate_addons(state: &ProcessState, addons: &[&Addon], bins: &[pcfpack::Bin]) -> anyhow::Result<u64> {
+    state.push_status("Validating addons");
+
+    let limits = addon::validation::Limits::default();
+    let mut content_size = 0;
+    let mut particle_size = 0;
+    let mut failures = Vec::new();
+    for addon in addons {
+        let report = addon.validate(&limits)?;
+        for warning in report.warnings() {
+            state.push_status(format!("{}: {warning}", addon.name()));
+        }
+
+        content_size += report.content_size;
+        particle_size += report.particle_size;
+        if report.is_blocking() {
+            failures.push((addon.name(), report));
+        }
+    }
+
+    if !failures.is_empty() {
+        let mut description = String::from("Some addons failed validation, so nothing was installed:");
+        for (name, report) in &failures {
+            description += &format!("\n\n{name}:");
+            for error in report.errors() {
+                description += &format!("\n  - {error}");
+            }
+        }
+
+        return Err(anyhow!(description));
+    }
+
+    let capacity: u64 = bins.iter().map(pcfpack::Bin::capacity).sum();
+    if particle_size > capacity {
+        state.push_status(format!(
+            "The enabled particle addons are {} KB larger than the vanilla particle budget, so they'll need to be \
+             stripped to fit",
+            (particle_size - capacity).div_ceil(1024)
+        ));
+    }
+
+    Ok(content_size)
+}
+
+/// Checks the packed particles against the engine's limits, returning a warning for every limit they nearly exceed.
+///
+/// Returns [`Err`] describing every exceeded limit, since installing the particles would break the game.
+fn check_particle_budget(state: &ProcessState, packer: &Packer) -> anyhow::Result<Vec<String>> {
+    state.push_status("Checking the particle budget");
+
+    let budget = ParticleBudget::measure(packer.bins().iter().map(|bin| (bin.name(), bin.as_pcf())));
+    let check = budget.check(&budget::Limits::default());
+    let warnings = check
+        .warnings
+        .iter()
+        .map(|warning| format!("The particles are close to an engine limit: {warning}"))
+        .collect();
+
+    if !check.errors.is_empty() {
+        let mut description = String::from(
+            "Your particle addons exceed the engine's limits, so nothing was installed. Try disabling some particle \
+             addons.\n",
+        );
+        for error in &check.errors {
+            description += &format!("\n  - {error}");
+        }
+
+        return Err(anyhow!(description));
+    }
+
+    Ok(warnings)
+}
+
+/// Checks that there's enough free space to copy `content_size` bytes of addon content into `working_vpk_dir`, and
+/// then pack it into `tf_custom_dir`. Both copies are needed at once, so if the two directories share a disk it must
+/// fit both.
+fn ensure_install_space(
     state: &ProcessState,
     working_vpk_dir: &Utf8PlatformPath,
-    bins: &mut Box<[pcfpack::Bin]>,
-    addon: &Addon,
+    tf_custom_dir: &Utf8PlatformPath,
+    content_size: u64,
 ) -> anyhow::Result<()> {
-    for (path, pcf) in &addon.particle_files {
-        state.push_status(format!("Bin-packing {}'s {path}", addon.name()));
+    const MB: u64 = 1024 * 1024;
+
+    state.push_status("Checking free disk space");
+
+    let working_available = paths::available_space(working_vpk_dir)?;
+    let custom_available = paths::available_space(tf_custom_dir)?;
+
+    // the free space is identical on both sides when the directories are on the same disk, which is a good enough
+    // heuristic without querying the volume each one belongs to
+    let checks = if working_available == custom_available {
+        vec![(working_vpk_dir, content_size * 2, working_available)]
+    } else {
+        vec![
+            (working_vpk_dir, content_size, working_available),
+            (tf_custom_dir, content_size, custom_available),
+        ]
+    };
+
+    for (dir, required, available) in checks {
+        if required > available {
+            return Err(anyhow!(
+                "There isn't enough free disk space to install your addons. Installing needs {} MB in '{dir}', but \
+                 only {} MB is available.",
+                required.div_ceil(MB),
+                available / MB
+            ));
+        }
+    }
+
+    Ok(())
+}
+
+fn describe_pack_failure(report: &pcfpack::PackReport) -> String {
+    const MAX_LISTED: usize = 5;
 
-        let graph = pcf.clone().into_connected();
-        for mut pcf in graph {
-            bins.pack(&mut pcf).unwrap();
+    let mut description = format!(
+        "Your particle addons exceed the vanilla particle budget by at least {} KB, so they can't be installed \
+         together. Try disabling some particle addons.\n\nParticles that didn't fit:",
+        report.total_shortfall().div_ceil(1024)
+    );
+
+    for failure in report.failures.iter().take(MAX_LISTED) {
+        description += &format!("\n  - {} ({} KB over)", failure.item, failure.shortfall.div_ceil(1024));
+    }
+
+    if report.failures.len() > MAX_LISTED {
+        description += &format!("\n  - and {} more", report.failures.len() - MAX_LISTED);
+    }
+
+    description
+}
+
+/// The addon's enabled PCFs, with `patches` & the addon's overrides applied, and its disabled systems left out. Each is
+/// returned as `(item, relative_path, pcf)`, where `item` names the PCF in status messages & pack reports.
+/// `targeted_edits` collects the edits of `patches` which targeted one of the PCFs, see [`patches::apply`].
+pub(crate) fn merged_pcfs(
+    state: &ProcessState,
+    addon_state: &AddonState,
+    patches: &[Patch],
+    targeted_edits: &mut HashSet<(usize, usize)>,
+) -> Vec<(String, String, Pcf)> {
+    let AddonState {
+        addon,
+        particles,
+        overrides,
+        ..
+    } = addon_state;
+
+    // particle_files is unordered, but packing is order-sensitive; so, we sort it to keep installs reproducible.
+    let mut particle_files: Vec<_> = addon.particle_files.iter().collect();
+    particle_files.sort_by_key(|(path, _)| *path);
+
+    let mut pcfs = Vec::new();
+    for (path, pcf) in particle_files {
+        let relative_path = relative_pcf_path(addon, path);
+        if !particles.is_pcf_enabled(&relative_path) {
+            continue;
+        }
+
+        // patches & overrides are applied before any systems are left out, since they're addressed by system name.
+        // The addon's own overrides come last, since they're more specific than the user's patches.
+        let item = format!("{}/{path}", addon.name());
+        let mut pcf = pcf.clone();
+        patches::apply(state, &item, &mut pcf, patches, targeted_edits);
+        for attribute_override in overrides.iter().filter(|tweak| tweak.pcf == relative_path) {
+            if let Err(err) = pcf.set_attribute(&attribute_override.path(), attribute_override.value.into()) {
+                state.push_status(format!(
+                    "{}: skipping a particle tweak to {relative_path}, since it no longer applies: {err}",
+                    addon.name()
+                ));
+            }
+        }
+
+        let pcf = match pcf.without_root_systems(|system| !particles.is_system_selected(&relative_path, &system.name)) {
+            Ok(pcf) => pcf,
+            Err(err) => {
+                state.push_status(format!("{}: skipping {relative_path}: {err}", addon.name()));
+                continue;
+            }
+        };
+
+        pcfs.push((item, relative_path, pcf));
+    }
+
+    pcfs
+}
+
+/// Packs the selected particles from `addons`, followed by every vanilla particle system they don't replace, into
+/// `bins`. Vanilla PCFs which aren't one of `bins` are left as they are, so their systems don't need to be packed.
+/// `addons` are merged in priority order first, see [`particle_merge`]. `patches` are applied to both first. If they
+/// don't fit, the particles are stripped with each [`StripStage`] in turn and packed again.
+///
+/// Particle systems which were packed by the previous install, according to `previous_bins`, are packed first & kept
+/// in the same bin if they still fit, so that the bins which didn't change are patched with the same bytes.
+///
+/// Returns the packer along with the [`StripStage`] that was required for everything to fit.
+fn pack_particles(
+    state: &ProcessState,
+    addons: &[&AddonState],
+    bins: &[pcfpack::Bin],
+    vanilla_graphs: &OrderMap<String, Vec<Pcf>>,
+    patches: &[Patch],
+    previous_bins: &BTreeMap<String, String>,
+) -> anyhow::Result<(Packer, StripStage)> {
+    let mut targeted_edits = HashSet::new();
+    let mut addon_particles = Vec::with_capacity(addons.len());
+    for addon_state in addons {
+        let pcfs = merged_pcfs(state, addon_state, patches, &mut targeted_edits)
+            .into_iter()
+            .map(|(item, _, pcf)| (item, pcf))
+            .collect();
+
+        addon_particles.push(AddonParticles {
+            addon: addon_state.addon.name(),
+            pcfs,
+            overrides: &addon_state.overrides,
+        });
+    }
+
+    let merged = particle_merge::merge(addon_particles);
+    for note in &merged.notes {
+        state.push_status(note);
+    }
+
+    let addon_pcfs = merged.pcfs;
+    let packed_system_names: HashSet<_> = addon_pcfs
+        .iter()
+        .flat_map(|(_, pcf)| pcf.particle_systems().iter().map(|system| system.name.clone()))
+        .collect();
+
+    // the bins don't contain any of the necessary particle systems by default, since they're supposed to be a blank
+    // slate for our addons; so, we pack every vanilla particle system not present in the bins.
+    let missing_vanilla_graphs: Vec<_> = vanilla_graphs
+        .iter()
+        .filter(|(name, _)| bins.iter().any(|bin| bin.name() == name.as_str()))
+        .flat_map(|(name, graphs)| graphs.iter().map(move |graph| (format!("vanilla {name}"), graph)))
+        .filter(|(_, graph)| {
+            graph
+                .particle_systems()
+                .iter()
+                .any(|system| !packed_system_names.contains(&system.name))
+        })
+        .map(|(item, graph)| {
+            let mut graph = graph.clone();
+            patches::apply(state, &item, &mut graph, patches, &mut targeted_edits);
+            (item, graph)
+        })
+        .collect();
+
+    patches::report_untargeted(state, patches, &targeted_edits);
+
+    let mut report = None;
+    let mut previous_size = None;
+    for stage in StripStage::ALL {
+        // addons often ship identical copies of the same helper systems, which only need to be packed once
+        let mut pcfs = Vec::new();
+        let mut packed_hashes: HashMap<u64, Vec<usize>> = HashMap::new();
+        let mut duplicates = 0;
+        for (item, pcf) in &addon_pcfs {
+            for graph in stage.apply(pcf.clone()).into_connected() {
+                // a matching hash is only a hint, the graphs are compared before one is skipped
+                let same_hash = packed_hashes.entry(graph.content_hash()).or_default();
+                if same_hash.iter().any(|&idx| pcfs[idx].1.same_content(&graph)) {
+                    duplicates += graph.particle_systems().len();
+                } else {
+                    same_hash.push(pcfs.len());
+                    pcfs.push((item, graph));
+                }
+            }
+        }
+
+        if duplicates > 0 {
+            state.push_status(format!("Skipping {duplicates} duplicated particle systems"));
+        }
+
+        for (item, graph) in &missing_vanilla_graphs {
+            pcfs.push((item, stage.apply(graph.clone())));
+        }
+
+        // the sort is stable, so the previously packed systems keep their relative order
+        let mut pcfs: Vec<_> = pcfs
+            .into_iter()
+            .map(|(item, pcf)| {
+                let preferred = previous_bin(previous_bins, &pcf);
+                (item, pcf, preferred)
+            })
+            .collect();
+        pcfs.sort_by_key(|(_, _, preferred)| preferred.is_none());
+
+        // a stage which didn't shrink anything won't make the particles fit either
+        let size: usize = pcfs.iter().map(|(_, pcf, _)| pcf.encoded_size()).sum();
+        if previous_size.replace(size) == Some(size) {
+            continue;
         }
+
+        state.begin_stage(format!("Bin-packing particles ({stage})"), pcfs.len(), size as u64);
+
+        let mut packer = Packer::new(bins.to_vec());
+        for (item, mut pcf, preferred) in pcfs {
+            state.push_status(format!("Bin-packing {item} ({stage})"));
+            let pcf_size = pcf.encoded_size() as u64;
+            pack_or_record_failure(&mut packer, item.clone(), &mut pcf, preferred)?;
+            state.advance_stage(1, pcf_size);
+        }
+
+        let stage_report = packer.report();
+        if stage_report.is_success() {
+            return Ok((packer, stage));
+        }
+
+        report = Some(stage_report);
     }
 
+    let report = report.expect("there is always at least one strip stage");
+    Err(anyhow!(describe_pack_failure(&report)))
+}
+
+/// The bin that the first of `graph`'s particle systems was packed into by the previous install, if any.
+fn previous_bin<'a>(previous_bins: &'a BTreeMap<String, String>, graph: &Pcf) -> Option<&'a str> {
+    graph
+        .particle_systems()
+        .iter()
+        .find_map(|system| previous_bins.get(&system.name))
+        .map(String::as_str)
+}
+
+/// The name of the bin each particle system in `bins` was packed into, by the system's name.
+fn bin_assignments(bins: &[pcfpack::Bin]) -> BTreeMap<String, String> {
+    bins.iter()
+        .flat_map(|bin| {
+            bin.as_pcf()
+                .particle_systems()
+                .iter()
+                .map(|system| (system.name.clone(), bin.name().to_string()))
+        })
+        .collect()
+}
+
+fn process_addon(state: &ProcessState, working_vpk_dir: &Utf8PlatformPath, addon: &Addon) -> anyhow::Result<()> {
     let content_path = &addon.content_path;
     for entry in WalkDir::new(content_path).contents_first(false) {
         let entry = entry?;
@@ -705,7 +1655,7 @@ fn process_addon(
 
         state.push_status(format!("Processing {}'s {}", addon.name(), entry.path().display()));
 
-        let path = paths::to_typed(entry.path()).absolutize()?;
+        let path = paths::try_to_typed(entry.path())?.absolutize()?;
         let new_out_path = working_vpk_dir.join(path.strip_prefix(content_path)?);
 
         // create the directory before we copy anything over. We guarantee that the directory is iterated first
@@ -719,38 +1669,33 @@ fn process_addon(
             continue;
         }
 
-        fs::copy(&path, &new_out_path)?;
-    }
-
-    Ok(())
-}
-
-fn restore_tf2_misc_vpk(vpk: &mut VPK) -> anyhow::Result<()> {
-    for (name, pcf_data) in particles_manifest::PARTICLES_BYTES {
-        let mut reader = pcf_data.reader();
-        vpk.patch_file(name, pcf_data.len() as u64, &mut reader)?;
+        let copied = fs::copy(&*paths::long_path(&path), &*paths::long_path(&new_out_path))?;
+        state.advance_stage(0, copied);
     }
 
     Ok(())
 }
 
-fn remove_old_dazzle_vpks(tf_custom_dir: &Utf8PlatformPath) -> anyhow::Result<()> {
+/// Removes every dazzle VPK & VGUI cache in `tf_custom_dir`, including those packed under a name which is no longer
+/// configured, except for the files named in `kept`.
+fn remove_old_dazzle_vpks(tf_custom_dir: &Utf8PlatformPath, kept: &[String]) -> anyhow::Result<()> {
     for entry in fs::read_dir(tf_custom_dir)? {
         let entry = entry?;
         let path = paths::std_buf_to_typed(entry.path());
         let file_name = path.file_name().unwrap();
         let extension = path.extension().unwrap_or("");
-        let is_dazzle = file_name.starts_with("_dazzle_addons")
+        let is_dazzle = file_name.starts_with(config::VPK_NAME_PREFIX)
             && (extension.eq_ignore_ascii_case("vpk") || extension.eq_ignore_ascii_case("cache"));
         let metadata = entry.metadata()?;
         if !metadata.is_file() {
             if is_dazzle {
-                return Err(anyhow!("Unexpected directory or symlink with _dazzle_addons*.vpk name"));
+                return Err(anyhow!("Unexpected directory or symlink with _dazzle_*.vpk name"));
             }
             continue;
         }
 
-        if is_dazzle {
+        let is_kept = kept.iter().any(|kept| kept == file_name);
+        if is_dazzle && !is_kept {
             fs::remove_file(&path)?;
         }
     }
@@ -766,46 +1711,52 @@ fn update_config_addon_states(addons: &[AddonState], config: &mut Config) {
             .and_modify(|addon_config| {
                 addon_config.enabled = addon_state.enabled;
                 addon_config.order = idx;
+                addon_config.particles = addon_state.particles.clone();
+                addon_config.overrides = addon_state.overrides.clone();
             })
             .or_insert(AddonConfig {
                 enabled: addon_state.enabled,
                 order: idx,
+                particles: addon_state.particles.clone(),
+                overrides: addon_state.overrides.clone(),
             });
     }
 }
 
-pub type AddonUninstallJob = JoinHandle<anyhow::Result<Vec<AddonState>>>;
+pub type AddonUninstallJob = Task<anyhow::Result<Vec<AddonState>>>;
 
 pub fn start_addon_uninstall(
     ctx: &egui::Context,
     paths: &Paths,
     config: &Config,
     addons: Vec<AddonState>,
-) -> (ProcessView, AddonUninstallJob) {
-    const TF2_VPK_NAME: &str = "tf2_misc_dir.vpk";
-
-    let (state, view) = ProcessState::with_spinner(ctx);
-
+) -> AddonUninstallJob {
     let working_vpk_dir = paths.working_vpk.clone();
 
     let tf_custom_dir = config.tf_dir.join("custom");
-    let vpk_path = config.tf_dir.join(TF2_VPK_NAME);
     let game_info_path = config.tf_dir.join("gameinfo.txt");
     let config_path = paths.config.clone();
     let mut config = config.clone();
 
-    let handle = thread::spawn(move || -> anyhow::Result<Vec<AddonState>> {
+    Task::spawn_with_spinner(ctx, move |state| -> anyhow::Result<Vec<AddonState>> {
         state.push_status("Saving updated config");
         update_config_addon_states(&addons, &mut config);
         config::write_config(&config_path, &config)?;
 
-        let mut tf2_misc_vpk = VPK::read(vpk_path)?;
+        let mut vanilla = VanillaSource::open(&config.tf_dir)?;
+
+        // restoring, removing, writing gameinfo.txt, cleaning up & verifying
+        state.begin_stage("Uninstalling", 5, 0);
 
-        state.push_status("Restoring tf2_misc.vpk");
-        restore_tf2_misc_vpk(&mut tf2_misc_vpk)?;
+        state.push_status("Restoring the vanilla particles");
+        vanilla.restore()?;
+        state.advance_stage(1, 0);
 
-        state.push_status("Removing old _dazzle_addons.vpk");
-        remove_old_dazzle_vpks(&tf_custom_dir)?;
+        state.push_status(format!("Removing old {}.vpk", config.vpk_name));
+        remove_old_dazzle_vpks(&tf_custom_dir, &[])?;
+        staging::remove_dir(&tf_custom_dir.join(staging::STAGED_VPK_DIR))?;
+        preview::remove(&config.tf_dir)?;
+        state.advance_stage(1, 0);
 
         // TODO: remove _dazzle_qpc.vpk
 
@@ -813,7 +1764,8 @@ pub fn start_addon_uninstall(
         state.push_status("Writing gameinfo.txt");
         let gameinfo = fs::read_to_string(&game_info_path)?;
         let gameinfo = gameinfo.replace("type singleplayer_only", "type multiplayer_only");
-        fs::write(&game_info_path, gameinfo)?;
+        staging::write_synced(&game_info_path, gameinfo)?;
+        state.advance_stage(1, 0);
 
         // we delete & re-create the working vpk dir to ensure that its empty when installing addons again.
         state.push_status("Cleaning up working files");
@@ -824,12 +1776,28 @@ pub fn start_addon_uninstall(
         }
 
         fs::create_dir(&working_vpk_dir)?;
+        state.advance_stage(1, 0);
+
+        // files the user moved or renamed by hand won't have been caught by the steps above
+        state.push_status("Verifying that nothing was left behind");
+        let orphans = orphans::find_orphans(&tf_custom_dir, &vanilla, &working_vpk_dir)?;
+        if !orphans.is_empty() {
+            let mut query = String::from("Some files from a previous dazzle install were left behind:");
+            for orphan in &orphans {
+                query += &format!("\n  - {orphan}");
+            }
+            query += "\n\nRemove them?";
+
+            if state.confirm(query, ["Leave them", "Remove them"]) == 1 {
+                state.push_status("Removing leftover files");
+                orphans::remove_orphans(&orphans, &mut vanilla)?;
+            }
+        }
+        state.advance_stage(1, 0);
 
         state.push_status("Done!");
         thread::sleep(Duration::from_millis(500));
 
         Ok(addons)
-    });
-
-    (view, handle)
+    })
 }
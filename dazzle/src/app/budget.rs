@@ -0,0 +1,136 @@
+//! Estimates how much of the engine's fixed particle limits the packed PCFs use, so that an install which would break
+//! the game can be stopped before anything is written. The particles are always packed into the vanilla PCFs, so the
+//! particles manifest never grows; only the contents of each PCF can push past a limit.
+
+use std::collections::HashSet;
+
+use pcf::Pcf;
+
+#[derive(Debug, Clone, Copy)]
+pub(crate) struct Limits {
+    /// the most symbols in one PCF, since binary DMX indexes its symbol table with 16 bits
+    pub max_symbols: usize,
+
+    /// the most distinct particle system names across every PCF. The engine looks particle systems up by name in a
+    /// symbol table, which is indexed with 16 bits
+    pub max_particle_systems: usize,
+}
+
+impl Limits {
+    /// Usage past this percentage of a limit is warned about, since the estimate isn't exact.
+    const WARN_PERCENT: usize = 90;
+}
+
+impl Default for Limits {
+    fn default() -> Self {
+        Self {
+            max_symbols: u16::MAX.into(),
+            max_particle_systems: u16::MAX.into(),
+        }
+    }
+}
+
+#[derive(Debug, Clone, Default)]
+pub(crate) struct ParticleBudget {
+    /// the number of symbols in each PCF, along with its name
+    pub symbols: Vec<(String, usize)>,
+
+    /// the number of distinct particle system names across every PCF
+    pub particle_systems: usize,
+}
+
+#[derive(Debug, Clone, Default)]
+pub(crate) struct BudgetCheck {
+    /// limits which are exceeded, so the game would crash or fail to load the particles
+    pub errors: Vec<String>,
+
+    /// limits which are nearly exceeded
+    pub warnings: Vec<String>,
+}
+
+impl ParticleBudget {
+    /// Measures `pcfs`, which are `(name, pcf)` pairs.
+    pub(crate) fn measure<'a>(pcfs: impl IntoIterator<Item = (&'a str, &'a Pcf)>) -> Self {
+        let mut symbols = Vec::new();
+        let mut particle_systems = HashSet::new();
+        for (name, pcf) in pcfs {
+            symbols.push((name.to_string(), pcf.symbols().base.len()));
+            particle_systems.extend(pcf.particle_systems().iter().map(|system| system.name.as_str()));
+        }
+
+        Self {
+            symbols,
+            particle_systems: particle_systems.len(),
+        }
+    }
+
+    pub(crate) fn check(&self, limits: &Limits) -> BudgetCheck {
+        let mut check = BudgetCheck::default();
+        for (name, count) in &self.symbols {
+            check.push(*count, limits.max_symbols, || format!("{name} has {count} symbols"));
+        }
+
+        check.push(self.particle_systems, limits.max_particle_systems, || {
+            format!("there are {} distinct particle systems", self.particle_systems)
+        });
+
+        check
+    }
+}
+
+impl BudgetCheck {
+    fn push(&mut self, count: usize, limit: usize, describe: impl FnOnce() -> String) {
+        if count > limit {
+            self.errors.push(format!("{}, but the limit is {limit}", describe()));
+        } else if count * 100 > limit * Limits::WARN_PERCENT {
+            self.warnings
+                .push(format!("{}, which is close to the limit of {limit}", describe()));
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{Limits, ParticleBudget};
+
+    const LIMITS: Limits = Limits {
+        max_symbols: 100,
+        max_particle_systems: 1000,
+    };
+
+    #[test]
+    fn usage_within_limits_passes() {
+        let budget = ParticleBudget {
+            symbols: vec![("particles/a.pcf".to_string(), 50), ("particles/b.pcf".to_string(), 90)],
+            particle_systems: 900,
+        };
+
+        let check = budget.check(&LIMITS);
+        assert_eq!(check.errors, Vec::<String>::new());
+        assert_eq!(check.warnings, Vec::<String>::new());
+    }
+
+    #[test]
+    fn usage_near_or_past_limits_is_reported() {
+        let budget = ParticleBudget {
+            symbols: vec![
+                ("particles/a.pcf".to_string(), 95),
+                ("particles/b.pcf".to_string(), 101),
+            ],
+            particle_systems: 1001,
+        };
+
+        let check = budget.check(&LIMITS);
+        assert_eq!(
+            check.warnings,
+            ["particles/a.pcf has 95 symbols, which is close to the limit of 100"]
+        );
+        assert_eq!(
+            check.errors,
+            [
+                "particles/b.pcf has 101 symbols, but the limit is 100",
+                "there are 1001 distinct particle systems, but the limit is 1000",
+            ]
+        );
+    }
+}
@@ -1,13 +1,20 @@
 use std::{
-    collections::HashMap,
+    cmp::Reverse,
+    collections::{BTreeMap, BTreeSet, HashMap},
     fs::{self, OpenOptions},
-    io::{self, Read, Write},
+    io::{self, ErrorKind, Read, Write},
 };
 
+use dmx::Color;
+use glob::{MatchOptions, Pattern};
+use pcf::{Attribute, AttributePath};
+use pcfpack::Bin;
 use serde::{Deserialize, Serialize};
 use thiserror::Error;
 use typed_path::{Utf8PlatformPath, Utf8PlatformPathBuf};
 
+use crate::{particles_manifest, styles::Theme};
+
 mod serde_path_string {
     use serde::{Deserializer, Serializer, de::Visitor};
     use typed_path::{Utf8PlatformPathBuf, Utf8TypedPath, Utf8UnixPathBuf, Utf8WindowsPathBuf};
@@ -71,15 +78,396 @@ pub struct Config {
 
     #[serde(default)]
     pub addons: HashMap<String, AddonConfig>,
+
+    /// the size, in bytes, at which `_dazzle_addons.vpk` is split into numbered archives
+    #[serde(default = "Config::default_vpk_split_size")]
+    pub vpk_split_size: u32,
+
+    /// whether installs reuse the unchanged parts of the previous `_dazzle_addons.vpk`, rather than rebuilding it
+    #[serde(default = "Config::default_incremental_builds")]
+    pub incremental_builds: bool,
+
+    /// whether installs keep particle systems in the bins the previous install packed them into, so that adding an
+    /// addon doesn't reshuffle & rewrite every bin
+    #[serde(default = "Config::default_stable_packing")]
+    pub stable_packing: bool,
+
+    /// the name of the VPK which addons are packed into, without the `.vpk` extension
+    #[serde(default = "Config::default_vpk_name")]
+    pub vpk_name: String,
+
+    /// whether installs are timed & measured in the data dir's metrics file, which never leaves the user's machine
+    #[serde(default = "Config::default_record_metrics")]
+    pub record_metrics: bool,
+
+    #[serde(default, skip_serializing_if = "BinConfig::is_empty")]
+    pub bins: BinConfig,
+
+    #[serde(default)]
+    pub theme: Theme,
 }
 
-#[derive(Debug, Clone, Copy, Serialize, Deserialize)]
+impl Config {
+    fn default_vpk_split_size() -> u32 {
+        writevpk::pack::DEFAULT_SPLIT_SIZE
+    }
+
+    fn default_incremental_builds() -> bool {
+        true
+    }
+
+    fn default_stable_packing() -> bool {
+        true
+    }
+
+    fn default_record_metrics() -> bool {
+        true
+    }
+
+    fn default_vpk_name() -> String {
+        DEFAULT_VPK_NAME.to_string()
+    }
+
+    /// Checks the settings which can't be checked while parsing the config.
+    ///
+    /// # Errors
+    ///
+    /// Returns [`SettingError`] describing the first invalid setting.
+    pub fn validate(&self) -> Result<(), SettingError> {
+        validate_vpk_name(&self.vpk_name)?;
+        self.bins.select(particles_manifest::bins())?;
+        Ok(())
+    }
+}
+
+/// The name of the VPK which addons are packed into, unless it's been configured.
+pub const DEFAULT_VPK_NAME: &str = "_dazzle_addons";
+
+/// Every VPK dazzle generates starts with this, so that they're recognized as dazzle's even after they're renamed.
+pub const VPK_NAME_PREFIX: &str = "_dazzle_";
+
+fn validate_vpk_name(name: &str) -> Result<(), SettingError> {
+    let is_valid = name.len() > VPK_NAME_PREFIX.len()
+        && name.starts_with(VPK_NAME_PREFIX)
+        && !name.ends_with("_dir")
+        && name
+            .chars()
+            .all(|char| char.is_ascii_alphanumeric() || char == '_' || char == '-');
+
+    if is_valid {
+        Ok(())
+    } else {
+        Err(SettingError::VpkName(name.to_string()))
+    }
+}
+
+/// Whether `file_name` is one of the files generated for the VPK named `vpk_name` - either a single VPK, or the
+/// directory or one of the numbered archives of a split VPK.
+pub fn is_output_vpk(file_name: &str, vpk_name: &str) -> bool {
+    let Some(suffix) = file_name
+        .strip_prefix(vpk_name)
+        .and_then(|rest| rest.strip_suffix(".vpk"))
+    else {
+        return false;
+    };
+
+    match suffix.strip_prefix('_') {
+        None => suffix.is_empty(),
+        Some(archive) => archive == "dir" || (!archive.is_empty() && archive.bytes().all(|byte| byte.is_ascii_digit())),
+    }
+}
+
+/// Which vanilla PCFs addon particles are packed into, and how much of each can be filled. Every vanilla PCF which is
+/// loaded on startup is a bin by default, and can be filled up to its vanilla size.
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub struct BinConfig {
+    /// the vanilla PCFs to pack into, e.g. `particles/explosion.pcf`. Every bin is used when there are none. The PCFs
+    /// which aren't used are left vanilla.
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub targets: Vec<String>,
+
+    /// the most bins to pack into, keeping the largest ones
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub max_count: Option<usize>,
+
+    /// the most bytes each bin can be filled with, by the bin's name. A bin can't be filled past its vanilla size.
+    #[serde(default, skip_serializing_if = "BTreeMap::is_empty")]
+    pub capacities: BTreeMap<String, u64>,
+}
+
+impl BinConfig {
+    pub fn is_empty(&self) -> bool {
+        self.targets.is_empty() && self.max_count.is_none() && self.capacities.is_empty()
+    }
+
+    /// The bins to pack into out of the `vanilla` bins, with their capacities overridden.
+    ///
+    /// # Errors
+    ///
+    /// Returns [`SettingError`] if a bin isn't one of the `vanilla` bins, if a capacity is larger than its bin's vanilla
+    /// size, or if no bins would be left.
+    pub fn select(&self, vanilla: impl Into<Vec<Bin>>) -> Result<Box<[Bin]>, SettingError> {
+        let mut bins = vanilla.into();
+
+        let unknown = self
+            .targets
+            .iter()
+            .chain(self.capacities.keys())
+            .find(|name| !bins.iter().any(|bin| bin.name() == *name));
+        if let Some(name) = unknown {
+            return Err(SettingError::UnknownBin(name.clone()));
+        }
+
+        if !self.targets.is_empty() {
+            bins.retain(|bin| self.targets.iter().any(|target| target == bin.name()));
+        }
+
+        if let Some(max_count) = self.max_count {
+            bins.sort_by_key(|bin| Reverse(bin.capacity()));
+            bins.truncate(max_count);
+        }
+
+        if bins.is_empty() {
+            return Err(SettingError::NoBins);
+        }
+
+        bins.into_iter()
+            .map(|bin| {
+                let Some(&capacity) = self.capacities.get(bin.name()) else {
+                    return Ok(bin);
+                };
+
+                if capacity > bin.capacity() {
+                    return Err(SettingError::BinCapacity {
+                        name: bin.name().to_string(),
+                        capacity,
+                        vanilla: bin.capacity(),
+                    });
+                }
+
+                let (name, pcf) = bin.into_inner();
+                Ok(Bin::new(capacity, name, pcf))
+            })
+            .collect()
+    }
+}
+
+#[derive(Debug, Error, PartialEq, Eq)]
+pub enum SettingError {
+    #[error(
+        "{0:?} isn't a valid VPK name. It must start with \"_dazzle_\", can't end with \"_dir\", and can only \
+         contain letters, numbers, underscores & dashes"
+    )]
+    VpkName(String),
+
+    #[error("{0:?} isn't one of the vanilla particle bins")]
+    UnknownBin(String),
+
+    #[error(
+        "the capacity of {name} can't be larger than its vanilla size of {vanilla} bytes, but it's set to {capacity}"
+    )]
+    BinCapacity { name: String, capacity: u64, vanilla: u64 },
+
+    #[error("the bin settings leave no bins to pack particles into")]
+    NoBins,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct AddonConfig {
     #[serde(default = "AddonConfig::default_enabled")]
     pub enabled: bool,
 
     #[serde(default = "AddonConfig::default_order")]
     pub order: usize,
+
+    #[serde(default, skip_serializing_if = "ParticleSelection::is_empty")]
+    pub particles: ParticleSelection,
+
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub overrides: Vec<AttributeOverride>,
+}
+
+/// Which of an addon's particles get installed. Everything is installed unless it's been disabled.
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub struct ParticleSelection {
+    /// PCFs which won't be installed, relative to the addon's content - e.g. `particles/fire.pcf`
+    #[serde(default, skip_serializing_if = "BTreeSet::is_empty")]
+    pub disabled_pcfs: BTreeSet<String>,
+
+    /// root particle systems which won't be installed, by the PCF they're in. Their children are also left out, unless
+    /// another installed system uses them.
+    #[serde(default, skip_serializing_if = "BTreeMap::is_empty")]
+    pub disabled_systems: BTreeMap<String, BTreeSet<String>>,
+
+    /// glob patterns for the names of the root particle systems to install, e.g. `explosion_*`. When there are any,
+    /// only the systems matching at least one of them are installed, from any PCF.
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub include_systems: Vec<String>,
+
+    /// glob patterns for the names of root particle systems which are never installed, even if they're included
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub exclude_systems: Vec<String>,
+}
+
+impl ParticleSelection {
+    pub const EMPTY: ParticleSelection = ParticleSelection {
+        disabled_pcfs: BTreeSet::new(),
+        disabled_systems: BTreeMap::new(),
+        include_systems: Vec::new(),
+        exclude_systems: Vec::new(),
+    };
+
+    pub fn is_empty(&self) -> bool {
+        self.disabled_pcfs.is_empty()
+            && self.disabled_systems.values().all(BTreeSet::is_empty)
+            && self.include_systems.is_empty()
+            && self.exclude_systems.is_empty()
+    }
+
+    pub fn is_pcf_enabled(&self, pcf: &str) -> bool {
+        !self.disabled_pcfs.contains(pcf)
+    }
+
+    pub fn set_pcf_enabled(&mut self, pcf: &str, enabled: bool) {
+        if enabled {
+            self.disabled_pcfs.remove(pcf);
+        } else {
+            self.disabled_pcfs.insert(pcf.to_string());
+        }
+    }
+
+    pub fn is_system_enabled(&self, pcf: &str, system: &str) -> bool {
+        self.disabled_systems
+            .get(pcf)
+            .is_none_or(|disabled| !disabled.contains(system))
+    }
+
+    /// Whether the root system `system` is left out by [`ParticleSelection::include_systems`] or
+    /// [`ParticleSelection::exclude_systems`]. Names are matched ignoring case. Blank patterns are ignored, and invalid
+    /// patterns never match.
+    pub fn is_system_filtered_out(&self, system: &str) -> bool {
+        let mut includes = non_blank(&self.include_systems).peekable();
+        let is_included = includes.peek().is_none() || includes.any(|pattern| system_matches(pattern, system));
+
+        !is_included || non_blank(&self.exclude_systems).any(|pattern| system_matches(pattern, system))
+    }
+
+    /// Whether the root system `system` in `pcf` is installed: it's enabled, and isn't filtered out.
+    pub fn is_system_selected(&self, pcf: &str, system: &str) -> bool {
+        self.is_system_enabled(pcf, system) && !self.is_system_filtered_out(system)
+    }
+
+    /// Removes blank patterns from the filters, which are left behind while they're being edited.
+    pub fn trim_filters(&mut self) {
+        for patterns in [&mut self.include_systems, &mut self.exclude_systems] {
+            patterns.retain_mut(|pattern| {
+                *pattern = pattern.trim().to_string();
+                !pattern.is_empty()
+            });
+        }
+    }
+
+    pub fn set_system_enabled(&mut self, pcf: &str, system: &str, enabled: bool) {
+        if enabled {
+            if let Some(disabled) = self.disabled_systems.get_mut(pcf) {
+                disabled.remove(system);
+                if disabled.is_empty() {
+                    self.disabled_systems.remove(pcf);
+                }
+            }
+        } else {
+            self.disabled_systems
+                .entry(pcf.to_string())
+                .or_default()
+                .insert(system.to_string());
+        }
+    }
+}
+
+fn non_blank(patterns: &[String]) -> impl Iterator<Item = &str> {
+    patterns
+        .iter()
+        .map(|pattern| pattern.trim())
+        .filter(|pattern| !pattern.is_empty())
+}
+
+/// Whether the system `name` matches the glob `pattern`, ignoring case. An invalid pattern never matches.
+fn system_matches(pattern: &str, name: &str) -> bool {
+    let options = MatchOptions {
+        case_sensitive: false,
+        ..MatchOptions::new()
+    };
+
+    Pattern::new(pattern).is_ok_and(|pattern| pattern.matches_with(name, options))
+}
+
+/// A change made in the particle tweaker to one attribute of one of an addon's particle systems. Overrides are applied
+/// to the addon's PCFs while installing, so the addon itself is never modified.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct AttributeOverride {
+    /// the PCF, relative to the addon's content - e.g. `particles/fire.pcf`
+    pub pcf: String,
+
+    pub system: String,
+
+    /// the operator which owns the attribute, or `None` if it's one of the particle system's own attributes
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub operator: Option<String>,
+
+    pub attribute: String,
+    pub value: OverrideValue,
+}
+
+impl AttributeOverride {
+    pub fn path(&self) -> AttributePath {
+        AttributePath {
+            system: self.system.clone(),
+            operator: self.operator.clone(),
+            attribute: self.attribute.clone(),
+        }
+    }
+
+    /// Whether this overrides the attribute at `path` in `pcf`.
+    pub fn is_at(&self, pcf: &str, path: &AttributePath) -> bool {
+        self.pcf == pcf
+            && self.system == path.system
+            && self.operator == path.operator
+            && self.attribute == path.attribute
+    }
+}
+
+/// The value of an [`AttributeOverride`]. Only scalar & color attributes can be tweaked.
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum OverrideValue {
+    Integer(i32),
+    Float(f32),
+    Bool(bool),
+    Color([u8; 4]),
+}
+
+impl OverrideValue {
+    /// The value of `attribute`, or `None` if its type can't be tweaked.
+    pub fn from_attribute(attribute: &Attribute) -> Option<Self> {
+        match attribute {
+            Attribute::Integer(value) => Some(Self::Integer(*value)),
+            Attribute::Float(value) => Some(Self::Float(value.0)),
+            Attribute::Bool(value) => Some(Self::Bool(*value)),
+            Attribute::Color(Color(r, g, b, a)) => Some(Self::Color([*r, *g, *b, *a])),
+            _ => None,
+        }
+    }
+}
+
+impl From<OverrideValue> for Attribute {
+    fn from(value: OverrideValue) -> Self {
+        match value {
+            OverrideValue::Integer(value) => Attribute::Integer(value),
+            OverrideValue::Float(value) => value.into(),
+            OverrideValue::Bool(value) => Attribute::Bool(value),
+            OverrideValue::Color([r, g, b, a]) => Attribute::Color(Color(r, g, b, a)),
+        }
+    }
 }
 
 impl Default for AddonConfig {
@@ -92,6 +480,8 @@ impl AddonConfig {
     const DEFAULT: AddonConfig = AddonConfig {
         enabled: true,
         order: usize::MAX,
+        particles: ParticleSelection::EMPTY,
+        overrides: Vec::new(),
     };
 
     fn default_enabled() -> bool {
@@ -113,6 +503,9 @@ pub enum Error {
 
     #[error("couldn't parse the app config")]
     Parse(#[from] toml::de::Error),
+
+    #[error("the app config has an invalid setting")]
+    Invalid(#[from] SettingError),
 }
 
 pub fn create_or_read_config(path: &Utf8PlatformPath) -> Result<Config, Error> {
@@ -120,7 +513,24 @@ pub fn create_or_read_config(path: &Utf8PlatformPath) -> Result<Config, Error> {
     let mut file = OpenOptions::new().create(true).append(true).read(true).open(path)?;
     let mut config = String::new();
     file.read_to_string(&mut config)?;
-    Ok(toml::from_str(&config)?)
+    parse_config(&config)
+}
+
+/// Reads the config at `path` without creating it, like [`create_or_read_config`] otherwise. A missing config is read
+/// as the default one.
+pub fn read_config(path: &Utf8PlatformPath) -> Result<Config, Error> {
+    let config = match fs::read_to_string(path) {
+        Err(err) if err.kind() == ErrorKind::NotFound => String::new(),
+        result => result?,
+    };
+
+    parse_config(&config)
+}
+
+fn parse_config(config: &str) -> Result<Config, Error> {
+    let config: Config = toml::from_str(config)?;
+    config.validate()?;
+    Ok(config)
 }
 
 pub fn write_config(path: &Utf8PlatformPath, config: &Config) -> Result<(), Error> {
@@ -131,3 +541,105 @@ pub fn write_config(path: &Utf8PlatformPath, config: &Config) -> Result<(), Erro
     file.flush()?;
     Ok(())
 }
+
+#[cfg(test)]
+mod tests {
+    use std::collections::BTreeMap;
+
+    use super::{BinConfig, SettingError, is_output_vpk, validate_vpk_name};
+    use crate::particles_manifest;
+
+    #[test]
+    fn recognizes_every_archive_of_an_output_vpk() {
+        for file_name in ["_dazzle_addons.vpk", "_dazzle_addons_dir.vpk", "_dazzle_addons_012.vpk"] {
+            assert!(is_output_vpk(file_name, "_dazzle_addons"), "{file_name}");
+        }
+
+        for file_name in [
+            "_dazzle_addons_old.vpk",
+            "_dazzle_addons_.vpk",
+            "_dazzle_addons.vpk.cache",
+            "_dazzle_x.vpk",
+        ] {
+            assert!(!is_output_vpk(file_name, "_dazzle_addons"), "{file_name}");
+        }
+    }
+
+    #[test]
+    fn only_accepts_dazzle_vpk_names() {
+        assert_eq!(validate_vpk_name("_dazzle_my-addons_2"), Ok(()));
+        for name in ["", "_dazzle_", "my_addons", "_dazzle_addons_dir", "_dazzle_../addons"] {
+            assert_eq!(validate_vpk_name(name), Err(SettingError::VpkName(name.to_string())));
+        }
+    }
+
+    #[test]
+    fn selects_targeted_bins_with_overridden_capacities() {
+        let vanilla = particles_manifest::bins();
+        let [first, second, ..] = &vanilla[..] else {
+            panic!("there should be at least two vanilla bins");
+        };
+
+        let config = BinConfig {
+            targets: vec![first.name().to_string(), second.name().to_string()],
+            max_count: None,
+            capacities: BTreeMap::from([(first.name().to_string(), first.capacity() / 2)]),
+        };
+
+        let bins = config.select(vanilla.clone()).unwrap();
+        let bins: Vec<_> = bins.iter().map(|bin| (bin.name(), bin.capacity())).collect();
+        assert_eq!(
+            bins,
+            [(first.name(), first.capacity() / 2), (second.name(), second.capacity())]
+        );
+    }
+
+    #[test]
+    fn keeps_the_largest_bins() {
+        let vanilla = particles_manifest::bins();
+        let largest = vanilla.iter().map(|bin| bin.capacity()).max().unwrap();
+
+        let config = BinConfig {
+            max_count: Some(1),
+            ..BinConfig::default()
+        };
+
+        let bins = config.select(vanilla).unwrap();
+        assert_eq!(bins.len(), 1);
+        assert_eq!(bins[0].capacity(), largest);
+    }
+
+    #[test]
+    fn rejects_invalid_bins() {
+        let vanilla = particles_manifest::bins();
+        let name = vanilla[0].name().to_string();
+
+        let unknown = BinConfig {
+            targets: vec!["particles/not_a_bin.pcf".to_string()],
+            ..BinConfig::default()
+        };
+        assert_eq!(
+            unknown.select(vanilla.clone()).unwrap_err(),
+            SettingError::UnknownBin("particles/not_a_bin.pcf".to_string())
+        );
+
+        let too_large = BinConfig {
+            capacities: BTreeMap::from([(name.clone(), vanilla[0].capacity() + 1)]),
+            ..BinConfig::default()
+        };
+        assert_eq!(
+            too_large.select(vanilla.clone()).unwrap_err(),
+            SettingError::BinCapacity {
+                name,
+                capacity: vanilla[0].capacity() + 1,
+                vanilla: vanilla[0].capacity(),
+            }
+        );
+
+        let none = BinConfig {
+            max_count: Some(0),
+            ..BinConfig::default()
+        };
+        assert_eq!(none.select(vanilla).unwrap_err(), SettingError::NoBins);
+    }
+}
@@ -0,0 +1,168 @@
+//! Compatibility with installations of cueki's casual preloader, so that users can switch to dazzle without losing
+//! their addon selections or leaving cueki's patches behind.
+//!
+//! A cueki installation looks like this - we only care about the parts listed here:
+//!
+//! ```text
+//! casual-preloader/
+//!   app_settings.json     the preloader's config, including which addons are enabled and in what order
+//!   backup/               pristine copies of every file the preloader patched, relative to tf/
+//!   mods/
+//!     addons/             one folder per addon
+//! ```
+
+use std::{
+    fs, io,
+    path::{Path, PathBuf},
+};
+
+use serde::Deserialize;
+use thiserror::Error;
+use typed_path::{Utf8PlatformPath, Utf8PlatformPathBuf};
+use walkdir::WalkDir;
+
+use crate::app::config::{AddonConfig, Config};
+
+#[derive(Debug, Error)]
+pub(crate) enum Error {
+    #[error("couldn't read cueki's preloader data, due to an IO error")]
+    Io(#[from] io::Error),
+
+    #[error("couldn't parse cueki's preloader settings")]
+    Settings(#[from] serde_json::Error),
+
+    #[error("couldn't walk cueki's backup directory")]
+    Walk(#[from] walkdir::Error),
+}
+
+#[derive(Debug, Default, Deserialize)]
+struct Settings {
+    /// the names of the enabled addons, in load order
+    #[serde(default)]
+    addon_selections: Vec<String>,
+}
+
+#[derive(Debug, Clone)]
+pub(crate) struct CuekiInstall {
+    pub root: Utf8PlatformPathBuf,
+    pub addons_dir: Utf8PlatformPathBuf,
+
+    /// the names of the addons that are enabled in cueki's preloader, in load order
+    pub enabled_addons: Vec<String>,
+
+    /// the names of every addon in cueki's addons directory, sorted
+    pub all_addons: Vec<String>,
+
+    /// the directory containing the original copies of files that cueki's preloader patched, if there are any
+    pub backup_dir: Option<Utf8PlatformPathBuf>,
+}
+
+impl CuekiInstall {
+    /// Detects an installation of cueki's preloader in `dir`.
+    ///
+    /// Returns `Ok(None)` if `dir` doesn't look like a cueki installation.
+    pub(crate) fn detect(dir: &Utf8PlatformPath) -> Result<Option<Self>, Error> {
+        let addons_dir = dir.join("mods").join("addons");
+        if !is_dir(&addons_dir) {
+            return Ok(None);
+        }
+
+        let settings_path = dir.join("app_settings.json");
+        let settings: Settings = match fs::read_to_string(&settings_path) {
+            Ok(settings) => serde_json::from_str(&settings)?,
+            Err(err) if err.kind() == io::ErrorKind::NotFound => Settings::default(),
+            Err(err) => return Err(err.into()),
+        };
+
+        let mut all_addons = Vec::new();
+        for entry in fs::read_dir(&addons_dir)? {
+            let entry = entry?;
+            if entry.file_type()?.is_dir() {
+                all_addons.push(entry.file_name().to_string_lossy().into_owned());
+            }
+        }
+
+        all_addons.sort();
+
+        // cueki's settings may still reference addons that have since been deleted
+        let enabled_addons = settings
+            .addon_selections
+            .into_iter()
+            .filter(|name| all_addons.contains(name))
+            .collect();
+
+        let backup_dir = dir.join("backup");
+        let backup_dir = is_dir(&backup_dir).then_some(backup_dir);
+
+        Ok(Some(Self {
+            root: dir.to_path_buf(),
+            addons_dir,
+            enabled_addons,
+            all_addons,
+            backup_dir,
+        }))
+    }
+
+    /// Migrates cueki's addon selections into `config`, so the imported addons are enabled & ordered the same way they
+    /// were in cueki's preloader. Any addon which wasn't enabled in cueki's preloader is disabled.
+    pub(crate) fn migrate_addon_selections(&self, config: &mut Config) {
+        for name in &self.all_addons {
+            config.addons.insert(
+                name.clone(),
+                AddonConfig {
+                    enabled: false,
+                    ..AddonConfig::default()
+                },
+            );
+        }
+
+        for (order, name) in self.enabled_addons.iter().enumerate() {
+            config.addons.insert(
+                name.clone(),
+                AddonConfig {
+                    enabled: true,
+                    order,
+                    ..AddonConfig::default()
+                },
+            );
+        }
+    }
+
+    /// Copies every file in cueki's backup directory back over its patched counterpart in `tf_dir`, returning the
+    /// number of files restored.
+    pub(crate) fn restore_backups(&self, tf_dir: &Utf8PlatformPath) -> Result<usize, Error> {
+        let Some(backup_dir) = &self.backup_dir else {
+            return Ok(0);
+        };
+
+        let backup_dir: &Path = backup_dir.as_ref();
+        let tf_dir: &Path = tf_dir.as_ref();
+
+        let mut restored = 0;
+        for entry in WalkDir::new(backup_dir) {
+            let entry = entry?;
+            if !entry.file_type().is_file() {
+                continue;
+            }
+
+            let relative_path = entry
+                .path()
+                .strip_prefix(backup_dir)
+                .expect("walkdir entries are always prefixed by the root");
+
+            let target: PathBuf = tf_dir.join(relative_path);
+            if let Some(parent) = target.parent() {
+                fs::create_dir_all(parent)?;
+            }
+
+            fs::copy(entry.path(), &target)?;
+            restored += 1;
+        }
+
+        Ok(restored)
+    }
+}
+
+fn is_dir(path: &Utf8PlatformPath) -> bool {
+    fs::metadata(path).is_ok_and(|metadata| metadata.is_dir())
+}
@@ -0,0 +1,726 @@
+//! Downloading addons from the GameBanana & Steam Workshop pages that users paste in. Every request goes through a
+//! [`Transport`], which is only implemented when dazzle is built with the `download` feature, so offline builds don't
+//! include a network stack at all.
+//!
+//! Downloads are written to a `.part` file in the downloads dir first, and only renamed once they're complete & are
+//! known to be VPKs. A download which fails part way is resumed from its `.part` file the next time it's tried.
+
+use std::{
+    fs::{self, File, OpenOptions},
+    io::{self, ErrorKind, Read, Seek, SeekFrom, Write},
+    sync::Arc,
+    task::Poll,
+};
+
+use eframe::egui;
+use serde::Deserialize;
+use thiserror::Error;
+use typed_path::{Utf8PlatformPath, Utf8PlatformPathBuf};
+
+use crate::app::{
+    AddingAddons, App, Crashed, HandleState, ManagingAddons, ManagingAddonsState, Paths, State,
+    addon_manager::AddonState, config::Config, steam::TF2_APP_ID, task::Task,
+};
+
+const VPK_SIGNATURE: u32 = 0x55aa1234;
+
+const PART_EXTENSION: &str = "part";
+
+/// A page which addons can be downloaded from.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub(crate) enum AddonUrl {
+    /// a mod on GameBanana, by its id - e.g. `https://gamebanana.com/mods/123456`
+    GameBanana(u64),
+
+    /// a Steam Workshop item or collection, by its id - e.g.
+    /// `https://steamcommunity.com/sharedfiles/filedetails/?id=123456`
+    Workshop(u64),
+}
+
+impl AddonUrl {
+    /// Parses the URL of a GameBanana mod, or of a Steam Workshop item or collection.
+    ///
+    /// # Errors
+    ///
+    /// Returns [`DownloadError::UnsupportedUrl`] if `url` isn't one of those.
+    pub(crate) fn parse(url: &str) -> Result<Self, DownloadError> {
+        let unsupported = || DownloadError::UnsupportedUrl(url.to_string());
+
+        let trimmed = url.trim().split('#').next().unwrap_or_default();
+        let without_scheme = ["https://", "http://"]
+            .into_iter()
+            .find_map(|scheme| trimmed.strip_prefix(scheme))
+            .unwrap_or(trimmed);
+        let (host, path) = without_scheme.split_once('/').unwrap_or((without_scheme, ""));
+        let host = host.strip_prefix("www.").unwrap_or(host).to_ascii_lowercase();
+        let (path, query) = path.split_once('?').unwrap_or((path, ""));
+        let segments: Vec<_> = path.split('/').filter(|segment| !segment.is_empty()).collect();
+
+        match (host.as_str(), &segments[..]) {
+            ("gamebanana.com", ["mods", id] | ["mods", "download", id]) => {
+                id.parse().map(Self::GameBanana).map_err(|_| unsupported())
+            }
+            ("steamcommunity.com", ["sharedfiles" | "workshop", "filedetails"]) => query
+                .split('&')
+                .find_map(|pair| pair.strip_prefix("id="))
+                .and_then(|id| id.parse().ok())
+                .map(Self::Workshop)
+                .ok_or_else(unsupported),
+            _ => Err(unsupported()),
+        }
+    }
+}
+
+/// A file which can be downloaded, found by [`resolve`].
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub(crate) struct RemoteFile {
+    pub(crate) name: String,
+    pub(crate) url: String,
+
+    /// the size of the file, if the page says
+    pub(crate) size: Option<u64>,
+}
+
+/// The body of a response to a [`Transport::get`].
+pub(crate) struct Response {
+    /// whether the body starts at the requested offset, rather than at the start of the file
+    pub(crate) resumed: bool,
+
+    /// the size of the whole file, if it's known
+    pub(crate) total_size: Option<u64>,
+
+    pub(crate) body: Box<dyn Read + Send>,
+}
+
+/// Sends the requests needed to find & download addons.
+pub(crate) trait Transport: Send + Sync {
+    /// Requests `url`, asking for its body from `offset` bytes on. The server may send the whole body anyway, see
+    /// [`Response::resumed`].
+    fn get(&self, url: &str, offset: u64) -> Result<Response, DownloadError>;
+
+    /// Posts `form` to `url` as a URL-encoded form, returning the body of the response.
+    fn post_form(&self, url: &str, form: &[(String, String)]) -> Result<Vec<u8>, DownloadError>;
+}
+
+#[derive(Debug, Error)]
+pub(crate) enum DownloadError {
+    #[error("'{0}' isn't the URL of a GameBanana mod or a Steam Workshop item")]
+    UnsupportedUrl(String),
+
+    #[error("the request failed: {0}")]
+    Http(String),
+
+    #[error("the response couldn't be read: {0}")]
+    Response(#[from] serde_json::Error),
+
+    #[error("'{0}' can't be downloaded outside of Steam")]
+    NotDownloadable(String),
+
+    #[error("there are no VPKs to download; only VPKs can be added so far, but there are: {}", .0.join(", "))]
+    NoVpks(Vec<String>),
+
+    #[error("'{0}' isn't a valid file name")]
+    InvalidName(String),
+
+    #[error("'{0}' isn't a VPK")]
+    NotAVpk(String),
+
+    #[error(transparent)]
+    Io(#[from] io::Error),
+}
+
+/// Whether dazzle was built with the `download` feature, and so can download addons.
+pub(crate) const IS_AVAILABLE: bool = cfg!(feature = "download");
+
+/// The [`Transport`] addons are downloaded with, or `None` if dazzle was built without the `download` feature.
+pub(crate) fn transport() -> Option<Arc<dyn Transport>> {
+    #[cfg(feature = "download")]
+    {
+        Some(Arc::new(reqwest_transport::ReqwestTransport::new()))
+    }
+
+    #[cfg(not(feature = "download"))]
+    {
+        None
+    }
+}
+
+/// Finds the VPKs which can be downloaded from the page at `url`. A Workshop collection's items are all downloaded.
+///
+/// # Errors
+///
+/// Returns [`DownloadError::NoVpks`] if the page only has other kinds of files, and
+/// [`DownloadError::NotDownloadable`] if a Workshop item can only be downloaded through Steam.
+pub(crate) fn resolve(url: AddonUrl, transport: &dyn Transport) -> Result<Vec<RemoteFile>, DownloadError> {
+    let files = match url {
+        AddonUrl::GameBanana(id) => resolve_gamebanana(id, transport)?,
+        AddonUrl::Workshop(id) => resolve_workshop(id, transport)?,
+    };
+
+    let (vpks, others): (Vec<_>, Vec<_>) = files.into_iter().partition(|file| {
+        Utf8PlatformPath::new(&file.name)
+            .extension()
+            .is_some_and(|extension| extension.eq_ignore_ascii_case("vpk"))
+    });
+
+    if vpks.is_empty() {
+        return Err(DownloadError::NoVpks(
+            others.into_iter().map(|file| file.name).collect(),
+        ));
+    }
+
+    Ok(vpks)
+}
+
+fn resolve_gamebanana(id: u64, transport: &dyn Transport) -> Result<Vec<RemoteFile>, DownloadError> {
+    #[derive(Deserialize)]
+    struct Profile {
+        #[serde(rename = "_aFiles", default)]
+        files: Vec<ProfileFile>,
+    }
+
+    #[derive(Deserialize)]
+    struct ProfileFile {
+        #[serde(rename = "_sFile")]
+        name: String,
+
+        #[serde(rename = "_nFilesize")]
+        size: Option<u64>,
+
+        #[serde(rename = "_sDownloadUrl")]
+        url: String,
+    }
+
+    let url = format!("https://gamebanana.com/apiv11/Mod/{id}?_csvProperties=_aFiles");
+    let mut body = Vec::new();
+    transport.get(&url, 0)?.body.read_to_end(&mut body)?;
+
+    let profile: Profile = serde_json::from_slice(&body)?;
+    Ok(profile
+        .files
+        .into_iter()
+        .map(|file| RemoteFile {
+            name: file.name,
+            url: file.url,
+            size: file.size,
+        })
+        .collect())
+}
+
+fn resolve_workshop(id: u64, transport: &dyn Transport) -> Result<Vec<RemoteFile>, DownloadError> {
+    #[derive(Deserialize)]
+    struct ApiResponse<T> {
+        response: T,
+    }
+
+    #[derive(Deserialize)]
+    struct Collections {
+        #[serde(rename = "collectiondetails", default)]
+        collections: Vec<Collection>,
+    }
+
+    #[derive(Deserialize)]
+    struct Collection {
+        #[serde(default)]
+        children: Vec<Child>,
+    }
+
+    #[derive(Deserialize)]
+    struct Child {
+        #[serde(rename = "publishedfileid")]
+        id: String,
+    }
+
+    #[derive(Deserialize)]
+    struct Items {
+        #[serde(rename = "publishedfiledetails", default)]
+        items: Vec<Item>,
+    }
+
+    #[derive(Deserialize)]
+    struct Item {
+        #[serde(rename = "publishedfileid")]
+        id: String,
+
+        #[serde(default)]
+        title: String,
+
+        #[serde(default)]
+        filename: String,
+
+        #[serde(default)]
+        file_url: String,
+
+        #[serde(default)]
+        file_size: Option<String>,
+
+        #[serde(default)]
+        consumer_app_id: u32,
+    }
+
+    const API: &str = "https://api.steampowered.com/ISteamRemoteStorage";
+
+    // an item which isn't a collection has no children
+    let form = [
+        ("collectioncount".to_string(), "1".to_string()),
+        ("publishedfileids[0]".to_string(), id.to_string()),
+    ];
+    let body = transport.post_form(&format!("{API}/GetCollectionDetails/v1/"), &form)?;
+    let collections: ApiResponse<Collections> = serde_json::from_slice(&body)?;
+    let mut ids: Vec<_> = collections
+        .response
+        .collections
+        .into_iter()
+        .flat_map(|collection| collection.children)
+        .map(|child| child.id)
+        .collect();
+    if ids.is_empty() {
+        ids.push(id.to_string());
+    }
+
+    let mut form = vec![("itemcount".to_string(), ids.len().to_string())];
+    form.extend(
+        ids.into_iter()
+            .enumerate()
+            .map(|(idx, id)| (format!("publishedfileids[{idx}]"), id)),
+    );
+    let body = transport.post_form(&format!("{API}/GetPublishedFileDetails/v1/"), &form)?;
+    let items: ApiResponse<Items> = serde_json::from_slice(&body)?;
+
+    items
+        .response
+        .items
+        .into_iter()
+        .map(|item| {
+            // items for other games, and TF2 items which are only ever downloaded by Steam itself, have no URL
+            if item.consumer_app_id != TF2_APP_ID || item.file_url.is_empty() {
+                let name = if item.title.is_empty() { item.id } else { item.title };
+                return Err(DownloadError::NotDownloadable(name));
+            }
+
+            let name = item.filename.rsplit(['/', '\\']).next().unwrap_or_default().to_string();
+            Ok(RemoteFile {
+                name,
+                url: item.file_url,
+                size: item.file_size.and_then(|size| size.parse().ok()),
+            })
+        })
+        .collect()
+}
+
+/// Downloads `file` into `downloads_dir`, resuming a download of it which was left unfinished. `progress` is called
+/// with the number of bytes downloaded so far, and the size of the file if it's known.
+///
+/// Returns the path of the downloaded file.
+///
+/// # Errors
+///
+/// Returns [`DownloadError::NotAVpk`] if the downloaded file isn't a VPK, in which case it's removed.
+pub(crate) fn download(
+    file: &RemoteFile,
+    transport: &dyn Transport,
+    downloads_dir: &Utf8PlatformPath,
+    mut progress: impl FnMut(u64, Option<u64>),
+) -> Result<Utf8PlatformPathBuf, DownloadError> {
+    let name = file.name.as_str();
+    if name.is_empty() || name == "." || name == ".." || name.contains(['/', '\\']) {
+        return Err(DownloadError::InvalidName(file.name.clone()));
+    }
+
+    let part_path = downloads_dir.join(format!("{name}.{PART_EXTENSION}"));
+    let mut part = OpenOptions::new()
+        .create(true)
+        .append(true)
+        .read(true)
+        .open(&part_path)?;
+    let offset = part.metadata()?.len();
+
+    let mut response = transport.get(&file.url, offset)?;
+    let mut downloaded = if response.resumed {
+        offset
+    } else {
+        part.set_len(0)?;
+        0
+    };
+
+    let total_size = response.total_size.or(file.size);
+    progress(downloaded, total_size);
+
+    let mut buffer = vec![0; 64 * 1024];
+    loop {
+        let read = match response.body.read(&mut buffer) {
+            Ok(0) => break,
+            Ok(read) => read,
+            Err(err) if err.kind() == ErrorKind::Interrupted => continue,
+            Err(err) => return Err(err.into()),
+        };
+
+        part.write_all(&buffer[..read])?;
+        downloaded += read as u64;
+        progress(downloaded, total_size);
+    }
+
+    part.sync_all()?;
+    if !is_vpk(&mut part)? {
+        drop(part);
+        fs::remove_file(&part_path)?;
+        return Err(DownloadError::NotAVpk(file.name.clone()));
+    }
+
+    drop(part);
+    let path = downloads_dir.join(name);
+    fs::rename(&part_path, &path)?;
+    Ok(path)
+}
+
+fn is_vpk(file: &mut File) -> io::Result<bool> {
+    let mut signature = [0; 4];
+    file.seek(SeekFrom::Start(0))?;
+    match file.read_exact(&mut signature) {
+        Ok(()) => Ok(u32::from_le_bytes(signature) == VPK_SIGNATURE),
+        Err(err) if err.kind() == ErrorKind::UnexpectedEof => Ok(false),
+        Err(err) => Err(err),
+    }
+}
+
+/// Removes the finished downloads in `downloads_dir`, which have already been added. Unfinished downloads are kept, so
+/// that they can be resumed.
+fn remove_finished_downloads(downloads_dir: &Utf8PlatformPath) -> io::Result<()> {
+    for entry in fs::read_dir(downloads_dir)? {
+        let entry = entry?;
+        let path = entry.path();
+        let is_part = path.extension().is_some_and(|extension| extension == PART_EXTENSION);
+        if entry.file_type()?.is_file() && !is_part {
+            fs::remove_file(path)?;
+        }
+    }
+
+    Ok(())
+}
+
+/// The downloaded files, and a description of every URL which couldn't be downloaded.
+pub(crate) type DownloadJob = Task<(Vec<Utf8PlatformPathBuf>, Vec<String>)>;
+
+/// Downloads the addons on the pages at `urls`, so they can be added like any other addon. The user can cancel between
+/// files, in which case only the files which finished downloading are added.
+pub(crate) fn start_addon_download(
+    ctx: &egui::Context,
+    paths: &Paths,
+    transport: Arc<dyn Transport>,
+    urls: Vec<String>,
+) -> DownloadJob {
+    let downloads_dir = paths.data.join("downloads");
+    Task::spawn_with_spinner(ctx, move |state| {
+        let mut files = Vec::new();
+        let mut errors = Vec::new();
+
+        if let Err(err) = fs::create_dir_all(&downloads_dir).and_then(|()| remove_finished_downloads(&downloads_dir)) {
+            errors.push(format!("The downloads folder couldn't be prepared: {err}"));
+            return (files, errors);
+        }
+
+        'urls: for url in urls {
+            if state.is_cancelled() {
+                break;
+            }
+
+            state.push_status(format!("Finding the addons at {url}"));
+            let remote_files = match AddonUrl::parse(&url).and_then(|addon_url| resolve(addon_url, &*transport)) {
+                Ok(remote_files) => remote_files,
+                Err(err) => {
+                    errors.push(format!("{url}: {err}"));
+                    continue;
+                }
+            };
+
+            for remote_file in remote_files {
+                if state.is_cancelled() {
+                    break 'urls;
+                }
+
+                state.begin_stage(
+                    format!("Downloading {}", remote_file.name),
+                    0,
+                    remote_file.size.unwrap_or(0),
+                );
+
+                let mut reported = 0;
+                let result = download(&remote_file, &*transport, &downloads_dir, |downloaded, _| {
+                    state.advance_stage(0, downloaded.saturating_sub(reported));
+                    reported = downloaded;
+                });
+
+                match result {
+                    Ok(path) => files.push(path),
+                    Err(err) => errors.push(format!("{url}: {err}")),
+                }
+            }
+        }
+
+        (files, errors)
+    })
+    .cancellable()
+}
+
+#[derive(Debug)]
+pub(crate) struct Downloading {
+    config: Config,
+    addons: Vec<AddonState>,
+    task: DownloadJob,
+}
+
+impl Downloading {
+    pub fn new(
+        config: Config,
+        addons: Vec<AddonState>,
+        transport: Arc<dyn Transport>,
+        urls: Vec<String>,
+        ctx: &egui::Context,
+        app: &App,
+    ) -> Self {
+        let task = start_addon_download(ctx, &app.paths, transport, urls);
+
+        Self { config, addons, task }
+    }
+}
+
+impl HandleState for Downloading {
+    fn handle(mut self, ui: &mut egui::Ui, app: &mut App) -> State {
+        self.task.show("downloading addons", ui.ctx());
+        let Poll::Ready(result) = self.task.poll() else {
+            return self.into();
+        };
+
+        let Ok((files, errors)) = result else {
+            return Crashed::new("downloading addons").into();
+        };
+
+        if errors.is_empty() {
+            return if files.is_empty() {
+                ManagingAddons::new(self.config, self.addons).into()
+            } else {
+                AddingAddons::new(self.config, self.addons, files, ui.ctx(), app).into()
+            };
+        }
+
+        // whatever was downloaded is added once the user has read why the rest wasn't
+        app.pending_addons.extend(files);
+        ManagingAddons {
+            state: ManagingAddonsState::ShowingMessage(format!(
+                "Some addons couldn't be downloaded:\n\n{}",
+                errors.join("\n")
+            )),
+            ..ManagingAddons::new(self.config, self.addons)
+        }
+        .into()
+    }
+}
+
+#[cfg(feature = "download")]
+mod reqwest_transport {
+    use reqwest::{
+        StatusCode,
+        blocking::Client,
+        header::{CONTENT_RANGE, RANGE},
+    };
+
+    use super::{DownloadError, Response, Transport};
+
+    pub(crate) struct ReqwestTransport {
+        client: Client,
+    }
+
+    impl ReqwestTransport {
+        pub(crate) fn new() -> Self {
+            Self { client: Client::new() }
+        }
+    }
+
+    impl Transport for ReqwestTransport {
+        fn get(&self, url: &str, offset: u64) -> Result<Response, DownloadError> {
+            let mut request = self.client.get(url);
+            if offset > 0 {
+                request = request.header(RANGE, format!("bytes={offset}-"));
+            }
+
+            let response = request.send().and_then(reqwest::blocking::Response::error_for_status);
+            let response = response.map_err(|err| DownloadError::Http(err.to_string()))?;
+
+            // e.g. `bytes 100-999/1000`
+            let resumed = offset > 0 && response.status() == StatusCode::PARTIAL_CONTENT;
+            let total_size = if resumed {
+                response
+                    .headers()
+                    .get(CONTENT_RANGE)
+                    .and_then(|range| range.to_str().ok())
+                    .and_then(|range| range.rsplit_once('/'))
+                    .and_then(|(_, total)| total.parse().ok())
+            } else {
+                response.content_length()
+            };
+
+            Ok(Response {
+                resumed,
+                total_size,
+                body: Box::new(response),
+            })
+        }
+
+        fn post_form(&self, url: &str, form: &[(String, String)]) -> Result<Vec<u8>, DownloadError> {
+            self.client
+                .post(url)
+                .form(form)
+                .send()
+                .and_then(reqwest::blocking::Response::error_for_status)
+                .and_then(reqwest::blocking::Response::bytes)
+                .map(|body| body.to_vec())
+                .map_err(|err| DownloadError::Http(err.to_string()))
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::{
+        env, fs,
+        io::{Cursor, Read},
+        process,
+    };
+
+    use typed_path::Utf8PlatformPathBuf;
+
+    use super::{AddonUrl, DownloadError, RemoteFile, Response, Transport, download};
+
+    /// Serves `body` for every request, honouring ranges if `supports_ranges` is set.
+    struct FakeTransport {
+        body: Vec<u8>,
+        supports_ranges: bool,
+    }
+
+    impl Transport for FakeTransport {
+        fn get(&self, _url: &str, offset: u64) -> Result<Response, DownloadError> {
+            let resumed = self.supports_ranges && offset > 0;
+            let start = if resumed { offset as usize } else { 0 };
+            Ok(Response {
+                resumed,
+                total_size: Some(self.body.len() as u64),
+                body: Box::new(Cursor::new(self.body[start..].to_vec())),
+            })
+        }
+
+        fn post_form(&self, _url: &str, _form: &[(String, String)]) -> Result<Vec<u8>, DownloadError> {
+            Ok(self.body.clone())
+        }
+    }
+
+    fn downloads_dir(name: &str) -> Utf8PlatformPathBuf {
+        let path = Utf8PlatformPathBuf::from(env::temp_dir().to_str().unwrap())
+            .join(format!("dazzle-download-{name}-{}", process::id()));
+        let _ = fs::remove_dir_all(&path);
+        fs::create_dir_all(&path).unwrap();
+        path
+    }
+
+    fn vpk_bytes() -> Vec<u8> {
+        let mut bytes = 0x55aa1234u32.to_le_bytes().to_vec();
+        bytes.extend(1..=200u8);
+        bytes
+    }
+
+    fn remote_file() -> RemoteFile {
+        RemoteFile {
+            name: "hats.vpk".to_string(),
+            url: "https://example.com/hats.vpk".to_string(),
+            size: None,
+        }
+    }
+
+    #[test]
+    fn parses_supported_urls() {
+        assert_eq!(
+            AddonUrl::parse("https://gamebanana.com/mods/123456").unwrap(),
+            AddonUrl::GameBanana(123456)
+        );
+        assert_eq!(
+            AddonUrl::parse(" gamebanana.com/mods/download/42#FileInfo ").unwrap(),
+            AddonUrl::GameBanana(42)
+        );
+        assert_eq!(
+            AddonUrl::parse("http://www.GameBanana.com/mods/download/42").unwrap(),
+            AddonUrl::GameBanana(42)
+        );
+        assert_eq!(
+            AddonUrl::parse("https://steamcommunity.com/sharedfiles/filedetails/?id=789&searchtext=").unwrap(),
+            AddonUrl::Workshop(789)
+        );
+        assert_eq!(
+            AddonUrl::parse("https://steamcommunity.com/workshop/filedetails/?id=5").unwrap(),
+            AddonUrl::Workshop(5)
+        );
+
+        for url in [
+            "https://gamebanana.com/wips/1",
+            "https://example.com/mods/1",
+            "steamcommunity.com/id/someone",
+        ] {
+            assert!(
+                matches!(AddonUrl::parse(url), Err(DownloadError::UnsupportedUrl(_))),
+                "{url}"
+            );
+        }
+    }
+
+    #[test]
+    fn resumes_unfinished_downloads() {
+        let dir = downloads_dir("resume");
+        let body = vpk_bytes();
+        fs::write(dir.join("hats.vpk.part"), &body[..100]).unwrap();
+
+        let transport = FakeTransport {
+            body: body.clone(),
+            supports_ranges: true,
+        };
+
+        let mut first_progress = None;
+        let path = download(&remote_file(), &transport, &dir, |downloaded, _| {
+            first_progress.get_or_insert(downloaded);
+        })
+        .unwrap();
+
+        assert_eq!(first_progress, Some(100));
+        assert_eq!(fs::read(&path).unwrap(), body);
+        assert!(!fs::exists(dir.join("hats.vpk.part")).unwrap());
+    }
+
+    #[test]
+    fn restarts_downloads_which_cant_be_resumed() {
+        let dir = downloads_dir("restart");
+        let body = vpk_bytes();
+        fs::write(dir.join("hats.vpk.part"), b"stale").unwrap();
+
+        let transport = FakeTransport {
+            body: body.clone(),
+            supports_ranges: false,
+        };
+
+        let path = download(&remote_file(), &transport, &dir, |_, _| {}).unwrap();
+        let mut contents = Vec::new();
+        fs::File::open(path).unwrap().read_to_end(&mut contents).unwrap();
+        assert_eq!(contents, body);
+    }
+
+    #[test]
+    fn rejects_files_which_arent_vpks() {
+        let dir = downloads_dir("reject");
+        let transport = FakeTransport {
+            body: b"PK\x03\x04 not a vpk".to_vec(),
+            supports_ranges: true,
+        };
+
+        assert!(matches!(
+            download(&remote_file(), &transport, &dir, |_, _| {}),
+            Err(DownloadError::NotAVpk(_))
+        ));
+        assert!(fs::read_dir(&dir).unwrap().next().is_none());
+    }
+}
@@ -1,24 +1,38 @@
-use std::{fs, process::Command};
+use std::{
+    fs,
+    io::{self, ErrorKind},
+    process::Command,
+};
 
 use typed_path::Utf8PlatformPath;
 
-pub(crate) fn open_file_explorer(path: &Utf8PlatformPath) {
+/// Opens `path` in the system's file explorer, or the directory containing it if it's a file.
+///
+/// # Errors
+///
+/// Returns [`Err`] if `path` doesn't exist, isn't a directory or file, or the file explorer couldn't be started.
+pub(crate) fn open_file_explorer(path: &Utf8PlatformPath) -> io::Result<()> {
     #[cfg(target_os = "windows")]
-    fn open(path: &str) {
-        Command::new("explorer").arg(path).spawn().unwrap();
+    fn open(path: &str) -> io::Result<()> {
+        Command::new("explorer").arg(path).spawn().map(drop)
     }
 
     #[cfg(target_os = "linux")]
-    fn open(path: &str) {
-        Command::new("xdg-open").arg(path).spawn().unwrap();
+    fn open(path: &str) -> io::Result<()> {
+        Command::new("xdg-open").arg(path).spawn().map(drop)
     }
 
-    let metadata = fs::metadata(path).unwrap();
+    let metadata = fs::metadata(path)?;
     if metadata.is_dir() {
-        open(path.as_str());
-    } else if metadata.is_file() {
-        open(path.parent().unwrap().as_str());
+        open(path.as_str())
+    } else if let Some(parent) = path.parent()
+        && metadata.is_file()
+    {
+        open(parent.as_str())
     } else {
-        panic!("the path provided is not a directory or file, will not open");
+        Err(io::Error::new(
+            ErrorKind::InvalidInput,
+            format!("'{path}' is not a directory or file, so it can't be opened"),
+        ))
     }
 }
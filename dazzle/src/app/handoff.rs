@@ -0,0 +1,234 @@
+//! Lets a second dazzle instance hand its addon paths over to the instance that's already running, e.g. when the user
+//! opens a VPK with dazzle while it's already open.
+//!
+//! The running instance listens on a loopback TCP port, which it records in `{data_dir}/instance.port` along with a
+//! random token. The second instance connects to that port, writes [`HANDOFF_HEADER`], the token, and then one absolute
+//! path per line, then exits.
+//!
+//! Anything on the machine can connect to a loopback port, so a handoff is only accepted with the token. The port file
+//! is only readable by the user who is running dazzle, so only their own processes can hand off addons.
+
+use std::{
+    fmt, fs,
+    io::{self, ErrorKind, Read, Write},
+    net::{Ipv4Addr, Shutdown, SocketAddr, TcpListener, TcpStream},
+    sync::{Arc, OnceLock, mpsc},
+    thread,
+    time::{Duration, Instant},
+};
+
+use eframe::egui;
+use single_instance::SingleInstance;
+use typed_path::{Utf8PlatformPath, Utf8PlatformPathBuf};
+use uuid::Uuid;
+
+const PORT_FILE_NAME: &str = "instance.port";
+const HANDOFF_HEADER: &str = "dazzle-handoff 2";
+
+/// How long a handoff may take, both to connect and to send the whole request.
+const TIMEOUT: Duration = Duration::from_secs(5);
+
+/// The most a handoff may send. This is plenty for thousands of paths, and stops a misbehaving client from making the
+/// running instance buffer an unbounded request.
+const MAX_HANDOFF_BYTES: u64 = 1024 * 1024;
+
+/// Owns the single instance lock, and receives the addon paths handed off by other instances.
+pub(crate) struct Handoff {
+    _instance: SingleInstance,
+    ctx: Arc<OnceLock<egui::Context>>,
+    receiver: mpsc::Receiver<Vec<Utf8PlatformPathBuf>>,
+}
+
+impl fmt::Debug for Handoff {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("Handoff").finish_non_exhaustive()
+    }
+}
+
+impl Handoff {
+    /// Starts listening for handoffs from other instances in the background. If the listener can't be started, dazzle
+    /// still runs, but other instances will fail to hand off to it.
+    pub(crate) fn listen(instance: SingleInstance, data_dir: &Utf8PlatformPath) -> Self {
+        let ctx = Arc::new(OnceLock::new());
+        let (sender, receiver) = mpsc::channel();
+
+        match bind(data_dir) {
+            Ok((listener, token)) => {
+                let ctx = ctx.clone();
+                thread::spawn(move || accept_handoffs(&listener, &token.into(), &sender, &ctx));
+            }
+            Err(err) => eprintln!("couldn't listen for handoffs from other instances: {err}"),
+        }
+
+        Self {
+            _instance: instance,
+            ctx,
+            receiver,
+        }
+    }
+
+    /// Gives the listener a context to repaint when a handoff arrives, so it's handled without waiting for input.
+    pub(crate) fn set_context(&self, ctx: &egui::Context) {
+        _ = self.ctx.set(ctx.clone());
+    }
+
+    /// Returns every path handed off since the last call, or [`None`] if there were no handoffs. A handoff may contain
+    /// no paths, if the other instance was launched without any.
+    pub(crate) fn try_recv(&self) -> Option<Vec<Utf8PlatformPathBuf>> {
+        self.receiver.try_iter().reduce(|mut all, paths| {
+            all.extend(paths);
+            all
+        })
+    }
+}
+
+/// Sends `paths` to the instance that's already running.
+///
+/// # Errors
+///
+/// Returns [`Err`] if the running instance's port & token couldn't be read, or it couldn't be reached.
+pub(crate) fn forward(data_dir: &Utf8PlatformPath, paths: &[Utf8PlatformPathBuf]) -> io::Result<()> {
+    let port_file = fs::read_to_string(data_dir.join(PORT_FILE_NAME))?;
+    let invalid = || io::Error::new(io::ErrorKind::InvalidData, "the instance's port file is malformed");
+    let (port, token) = port_file.trim().split_once('\n').ok_or_else(invalid)?;
+    let port: u16 = port.trim().parse().map_err(|_| invalid())?;
+
+    let mut stream = TcpStream::connect_timeout(&SocketAddr::from((Ipv4Addr::LOCALHOST, port)), TIMEOUT)?;
+    stream.set_write_timeout(Some(TIMEOUT))?;
+
+    writeln!(stream, "{HANDOFF_HEADER}")?;
+    writeln!(stream, "{}", token.trim())?;
+    for path in paths {
+        // the running instance almost certainly has a different working directory
+        writeln!(stream, "{}", path.absolutize()?)?;
+    }
+
+    stream.shutdown(Shutdown::Write)
+}
+
+/// Listens on a loopback port, and records it in the port file with a new token. Returns the listener and the token.
+fn bind(data_dir: &Utf8PlatformPath) -> io::Result<(TcpListener, String)> {
+    let listener = TcpListener::bind((Ipv4Addr::LOCALHOST, 0))?;
+    let port = listener.local_addr()?.port();
+    let token = new_token();
+    write_private(&data_dir.join(PORT_FILE_NAME), &format!("{port}\n{token}\n"))?;
+
+    Ok((listener, token))
+}
+
+/// A random 128 bit token, in hex. A v4 UUID's random bits come from the OS's random source, so the token can't be
+/// predicted by other processes.
+fn new_token() -> String {
+    Uuid::new_v4().simple().to_string()
+}
+
+/// Writes `contents` to `path`, only letting the current user read it. On Windows, the data dir is already only
+/// accessible to the user who owns it.
+fn write_private(path: &Utf8PlatformPath, contents: &str) -> io::Result<()> {
+    let mut options = fs::OpenOptions::new();
+    options.write(true).create(true).truncate(true);
+
+    #[cfg(unix)]
+    {
+        use std::os::unix::fs::{OpenOptionsExt, PermissionsExt};
+
+        options.mode(0o600);
+        let mut file = options.open(path)?;
+
+        // the mode only applies to new files, so a port file left by an older version is restricted too
+        file.set_permissions(fs::Permissions::from_mode(0o600))?;
+        file.write_all(contents.as_bytes())
+    }
+
+    #[cfg(not(unix))]
+    {
+        options.open(path)?.write_all(contents.as_bytes())
+    }
+}
+
+/// Compares the token a client sent to the expected one, in time which doesn't depend on where they first differ.
+fn token_matches(sent: &str, expected: &str) -> bool {
+    sent.len() == expected.len()
+        && sent
+            .bytes()
+            .zip(expected.bytes())
+            .fold(0, |difference, (a, b)| difference | (a ^ b))
+            == 0
+}
+
+/// Reads a whole handoff, or returns [`None`] if it couldn't be read within [`TIMEOUT`] or is larger than
+/// [`MAX_HANDOFF_BYTES`]. A handoff is never partially accepted, since a truncated path could name the wrong addon.
+fn read_request(mut stream: TcpStream) -> Option<String> {
+    let deadline = Instant::now() + TIMEOUT;
+    let mut request = Vec::new();
+    let mut buffer = [0; 8 * 1024];
+    loop {
+        // each read only waits until the deadline, so a client can't hold the connection open by trickling bytes
+        let remaining = deadline.saturating_duration_since(Instant::now());
+        if remaining.is_zero() {
+            return None;
+        }
+
+        stream.set_read_timeout(Some(remaining)).ok()?;
+        match stream.read(&mut buffer) {
+            Ok(0) => break,
+            Ok(read) => {
+                request.extend_from_slice(&buffer[..read]);
+                if request.len() as u64 > MAX_HANDOFF_BYTES {
+                    return None;
+                }
+            }
+            Err(err) if err.kind() == ErrorKind::Interrupted => {}
+            Err(_) => return None,
+        }
+    }
+
+    String::from_utf8(request).ok()
+}
+
+fn accept_handoffs(
+    listener: &TcpListener,
+    token: &Arc<str>,
+    sender: &mpsc::Sender<Vec<Utf8PlatformPathBuf>>,
+    ctx: &Arc<OnceLock<egui::Context>>,
+) {
+    for stream in listener.incoming() {
+        let Ok(stream) = stream else {
+            continue;
+        };
+
+        // each connection is handled on its own thread, so a slow client can't hold up anyone else's handoff
+        let (token, sender, ctx) = (token.clone(), sender.clone(), ctx.clone());
+        thread::spawn(move || handle_handoff(stream, &token, &sender, &ctx));
+    }
+}
+
+fn handle_handoff(
+    stream: TcpStream,
+    token: &str,
+    sender: &mpsc::Sender<Vec<Utf8PlatformPathBuf>>,
+    ctx: &OnceLock<egui::Context>,
+) {
+    // anything else on the machine can connect to the port, so we ignore connections which aren't a handoff from a
+    // process that could read the token
+    let Some(request) = read_request(stream) else {
+        return;
+    };
+
+    let mut lines = request.lines();
+    if lines.next() != Some(HANDOFF_HEADER) || !lines.next().is_some_and(|sent| token_matches(sent, token)) {
+        return;
+    }
+
+    let paths = lines
+        .filter(|line| !line.is_empty())
+        .map(Utf8PlatformPathBuf::from)
+        .collect();
+
+    // if sending fails, the app has shut down and there's nothing to repaint
+    if sender.send(paths).is_ok()
+        && let Some(ctx) = ctx.get()
+    {
+        ctx.request_repaint();
+    }
+}
@@ -0,0 +1,429 @@
+//! Runs the [`HeadlessCommand`]s, which inspect & install addons without the GUI so scripts can drive dazzle. Progress
+//! is printed to stderr, and the result is printed to stdout, as JSON if asked for.
+//!
+//! [`pack`] is here too, since it's run without the GUI, though it packages a mod folder rather than managing addons.
+
+use std::{fmt::Write as _, fs, task::Poll, thread, time::Duration};
+
+use addon::{
+    Addon, Source,
+    packaging::{self, PackOptions},
+};
+use eframe::egui;
+use pcf::Pcf;
+use serde::Serialize;
+use typed_path::{Utf8PlatformPath, Utf8PlatformPathBuf};
+use walkdir::WalkDir;
+
+use crate::{
+    app::{
+        self, BuildError,
+        addon_manager::{self, AddonState},
+        config::Config,
+        initial_load,
+        metrics::{self, InstallMetrics},
+        provenance::{self, Conflict, Manifest},
+        steam,
+        strip_stage::StripStage,
+        task::Task,
+        tf_dir_picker, tf_process, vanilla,
+    },
+    cli::{ExitStatus, HeadlessCommand},
+};
+
+#[derive(Debug, Serialize)]
+struct AddonReport {
+    name: String,
+    enabled: bool,
+
+    /// the addon's priority, or `None` if the user has never chosen one
+    order: Option<usize>,
+
+    /// the size of the addon's content, in bytes
+    size: u64,
+}
+
+#[derive(Debug, Serialize)]
+struct ConflictReport {
+    #[serde(flatten)]
+    conflict: Conflict,
+
+    /// whether any of the conflicting addons has never been given a priority
+    unresolved: bool,
+}
+
+#[derive(Debug, Serialize)]
+struct AddonsReport {
+    addons: Vec<AddonReport>,
+    conflicts: Vec<ConflictReport>,
+}
+
+#[derive(Debug, Serialize)]
+struct StatusReport {
+    tf_dir: String,
+
+    /// why the configured tf/ directory isn't valid, if it isn't
+    tf_dir_error: Option<String>,
+    game_running: bool,
+
+    /// the manifest of the current install, if there is one
+    installed: Option<Manifest>,
+}
+
+#[derive(Debug, Serialize)]
+struct Report<T> {
+    exit_code: i32,
+    error: Option<String>,
+
+    #[serde(flatten)]
+    result: Option<T>,
+}
+
+/// Runs `command`, printing its result as JSON if `json` is set.
+pub(crate) fn run(command: HeadlessCommand, json: bool) -> ExitStatus {
+    let (paths, config, _instance) = match app::headless_environment() {
+        Ok(environment) => environment,
+        Err(err) => {
+            let status = match err {
+                BuildError::MultipleInstances => ExitStatus::AlreadyRunning,
+                _ => ExitStatus::Failed,
+            };
+
+            return finish::<()>(json, status, Err(err.to_string()), |_| String::new());
+        }
+    };
+
+    match command {
+        HeadlessCommand::List => {
+            let (status, result) = match load_addons(&paths, &config) {
+                Ok(addons) => addons_report(&config, &addons).map_or_else(
+                    |err| (ExitStatus::Failed, Err(err.to_string())),
+                    |report| (ExitStatus::Success, Ok(report)),
+                ),
+                Err(err) => (ExitStatus::Failed, Err(err)),
+            };
+
+            finish(json, status, result, describe_addons)
+        }
+        HeadlessCommand::Status => {
+            let tf_dir_error = tf_dir_picker::validate(&config.tf_dir).err();
+            let installed = if tf_dir_error.is_none() {
+                provenance::read_installed(&config.tf_dir.join("custom"), &config.vpk_name)
+            } else {
+                Ok(None)
+            };
+
+            let (status, result) = match installed {
+                Ok(installed) => {
+                    let report = StatusReport {
+                        tf_dir: config.tf_dir.to_string(),
+                        tf_dir_error: tf_dir_error.map(|err| err.to_string()),
+                        game_running: tf_process::is_tf2_running(),
+                        installed,
+                    };
+
+                    (ExitStatus::Success, Ok(report))
+                }
+                Err(err) => (
+                    ExitStatus::Failed,
+                    Err(format!("the installed manifest couldn't be read: {err}")),
+                ),
+            };
+
+            finish(json, status, result, describe_status)
+        }
+        HeadlessCommand::Install => {
+            let (status, result) = install(&paths, &config);
+            finish(json, status, result, describe_addons)
+        }
+    }
+}
+
+/// Packages the mod `folder` into a VPK at `output`, or next to `folder` if there isn't one.
+pub(crate) fn pack(folder: &Utf8PlatformPath, output: Option<Utf8PlatformPathBuf>, strip_defaults: bool) -> ExitStatus {
+    const KB: u64 = 1024;
+
+    let output = output.unwrap_or_else(|| {
+        let name = folder.file_name().unwrap_or("addon");
+        folder.with_file_name(format!("{name}.vpk"))
+    });
+
+    let strip_pcf: &dyn Fn(Pcf) -> Pcf = &|pcf| StripStage::Defaults.apply(pcf);
+    let options = PackOptions {
+        strip_pcf: strip_defaults.then_some(strip_pcf),
+    };
+
+    let report = match packaging::pack_folder(folder, &output, &options) {
+        Ok(report) => report,
+        Err(err) => {
+            eprintln!("error: {:#}", anyhow::Error::from(err));
+            return ExitStatus::Failed;
+        }
+    };
+
+    for (path, reason) in &report.skipped {
+        eprintln!("skipped {path}: {reason}");
+    }
+
+    println!(
+        "packed {} files ({} KB) into {output}",
+        report.packed_files,
+        report.packed_bytes.div_ceil(KB)
+    );
+
+    if strip_defaults {
+        println!("stripping default values saved {} KB", report.stripped_bytes / KB);
+    }
+
+    ExitStatus::Success
+}
+
+fn install(paths: &app::Paths, config: &Config) -> (ExitStatus, Result<AddonsReport, String>) {
+    if let Err(err) = tf_dir_picker::validate(&config.tf_dir) {
+        return (
+            ExitStatus::InvalidTfDir,
+            Err(format!("the configured tf/ directory isn't valid: {err}")),
+        );
+    }
+
+    if tf_process::is_tf2_running() {
+        return (
+            ExitStatus::GameRunning,
+            Err("TF2 is running, close it before installing".to_string()),
+        );
+    }
+
+    if let Err(err) = vanilla::check(&config.tf_dir) {
+        return (
+            ExitStatus::Failed,
+            Err(format!(
+                "TF2's original particles can't be restored, because {err}. Verify TF2's game files with Steam \
+                 (steam://validate/{}), then try again",
+                steam::TF2_APP_ID
+            )),
+        );
+    }
+
+    let addons = match load_addons(paths, config) {
+        Ok(addons) => addons,
+        Err(err) => return (ExitStatus::Failed, Err(err)),
+    };
+
+    let report = match addons_report(config, &addons) {
+        Ok(report) => report,
+        Err(err) => return (ExitStatus::Failed, Err(err.to_string())),
+    };
+
+    if report.conflicts.iter().any(|conflict| conflict.unresolved) {
+        return (
+            ExitStatus::ConflictsUnresolved,
+            Err("some addons conflict, but haven't been given a priority yet. Order them in dazzle first".to_string()),
+        );
+    }
+
+    let mut task = addon_manager::start_addon_install(&egui::Context::default(), paths, config, addons);
+    let result = wait_for(&mut task);
+    if config.record_metrics {
+        let install_report = match &result {
+            Ok(Ok((_, install_report))) => Some(install_report),
+            _ => None,
+        };
+        metrics::record_install(
+            &paths.data,
+            InstallMetrics::new(task.view().stage_clock(), install_report),
+        );
+    }
+
+    match result {
+        Ok(Ok((_, install_report))) => {
+            // the install itself succeeded, so a report which can't be written is only worth mentioning
+            if let Err(err) = install_report.write(&paths.data) {
+                eprintln!("The install report couldn't be written: {err:#}");
+            }
+
+            (ExitStatus::Success, Ok(report))
+        }
+        Ok(Err(err)) => (ExitStatus::Failed, Err(format!("{err:#}"))),
+        Err(err) => (ExitStatus::Failed, Err(err)),
+    }
+}
+
+/// Loads every addon in the addons directory, with its state from `config`.
+fn load_addons(paths: &app::Paths, config: &Config) -> Result<Vec<AddonState>, String> {
+    let mut task = initial_load::start_initial_load(&egui::Context::default(), paths);
+    match wait_for(&mut task)? {
+        Ok((addons, problems)) => {
+            for problem in problems {
+                eprintln!("'{}' couldn't be loaded: {}", problem.path, problem.description());
+            }
+
+            Ok(app::addon_states(config, addons))
+        }
+        Err(err) => Err(format!("the addons couldn't be loaded, since {err}")),
+    }
+}
+
+/// Prints `task`'s statuses to stderr until it's finished.
+fn wait_for<T>(task: &mut Task<T>) -> Result<T, String> {
+    loop {
+        let result = task.poll();
+        for status in task.view().status_receiver.try_iter() {
+            eprintln!("{status}");
+        }
+
+        if let Poll::Ready(result) = result {
+            return result.map_err(|_| "dazzle crashed, see the crash log for details".to_string());
+        }
+
+        thread::sleep(Duration::from_millis(100));
+    }
+}
+
+fn addons_report(config: &Config, addons: &[AddonState]) -> anyhow::Result<AddonsReport> {
+    let addon_reports = addons
+        .iter()
+        .map(|state| AddonReport {
+            name: state.addon.name().to_string(),
+            enabled: state.enabled,
+            order: config.addons.get(state.addon.name()).map(|addon| addon.order),
+            size: content_size(&state.addon),
+        })
+        .collect();
+
+    // N.B. like installing, higher priority addons come last so they override the others
+    let enabled_addons: Vec<_> = addons
+        .iter()
+        .rev()
+        .filter(|state| state.enabled)
+        .map(|state| &state.addon)
+        .collect();
+
+    let conflicts = provenance::find_conflicts(&enabled_addons)?
+        .into_iter()
+        .map(|conflict| ConflictReport {
+            unresolved: conflict
+                .overridden
+                .iter()
+                .chain([&conflict.winner])
+                .any(|name| !config.addons.contains_key(name)),
+            conflict,
+        })
+        .collect();
+
+    Ok(AddonsReport {
+        addons: addon_reports,
+        conflicts,
+    })
+}
+
+fn content_size(addon: &Addon) -> u64 {
+    // an addon which hasn't been extracted yet is as large as its VPK's content
+    if !addon.extracted {
+        return Source::Vpk(addon.source_path.clone())
+            .content_size()
+            .unwrap_or_default();
+    }
+
+    WalkDir::new(&addon.content_path)
+        .into_iter()
+        .flatten()
+        .filter_map(|entry| entry.metadata().ok())
+        .filter(fs::Metadata::is_file)
+        .map(|metadata| metadata.len())
+        .sum()
+}
+
+/// Prints `result`, either as JSON or via `describe`, and returns `status`.
+fn finish<T: Serialize>(
+    json: bool,
+    status: ExitStatus,
+    result: Result<T, String>,
+    describe: impl FnOnce(&T) -> String,
+) -> ExitStatus {
+    if json {
+        let (result, error) = match result {
+            Ok(result) => (Some(result), None),
+            Err(err) => (None, Some(err)),
+        };
+
+        let report = Report {
+            exit_code: status.code(),
+            error,
+            result,
+        };
+
+        println!("{}", serde_json::to_string_pretty(&report).unwrap());
+    } else {
+        match result {
+            Ok(result) => print!("{}", describe(&result)),
+            Err(err) => eprintln!("error: {err}"),
+        }
+    }
+
+    status
+}
+
+fn describe_addons(report: &AddonsReport) -> String {
+    const MB: u64 = 1024 * 1024;
+
+    let mut description = String::new();
+    for addon in &report.addons {
+        let enabled = if addon.enabled { "enabled" } else { "disabled" };
+        writeln!(
+            description,
+            "{} ({enabled}, {} MB)",
+            addon.name,
+            addon.size.div_ceil(MB)
+        )
+        .unwrap();
+    }
+
+    if !report.conflicts.is_empty() {
+        writeln!(description, "\n{} conflicting files:", report.conflicts.len()).unwrap();
+        for conflict in &report.conflicts {
+            let unresolved = if conflict.unresolved { " (unresolved)" } else { "" };
+            writeln!(
+                description,
+                "  {}: {} overrides {}{unresolved}",
+                conflict.conflict.path,
+                conflict.conflict.winner,
+                conflict.conflict.overridden.join(", ")
+            )
+            .unwrap();
+        }
+    }
+
+    description
+}
+
+fn describe_status(report: &StatusReport) -> String {
+    let mut description = format!("tf/ directory: {}\n", report.tf_dir);
+    if let Some(err) = &report.tf_dir_error {
+        writeln!(description, "  not valid: {err}").unwrap();
+    }
+
+    writeln!(
+        description,
+        "TF2 running: {}",
+        if report.game_running { "yes" } else { "no" }
+    )
+    .unwrap();
+
+    match &report.installed {
+        Some(manifest) => {
+            writeln!(
+                description,
+                "installed {} addons with dazzle {}:",
+                manifest.addons.len(),
+                manifest.dazzle_version
+            )
+            .unwrap();
+            for addon in &manifest.addons {
+                writeln!(description, "  {}", addon.name).unwrap();
+            }
+        }
+        None => writeln!(description, "no addons are installed").unwrap(),
+    }
+
+    description
+}
@@ -1,21 +1,38 @@
-use std::thread::{self, JoinHandle};
+use std::{
+    num::NonZero,
+    sync::{mpmc, mpsc},
+    thread,
+};
 
 use super::process::ProcessState;
 use eframe::egui;
-use rayon::iter::{IntoParallelIterator, ParallelIterator};
+use rayon::iter::{IntoParallelRefIterator, ParallelIterator};
 use thiserror::Error;
 
-use crate::app::{Paths, process::ProcessView};
-use addon::{self, Addon, ExtractionError, Sources};
+use crate::{
+    app::{Paths, SYMBOL_POOL, load_problems::LoadProblem, task::Task},
+    particles_manifest,
+};
+use addon::{self, Addon, ExtractionError, ExtractionStrategy, Source, Sources};
+
+/// The most addons loaded at once. Each worker holds an addon's decoded PCFs while it's parsing them, so this bounds
+/// the peak memory of a load as well as the number of threads.
+const MAX_WORKERS: usize = 4;
 
 struct InitialLoader {
     paths: Paths,
 }
 
+/// Why a single addon couldn't be loaded. It's reported as a [`LoadProblem`], and doesn't stop the other addons from
+/// loading.
 #[derive(Debug, Error)]
 pub(crate) enum LoadError {
+    /// the addons folder itself couldn't be listed, so none of the addons in it could be loaded
+    #[error("the addons folder couldn't be read")]
+    AddonsDir(#[source] addon::Error),
+
     #[error(transparent)]
-    Sources(#[from] addon::Error),
+    Source(#[from] addon::Error),
 
     #[error(transparent)]
     Extraction(#[from] ExtractionError),
@@ -24,23 +41,38 @@ pub(crate) enum LoadError {
     Parse(#[from] addon::ParseError),
 }
 
+/// Why the initial load failed as a whole. Every addon is patched over the vanilla particles, so without them there's
+/// nothing to load the addons for.
+#[derive(Debug, Error)]
+pub(crate) enum InitialLoadError {
+    #[error("the vanilla particle '{name}' bundled with dazzle couldn't be decoded")]
+    Vanilla {
+        name: &'static str,
+
+        #[source]
+        source: pcf::DecodeError,
+    },
+}
+
 // A LoadOperation is an operation which processes some state and has a UI presentation to reflect the current state
 // of the operation. Like
 // - loading/processing setup files
 // - handling new addons when theyre imported
 // - installing addons to tf2
 
-pub type InitialLoadJob = JoinHandle<Result<Vec<Addon>, LoadError>>;
-
-pub(crate) fn start_initial_load(ctx: &egui::Context, paths: &Paths) -> (ProcessView, InitialLoadJob) {
-    let loader = InitialLoader { paths: paths.clone() };
+/// The addons which loaded, and the problem with each one which didn't.
+pub(crate) type LoadedAddons = (Vec<Addon>, Vec<LoadProblem>);
 
-    let (load_state, load_view) =
-        ProcessState::with_progress_bar(ctx, InitialLoader::operation_steps().try_into().unwrap());
+pub type InitialLoadJob = Task<Result<LoadedAddons, InitialLoadError>>;
 
-    let handle = thread::spawn(move || -> Result<Vec<Addon>, LoadError> { loader.run(&load_state) });
+pub(crate) fn start_initial_load(ctx: &egui::Context, paths: &Paths) -> InitialLoadJob {
+    let loader = InitialLoader { paths: paths.clone() };
 
-    (load_view, handle)
+    Task::spawn_with_progress_bar(
+        ctx,
+        InitialLoader::operation_steps().try_into().unwrap(),
+        move |state| loader.run(&state),
+    )
 }
 
 impl InitialLoader {
@@ -48,37 +80,134 @@ impl InitialLoader {
         90
     }
 
-    fn run(&self, load_operation: &ProcessState) -> Result<Vec<Addon>, LoadError> {
+    fn run(&self, load_operation: &ProcessState) -> Result<LoadedAddons, InitialLoadError> {
+        load_operation.push_status("Checking the vanilla particles...");
+        Self::check_vanilla_particles()?;
+        load_operation.add_progress(15);
+
         load_operation.push_status("Loading addons...");
-        let sources = Sources::read_dir(&self.paths.addons)?;
-        load_operation.add_progress(30);
-
-        if !sources.failures.is_empty() {
-            // TODO: we should present information about addons that failed to load to the user
-            eprintln!("There were some errors reading some or all addon sources:");
-            for (path, error) in sources.failures {
-                eprintln!("  {path}: {error}");
+        let (sources, mut problems) = match Sources::read_dir(&self.paths.addons) {
+            Ok(sources) => {
+                let problems = sources
+                    .failures
+                    .into_iter()
+                    .map(|(path, error)| LoadProblem::new(path, error))
+                    .collect();
+
+                (sources.sources.into_vec(), problems)
             }
+            Err(err) => (
+                Vec::new(),
+                vec![LoadProblem::new(self.paths.addons.clone(), LoadError::AddonsDir(err))],
+            ),
+        };
+        load_operation.add_progress(15);
+
+        let source_count = sources.len();
+        let workers = thread::available_parallelism()
+            .map_or(1, NonZero::get)
+            .min(MAX_WORKERS)
+            .min(source_count)
+            .max(1);
+
+        load_operation.begin_stage("Loading addons", source_count, 0);
+
+        let (source_sender, source_receiver) = mpmc::channel();
+        for source in sources.into_iter().enumerate() {
+            source_sender.send(source).unwrap();
         }
+        drop(source_sender);
+
+        // N.B. the channel is bounded so that workers wait for the collector, rather than piling up loaded addons
+        let (addon_sender, addon_receiver) = mpsc::sync_channel(workers);
+        let addons = thread::scope(|scope| {
+            for _ in 0..workers {
+                let source_receiver = source_receiver.clone();
+                let addon_sender = addon_sender.clone();
+                scope.spawn(move || {
+                    while let Ok((idx, source)) = source_receiver.recv() {
+                        let addon = self.load(load_operation, source);
+
+                        // the collector has gone away, so there's no point loading the rest
+                        if addon_sender.send((idx, addon)).is_err() {
+                            break;
+                        }
+                    }
+                });
+            }
 
-        let extracted_addons: Result<Vec<_>, _> = sources
-            .sources
-            .into_par_iter()
-            .map(|source| {
-                load_operation.push_status(format!("Extracting addon {}", source.name().unwrap_or_default()));
-                source.extract_as_subfolder_in(&self.paths.extracted_content)
-            })
+            drop(addon_sender);
+            Self::collect(load_operation, addon_receiver, source_count)
+        });
+
+        let addons = addons
+            .into_iter()
+            .filter_map(|addon| addon.map_err(|problem| problems.push(problem)).ok())
             .collect();
-        load_operation.add_progress(30);
 
-        let mut addons = Vec::new();
-        for addon in extracted_addons? {
-            load_operation.push_status(format!("Parsing contents of {}", addon.name().unwrap_or_default()));
-            addons.push(addon.parse_content()?);
-        }
-        load_operation.add_progress(30);
+        load_operation.add_progress(60);
         load_operation.push_status("Done!");
 
-        Ok(addons)
+        Ok((addons, problems))
+    }
+
+    /// Ensures that every vanilla particle bundled with dazzle decodes, since installing & uninstalling depend on them.
+    fn check_vanilla_particles() -> Result<(), InitialLoadError> {
+        particles_manifest::PARTICLES_BYTES
+            .par_iter()
+            .try_for_each(|&(name, data)| match pcf::decode(&mut &data[..]) {
+                Ok(_) => Ok(()),
+                Err(source) => Err(InitialLoadError::Vanilla { name, source }),
+            })
+    }
+
+    /// Parses the addon from `source`. A VPK is read without extracting it, which is left until the addon is installed,
+    /// while a folder is copied into the extracted content first.
+    fn load(&self, load_operation: &ProcessState, source: Source) -> Result<Addon, LoadProblem> {
+        let mut addon = match source {
+            Source::Vpk(source_path) => {
+                load_operation.push_status(format!("Reading addon {}", source_path.file_name().unwrap_or_default()));
+                Addon::parse_from_vpk(&source_path, &self.paths.extracted_content)
+                    .map_err(|err| LoadProblem::new(source_path, err))?
+            }
+            source @ Source::Folder(_) => {
+                load_operation.push_status(format!("Extracting addon {}", source.name().unwrap_or_default()));
+                let addon =
+                    match source.extract_as_subfolder_in(&self.paths.extracted_content, ExtractionStrategy::Error) {
+                        Ok(addon) => addon,
+                        Err(err) => return Err(LoadProblem::new(source.into_inner(), err)),
+                    };
+
+                load_operation.push_status(format!("Parsing contents of {}", addon.name().unwrap_or_default()));
+                let source_path = addon.source_path().to_owned();
+                addon
+                    .parse_content()
+                    .map_err(|err| LoadProblem::new(source_path, err))?
+            }
+        };
+
+        // a missing thumbnail isn't worth failing the load over
+        if let Err(err) = addon.cache_thumbnail(&self.paths.thumbnails) {
+            eprintln!("Couldn't cache the thumbnail of '{}': {err}", addon.name());
+        }
+
+        addon.intern_symbols(&SYMBOL_POOL);
+        Ok(addon)
+    }
+
+    /// Receives `count` loaded addons from the workers, in the order of their sources. An addon which couldn't be
+    /// loaded doesn't stop the others.
+    fn collect(
+        load_operation: &ProcessState,
+        addons: mpsc::Receiver<(usize, Result<Addon, LoadProblem>)>,
+        count: usize,
+    ) -> Vec<Result<Addon, LoadProblem>> {
+        let mut loaded: Vec<Option<Result<Addon, LoadProblem>>> = (0..count).map(|_| None).collect();
+        for (idx, addon) in addons {
+            loaded[idx] = Some(addon);
+            load_operation.advance_stage(1, 0);
+        }
+
+        loaded.into_iter().flatten().collect()
     }
 }
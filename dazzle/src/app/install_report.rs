@@ -0,0 +1,238 @@
+//! The report written after every install, describing what was installed and anything the user should know about it.
+//! It's written to the data dir as [`REPORT_TEXT`] for people & [`REPORT_JSON`] for tools, and shown once the install
+//! finishes.
+
+use std::{fmt::Write as _, fs};
+
+use eframe::egui::{self, Id, Modal, ScrollArea, Sides};
+use pcfpack::BinUsage;
+use serde::{Deserialize, Serialize};
+use typed_path::{Utf8PlatformPath, Utf8PlatformPathBuf};
+
+use crate::app::{
+    App, HandleState, ManagingAddons, State, addon_manager::AddonState, config::Config, file_explorer,
+    strip_stage::StripStage,
+};
+
+/// The name of the human-readable report, in the data dir.
+pub(crate) const REPORT_TEXT: &str = "last_install.txt";
+
+/// The name of the JSON report, in the data dir.
+pub(crate) const REPORT_JSON: &str = "last_install.json";
+
+/// The tallest the report gets in the summary before it scrolls.
+const MAX_REPORT_HEIGHT: f32 = 400.0;
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub(crate) struct InstallReport {
+    pub dazzle_version: String,
+
+    /// every enabled addon, highest priority first
+    pub addons: Vec<String>,
+
+    /// how the particles were stripped to fit into the vanilla particle budget
+    pub particle_stripping: String,
+
+    /// every particle bin in `tf2_misc_dir.vpk`, and how much of it is used
+    pub bins: Vec<BinReport>,
+
+    /// every game file which was patched in place, rather than installed through `tf/custom/`
+    pub patched_files: Vec<String>,
+
+    /// the number of addon files whose contents were identical to another's, and so were only packed once
+    pub deduplicated_files: usize,
+
+    /// the bytes saved by packing the duplicate files only once
+    pub deduplicated_bytes: u64,
+
+    /// the size of the enabled addons' content
+    #[serde(default)]
+    pub content_bytes: u64,
+
+    /// the size of the files packed into the VPK, whether they were written or reused
+    #[serde(default)]
+    pub packed_bytes: u64,
+
+    /// anything that went wrong without stopping the install
+    pub warnings: Vec<String>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub(crate) struct BinReport {
+    pub name: String,
+    pub capacity: u64,
+    pub used: u64,
+}
+
+impl From<BinUsage> for BinReport {
+    fn from(usage: BinUsage) -> Self {
+        Self {
+            name: usage.name,
+            capacity: usage.capacity,
+            used: usage.used,
+        }
+    }
+}
+
+impl BinReport {
+    /// How full the bin is, as a percentage.
+    #[allow(clippy::cast_precision_loss)]
+    pub(crate) fn utilization(&self) -> f64 {
+        if self.capacity == 0 {
+            100.0
+        } else {
+            self.used as f64 / self.capacity as f64 * 100.0
+        }
+    }
+}
+
+impl InstallReport {
+    pub(crate) fn new(addons: &[&AddonState], strip_stage: StripStage) -> Self {
+        Self {
+            dazzle_version: env!("CARGO_PKG_VERSION").to_string(),
+            addons: addons
+                .iter()
+                .rev()
+                .map(|addon_state| addon_state.addon.name().to_string())
+                .collect(),
+            particle_stripping: strip_stage.to_string(),
+            bins: Vec::new(),
+            patched_files: Vec::new(),
+            deduplicated_files: 0,
+            deduplicated_bytes: 0,
+            warnings: Vec::new(),
+        }
+    }
+
+    /// The report as plain text, as it's written to [`REPORT_TEXT`].
+    pub(crate) fn text(&self) -> String {
+        let mut text = format!("dazzle {} install report\n", self.dazzle_version);
+
+        write!(
+            text,
+            "\nAddons installed ({}), highest priority first:\n",
+            self.addons.len()
+        )
+        .unwrap();
+        for addon in &self.addons {
+            writeln!(text, "  - {addon}").unwrap();
+        }
+
+        write!(text, "\nParticle stripping: {}\n", self.particle_stripping).unwrap();
+
+        text += "\nParticle bins:\n";
+        for bin in &self.bins {
+            writeln!(
+                text,
+                "  - {}: {} / {} KB ({:.1}%)",
+                bin.name,
+                bin.used.div_ceil(1024),
+                bin.capacity.div_ceil(1024),
+                bin.utilization()
+            )
+            .unwrap();
+        }
+
+        text += "\nPatched files:\n";
+        for file in &self.patched_files {
+            writeln!(text, "  - {file}").unwrap();
+        }
+
+        write!(
+            text,
+            "\nDuplicate addon files: {} ({} KB saved by packing them once)\n",
+            self.deduplicated_files,
+            self.deduplicated_bytes.div_ceil(1024)
+        )
+        .unwrap();
+
+        if self.warnings.is_empty() {
+            text += "\nNo warnings.\n";
+        } else {
+            text += "\nWarnings:\n";
+            for warning in &self.warnings {
+                writeln!(text, "  - {warning}").unwrap();
+            }
+        }
+
+        text
+    }
+
+    /// Writes the report to [`REPORT_TEXT`] & [`REPORT_JSON`] in `data_dir`, replacing the previous install's.
+    pub(crate) fn write(&self, data_dir: &Utf8PlatformPath) -> anyhow::Result<()> {
+        fs::write(data_dir.join(REPORT_TEXT), self.text())?;
+        fs::write(data_dir.join(REPORT_JSON), serde_json::to_string_pretty(self)?)?;
+        Ok(())
+    }
+}
+
+/// The install finished, and the user is reading its report.
+#[derive(Debug)]
+pub(crate) struct InstallSummary {
+    config: Config,
+    addons: Vec<AddonState>,
+    text: String,
+
+    /// the written [`REPORT_TEXT`], or `None` if it couldn't be written
+    report_path: Option<Utf8PlatformPathBuf>,
+}
+
+impl InstallSummary {
+    pub fn new(
+        config: Config,
+        addons: Vec<AddonState>,
+        report: &InstallReport,
+        report_path: Option<Utf8PlatformPathBuf>,
+    ) -> Self {
+        Self {
+            config,
+            addons,
+            text: report.text(),
+            report_path,
+        }
+    }
+}
+
+impl HandleState for InstallSummary {
+    fn handle(self, ui: &mut egui::Ui, _app: &mut App) -> State {
+        let mut done = false;
+        Modal::new(Id::new("Install Summary")).show(ui.ctx(), |ui| {
+            ui.set_width(500.0);
+            ui.heading("Your addons were installed");
+            ui.add_space(16.0);
+            ScrollArea::vertical().max_height(MAX_REPORT_HEIGHT).show(ui, |ui| {
+                ui.monospace(&self.text);
+            });
+            ui.add_space(8.0);
+            if let Some(report_path) = &self.report_path {
+                ui.label("This report was saved to:");
+                ui.code(report_path.as_str());
+            } else {
+                ui.label("The report couldn't be saved.");
+            }
+            ui.add_space(16.0);
+            Sides::new().show(
+                ui,
+                |_ui| {},
+                |ui| {
+                    if ui.button("Done").clicked() {
+                        done = true;
+                    }
+
+                    if let Some(report_path) = &self.report_path
+                        && ui.button("Open report folder").clicked()
+                        && let Err(err) = file_explorer::open_file_explorer(report_path)
+                    {
+                        eprintln!("couldn't open the install report's folder: {err}");
+                    }
+                },
+            );
+        });
+
+        if done {
+            ManagingAddons::new(self.config, self.addons).into()
+        } else {
+            self.into()
+        }
+    }
+}
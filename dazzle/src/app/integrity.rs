@@ -0,0 +1,296 @@
+//! A background check that an install is still intact. TF2 updates & Steam's "verify integrity of game files" both
+//! quietly undo parts of an install, and addons can change after they're installed, so the install is scanned once the
+//! addons are loaded, after every install, and then every [`SCAN_INTERVAL`]. Problems are shown as a notification which
+//! doesn't block the addon manager, with the option to repair them by installing again.
+
+use std::{
+    collections::BTreeMap,
+    fmt, fs,
+    io::{self, Read},
+    task::Poll,
+    time::{Duration, Instant},
+};
+
+use eframe::egui::{self, Align2, Window};
+use md5::{Digest, Md5};
+use typed_path::{Utf8PlatformPath, Utf8PlatformPathBuf};
+use vpk::VPK;
+
+use crate::app::{
+    addon_manager::AddonState,
+    provenance::{self, Manifest},
+    task::Task,
+    vanilla_source::TF2_VPK_NAME,
+};
+
+/// How long after a scan finishes the next one starts.
+pub(crate) const SCAN_INTERVAL: Duration = Duration::from_mins(30);
+
+/// The archive index of VPK entries whose data is in the directory VPK itself.
+const DIR_ARCHIVE_INDEX: u16 = 0x7fff;
+
+/// Something wrong with an install, which can be repaired by installing again.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub(crate) enum Issue {
+    /// gameinfo.txt was replaced, probably by a TF2 update, so the game won't load the installed addons
+    GameInfoReverted,
+
+    /// the particle's entry in `tf2_misc_dir.vpk` was moved or removed, probably by a TF2 update
+    ParticleMoved(String),
+
+    /// the patched particle's contents changed, probably because the game's files were verified
+    ParticleChanged(String),
+
+    /// an archive of the installed VPK is missing, or is too short for its entries
+    ArchiveDamaged(Utf8PlatformPathBuf),
+
+    /// the installed addon is no longer in the addons folder
+    AddonMissing(String),
+
+    /// the installed addon's files have changed since it was installed
+    AddonChanged(String),
+}
+
+impl fmt::Display for Issue {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Issue::GameInfoReverted => f.write_str("gameinfo.txt was reset, so TF2 won't load your addons"),
+            Issue::ParticleMoved(name) => write!(f, "tf2_misc_dir.vpk was updated, so {name} isn't patched anymore"),
+            Issue::ParticleChanged(name) => write!(f, "the installed {name} was overwritten"),
+            Issue::ArchiveDamaged(path) => write!(f, "'{path}' is missing or damaged"),
+            Issue::AddonMissing(name) => write!(f, "'{name}' is installed, but isn't in your addons anymore"),
+            Issue::AddonChanged(name) => write!(f, "'{name}' has changed since it was installed"),
+        }
+    }
+}
+
+/// What the user chose to do about the issues in the notification.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub(crate) enum NotificationAction {
+    Repair,
+    Dismiss,
+}
+
+pub(crate) type IntegrityScanJob = Task<anyhow::Result<Vec<Issue>>>;
+
+/// Schedules integrity scans, and holds the issues found by the last one.
+#[derive(Debug, Default)]
+pub(crate) struct IntegrityScanner {
+    job: Option<IntegrityScanJob>,
+
+    /// when the last scan finished, or `None` if the next scan should start right away
+    last_scan: Option<Instant>,
+
+    /// the issues found by the last scan, which are cleared once the user dismisses them
+    issues: Vec<Issue>,
+}
+
+impl IntegrityScanner {
+    /// Makes the next [`IntegrityScanner::poll`] start a scan, e.g. because the install just changed.
+    pub(crate) fn invalidate(&mut self) {
+        self.last_scan = None;
+        self.issues.clear();
+    }
+
+    /// Collects the result of a finished scan, and starts a new one if it's due. `addons` are compared against the
+    /// addons installed into the VPK named `vpk_name`.
+    pub(crate) fn poll(
+        &mut self,
+        ctx: &egui::Context,
+        tf_dir: &Utf8PlatformPath,
+        vpk_name: &str,
+        addons: &[AddonState],
+    ) {
+        if let Some(job) = &mut self.job
+            && let Poll::Ready(result) = job.poll()
+        {
+            self.job = None;
+            self.last_scan = Some(Instant::now());
+            match result {
+                Ok(Ok(issues)) => self.issues = issues,
+                Ok(Err(err)) => eprintln!("couldn't check the install's integrity: {err:#}"),
+                Err(_) => eprintln!("the install's integrity check panicked"),
+            }
+        }
+
+        // a running scan wakes the UI itself once it finishes
+        if self.job.is_some() {
+            return;
+        }
+
+        let until_due = self.last_scan.map_or(Duration::ZERO, |last_scan| {
+            SCAN_INTERVAL.saturating_sub(last_scan.elapsed())
+        });
+        if !until_due.is_zero() {
+            ctx.request_repaint_after(until_due);
+            return;
+        }
+
+        let tf_dir = tf_dir.to_path_buf();
+        let vpk_name = vpk_name.to_string();
+        let addons: Vec<_> = addons
+            .iter()
+            .map(|addon_state| {
+                (
+                    addon_state.addon.name().to_string(),
+                    addon_state.addon.source_path.clone(),
+                )
+            })
+            .collect();
+
+        self.job = Some(Task::spawn_with_spinner(ctx, move |_| {
+            scan(&tf_dir, &vpk_name, &addons)
+        }));
+    }
+
+    /// Shows the issues found by the last scan in the corner of the window, if there are any.
+    pub(crate) fn notification(&mut self, ctx: &egui::Context) -> Option<NotificationAction> {
+        if self.issues.is_empty() {
+            return None;
+        }
+
+        let mut action = None;
+        Window::new("⚠ Your install needs repairing")
+            .collapsible(true)
+            .resizable(false)
+            .anchor(Align2::RIGHT_BOTTOM, (-16.0, -16.0))
+            .default_width(360.0)
+            .show(ctx, |ui| {
+                for issue in &self.issues {
+                    ui.label(format!("• {issue}"));
+                }
+
+                ui.add_space(8.0);
+                ui.horizontal(|ui| {
+                    if ui
+                        .button("Repair")
+                        .on_hover_text("installs your addons again, as you've configured them")
+                        .clicked()
+                    {
+                        action = Some(NotificationAction::Repair);
+                    }
+
+                    if ui.button("Dismiss").clicked() {
+                        action = Some(NotificationAction::Dismiss);
+                    }
+                });
+            });
+
+        if action.is_some() {
+            self.issues.clear();
+        }
+
+        action
+    }
+}
+
+/// Checks the install in `tf_dir`, packed into the VPK named `vpk_name`, against its manifest. `addons` are the name &
+/// source path of every addon the user has. Nothing is checked if there's no install, or if it predates the manifest.
+///
+/// # Errors
+///
+/// Returns [`Err`] if the install's files couldn't be read at all.
+pub(crate) fn scan(
+    tf_dir: &Utf8PlatformPath,
+    vpk_name: &str,
+    addons: &[(String, Utf8PlatformPathBuf)],
+) -> anyhow::Result<Vec<Issue>> {
+    let tf_custom_dir = tf_dir.join("custom");
+    let Some(vpk_path) = provenance::find_installed_vpk(&tf_custom_dir, vpk_name) else {
+        return Ok(Vec::new());
+    };
+
+    let vpk = VPK::read(&vpk_path)?;
+    let Some(manifest) = provenance::read_manifest(&vpk)? else {
+        return Ok(Vec::new());
+    };
+
+    let mut issues = Vec::new();
+
+    let gameinfo = fs::read_to_string(tf_dir.join("gameinfo.txt"))?;
+    if gameinfo.contains("type multiplayer_only") {
+        issues.push(Issue::GameInfoReverted);
+    }
+
+    // particles are only recorded when they're patched into tf2_misc_dir.vpk, not when they're loose files
+    if !manifest.patched_particles.is_empty() {
+        check_particles(&manifest, &VPK::read(tf_dir.join(TF2_VPK_NAME))?, &mut issues)?;
+    }
+    check_archives(&vpk_path, &vpk, &mut issues);
+    check_addons(&manifest, addons, &mut issues)?;
+
+    Ok(issues)
+}
+
+/// Checks that every particle in the manifest is still patched into `tf2_misc_vpk`, where it was patched.
+fn check_particles(manifest: &Manifest, tf2_misc_vpk: &VPK, issues: &mut Vec<Issue>) -> anyhow::Result<()> {
+    for patched in &manifest.patched_particles {
+        let Some(entry) = tf2_misc_vpk.tree.get(&patched.name) else {
+            issues.push(Issue::ParticleMoved(patched.name.clone()));
+            continue;
+        };
+
+        if entry.dir_entry.archive_index != patched.archive_index
+            || entry.dir_entry.archive_offset != patched.archive_offset
+            || u64::from(entry.dir_entry.file_length) < patched.size
+        {
+            issues.push(Issue::ParticleMoved(patched.name.clone()));
+            continue;
+        }
+
+        let mut hasher = Md5::new();
+        io::copy(&mut entry.reader()?.take(patched.size), &mut hasher)?;
+        if provenance::hex_digest(hasher) != patched.hash {
+            issues.push(Issue::ParticleChanged(patched.name.clone()));
+        }
+    }
+
+    Ok(())
+}
+
+/// Checks that every numbered archive of the split VPK at `vpk_path` exists, and is long enough for its entries.
+fn check_archives(vpk_path: &Utf8PlatformPath, vpk: &VPK, issues: &mut Vec<Issue>) {
+    let Some(prefix) = vpk_path.file_name().and_then(|name| name.strip_suffix("_dir.vpk")) else {
+        // a single VPK has no archives, and it's already been read
+        return;
+    };
+
+    let mut archive_ends: BTreeMap<u16, u64> = BTreeMap::new();
+    for entry in vpk.tree.values() {
+        let index = entry.dir_entry.archive_index;
+        if index == DIR_ARCHIVE_INDEX {
+            continue;
+        }
+
+        let end = u64::from(entry.dir_entry.archive_offset) + u64::from(entry.dir_entry.file_length);
+        let archive_end = archive_ends.entry(index).or_default();
+        *archive_end = (*archive_end).max(end);
+    }
+
+    for (index, end) in archive_ends {
+        let archive_path = vpk_path.with_file_name(format!("{prefix}_{index:03}.vpk"));
+        if !fs::metadata(&archive_path).is_ok_and(|metadata| metadata.len() >= end) {
+            issues.push(Issue::ArchiveDamaged(archive_path));
+        }
+    }
+}
+
+/// Checks that every installed addon is still in `addons`, unchanged.
+fn check_addons(
+    manifest: &Manifest,
+    addons: &[(String, Utf8PlatformPathBuf)],
+    issues: &mut Vec<Issue>,
+) -> io::Result<()> {
+    for installed in &manifest.addons {
+        let Some((_, source_path)) = addons.iter().find(|(name, _)| *name == installed.name) else {
+            issues.push(Issue::AddonMissing(installed.name.clone()));
+            continue;
+        };
+
+        if provenance::source_hash(source_path)? != installed.hash {
+            issues.push(Issue::AddonChanged(installed.name.clone()));
+        }
+    }
+
+    Ok(())
+}
@@ -0,0 +1,126 @@
+//! Addons which couldn't be loaded, e.g. because they aren't a supported type, couldn't be read, or have a corrupt PCF.
+//! They're listed in a panel below the addon manager until the user retries, removes or dismisses them.
+
+use std::{error::Error, fs, io};
+
+use eframe::egui::{self, CollapsingHeader, RichText, ScrollArea, TopBottomPanel};
+use typed_path::{Utf8PlatformPath, Utf8PlatformPathBuf};
+
+use crate::app::initial_load::LoadError;
+
+/// The tallest the list of problems gets before it scrolls.
+const MAX_LIST_HEIGHT: f32 = 160.0;
+
+#[derive(Debug)]
+pub(crate) struct LoadProblem {
+    /// the addon's file or folder
+    pub path: Utf8PlatformPathBuf,
+    pub error: LoadError,
+}
+
+impl LoadProblem {
+    pub(crate) fn new(path: Utf8PlatformPathBuf, error: impl Into<LoadError>) -> Self {
+        Self {
+            path,
+            error: error.into(),
+        }
+    }
+
+    /// Whether the addon is in `addons_dir`, and so can be removed by dazzle. An addon which couldn't be copied there is
+    /// still wherever the user added it from, so it's left alone.
+    pub(crate) fn is_removable(&self, addons_dir: &Utf8PlatformPath) -> bool {
+        self.path.parent() == Some(addons_dir)
+    }
+
+    /// The error, followed by each of its causes.
+    pub(crate) fn description(&self) -> String {
+        let mut description = self.error.to_string();
+        let mut source = self.error.source();
+        while let Some(error) = source {
+            description += &format!(": {error}");
+            source = error.source();
+        }
+
+        description
+    }
+}
+
+/// What the user chose to do about one of the problems, by its index.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub(crate) enum ProblemAction {
+    Retry(usize),
+    OpenLocation(usize),
+    Remove(usize),
+    Dismiss(usize),
+}
+
+/// Shows `problems` in a panel along the bottom of `ui`. Must be shown before anything else in `ui`. When `read_only`,
+/// problems can't be retried or removed.
+pub(crate) fn problems_panel(
+    ui: &mut egui::Ui,
+    problems: &[LoadProblem],
+    addons_dir: &Utf8PlatformPath,
+    read_only: bool,
+) -> Option<ProblemAction> {
+    if problems.is_empty() {
+        return None;
+    }
+
+    let mut action = None;
+    TopBottomPanel::bottom("load problems").show_inside(ui, |ui| {
+        let heading = match problems.len() {
+            1 => "⚠ 1 addon couldn't be loaded".to_string(),
+            count => format!("⚠ {count} addons couldn't be loaded"),
+        };
+
+        CollapsingHeader::new(RichText::new(heading).strong())
+            .default_open(true)
+            .show(ui, |ui| {
+                ScrollArea::vertical().max_height(MAX_LIST_HEIGHT).show(ui, |ui| {
+                    for (idx, problem) in problems.iter().enumerate() {
+                        ui.horizontal(|ui| {
+                            if ui.add_enabled(!read_only, egui::Button::new("Retry")).clicked() {
+                                action = Some(ProblemAction::Retry(idx));
+                            }
+
+                            if ui
+                                .add_enabled(fs::exists(&problem.path).unwrap_or(false), egui::Button::new("Show"))
+                                .on_hover_text("opens the folder the addon is in")
+                                .clicked()
+                            {
+                                action = Some(ProblemAction::OpenLocation(idx));
+                            }
+
+                            if !read_only && problem.is_removable(addons_dir) && ui.button("Remove").clicked() {
+                                action = Some(ProblemAction::Remove(idx));
+                            }
+
+                            if ui.button("Dismiss").clicked() {
+                                action = Some(ProblemAction::Dismiss(idx));
+                            }
+
+                            ui.strong(problem.path.file_name().unwrap_or(problem.path.as_str()))
+                                .on_hover_text(problem.path.as_str());
+                        });
+                        ui.label(problem.description());
+                        ui.add_space(4.0);
+                    }
+                });
+            });
+    });
+
+    action
+}
+
+/// Deletes the addon file or folder at `path`.
+///
+/// # Errors
+///
+/// Returns [`Err`] if the addon couldn't be deleted.
+pub(crate) fn remove(path: &Utf8PlatformPath) -> io::Result<()> {
+    if fs::metadata(path)?.is_dir() {
+        fs::remove_dir_all(path)
+    } else {
+        fs::remove_file(path)
+    }
+}
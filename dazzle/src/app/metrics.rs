@@ -0,0 +1,228 @@
+//! Usage metrics which are only ever kept on the user's own machine, in [`METRICS_FILE`] in the data dir. Nothing is
+//! sent anywhere; the file is only there so that the user can attach it to a bug report, e.g. about slow installs.
+//! Recording them can be turned off with [`Config::record_metrics`](crate::app::config::Config::record_metrics).
+
+use std::{
+    collections::VecDeque,
+    fs,
+    io::ErrorKind,
+    sync::{Arc, Mutex},
+    time::{Duration, Instant, SystemTime, UNIX_EPOCH},
+};
+
+use serde::{Deserialize, Serialize};
+use typed_path::Utf8PlatformPath;
+
+use crate::app::install_report::InstallReport;
+
+/// The name of the metrics file, in the data dir.
+pub(crate) const METRICS_FILE: &str = "metrics.json";
+
+/// The most installs which are kept in [`Metrics::recent_installs`].
+const MAX_RECENT_INSTALLS: usize = 20;
+
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub(crate) struct Metrics {
+    /// every install which was started, whether or not it succeeded
+    #[serde(default)]
+    pub installs: u64,
+
+    #[serde(default)]
+    pub failed_installs: u64,
+
+    /// the most recent installs, oldest first
+    #[serde(default)]
+    pub recent_installs: VecDeque<InstallMetrics>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub(crate) struct InstallMetrics {
+    pub dazzle_version: String,
+
+    /// when the install finished, in seconds since the unix epoch
+    pub finished_at: u64,
+
+    pub succeeded: bool,
+    pub duration_ms: u64,
+
+    /// every stage of the install which was started, in order
+    pub stages: Vec<StageTiming>,
+
+    /// the number of enabled addons
+    pub addons: usize,
+
+    /// the size of the enabled addons' content
+    pub content_bytes: u64,
+
+    /// the size of the particles patched into the bins
+    pub particle_bytes: u64,
+
+    /// the size of the files packed into the VPK
+    pub packed_bytes: u64,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub(crate) struct StageTiming {
+    pub name: String,
+    pub duration_ms: u64,
+}
+
+/// Times the stages of a process, as they're started by [`ProcessState::begin_stage`]. Clones share the same timings,
+/// so the view can read what the worker thread timed.
+///
+/// [`ProcessState::begin_stage`]: crate::app::process::ProcessState::begin_stage
+#[derive(Debug, Clone)]
+pub(crate) struct StageClock {
+    inner: Arc<Mutex<ClockState>>,
+}
+
+#[derive(Debug)]
+struct ClockState {
+    started: Instant,
+    current: Option<(String, Instant)>,
+    finished: Vec<StageTiming>,
+}
+
+impl StageClock {
+    pub(crate) fn new() -> Self {
+        Self {
+            inner: Arc::new(Mutex::new(ClockState {
+                started: Instant::now(),
+                current: None,
+                finished: Vec::new(),
+            })),
+        }
+    }
+
+    /// Finishes the current stage, if there is one, and starts timing the stage `name`.
+    pub(crate) fn begin(&self, name: String) {
+        let mut state = self.inner.lock().unwrap();
+        let now = Instant::now();
+        if let Some(timing) = state.finish_current(now) {
+            state.finished.push(timing);
+        }
+
+        state.current = Some((name, now));
+    }
+
+    /// Every stage timed so far, with the current stage timed until now, and the time since the clock was created.
+    pub(crate) fn timings(&self) -> (Vec<StageTiming>, Duration) {
+        let state = self.inner.lock().unwrap();
+        let now = Instant::now();
+        let mut timings = state.finished.clone();
+        timings.extend(state.finish_current(now));
+        (timings, now - state.started)
+    }
+}
+
+impl ClockState {
+    fn finish_current(&self, now: Instant) -> Option<StageTiming> {
+        self.current.as_ref().map(|(name, started)| StageTiming {
+            name: name.clone(),
+            duration_ms: as_millis(now - *started),
+        })
+    }
+}
+
+impl InstallMetrics {
+    /// The metrics of an install whose stages were timed by `clock`. `report` is the install's report, or `None` if
+    /// the install failed.
+    pub(crate) fn new(clock: &StageClock, report: Option<&InstallReport>) -> Self {
+        let (stages, duration) = clock.timings();
+        let finished_at = SystemTime::now()
+            .duration_since(UNIX_EPOCH)
+            .map(|duration| duration.as_secs())
+            .unwrap_or_default();
+
+        Self {
+            dazzle_version: env!("CARGO_PKG_VERSION").to_string(),
+            finished_at,
+            succeeded: report.is_some(),
+            duration_ms: as_millis(duration),
+            stages,
+            addons: report.map_or(0, |report| report.addons.len()),
+            content_bytes: report.map_or(0, |report| report.content_bytes),
+            particle_bytes: report.map_or(0, |report| report.bins.iter().map(|bin| bin.used).sum()),
+            packed_bytes: report.map_or(0, |report| report.packed_bytes),
+        }
+    }
+}
+
+impl Metrics {
+    /// Reads the metrics in `data_dir`. Missing metrics are read as empty ones.
+    pub(crate) fn read(data_dir: &Utf8PlatformPath) -> anyhow::Result<Self> {
+        match fs::read_to_string(data_dir.join(METRICS_FILE)) {
+            Ok(metrics) => Ok(serde_json::from_str(&metrics)?),
+            Err(err) if err.kind() == ErrorKind::NotFound => Ok(Self::default()),
+            Err(err) => Err(err.into()),
+        }
+    }
+
+    pub(crate) fn write(&self, data_dir: &Utf8PlatformPath) -> anyhow::Result<()> {
+        fs::write(data_dir.join(METRICS_FILE), serde_json::to_string_pretty(self)?)?;
+        Ok(())
+    }
+
+    /// Counts `install`, and keeps it as one of the [`MAX_RECENT_INSTALLS`] most recent installs.
+    pub(crate) fn record_install(&mut self, install: InstallMetrics) {
+        self.installs += 1;
+        if !install.succeeded {
+            self.failed_installs += 1;
+        }
+
+        if self.recent_installs.len() == MAX_RECENT_INSTALLS {
+            self.recent_installs.pop_front();
+        }
+        self.recent_installs.push_back(install);
+    }
+}
+
+/// Records `install` in the metrics in `data_dir`. Metrics aren't worth interrupting the user for, so they're only
+/// logged if they can't be recorded.
+pub(crate) fn record_install(data_dir: &Utf8PlatformPath, install: InstallMetrics) {
+    let result = Metrics::read(data_dir).and_then(|mut metrics| {
+        metrics.record_install(install);
+        metrics.write(data_dir)
+    });
+
+    if let Err(err) = result {
+        eprintln!("The install's metrics couldn't be recorded: {err:#}");
+    }
+}
+
+fn as_millis(duration: Duration) -> u64 {
+    u64::try_from(duration.as_millis()).unwrap_or(u64::MAX)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{InstallMetrics, MAX_RECENT_INSTALLS, Metrics, StageClock};
+
+    #[test]
+    fn times_every_stage_in_order() {
+        let clock = StageClock::new();
+        clock.begin("Copying addon content".to_string());
+        clock.begin("Packing addons".to_string());
+
+        let (stages, _) = clock.timings();
+        let names: Vec<_> = stages.iter().map(|stage| stage.name.as_str()).collect();
+        assert_eq!(names, ["Copying addon content", "Packing addons"]);
+
+        // the current stage isn't finished by reading the timings
+        clock.begin("Writing gameinfo.txt".to_string());
+        assert_eq!(clock.timings().0.len(), 3);
+    }
+
+    #[test]
+    fn keeps_only_the_most_recent_installs() {
+        let clock = StageClock::new();
+        let mut metrics = Metrics::default();
+        for _ in 0..MAX_RECENT_INSTALLS + 5 {
+            metrics.record_install(InstallMetrics::new(&clock, None));
+        }
+
+        assert_eq!(metrics.installs, MAX_RECENT_INSTALLS as u64 + 5);
+        assert_eq!(metrics.failed_installs, metrics.installs);
+        assert_eq!(metrics.recent_installs.len(), MAX_RECENT_INSTALLS);
+    }
+}
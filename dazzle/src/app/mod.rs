@@ -1,37 +1,96 @@
 mod addon_manager;
+mod budget;
 mod config;
+mod cueki;
+mod download;
 mod file_explorer;
+mod handoff;
+pub(crate) mod headless;
 mod initial_load;
+mod install_report;
+mod integrity;
+mod load_problems;
+mod metrics;
+mod orphans;
+mod particle_merge;
+mod particle_tweaker;
+mod patches;
+mod preview;
 mod process;
+mod profile;
+mod provenance;
+mod search;
+mod setup;
+mod staging;
+mod steam;
+mod strip_stage;
+mod task;
 mod tf_dir_picker;
+mod tf_process;
+mod vanilla;
+mod vanilla_source;
 
-use std::{env, fs, io, mem};
+use std::{env, fs, io, mem, sync::LazyLock, task::Poll, time::Duration};
 
-use addon::Addon;
+use addon::{
+    Addon,
+    removal::{self, Trashed},
+};
 use derive_more::From;
 use directories::ProjectDirs;
-use eframe::egui::{self, CentralPanel, Id, Modal, Sides};
+use eframe::egui::{self, CentralPanel, Id, Modal, Sides, ViewportCommand};
+use pcf::{SymbolPool, stats::StripStats};
 use rfd::FileDialog;
 use single_instance::SingleInstance;
 use thiserror::Error;
 use typed_path::{Utf8PlatformPath, Utf8PlatformPathBuf};
 
 use crate::app::{
-    addon_manager::{Action, AddingAddonsJob, AddonInstallJob, AddonState, AddonUninstallJob, RemovingAddonJob},
+    addon_manager::{
+        Action, AddingAddonsJob, AddonInstallJob, AddonState, AddonUninstallJob, RemovalAction, RemovingAddonJob,
+    },
     config::{Config, Error},
-    initial_load::InitialLoadJob,
-    process::ProcessView,
+    download::Downloading,
+    handoff::Handoff,
+    initial_load::{InitialLoadJob, LoadError},
+    install_report::InstallSummary,
+    integrity::{IntegrityScanner, NotificationAction},
+    load_problems::{LoadProblem, ProblemAction},
+    metrics::InstallMetrics,
+    preview::Previewing,
+    profile::{PROFILE_EXTENSION, Profile},
+    search::AddonSearch,
+    setup::{ChoosingImport, ImportingAddons, SetupSummary, Welcome},
+    vanilla::Restoration,
 };
+use crate::styles::{self, Theme, ThemeMode};
+use particle_tweaker::ParticleTweaker;
 use tf_dir_picker::TfDirPicker;
 
 use super::{APP_INSTANCE_NAME, APP_NAME, APP_ORG, APP_TLD};
 
+/// How long removed addons are kept in the trash before they're deleted for good.
+const TRASH_MAX_AGE: Duration = Duration::from_secs(7 * 24 * 60 * 60);
+
+/// Shared by the PCFs of every loaded addon, which mostly have the same symbols.
+static SYMBOL_POOL: LazyLock<SymbolPool> = LazyLock::new(SymbolPool::new);
+
 #[derive(Debug, Clone)]
 pub(crate) struct Paths {
     pub addons: Utf8PlatformPathBuf,
+    pub patches: Utf8PlatformPathBuf,
     pub extracted_content: Utf8PlatformPathBuf,
     pub working_vpk: Utf8PlatformPathBuf,
     pub config: Utf8PlatformPathBuf,
+
+    /// where each addon's thumbnail is cached, see [`Addon::cache_thumbnail`]
+    pub thumbnails: Utf8PlatformPathBuf,
+
+    /// where the last install's report is written, see [`install_report`]
+    pub data: Utf8PlatformPathBuf,
+
+    /// where removed addons are kept until they're older than [`TRASH_MAX_AGE`], see [`addon::removal`]
+    pub trash: Utf8PlatformPathBuf,
 }
 
 pub trait HandleState {
@@ -51,8 +110,11 @@ impl Launch {
 
 impl HandleState for Launch {
     fn handle(self, ui: &mut egui::Ui, app: &mut App) -> State {
-        if self.config.tf_dir.as_str().is_empty() {
-            ConfiguringTfDir::new(self.config, get_default_platform_tf_dir()).into()
+        if app.read_only {
+            // the setup wizard & tf/ dir picker write the config, and the addons can be inspected without a tf/ dir
+            InitialLoad::new(self.config, ui.ctx(), &app.paths).into()
+        } else if self.config.tf_dir.as_str().is_empty() {
+            Welcome::new(self.config).into()
         } else if tf_dir_picker::validate(&self.config.tf_dir).is_err() {
             let tf_dir = self.config.tf_dir.to_string();
             ConfiguringTfDir::new(self.config, tf_dir).into()
@@ -66,12 +128,26 @@ impl HandleState for Launch {
 pub(crate) struct ConfiguringTfDir {
     config: Config,
     picker: TfDirPicker,
+    first_run: bool,
 }
 
 impl ConfiguringTfDir {
     pub fn new(config: Config, tf_path: String) -> Self {
         let picker = TfDirPicker::new(tf_path);
-        Self { config, picker }
+        Self {
+            config,
+            picker,
+            first_run: false,
+        }
+    }
+
+    /// Creates the tf/ directory step of the setup wizard, which continues on to the rest of the wizard rather than
+    /// straight to [`InitialLoad`].
+    pub fn first_run(config: Config, tf_path: String) -> Self {
+        Self {
+            first_run: true,
+            ..Self::new(config, tf_path)
+        }
     }
 }
 
@@ -89,6 +165,11 @@ impl HandleState for ConfiguringTfDir {
                 ..self.config
             };
 
+            if self.first_run {
+                // the config is written once the user finishes the wizard
+                return ChoosingImport::new(config).into();
+            }
+
             // TODO: present errors to the user as a modal
             config::write_config(&app.paths.config, &config).unwrap();
 
@@ -100,6 +181,7 @@ impl HandleState for ConfiguringTfDir {
                     ..self.config
                 },
                 picker: self.picker,
+                first_run: self.first_run,
             }
             .into()
         }
@@ -109,43 +191,42 @@ impl HandleState for ConfiguringTfDir {
 #[derive(Debug)]
 pub(crate) struct InitialLoad {
     config: Config,
-    view: ProcessView,
-    job: InitialLoadJob,
+    task: InitialLoadJob,
 }
 
 impl InitialLoad {
     pub fn new(config: Config, ctx: &egui::Context, paths: &Paths) -> Self {
-        let (view, job) = initial_load::start_initial_load(ctx, paths);
+        let task = initial_load::start_initial_load(ctx, paths);
 
-        Self { config, view, job }
+        Self { config, task }
     }
 }
 
 impl HandleState for InitialLoad {
-    fn handle(mut self, ui: &mut egui::Ui, _app: &mut App) -> State {
-        self.view.show("vanilla pcf and addon loading", ui.ctx());
+    fn handle(mut self, ui: &mut egui::Ui, app: &mut App) -> State {
+        self.task.show("vanilla pcf and addon loading", ui.ctx());
 
-        if self.job.is_finished() {
-            // TODO: present errors to the user as a modal
-            let addons = self.job.join().unwrap().unwrap();
-            let mut addons: Vec<_> = addons
-                .into_iter()
-                .map(|addon| (self.config.addons.get(addon.name()).copied().unwrap_or_default(), addon))
-                .collect();
-
-            addons.sort_by_key(|(config, _)| config.order);
-
-            let addons = addons
-                .into_iter()
-                .map(|(config, addon)| AddonState {
-                    enabled: config.enabled,
-                    addon,
-                })
-                .collect();
-
-            ManagingAddons::new(self.config, addons).into()
-        } else {
-            self.into()
+        let Poll::Ready(result) = self.task.poll() else {
+            return self.into();
+        };
+
+        let Ok(result) = result else {
+            return Crashed::new("loading addons").into();
+        };
+
+        match result {
+            Ok((addons, problems)) => {
+                app.load_problems = problems;
+                let addons = addon_states(&self.config, addons);
+                ManagingAddons::new(self.config, addons).into()
+            }
+            Err(err) => ManagingAddons {
+                state: ManagingAddonsState::ShowingMessage(format!(
+                    "Your addons couldn't be loaded, since {err}. Try reinstalling dazzle."
+                )),
+                ..ManagingAddons::new(self.config, Vec::new())
+            }
+            .into(),
         }
     }
 }
@@ -156,6 +237,22 @@ enum ManagingAddonsState {
     ConfirmingInstall,
     ConfirmingUninstall,
     ConfirmingDelete(usize),
+    ConfirmingProblemRemoval(usize),
+    SelectingParticles(usize),
+    TweakingParticles(usize),
+    ShowingStripStats(usize, Vec<(String, StripStats)>),
+    EditingAppearance,
+    EnteringUrls(String),
+    ShowingMessage(String),
+    WaitingForGameExit(GameAction),
+    RestoringVanilla(GameAction, Restoration),
+}
+
+/// An action which modifies the user's TF2 installation, and so can't be started while TF2 is running.
+#[derive(Debug, Clone, Copy)]
+enum GameAction {
+    Install,
+    Uninstall,
 }
 
 #[derive(Debug)]
@@ -163,14 +260,18 @@ pub(crate) struct ManagingAddons {
     config: Config,
     addons: Vec<AddonState>,
     state: ManagingAddonsState,
+    tweaker: ParticleTweaker,
+    search: AddonSearch,
 }
 
 impl ManagingAddons {
     pub fn new(config: Config, addons: Vec<AddonState>) -> Self {
         Self {
             config,
+            search: AddonSearch::new(&addons),
             addons,
             state: ManagingAddonsState::Managing,
+            tweaker: ParticleTweaker::default(),
         }
     }
 
@@ -196,20 +297,84 @@ impl ManagingAddons {
         }
     }
 
+    fn handle_entering_urls(self, ui: &mut egui::Ui, app: &mut App, mut urls: String) -> State {
+        let mut download_confirmed = false;
+        let modal = Modal::new(Id::new("Addon Manager URLs")).show(ui.ctx(), |ui| {
+            ui.set_width(500.0);
+            ui.heading("Add addons from the web");
+            ui.add_space(16.0);
+            ui.label("Paste the pages of GameBanana mods or Steam Workshop items & collections, one per line.");
+            ui.add_space(8.0);
+            ui.add(
+                egui::TextEdit::multiline(&mut urls)
+                    .hint_text("https://gamebanana.com/mods/123456")
+                    .desired_width(f32::INFINITY),
+            );
+            ui.add_space(16.0);
+            Sides::new().show(
+                ui,
+                |_ui| {},
+                |ui| {
+                    if ui
+                        .add_enabled(!urls.trim().is_empty(), egui::Button::new("Download"))
+                        .clicked()
+                    {
+                        download_confirmed = true;
+                        ui.close();
+                    }
+
+                    if ui.button("Cancel").clicked() {
+                        ui.close();
+                    }
+                },
+            )
+        });
+
+        if download_confirmed && let Some(transport) = download::transport() {
+            let urls = urls.split_whitespace().map(str::to_string).collect();
+            Downloading::new(self.config, self.addons, transport, urls, ui.ctx(), app).into()
+        } else if modal.should_close() {
+            Self {
+                state: ManagingAddonsState::Managing,
+                ..self
+            }
+            .into()
+        } else {
+            Self {
+                state: ManagingAddonsState::EnteringUrls(urls),
+                ..self
+            }
+            .into()
+        }
+    }
+
     #[allow(clippy::needless_pass_by_value)]
     fn handle_action(self, action: Action, ui: &mut egui::Ui, app: &mut App) -> State {
+        if app.read_only && action.modifies_files() {
+            return self.into();
+        }
+
         match action {
             Action::OpenAddonsFolder => {
-                file_explorer::open_file_explorer(&app.paths.addons);
+                if let Err(err) = file_explorer::open_file_explorer(&app.paths.addons) {
+                    eprintln!("couldn't open the addons folder: {err}");
+                }
                 self.into()
             }
             Action::OpenTfFolder => {
-                file_explorer::open_file_explorer(&self.config.tf_dir);
+                if let Err(err) = file_explorer::open_file_explorer(&self.config.tf_dir) {
+                    eprintln!("couldn't open the tf folder: {err}");
+                }
                 self.into()
             }
             // TODO: after adding the selected addon, refresh all of our other addons to ensure we're up to date
             Action::AddAddonFiles => self.handle_add_addon_files(ui, app),
             Action::AddAddonFolders => self.handle_add_addon_folders(ui, app),
+            Action::AddAddonUrls => Self {
+                state: ManagingAddonsState::EnteringUrls(String::new()),
+                ..self
+            }
+            .into(),
             // TODO: detect if any of the addons have been changed since load, and ask user for confirmation if they have been
             // TODO: show installation confirmation modal, then transition accordingly
             Action::InstallAddons => Self {
@@ -227,37 +392,455 @@ impl ManagingAddons {
                 state: ManagingAddonsState::ConfirmingDelete(delete_idx),
                 ..self
             }
-            .into(),
+            .into(),
+            Action::SelectParticles(addon_idx) => Self {
+                state: ManagingAddonsState::SelectingParticles(addon_idx),
+                ..self
+            }
+            .into(),
+            Action::TweakParticles(addon_idx) => Self {
+                state: ManagingAddonsState::TweakingParticles(addon_idx),
+                tweaker: ParticleTweaker::default(),
+                ..self
+            }
+            .into(),
+            Action::ShowStripStats(addon_idx) => {
+                let stats = addon_manager::strip_stats(&self.addons[addon_idx]);
+                Self {
+                    state: ManagingAddonsState::ShowingStripStats(addon_idx, stats),
+                    ..self
+                }
+                .into()
+            }
+            Action::EditAppearance => Self {
+                state: ManagingAddonsState::EditingAppearance,
+                ..self
+            }
+            .into(),
+            Action::ExportProfile => self.handle_export_profile(),
+            Action::ImportProfile => self.handle_import_profile(),
+            Action::PreviewParticles(addon_idx) => {
+                Previewing::new(self.config, self.addons, addon_idx, ui.ctx(), app).into()
+            }
+            Action::RemovePreview => self.handle_remove_preview(),
+        }
+    }
+
+    fn handle_remove_preview(self) -> State {
+        let message = match preview::remove(&self.config.tf_dir) {
+            Ok(true) => "The particle preview was removed.".to_string(),
+            Ok(false) => "There's no particle preview to remove.".to_string(),
+            Err(err) => format!("The particle preview couldn't be removed: {err}"),
+        };
+
+        Self {
+            state: ManagingAddonsState::ShowingMessage(message),
+            ..self
+        }
+        .into()
+    }
+
+    fn handle_export_profile(self) -> State {
+        let Some(path) = FileDialog::new()
+            .add_filter("Dazzle Profile", &[PROFILE_EXTENSION])
+            .set_file_name(format!("addons.{PROFILE_EXTENSION}"))
+            .save_file()
+        else {
+            return self.into();
+        };
+
+        let path = paths::std_buf_to_typed(path);
+        let result = Profile::from_addons(&self.addons)
+            .map_err(Into::into)
+            .and_then(|profile| profile.write(&path));
+        let message = match result {
+            Ok(()) => format!("Your profile was saved to '{path}'."),
+            Err(err) => format!("Your profile couldn't be saved: {err}"),
+        };
+
+        Self {
+            state: ManagingAddonsState::ShowingMessage(message),
+            ..self
+        }
+        .into()
+    }
+
+    fn handle_import_profile(mut self) -> State {
+        let Some(path) = FileDialog::new()
+            .add_filter("Dazzle Profile", &[PROFILE_EXTENSION])
+            .pick_file()
+        else {
+            return self.into();
+        };
+
+        let profile = match Profile::read(&paths::std_buf_to_typed(path)) {
+            Ok(profile) => profile,
+            Err(err) => {
+                return Self {
+                    state: ManagingAddonsState::ShowingMessage(format!("The profile couldn't be imported: {err}")),
+                    ..self
+                }
+                .into();
+            }
+        };
+
+        let message = match profile.apply(&mut self.addons) {
+            Ok(missing) if missing.is_empty() => {
+                "The profile was imported. Install your addons to apply it.".to_string()
+            }
+            Ok(missing) => {
+                let mut message = String::from(
+                    "The profile was imported, but these addons from it are missing, so you'll need to add them \
+                     yourself:",
+                );
+                for name in &missing {
+                    message += &format!("\n  - {name}");
+                }
+
+                message
+            }
+            Err(err) => format!("Your addons couldn't be read to import the profile: {err}"),
+        };
+
+        Self {
+            state: ManagingAddonsState::ShowingMessage(message),
+            ..self
+        }
+        .into()
+    }
+
+    fn handle_showing_message(self, ui: &mut egui::Ui, message: &str) -> State {
+        let modal = Modal::new(Id::new("Addon Manager Message")).show(ui.ctx(), |ui| {
+            ui.set_width(500.0);
+            ui.strong(message);
+            ui.add_space(16.0);
+            Sides::new().show(
+                ui,
+                |_ui| {},
+                |ui| {
+                    if ui.button("Ok").clicked() {
+                        ui.close();
+                    }
+                },
+            )
+        });
+
+        if modal.should_close() {
+            Self {
+                state: ManagingAddonsState::Managing,
+                ..self
+            }
+            .into()
+        } else {
+            self.into()
+        }
+    }
+
+    fn handle_selecting_particles(mut self, ui: &mut egui::Ui, addon_idx: usize) -> State {
+        let addon_state = &mut self.addons[addon_idx];
+        let modal = Modal::new(Id::new("Addon Particle Selection")).show(ui.ctx(), |ui| {
+            ui.set_width(500.0);
+            ui.heading(format!("{}'s particles", addon_state.addon.name()));
+            ui.add_space(16.0);
+            ui.label("Unchecked particles won't be installed. Particles used by another checked particle system are still installed.");
+            if self.search.matches().is_filtered() {
+                ui.label("Only the particle systems matching your search are listed.");
+            }
+            ui.add_space(16.0);
+            addon_manager::particle_selection(ui, addon_state, self.search.matches());
+            ui.add_space(16.0);
+            Sides::new().show(
+                ui,
+                |_ui| {},
+                |ui| {
+                    if ui.button("Done").clicked() {
+                        ui.close();
+                    }
+                },
+            )
+        });
+
+        if modal.should_close() {
+            self.addons[addon_idx].particles.trim_filters();
+            Self {
+                state: ManagingAddonsState::Managing,
+                ..self
+            }
+            .into()
+        } else {
+            self.into()
+        }
+    }
+
+    fn handle_showing_strip_stats(self, ui: &mut egui::Ui, addon_idx: usize, stats: &[(String, StripStats)]) -> State {
+        let modal = Modal::new(Id::new("Addon Strip Stats")).show(ui.ctx(), |ui| {
+            ui.set_width(700.0);
+            ui.heading(format!("Stripping {}'s particles", self.addons[addon_idx].addon.name()));
+            ui.add_space(16.0);
+            ui.label("When the enabled particles don't fit into the particle budget, they're stripped until they do. This is what stripping as much as possible removes from each of this addon's enabled PCFs.");
+            ui.add_space(16.0);
+            addon_manager::strip_stats_table(ui, stats);
+            ui.add_space(16.0);
+            Sides::new().show(
+                ui,
+                |_ui| {},
+                |ui| {
+                    if ui.button("Done").clicked() {
+                        ui.close();
+                    }
+                },
+            )
+        });
+
+        if modal.should_close() {
+            Self {
+                state: ManagingAddonsState::Managing,
+                ..self
+            }
+            .into()
+        } else {
+            self.into()
+        }
+    }
+
+    fn handle_tweaking_particles(mut self, ui: &mut egui::Ui, addon_idx: usize) -> State {
+        let addon_state = &mut self.addons[addon_idx];
+        let tweaker = &mut self.tweaker;
+        let modal = Modal::new(Id::new("Addon Particle Tweaker")).show(ui.ctx(), |ui| {
+            ui.set_width(900.0);
+            ui.heading(format!("Tweak {}'s particles", addon_state.addon.name()));
+            ui.add_space(16.0);
+            ui.label("Tweaks are applied when installing, and don't change the addon's own files. Bold attributes have been tweaked.");
+            ui.add_space(16.0);
+            particle_tweaker::particle_tweaker(ui, tweaker, addon_state);
+            ui.add_space(16.0);
+            Sides::new().show(
+                ui,
+                |_ui| {},
+                |ui| {
+                    if ui.button("Done").clicked() {
+                        ui.close();
+                    }
+                },
+            )
+        });
+
+        if modal.should_close() {
+            Self {
+                state: ManagingAddonsState::Managing,
+                ..self
+            }
+            .into()
+        } else {
+            self.into()
+        }
+    }
+
+    fn handle_editing_appearance(mut self, ui: &mut egui::Ui, app: &mut App) -> State {
+        let theme = &mut self.config.theme;
+        let modal = Modal::new(Id::new("Addon Manager Appearance")).show(ui.ctx(), |ui| {
+            ui.set_width(400.0);
+            ui.heading("Appearance");
+            ui.add_space(16.0);
+            let mut scaling = false;
+            egui::Grid::new("appearance settings")
+                .num_columns(2)
+                .spacing([16.0, 8.0])
+                .show(ui, |ui| {
+                    ui.label("Theme");
+                    ui.horizontal(|ui| {
+                        for mode in ThemeMode::ALL {
+                            ui.selectable_value(&mut theme.mode, mode, mode.label());
+                        }
+                    });
+                    ui.end_row();
+
+                    ui.label("Accent color");
+                    ui.color_edit_button_srgb(&mut theme.accent);
+                    ui.end_row();
+
+                    ui.label("Scale");
+                    scaling = ui
+                        .add(egui::Slider::new(&mut theme.scale, Theme::MIN_SCALE..=Theme::MAX_SCALE).step_by(0.05))
+                        .on_hover_text("Makes everything bigger or smaller, on top of your display's own scaling")
+                        .dragged();
+                    ui.end_row();
+                });
+            ui.add_space(16.0);
+            Sides::new().show(
+                ui,
+                |ui| {
+                    if ui.button("Reset").clicked() {
+                        *theme = Theme::default();
+                    }
+                },
+                |ui| {
+                    if ui.button("Done").clicked() {
+                        ui.close();
+                    }
+                },
+            );
+
+            // the theme is previewed as it's edited, except for the scale while it's being dragged - otherwise the
+            // slider would move out from under the cursor
+            if !scaling {
+                app.theme = *theme;
+            }
+        });
+
+        if modal.should_close() {
+            app.theme = self.config.theme;
+            let state = match config::write_config(&app.paths.config, &self.config) {
+                Ok(()) => ManagingAddonsState::Managing,
+                Err(err) => ManagingAddonsState::ShowingMessage(format!("Your appearance couldn't be saved: {err}")),
+            };
+
+            Self { state, ..self }.into()
+        } else {
+            self.into()
+        }
+    }
+
+    fn handle_confirming_install(self, ui: &mut egui::Ui, app: &mut App) -> State {
+        let mut install_confirmed = false;
+        let modal = Modal::new(Id::new("Confirm Addon Installation")).show(ui.ctx(), |ui| {
+            ui.set_width(500.0);
+            ui.heading("Are you sure?");
+            ui.add_space(16.0);
+            ui.strong("You're about to install the addons as you've configured them. Doing so will override any addons you've installed via dazzle.");
+            ui.add_space(16.0);
+            ui.horizontal_wrapped(|ui| {
+                for AddonState { addon, .. } in self.addons.iter().filter(|state| state.enabled) {
+                    addon_manager::thumbnail(ui, addon, 48.0).on_hover_text(addon.name());
+                }
+            });
+            ui.add_space(16.0);
+            Sides::new().show(
+                ui,
+                |_ui| {},
+                |ui| {
+                    if ui.button("No! Stop that!").clicked() {
+                        ui.close();
+                    }
+
+                    if ui.button("Yes, install!").clicked() {
+                        install_confirmed = true;
+                        ui.close();
+                    }
+                },
+            )
+        });
+
+        if install_confirmed {
+            // the user confirmed that they want to install their addons
+            self.start_game_action(GameAction::Install, ui, app)
+        } else if modal.should_close() {
+            Self {
+                state: ManagingAddonsState::Managing,
+                ..self
+            }
+            .into()
+        } else {
+            self.into()
+        }
+    }
+
+    fn handle_confirming_uninstall(self, ui: &mut egui::Ui, app: &mut App) -> State {
+        let mut uninstall_confirmed = false;
+        let modal = Modal::new(Id::new("Confirm Addon Uninstallation")).show(ui.ctx(), |ui| {
+            ui.set_width(500.0);
+            ui.heading("Are you sure?");
+            ui.add_space(16.0);
+            ui.strong("You're about to uninstall any addons you've previously installed via dazzle.");
+            ui.add_space(16.0);
+            Sides::new().show(
+                ui,
+                |_ui| {},
+                |ui| {
+                    if ui.button("No! Stop that!").clicked() {
+                        ui.close();
+                    }
+
+                    if ui.button("Yes, uninstall!").clicked() {
+                        uninstall_confirmed = true;
+                        ui.close();
+                    }
+                },
+            )
+        });
+
+        if uninstall_confirmed {
+            // the user confirmed that they want to install their addons
+            self.start_game_action(GameAction::Uninstall, ui, app)
+        } else if modal.should_close() {
+            Self {
+                state: ManagingAddonsState::Managing,
+                ..self
+            }
+            .into()
+        } else {
+            self.into()
+        }
+    }
+
+    /// Starts `action`, unless TF2 is running, in which case the user is asked to close it first, or the vanilla
+    /// particles can't be restored, in which case the user is asked to have Steam verify the game files.
+    fn start_game_action(self, action: GameAction, ui: &mut egui::Ui, app: &mut App) -> State {
+        if tf_process::is_tf2_running() {
+            return Self {
+                state: ManagingAddonsState::WaitingForGameExit(action),
+                ..self
+            }
+            .into();
+        }
+
+        if let Err(err) = vanilla::check(&self.config.tf_dir) {
+            return Self {
+                state: ManagingAddonsState::RestoringVanilla(action, Restoration::new(&err)),
+                ..self
+            }
+            .into();
         }
+
+        self.start_game_action_unchecked(action, ui, app)
     }
 
-    fn handle_confirming_install(self, ui: &mut egui::Ui, app: &mut App) -> State {
-        let mut install_confirmed = false;
-        let modal = Modal::new(Id::new("Confirm Addon Installation")).show(ui.ctx(), |ui| {
+    fn start_game_action_unchecked(self, action: GameAction, ui: &mut egui::Ui, app: &mut App) -> State {
+        match action {
+            GameAction::Install => Installing::new(self.config, self.addons, ui.ctx(), app).into(),
+            GameAction::Uninstall => Uninstalling::new(self.config, self.addons, ui.ctx(), app).into(),
+        }
+    }
+
+    fn handle_waiting_for_game_exit(self, ui: &mut egui::Ui, app: &mut App, action: GameAction) -> State {
+        let mut check_again = false;
+        let modal = Modal::new(Id::new("Close TF2 First")).show(ui.ctx(), |ui| {
             ui.set_width(500.0);
-            ui.heading("Are you sure?");
+            ui.heading("Close TF2 first");
             ui.add_space(16.0);
-            ui.strong("You're about to install the addons as you've configured them. Doing so will override any addons you've installed via dazzle.");
+            ui.strong(
+                "TF2 is running. Changing its files while it's running can crash the game or corrupt its view of \
+                 them, so please close TF2 before continuing.",
+            );
             ui.add_space(16.0);
             Sides::new().show(
                 ui,
                 |_ui| {},
                 |ui| {
-                    if ui.button("No! Stop that!").clicked() {
+                    if ui.button("Cancel").clicked() {
                         ui.close();
                     }
 
-                    if ui.button("Yes, install!").clicked() {
-                        install_confirmed = true;
-                        ui.close();
+                    if ui.button("I closed it, check again").clicked() {
+                        check_again = true;
                     }
                 },
             )
         });
 
-        if install_confirmed {
-            // the user confirmed that they want to install their addons
-            Installing::new(self.config, self.addons, ui.ctx(), app).into()
+        if check_again {
+            // stays in this state if TF2 is still running
+            self.start_game_action(action, ui, app)
         } else if modal.should_close() {
             Self {
                 state: ManagingAddonsState::Managing,
@@ -269,53 +852,106 @@ impl ManagingAddons {
         }
     }
 
-    fn handle_confirming_uninstall(self, ui: &mut egui::Ui, app: &mut App) -> State {
-        let mut uninstall_confirmed = false;
-        let modal = Modal::new(Id::new("Confirm Addon Uninstallation")).show(ui.ctx(), |ui| {
+    fn handle_restoring_vanilla(
+        self,
+        ui: &mut egui::Ui,
+        app: &mut App,
+        action: GameAction,
+        mut restoration: Restoration,
+    ) -> State {
+        if restoration.is_validating() && restoration.poll(&self.config.tf_dir) {
+            // N.B. TF2 can't have been started while Steam was verifying its files
+            return self.start_game_action_unchecked(action, ui, app);
+        }
+
+        let mut validate = false;
+        let modal = Modal::new(Id::new("Restore Vanilla Particles")).show(ui.ctx(), |ui| {
             ui.set_width(500.0);
-            ui.heading("Are you sure?");
+            ui.heading("TF2's files need restoring");
             ui.add_space(16.0);
-            ui.strong("You're about to uninstall any addons you've previously installed via dazzle.");
+            ui.strong(format!(
+                "dazzle can't restore TF2's original particles, because {}. Steam can restore them by verifying \
+                 TF2's game files.",
+                restoration.reason
+            ));
             ui.add_space(16.0);
+            if restoration.is_validating() {
+                ui.horizontal(|ui| {
+                    ui.spinner();
+                    ui.label("Waiting for Steam to restore tf2_misc_dir.vpk. dazzle will carry on once it has.");
+                });
+                ui.add_space(16.0);
+            }
+
             Sides::new().show(
                 ui,
                 |_ui| {},
                 |ui| {
-                    if ui.button("No! Stop that!").clicked() {
+                    if ui.button("Cancel").clicked() {
                         ui.close();
                     }
 
-                    if ui.button("Yes, uninstall!").clicked() {
-                        uninstall_confirmed = true;
-                        ui.close();
+                    let label = if restoration.is_validating() {
+                        "Verify again"
+                    } else {
+                        "Verify with Steam"
+                    };
+                    if ui.button(label).clicked() {
+                        validate = true;
                     }
                 },
             )
         });
 
-        if uninstall_confirmed {
-            // the user confirmed that they want to install their addons
-            Uninstalling::new(self.config, self.addons, ui.ctx(), app).into()
-        } else if modal.should_close() {
+        if validate {
+            match steam::validate_tf2() {
+                Ok(()) => restoration.start_watching(&self.config.tf_dir),
+                Err(err) => {
+                    return Self {
+                        state: ManagingAddonsState::ShowingMessage(format!(
+                            "Steam couldn't be asked to verify TF2's game files: {err}. Verify them from TF2's \
+                             properties in your Steam library instead, then try again."
+                        )),
+                        ..self
+                    }
+                    .into();
+                }
+            }
+        }
+
+        if modal.should_close() {
             Self {
                 state: ManagingAddonsState::Managing,
                 ..self
             }
             .into()
         } else {
-            self.into()
+            if restoration.is_validating() {
+                ui.ctx().request_repaint_after(vanilla::POLL_INTERVAL);
+            }
+
+            Self {
+                state: ManagingAddonsState::RestoringVanilla(action, restoration),
+                ..self
+            }
+            .into()
         }
     }
 
-    fn handle_confirming_delete(mut self, ui: &mut egui::Ui, delete_idx: usize) -> State {
+    fn handle_confirming_delete(mut self, ui: &mut egui::Ui, app: &App, delete_idx: usize) -> State {
         let mut delete_confirmed = false;
         let modal = Modal::new(Id::new("Confirm Addon Deletion")).show(ui.ctx(), |ui| {
             ui.set_width(500.0);
             ui.heading("Are you sure?");
             ui.add_space(16.0);
+            let addon = &self.addons.get(delete_idx).unwrap().addon;
+            if addon.thumbnail.is_some() {
+                addon_manager::thumbnail(ui, addon, 128.0);
+                ui.add_space(16.0);
+            }
             ui.strong(format!(
-                "You're about to permanently delete '{}'. Please confirm:",
-                self.addons.get(delete_idx).unwrap().addon.name()
+                "You're about to delete '{}'. You can undo this until you close dazzle. Please confirm:",
+                addon.name()
             ));
             ui.add_space(16.0);
             Sides::new().show(
@@ -339,7 +975,105 @@ impl ManagingAddons {
             // should start the delete process & transition to the delete state.
             let addon = self.addons.remove(delete_idx);
 
-            RemovingAddon::new(self.config, self.addons, ui.ctx(), addon.addon).into()
+            RemovingAddon::new(self.config, self.addons, ui.ctx(), app, addon.addon).into()
+        } else if modal.should_close() {
+            Self {
+                state: ManagingAddonsState::Managing,
+                ..self
+            }
+            .into()
+        } else {
+            self.into()
+        }
+    }
+
+    fn handle_removal_action(self, action: RemovalAction, ui: &mut egui::Ui, app: &mut App) -> State {
+        let Some(trashed) = app.removed_addon.take() else {
+            return self.into();
+        };
+
+        match action {
+            RemovalAction::Undo => match trashed.restore() {
+                Ok(()) => AddingAddons::new(self.config, self.addons, vec![trashed.source_path], ui.ctx(), app).into(),
+                Err(err) => Self {
+                    state: ManagingAddonsState::ShowingMessage(format!("The addon couldn't be put back: {err}")),
+                    ..self
+                }
+                .into(),
+            },
+            RemovalAction::Dismiss => self.into(),
+        }
+    }
+
+    fn handle_problem_action(self, action: ProblemAction, ui: &mut egui::Ui, app: &mut App) -> State {
+        match action {
+            ProblemAction::Retry(problem_idx) => {
+                let problem = app.load_problems.remove(problem_idx);
+
+                // none of the addons could be listed, so they're all loaded again
+                if matches!(problem.error, LoadError::AddonsDir(_)) {
+                    return InitialLoad::new(self.config, ui.ctx(), &app.paths).into();
+                }
+
+                AddingAddons::new(self.config, self.addons, vec![problem.path], ui.ctx(), app).into()
+            }
+            ProblemAction::OpenLocation(problem_idx) => {
+                let path = &app.load_problems[problem_idx].path;
+                if let Err(err) = file_explorer::open_file_explorer(path.parent().unwrap_or(path)) {
+                    eprintln!("couldn't open '{path}': {err}");
+                }
+                self.into()
+            }
+            ProblemAction::Remove(problem_idx) => Self {
+                state: ManagingAddonsState::ConfirmingProblemRemoval(problem_idx),
+                ..self
+            }
+            .into(),
+            ProblemAction::Dismiss(problem_idx) => {
+                app.load_problems.remove(problem_idx);
+                self.into()
+            }
+        }
+    }
+
+    fn handle_confirming_problem_removal(self, ui: &mut egui::Ui, app: &mut App, problem_idx: usize) -> State {
+        let mut remove_confirmed = false;
+        let path = app.load_problems[problem_idx].path.clone();
+        let modal = Modal::new(Id::new("Confirm Problem Removal")).show(ui.ctx(), |ui| {
+            ui.set_width(500.0);
+            ui.heading("Are you sure?");
+            ui.add_space(16.0);
+            ui.strong(format!(
+                "You're about to permanently delete '{}' from your addons folder. Please confirm:",
+                path.file_name().unwrap_or(path.as_str())
+            ));
+            ui.add_space(16.0);
+            Sides::new().show(
+                ui,
+                |_ui| {},
+                |ui| {
+                    if ui.button("Delete It!").clicked() {
+                        remove_confirmed = true;
+                        ui.close();
+                    }
+
+                    if ui.button("No! Stop that!").clicked() {
+                        ui.close();
+                    }
+                },
+            )
+        });
+
+        if remove_confirmed {
+            let state = match load_problems::remove(&path) {
+                Ok(()) => {
+                    app.load_problems.remove(problem_idx);
+                    ManagingAddonsState::Managing
+                }
+                Err(err) => ManagingAddonsState::ShowingMessage(format!("'{path}' couldn't be deleted: {err}")),
+            };
+
+            Self { state, ..self }.into()
         } else if modal.should_close() {
             Self {
                 state: ManagingAddonsState::Managing,
@@ -356,15 +1090,69 @@ impl HandleState for ManagingAddons {
     fn handle(mut self, ui: &mut egui::Ui, app: &mut App) -> State {
         match self.state {
             ManagingAddonsState::Managing => {
-                if let Some(action) = addon_manager::addons_manager(ui, &mut self.addons).action {
+                // addons handed off by another instance are added as soon as the user is free to manage them
+                if !app.pending_addons.is_empty() {
+                    let files = mem::take(&mut app.pending_addons);
+                    return AddingAddons::new(self.config, self.addons, files, ui.ctx(), app).into();
+                }
+
+                if app.read_only {
+                    ui.colored_label(
+                        ui.visuals().warn_fg_color,
+                        format!(
+                            "🔒 Inspecting the addons in '{}'. Nothing can be installed, added or removed.",
+                            app.paths.data
+                        ),
+                    );
+                } else {
+                    app.integrity
+                        .poll(ui.ctx(), &self.config.tf_dir, &self.config.vpk_name, &self.addons);
+                }
+
+                let problem_action =
+                    load_problems::problems_panel(ui, &app.load_problems, &app.paths.addons, app.read_only);
+                let action =
+                    addon_manager::addons_manager(ui, &mut self.addons, &mut self.search, app.read_only).action;
+                if let Some(problem_action) = problem_action {
+                    self.handle_problem_action(problem_action, ui, app)
+                } else if let Some(action) = action {
                     self.handle_action(action, ui, app)
+                } else if !app.read_only && app.integrity.notification(ui.ctx()) == Some(NotificationAction::Repair) {
+                    self.start_game_action(GameAction::Install, ui, app)
+                } else if let Some(trashed) = &app.removed_addon
+                    && let Some(action) = addon_manager::removal_notification(ui.ctx(), trashed)
+                {
+                    self.handle_removal_action(action, ui, app)
                 } else {
                     self.into()
                 }
             },
             ManagingAddonsState::ConfirmingInstall => self.handle_confirming_install(ui, app),
             ManagingAddonsState::ConfirmingUninstall => self.handle_confirming_uninstall(ui, app),
-            ManagingAddonsState::ConfirmingDelete(delete_idx) => self.handle_confirming_delete(ui, delete_idx),
+            ManagingAddonsState::ConfirmingDelete(delete_idx) => self.handle_confirming_delete(ui, app, delete_idx),
+            ManagingAddonsState::ConfirmingProblemRemoval(problem_idx) => {
+                self.handle_confirming_problem_removal(ui, app, problem_idx)
+            }
+            ManagingAddonsState::SelectingParticles(addon_idx) => self.handle_selecting_particles(ui, addon_idx),
+            ManagingAddonsState::TweakingParticles(addon_idx) => self.handle_tweaking_particles(ui, addon_idx),
+            ManagingAddonsState::ShowingStripStats(addon_idx, ref stats) => {
+                let stats = stats.clone();
+                self.handle_showing_strip_stats(ui, addon_idx, &stats)
+            }
+            ManagingAddonsState::EditingAppearance => self.handle_editing_appearance(ui, app),
+            ManagingAddonsState::EnteringUrls(ref urls) => {
+                let urls = urls.clone();
+                self.handle_entering_urls(ui, app, urls)
+            }
+            ManagingAddonsState::ShowingMessage(ref message) => {
+                let message = message.clone();
+                self.handle_showing_message(ui, &message)
+            }
+            ManagingAddonsState::WaitingForGameExit(action) => self.handle_waiting_for_game_exit(ui, app, action),
+            ManagingAddonsState::RestoringVanilla(action, ref restoration) => {
+                let restoration = restoration.clone();
+                self.handle_restoring_vanilla(ui, app, action, restoration)
+            }
         }
     }
 }
@@ -373,32 +1161,38 @@ impl HandleState for ManagingAddons {
 pub(crate) struct RemovingAddon {
     config: Config,
     addons: Vec<AddonState>,
-    view: ProcessView,
-    job: RemovingAddonJob,
+    task: RemovingAddonJob,
 }
 
 impl RemovingAddon {
-    pub fn new(config: Config, addons: Vec<AddonState>, ctx: &egui::Context, addon: Addon) -> Self {
-        let (view, job) = addon_manager::start_addon_removal(ctx, addon);
+    pub fn new(config: Config, addons: Vec<AddonState>, ctx: &egui::Context, app: &App, addon: Addon) -> Self {
+        let task = addon_manager::start_addon_removal(ctx, &app.paths, &config, addon);
 
-        Self {
-            config,
-            addons,
-            view,
-            job,
-        }
+        Self { config, addons, task }
     }
 }
 
 impl HandleState for RemovingAddon {
-    fn handle(mut self, ui: &mut egui::Ui, _app: &mut App) -> State {
-        self.view.show("removing addon contents", ui.ctx());
-        if self.job.is_finished() {
-            // TODO: present job errors to the user as a modal
-            self.job.join().unwrap().unwrap();
-            ManagingAddons::new(self.config, self.addons).into()
-        } else {
-            self.into()
+    fn handle(mut self, ui: &mut egui::Ui, app: &mut App) -> State {
+        self.task.show("removing addon contents", ui.ctx());
+        let Poll::Ready(result) = self.task.poll() else {
+            return self.into();
+        };
+
+        let Ok(result) = result else {
+            return Crashed::new("removing an addon").into();
+        };
+
+        match result {
+            Ok((config, trashed)) => {
+                app.removed_addon = trashed;
+                ManagingAddons::new(config, self.addons).into()
+            }
+            Err(err) => ManagingAddons {
+                state: ManagingAddonsState::ShowingMessage(format!("The addon couldn't be removed: {err:#}")),
+                ..ManagingAddons::new(self.config, self.addons)
+            }
+            .into(),
         }
     }
 }
@@ -406,8 +1200,7 @@ impl HandleState for RemovingAddon {
 #[derive(Debug)]
 pub(crate) struct AddingAddons {
     config: Config,
-    view: ProcessView,
-    job: AddingAddonsJob,
+    task: AddingAddonsJob,
 }
 
 impl AddingAddons {
@@ -418,95 +1211,234 @@ impl AddingAddons {
         ctx: &egui::Context,
         app: &App,
     ) -> Self {
-        let (view, job) = addon_manager::start_addon_add(ctx, &app.paths, addons, files);
+        let task = addon_manager::start_addon_add(ctx, &app.paths, addons, files);
 
-        Self { config, view, job }
+        Self { config, task }
     }
 }
 
 impl HandleState for AddingAddons {
-    fn handle(mut self, ui: &mut egui::Ui, _app: &mut App) -> State {
-        self.view.show("adding addons", ui.ctx());
-        if self.job.is_finished() {
-            // TODO: present job errors to the user as a modal
-            let result = self.job.join().unwrap();
-            for (path, err) in result.1 {
-                eprintln!("There was an error loading {path}: {err}");
-            }
+    fn handle(mut self, ui: &mut egui::Ui, app: &mut App) -> State {
+        self.task.show("adding addons", ui.ctx());
+        let Poll::Ready(result) = self.task.poll() else {
+            return self.into();
+        };
 
-            ManagingAddons::new(self.config, result.0).into()
-        } else {
-            self.into()
+        let Ok((addons, problems, updated)) = result else {
+            return Crashed::new("adding addons").into();
+        };
+
+        // a retried addon replaces its old problem, if it still has one
+        app.load_problems
+            .retain(|existing| !problems.iter().any(|problem| problem.path == existing.path));
+        app.load_problems.extend(problems);
+
+        if updated.is_empty() {
+            return ManagingAddons::new(self.config, addons).into();
+        }
+
+        // an updated addon no longer matches what was installed, which the next scan reports with the option to
+        // install again
+        app.integrity.invalidate();
+        ManagingAddons {
+            state: ManagingAddonsState::ShowingMessage(format!(
+                "Updated {}. Install your addons again to use the new versions.",
+                updated.join(", ")
+            )),
+            ..ManagingAddons::new(self.config, addons)
         }
+        .into()
     }
 }
 
+/// Pairs each of `addons` with its saved state in `config`, sorted by the order the user chose.
+fn addon_states(config: &Config, addons: Vec<Addon>) -> Vec<AddonState> {
+    let mut addons: Vec<_> = addons
+        .into_iter()
+        .map(|addon| (config.addons.get(addon.name()).cloned().unwrap_or_default(), addon))
+        .collect();
+
+    addons.sort_by_key(|(config, _)| config.order);
+
+    addons
+        .into_iter()
+        .map(|(config, addon)| AddonState {
+            enabled: config.enabled,
+            addon,
+            particles: config.particles,
+            overrides: config.overrides,
+        })
+        .collect()
+}
+
 #[derive(Debug)]
 pub(crate) struct Installing {
     config: Config,
-    view: ProcessView,
-    job: AddonInstallJob,
+    task: AddonInstallJob,
 }
 
 impl Installing {
     pub fn new(config: Config, addons: Vec<AddonState>, ctx: &egui::Context, app: &App) -> Self {
-        let (view, job) = addon_manager::start_addon_install(ctx, &app.paths, &config, addons);
+        let task = addon_manager::start_addon_install(ctx, &app.paths, &config, addons);
 
-        Self { config, view, job }
+        Self { config, task }
     }
 }
 
 impl HandleState for Installing {
-    fn handle(mut self, ui: &mut egui::Ui, _app: &mut App) -> State {
-        self.view.show("installing addons", ui.ctx());
+    fn handle(mut self, ui: &mut egui::Ui, app: &mut App) -> State {
+        self.task.show("installing addons", ui.ctx());
 
-        if self.job.is_finished() {
-            // TODO: present job errors to the user as a modal
-            let addons = self.job.join().unwrap().unwrap();
-            ManagingAddons::new(self.config, addons).into()
-        } else {
-            self.into()
+        let Poll::Ready(result) = self.task.poll() else {
+            return self.into();
+        };
+
+        let Ok(result) = result else {
+            return Crashed::new("installing addons").into();
+        };
+
+        if self.config.record_metrics {
+            let report = result.as_ref().ok().map(|(_, report)| report);
+            metrics::record_install(
+                &app.paths.data,
+                InstallMetrics::new(self.task.view().stage_clock(), report),
+            );
         }
+
+        // TODO: present job errors to the user as a modal
+        let (addons, mut report) = result.unwrap();
+        app.integrity.invalidate();
+
+        let report_path = match report.write(&app.paths.data) {
+            Ok(()) => Some(app.paths.data.join(install_report::REPORT_TEXT)),
+            Err(err) => {
+                report.warnings.push(format!("The report couldn't be saved: {err:#}"));
+                None
+            }
+        };
+
+        InstallSummary::new(self.config, addons, &report, report_path).into()
     }
 }
 
 #[derive(Debug)]
 pub(crate) struct Uninstalling {
     config: Config,
-    view: ProcessView,
-    job: AddonUninstallJob,
+    task: AddonUninstallJob,
 }
 
 impl Uninstalling {
     pub fn new(config: Config, addons: Vec<AddonState>, ctx: &egui::Context, app: &App) -> Self {
-        let (view, job) = addon_manager::start_addon_uninstall(ctx, &app.paths, &config, addons);
+        let task = addon_manager::start_addon_uninstall(ctx, &app.paths, &config, addons);
 
-        Self { config, view, job }
+        Self { config, task }
     }
 }
 
 impl HandleState for Uninstalling {
-    fn handle(mut self, ui: &mut egui::Ui, _app: &mut App) -> State {
-        self.view.show("installing addons", ui.ctx());
+    fn handle(mut self, ui: &mut egui::Ui, app: &mut App) -> State {
+        self.task.show("installing addons", ui.ctx());
 
-        if self.job.is_finished() {
-            // TODO: present job errors to the user as a modal
-            let addons = self.job.join().unwrap().unwrap();
-            ManagingAddons::new(self.config, addons).into()
-        } else {
-            self.into()
+        let Poll::Ready(result) = self.task.poll() else {
+            return self.into();
+        };
+
+        let Ok(result) = result else {
+            return Crashed::new("uninstalling addons").into();
+        };
+
+        // TODO: present job errors to the user as a modal
+        let addons = result.unwrap();
+        app.integrity.invalidate();
+        ManagingAddons::new(self.config, addons).into()
+    }
+}
+
+/// A worker thread panicked, so whatever state it owned is gone. The only thing left to do is tell the user where the
+/// crash log is and close.
+#[derive(Debug)]
+pub(crate) struct Crashed {
+    task: &'static str,
+    log_path: Option<Utf8PlatformPathBuf>,
+}
+
+impl Crashed {
+    pub fn new(task: &'static str) -> Self {
+        Self {
+            task,
+            log_path: crate::crash::crash_log_path().map(Utf8PlatformPath::to_path_buf),
         }
     }
 }
 
+impl HandleState for Crashed {
+    fn handle(self, ui: &mut egui::Ui, _app: &mut App) -> State {
+        Modal::new(Id::new("Dazzle Crashed")).show(ui.ctx(), |ui| {
+            ui.set_width(500.0);
+            ui.heading("Dazzle crashed");
+            ui.add_space(16.0);
+            ui.strong(format!(
+                "Something went wrong while {} and dazzle can't continue.",
+                self.task
+            ));
+            ui.add_space(8.0);
+            if let Some(log_path) = &self.log_path {
+                ui.label(
+                    "Details about the crash were written to the crash log. Please include it when reporting this bug:",
+                );
+                ui.code(log_path.as_str());
+            } else {
+                ui.label("The crash log couldn't be written, so there aren't any further details.");
+            }
+            ui.add_space(16.0);
+            Sides::new().show(
+                ui,
+                |_ui| {},
+                |ui| {
+                    if ui.button("Close").clicked() {
+                        ui.ctx().send_viewport_cmd(egui::ViewportCommand::Close);
+                    }
+
+                    if let Some(log_dir) = self.log_path.as_deref().and_then(Utf8PlatformPath::parent)
+                        && ui.button("Open log folder").clicked()
+                        && let Err(err) = file_explorer::open_file_explorer(log_dir)
+                    {
+                        eprintln!("couldn't open the crash log's folder: {err}");
+                    }
+                },
+            )
+        });
+
+        self.into()
+    }
+}
+
 #[derive(Debug, From)]
 pub(crate) enum State {
     Launch(Launch),
 
-    /// The user has launched for the first time is choosing a valid tf/ directory
-    /// Will always transition to [`State::InitialLoad`].
+    /// The user has launched for the first time and is being greeted by the setup wizard.
+    /// Will always transition to [`State::ConfiguringTfDir`].
+    Welcome(Welcome),
+
+    /// The user is choosing a valid tf/ directory, either during the setup wizard or because their configured one is
+    /// no longer valid.
+    /// Will transition to [`State::ChoosingImport`] during the setup wizard, otherwise to [`State::InitialLoad`].
     ConfiguringTfDir(ConfiguringTfDir),
 
+    /// The user is optionally choosing addons to import from cueki's preloader, or another folder of addons.
+    /// Will always transition to [`State::SetupSummary`].
+    ChoosingImport(ChoosingImport),
+
+    /// The user is reviewing their choices before finishing the setup wizard.
+    /// Will transition to [`State::ImportingAddons`] if there are addons to import, otherwise to
+    /// [`State::InitialLoad`].
+    SetupSummary(SetupSummary),
+
+    /// We're copying the addons the user chose to import into the addons directory.
+    /// Will always transition to [`State::InitialLoad`].
+    ImportingAddons(ImportingAddons),
+
     /// We're loading vanilla PCFs & all addons in their addons directory. Doing so allows us to ensure each addon is
     /// valid, and to evaluate conflicts between addons.
     /// Will always transition to [`State::ChoosingAddons`].
@@ -524,15 +1456,31 @@ pub(crate) enum State {
     /// Will always transition to [`State::ManagingAddons`].
     AddingAddons(AddingAddons),
 
+    /// We're downloading addons from the pages the user pasted.
+    /// Will transition to [`State::AddingAddons`] if everything was downloaded, otherwise to [`State::ManagingAddons`].
+    Downloading(Downloading),
+
     /// We're processing all of their addons and installing them!
-    /// Will always transition to [`State::ManagingAddons`].
+    /// Will always transition to [`State::InstallSummary`].
     Installing(Installing),
 
+    /// The install finished, and the user is reading its report.
+    /// Will always transition to [`State::ManagingAddons`].
+    InstallSummary(InstallSummary),
+
+    /// We're exporting one addon's particles as a preview, without installing anything.
+    /// Will always transition to [`State::ManagingAddons`].
+    Previewing(Previewing),
+
     #[allow(clippy::doc_markdown)]
     /// We're restoring tf2_misc.vpk, removing _dazzle_addons.vpk, and removing _dazzle_qpc.vpk
     /// Will always transition to [`State::ManagingAddons`].
     Uninstalling(Uninstalling),
 
+    /// A worker thread panicked while processing one of the above states.
+    /// Never transitions; the user can only close the app from here.
+    Crashed(Crashed),
+
     /// An intermediate value used as the enum's default when using helpers like [`std::mem::take`] and [`std::mem::replace`]
     Intermediate,
 }
@@ -541,44 +1489,146 @@ pub(crate) enum State {
 pub(crate) struct App {
     paths: Paths,
     state: State,
+
+    /// receives addons from other instances, unless dazzle is only inspecting
+    handoff: Option<Handoff>,
+
+    /// whether dazzle is only inspecting the addons, and mustn't change the game or the data dir
+    read_only: bool,
+
+    /// addon paths which should be added once the user is managing their addons
+    pending_addons: Vec<Utf8PlatformPathBuf>,
+
+    /// the theme the user has chosen, which is applied to the context whenever it changes
+    theme: Theme,
+    applied_theme: Option<Theme>,
+
+    integrity: IntegrityScanner,
+
+    /// addons which couldn't be loaded, which are shown until the user deals with them
+    load_problems: Vec<LoadProblem>,
+
+    /// the last addon the user removed, whose removal can be undone until the notification is dismissed
+    removed_addon: Option<Trashed>,
 }
 
 impl App {
-    pub(crate) fn new() -> Result<Self, BuildError> {
-        _ = create_single_instance()?;
+    /// Creates the app, which will add `pending_addons` once it's loaded. If dazzle is already running,
+    /// `pending_addons` are handed off to that instance instead, and [`BuildError::HandedOff`] is returned.
+    pub(crate) fn new(pending_addons: Vec<Utf8PlatformPathBuf>) -> Result<Self, BuildError> {
+        let project_dirs = create_project_dirs()?;
+        let data_dir = get_data_dir(&project_dirs)?;
+
+        let instance = match create_single_instance() {
+            Err(BuildError::MultipleInstances) => {
+                return match handoff::forward(&data_dir, &pending_addons) {
+                    Ok(()) => Err(BuildError::HandedOff),
+                    Err(err) => {
+                        eprintln!("couldn't hand off to the running instance: {err}");
+                        Err(BuildError::MultipleInstances)
+                    }
+                };
+            }
+            instance => instance?,
+        };
 
+        let paths = create_paths(&project_dirs, &data_dir)?;
+        let config = config::create_or_read_config(&paths.config)?;
+        let handoff = Handoff::listen(instance, &data_dir);
+
+        Ok(Self {
+            paths,
+            theme: config.theme,
+            state: Launch::new(config).into(),
+            handoff: Some(handoff),
+            read_only: false,
+            pending_addons,
+            applied_theme: None,
+            integrity: IntegrityScanner::default(),
+            load_problems: Vec::new(),
+            removed_addon: None,
+        })
+    }
+
+    /// Creates the app in read-only mode, to look through the addons in `data_dir` - or the user's own data dir if
+    /// it's [`None`] - without changing them or the game. The addons are extracted into a scratch dir, rather than the
+    /// data dir, and nothing that would write to either can be started. Any number of these can run alongside a
+    /// normal instance.
+    pub(crate) fn inspect(data_dir: Option<Utf8PlatformPathBuf>) -> Result<Self, BuildError> {
         let project_dirs = create_project_dirs()?;
-        let data_dir = get_data_dir(&project_dirs);
-        let extracted_content_dir = create_new_content_cache_dir(&data_dir)?;
-        let working_vpk_dir = create_new_working_vpk_dir(&data_dir)?;
-        let addons_dir = create_addons_dir(&data_dir)?;
-        let config_path = get_config_path(&project_dirs);
-        let config = config::create_or_read_config(&config_path)?;
+        let (data_dir, config_path) = match data_dir {
+            Some(data_dir) => {
+                let config_path = data_dir.join("config.toml");
+                (data_dir, config_path)
+            }
+            None => (get_data_dir(&project_dirs)?, get_config_path(&project_dirs)?),
+        };
+
+        let paths = create_inspection_paths(&data_dir, config_path)?;
+        let config = config::read_config(&paths.config)?;
 
         Ok(Self {
-            paths: Paths {
-                addons: addons_dir,
-                extracted_content: extracted_content_dir,
-                working_vpk: working_vpk_dir,
-                config: config_path,
-            },
+            paths,
+            theme: config.theme,
             state: Launch::new(config).into(),
+            handoff: None,
+            read_only: true,
+            pending_addons: Vec::new(),
+            applied_theme: None,
+            integrity: IntegrityScanner::default(),
+            load_problems: Vec::new(),
+            removed_addon: None,
         })
     }
 }
 
+/// Prepares dazzle's directories & config for a headless command. The returned [`SingleInstance`] must be held until
+/// the command is done, so that the GUI can't start and clear the directories while the command is using them.
+pub(crate) fn headless_environment() -> Result<(Paths, Config, SingleInstance), BuildError> {
+    let project_dirs = create_project_dirs()?;
+    let data_dir = get_data_dir(&project_dirs)?;
+    let instance = create_single_instance()?;
+    let paths = create_paths(&project_dirs, &data_dir)?;
+    let config = config::create_or_read_config(&paths.config)?;
+
+    Ok((paths, config, instance))
+}
+
 impl eframe::App for App {
     fn update(&mut self, ctx: &egui::Context, _frame: &mut eframe::Frame) {
+        if let Some(handoff) = &self.handoff {
+            handoff.set_context(ctx);
+        }
+
+        if let Some(paths) = self.handoff.as_ref().and_then(Handoff::try_recv) {
+            ctx.send_viewport_cmd(ViewportCommand::Minimized(false));
+            ctx.send_viewport_cmd(ViewportCommand::Focus);
+            self.pending_addons.extend(paths);
+        }
+
+        if self.applied_theme != Some(self.theme) {
+            styles::apply_theme(ctx, self.theme);
+            self.applied_theme = Some(self.theme);
+        }
+
         CentralPanel::default().show(ctx, |ui| {
             let state = match mem::replace(&mut self.state, State::Intermediate) {
                 State::Launch(launch) => launch.handle(ui, self),
+                State::Welcome(welcome) => welcome.handle(ui, self),
                 State::ConfiguringTfDir(configuring_tf_dir) => configuring_tf_dir.handle(ui, self),
+                State::ChoosingImport(choosing_import) => choosing_import.handle(ui, self),
+                State::SetupSummary(setup_summary) => setup_summary.handle(ui, self),
+                State::ImportingAddons(importing_addons) => importing_addons.handle(ui, self),
                 State::InitialLoad(initial_load) => initial_load.handle(ui, self),
                 State::ManagingAddons(managing_addons) => managing_addons.handle(ui, self),
                 State::RemovingAddon(removing_addon) => removing_addon.handle(ui, self),
                 State::AddingAddons(adding_addons) => adding_addons.handle(ui, self),
+                State::Downloading(downloading) => downloading.handle(ui, self),
                 State::Installing(installing) => installing.handle(ui, self),
+                State::InstallSummary(install_summary) => install_summary.handle(ui, self),
+                State::Previewing(previewing) => previewing.handle(ui, self),
                 State::Uninstalling(uninstalling) => uninstalling.handle(ui, self),
+                State::Crashed(crashed) => crashed.handle(ui, self),
                 State::Intermediate => panic!("under no circumstances should state be Intermediate in the matcher"),
             };
 
@@ -595,6 +1645,9 @@ pub(crate) enum BuildError {
     #[error("there are multiple instances of dazzle running")]
     MultipleInstances,
 
+    #[error("dazzle is already running, so this instance handed its addons off to it")]
+    HandedOff,
+
     #[error("couldn't find a valid home directory, which is necessary for some operations")]
     NoValidHomeDirectory,
 
@@ -613,6 +1666,24 @@ pub(crate) enum BuildError {
     #[error("couldn't create the addons directory, due to an IO error")]
     CantCreateAddonsDirectory(io::Error),
 
+    #[error("couldn't create the particle patches directory, due to an IO error")]
+    CantCreatePatchesDirectory(io::Error),
+
+    #[error("couldn't create the addon thumbnails directory, due to an IO error")]
+    CantCreateThumbnailsDirectory(io::Error),
+
+    #[error("couldn't create the removed addons directory, due to an IO error")]
+    CantCreateTrashDirectory(io::Error),
+
+    #[error("'{0}' isn't a dazzle data directory, since it doesn't have an addons folder")]
+    NotADataDirectory(Utf8PlatformPathBuf),
+
+    #[error("couldn't create the scratch directory to inspect addons in, due to an IO error")]
+    CantCreateScratchDirectory(io::Error),
+
+    #[error("dazzle's data & config directories must be valid UTF-8")]
+    NonUtf8Path(#[from] paths::PathError),
+
     #[error(transparent)]
     Config(#[from] Error),
 }
@@ -671,14 +1742,60 @@ fn create_project_dirs() -> Result<ProjectDirs, BuildError> {
     ProjectDirs::from(APP_TLD, APP_ORG, APP_NAME).ok_or(BuildError::NoValidHomeDirectory)
 }
 
-fn get_data_dir(dirs: &ProjectDirs) -> Utf8PlatformPathBuf {
+fn get_data_dir(dirs: &ProjectDirs) -> Result<Utf8PlatformPathBuf, BuildError> {
     let working_dir = dirs.data_local_dir();
-    paths::to_typed(working_dir).into_owned()
+    Ok(paths::try_to_typed(working_dir)?.to_owned())
 }
 
-fn get_config_path(dirs: &ProjectDirs) -> Utf8PlatformPathBuf {
+fn get_config_path(dirs: &ProjectDirs) -> Result<Utf8PlatformPathBuf, BuildError> {
     let working_dir = dirs.config_local_dir().join("config.toml");
-    paths::to_typed(&working_dir).into_owned()
+    Ok(paths::try_buf_to_typed(working_dir)?)
+}
+
+fn create_paths(project_dirs: &ProjectDirs, data_dir: &Utf8PlatformPath) -> Result<Paths, BuildError> {
+    let extracted_content_dir = create_new_content_cache_dir(data_dir)?;
+    let working_vpk_dir = create_new_working_vpk_dir(data_dir)?;
+    let addons_dir = create_addons_dir(data_dir)?;
+    let patches_dir = create_patches_dir(data_dir)?;
+    let thumbnails_dir = create_thumbnails_dir(data_dir)?;
+    let trash_dir = create_trash_dir(data_dir)?;
+    crate::crash::install_panic_hook(data_dir.join("crash.log"));
+    let config_path = get_config_path(project_dirs)?;
+
+    Ok(Paths {
+        addons: addons_dir,
+        patches: patches_dir,
+        extracted_content: extracted_content_dir,
+        working_vpk: working_vpk_dir,
+        config: config_path,
+        thumbnails: thumbnails_dir,
+        data: data_dir.to_path_buf(),
+        trash: trash_dir,
+    })
+}
+
+/// Creates the paths for [`App::inspect`]: the addons & patches are read from `data_dir`, and everything dazzle writes
+/// while loading them goes into a scratch dir of this process's own in the temp dir.
+fn create_inspection_paths(data_dir: &Utf8PlatformPath, config: Utf8PlatformPathBuf) -> Result<Paths, BuildError> {
+    let addons_dir = data_dir.join("addons");
+    if !fs::metadata(&addons_dir).is_ok_and(|metadata| metadata.is_dir()) {
+        return Err(BuildError::NotADataDirectory(data_dir.to_path_buf()));
+    }
+
+    let scratch_dir = paths::try_buf_to_typed(env::temp_dir())?.join(format!("dazzle-inspect-{}", std::process::id()));
+    fs::create_dir_all(&scratch_dir).map_err(BuildError::CantCreateScratchDirectory)?;
+    crate::crash::install_panic_hook(scratch_dir.join("crash.log"));
+
+    Ok(Paths {
+        addons: addons_dir,
+        patches: data_dir.join("patches"),
+        extracted_content: create_new_content_cache_dir(&scratch_dir)?,
+        working_vpk: create_new_working_vpk_dir(&scratch_dir)?,
+        config,
+        thumbnails: create_thumbnails_dir(&scratch_dir)?,
+        data: data_dir.to_path_buf(),
+        trash: scratch_dir.join("trash"),
+    })
 }
 
 fn create_new_content_cache_dir(dir: &Utf8PlatformPath) -> Result<Utf8PlatformPathBuf, BuildError> {
@@ -710,3 +1827,27 @@ fn create_addons_dir(dir: &Utf8PlatformPath) -> Result<Utf8PlatformPathBuf, Buil
     fs::create_dir_all(&addons_dir).map_err(BuildError::CantCreateAddonsDirectory)?;
     Ok(addons_dir)
 }
+
+fn create_patches_dir(dir: &Utf8PlatformPath) -> Result<Utf8PlatformPathBuf, BuildError> {
+    let patches_dir = dir.join("patches");
+    fs::create_dir_all(&patches_dir).map_err(BuildError::CantCreatePatchesDirectory)?;
+    Ok(patches_dir)
+}
+
+fn create_thumbnails_dir(dir: &Utf8PlatformPath) -> Result<Utf8PlatformPathBuf, BuildError> {
+    let thumbnails_dir = dir.join("thumbnails");
+    fs::create_dir_all(&thumbnails_dir).map_err(BuildError::CantCreateThumbnailsDirectory)?;
+    Ok(thumbnails_dir)
+}
+
+/// Creates the trash dir, emptying it of any addons which were removed more than [`TRASH_MAX_AGE`] ago.
+fn create_trash_dir(dir: &Utf8PlatformPath) -> Result<Utf8PlatformPathBuf, BuildError> {
+    let trash_dir = dir.join("trash");
+    if let Err(err) = removal::empty_trash(&trash_dir, TRASH_MAX_AGE) {
+        // old addons are only taking up space, which isn't worth failing to launch over
+        eprintln!("couldn't empty the trash: {err}");
+    }
+
+    fs::create_dir_all(&trash_dir).map_err(BuildError::CantCreateTrashDirectory)?;
+    Ok(trash_dir)
+}
@@ -0,0 +1,122 @@
+//! Finds artifacts left over from previous installs, e.g. because the user moved or renamed files by hand, so the
+//! uninstaller can verify that nothing of dazzle's is left behind.
+//!
+//! Every VPK dazzle generates contains a [`MARKER_ENTRY`] & a [`MANIFEST_ENTRY`], so renamed VPKs are still recognized
+//! as dazzle's.
+
+use std::{fmt, fs, io};
+
+use typed_path::{Utf8PlatformPath, Utf8PlatformPathBuf};
+use vpk::VPK;
+
+use crate::app::{provenance::MANIFEST_ENTRY, vanilla_source::VanillaSource};
+
+/// The path of the marker entry inside every VPK generated by dazzle.
+pub(crate) const MARKER_ENTRY: &str = "dazzle/generated_by_dazzle.txt";
+
+const MARKER_CONTENTS: &str = "This VPK was generated by dazzle. Uninstall your addons with dazzle to remove it.\n";
+
+/// Writes the marker entry into `working_vpk_dir`, so it's packed into the generated VPK.
+pub(crate) fn write_marker(working_vpk_dir: &Utf8PlatformPath) -> io::Result<()> {
+    let marker_path = working_vpk_dir.join(MARKER_ENTRY);
+    if let Some(parent) = marker_path.parent() {
+        fs::create_dir_all(parent)?;
+    }
+
+    fs::write(marker_path, MARKER_CONTENTS)
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub(crate) enum Orphan {
+    /// A VPK, VPK archive, or VGUI cache in `tf/custom/` that was generated by dazzle.
+    CustomFile(Utf8PlatformPathBuf),
+
+    /// Vanilla particles which are still changed, see [`VanillaSource::patched_particles`].
+    PatchedParticles(Vec<String>),
+
+    /// Files left over in dazzle's working VPK directory.
+    WorkingFiles(Utf8PlatformPathBuf),
+}
+
+impl fmt::Display for Orphan {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Orphan::CustomFile(path) => write!(f, "{path}"),
+            Orphan::PatchedParticles(names) => write!(f, "{} patched vanilla particles", names.len()),
+            Orphan::WorkingFiles(path) => write!(f, "leftover working files in {path}"),
+        }
+    }
+}
+
+/// Scans `tf_custom_dir`, the `vanilla` particles and `working_vpk_dir` for anything dazzle installed.
+///
+/// # Errors
+///
+/// Returns [`Err`] if any of the directories or VPK entries couldn't be read.
+pub(crate) fn find_orphans(
+    tf_custom_dir: &Utf8PlatformPath,
+    vanilla: &VanillaSource,
+    working_vpk_dir: &Utf8PlatformPath,
+) -> anyhow::Result<Vec<Orphan>> {
+    let mut orphans = Vec::new();
+
+    for entry in fs::read_dir(tf_custom_dir)? {
+        let entry = entry?;
+        if !entry.metadata()?.is_file() {
+            continue;
+        }
+
+        let path = paths::try_buf_to_typed(entry.path())?;
+        if is_dazzle_custom_file(&path) {
+            orphans.push(Orphan::CustomFile(path));
+        }
+    }
+
+    let patched = vanilla.patched_particles()?;
+    if !patched.is_empty() {
+        orphans.push(Orphan::PatchedParticles(patched));
+    }
+
+    if fs::read_dir(working_vpk_dir)?.next().is_some() {
+        orphans.push(Orphan::WorkingFiles(working_vpk_dir.to_owned()));
+    }
+
+    Ok(orphans)
+}
+
+/// Removes every orphan in `orphans`.
+///
+/// # Errors
+///
+/// Returns [`Err`] if any orphan couldn't be removed. Orphans before it will have been removed already.
+pub(crate) fn remove_orphans(orphans: &[Orphan], vanilla: &mut VanillaSource) -> anyhow::Result<()> {
+    for orphan in orphans {
+        match orphan {
+            Orphan::CustomFile(path) => fs::remove_file(path)?,
+            Orphan::PatchedParticles(names) => vanilla.restore_particles(names)?,
+            Orphan::WorkingFiles(path) => {
+                fs::remove_dir_all(path)?;
+                fs::create_dir(path)?;
+            }
+        }
+    }
+
+    Ok(())
+}
+
+/// Returns true if `path` is a VPK containing the [`MARKER_ENTRY`] or [`MANIFEST_ENTRY`], or is named like one of
+/// dazzle's VPKs or caches. Numbered archives like `_dazzle_addons_000.vpk` can't be opened on their own, so they're
+/// only recognized by name.
+fn is_dazzle_custom_file(path: &Utf8PlatformPath) -> bool {
+    let (Some(file_name), Some(extension)) = (path.file_name(), path.extension()) else {
+        return false;
+    };
+
+    let is_vpk = extension.eq_ignore_ascii_case("vpk");
+    if file_name.starts_with("_dazzle_") && (is_vpk || extension.eq_ignore_ascii_case("cache")) {
+        return true;
+    }
+
+    is_vpk
+        && VPK::read(path).is_ok_and(|vpk| vpk.tree.contains_key(MARKER_ENTRY) || vpk.tree.contains_key(MANIFEST_ENTRY))
+}
@@ -0,0 +1,258 @@
+//! Merges the particles of every enabled addon in the user's priority order. The engine looks particle systems up by
+//! name, so when more than one addon has a system with the same name only one of them can be installed: the highest
+//! priority addon's system replaces the others.
+//!
+//! Attribute overrides made to a replaced system aren't lost; they're carried over to the system replacing it. They're
+//! applied lowest priority first, so whenever more than one addon overrides the same attribute of the same system, the
+//! last writer - the highest priority addon - wins. The replacing addon's own overrides always win, since it has the
+//! highest priority of them all.
+
+use std::collections::{HashMap, HashSet};
+
+use ordermap::OrderMap;
+use pcf::Pcf;
+
+use crate::app::config::AttributeOverride;
+
+/// One addon's particles, with its own overrides already applied, see [`super::addon_manager::merged_pcfs`].
+#[derive(Debug)]
+pub(crate) struct AddonParticles<'a> {
+    pub addon: &'a str,
+
+    /// every `(item, pcf)`, where `item` names the PCF in status messages & pack reports
+    pub pcfs: Vec<(String, Pcf)>,
+
+    pub overrides: &'a [AttributeOverride],
+}
+
+#[derive(Debug, Default)]
+pub(crate) struct MergedParticles {
+    /// every `(item, pcf)` left to install, lowest priority addon first
+    pub pcfs: Vec<(String, Pcf)>,
+
+    /// every system which was replaced by a higher priority addon's, and every override which couldn't be carried over
+    pub notes: Vec<String>,
+}
+
+/// Merges `addons`, which are ordered lowest priority first.
+pub(crate) fn merge(addons: Vec<AddonParticles>) -> MergedParticles {
+    let names: Vec<_> = addons.iter().map(|addon| addon.addon).collect();
+    let overrides: Vec<_> = addons.iter().map(|addon| addon.overrides).collect();
+
+    // the addon installing each system, by the system's name
+    let mut owners: HashMap<String, usize> = HashMap::new();
+
+    // every `(addon, system)` whose system was replaced
+    let mut replaced = HashSet::new();
+
+    let mut notes = Vec::new();
+    let mut kept = Vec::with_capacity(addons.len());
+    for (idx, addon) in addons.into_iter().enumerate().rev() {
+        let mut claimed = Vec::new();
+        let mut pcfs = Vec::with_capacity(addon.pcfs.len());
+        for (item, pcf) in addon.pcfs {
+            let pcf = pcf.without_root_systems(|system| {
+                let Some(&owner) = owners.get(&system.name) else {
+                    return false;
+                };
+
+                notes.push(format!(
+                    "{}'s {} is replaced by {}'s, which has a higher priority",
+                    addon.addon, system.name, names[owner]
+                ));
+                replaced.insert((idx, system.name.clone()));
+                true
+            });
+
+            let pcf = match pcf {
+                Ok(pcf) => pcf,
+                Err(err) => {
+                    notes.push(format!("{}'s {item} is left out: {err}", addon.addon));
+                    continue;
+                }
+            };
+
+            claimed.extend(pcf.particle_systems().iter().map(|system| system.name.clone()));
+            pcfs.push((item, pcf));
+        }
+
+        // an addon's systems only replace lower priority addons' systems, so they're claimed once it's been merged
+        for name in claimed {
+            owners.entry(name).or_insert(idx);
+        }
+
+        kept.push(pcfs);
+    }
+
+    kept.reverse();
+
+    // later insertions overwrite earlier ones, so each attribute is left with its highest priority override
+    let mut carried: OrderMap<_, (usize, &AttributeOverride)> = OrderMap::new();
+    for (idx, overrides) in overrides.iter().enumerate() {
+        for attribute_override in overrides
+            .iter()
+            .filter(|tweak| replaced.contains(&(idx, tweak.system.clone())))
+        {
+            let key = (
+                &attribute_override.system,
+                &attribute_override.operator,
+                &attribute_override.attribute,
+            );
+            carried.insert(key, (idx, attribute_override));
+        }
+    }
+
+    for &(idx, attribute_override) in carried.values() {
+        let owner = owners[&attribute_override.system];
+        let path = attribute_override.path();
+        if overrides[owner].iter().any(|tweak| tweak.path() == path) {
+            continue;
+        }
+
+        let Some((_, pcf)) = kept[owner].iter_mut().find(|(_, pcf)| {
+            pcf.particle_systems()
+                .iter()
+                .any(|system| system.name == attribute_override.system)
+        }) else {
+            continue;
+        };
+
+        if let Err(err) = pcf.set_attribute(&path, attribute_override.value.into()) {
+            notes.push(format!(
+                "{}: skipping a particle tweak to {}, since it doesn't apply to {}'s: {err}",
+                names[idx], attribute_override.system, names[owner]
+            ));
+        }
+    }
+
+    MergedParticles {
+        pcfs: kept.into_iter().flatten().collect(),
+        notes,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use dmx::dmx::Version;
+    use pcf::{AttributeMap, AttributePath, ParticleSystem, Pcf, Root, Symbols};
+
+    use super::{AddonParticles, merge};
+    use crate::app::config::{AttributeOverride, OverrideValue};
+
+    fn pcf_with_system(name: &str) -> Pcf {
+        Pcf::new(
+            Version::Binary2Pcf1,
+            Symbols::new_with_all_special(),
+            Root::new(
+                "untitled".to_string(),
+                [0; 16],
+                Box::from([ParticleSystem {
+                    name: name.to_string(),
+                    ..ParticleSystem::default()
+                }]),
+                AttributeMap::new(),
+            ),
+        )
+    }
+
+    fn path(attribute: &str) -> AttributePath {
+        AttributePath {
+            system: "fire".to_string(),
+            operator: None,
+            attribute: attribute.to_string(),
+        }
+    }
+
+    fn tweak(attribute: &str, value: f32) -> AttributeOverride {
+        AttributeOverride {
+            pcf: "particles/fire.pcf".to_string(),
+            system: "fire".to_string(),
+            operator: None,
+            attribute: attribute.to_string(),
+            value: OverrideValue::Float(value),
+        }
+    }
+
+    /// An addon with a "fire" system, and `overrides` already applied to it.
+    fn addon<'a>(name: &'a str, overrides: &'a [AttributeOverride]) -> AddonParticles<'a> {
+        let mut pcf = pcf_with_system("fire");
+        for tweak in overrides {
+            pcf.set_attribute(&tweak.path(), tweak.value.into()).unwrap();
+        }
+
+        AddonParticles {
+            addon: name,
+            pcfs: vec![(format!("{name}/particles/fire.pcf"), pcf)],
+            overrides,
+        }
+    }
+
+    fn attribute(pcfs: &[(String, Pcf)], attribute: &str) -> Option<pcf::Attribute> {
+        let fires: Vec<_> = pcfs
+            .iter()
+            .filter(|(_, pcf)| pcf.particle_systems().iter().any(|system| system.name == "fire"))
+            .collect();
+
+        assert_eq!(fires.len(), 1, "only one addon's fire should be installed");
+        fires[0].1.attribute(&path(attribute)).unwrap().cloned()
+    }
+
+    #[test]
+    fn higher_priority_system_replaces_lower() {
+        let low = [tweak("radius", 1.0)];
+        let high = [tweak("alpha", 2.0)];
+        let merged = merge(vec![addon("low", &low), addon("high", &high)]);
+
+        assert_eq!(merged.pcfs[0].0, "low/particles/fire.pcf");
+        assert_eq!(merged.pcfs[0].1.particle_systems(), []);
+        assert_eq!(
+            merged.notes,
+            ["low's fire is replaced by high's, which has a higher priority"]
+        );
+
+        // the replaced system's override is carried over
+        assert_eq!(
+            attribute(&merged.pcfs, "radius"),
+            Some(OverrideValue::Float(1.0).into())
+        );
+        assert_eq!(attribute(&merged.pcfs, "alpha"), Some(OverrideValue::Float(2.0).into()));
+    }
+
+    #[test]
+    fn overlapping_overrides_are_won_by_priority() {
+        let low = [tweak("radius", 1.0), tweak("alpha", 1.0), tweak("scale", 1.0)];
+        let middle = [tweak("radius", 2.0), tweak("alpha", 2.0)];
+        let high = [tweak("radius", 3.0)];
+        let merged = merge(vec![addon("low", &low), addon("middle", &middle), addon("high", &high)]);
+
+        assert_eq!(
+            attribute(&merged.pcfs, "radius"),
+            Some(OverrideValue::Float(3.0).into())
+        );
+        assert_eq!(attribute(&merged.pcfs, "alpha"), Some(OverrideValue::Float(2.0).into()));
+        assert_eq!(attribute(&merged.pcfs, "scale"), Some(OverrideValue::Float(1.0).into()));
+    }
+
+    #[test]
+    fn reordering_addons_changes_the_winner() {
+        let first = [tweak("alpha", 1.0)];
+        let second = [tweak("alpha", 2.0)];
+
+        let merged = merge(vec![addon("first", &first), addon("second", &second)]);
+        assert_eq!(attribute(&merged.pcfs, "alpha"), Some(OverrideValue::Float(2.0).into()));
+
+        let merged = merge(vec![addon("second", &second), addon("first", &first)]);
+        assert_eq!(attribute(&merged.pcfs, "alpha"), Some(OverrideValue::Float(1.0).into()));
+    }
+
+    #[test]
+    fn distinct_systems_are_all_kept() {
+        let mut other = addon("other", &[]);
+        other.pcfs = vec![("other/particles/smoke.pcf".to_string(), pcf_with_system("smoke"))];
+        let merged = merge(vec![addon("fire", &[]), other]);
+
+        assert_eq!(merged.notes, Vec::<String>::new());
+        assert_eq!(merged.pcfs.len(), 2);
+        assert!(merged.pcfs.iter().all(|(_, pcf)| pcf.particle_systems().len() == 1));
+    }
+}
@@ -0,0 +1,237 @@
+//! A basic editor for the numeric, boolean & color attributes of an addon's particle systems. Tweaks are kept as
+//! [`AttributeOverride`]s in the addon's config and applied to a copy of the PCF while installing, so the addon's own
+//! files are never changed.
+
+use eframe::egui::{self, CollapsingHeader, ComboBox, DragValue, ScrollArea};
+use pcf::{AttributeMap, AttributePath, ParticleSystem, Pcf};
+
+use crate::app::{
+    addon_manager::{self, AddonState},
+    config::{AttributeOverride, OverrideValue},
+};
+
+/// The PCF & particle system the user is tweaking.
+#[derive(Debug, Default)]
+pub(crate) struct ParticleTweaker {
+    pcf: Option<String>,
+    system: Option<String>,
+}
+
+pub(crate) fn particle_tweaker(ui: &mut egui::Ui, tweaker: &mut ParticleTweaker, addon_state: &mut AddonState) {
+    let AddonState { addon, overrides, .. } = addon_state;
+
+    let mut particle_files: Vec<_> = addon
+        .particle_files
+        .iter()
+        .map(|(path, pcf)| (addon_manager::relative_pcf_path(addon, path), pcf))
+        .collect();
+    particle_files.sort_by(|(a, _), (b, _)| a.cmp(b));
+
+    ComboBox::from_label("PCF")
+        .selected_text(tweaker.pcf.as_deref().unwrap_or("none selected"))
+        .show_ui(ui, |ui| {
+            for (pcf_path, _) in &particle_files {
+                if ui
+                    .selectable_label(tweaker.pcf.as_ref() == Some(pcf_path), pcf_path)
+                    .clicked()
+                {
+                    tweaker.pcf = Some(pcf_path.clone());
+                    tweaker.system = None;
+                }
+            }
+        });
+
+    let Some((pcf_path, pcf)) = particle_files
+        .iter()
+        .find(|(pcf_path, _)| tweaker.pcf.as_ref() == Some(pcf_path))
+    else {
+        return;
+    };
+
+    ui.add_space(8.0);
+    ui.horizontal_top(|ui| {
+        ScrollArea::vertical()
+            .id_salt("Tweaker Systems")
+            .max_height(400.0)
+            .show(ui, |ui| {
+                ui.set_width(250.0);
+                for system_idx in pcf.root_systems().unwrap_or_default() {
+                    system_tree(ui, &mut tweaker.system, pcf, system_idx, &mut Vec::new());
+                }
+            });
+
+        ui.separator();
+
+        ScrollArea::vertical()
+            .id_salt("Tweaker Attributes")
+            .max_height(400.0)
+            .show(ui, |ui| {
+                let system = tweaker
+                    .system
+                    .as_ref()
+                    .and_then(|name| pcf.particle_systems().iter().find(|system| &system.name == name));
+
+                match system {
+                    Some(system) => system_editor(ui, pcf_path, pcf, system, overrides),
+                    None => {
+                        ui.label("Select a particle system to tweak it.");
+                    }
+                }
+            });
+    });
+}
+
+/// Lists the system at `system_idx` and, indented beneath it, its children. `ancestors` guards against systems which
+/// are their own descendants.
+fn system_tree(
+    ui: &mut egui::Ui,
+    selected: &mut Option<String>,
+    pcf: &Pcf,
+    system_idx: usize,
+    ancestors: &mut Vec<usize>,
+) {
+    let system = &pcf.particle_systems()[system_idx];
+    if ui
+        .selectable_label(selected.as_ref() == Some(&system.name), &system.name)
+        .clicked()
+    {
+        *selected = Some(system.name.clone());
+    }
+
+    if system.children.is_empty() || ancestors.contains(&system_idx) {
+        return;
+    }
+
+    ancestors.push(system_idx);
+    ui.indent((system_idx, ancestors.len()), |ui| {
+        for child in &system.children {
+            system_tree(ui, selected, pcf, usize::from(child.child), ancestors);
+        }
+    });
+    ancestors.pop();
+}
+
+fn system_editor(
+    ui: &mut egui::Ui,
+    pcf_path: &str,
+    pcf: &Pcf,
+    system: &ParticleSystem,
+    overrides: &mut Vec<AttributeOverride>,
+) {
+    let system_path = AttributePath {
+        system: system.name.clone(),
+        operator: None,
+        attribute: String::new(),
+    };
+
+    ui.heading(&system.name);
+    attribute_editor(ui, pcf_path, pcf, &system_path, &system.attributes, overrides);
+
+    let groups = [
+        ("Constraints", &system.constraints),
+        ("Emitters", &system.emitters),
+        ("Forces", &system.forces),
+        ("Initializers", &system.initializers),
+        ("Operators", &system.operators),
+        ("Renderers", &system.renderers),
+    ];
+
+    for (group, operators) in groups {
+        if operators.is_empty() {
+            continue;
+        }
+
+        ui.add_space(8.0);
+        ui.strong(group);
+        for (operator_idx, operator) in operators.iter().enumerate() {
+            CollapsingHeader::new(&operator.name)
+                .id_salt((group, operator_idx))
+                .show(ui, |ui| {
+                    // tweaks find their operator by name, so operators which share a name can't be told apart
+                    let shares_name = system
+                        .all_operators()
+                        .filter(|other| other.name == operator.name)
+                        .count()
+                        > 1;
+
+                    if shares_name {
+                        ui.label("Another operator in this system has the same name, so this one can't be tweaked.");
+                        return;
+                    }
+
+                    let operator_path = AttributePath {
+                        operator: Some(operator.name.clone()),
+                        ..system_path.clone()
+                    };
+
+                    attribute_editor(ui, pcf_path, pcf, &operator_path, &operator.attributes, overrides);
+                });
+        }
+    }
+}
+
+/// Shows a widget for each of `attributes` which can be tweaked. `owner` is the path of the system or operator which
+/// owns them, with an empty attribute name.
+fn attribute_editor(
+    ui: &mut egui::Ui,
+    pcf_path: &str,
+    pcf: &Pcf,
+    owner: &AttributePath,
+    attributes: &AttributeMap,
+    overrides: &mut Vec<AttributeOverride>,
+) {
+    for (name_idx, attribute) in attributes {
+        let Some(original) = OverrideValue::from_attribute(attribute) else {
+            continue;
+        };
+
+        let Some(name) = pcf.symbols().base.get_index(*name_idx as usize) else {
+            continue;
+        };
+
+        let path = AttributePath {
+            attribute: name.to_string(),
+            ..owner.clone()
+        };
+
+        let override_idx = overrides.iter().position(|tweak| tweak.is_at(pcf_path, &path));
+        let mut value = override_idx.map_or(original, |idx| overrides[idx].value);
+
+        ui.horizontal(|ui| {
+            let changed = match &mut value {
+                OverrideValue::Integer(value) => ui.add(DragValue::new(value)).changed(),
+                OverrideValue::Float(value) => ui.add(DragValue::new(value).speed(0.1)).changed(),
+                OverrideValue::Bool(value) => ui.checkbox(value, "").changed(),
+                OverrideValue::Color(color) => ui.color_edit_button_srgba_unmultiplied(color).changed(),
+            };
+
+            if override_idx.is_some() {
+                ui.strong(name);
+            } else {
+                ui.label(name);
+            }
+
+            let reset = ui
+                .add_enabled_ui(override_idx.is_some(), |ui| {
+                    ui.small_button("reset").on_hover_text("Go back to the addon's value")
+                })
+                .inner
+                .clicked();
+
+            match override_idx {
+                Some(idx) if reset || (changed && value == original) => {
+                    overrides.remove(idx);
+                }
+                Some(idx) if changed => overrides[idx].value = value,
+                None if changed => overrides.push(AttributeOverride {
+                    pcf: pcf_path.to_string(),
+                    system: path.system,
+                    operator: path.operator,
+                    attribute: path.attribute,
+                    value,
+                }),
+                _ => {}
+            }
+        });
+    }
+}
@@ -0,0 +1,104 @@
+//! Particle patches the user has put in the patches directory, as `.json` or `.toml` [`PcfPatch`]es. They're applied to
+//! every installed PCF - both the addons' and vanilla - so particles can be tweaked without shipping whole PCFs.
+
+use std::{collections::HashSet, fs, io, path::Path};
+
+use pcf::{Pcf, patch::PcfPatch};
+use thiserror::Error;
+use typed_path::Utf8PlatformPath;
+
+use crate::app::process::ProcessState;
+
+#[derive(Debug)]
+pub(crate) struct Patch {
+    pub file_name: String,
+    pub patch: PcfPatch,
+}
+
+#[derive(Debug, Error)]
+pub(crate) enum PatchFileError {
+    #[error("couldn't read the patch, due to an IO error")]
+    Io(#[from] io::Error),
+
+    #[error("the patch is malformed")]
+    Json(#[from] serde_json::Error),
+
+    #[error("the patch is malformed")]
+    Toml(#[from] toml::de::Error),
+}
+
+/// Reads every patch in `dir`, ordered by file name. Patches which can't be read are returned as errors alongside
+/// their file name, so that one malformed patch doesn't stop the others from applying.
+pub(crate) fn read_dir(dir: &Utf8PlatformPath) -> io::Result<Vec<Result<Patch, (String, PatchFileError)>>> {
+    let mut paths = Vec::new();
+    for entry in fs::read_dir(dir)? {
+        let path = entry?.path();
+        let is_patch = path
+            .extension()
+            .is_some_and(|extension| extension == "json" || extension == "toml");
+
+        if is_patch && path.is_file() {
+            paths.push(path);
+        }
+    }
+
+    paths.sort();
+
+    let patches = paths
+        .into_iter()
+        .map(|path| {
+            let file_name = path.file_name().unwrap_or_default().to_string_lossy().to_string();
+            match read(&path) {
+                Ok(patch) => Ok(Patch { file_name, patch }),
+                Err(err) => Err((file_name, err)),
+            }
+        })
+        .collect();
+
+    Ok(patches)
+}
+
+fn read(path: &Path) -> Result<PcfPatch, PatchFileError> {
+    let contents = fs::read_to_string(path)?;
+    if path.extension().is_some_and(|extension| extension == "toml") {
+        Ok(toml::from_str(&contents)?)
+    } else {
+        Ok(serde_json::from_str(&contents)?)
+    }
+}
+
+/// Applies `patches` to `pcf`, reporting the edits which can't be applied. `targeted` collects the `(patch, edit)`
+/// indices of every edit to a particle system in `pcf`, see [`report_untargeted`].
+pub(crate) fn apply(
+    state: &ProcessState,
+    item: &str,
+    pcf: &mut Pcf,
+    patches: &[Patch],
+    targeted: &mut HashSet<(usize, usize)>,
+) {
+    for (patch_idx, Patch { file_name, patch }) in patches.iter().enumerate() {
+        let report = pcf.apply_patch(patch);
+        for err in &report.errors {
+            state.push_status(format!("{file_name}: skipping an edit to {item}: {err}"));
+        }
+
+        targeted.extend(report.applied.into_iter().map(|edit_idx| (patch_idx, edit_idx)));
+        targeted.extend(report.errors.iter().map(|err| (patch_idx, err.edit)));
+    }
+}
+
+/// Reports the edits in `patches` which weren't in `targeted`, because none of the installed PCFs have their particle
+/// system.
+pub(crate) fn report_untargeted(state: &ProcessState, patches: &[Patch], targeted: &HashSet<(usize, usize)>) {
+    for (patch_idx, Patch { file_name, patch }) in patches.iter().enumerate() {
+        for (edit_idx, edit) in patch.edits.iter().enumerate() {
+            if !targeted.contains(&(patch_idx, edit_idx)) {
+                state.push_status(format!(
+                    "{file_name}: edit {} wasn't applied, since no installed particle system is named '{}'",
+                    edit_idx + 1,
+                    edit.system
+                ));
+            }
+        }
+    }
+}
@@ -0,0 +1,165 @@
+//! Particle previews, which export one addon's particles - as they'd be installed, with the user's selection, tweaks &
+//! patches applied - as loose PCFs in `tf/custom/`[`PREVIEW_DIR`]. Nothing else is installed and no VPKs are touched,
+//! so a single addon can be tried in game much faster than by installing everything. Only one addon is previewed at a
+//! time, and the preview is left in place until the user removes it.
+
+use std::{
+    collections::HashSet,
+    fs,
+    io::{self, ErrorKind},
+    task::Poll,
+};
+
+use dmx::Dmx;
+use eframe::egui;
+use typed_path::{Utf8PlatformPath, Utf8PlatformPathBuf};
+
+use crate::app::{
+    App, Crashed, HandleState, ManagingAddons, ManagingAddonsState, Paths, State,
+    addon_manager::{self, AddonState},
+    config::Config,
+    patches,
+    process::ProcessState,
+    provenance::Manifest,
+    strip_stage::StripStage,
+    task::Task,
+};
+
+/// The folder in `tf/custom/` which previews are exported to.
+pub(crate) const PREVIEW_DIR: &str = "_dazzle_preview";
+
+/// The preview folder for the TF2 installation in `tf_dir`.
+pub(crate) fn preview_dir(tf_dir: &Utf8PlatformPath) -> Utf8PlatformPathBuf {
+    tf_dir.join("custom").join(PREVIEW_DIR)
+}
+
+/// Removes the preview from the TF2 installation in `tf_dir`. Returns false if there wasn't one.
+///
+/// # Errors
+///
+/// Returns [`Err`] if the preview couldn't be removed.
+pub(crate) fn remove(tf_dir: &Utf8PlatformPath) -> io::Result<bool> {
+    match fs::remove_dir_all(preview_dir(tf_dir)) {
+        Ok(()) => Ok(true),
+        Err(err) if err.kind() == ErrorKind::NotFound => Ok(false),
+        Err(err) => Err(err),
+    }
+}
+
+/// The addons, handed back for the addon manager, and the number of PCFs exported or why the preview failed.
+pub(crate) type AddonPreviewJob = Task<(Vec<AddonState>, anyhow::Result<usize>)>;
+
+/// Exports the particles of the addon at `addon_idx` in `addons` as a preview, replacing any previous preview.
+pub(crate) fn start_addon_preview(
+    ctx: &egui::Context,
+    paths: &Paths,
+    config: &Config,
+    addons: Vec<AddonState>,
+    addon_idx: usize,
+) -> AddonPreviewJob {
+    let patches_dir = paths.patches.clone();
+    let tf_dir = config.tf_dir.clone();
+
+    Task::spawn_with_spinner(ctx, move |state| {
+        let result = export(&state, &patches_dir, &tf_dir, &addons[addon_idx]);
+        state.push_status("Done!");
+        (addons, result)
+    })
+}
+
+fn export(
+    state: &ProcessState,
+    patches_dir: &Utf8PlatformPath,
+    tf_dir: &Utf8PlatformPath,
+    addon_state: &AddonState,
+) -> anyhow::Result<usize> {
+    state.push_status("Removing the previous preview");
+    remove(tf_dir)?;
+
+    state.push_status("Reading particle patches");
+    let mut patches = Vec::new();
+    for patch in patches::read_dir(patches_dir)? {
+        match patch {
+            Ok(patch) => patches.push(patch),
+            Err((file_name, err)) => state.push_status(format!(
+                "Skipping the patch {file_name}, since it couldn't be read: {:#}",
+                anyhow::Error::from(err)
+            )),
+        }
+    }
+
+    let preview_dir = preview_dir(tf_dir);
+    let pcfs = addon_manager::merged_pcfs(state, addon_state, &patches, &mut HashSet::new());
+    state.begin_stage("Exporting particles", pcfs.len(), 0);
+    for (_, relative_path, pcf) in &pcfs {
+        state.push_status(format!("Exporting {relative_path}"));
+
+        // the engine expects parent systems to come before their children
+        let pcf = pcf.clone().topologically_sorted(true)?;
+        let size = pcf.encoded_size();
+        let dmx: Dmx = pcf.into();
+
+        let mut buffer = Vec::with_capacity(size);
+        dmx.encode_sized(&mut buffer, size)?;
+
+        let path = preview_dir.join_checked(relative_path)?;
+        if let Some(parent) = path.parent() {
+            fs::create_dir_all(parent)?;
+        }
+
+        fs::write(path, buffer)?;
+        state.advance_stage(1, 0);
+    }
+
+    Manifest::new(&[&addon_state.addon], StripStage::None, Vec::new())?.write(&preview_dir)?;
+
+    Ok(pcfs.len())
+}
+
+#[derive(Debug)]
+pub(crate) struct Previewing {
+    config: Config,
+    addon_name: String,
+    task: AddonPreviewJob,
+}
+
+impl Previewing {
+    pub fn new(config: Config, addons: Vec<AddonState>, addon_idx: usize, ctx: &egui::Context, app: &App) -> Self {
+        let addon_name = addons[addon_idx].addon.name().to_string();
+        let task = start_addon_preview(ctx, &app.paths, &config, addons, addon_idx);
+
+        Self {
+            config,
+            addon_name,
+            task,
+        }
+    }
+}
+
+impl HandleState for Previewing {
+    fn handle(mut self, ui: &mut egui::Ui, _app: &mut App) -> State {
+        self.task.show("exporting a particle preview", ui.ctx());
+        let Poll::Ready(result) = self.task.poll() else {
+            return self.into();
+        };
+
+        let Ok((addons, result)) = result else {
+            return Crashed::new("exporting a particle preview").into();
+        };
+
+        let message = match result {
+            Ok(count) => format!(
+                "Exported {count} particle files from '{}' to tf/custom/{PREVIEW_DIR}. Restart TF2 to try them out, \
+                 and remove the preview once you're done.",
+                self.addon_name
+            ),
+            Err(err) => format!("The preview couldn't be exported: {err:#}"),
+        };
+
+        ManagingAddons {
+            state: ManagingAddonsState::ShowingMessage(message),
+            ..ManagingAddons::new(self.config, addons)
+        }
+        .into()
+    }
+}
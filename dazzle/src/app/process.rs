@@ -6,7 +6,108 @@ use eframe::egui::{
 };
 use std::num::NonZero;
 use std::rc::Rc;
+use std::sync::atomic::{AtomicBool, Ordering};
 use std::sync::{Arc, mpmc, mpsc};
+use std::time::{Duration, Instant};
+
+use crate::app::metrics::StageClock;
+
+/// A structured progress update sent from a worker thread to its [`ProcessView`].
+#[derive(Debug, Clone)]
+pub(crate) enum ProgressEvent {
+    /// A new stage has started. `items` & `bytes` are the amount of work in the stage, or 0 if it isn't known.
+    Stage { name: String, items: usize, bytes: u64 },
+
+    /// Some of the current stage's work has been done.
+    Advance { items: usize, bytes: u64 },
+}
+
+#[derive(Debug, Clone)]
+struct StageProgress {
+    name: String,
+    items_done: usize,
+    items_total: usize,
+    bytes_done: u64,
+    bytes_total: u64,
+    started: Instant,
+}
+
+impl StageProgress {
+    /// The ETA is too noisy to be useful until the stage has been running for a little while.
+    const ETA_WARMUP: Duration = Duration::from_secs(2);
+
+    /// How much of the stage is done, preferring bytes over items since items can vary wildly in size.
+    #[allow(clippy::cast_precision_loss)]
+    fn fraction(&self) -> Option<f32> {
+        if self.bytes_total > 0 {
+            Some(f32::clamp(self.bytes_done as f32 / self.bytes_total as f32, 0.0, 1.0))
+        } else if self.items_total > 0 {
+            Some(f32::clamp(self.items_done as f32 / self.items_total as f32, 0.0, 1.0))
+        } else {
+            None
+        }
+    }
+
+    /// A rough estimate of the time left in the stage, assuming the rest of the work goes as fast as it has so far.
+    fn eta(&self) -> Option<Duration> {
+        let elapsed = self.started.elapsed();
+        let fraction = self.fraction()?;
+        if elapsed < Self::ETA_WARMUP || fraction <= 0.0 || fraction >= 1.0 {
+            return None;
+        }
+
+        Some(elapsed.mul_f32((1.0 - fraction) / fraction))
+    }
+
+    fn apply(stage: &mut Option<Self>, event: ProgressEvent) {
+        match event {
+            ProgressEvent::Stage { name, items, bytes } => {
+                *stage = Some(Self {
+                    name,
+                    items_done: 0,
+                    items_total: items,
+                    bytes_done: 0,
+                    bytes_total: bytes,
+                    started: Instant::now(),
+                });
+            }
+            ProgressEvent::Advance { items, bytes } => {
+                if let Some(stage) = stage {
+                    stage.items_done += items;
+                    stage.bytes_done += bytes;
+                }
+            }
+        }
+    }
+
+    fn summary(&self) -> String {
+        const MB: u64 = 1024 * 1024;
+
+        let mut summary = self.name.clone();
+        if self.items_total > 0 {
+            summary += &format!(" - {}/{}", self.items_done.min(self.items_total), self.items_total);
+        }
+
+        if self.bytes_total > 0 {
+            summary += &format!(
+                " - {}/{} MB",
+                self.bytes_done.min(self.bytes_total) / MB,
+                self.bytes_total.div_ceil(MB)
+            );
+        }
+
+        if let Some(eta) = self.eta() {
+            let seconds = eta.as_secs() + 1;
+            if seconds < 60 {
+                summary += &format!(" - about {seconds}s left");
+            } else {
+                summary += &format!(" - about {}m left", seconds.div_ceil(60));
+            }
+        }
+
+        summary
+    }
+}
 
 #[derive(Clone, Debug)]
 pub(crate) struct ProcessView {
@@ -15,12 +116,32 @@ pub(crate) struct ProcessView {
     pub(crate) completed: Arc<RelaxedCounter>,
     pub(crate) status_receiver: Rc<mpsc::Receiver<String>>,
 
+    stage: Option<StageProgress>,
+    progress_receiver: Rc<mpsc::Receiver<ProgressEvent>>,
+
+    /// the worker's stage timings, see [`ProcessView::stage_clock`]
+    stage_clock: StageClock,
+
+    /// whether the user can cancel the process from its window
+    pub(crate) cancellable: bool,
+    cancelled: Arc<AtomicBool>,
+
     last_request: Option<ProcessConfirmation>,
     confirm_request_receiver: Rc<mpsc::Receiver<ProcessConfirmation>>,
     confirm_result_sender: Rc<mpmc::Sender<usize>>,
 }
 
 impl ProcessView {
+    /// The timings of the worker's stages, which are shared with the worker as it runs.
+    pub(crate) fn stage_clock(&self) -> &StageClock {
+        &self.stage_clock
+    }
+
+    /// Asks the worker to stop, which it does once it next checks [`ProcessState::is_cancelled`].
+    pub(crate) fn cancel(&self) {
+        self.cancelled.store(true, Ordering::Relaxed);
+    }
+
     pub(crate) fn flush_statuses(&self) -> Option<String> {
         self.status_receiver.try_iter().last()
     }
@@ -56,6 +177,37 @@ impl ProcessView {
             ui.add(ProgressBar::new(progress).animate(true).show_percentage());
         }
 
+        for event in self.progress_receiver.try_iter() {
+            StageProgress::apply(&mut self.stage, event);
+        }
+
+        if let Some(stage) = &self.stage {
+            ui.add_space(8.0);
+            ui.label(stage.summary());
+            if let Some(fraction) = stage.fraction() {
+                ui.add(ProgressBar::new(fraction).desired_height(4.0));
+            }
+
+            // keep the ETA ticking even when the worker hasn't sent anything new
+            ui.ctx().request_repaint_after(Duration::from_secs(1));
+        }
+
+        if self.cancellable {
+            ui.add_space(8.0);
+            let cancelled = self.cancelled.load(Ordering::Relaxed);
+            ui.vertical_centered(|ui| {
+                if ui
+                    .add_enabled(
+                        !cancelled,
+                        egui::Button::new(if cancelled { "Cancelling…" } else { "Cancel" }),
+                    )
+                    .clicked()
+                {
+                    self.cancel();
+                }
+            });
+        }
+
         if let Some(request) = self.flush_confirm_requests() {
             self.last_request = Some(request);
         }
@@ -108,23 +260,30 @@ pub(crate) struct ProcessConfirmation {
 pub(crate) struct ProcessState {
     pub(crate) ctx: egui::Context,
     pub(crate) status_sender: mpsc::Sender<String>,
+    pub(crate) progress_sender: mpsc::Sender<ProgressEvent>,
     pub(crate) confirm_request_sender: mpsc::Sender<ProcessConfirmation>,
     pub(crate) confirm_result_receiver: Arc<mpmc::Receiver<usize>>,
     pub(crate) completed: Arc<RelaxedCounter>,
+    stage_clock: StageClock,
+    cancelled: Arc<AtomicBool>,
 }
 
 impl ProcessState {
     fn new(ctx: &egui::Context, steps: usize) -> (Self, ProcessView) {
         let (status_sender, status_receiver) = mpsc::channel();
+        let (progress_sender, progress_receiver) = mpsc::channel();
         let (confirm_request_sender, confirm_request_receiver) = mpsc::channel();
         let (confirm_result_sender, confirm_result_receiver) = mpmc::channel();
 
         let op = Self {
             ctx: ctx.clone(),
             status_sender,
+            progress_sender,
             confirm_request_sender,
             confirm_result_receiver: Arc::new(confirm_result_receiver),
             completed: Arc::new(RelaxedCounter::new(0)),
+            stage_clock: StageClock::new(),
+            cancelled: Arc::new(AtomicBool::new(false)),
         };
 
         let view = ProcessView {
@@ -132,6 +291,11 @@ impl ProcessState {
             latest_status: String::new(),
             completed: op.completed.clone(),
             status_receiver: Rc::new(status_receiver),
+            stage: None,
+            progress_receiver: Rc::new(progress_receiver),
+            stage_clock: op.stage_clock.clone(),
+            cancellable: false,
+            cancelled: op.cancelled.clone(),
             confirm_request_receiver: Rc::new(confirm_request_receiver),
             confirm_result_sender: Rc::new(confirm_result_sender),
             last_request: None,
@@ -148,11 +312,36 @@ impl ProcessState {
         Self::new(ctx, steps.into())
     }
 
+    /// Whether the user asked the process to stop. Cancellable jobs should check this between units of work, and
+    /// return early with whatever they've finished so far.
+    pub(crate) fn is_cancelled(&self) -> bool {
+        self.cancelled.load(Ordering::Relaxed)
+    }
+
     pub(crate) fn push_status(&self, status: impl Into<String>) {
         self.status_sender.send(status.into()).unwrap();
         self.ctx.request_repaint();
     }
 
+    /// Starts a new stage, replacing the previous one. `items` & `bytes` are the amount of work in the stage, or 0 if
+    /// it isn't known.
+    pub(crate) fn begin_stage(&self, name: impl Into<String>, items: usize, bytes: u64) {
+        let name = name.into();
+        self.stage_clock.begin(name.clone());
+        self.send_progress(ProgressEvent::Stage { name, items, bytes });
+    }
+
+    /// Records that `items` & `bytes` of the current stage's work have been done.
+    pub(crate) fn advance_stage(&self, items: usize, bytes: u64) {
+        self.send_progress(ProgressEvent::Advance { items, bytes });
+    }
+
+    fn send_progress(&self, event: ProgressEvent) {
+        // the view may have been dropped if the user moved on while the worker finishes up
+        _ = self.progress_sender.send(event);
+        self.ctx.request_repaint();
+    }
+
     pub(crate) fn increment_progress(&self) {
         self.completed.inc();
         self.ctx.request_repaint();
@@ -0,0 +1,193 @@
+//! Shareable `.dazzleprofile` files, which describe a user's addon setup - which addons are enabled, and in which
+//! order - without any of the addons' content.
+//!
+//! Importing a profile applies it to the addons the user already has. Each addon in the profile is matched to a local
+//! addon with the same hash, or failing that, the same name; so renamed copies of the same addon are still found.
+
+use std::{fs, io};
+
+use serde::{Deserialize, Serialize};
+use thiserror::Error;
+use typed_path::Utf8PlatformPath;
+
+use crate::app::{addon_manager::AddonState, provenance::addon_hash};
+
+pub(crate) const PROFILE_EXTENSION: &str = "dazzleprofile";
+
+const PROFILE_VERSION: u32 = 1;
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub(crate) struct Profile {
+    pub version: u32,
+    pub dazzle_version: String,
+
+    /// every addon, from the highest to the lowest priority
+    pub addons: Vec<ProfileAddon>,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub(crate) struct ProfileAddon {
+    pub name: String,
+
+    /// see [`addon_hash`]
+    pub hash: String,
+
+    pub enabled: bool,
+}
+
+#[derive(Debug, Error)]
+pub(crate) enum ProfileError {
+    #[error("couldn't read or write the profile, due to an IO error")]
+    Io(#[from] io::Error),
+
+    #[error("the profile is malformed")]
+    Json(#[from] serde_json::Error),
+
+    #[error("the profile was made with a newer version of dazzle, which uses profile version {0}")]
+    UnsupportedVersion(u32),
+}
+
+impl Profile {
+    /// Describes `addons`, which should be in priority order.
+    pub(crate) fn from_addons(addons: &[AddonState]) -> io::Result<Self> {
+        let addons = addons
+            .iter()
+            .map(|addon_state| {
+                Ok(ProfileAddon {
+                    name: addon_state.addon.name().to_string(),
+                    hash: addon_hash(&addon_state.addon)?,
+                    enabled: addon_state.enabled,
+                })
+            })
+            .collect::<io::Result<_>>()?;
+
+        Ok(Self {
+            version: PROFILE_VERSION,
+            dazzle_version: env!("CARGO_PKG_VERSION").to_string(),
+            addons,
+        })
+    }
+
+    pub(crate) fn read(path: &Utf8PlatformPath) -> Result<Self, ProfileError> {
+        let profile: Self = serde_json::from_str(&fs::read_to_string(path)?)?;
+        if profile.version > PROFILE_VERSION {
+            return Err(ProfileError::UnsupportedVersion(profile.version));
+        }
+
+        Ok(profile)
+    }
+
+    pub(crate) fn write(&self, path: &Utf8PlatformPath) -> Result<(), ProfileError> {
+        fs::write(path, serde_json::to_string_pretty(self)?)?;
+        Ok(())
+    }
+
+    /// Reorders & enables `addons` to match the profile. Addons that aren't in the profile come last, and are
+    /// disabled.
+    ///
+    /// Returns the names of addons in the profile which didn't match any of `addons`. If `addons` couldn't be hashed,
+    /// they're left untouched.
+    pub(crate) fn apply(&self, addons: &mut Vec<AddonState>) -> io::Result<Vec<String>> {
+        let local = addons
+            .iter()
+            .map(|addon_state| Ok((addon_state.addon.name().to_string(), addon_hash(&addon_state.addon)?)))
+            .collect::<io::Result<Vec<_>>>()?;
+
+        let matches = match_addons(&self.addons, &local);
+
+        let mut unordered: Vec<_> = addons.drain(..).map(Some).collect();
+        let mut missing = Vec::new();
+        for (profile_addon, local_idx) in self.addons.iter().zip(matches) {
+            match local_idx.and_then(|local_idx| unordered[local_idx].take()) {
+                Some(mut addon_state) => {
+                    addon_state.enabled = profile_addon.enabled;
+                    addons.push(addon_state);
+                }
+                None => missing.push(profile_addon.name.clone()),
+            }
+        }
+
+        for mut addon_state in unordered.into_iter().flatten() {
+            addon_state.enabled = false;
+            addons.push(addon_state);
+        }
+
+        Ok(missing)
+    }
+}
+
+/// Matches each of `profile`'s addons to the index of a `(name, hash)` in `local`. Hashes are matched first, then
+/// names, ignoring case; and each local addon is matched at most once.
+fn match_addons(profile: &[ProfileAddon], local: &[(String, String)]) -> Vec<Option<usize>> {
+    let mut matched = vec![false; local.len()];
+    let mut matches = vec![None; profile.len()];
+
+    for (profile_idx, profile_addon) in profile.iter().enumerate() {
+        if let Some(local_idx) = (0..local.len()).find(|&idx| !matched[idx] && local[idx].1 == profile_addon.hash) {
+            matched[local_idx] = true;
+            matches[profile_idx] = Some(local_idx);
+        }
+    }
+
+    for (profile_idx, profile_addon) in profile.iter().enumerate() {
+        if matches[profile_idx].is_some() {
+            continue;
+        }
+
+        if let Some(local_idx) =
+            (0..local.len()).find(|&idx| !matched[idx] && local[idx].0.eq_ignore_ascii_case(&profile_addon.name))
+        {
+            matched[local_idx] = true;
+            matches[profile_idx] = Some(local_idx);
+        }
+    }
+
+    matches
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{ProfileAddon, match_addons};
+
+    fn profile_addon(name: &str, hash: &str) -> ProfileAddon {
+        ProfileAddon {
+            name: name.to_string(),
+            hash: hash.to_string(),
+            enabled: true,
+        }
+    }
+
+    fn local(addons: &[(&str, &str)]) -> Vec<(String, String)> {
+        addons
+            .iter()
+            .map(|(name, hash)| (name.to_string(), hash.to_string()))
+            .collect()
+    }
+
+    #[test]
+    fn hashes_match_before_names() {
+        let profile = [profile_addon("fire.vpk", "aaaa"), profile_addon("smoke.vpk", "bbbb")];
+        let local = local(&[("smoke.vpk", "cccc"), ("renamed fire.vpk", "aaaa")]);
+
+        assert_eq!(match_addons(&profile, &local), vec![Some(1), Some(0)]);
+    }
+
+    #[test]
+    fn names_match_ignoring_case() {
+        let profile = [profile_addon("Fire.vpk", "aaaa")];
+        let local = local(&[("fire.VPK", "bbbb")]);
+
+        assert_eq!(match_addons(&profile, &local), vec![Some(0)]);
+    }
+
+    #[test]
+    fn local_addons_match_at_most_once() {
+        let profile = [
+            profile_addon("fire.vpk", "aaaa"),
+            profile_addon("fire copy.vpk", "aaaa"),
+        ];
+        let local = local(&[("fire.vpk", "aaaa")]);
+
+        assert_eq!(match_addons(&profile, &local), vec![Some(0), None]);
+    }
+}
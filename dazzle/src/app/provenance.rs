@@ -0,0 +1,259 @@
+//! Provenance metadata embedded in every VPK dazzle generates, at [`MANIFEST_ENTRY`]. It records what was installed
+//! and how, so existing installs can be recognized & described, and so a user's install can be debugged from the VPK
+//! alone.
+
+use std::{
+    collections::BTreeMap,
+    fmt::Write as _,
+    fs::{self, File},
+    io::{self, Read},
+};
+
+use addon::Addon;
+use md5::{Digest, Md5};
+use ordermap::OrderMap;
+use serde::{Deserialize, Serialize};
+use typed_path::{Utf8PlatformPath, Utf8PlatformPathBuf};
+use vpk::VPK;
+use walkdir::WalkDir;
+use writevpk::pack::GeneratedFile;
+
+use crate::app::strip_stage::StripStage;
+
+/// The path of the manifest entry inside every VPK generated by dazzle.
+pub(crate) const MANIFEST_ENTRY: &str = "dazzle/manifest.json";
+
+/// Describes an install. It deliberately has no timestamp, so that installing the same addons always generates the same
+/// VPK; when an install happened can be read from the VPK's modification time instead.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub(crate) struct Manifest {
+    pub dazzle_version: String,
+
+    /// every installed addon, in the order they were installed. Later addons override earlier ones.
+    pub addons: Vec<ManifestAddon>,
+
+    /// how the particles were stripped to fit into the vanilla particle budget
+    pub particle_stripping: String,
+
+    /// every file provided by more than one addon, and which addon's file was installed
+    pub conflicts: Vec<Conflict>,
+
+    /// every PCF which was patched into `tf2_misc_dir.vpk`, so that the patches can be checked later. Manifests from
+    /// before this was recorded don't have it.
+    #[serde(default)]
+    pub patched_particles: Vec<PatchedParticle>,
+
+    /// the bin each particle system was packed into, so that the next install can keep it there. Manifests from before
+    /// this was recorded don't have it.
+    #[serde(default)]
+    pub bin_assignments: BTreeMap<String, String>,
+
+    /// every packed file whose contents are identical to an earlier file's, and that file, whose packed copy it shares.
+    /// Manifests from before this was recorded don't have it.
+    #[serde(default)]
+    pub deduplicated_files: BTreeMap<String, String>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub(crate) struct ManifestAddon {
+    pub name: String,
+
+    /// see [`addon_hash`]
+    pub hash: String,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub(crate) struct Conflict {
+    /// the conflicting file, relative to the game directory
+    pub path: String,
+
+    /// the addon whose file was installed
+    pub winner: String,
+
+    /// the addons whose file was overridden by `winner`
+    pub overridden: Vec<String>,
+}
+
+/// A PCF which was patched into `tf2_misc_dir.vpk`, and where.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub(crate) struct PatchedParticle {
+    /// the PCF's path in the VPK
+    pub name: String,
+
+    pub archive_index: u16,
+    pub archive_offset: u32,
+
+    /// the number of bytes that were patched in, from the start of the entry
+    pub size: u64,
+
+    /// the MD5 digest of the patched bytes, as lowercase hex
+    pub hash: String,
+}
+
+impl PatchedParticle {
+    /// Describes `data` being patched over the entry at `name` in `vpk`, or `None` if there's no such entry.
+    pub(crate) fn new(vpk: &VPK, name: &str, data: &[u8]) -> Option<Self> {
+        let entry = vpk.tree.get(name)?;
+        Some(Self {
+            name: name.to_string(),
+            archive_index: entry.dir_entry.archive_index,
+            archive_offset: entry.dir_entry.archive_offset,
+            size: data.len() as u64,
+            hash: hex_digest(Md5::new_with_prefix(data)),
+        })
+    }
+}
+
+impl Manifest {
+    /// Describes an install of `addons`, in the order they're installed, which patched `patched_particles` into
+    /// `tf2_misc_dir.vpk`.
+    pub(crate) fn new(
+        addons: &[&Addon],
+        strip_stage: StripStage,
+        patched_particles: Vec<PatchedParticle>,
+    ) -> anyhow::Result<Self> {
+        let mut manifest_addons = Vec::with_capacity(addons.len());
+        for addon in addons {
+            manifest_addons.push(ManifestAddon {
+                name: addon.name().to_string(),
+                hash: addon_hash(addon)?,
+            });
+        }
+
+        Ok(Self {
+            dazzle_version: env!("CARGO_PKG_VERSION").to_string(),
+            addons: manifest_addons,
+            particle_stripping: strip_stage.to_string(),
+            conflicts: find_conflicts(addons)?,
+            patched_particles,
+            bin_assignments: BTreeMap::new(),
+            deduplicated_files: BTreeMap::new(),
+        })
+    }
+
+    /// Writes the manifest into `working_vpk_dir`, so it's packed into the generated VPK.
+    pub(crate) fn write(&self, working_vpk_dir: &Utf8PlatformPath) -> anyhow::Result<()> {
+        let manifest_path = working_vpk_dir.join(MANIFEST_ENTRY);
+        if let Some(parent) = manifest_path.parent() {
+            fs::create_dir_all(parent)?;
+        }
+
+        fs::write(manifest_path, serde_json::to_string_pretty(self)?)?;
+        Ok(())
+    }
+
+    /// Packs the manifest as it's generated, once every other file has been packed, so it can record which of them
+    /// were duplicates.
+    pub(crate) fn into_packed(mut self) -> GeneratedFile<'static> {
+        GeneratedFile {
+            path: MANIFEST_ENTRY,
+            generate: Box::new(move |duplicates| {
+                self.deduplicated_files = duplicates
+                    .iter()
+                    .map(|duplicate| (duplicate.path.clone(), duplicate.original.clone()))
+                    .collect();
+
+                serde_json::to_vec_pretty(&self).expect("the manifest should always serialize")
+            }),
+        }
+    }
+}
+
+/// Reads the manifest of the addons currently installed in `tf_custom_dir`, packed into the VPK named `vpk_name`.
+///
+/// Returns `None` if there's no such VPK, or if it predates the manifest.
+pub(crate) fn read_installed(tf_custom_dir: &Utf8PlatformPath, vpk_name: &str) -> anyhow::Result<Option<Manifest>> {
+    let Some(vpk_path) = find_installed_vpk(tf_custom_dir, vpk_name) else {
+        return Ok(None);
+    };
+
+    read_manifest(&VPK::read(&vpk_path)?)
+}
+
+/// Finds the VPK named `vpk_name` in `tf_custom_dir`. Split VPKs are opened by their index, and small installs may
+/// have been packed into a single VPK.
+pub(crate) fn find_installed_vpk(tf_custom_dir: &Utf8PlatformPath, vpk_name: &str) -> Option<Utf8PlatformPathBuf> {
+    [format!("{vpk_name}_dir.vpk"), format!("{vpk_name}.vpk")]
+        .into_iter()
+        .map(|name| tf_custom_dir.join(name))
+        .find(|path| fs::metadata(path).is_ok_and(|metadata| metadata.is_file()))
+}
+
+/// Reads the manifest in `vpk`, or `None` if it predates the manifest.
+pub(crate) fn read_manifest(vpk: &VPK) -> anyhow::Result<Option<Manifest>> {
+    let Some(entry) = vpk.tree.get(MANIFEST_ENTRY) else {
+        return Ok(None);
+    };
+
+    let mut contents = String::new();
+    entry.reader()?.read_to_string(&mut contents)?;
+    Ok(Some(serde_json::from_str(&contents)?))
+}
+
+/// Finds every file provided by more than one of `addons`, in the order they're installed. The last addon providing a
+/// file is the one whose file is installed.
+pub(crate) fn find_conflicts(addons: &[&Addon]) -> anyhow::Result<Vec<Conflict>> {
+    let mut providers: OrderMap<String, Vec<String>> = OrderMap::new();
+    for addon in addons {
+        // the game's filesystem is case-insensitive, so paths differing only in case still conflict
+        for path in addon.content_files()? {
+            providers
+                .entry(path.into_string())
+                .or_default()
+                .push(addon.name().to_string());
+        }
+    }
+
+    let conflicts = providers
+        .into_iter()
+        .filter(|(_, providers)| providers.len() > 1)
+        .map(|(path, mut overridden)| {
+            let winner = overridden.pop().unwrap();
+            Conflict {
+                path,
+                winner,
+                overridden,
+            }
+        })
+        .collect();
+
+    Ok(conflicts)
+}
+
+/// Hashes `addon`'s source, as a lowercase hex MD5 digest. A VPK source is hashed as-is, and a folder source is hashed
+/// by its file paths & contents, so the same addon has the same hash on every machine.
+pub(crate) fn addon_hash(addon: &Addon) -> io::Result<String> {
+    source_hash(&addon.source_path)
+}
+
+/// Hashes the addon source at `source_path`, see [`addon_hash`].
+pub(crate) fn source_hash(source_path: &Utf8PlatformPath) -> io::Result<String> {
+    let mut hasher = Md5::new();
+
+    if fs::metadata(source_path)?.is_file() {
+        io::copy(&mut File::open(source_path)?, &mut hasher)?;
+    } else {
+        for entry in WalkDir::new(source_path).sort_by_file_name() {
+            let entry = entry?;
+            if !entry.file_type().is_file() {
+                continue;
+            }
+
+            let relative_path = entry.path().strip_prefix(source_path).map_err(io::Error::other)?;
+            hasher.update(relative_path.to_string_lossy().replace('\\', "/").as_bytes());
+            io::copy(&mut File::open(entry.path())?, &mut hasher)?;
+        }
+    }
+
+    Ok(hex_digest(hasher))
+}
+
+/// The digest of `hasher`, as lowercase hex.
+pub(crate) fn hex_digest(hasher: Md5) -> String {
+    let mut hash = String::new();
+    for byte in hasher.finalize() {
+        write!(hash, "{byte:02x}").unwrap();
+    }
+
+    hash
+}
@@ -0,0 +1,292 @@
+//! Searching the addon manager by addon & particle system name. Every loaded addon's particle systems are indexed in
+//! lowercase once, when the manager is opened, so that filtering thousands of systems as the user types stays
+//! responsive.
+//!
+//! A query with a glob wildcard - `*`, `?` or `[...]` - has to match a whole name, e.g. `explosion_*`. Any other query
+//! matches every name containing it. Neither cares about case, in any language.
+
+use std::collections::HashSet;
+
+use eframe::egui::{self, TextEdit};
+use glob::Pattern;
+
+use crate::app::addon_manager::{AddonState, relative_pcf_path};
+
+#[derive(Debug, Clone, PartialEq)]
+pub(crate) enum SearchQuery {
+    /// Matches everything.
+    Everything,
+
+    /// Matches names containing the lowercase string.
+    Substring(String),
+
+    /// Matches whole names, against a lowercase pattern.
+    Glob(Pattern),
+}
+
+impl SearchQuery {
+    /// Parses what the user typed. A query which isn't a valid glob pattern, like `[fire`, is searched for as a
+    /// substring instead.
+    pub(crate) fn parse(text: &str) -> Self {
+        let text = text.trim().to_lowercase();
+        if text.is_empty() {
+            Self::Everything
+        } else if text.contains(['*', '?', '['])
+            && let Ok(pattern) = Pattern::new(&text)
+        {
+            Self::Glob(pattern)
+        } else {
+            Self::Substring(text)
+        }
+    }
+
+    /// Whether the query matches `name`, which has to already be lowercase.
+    fn matches(&self, name: &str) -> bool {
+        match self {
+            Self::Everything => true,
+            Self::Substring(text) => name.contains(text.as_str()),
+            Self::Glob(pattern) => pattern.matches(name),
+        }
+    }
+}
+
+#[derive(Debug)]
+struct IndexedSystem {
+    /// the index of the system's addon in [`SearchIndex::addons`]
+    addon: usize,
+
+    /// the system's PCF, relative to the addon's content
+    pcf: String,
+
+    name: String,
+    lowercase: String,
+}
+
+/// Every addon & particle system name, in lowercase.
+#[derive(Debug, Default)]
+pub(crate) struct SearchIndex {
+    /// every `(name, lowercase)` addon name
+    addons: Vec<(String, String)>,
+
+    systems: Vec<IndexedSystem>,
+}
+
+impl SearchIndex {
+    pub(crate) fn new(addons: &[AddonState]) -> Self {
+        let mut index = Self::default();
+        for AddonState { addon, .. } in addons {
+            let addon_idx = index.add_addon(addon.name());
+            for (path, pcf) in &addon.particle_files {
+                let pcf_path = relative_pcf_path(addon, path);
+                for system in pcf.particle_systems() {
+                    index.add_system(addon_idx, &pcf_path, &system.name);
+                }
+            }
+        }
+
+        index
+    }
+
+    fn add_addon(&mut self, name: &str) -> usize {
+        self.addons.push((name.to_string(), name.to_lowercase()));
+        self.addons.len() - 1
+    }
+
+    fn add_system(&mut self, addon: usize, pcf: &str, name: &str) {
+        self.systems.push(IndexedSystem {
+            addon,
+            pcf: pcf.to_string(),
+            name: name.to_string(),
+            lowercase: name.to_lowercase(),
+        });
+    }
+
+    pub(crate) fn search(&self, query: &SearchQuery) -> SearchMatches {
+        if *query == SearchQuery::Everything {
+            return SearchMatches::default();
+        }
+
+        let mut matches = SearchMatches {
+            filtered: true,
+            ..SearchMatches::default()
+        };
+
+        let mut matched_addons = vec![false; self.addons.len()];
+        for (idx, (name, lowercase)) in self.addons.iter().enumerate() {
+            if query.matches(lowercase) {
+                matched_addons[idx] = true;
+                matches.addons.insert(name.clone());
+            }
+        }
+
+        for system in &self.systems {
+            if matched_addons[system.addon] || query.matches(&system.lowercase) {
+                let addon = &self.addons[system.addon].0;
+                matches.addons.insert(addon.clone());
+                matches
+                    .systems
+                    .insert((addon.clone(), system.pcf.clone(), system.name.clone()));
+            }
+        }
+
+        matches
+    }
+}
+
+/// What a [`SearchQuery`] matched. An addon whose own name matched has all of its systems shown.
+#[derive(Debug, Default, Clone)]
+pub(crate) struct SearchMatches {
+    /// whether anything was searched for; when nothing was, everything is shown
+    filtered: bool,
+
+    /// every addon which matched, or which has a system that matched, by name
+    addons: HashSet<String>,
+
+    /// every `(addon, pcf, system)` which matched
+    systems: HashSet<(String, String, String)>,
+}
+
+impl SearchMatches {
+    pub(crate) fn is_filtered(&self) -> bool {
+        self.filtered
+    }
+
+    pub(crate) fn is_addon_shown(&self, addon: &str) -> bool {
+        !self.filtered || self.addons.contains(addon)
+    }
+
+    pub(crate) fn is_system_shown(&self, addon: &str, pcf: &str, system: &str) -> bool {
+        !self.filtered
+            || self
+                .systems
+                .contains(&(addon.to_string(), pcf.to_string(), system.to_string()))
+    }
+
+    /// The number of particle systems which matched.
+    pub(crate) fn system_count(&self) -> usize {
+        self.systems.len()
+    }
+}
+
+/// The addon manager's search box, and what it matches.
+#[derive(Debug, Default)]
+pub(crate) struct AddonSearch {
+    text: String,
+    index: SearchIndex,
+    matches: SearchMatches,
+}
+
+impl AddonSearch {
+    pub(crate) fn new(addons: &[AddonState]) -> Self {
+        Self {
+            index: SearchIndex::new(addons),
+            ..Self::default()
+        }
+    }
+
+    pub(crate) fn matches(&self) -> &SearchMatches {
+        &self.matches
+    }
+
+    /// Shows the search box, searching again whenever its text is changed.
+    pub(crate) fn search_box(&mut self, ui: &mut egui::Ui) {
+        ui.horizontal(|ui| {
+            ui.label("🔍");
+            let changed = TextEdit::singleline(&mut self.text)
+                .hint_text("Search addons & particle systems, e.g. flame or explosion_*")
+                .desired_width(300.0)
+                .show(ui)
+                .response
+                .changed();
+
+            let cleared = ui
+                .add_enabled(!self.text.is_empty(), egui::Button::new("clear"))
+                .clicked();
+            if cleared {
+                self.text.clear();
+            }
+
+            if changed || cleared {
+                self.matches = self.index.search(&SearchQuery::parse(&self.text));
+            }
+
+            if self.matches.is_filtered() {
+                ui.label(format!(
+                    "{} addons, {} particle systems",
+                    self.matches.addons.len(),
+                    self.matches.system_count()
+                ));
+            }
+        });
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{SearchIndex, SearchQuery};
+
+    fn index() -> SearchIndex {
+        let mut index = SearchIndex::default();
+        let explosions = index.add_addon("Big Explosions");
+        index.add_system(explosions, "particles/explosion.pcf", "explosion_huge");
+        index.add_system(explosions, "particles/explosion.pcf", "explosion_small");
+        let effects = index.add_addon("Effets spéciaux");
+        index.add_system(effects, "particles/fire.pcf", "Flamme_Éclair");
+        index.add_system(effects, "particles/fire.pcf", "smoke_trail");
+        index
+    }
+
+    #[test]
+    fn queries_are_parsed_by_their_wildcards() {
+        assert_eq!(SearchQuery::parse("  "), SearchQuery::Everything);
+        assert_eq!(SearchQuery::parse("Fire"), SearchQuery::Substring("fire".to_string()));
+        assert!(matches!(SearchQuery::parse("explosion_*"), SearchQuery::Glob(_)));
+
+        // an unclosed class isn't a valid pattern
+        assert_eq!(SearchQuery::parse("[fire"), SearchQuery::Substring("[fire".to_string()));
+    }
+
+    #[test]
+    fn substrings_match_systems_ignoring_case() {
+        let matches = index().search(&SearchQuery::parse("ÉCLAIR"));
+
+        assert!(matches.is_addon_shown("Effets spéciaux"));
+        assert!(!matches.is_addon_shown("Big Explosions"));
+        assert!(matches.is_system_shown("Effets spéciaux", "particles/fire.pcf", "Flamme_Éclair"));
+        assert!(!matches.is_system_shown("Effets spéciaux", "particles/fire.pcf", "smoke_trail"));
+        assert_eq!(matches.system_count(), 1);
+    }
+
+    #[test]
+    fn globs_match_whole_names() {
+        let matches = index().search(&SearchQuery::parse("explosion_*"));
+        assert_eq!(matches.system_count(), 2);
+
+        let matches = index().search(&SearchQuery::parse("*_s?all"));
+        assert!(matches.is_system_shown("Big Explosions", "particles/explosion.pcf", "explosion_small"));
+        assert_eq!(matches.system_count(), 1);
+
+        let matches = index().search(&SearchQuery::parse("explosion"));
+        assert_eq!(matches.system_count(), 2);
+        let matches = index().search(&SearchQuery::parse("explosion?"));
+        assert_eq!(matches.system_count(), 0);
+    }
+
+    #[test]
+    fn matching_addon_names_show_all_their_systems() {
+        let matches = index().search(&SearchQuery::parse("spéciaux"));
+
+        assert!(matches.is_addon_shown("Effets spéciaux"));
+        assert!(matches.is_system_shown("Effets spéciaux", "particles/fire.pcf", "smoke_trail"));
+        assert_eq!(matches.system_count(), 2);
+    }
+
+    #[test]
+    fn empty_queries_show_everything() {
+        let matches = index().search(&SearchQuery::parse(""));
+
+        assert!(!matches.is_filtered());
+        assert!(matches.is_addon_shown("anything"));
+        assert!(matches.is_system_shown("anything", "particles/any.pcf", "any"));
+    }
+}
@@ -0,0 +1,435 @@
+//! The first-run setup wizard. Each step is its own [`State`], and the wizard always runs in this order:
+//!
+//! 1. [`Welcome`]
+//! 2. [`ConfiguringTfDir`], pre-filled with the TF2 installation found in the user's Steam libraries
+//! 3. [`ChoosingImport`], where the user may import addons from cueki's preloader or any other folder
+//! 4. [`SetupSummary`]
+//! 5. [`ImportingAddons`], if the user chose any addons to import
+
+use std::{fs, io, task::Poll};
+
+use eframe::egui::{self, Align2, RichText, ScrollArea, Sides, Vec2b, Window};
+use typed_path::{Utf8PlatformPath, Utf8PlatformPathBuf};
+
+use crate::{
+    app::{
+        App, ConfiguringTfDir, Crashed, HandleState, InitialLoad, Paths, State,
+        config::{self, Config},
+        cueki::CuekiInstall,
+        get_default_platform_tf_dir, steam,
+        task::Task,
+    },
+    styles,
+};
+
+fn wizard_window(title: &str) -> Window<'static> {
+    Window::new(title)
+        .collapsible(false)
+        .resizable(false)
+        .anchor(Align2::CENTER_CENTER, (0.0, 0.0))
+        .max_width(600.0)
+        .scroll(Vec2b::FALSE)
+}
+
+#[derive(Debug)]
+pub(crate) struct Welcome {
+    config: Config,
+}
+
+impl Welcome {
+    pub fn new(config: Config) -> Self {
+        Self { config }
+    }
+}
+
+impl HandleState for Welcome {
+    fn handle(self, ui: &mut egui::Ui, _app: &mut App) -> State {
+        let mut get_started = false;
+        wizard_window("Welcome").show(ui.ctx(), |ui| {
+            ui.horizontal(|ui| {
+                ui.strong(RichText::new("This is dazzle").text_style(styles::big()));
+                ui.add_space(8.0);
+                ui.label(RichText::new(" - a mod installer for Team Fortress 2.").text_style(styles::big()))
+            });
+
+            ui.add_space(24.0);
+
+            ui.label(
+                RichText::new("Dazzle will process many kinds of mods & install them into your Team Fortress 2 installation. Mods installed by dazzle will typically work in Casual and other sv_pure servers!")
+                    .text_style(styles::big()),
+            );
+
+            ui.add_space(16.0);
+
+            ui.label(
+                RichText::new("Before you get started, dazzle needs to find your TF2 installation. You'll also have the option to import addons you've been using with another preloader.")
+                    .text_style(styles::big()),
+            );
+
+            ui.add_space(16.0);
+
+            ui.vertical_centered(|ui| {
+                if ui.button("Get started").clicked() {
+                    get_started = true;
+                }
+            });
+        });
+
+        if get_started {
+            let tf_dir =
+                steam::locate_tf_dir().map_or_else(get_default_platform_tf_dir, Utf8PlatformPathBuf::into_string);
+            ConfiguringTfDir::first_run(self.config, tf_dir).into()
+        } else {
+            self.into()
+        }
+    }
+}
+
+/// What the user chose to import during the setup wizard.
+#[derive(Debug, Default)]
+pub(crate) struct Import {
+    addons: Vec<Utf8PlatformPathBuf>,
+
+    /// The cueki installation whose backups should be restored before dazzle takes over, if any.
+    restore_cueki_backups: Option<CuekiInstall>,
+}
+
+impl Import {
+    fn is_empty(&self) -> bool {
+        self.addons.is_empty() && self.restore_cueki_backups.is_none()
+    }
+}
+
+#[derive(Debug)]
+pub(crate) struct ChoosingImport {
+    config: Config,
+    import_dir: Option<Utf8PlatformPathBuf>,
+    imports: Vec<Utf8PlatformPathBuf>,
+    cueki: Option<CuekiInstall>,
+    migrate_cueki_selections: bool,
+    restore_cueki_backups: bool,
+}
+
+impl ChoosingImport {
+    pub fn new(config: Config) -> Self {
+        Self {
+            config,
+            import_dir: None,
+            imports: Vec::new(),
+            cueki: None,
+            migrate_cueki_selections: true,
+            restore_cueki_backups: true,
+        }
+    }
+
+    fn select_import_dir(&mut self, import_dir: Utf8PlatformPathBuf) {
+        self.imports = find_importable_addons(&import_dir);
+        self.cueki = match CuekiInstall::detect(&import_dir) {
+            Ok(cueki) => cueki,
+            Err(err) => {
+                // TODO: present errors to the user as a modal
+                eprintln!("There was an error reading cueki's preloader data in {import_dir}: {err}");
+                None
+            }
+        };
+
+        self.restore_cueki_backups = self.cueki.as_ref().is_some_and(|cueki| cueki.backup_dir.is_some());
+        self.import_dir = Some(import_dir);
+    }
+}
+
+impl HandleState for ChoosingImport {
+    fn handle(mut self, ui: &mut egui::Ui, _app: &mut App) -> State {
+        let mut skip = false;
+        let mut next = false;
+        wizard_window("Import addons").show(ui.ctx(), |ui| {
+            ui.label(
+                RichText::new("If you've been using cueki's casual preloader, dazzle can import the addons from it. Pick the preloader's folder, or any folder containing addon VPKs or addon folders:")
+                    .text_style(styles::big()),
+            );
+
+            ui.add_space(16.0);
+
+            ui.group(|ui| {
+                ui.horizontal(|ui| {
+                    match &self.import_dir {
+                        Some(import_dir) => ui.monospace(import_dir.as_str()),
+                        None => ui.weak("no folder selected"),
+                    };
+
+                    if ui.button("Browse").clicked()
+                        && let Some(selected_path) = rfd::FileDialog::new().pick_folder()
+                    {
+                        self.select_import_dir(paths::std_buf_to_typed(selected_path));
+                    }
+                })
+            });
+
+            if self.import_dir.is_some() {
+                ui.add_space(8.0);
+                if self.imports.is_empty() {
+                    ui.strong("Couldn't find any addons in the selected folder.");
+                } else {
+                    ui.strong(format!("Found {} addons to import:", self.imports.len()));
+                    ScrollArea::vertical().max_height(160.0).show(ui, |ui| {
+                        for import in &self.imports {
+                            ui.monospace(import.file_name().unwrap_or_default());
+                        }
+                    });
+                }
+            }
+
+            if let Some(cueki) = &self.cueki {
+                ui.add_space(8.0);
+                ui.group(|ui| {
+                    ui.take_available_width();
+                    ui.strong("This looks like an installation of cueki's casual preloader.");
+                    ui.checkbox(
+                        &mut self.migrate_cueki_selections,
+                        format!(
+                            "Keep the {} addons enabled in cueki's preloader enabled, in the same order",
+                            cueki.enabled_addons.len()
+                        ),
+                    );
+                    ui.add_enabled_ui(cueki.backup_dir.is_some(), |ui| {
+                        ui.checkbox(
+                            &mut self.restore_cueki_backups,
+                            "Restore the game files cueki's preloader patched, so dazzle can take over",
+                        );
+                    });
+                });
+            }
+
+            ui.add_space(16.0);
+
+            Sides::new().show(
+                ui,
+                |_ui| {},
+                |ui| {
+                    ui.add_enabled_ui(!self.imports.is_empty(), |ui| {
+                        if ui.button("Import these").clicked() {
+                            next = true;
+                        }
+                    });
+
+                    if ui.button("Skip").clicked() {
+                        skip = true;
+                    }
+                },
+            );
+        });
+
+        if skip {
+            SetupSummary::new(self.config, Import::default()).into()
+        } else if next {
+            if let Some(cueki) = &self.cueki
+                && self.migrate_cueki_selections
+            {
+                cueki.migrate_addon_selections(&mut self.config);
+            }
+
+            let import = Import {
+                addons: self.imports,
+                restore_cueki_backups: self.cueki.filter(|_| self.restore_cueki_backups),
+            };
+
+            SetupSummary::new(self.config, import).into()
+        } else {
+            self.into()
+        }
+    }
+}
+
+/// Cueki's preloader keeps its addons in `mods/addons/`, so we look there first. Otherwise we treat the folder itself
+/// as a folder of addons.
+fn find_importable_addons(dir: &Utf8PlatformPath) -> Vec<Utf8PlatformPathBuf> {
+    let cueki_addons_dir = dir.join("mods").join("addons");
+    let dir = if fs::metadata(&cueki_addons_dir).is_ok_and(|metadata| metadata.is_dir()) {
+        cueki_addons_dir
+    } else {
+        dir.to_path_buf()
+    };
+
+    let Ok(entries) = fs::read_dir(&dir) else {
+        return Vec::new();
+    };
+
+    let mut imports: Vec<_> = entries
+        .filter_map(Result::ok)
+        .filter(|entry| {
+            let path = entry.path();
+            path.is_dir()
+                || path
+                    .extension()
+                    .is_some_and(|extension| extension.eq_ignore_ascii_case("vpk"))
+        })
+        .map(|entry| paths::std_buf_to_typed(entry.path()))
+        .collect();
+
+    imports.sort();
+    imports
+}
+
+#[derive(Debug)]
+pub(crate) struct SetupSummary {
+    config: Config,
+    import: Import,
+}
+
+impl SetupSummary {
+    pub fn new(config: Config, import: Import) -> Self {
+        Self { config, import }
+    }
+}
+
+impl HandleState for SetupSummary {
+    fn handle(self, ui: &mut egui::Ui, app: &mut App) -> State {
+        let mut back = false;
+        let mut finish = false;
+        wizard_window("All set!").show(ui.ctx(), |ui| {
+            ui.label(RichText::new("Dazzle will install your mods into:").text_style(styles::big()));
+            ui.monospace(self.config.tf_dir.as_str());
+
+            ui.add_space(16.0);
+
+            if self.import.addons.is_empty() {
+                ui.label(RichText::new("No addons will be imported.").text_style(styles::big()));
+            } else {
+                ui.label(
+                    RichText::new(format!(
+                        "{} addons will be copied into dazzle's addons folder.",
+                        self.import.addons.len()
+                    ))
+                    .text_style(styles::big()),
+                );
+            }
+
+            if self.import.restore_cueki_backups.is_some() {
+                ui.label(
+                    RichText::new("The game files patched by cueki's preloader will be restored.")
+                        .text_style(styles::big()),
+                );
+            }
+
+            ui.add_space(16.0);
+
+            Sides::new().show(
+                ui,
+                |ui| {
+                    if ui.button("Back").clicked() {
+                        back = true;
+                    }
+                },
+                |ui| {
+                    if ui.button("Finish").clicked() {
+                        finish = true;
+                    }
+                },
+            );
+        });
+
+        if back {
+            ChoosingImport::new(self.config).into()
+        } else if finish {
+            // TODO: present errors to the user as a modal
+            config::write_config(&app.paths.config, &self.config).unwrap();
+
+            if self.import.is_empty() {
+                InitialLoad::new(self.config, ui.ctx(), &app.paths).into()
+            } else {
+                ImportingAddons::new(self.config, self.import, ui.ctx(), &app.paths).into()
+            }
+        } else {
+            self.into()
+        }
+    }
+}
+
+pub type ImportAddonsJob = Task<Vec<(Utf8PlatformPathBuf, io::Error)>>;
+
+#[derive(Debug)]
+pub(crate) struct ImportingAddons {
+    config: Config,
+    task: ImportAddonsJob,
+}
+
+impl ImportingAddons {
+    pub fn new(config: Config, import: Import, ctx: &egui::Context, paths: &Paths) -> Self {
+        let task = start_addon_import(ctx, paths, &config.tf_dir, import);
+
+        Self { config, task }
+    }
+}
+
+impl HandleState for ImportingAddons {
+    fn handle(mut self, ui: &mut egui::Ui, app: &mut App) -> State {
+        self.task.show("importing addons", ui.ctx());
+
+        let Poll::Ready(result) = self.task.poll() else {
+            return self.into();
+        };
+
+        let Ok(errors) = result else {
+            return Crashed::new("importing addons").into();
+        };
+
+        // TODO: present job errors to the user as a modal
+        for (path, err) in errors {
+            eprintln!("There was an error importing {path}: {err}");
+        }
+
+        InitialLoad::new(self.config, ui.ctx(), &app.paths).into()
+    }
+}
+
+fn start_addon_import(
+    ctx: &egui::Context,
+    paths: &Paths,
+    tf_dir: &Utf8PlatformPath,
+    import: Import,
+) -> ImportAddonsJob {
+    let addons_dir = paths.addons.clone();
+    let tf_dir = tf_dir.to_path_buf();
+    let steps = import.addons.len() + usize::from(import.restore_cueki_backups.is_some());
+    Task::spawn_with_progress_bar(ctx, steps.try_into().unwrap(), move |state| {
+        let mut errors = Vec::new();
+
+        // cueki's backups need to be restored before anything else touches the game files
+        if let Some(cueki) = import.restore_cueki_backups {
+            state.push_status("Restoring files patched by cueki's preloader");
+            match cueki.restore_backups(&tf_dir) {
+                Ok(restored) => eprintln!("Restored {restored} files from {}", cueki.root),
+                Err(err) => errors.push((cueki.root, io::Error::other(err))),
+            }
+
+            state.increment_progress();
+        }
+
+        for import in import.addons {
+            let Some(name) = import.file_name() else {
+                state.increment_progress();
+                continue;
+            };
+
+            state.push_status(format!("Importing {name}"));
+
+            let target = addons_dir.join(name);
+            if fs::exists(&target).unwrap_or_default() {
+                // an addon with the same name has already been added, so we don't want to clobber it
+                errors.push((import, io::Error::from(io::ErrorKind::AlreadyExists)));
+            } else if fs::metadata(&import).is_ok_and(|metadata| metadata.is_dir()) {
+                match copy_dir::copy_dir(&*paths::long_path(&import), &*paths::long_path(&target)) {
+                    Ok(copy_errors) => errors.extend(copy_errors.into_iter().map(|err| (import.clone(), err))),
+                    Err(err) => errors.push((import, err)),
+                }
+            } else if let Err(err) = fs::copy(&*paths::long_path(&import), &*paths::long_path(&target)) {
+                errors.push((import, err));
+            }
+
+            state.increment_progress();
+        }
+
+        state.push_status("Done!");
+        errors
+    })
+}
@@ -0,0 +1,206 @@
+//! The staging area an install is generated into before anything in the game is changed. The generated particles are
+//! written to the staging dir, and everything packed into `_dazzle_addons.vpk` - the addons' content, generated VMTs &
+//! the provenance manifest - to the working VPK dir. A [`StagingManifest`] listing them is written alongside. The VPK
+//! is then packed into [`STAGED_VPK_DIR`] in `tf/custom/`, which is on the same disk as the game so its files can be
+//! renamed into place.
+//!
+//! Only once everything has been generated & packed is it committed into the game: every staged file is checked
+//! against the manifest, the particles are patched in, and the packed VPK's files are renamed over the previous ones. A
+//! failure while generating or packing leaves the game as it was. Committing only writes the particles & renames files,
+//! so it's short, but it isn't atomic; a failure part-way through it is fixed by installing again.
+//!
+//! Everything committed into the game is synced to disk as it's written, see [`write_synced`], so that a power loss
+//! right after an install can't leave the game with half-written files.
+
+use std::{
+    fs::{self, File},
+    io::{self, ErrorKind, Write},
+};
+
+use anyhow::anyhow;
+use md5::{Digest, Md5};
+use paths::GamePath;
+use serde::{Deserialize, Serialize};
+use typed_path::{Utf8PlatformPath, Utf8PlatformPathBuf};
+
+use crate::app::{config, provenance};
+
+/// The name of the manifest in the staging dir.
+pub(crate) const STAGING_MANIFEST: &str = "staging.json";
+
+/// The directory in `tf/custom/` the VPK is packed into, before it's committed.
+pub(crate) const STAGED_VPK_DIR: &str = "_dazzle_staging";
+
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub(crate) struct StagingManifest {
+    /// the directory holding everything packed into `_dazzle_addons.vpk`
+    pub vpk_content: String,
+
+    /// every generated particle, which is written over the vanilla particle with the same name
+    pub particles: Vec<StagedFile>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub(crate) struct StagedFile {
+    /// the file's game path, e.g. `particles/fire.pcf`, which is also where it's staged in the staging dir
+    pub name: String,
+
+    pub size: u64,
+
+    /// the MD5 digest of the file, as lowercase hex
+    pub hash: String,
+}
+
+#[derive(Debug)]
+pub(crate) struct Staging {
+    dir: Utf8PlatformPathBuf,
+    vpk_dir: Utf8PlatformPathBuf,
+    manifest: StagingManifest,
+}
+
+impl Staging {
+    /// Creates an empty staging area in `dir`, and an empty [`STAGED_VPK_DIR`] in `tf_custom_dir`, removing whatever a
+    /// previous install left there. `vpk_content` is where the content of `_dazzle_addons.vpk` is generated.
+    pub(crate) fn create(
+        dir: &Utf8PlatformPath,
+        tf_custom_dir: &Utf8PlatformPath,
+        vpk_content: &Utf8PlatformPath,
+    ) -> io::Result<Self> {
+        let vpk_dir = tf_custom_dir.join(STAGED_VPK_DIR);
+        remove_dir(dir)?;
+        remove_dir(&vpk_dir)?;
+        fs::create_dir_all(dir)?;
+        fs::create_dir_all(&vpk_dir)?;
+
+        Ok(Self {
+            dir: dir.to_path_buf(),
+            vpk_dir,
+            manifest: StagingManifest {
+                vpk_content: vpk_content.to_string(),
+                particles: Vec::new(),
+            },
+        })
+    }
+
+    /// Stages `data` to be written over the vanilla particle `name`.
+    pub(crate) fn stage_particle(&mut self, name: &str, data: &[u8]) -> io::Result<()> {
+        let path = GamePath::new(name).to_platform_path(&self.dir);
+        if let Some(parent) = path.parent() {
+            fs::create_dir_all(parent)?;
+        }
+
+        fs::write(&path, data)?;
+        self.manifest.particles.push(StagedFile {
+            name: name.to_string(),
+            size: data.len() as u64,
+            hash: provenance::hex_digest(Md5::new_with_prefix(data)),
+        });
+
+        Ok(())
+    }
+
+    /// Writes the [`StagingManifest`], once everything has been staged.
+    pub(crate) fn write_manifest(&self) -> anyhow::Result<()> {
+        let contents = serde_json::to_string_pretty(&self.manifest)?;
+        fs::write(self.dir.join(STAGING_MANIFEST), contents)?;
+        Ok(())
+    }
+
+    /// Reads every staged particle, checking that it's still what was staged. Nothing is committed unless every file
+    /// is intact, so this is done before the game is changed.
+    ///
+    /// # Errors
+    ///
+    /// Returns [`Err`] if any staged file is missing, or differs from the manifest.
+    pub(crate) fn read_particles(&self) -> anyhow::Result<Vec<(String, Vec<u8>)>> {
+        let mut particles = Vec::with_capacity(self.manifest.particles.len());
+        for staged in &self.manifest.particles {
+            let data = fs::read(GamePath::new(&staged.name).to_platform_path(&self.dir))?;
+            if data.len() as u64 != staged.size || provenance::hex_digest(Md5::new_with_prefix(&data)) != staged.hash {
+                return Err(anyhow!("the staged {} changed after it was generated", staged.name));
+            }
+
+            particles.push((staged.name.clone(), data));
+        }
+
+        Ok(particles)
+    }
+
+    /// The directory the VPK is packed into.
+    pub(crate) fn vpk_dir(&self) -> &Utf8PlatformPath {
+        &self.vpk_dir
+    }
+
+    /// Links every file of the VPK `vpk_name` in `tf_custom_dir` into the [`vpk_dir`](Self::vpk_dir), so an
+    /// incremental build can reuse its archives without changing them. Files are copied instead if they can't be
+    /// hard-linked. The incremental build only ever replaces these files, and never writes into them.
+    pub(crate) fn link_previous_vpk(&self, tf_custom_dir: &Utf8PlatformPath, vpk_name: &str) -> io::Result<()> {
+        for entry in fs::read_dir(tf_custom_dir)? {
+            let entry = entry?;
+            let Ok(file_name) = entry.file_name().into_string() else {
+                continue;
+            };
+
+            if !config::is_output_vpk(&file_name, vpk_name) || !entry.metadata()?.is_file() {
+                continue;
+            }
+
+            let source = tf_custom_dir.join(&file_name);
+            let dest = self.vpk_dir.join(&file_name);
+            if fs::hard_link(&source, &dest).is_err() {
+                fs::copy(&source, &dest)?;
+            }
+        }
+
+        Ok(())
+    }
+
+    /// Renames every file packed into the [`vpk_dir`](Self::vpk_dir) into `tf_custom_dir`, replacing any file with the
+    /// same name, and returns their names. The dir file is renamed last, so the VPK is only complete once every
+    /// archive it references is in place.
+    pub(crate) fn commit_vpk(&self, tf_custom_dir: &Utf8PlatformPath) -> io::Result<Vec<String>> {
+        let mut file_names = Vec::new();
+        for entry in fs::read_dir(&self.vpk_dir)? {
+            let Ok(file_name) = entry?.file_name().into_string() else {
+                continue;
+            };
+
+            file_names.push(file_name);
+        }
+
+        // archives before the dir file, or the single-file VPK
+        file_names.sort_by_key(|file_name| file_name.ends_with("_dir.vpk"));
+        for file_name in &file_names {
+            fs::rename(self.vpk_dir.join(file_name), tf_custom_dir.join(file_name))?;
+        }
+
+        writevpk::sync_dir(tf_custom_dir)?;
+        Ok(file_names)
+    }
+
+    /// Removes the staging area, once it's been committed.
+    pub(crate) fn remove(self) -> io::Result<()> {
+        remove_dir(&self.vpk_dir)?;
+        remove_dir(&self.dir)
+    }
+}
+
+/// Writes `contents` to `path` like [`fs::write`], then syncs the file & its parent directory to disk.
+pub(crate) fn write_synced(path: &Utf8PlatformPath, contents: impl AsRef<[u8]>) -> io::Result<()> {
+    let mut file = File::create(path)?;
+    file.write_all(contents.as_ref())?;
+    file.sync_all()?;
+
+    match path.parent() {
+        Some(parent) => writevpk::sync_dir(parent),
+        None => Ok(()),
+    }
+}
+
+/// Removes `dir` & everything in it, if it exists.
+pub(crate) fn remove_dir(dir: &Utf8PlatformPath) -> io::Result<()> {
+    match fs::remove_dir_all(dir) {
+        Err(err) if err.kind() == ErrorKind::NotFound => Ok(()),
+        result => result,
+    }
+}
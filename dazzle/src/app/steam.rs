@@ -0,0 +1,153 @@
+use std::{env, fs, io, process::Command};
+
+use typed_path::{Utf8PlatformPath, Utf8PlatformPathBuf};
+
+use crate::app::tf_dir_picker;
+
+/// TF2's Steam app id.
+pub(crate) const TF2_APP_ID: u32 = 440;
+
+/// Asks Steam to verify TF2's game files, which restores any that are missing or were modified. Steam shows its own
+/// progress, and is started first if it isn't running.
+///
+/// # Errors
+///
+/// Returns [`Err`] if the `steam://` link couldn't be opened.
+pub(crate) fn validate_tf2() -> io::Result<()> {
+    open_link(&format!("steam://validate/{TF2_APP_ID}"))
+}
+
+#[cfg(target_os = "windows")]
+fn open_link(link: &str) -> io::Result<()> {
+    Command::new("explorer").arg(link).spawn().map(drop)
+}
+
+#[cfg(target_os = "linux")]
+fn open_link(link: &str) -> io::Result<()> {
+    Command::new("xdg-open").arg(link).spawn().map(drop)
+}
+
+/// Searches every Steam library known to the user's Steam installation for a valid `Team Fortress 2/tf` directory.
+///
+/// Returns `None` if Steam couldn't be found, or if none of its libraries contain a valid TF2 installation.
+pub(crate) fn locate_tf_dir() -> Option<Utf8PlatformPathBuf> {
+    steam_roots()
+        .iter()
+        .flat_map(|root| library_folders(root))
+        .map(|library| {
+            let mut path = library;
+            path.extend(["steamapps", "common", "Team Fortress 2", "tf"]);
+            path
+        })
+        .find(|tf_dir| tf_dir_picker::validate(tf_dir).is_ok())
+}
+
+/// All of the Steam library folders listed in `root`'s `libraryfolders.vdf`, including `root` itself.
+fn library_folders(root: &Utf8PlatformPath) -> Vec<Utf8PlatformPathBuf> {
+    let mut folders = vec![root.to_path_buf()];
+
+    let Ok(vdf) = fs::read_to_string(root.join("steamapps").join("libraryfolders.vdf")) else {
+        return folders;
+    };
+
+    let Ok(root_kv) = keyvalues_parser::parse(&vdf) else {
+        return folders;
+    };
+
+    let Some(libraries) = root_kv.value.get_obj() else {
+        return folders;
+    };
+
+    // each library is an object keyed by its index, e.g. "0" { "path" "..." "apps" { ... } }
+    for library in libraries.values().flatten() {
+        let Some(library) = library.get_obj() else {
+            continue;
+        };
+
+        let Some(path) = library
+            .get("path")
+            .and_then(|values| values.first())
+            .and_then(keyvalues_parser::Value::get_str)
+        else {
+            continue;
+        };
+
+        // libraryfolders.vdf escapes the backslashes in windows paths
+        let path = Utf8PlatformPathBuf::from(path.replace(r"\\", r"\"));
+        if !folders.contains(&path) {
+            folders.push(path);
+        }
+    }
+
+    folders
+}
+
+#[cfg(target_os = "windows")]
+fn steam_roots() -> Vec<Utf8PlatformPathBuf> {
+    ["PROGRAMFILES(X86)", "PROGRAMFILES"]
+        .into_iter()
+        .filter_map(|var| env::var(var).ok())
+        .map(|programfiles| Utf8PlatformPathBuf::from(programfiles).join("Steam"))
+        .filter(|root| fs::metadata(root).is_ok_and(|metadata| metadata.is_dir()))
+        .collect()
+}
+
+#[cfg(target_os = "linux")]
+fn steam_roots() -> Vec<Utf8PlatformPathBuf> {
+    let Ok(home) = env::var("HOME") else {
+        return Vec::new();
+    };
+
+    let home = Utf8PlatformPathBuf::from(home);
+    [
+        // native installs
+        home.join(".local/share/Steam"),
+        home.join(".steam/steam"),
+        home.join(".steam/root"),
+        // flatpak installs
+        home.join(".var/app/com.valvesoftware.Steam/.local/share/Steam"),
+    ]
+    .into_iter()
+    .filter(|root| fs::metadata(root).is_ok_and(|metadata| metadata.is_dir()))
+    .collect()
+}
+
+/// Whether the Steam client is currently running.
+///
+/// Steam records the pid of its running client in `HKCU\Software\Valve\Steam\ActiveProcess`, and resets it to 0 when
+/// it exits.
+#[cfg(target_os = "windows")]
+pub(crate) fn is_running() -> bool {
+    let Ok(output) = Command::new("reg")
+        .args([r"query", r"HKCU\Software\Valve\Steam\ActiveProcess", "/v", "pid"])
+        .output()
+    else {
+        return false;
+    };
+
+    // the value is listed as e.g. "    pid    REG_DWORD    0x1a2b"
+    String::from_utf8_lossy(&output.stdout)
+        .split_whitespace()
+        .last()
+        .and_then(|pid| u32::from_str_radix(pid.trim_start_matches("0x"), 16).ok())
+        .is_some_and(|pid| pid != 0)
+}
+
+/// Whether the Steam client is currently running.
+///
+/// Steam writes the pid of its running client to `~/.steam/steam.pid`, but doesn't remove it when it exits, so the pid
+/// is only trusted if a process with that pid still exists.
+#[cfg(target_os = "linux")]
+pub(crate) fn is_running() -> bool {
+    let Ok(home) = env::var("HOME") else {
+        return false;
+    };
+
+    let Ok(pid) = fs::read_to_string(Utf8PlatformPathBuf::from(home).join(".steam/steam.pid")) else {
+        return false;
+    };
+
+    pid.trim()
+        .parse::<u32>()
+        .is_ok_and(|pid| fs::metadata(format!("/proc/{pid}")).is_ok())
+}
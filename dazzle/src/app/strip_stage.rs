@@ -0,0 +1,77 @@
+//! When the enabled particle addons don't fit into the vanilla particle budget, the installer strips the particles
+//! progressively harder and tries packing them again. Each [`StripStage`] also applies every stage before it.
+
+use std::fmt;
+
+use pcf::{
+    Comparison, Pcf,
+    stats::{StripCounts, StripStats},
+};
+
+use crate::strip_defaults::{get_default_operator_map, get_particle_system_defaults};
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord)]
+pub(crate) enum StripStage {
+    /// The particles are packed as-is.
+    None,
+
+    /// Attributes which are set to their default value are removed.
+    Defaults,
+
+    /// Symbols which are no longer referenced by any element or attribute are removed.
+    UnusedSymbols,
+
+    /// The names of operators & child references are removed. These are only shown in the particle editor.
+    EditorMetadata,
+}
+
+impl StripStage {
+    /// Every stage, from the least to the most aggressive.
+    pub(crate) const ALL: [Self; 4] = [Self::None, Self::Defaults, Self::UnusedSymbols, Self::EditorMetadata];
+
+    /// Strips `pcf` with this stage, and every stage before it.
+    pub(crate) fn apply(self, pcf: Pcf) -> Pcf {
+        self.apply_with_stats(pcf).0
+    }
+
+    /// Like [`StripStage::apply`], but also returns what every stage stripped.
+    pub(crate) fn apply_with_stats(self, pcf: Pcf) -> (Pcf, StripStats) {
+        let mut stats = StripStats::new(StripCounts::of(&pcf), &pcf);
+        let mut pcf = pcf;
+        if self >= Self::Defaults {
+            let (stripped, defaults) = pcf.defaults_stripped_nth_with_stats(
+                usize::MAX,
+                &get_particle_system_defaults(),
+                &get_default_operator_map(),
+                Comparison::Exact,
+            );
+            pcf = stripped;
+            stats = stats.then(defaults);
+        }
+
+        if self >= Self::UnusedSymbols {
+            let (stripped, symbols) = pcf.unused_symbols_stripped_with_stats();
+            pcf = stripped;
+            stats = stats.then(symbols);
+        }
+
+        if self >= Self::EditorMetadata {
+            let (stripped, names) = pcf.stripped_with_stats(Pcf::editor_names_stripped);
+            pcf = stripped;
+            stats = stats.then(names);
+        }
+
+        (pcf, stats)
+    }
+}
+
+impl fmt::Display for StripStage {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            StripStage::None => write!(f, "no stripping"),
+            StripStage::Defaults => write!(f, "stripping default attribute values"),
+            StripStage::UnusedSymbols => write!(f, "stripping unused symbols"),
+            StripStage::EditorMetadata => write!(f, "stripping editor-only names"),
+        }
+    }
+}
@@ -0,0 +1,165 @@
+use std::{
+    num::NonZero,
+    panic::{self, AssertUnwindSafe},
+    sync::mpsc::{self, TryRecvError},
+    task::Poll,
+    thread,
+};
+
+use eframe::egui::{self, WidgetText};
+
+use crate::app::process::{ProcessState, ProcessView};
+
+/// A job running on a worker thread, along with the [`ProcessView`] which shows its progress.
+///
+/// The job is handed the [`ProcessState`] to report its progress with, and to check whether it was cancelled. Its
+/// progress & result are sent back over channels, and each one requests a repaint, so the UI notices them even while
+/// it's otherwise idle.
+#[derive(Debug)]
+pub(crate) struct Task<T> {
+    view: ProcessView,
+
+    /// where the worker sends its result, which is `None` once the result has been taken by [`Task::poll`]
+    result_receiver: Option<mpsc::Receiver<thread::Result<T>>>,
+}
+
+impl<T: Send + 'static> Task<T> {
+    /// Runs `job` on a worker thread, showing a spinner with its latest status while it runs.
+    pub(crate) fn spawn_with_spinner(
+        ctx: &egui::Context,
+        job: impl FnOnce(ProcessState) -> T + Send + 'static,
+    ) -> Self {
+        let (state, view) = ProcessState::with_spinner(ctx);
+        Self::spawn(state, view, job)
+    }
+
+    /// Runs `job` on a worker thread, showing a progress bar which is full once `steps` steps have been completed.
+    pub(crate) fn spawn_with_progress_bar(
+        ctx: &egui::Context,
+        steps: NonZero<usize>,
+        job: impl FnOnce(ProcessState) -> T + Send + 'static,
+    ) -> Self {
+        let (state, view) = ProcessState::with_progress_bar(ctx, steps);
+        Self::spawn(state, view, job)
+    }
+
+    fn spawn(state: ProcessState, view: ProcessView, job: impl FnOnce(ProcessState) -> T + Send + 'static) -> Self {
+        let (result_sender, result_receiver) = mpsc::channel();
+        let ctx = state.ctx.clone();
+        thread::spawn(move || {
+            // a panicking job still has to wake the UI, so that it can show the failure
+            let result = panic::catch_unwind(AssertUnwindSafe(|| job(state)));
+
+            // the task may have been dropped if the user moved on while the job finished up
+            _ = result_sender.send(result);
+            ctx.request_repaint();
+        });
+
+        Self {
+            view,
+            result_receiver: Some(result_receiver),
+        }
+    }
+}
+
+impl<T> Task<T> {
+    /// Lets the user cancel the task from its window. The job only stops once it next checks
+    /// [`ProcessState::is_cancelled`].
+    pub(crate) fn cancellable(mut self) -> Self {
+        self.view.cancellable = true;
+        self
+    }
+
+    pub(crate) fn view(&self) -> &ProcessView {
+        &self.view
+    }
+
+    /// Shows the task's progress in a window in the middle of the screen.
+    pub(crate) fn show(&mut self, id: impl Into<WidgetText>, ctx: &egui::Context) {
+        self.view.show(id, ctx);
+    }
+
+    /// Takes the job's result if it has finished, or [`Err`] if the job panicked. This only checks for a result the
+    /// worker already sent, so it's cheap enough to call every frame.
+    ///
+    /// # Panics
+    ///
+    /// Panics if the result was already taken by an earlier poll.
+    pub(crate) fn poll(&mut self) -> Poll<thread::Result<T>> {
+        let receiver = self
+            .result_receiver
+            .as_ref()
+            .expect("the task's result was already taken");
+        let result: thread::Result<T> = match receiver.try_recv() {
+            Ok(result) => result,
+            Err(TryRecvError::Empty) => return Poll::Pending,
+
+            // the worker always sends a result, so it can only go missing if the worker thread itself died
+            Err(TryRecvError::Disconnected) => Err(Box::new("the task's worker stopped without a result")),
+        };
+
+        self.result_receiver = None;
+        Poll::Ready(result)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::{task::Poll, thread, time::Duration};
+
+    use eframe::egui;
+
+    use super::Task;
+
+    fn wait<T>(task: &mut Task<T>) -> T {
+        loop {
+            if let Poll::Ready(result) = task.poll() {
+                return result.unwrap();
+            }
+
+            thread::sleep(Duration::from_millis(10));
+        }
+    }
+
+    #[test]
+    fn polls_the_job_result() {
+        let mut task = Task::spawn_with_spinner(&egui::Context::default(), |state| {
+            state.push_status("Adding");
+            1 + 2
+        });
+
+        assert_eq!(wait(&mut task), 3);
+        assert_eq!(task.view().flush_statuses().as_deref(), Some("Adding"));
+    }
+
+    #[test]
+    fn cancelled_jobs_stop_early() {
+        let mut task = Task::spawn_with_spinner(&egui::Context::default(), |state| {
+            let mut steps = 0;
+            while !state.is_cancelled() {
+                steps += 1;
+                thread::sleep(Duration::from_millis(1));
+            }
+
+            steps
+        })
+        .cancellable();
+
+        task.view().cancel();
+        wait(&mut task);
+    }
+
+    #[test]
+    fn panicking_jobs_fail_the_poll() {
+        let mut task = Task::spawn_with_spinner(&egui::Context::default(), |_| -> usize { panic!("the job failed") });
+
+        loop {
+            if let Poll::Ready(result) = task.poll() {
+                assert!(result.is_err());
+                break;
+            }
+
+            thread::sleep(Duration::from_millis(10));
+        }
+    }
+}
@@ -1,13 +1,16 @@
 use eframe::egui::{self, Align2, TextEdit, TextStyle, Vec2b};
 use faccess::{AccessMode, PathExt};
 use std::{
-    fs,
-    io::{self, ErrorKind},
+    fs::{self, File, OpenOptions, TryLockError},
+    io::{self, ErrorKind, Read},
 };
 use thiserror::Error;
 use typed_path::{Utf8PlatformPath, Utf8PlatformPathBuf};
 
-use crate::styles;
+use crate::{
+    app::{steam, vanilla_source},
+    styles,
+};
 
 #[derive(Debug)]
 pub(crate) struct TfDirPicker {
@@ -27,7 +30,7 @@ impl TfDirPicker {
 
     pub(crate) fn update(&mut self, ctx: &egui::Context, tf_dir: &mut Option<Utf8PlatformPathBuf>) -> bool {
         let mut done = false;
-        egui::Window::new("Welcome")
+        egui::Window::new("Locate Team Fortress 2")
             .collapsible(false)
             .resizable(false)
             .anchor(Align2::CENTER_CENTER, (0.0, 0.0))
@@ -35,22 +38,6 @@ impl TfDirPicker {
             .scroll(Vec2b::FALSE)
             .show(ctx, |ui| {
                 ui.vertical(|ui| {
-
-                    ui.horizontal(|ui| {
-                        ui.strong(egui::RichText::new("This is dazzle").text_style(styles::big()));
-                        ui.add_space(8.0);
-                        ui.label(egui::RichText::new(" - a mod installer for Team Fortress 2.").text_style(styles::big()))
-                    });
-
-                    ui.add_space(24.0);
-
-                    ui.label(
-                        egui::RichText::new("Dazzle will process many kinds of mods & install them into your Team Fortress 2 installation. Mods installed by dazzle will typically work in Casual and other sv_pure servers!")
-                            .text_style(styles::big())
-                    );
-
-                    ui.add_space(16.0);
-
                     ui.label(
                         egui::RichText::new("In order to install mods, dazzle needs to know where your TF2 installation is. Please provide a valid path to your 'Team Fortress 2/tf' directory:")
                             .text_style(styles::big())
@@ -94,7 +81,8 @@ impl TfDirPicker {
                             ui.horizontal(|ui| {
                                 ui.image(egui::include_image!("../static/images/warning.png"));
                                 ui.strong(format!("the selected path is not valid: {err}"));
-                            })
+                            });
+                            ui.label(err.hint());
                         });
                     }
 
@@ -136,7 +124,7 @@ pub(crate) enum TfValidationError {
     #[error("The 'custom/' subfolder exists but we lack permissions to read or write to it")]
     MissingCustomFolderPermissions,
 
-    #[error("Couldn't find 'tf2_misc_dir.vpk' in the path specified")]
+    #[error("Couldn't find 'tf2_misc_dir.vpk' or a 'particles/' subfolder in the path specified")]
     MissingVpk,
 
     #[error("'tf2_misc_dir.vpk' exists but it is not a file")]
@@ -145,6 +133,15 @@ pub(crate) enum TfValidationError {
     #[error("The 'tf2_misc_dir.vpk' file exists but we lack permissions to read or write to it")]
     MissingVpkPermissions,
 
+    #[error("'tf2_misc_dir.vpk' exists but it is not a valid VPK")]
+    InvalidVpk,
+
+    #[error("'tf2_misc_dir.vpk' is in use by another program")]
+    VpkInUse,
+
+    #[error("'tf2_misc_dir.vpk' is in use, and Steam is running")]
+    SteamRunning,
+
     #[error("Couldn't find 'gameinfo.txt' in the path specified")]
     MissingGameInfo,
 
@@ -159,6 +156,8 @@ pub(crate) fn validate(path: &Utf8PlatformPath) -> Result<(), TfValidationError>
     // the picked directory must be a valid tf2 installation. We have the following heuristics to
     // ensure that this is the case:
     //   - {picked_dir}/tf2_misc_dir.vpk exists, is a file, is a valid VPK index, and we have read/write permissions
+    //   - {picked_dir}/tf2_misc_dir.vpk isn't in use by TF2 or any other program
+    //   - or, for extracted installs without the VPK, {picked_dir}/particles exists, and is a dir
     //   - {picked_dir}/custom exists, and is a dir, and we have read/write permissions
     //   - {picked_dir}/gameinfo.txt exists, and is a file, and we have read/write permissions
 
@@ -191,19 +190,8 @@ pub(crate) fn validate(path: &Utf8PlatformPath) -> Result<(), TfValidationError>
         return Err(TfValidationError::MissingCustomFolderPermissions);
     }
 
-    let tf2_misc_vpk = path.join("tf2_misc_dir.vpk");
-    let metadata = fs::metadata(&tf2_misc_vpk).map_err(|err| match err.kind() {
-        ErrorKind::NotFound => TfValidationError::MissingVpk,
-        ErrorKind::PermissionDenied => TfValidationError::MissingVpkPermissions,
-        _ => TfValidationError::Io(err),
-    })?;
-
-    if !metadata.is_file() {
-        return Err(TfValidationError::VpkNotAFile);
-    }
-
-    if tf2_misc_vpk.access(AccessMode::READ | AccessMode::WRITE).is_err() {
-        return Err(TfValidationError::MissingVpkPermissions);
+    if vanilla_source::has_vpk(path) || !vanilla_source::has_loose_particles(path) {
+        validate_vpk(&path.join(vanilla_source::TF2_VPK_NAME))?;
     }
 
     let gameinfo_path = path.join("gameinfo.txt");
@@ -218,8 +206,118 @@ pub(crate) fn validate(path: &Utf8PlatformPath) -> Result<(), TfValidationError>
     }
 
     if gameinfo_path.access(AccessMode::READ | AccessMode::WRITE).is_err() {
+        return Err(TfValidationError::MissingGameInfoPermissions);
+    }
+
+    Ok(())
+}
+
+impl TfValidationError {
+    /// A short suggestion for how the user can resolve this error.
+    pub(crate) fn hint(&self) -> &'static str {
+        match self {
+            Self::InvalidPath | Self::DoesntExist | Self::NotADirectory => {
+                "Make sure the path points at the 'Team Fortress 2/tf' folder inside of your Steam library."
+            }
+            Self::MissingCustomFolder | Self::CustomNotADirectory => {
+                "Make sure you selected the 'tf' folder, not 'Team Fortress 2'. If it is the 'tf' folder, try creating \
+                 an empty 'custom' folder inside of it."
+            }
+            Self::MissingVpk
+            | Self::VpkNotAFile
+            | Self::InvalidVpk
+            | Self::MissingGameInfo
+            | Self::GameInfoNotAFile => {
+                "Your TF2 installation may be incomplete. Try verifying the integrity of the game files in Steam."
+            }
+            Self::PermissionDenied
+            | Self::MissingCustomFolderPermissions
+            | Self::MissingVpkPermissions
+            | Self::MissingGameInfoPermissions => {
+                "Make sure your user owns the TF2 folder, or try running dazzle with the same user that runs Steam."
+            }
+            Self::VpkInUse => "Close TF2 and any other program that might have the game's files open, then try again.",
+            Self::SteamRunning => "Close TF2 and exit Steam completely, then try again.",
+            Self::Io(_) => "Something unexpected went wrong while reading the folder. Try again, or pick another path.",
+        }
+    }
+}
+
+/// Ensures that `tf2_misc_vpk` is a VPK which dazzle can patch.
+fn validate_vpk(tf2_misc_vpk: &Utf8PlatformPath) -> Result<(), TfValidationError> {
+    let metadata = fs::metadata(tf2_misc_vpk).map_err(|err| match err.kind() {
+        ErrorKind::NotFound => TfValidationError::MissingVpk,
+        ErrorKind::PermissionDenied => TfValidationError::MissingVpkPermissions,
+        _ => TfValidationError::Io(err),
+    })?;
+
+    if !metadata.is_file() {
+        return Err(TfValidationError::VpkNotAFile);
+    }
+
+    if tf2_misc_vpk.access(AccessMode::READ | AccessMode::WRITE).is_err() {
         return Err(TfValidationError::MissingVpkPermissions);
     }
 
+    check_vpk_signature(tf2_misc_vpk)?;
+    check_vpk_unlocked(tf2_misc_vpk)
+}
+
+/// Ensures that `vpk_path` begins with the VPK signature.
+fn check_vpk_signature(vpk_path: &Utf8PlatformPath) -> Result<(), TfValidationError> {
+    const VPK_SIGNATURE: u32 = 0x55aa1234;
+
+    let mut signature = [0; 4];
+    File::open(vpk_path)?
+        .read_exact(&mut signature)
+        .map_err(|err| match err.kind() {
+            ErrorKind::UnexpectedEof => TfValidationError::InvalidVpk,
+            _ => TfValidationError::Io(err),
+        })?;
+
+    if u32::from_le_bytes(signature) != VPK_SIGNATURE {
+        return Err(TfValidationError::InvalidVpk);
+    }
+
     Ok(())
 }
+
+/// Ensures that no other program, e.g. TF2 itself, has `vpk_path` open or locked.
+///
+/// On Windows, the game opens its VPKs without sharing write access, so opening the VPK for writing fails with a
+/// sharing violation while TF2 is running. Other programs may hold an advisory lock on the file instead.
+fn check_vpk_unlocked(vpk_path: &Utf8PlatformPath) -> Result<(), TfValidationError> {
+    // ERROR_SHARING_VIOLATION and ERROR_LOCK_VIOLATION
+    const WINDOWS_IN_USE_ERRORS: [i32; 2] = [32, 33];
+
+    let in_use = || {
+        if steam::is_running() {
+            TfValidationError::SteamRunning
+        } else {
+            TfValidationError::VpkInUse
+        }
+    };
+
+    let file = match OpenOptions::new().read(true).write(true).open(vpk_path) {
+        Ok(file) => file,
+        Err(err)
+            if cfg!(target_os = "windows")
+                && err
+                    .raw_os_error()
+                    .is_some_and(|code| WINDOWS_IN_USE_ERRORS.contains(&code)) =>
+        {
+            return Err(in_use());
+        }
+        Err(err) if err.kind() == ErrorKind::PermissionDenied => {
+            return Err(TfValidationError::MissingVpkPermissions);
+        }
+        Err(err) => return Err(err.into()),
+    };
+
+    // the lock is released as soon as `file` is dropped
+    match file.try_lock() {
+        Ok(()) => Ok(()),
+        Err(TryLockError::WouldBlock) => Err(in_use()),
+        Err(TryLockError::Error(err)) => Err(err.into()),
+    }
+}
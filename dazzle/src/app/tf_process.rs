@@ -0,0 +1,107 @@
+//! Detects whether TF2 is running. Patching `tf2_misc_dir.vpk` or replacing `custom/` VPKs while the game has them open
+//! leaves the game with a stale view of their contents, which can crash it or corrupt its caches.
+
+/// The executable names TF2 has shipped with. The 64-bit executables were introduced in 2024, but older installs, and
+/// installs running under Proton, may still use `hl2`.
+const TF2_EXECUTABLES: [&str; 5] = ["tf_linux64", "hl2_linux", "tf_win64.exe", "tf.exe", "hl2.exe"];
+
+/// Whether any process running TF2 is found.
+pub(crate) fn is_tf2_running() -> bool {
+    process_names().iter().any(|name| is_tf2_executable(name))
+}
+
+fn is_tf2_executable(name: &str) -> bool {
+    TF2_EXECUTABLES
+        .iter()
+        .any(|executable| executable.eq_ignore_ascii_case(name))
+}
+
+/// The executable name of every process we can see, found by scanning `/proc`.
+#[cfg(target_os = "linux")]
+fn process_names() -> Vec<String> {
+    use std::fs;
+
+    let Ok(entries) = fs::read_dir("/proc") else {
+        return Vec::new();
+    };
+
+    entries
+        .flatten()
+        .filter(|entry| {
+            entry
+                .file_name()
+                .to_str()
+                .is_some_and(|pid| pid.bytes().all(|b| b.is_ascii_digit()))
+        })
+        .filter_map(|entry| {
+            // `comm` is truncated to 15 bytes, so prefer the name of the executable if we're allowed to read it
+            fs::read_link(entry.path().join("exe"))
+                .ok()
+                .and_then(|exe| exe.file_name().and_then(|name| name.to_str()).map(str::to_string))
+                .or_else(|| {
+                    fs::read_to_string(entry.path().join("comm"))
+                        .ok()
+                        .map(|comm| comm.trim_end().to_string())
+                })
+        })
+        .collect()
+}
+
+/// The executable name of every process we can see, found by walking a toolhelp snapshot of the running processes.
+#[cfg(target_os = "windows")]
+fn process_names() -> Vec<String> {
+    use windows_sys::Win32::{
+        Foundation::{CloseHandle, INVALID_HANDLE_VALUE},
+        System::Diagnostics::ToolHelp::{
+            CreateToolhelp32Snapshot, PROCESSENTRY32W, Process32FirstW, Process32NextW, TH32CS_SNAPPROCESS,
+        },
+    };
+
+    let mut names = Vec::new();
+
+    // SAFETY: the snapshot handle is checked before it's used and closed once we're done with it, and `entry` is
+    // initialized with the size the toolhelp functions expect.
+    unsafe {
+        let snapshot = CreateToolhelp32Snapshot(TH32CS_SNAPPROCESS, 0);
+        if snapshot == INVALID_HANDLE_VALUE {
+            return names;
+        }
+
+        let mut entry: PROCESSENTRY32W = std::mem::zeroed();
+        entry.dwSize = u32::try_from(size_of::<PROCESSENTRY32W>()).unwrap();
+
+        let mut found = Process32FirstW(snapshot, &raw mut entry) != 0;
+        while found {
+            let len = entry
+                .szExeFile
+                .iter()
+                .position(|&c| c == 0)
+                .unwrap_or(entry.szExeFile.len());
+            names.push(String::from_utf16_lossy(&entry.szExeFile[..len]));
+            found = Process32NextW(snapshot, &raw mut entry) != 0;
+        }
+
+        CloseHandle(snapshot);
+    }
+
+    names
+}
+
+#[cfg(test)]
+mod tests {
+    use super::is_tf2_executable;
+
+    #[test]
+    fn matches_tf2_executables() {
+        assert!(is_tf2_executable("tf_linux64"));
+        assert!(is_tf2_executable("TF_WIN64.EXE"));
+        assert!(is_tf2_executable("hl2.exe"));
+    }
+
+    #[test]
+    fn ignores_other_executables() {
+        assert!(!is_tf2_executable("steam"));
+        assert!(!is_tf2_executable("hl2"));
+        assert!(!is_tf2_executable("tf_linux64.sh"));
+    }
+}
@@ -0,0 +1,183 @@
+//! Checks that the vanilla particles bundled with dazzle can still be patched back into `tf2_misc_dir.vpk`. Installing
+//! & uninstalling both start by restoring them, which needs each particle's entry to still be in one of the VPK's
+//! archives, with room for it. Other mods & interrupted patches can break that, and the only way to get the original
+//! VPK back is Steam's "verify integrity of game files" - so the user is offered that, and the VPK is watched until
+//! it's restored.
+//!
+//! Installs without the VPK keep the particles as loose files, which can always be restored from their backups, so
+//! there's nothing to check. See [`vanilla_source`](crate::app::vanilla_source).
+
+use std::{
+    fs,
+    hash::{DefaultHasher, Hash, Hasher},
+    io,
+    time::{Duration, Instant},
+};
+
+use thiserror::Error;
+use typed_path::{Utf8PlatformPath, Utf8PlatformPathBuf};
+use vpk::VPK;
+
+use crate::{app::vanilla_source, particles_manifest};
+
+/// How often the VPK is checked on while Steam verifies the game files.
+pub(crate) const POLL_INTERVAL: Duration = Duration::from_secs(1);
+
+/// How long the VPKs must stay unchanged before they're checked, so that they aren't checked while Steam is still
+/// writing them.
+const SETTLE_TIME: Duration = Duration::from_secs(5);
+
+/// The archive index of VPK entries whose data is in the directory VPK itself.
+const DIR_ARCHIVE_INDEX: u16 = 0x7fff;
+
+/// Why the vanilla particles can't be restored.
+#[derive(Debug, Error)]
+pub(crate) enum VanillaError {
+    #[error("tf2_misc_dir.vpk couldn't be read")]
+    Vpk(#[from] vpk::Error),
+
+    #[error("tf2_misc_dir.vpk doesn't have {0} anymore")]
+    Missing(&'static str),
+
+    #[error("{0} isn't in one of tf2_misc_dir.vpk's archives, so it can't be patched")]
+    NotInArchive(&'static str),
+
+    #[error("{0} in tf2_misc_dir.vpk is smaller than the vanilla particle")]
+    TooSmall(&'static str),
+
+    #[error("'{0}' is missing or damaged")]
+    ArchiveDamaged(Utf8PlatformPathBuf),
+}
+
+/// Checks that every vanilla particle can be patched back into the `tf2_misc_dir.vpk` in `tf_dir`, if it has one.
+///
+/// # Errors
+///
+/// Returns the first [`VanillaError`] found.
+pub(crate) fn check(tf_dir: &Utf8PlatformPath) -> Result<(), VanillaError> {
+    if !vanilla_source::has_vpk(tf_dir) {
+        return Ok(());
+    }
+
+    let vpk = VPK::read(tf_dir.join("tf2_misc_dir.vpk"))?;
+    for (name, pcf_data) in particles_manifest::PARTICLES_BYTES {
+        let entry = vpk.tree.get(name).ok_or(VanillaError::Missing(name))?;
+        let dir_entry = &entry.dir_entry;
+        if dir_entry.preload_length > 0 || dir_entry.archive_index == DIR_ARCHIVE_INDEX {
+            return Err(VanillaError::NotInArchive(name));
+        }
+
+        if u64::from(dir_entry.file_length) < pcf_data.len() as u64 {
+            return Err(VanillaError::TooSmall(name));
+        }
+
+        let archive_path = tf_dir.join(format!("tf2_misc_{:03}.vpk", dir_entry.archive_index));
+        let end = u64::from(dir_entry.archive_offset) + u64::from(dir_entry.file_length);
+        if !fs::metadata(&archive_path).is_ok_and(|metadata| metadata.len() >= end) {
+            return Err(VanillaError::ArchiveDamaged(archive_path));
+        }
+    }
+
+    Ok(())
+}
+
+/// Identifies the current state of `tf2_misc_dir.vpk` & its archives, by their sizes & modification times. It changes
+/// whenever Steam replaces any of them.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub(crate) struct Fingerprint(u64);
+
+impl Fingerprint {
+    /// Fingerprints the `tf2_misc` VPKs in `tf_dir`.
+    ///
+    /// # Errors
+    ///
+    /// Returns [`Err`] if `tf_dir` couldn't be listed.
+    pub(crate) fn of(tf_dir: &Utf8PlatformPath) -> io::Result<Self> {
+        let mut vpks = Vec::new();
+        for entry in fs::read_dir(tf_dir)? {
+            let entry = entry?;
+            let file_name = entry.file_name().to_string_lossy().to_string();
+            if file_name.starts_with("tf2_misc_") && file_name.ends_with(".vpk") {
+                // a file that vanished mid-listing is fingerprinted as missing
+                let metadata = entry
+                    .metadata()
+                    .ok()
+                    .map(|metadata| (metadata.len(), metadata.modified().ok()));
+                vpks.push((file_name, metadata));
+            }
+        }
+
+        vpks.sort();
+
+        let mut hasher = DefaultHasher::new();
+        vpks.hash(&mut hasher);
+        Ok(Self(hasher.finish()))
+    }
+}
+
+/// Watches for Steam to restore `tf2_misc_dir.vpk`, after the user asked it to verify the game files.
+#[derive(Debug, Clone)]
+pub(crate) struct Restoration {
+    /// why the vanilla particles couldn't be restored, the last time it was checked
+    pub reason: String,
+
+    /// the fingerprint the VPK was last checked with, or `None` if Steam hasn't been asked to verify the files yet
+    checked: Option<Fingerprint>,
+
+    /// the latest fingerprint, and when it was first seen
+    latest: Option<(Fingerprint, Instant)>,
+}
+
+impl Restoration {
+    pub(crate) fn new(err: &VanillaError) -> Self {
+        Self {
+            reason: err.to_string(),
+            checked: None,
+            latest: None,
+        }
+    }
+
+    /// Whether Steam has been asked to verify the game files.
+    pub(crate) fn is_validating(&self) -> bool {
+        self.checked.is_some()
+    }
+
+    /// Starts watching the VPKs in `tf_dir` for changes from their current state.
+    pub(crate) fn start_watching(&mut self, tf_dir: &Utf8PlatformPath) {
+        // N.B. a fingerprint which can't be taken is compared as 0, so that the VPK is checked as soon as it can be
+        self.checked = Some(Fingerprint::of(tf_dir).unwrap_or(Fingerprint(0)));
+    }
+
+    /// Checks the VPK again once it's changed, and then stayed unchanged for [`SETTLE_TIME`]. Returns true once it's
+    /// been restored.
+    pub(crate) fn poll(&mut self, tf_dir: &Utf8PlatformPath) -> bool {
+        let Ok(fingerprint) = Fingerprint::of(tf_dir) else {
+            return false;
+        };
+
+        match self.latest {
+            Some((latest, seen_at)) if latest == fingerprint => {
+                if seen_at.elapsed() < SETTLE_TIME {
+                    return false;
+                }
+            }
+            _ => {
+                self.latest = Some((fingerprint, Instant::now()));
+                return false;
+            }
+        }
+
+        if self.checked == Some(fingerprint) {
+            return false;
+        }
+
+        self.checked = Some(fingerprint);
+        match check(tf_dir) {
+            Ok(()) => true,
+            Err(err) => {
+                self.reason = err.to_string();
+                false
+            }
+        }
+    }
+}
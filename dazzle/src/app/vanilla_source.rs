@@ -0,0 +1,216 @@
+//! Where a TF2 installation keeps its vanilla particles, and so where installed particles are written. A normal install
+//! has them in `tf2_misc_dir.vpk`, whose entries are patched in place. Extracted & custom installs may not have the VPK,
+//! and keep the particles as loose files in `particles/` instead. Those are overwritten, after the original file is
+//! backed up next to it with [`BACKUP_SUFFIX`], and restored from the backup.
+//!
+//! Either way, every write is synced to disk before it's considered done.
+
+use std::{
+    collections::BTreeSet,
+    fs,
+    io::{ErrorKind, Read},
+};
+
+use anyhow::anyhow;
+use paths::GamePath;
+use typed_path::{Utf8PlatformPath, Utf8PlatformPathBuf};
+use vpk::VPK;
+use writevpk::patch::PatchVpkExt;
+
+use crate::{app::staging::write_synced, particles_manifest};
+
+pub(crate) const TF2_VPK_NAME: &str = "tf2_misc_dir.vpk";
+
+/// Appended to a loose particle's file name to name its backup.
+pub(crate) const BACKUP_SUFFIX: &str = ".dazzle_backup";
+
+/// Whether `tf_dir` has `tf2_misc_dir.vpk`. If it can't be told, the VPK is assumed to be there, so that whatever is
+/// wrong with it is reported when it's read.
+pub(crate) fn has_vpk(tf_dir: &Utf8PlatformPath) -> bool {
+    fs::exists(tf_dir.join(TF2_VPK_NAME)).unwrap_or(true)
+}
+
+/// Whether `tf_dir` has a loose `particles/` folder.
+pub(crate) fn has_loose_particles(tf_dir: &Utf8PlatformPath) -> bool {
+    fs::metadata(tf_dir.join("particles")).is_ok_and(|metadata| metadata.is_dir())
+}
+
+#[derive(Debug)]
+pub(crate) enum VanillaSource {
+    /// `tf2_misc_dir.vpk`, whose particle entries are patched in place.
+    Vpk(VPK),
+
+    /// The `tf/` folder of an install without `tf2_misc_dir.vpk`, whose loose particles are overwritten.
+    Loose(Utf8PlatformPathBuf),
+}
+
+impl VanillaSource {
+    /// Finds the vanilla particles in `tf_dir`: in `tf2_misc_dir.vpk` if there is one, and in `particles/` otherwise.
+    ///
+    /// # Errors
+    ///
+    /// Returns [`Err`] if the VPK couldn't be read, or if there's neither a VPK nor a `particles/` folder.
+    pub(crate) fn open(tf_dir: &Utf8PlatformPath) -> anyhow::Result<Self> {
+        if has_vpk(tf_dir) {
+            Ok(Self::Vpk(VPK::read(tf_dir.join(TF2_VPK_NAME))?))
+        } else if has_loose_particles(tf_dir) {
+            Ok(Self::Loose(tf_dir.to_path_buf()))
+        } else {
+            Err(anyhow!("'{tf_dir}' has neither {TF2_VPK_NAME} nor a particles/ folder"))
+        }
+    }
+
+    /// The VPK, if the particles are in one.
+    pub(crate) fn vpk(&self) -> Option<&VPK> {
+        match self {
+            Self::Vpk(vpk) => Some(vpk),
+            Self::Loose(_) => None,
+        }
+    }
+
+    /// Reads the vanilla game file at `path`, e.g. `resource/ui/mainmenuoverride.res`, from wherever the particles
+    /// are. Returns `None` if there's no such file.
+    pub(crate) fn read(&self, path: &str) -> anyhow::Result<Option<Vec<u8>>> {
+        match self {
+            Self::Vpk(vpk) => {
+                let Some(entry) = vpk.tree.get(path) else {
+                    return Ok(None);
+                };
+
+                let mut contents = Vec::new();
+                entry.reader()?.read_to_end(&mut contents)?;
+                Ok(Some(contents))
+            }
+            Self::Loose(tf_dir) => match fs::read(GamePath::new(path).to_platform_path(tf_dir)) {
+                Ok(contents) => Ok(Some(contents)),
+                Err(err) if err.kind() == ErrorKind::NotFound => Ok(None),
+                Err(err) => Err(err.into()),
+            },
+        }
+    }
+
+    /// Describes where the particle `name` is written, for the user.
+    pub(crate) fn describe(&self, name: &str) -> String {
+        match self {
+            Self::Vpk(_) => format!("{TF2_VPK_NAME}/{name}"),
+            Self::Loose(_) => name.to_string(),
+        }
+    }
+
+    /// Writes `data` over the particle `name`. A loose particle is backed up first, unless it already has been, so
+    /// that its backup is always the file from before dazzle first wrote it.
+    pub(crate) fn write(&mut self, name: &str, data: &[u8]) -> anyhow::Result<()> {
+        match self {
+            Self::Vpk(vpk) => vpk.patch_file(name, data.len() as u64, &mut &data[..])?,
+            Self::Loose(tf_dir) => {
+                let path = GamePath::new(name).to_platform_path(tf_dir);
+                if let Some(parent) = path.parent() {
+                    fs::create_dir_all(parent)?;
+                }
+
+                let backup_path = backup_path(&path);
+                if !fs::exists(&backup_path)? {
+                    match fs::read(&path) {
+                        Ok(original) => write_synced(&backup_path, original)?,
+                        // a particle missing from the install is backed up as the vanilla particle, so that restoring
+                        // it works like any other
+                        Err(err) if err.kind() == ErrorKind::NotFound => {
+                            write_synced(&backup_path, bundled_particle(name).unwrap_or_default())?;
+                        }
+                        Err(err) => Err(err)?,
+                    }
+                }
+
+                write_synced(&path, data)?;
+            }
+        }
+
+        Ok(())
+    }
+
+    /// Writes every `(name, data)` particle, like [`VanillaSource::write`]. The VPK's entries are patched in one batch,
+    /// so each of its archives is only opened & flushed once.
+    pub(crate) fn write_all(&mut self, particles: &[(String, Vec<u8>)]) -> anyhow::Result<()> {
+        match self {
+            Self::Vpk(vpk) => vpk.patch_files(particles.iter().map(|(name, data)| (name.as_str(), data.as_slice())))?,
+            Self::Loose(_) => {
+                for (name, data) in particles {
+                    self.write(name, data)?;
+                }
+            }
+        }
+
+        Ok(())
+    }
+
+    /// Restores every vanilla particle. The VPK's are patched back from the particles bundled with dazzle, and loose
+    /// particles are restored from their backups.
+    pub(crate) fn restore(&mut self) -> anyhow::Result<()> {
+        self.restore_matching(|_| true)
+    }
+
+    /// Restores the vanilla particles in `names`, like [`VanillaSource::restore`].
+    pub(crate) fn restore_particles(&mut self, names: &[String]) -> anyhow::Result<()> {
+        self.restore_matching(|name| names.iter().any(|patched| patched == name))
+    }
+
+    fn restore_matching(&mut self, filter: impl Fn(&str) -> bool) -> anyhow::Result<()> {
+        let particles = particles_manifest::PARTICLES_BYTES
+            .into_iter()
+            .filter(|(name, _)| filter(name));
+
+        match self {
+            Self::Vpk(vpk) => vpk.patch_files(particles)?,
+            Self::Loose(tf_dir) => {
+                let mut restored_dirs = BTreeSet::new();
+                for (name, _) in particles {
+                    let path = GamePath::new(name).to_platform_path(tf_dir);
+                    match fs::rename(backup_path(&path), &path) {
+                        // a particle without a backup was never overwritten
+                        Err(err) if err.kind() == ErrorKind::NotFound => {}
+                        result => result?,
+                    }
+
+                    restored_dirs.extend(path.parent().map(Utf8PlatformPath::to_path_buf));
+                }
+
+                // the renames are only durable once the directories holding them are synced
+                for dir in restored_dirs {
+                    writevpk::sync_dir(&dir)?;
+                }
+            }
+        }
+
+        Ok(())
+    }
+
+    /// The vanilla particles which are still changed by dazzle: the VPK's particles which differ from the ones bundled
+    /// with dazzle, or the loose particles which have a backup.
+    pub(crate) fn patched_particles(&self) -> anyhow::Result<Vec<String>> {
+        let mut patched = Vec::new();
+        for (name, vanilla) in particles_manifest::PARTICLES_BYTES {
+            let is_patched = match self {
+                Self::Vpk(_) => self.read(name)?.is_some_and(|contents| contents != vanilla),
+                Self::Loose(tf_dir) => fs::exists(backup_path(&GamePath::new(name).to_platform_path(tf_dir)))?,
+            };
+
+            if is_patched {
+                patched.push(name.to_string());
+            }
+        }
+
+        Ok(patched)
+    }
+}
+
+fn backup_path(path: &Utf8PlatformPath) -> Utf8PlatformPathBuf {
+    Utf8PlatformPathBuf::from(format!("{path}{BACKUP_SUFFIX}"))
+}
+
+/// The vanilla particle `name`, as bundled with dazzle.
+fn bundled_particle(name: &str) -> Option<&'static [u8]> {
+    particles_manifest::PARTICLES_BYTES
+        .iter()
+        .find(|(bundled_name, _)| *bundled_name == name)
+        .map(|(_, data)| *data)
+}
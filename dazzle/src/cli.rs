@@ -0,0 +1,325 @@
+//! Parses dazzle's command line. dazzle is a GUI app, so the command line mostly exists to let other programs, like a
+//! file association for `.vpk`, hand addons to it. A few headless commands let scripts inspect & install addons
+//! without the GUI; see [`ExitStatus`] for what they return.
+
+use std::{ffi::OsString, path::PathBuf};
+
+use thiserror::Error;
+use typed_path::Utf8PlatformPathBuf;
+
+pub(crate) const USAGE: &str = "\
+usage:
+    dazzle                  start dazzle
+    dazzle add <path>...    start dazzle, then add each addon at <path>
+    dazzle <path>...        same as `dazzle add <path>...`
+    dazzle list [--json]    list your addons, and the files they conflict on
+    dazzle status [--json]  show what's installed into TF2
+    dazzle install [--json] install your addons without starting the GUI
+    dazzle inspect [<data dir>]
+                            start dazzle without changing anything, to look through your addons or through a copy
+                            of someone else's data dir. A copied data dir's config.toml has to be copied into it
+    dazzle pack <folder> [-o <path>] [--strip-defaults]
+                            package a mod folder into a VPK, <folder>.vpk unless -o is given. --strip-defaults
+                            removes attributes which are set to their default value from its PCFs
+    dazzle --help           show this message
+
+exit codes:
+    0   success
+    1   the command failed
+    2   some addons conflict, and their priority hasn't been chosen yet
+    3   TF2 is running
+    4   the configured tf/ directory isn't valid
+    5   dazzle is already running
+    64  the command line couldn't be parsed";
+
+#[derive(Debug, PartialEq, Eq)]
+pub(crate) enum Command {
+    /// Start the GUI, adding `pending_addons` once the user's addons are loaded.
+    Run { pending_addons: Vec<Utf8PlatformPathBuf> },
+
+    /// Start the GUI without letting it change anything, showing the addons in `data_dir` if it's given, or the
+    /// user's own otherwise.
+    Inspect { data_dir: Option<Utf8PlatformPathBuf> },
+
+    /// Run `command` without the GUI, printing its result as JSON instead of text if `json` is set.
+    Headless { command: HeadlessCommand, json: bool },
+
+    /// Package the mod `folder` into a VPK at `output`, stripping default attribute values from its PCFs if
+    /// `strip_defaults` is set.
+    Pack {
+        folder: Utf8PlatformPathBuf,
+        output: Option<Utf8PlatformPathBuf>,
+        strip_defaults: bool,
+    },
+
+    /// Print [`USAGE`].
+    Help,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub(crate) enum HeadlessCommand {
+    /// List every addon, its size & whether it's enabled, and the files they conflict on.
+    List,
+
+    /// Describe what's installed into TF2, and whether TF2 is running.
+    Status,
+
+    /// Install the addons as they're configured, like the GUI's install button.
+    Install,
+}
+
+/// The process exit status of a command. These are part of dazzle's command line interface, so existing values must
+/// never change.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub(crate) enum ExitStatus {
+    Success = 0,
+    Failed = 1,
+
+    /// Enabled addons provide the same files, but at least one of them has never been given a priority.
+    ConflictsUnresolved = 2,
+    GameRunning = 3,
+    InvalidTfDir = 4,
+    AlreadyRunning = 5,
+
+    /// The command line couldn't be parsed. Matches `EX_USAGE` from `sysexits.h`.
+    Usage = 64,
+}
+
+impl ExitStatus {
+    pub(crate) fn code(self) -> i32 {
+        self as i32
+    }
+}
+
+#[derive(Debug, Error)]
+pub(crate) enum CliError {
+    #[error("'add' needs at least one addon path")]
+    MissingAddonPaths,
+
+    #[error("'pack' needs a mod folder")]
+    MissingPackFolder,
+
+    #[error("'{0}' needs a value")]
+    MissingOptionValue(String),
+
+    #[error("unknown option '{0}'")]
+    UnknownOption(String),
+
+    #[error("unexpected argument '{0}'")]
+    UnexpectedArgument(String),
+
+    #[error(transparent)]
+    Path(#[from] paths::PathError),
+}
+
+/// Parses `args`, which shouldn't include the program name.
+///
+/// # Errors
+///
+/// See [`CliError`].
+pub(crate) fn parse(args: impl IntoIterator<Item = OsString>) -> Result<Command, CliError> {
+    let mut args = args.into_iter().peekable();
+
+    let paths = match args.peek().and_then(|arg| arg.to_str()) {
+        Some("add") => {
+            args.next();
+            if args.peek().is_none() {
+                return Err(CliError::MissingAddonPaths);
+            }
+
+            args
+        }
+        Some("list") => return parse_headless(HeadlessCommand::List, args.skip(1)),
+        Some("status") => return parse_headless(HeadlessCommand::Status, args.skip(1)),
+        Some("install") => return parse_headless(HeadlessCommand::Install, args.skip(1)),
+        Some("pack") => return parse_pack(args.skip(1)),
+        Some("inspect") => return parse_inspect(args.skip(1)),
+        Some("-h" | "--help" | "help") => return Ok(Command::Help),
+        Some(option) if option.starts_with('-') => return Err(CliError::UnknownOption(option.to_string())),
+        // file associations & "open with" pass the paths without a subcommand
+        _ => args,
+    };
+
+    let pending_addons = paths
+        .map(|path| paths::try_buf_to_typed(PathBuf::from(path)))
+        .collect::<Result<_, _>>()?;
+
+    Ok(Command::Run { pending_addons })
+}
+
+/// Parses the options after a headless `command`.
+fn parse_headless(command: HeadlessCommand, args: impl Iterator<Item = OsString>) -> Result<Command, CliError> {
+    let mut json = false;
+    for arg in args {
+        match arg.to_str() {
+            Some("--json") => json = true,
+            Some(option) if option.starts_with('-') => return Err(CliError::UnknownOption(option.to_string())),
+            _ => return Err(CliError::UnexpectedArgument(arg.to_string_lossy().into_owned())),
+        }
+    }
+
+    Ok(Command::Headless { command, json })
+}
+
+/// Parses the data dir after `inspect`.
+fn parse_inspect(args: impl Iterator<Item = OsString>) -> Result<Command, CliError> {
+    let mut data_dir = None;
+    for arg in args {
+        match arg.to_str() {
+            Some(option) if option.starts_with('-') => return Err(CliError::UnknownOption(option.to_string())),
+            _ if data_dir.is_none() => data_dir = Some(paths::try_buf_to_typed(PathBuf::from(arg))?),
+            _ => return Err(CliError::UnexpectedArgument(arg.to_string_lossy().into_owned())),
+        }
+    }
+
+    Ok(Command::Inspect { data_dir })
+}
+
+/// Parses the folder & options after `pack`.
+fn parse_pack(mut args: impl Iterator<Item = OsString>) -> Result<Command, CliError> {
+    let mut folder = None;
+    let mut output = None;
+    let mut strip_defaults = false;
+    while let Some(arg) = args.next() {
+        match arg.to_str() {
+            Some(option @ ("-o" | "--output")) => {
+                let path = args
+                    .next()
+                    .ok_or_else(|| CliError::MissingOptionValue(option.to_string()))?;
+                output = Some(paths::try_buf_to_typed(PathBuf::from(path))?);
+            }
+            Some("--strip-defaults") => strip_defaults = true,
+            Some(option) if option.starts_with('-') => return Err(CliError::UnknownOption(option.to_string())),
+            _ if folder.is_none() => folder = Some(paths::try_buf_to_typed(PathBuf::from(arg))?),
+            _ => return Err(CliError::UnexpectedArgument(arg.to_string_lossy().into_owned())),
+        }
+    }
+
+    Ok(Command::Pack {
+        folder: folder.ok_or(CliError::MissingPackFolder)?,
+        output,
+        strip_defaults,
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use std::ffi::OsString;
+
+    use typed_path::Utf8PlatformPathBuf;
+
+    use super::{CliError, Command, HeadlessCommand, parse};
+
+    fn args(args: &[&str]) -> Vec<OsString> {
+        args.iter().map(OsString::from).collect()
+    }
+
+    fn run(paths: &[&str]) -> Command {
+        Command::Run {
+            pending_addons: paths.iter().map(Utf8PlatformPathBuf::from).collect(),
+        }
+    }
+
+    #[test]
+    fn no_args_runs_without_addons() {
+        assert_eq!(parse(args(&[])).unwrap(), run(&[]));
+    }
+
+    #[test]
+    fn add_and_bare_paths_are_equivalent() {
+        let expected = run(&["first.vpk", "second.vpk"]);
+        assert_eq!(parse(args(&["add", "first.vpk", "second.vpk"])).unwrap(), expected);
+        assert_eq!(parse(args(&["first.vpk", "second.vpk"])).unwrap(), expected);
+    }
+
+    #[test]
+    fn add_requires_paths() {
+        assert!(matches!(parse(args(&["add"])), Err(CliError::MissingAddonPaths)));
+    }
+
+    #[test]
+    fn options_are_recognized() {
+        assert_eq!(parse(args(&["--help"])).unwrap(), Command::Help);
+        assert!(matches!(
+            parse(args(&["--verbose"])),
+            Err(CliError::UnknownOption(option)) if option == "--verbose"
+        ));
+    }
+
+    #[test]
+    fn headless_commands_take_json_option() {
+        assert_eq!(
+            parse(args(&["list"])).unwrap(),
+            Command::Headless {
+                command: HeadlessCommand::List,
+                json: false
+            }
+        );
+        assert_eq!(
+            parse(args(&["install", "--json"])).unwrap(),
+            Command::Headless {
+                command: HeadlessCommand::Install,
+                json: true
+            }
+        );
+    }
+
+    #[test]
+    fn headless_commands_reject_other_arguments() {
+        assert!(matches!(
+            parse(args(&["status", "--verbose"])),
+            Err(CliError::UnknownOption(option)) if option == "--verbose"
+        ));
+        assert!(matches!(
+            parse(args(&["install", "fire.vpk"])),
+            Err(CliError::UnexpectedArgument(arg)) if arg == "fire.vpk"
+        ));
+    }
+    #[test]
+    fn pack_takes_a_folder_and_options() {
+        assert_eq!(
+            parse(args(&["pack", "my_mod"])).unwrap(),
+            Command::Pack {
+                folder: Utf8PlatformPathBuf::from("my_mod"),
+                output: None,
+                strip_defaults: false
+            }
+        );
+        assert_eq!(
+            parse(args(&["pack", "--strip-defaults", "my_mod", "-o", "out/fire.vpk"])).unwrap(),
+            Command::Pack {
+                folder: Utf8PlatformPathBuf::from("my_mod"),
+                output: Some(Utf8PlatformPathBuf::from("out/fire.vpk")),
+                strip_defaults: true
+            }
+        );
+    }
+
+    #[test]
+    fn pack_requires_a_folder_and_option_values() {
+        assert!(matches!(parse(args(&["pack"])), Err(CliError::MissingPackFolder)));
+        assert!(matches!(
+            parse(args(&["pack", "my_mod", "-o"])),
+            Err(CliError::MissingOptionValue(option)) if option == "-o"
+        ));
+        assert!(matches!(
+            parse(args(&["pack", "my_mod", "other_mod"])),
+            Err(CliError::UnexpectedArgument(arg)) if arg == "other_mod"
+        ));
+    }
+
+    #[test]
+    fn inspect_takes_an_optional_data_dir() {
+        assert_eq!(parse(args(&["inspect"])).unwrap(), Command::Inspect { data_dir: None });
+        assert_eq!(
+            parse(args(&["inspect", "friends_data"])).unwrap(),
+            Command::Inspect {
+                data_dir: Some(Utf8PlatformPathBuf::from("friends_data"))
+            }
+        );
+        assert!(matches!(
+            parse(args(&["inspect", "friends_data", "other_data"])),
+            Err(CliError::UnexpectedArgument(arg)) if arg == "other_data"
+        ));
+    }
+}
@@ -0,0 +1,89 @@
+use std::{
+    backtrace::Backtrace,
+    fs::OpenOptions,
+    io::Write,
+    panic::{self, PanicHookInfo},
+    sync::{
+        OnceLock,
+        atomic::{AtomicBool, Ordering},
+    },
+    thread,
+    time::{SystemTime, UNIX_EPOCH},
+};
+
+use typed_path::{Utf8PlatformPath, Utf8PlatformPathBuf};
+
+static CRASH_LOG_PATH: OnceLock<Utf8PlatformPathBuf> = OnceLock::new();
+
+/// Whether a crash has been written to the crash log since the hook was installed.
+static CRASH_LOG_WRITTEN: AtomicBool = AtomicBool::new(false);
+
+/// Installs a panic hook which appends the panic message and a backtrace to the crash log at `path`, before deferring
+/// to the default hook.
+///
+/// Panics in worker threads don't abort the app, so the log is the only place the backtrace survives; the GUI points
+/// the user at it via [`crash_log_path`].
+pub(crate) fn install_panic_hook(path: Utf8PlatformPathBuf) {
+    if CRASH_LOG_PATH.set(path).is_err() {
+        // the hook has already been installed
+        return;
+    }
+
+    let default_hook = panic::take_hook();
+    panic::set_hook(Box::new(move |info| {
+        if let Some(path) = CRASH_LOG_PATH.get() {
+            // if we can't write the crash log there isn't anything else we can do from inside a panic hook, so the
+            // default hook's output on stderr will have to suffice
+            if write_crash_log(path, info).is_ok() {
+                CRASH_LOG_WRITTEN.store(true, Ordering::Release);
+            }
+        }
+
+        default_hook(info);
+    }));
+}
+
+/// The path of the crash log, if a crash has been written to it. If writing it failed, there's nothing there worth
+/// pointing the user at.
+pub(crate) fn crash_log_path() -> Option<&'static Utf8PlatformPath> {
+    if !CRASH_LOG_WRITTEN.load(Ordering::Acquire) {
+        return None;
+    }
+
+    CRASH_LOG_PATH.get().map(Utf8PlatformPathBuf::as_path)
+}
+
+fn write_crash_log(path: &Utf8PlatformPath, info: &PanicHookInfo) -> std::io::Result<()> {
+    let timestamp = SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .map(|duration| duration.as_secs())
+        .unwrap_or_default();
+
+    let thread = thread::current();
+    let thread_name = thread.name().unwrap_or("<unnamed>");
+
+    let message = if let Some(message) = info.payload().downcast_ref::<&str>() {
+        message
+    } else if let Some(message) = info.payload().downcast_ref::<String>() {
+        message.as_str()
+    } else {
+        "<non-string panic payload>"
+    };
+
+    let location = info
+        .location()
+        .map_or_else(|| String::from("<unknown location>"), ToString::to_string);
+
+    let backtrace = Backtrace::force_capture();
+
+    let mut file = OpenOptions::new().create(true).append(true).open(path)?;
+    writeln!(
+        file,
+        "=== dazzle {} crashed at {timestamp} (unix time) ===",
+        env!("CARGO_PKG_VERSION")
+    )?;
+    writeln!(file, "thread '{thread_name}' panicked at {location}:")?;
+    writeln!(file, "{message}")?;
+    writeln!(file, "{backtrace}")?;
+    file.flush()
+}
@@ -33,13 +33,19 @@
 #![cfg_attr(windows, windows_subsystem = "windows")]
 
 mod app;
+mod cli;
+mod crash;
 mod particles_manifest;
 mod pcf_defaults;
+mod strip_defaults;
 mod styles;
 
 use eframe::egui::{self, Align2, CentralPanel, Window};
 
-use crate::app::{App, BuildError};
+use crate::{
+    app::{App, BuildError},
+    cli::{Command, ExitStatus},
+};
 
 const APP_INSTANCE_NAME: &str = "net.dresswithpockets.dazzletf2.lock";
 const APP_TLD: &str = "net";
@@ -98,8 +104,33 @@ fn present_fatal_error_dialogue(err: BuildError) {
 }
 
 fn main() {
-    let app = match App::new() {
+    let app = match cli::parse(std::env::args_os().skip(1)) {
+        Ok(Command::Run { pending_addons }) => App::new(pending_addons),
+        Ok(Command::Inspect { data_dir }) => App::inspect(data_dir),
+        Ok(Command::Headless { command, json }) => {
+            std::process::exit(app::headless::run(command, json).code());
+        }
+        Ok(Command::Pack {
+            folder,
+            output,
+            strip_defaults,
+        }) => {
+            std::process::exit(app::headless::pack(&folder, output, strip_defaults).code());
+        }
+        Ok(Command::Help) => {
+            println!("{}", cli::USAGE);
+            return;
+        }
+        Err(err) => {
+            eprintln!("{err}\n\n{}", cli::USAGE);
+            std::process::exit(ExitStatus::Usage.code());
+        }
+    };
+
+    let app = match app {
         Ok(app) => app,
+        // the running instance takes it from here
+        Err(BuildError::HandedOff) => return,
         Err(err) => {
             present_fatal_error_dialogue(err);
             std::process::exit(1);
@@ -0,0 +1,61 @@
+//! The attribute defaults which are stripped from particle systems & operators. These are shared by build.rs, which
+//! strips the vanilla particles ahead of time, and the installer, which strips addon particles when they don't fit.
+
+use std::collections::HashMap;
+
+pub(crate) fn get_default_operator_map() -> HashMap<&'static str, pcf::Attribute> {
+    HashMap::from([
+        ("operator start fadein", 0.0.into()),
+        ("operator end fadein", 0.0.into()),
+        ("operator start fadeout", 0.0.into()),
+        ("operator end fadeout", 0.0.into()),
+        ("Visibility Proxy Input Control Point Number", (-1).into()),
+        ("Visibility Proxy Radius", 1.0.into()),
+        ("Visibility input minimum", 0.0.into()),
+        ("Visibility input maximum", 1.0.into()),
+        ("Visibility Alpha Scale minimum", 0.0.into()),
+        ("Visibility Alpha Scale maximum", 1.0.into()),
+        ("Visibility Radius Scale minimum", 1.0.into()),
+        ("Visibility Radius Scale maximum", 1.0.into()),
+        ("Visibility Camera Depth Bias", 0.0.into()),
+    ])
+}
+
+pub(crate) fn get_particle_system_defaults() -> HashMap<&'static str, pcf::Attribute> {
+    HashMap::from([
+        // ("batch particle systems", false.into()),
+        (
+            "bounding_box_min",
+            dmx::Vector3((-10.0).into(), (-10.0).into(), (-10.0).into()).into(),
+        ),
+        (
+            "bounding_box_max",
+            dmx::Vector3(10.0.into(), 10.0.into(), 10.0.into()).into(),
+        ),
+        ("color", dmx::Color(255, 255, 255, 255).into()),
+        ("control point to disable rendering if it is the camera", (-1).into()),
+        ("cull_control_point", 0.into()),
+        ("cull_cost", 1.0.into()),
+        ("cull_radius", 0.0.into()),
+        ("cull_replacement_definition", String::new().into()),
+        ("group id", 0.into()),
+        ("initial_particles", 0i32.into()),
+        ("max_particles", 1000i32.into()),
+        ("material", "vgui/white".to_string().into()),
+        ("max_particles", 1000.into()),
+        ("maximum draw distance", 100_000.0.into()),
+        ("maximum sim tick rate", 0.0.into()),
+        ("maximum time step", 0.1.into()),
+        ("minimum rendered frames", 0.into()),
+        ("minimum sim tick rate", 0.0.into()),
+        ("preventNameBasedLookup", false.into()),
+        ("radius", 5.0.into()),
+        ("rotation", 0.0.into()),
+        ("rotation_speed", 0.0.into()),
+        ("sequence_number", 0.into()),
+        ("sequence_number1", 0.into()),
+        ("Sort particles", true.into()),
+        ("time to sleep when not drawn", 8.0.into()),
+        ("view model effect", false.into()),
+    ])
+}
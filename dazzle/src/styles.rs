@@ -2,11 +2,16 @@ use std::collections::BTreeMap;
 use std::sync::Arc;
 
 use eframe::egui;
+use eframe::egui::Color32;
 use eframe::egui::FontData;
 use eframe::egui::FontDefinitions;
 use eframe::egui::FontFamily;
 use eframe::egui::FontId;
 use eframe::egui::TextStyle;
+use eframe::egui::ThemePreference;
+use eframe::egui::Visuals;
+use serde::Deserialize;
+use serde::Serialize;
 
 pub(crate) fn configure_fonts(ctx: &egui::Context) {
     let mut fonts = FontDefinitions::default();
@@ -59,3 +64,98 @@ pub(crate) fn configure_text_styles(ctx: &egui::Context) {
 
     ctx.all_styles_mut(move |style| style.text_styles = text_styles.clone());
 }
+
+/// Whether dazzle is dark or light.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub(crate) enum ThemeMode {
+    /// follow the operating system's preference
+    System,
+    #[default]
+    Dark,
+    Light,
+}
+
+impl ThemeMode {
+    pub(crate) const ALL: [ThemeMode; 3] = [ThemeMode::System, ThemeMode::Dark, ThemeMode::Light];
+
+    pub(crate) fn label(self) -> &'static str {
+        match self {
+            ThemeMode::System => "System",
+            ThemeMode::Dark => "Dark",
+            ThemeMode::Light => "Light",
+        }
+    }
+}
+
+impl From<ThemeMode> for ThemePreference {
+    fn from(mode: ThemeMode) -> Self {
+        match mode {
+            ThemeMode::System => ThemePreference::System,
+            ThemeMode::Dark => ThemePreference::Dark,
+            ThemeMode::Light => ThemePreference::Light,
+        }
+    }
+}
+
+/// How dazzle looks. This is applied to the whole app, so every view & modal shares it.
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
+pub(crate) struct Theme {
+    #[serde(default)]
+    pub mode: ThemeMode,
+
+    /// the color of selections, links, and focused widgets, as sRGB
+    #[serde(default = "Theme::default_accent")]
+    pub accent: [u8; 3],
+
+    /// how much bigger everything is drawn than egui's default, on top of the display's own scaling
+    #[serde(default = "Theme::default_scale")]
+    pub scale: f32,
+}
+
+impl Theme {
+    pub(crate) const MIN_SCALE: f32 = 0.75;
+    pub(crate) const MAX_SCALE: f32 = 2.0;
+
+    fn default_accent() -> [u8; 3] {
+        [0, 92, 128]
+    }
+
+    fn default_scale() -> f32 {
+        1.0
+    }
+
+    fn accent_color(self) -> Color32 {
+        let [r, g, b] = self.accent;
+        Color32::from_rgb(r, g, b)
+    }
+
+    fn visuals(self, base: Visuals) -> Visuals {
+        let accent = self.accent_color();
+        let mut visuals = base;
+        visuals.selection.bg_fill = accent;
+        visuals.hyperlink_color = accent;
+        visuals.widgets.hovered.bg_stroke.color = accent;
+        visuals.widgets.active.bg_stroke.color = accent;
+        visuals
+    }
+}
+
+impl Default for Theme {
+    fn default() -> Self {
+        Self {
+            mode: ThemeMode::default(),
+            accent: Self::default_accent(),
+            scale: Self::default_scale(),
+        }
+    }
+}
+
+/// Applies `theme` to every style of `ctx`. Only the light or dark visuals are used at a time, but both get the accent
+/// color so that following the system's preference looks right either way.
+pub(crate) fn apply_theme(ctx: &egui::Context, theme: Theme) {
+    ctx.set_visuals_of(egui::Theme::Dark, theme.visuals(Visuals::dark()));
+    ctx.set_visuals_of(egui::Theme::Light, theme.visuals(Visuals::light()));
+    ctx.set_theme(theme.mode);
+    ctx.set_zoom_factor(theme.scale.clamp(Theme::MIN_SCALE, Theme::MAX_SCALE));
+}
@@ -0,0 +1,109 @@
+//! Decodes & encodes a large DMX: the PCF named by `PCF_BENCH_CORPUS`, or a synthetic one shaped like a particle file,
+//! with a root element referencing thousands of elements that each have a mix of scalar, string, array & binary
+//! attributes.
+
+use std::{env, ffi::CString, fs, hint::black_box};
+
+use bytes::Bytes;
+use criterion::{Criterion, Throughput, criterion_group, criterion_main};
+use dmx::{
+    Blob, Dmx, ElementIdx, Symbols, Vector3,
+    attribute::Attribute,
+    dmx::{Element, Version},
+    reference::SignatureReferences,
+};
+use ordermap::OrderMap;
+
+const CORPUS_VAR: &str = "PCF_BENCH_CORPUS";
+const ELEMENTS: usize = 8000;
+
+fn element(type_idx: u16, name: String, attributes: impl IntoIterator<Item = (u16, Attribute)>) -> Element {
+    Element {
+        type_idx,
+        name: CString::new(name).unwrap(),
+        signature: [0; 16],
+        attributes: attributes.into_iter().collect::<OrderMap<_, _>>(),
+    }
+}
+
+fn synthetic() -> Dmx {
+    let strings = Symbols::from(
+        [
+            "DmElement",
+            "DmeParticleOperator",
+            "children",
+            "radius",
+            "alpha",
+            "material",
+            "position",
+            "curve",
+            "lifetimes",
+        ]
+        .map(|string| CString::new(string).unwrap()),
+    );
+
+    let mut elements = vec![element(
+        0,
+        "untitled".to_string(),
+        [(
+            2,
+            Attribute::ElementArray((1..=ELEMENTS as u32).map(ElementIdx::from).collect()),
+        )],
+    )];
+    elements.extend((0..ELEMENTS).map(|idx| {
+        element(
+            1,
+            format!("operator_{idx}"),
+            [
+                (3, Attribute::Integer(idx as i32)),
+                (4, Attribute::Float((idx as f32).into())),
+                (
+                    5,
+                    Attribute::String(CString::new(format!("effects/fire_{}.vmt", idx % 64)).unwrap()),
+                ),
+                (6, Attribute::Vector3(Vector3(1.0.into(), 2.0.into(), 3.0.into()))),
+                (7, Attribute::Binary(Blob::from(vec![idx as u8; 256]))),
+                (
+                    8,
+                    Attribute::FloatArray((0..16).map(|value| (value as f32).into()).collect()),
+                ),
+            ],
+        )
+    }));
+
+    Dmx {
+        version: Version::Binary2Pcf1,
+        strings,
+        elements,
+        signature_references: SignatureReferences::new(),
+    }
+}
+
+fn corpus() -> Bytes {
+    match env::var(CORPUS_VAR) {
+        Ok(path) => fs::read(&path)
+            .unwrap_or_else(|err| panic!("{CORPUS_VAR} should name a readable PCF, '{path}': {err}"))
+            .into(),
+        Err(_) => synthetic().encode_to_vec().into(),
+    }
+}
+
+fn codec(c: &mut Criterion) {
+    let bytes = corpus();
+    let dmx = dmx::decode_bytes(bytes.clone()).unwrap();
+
+    let mut group = c.benchmark_group("dmx");
+    group.throughput(Throughput::Bytes(bytes.len() as u64));
+    group.bench_function("decode", |b| {
+        b.iter(|| dmx::decode(&mut black_box(&bytes[..])).unwrap())
+    });
+    group.bench_function("decode from bytes", |b| {
+        b.iter(|| dmx::decode_bytes(black_box(bytes.clone())).unwrap());
+    });
+    group.bench_function("encode", |b| b.iter(|| black_box(&dmx).encode_to_vec()));
+    group.bench_function("encoded size", |b| b.iter(|| black_box(&dmx).encoded_size()));
+    group.finish();
+}
+
+criterion_group!(benches, codec);
+criterion_main!(benches);
@@ -1,21 +1,27 @@
-use std::{backtrace::Backtrace, ffi::CString, fmt::Display, hash::Hash, io, vec};
+use std::{collections::HashMap, ffi::CString, fmt::Display, hash::Hash, io};
 
 use byteorder::{LittleEndian, ReadBytesExt, WriteBytesExt};
 use derive_more::{From, Into};
 use ordered_float::OrderedFloat;
 use thiserror::Error;
 
-use crate::{ElementIdx, dmx::Element};
+use crate::{
+    ElementIdx, ElementIdxError, Signature, Symbols,
+    blob::Blob,
+    dmx::{self, Element, EncodeErrorKind, ReadBlob, Table},
+    profile::Encoding,
+    reference::{self, ReferencePosition, SIGNATURE_REFERENCE, SignatureReferences},
+};
 pub type NameIndex = u16;
 
-#[derive(Debug, From, Clone, Hash, PartialEq, Eq)]
+#[derive(Debug, From, Clone, Hash, PartialEq, Eq, PartialOrd, Ord)]
 pub enum Attribute {
     Element(ElementIdx),
     Integer(i32),
     Float(Float),
     Bool(Bool8),
     String(CString),
-    Binary(Box<[u8]>),
+    Binary(Blob),
     Color(Color),
     Vector2(Vector2),
     Vector3(Vector3),
@@ -26,7 +32,7 @@ pub enum Attribute {
     FloatArray(Box<[Float]>),
     BoolArray(Box<[Bool8]>),
     StringArray(Box<[CString]>),
-    BinaryArray(Box<[Box<[u8]>]>),
+    BinaryArray(Box<[Blob]>),
     ColorArray(Box<[Color]>),
     Vector2Array(Box<[Vector2]>),
     Vector3Array(Box<[Vector3]>),
@@ -52,12 +58,6 @@ impl From<bool> for Attribute {
     }
 }
 
-// impl From<Box<[u8]>> for Attribute {
-//     fn from(value: Box<[u8]>) -> Self {
-//         Self::Binary(value)
-//     }
-// }
-
 impl Default for Attribute {
     fn default() -> Self {
         Self::Element(ElementIdx::INVALID)
@@ -92,70 +92,181 @@ impl Attribute {
         }
     }
 
+    /// The number of bytes this attribute's value takes up when encoded, excluding its name index & type.
+    pub fn encoded_size(&self) -> usize {
+        fn array<T: FixedEncodedSize>(values: &[T]) -> usize {
+            size_of::<u32>() + values.len() * T::ENCODED_SIZE
+        }
+
+        match self {
+            Attribute::Element(_) => ElementIdx::ENCODED_SIZE,
+            Attribute::Integer(_) => i32::ENCODED_SIZE,
+            Attribute::Float(_) => Float::ENCODED_SIZE,
+            Attribute::Bool(_) => Bool8::ENCODED_SIZE,
+            Attribute::String(value) => value.as_bytes_with_nul().len(),
+            Attribute::Binary(value) => size_of::<u32>() + value.len(),
+            Attribute::Color(_) => Color::ENCODED_SIZE,
+            Attribute::Vector2(_) => Vector2::ENCODED_SIZE,
+            Attribute::Vector3(_) => Vector3::ENCODED_SIZE,
+            Attribute::Vector4(_) => Vector4::ENCODED_SIZE,
+            Attribute::Matrix(_) => Matrix::ENCODED_SIZE,
+            Attribute::ElementArray(values) => array(values),
+            Attribute::IntegerArray(values) => array(values),
+            Attribute::FloatArray(values) => array(values),
+            Attribute::BoolArray(values) => array(values),
+            Attribute::StringArray(values) => {
+                size_of::<u32>()
+                    + values
+                        .iter()
+                        .map(|value| value.as_bytes_with_nul().len())
+                        .sum::<usize>()
+            }
+            Attribute::BinaryArray(values) => {
+                size_of::<u32>() + values.iter().map(|value| size_of::<u32>() + value.len()).sum::<usize>()
+            }
+            Attribute::ColorArray(values) => array(values),
+            Attribute::Vector2Array(values) => array(values),
+            Attribute::Vector3Array(values) => array(values),
+            Attribute::Vector4Array(values) => array(values),
+            Attribute::MatrixArray(values) => array(values),
+        }
+    }
+
+    /// The number of values in an array attribute, or of bytes in a binary attribute, which is encoded as a count
+    /// before them. [`None`] for other attributes.
+    pub fn array_len(&self) -> Option<usize> {
+        match self {
+            Attribute::Binary(value) => Some(value.len()),
+            Attribute::ElementArray(values) => Some(values.len()),
+            Attribute::IntegerArray(values) => Some(values.len()),
+            Attribute::FloatArray(values) => Some(values.len()),
+            Attribute::BoolArray(values) => Some(values.len()),
+            Attribute::StringArray(values) => Some(values.len()),
+            Attribute::BinaryArray(values) => Some(values.len()),
+            Attribute::ColorArray(values) => Some(values.len()),
+            Attribute::Vector2Array(values) => Some(values.len()),
+            Attribute::Vector3Array(values) => Some(values.len()),
+            Attribute::Vector4Array(values) => Some(values.len()),
+            Attribute::MatrixArray(values) => Some(values.len()),
+            _ => None,
+        }
+    }
+
     pub fn is_empty_element_array(&self) -> bool {
         matches!(self, Attribute::ElementArray(items) if items.is_empty())
     }
 }
 
+/// A value which always takes up the same number of bytes when encoded. The size is defined by the format rather than
+/// by the value's type, so it's the same on every platform, whatever the type's size & layout in memory.
+pub trait FixedEncodedSize {
+    const ENCODED_SIZE: usize;
+}
+
+impl FixedEncodedSize for ElementIdx {
+    const ENCODED_SIZE: usize = size_of::<u32>();
+}
+
+impl FixedEncodedSize for i32 {
+    const ENCODED_SIZE: usize = size_of::<i32>();
+}
+
+impl FixedEncodedSize for Float {
+    const ENCODED_SIZE: usize = size_of::<f32>();
+}
+
+impl FixedEncodedSize for Bool8 {
+    const ENCODED_SIZE: usize = size_of::<u8>();
+}
+
+impl FixedEncodedSize for Color {
+    const ENCODED_SIZE: usize = 4 * size_of::<u8>();
+}
+
+impl FixedEncodedSize for Vector2 {
+    const ENCODED_SIZE: usize = 2 * Float::ENCODED_SIZE;
+}
+
+impl FixedEncodedSize for Vector3 {
+    const ENCODED_SIZE: usize = 3 * Float::ENCODED_SIZE;
+}
+
+impl FixedEncodedSize for Vector4 {
+    const ENCODED_SIZE: usize = 4 * Float::ENCODED_SIZE;
+}
+
+impl FixedEncodedSize for Matrix {
+    const ENCODED_SIZE: usize = 4 * Vector4::ENCODED_SIZE;
+}
+
 pub trait ReadAttribute: Sized {
-    type Err: From<io::Error> = io::Error;
+    type Err: From<io::Error>;
     fn read_attribute(reader: &mut impl io::BufRead) -> Result<Self, Self::Err>;
 }
 
 pub trait WriteAttribute: Sized {
-    type Err: From<io::Error> = io::Error;
+    type Err: From<io::Error>;
     fn write_attribute(&self, writer: &mut impl io::Write) -> Result<(), Self::Err>;
 }
 
 impl ReadAttribute for u32 {
+    type Err = io::Error;
     fn read_attribute(reader: &mut impl io::BufRead) -> Result<Self, Self::Err> {
         reader.read_u32::<LittleEndian>()
     }
 }
 
 impl ReadAttribute for ElementIdx {
+    type Err = io::Error;
     fn read_attribute(reader: &mut impl io::BufRead) -> Result<Self, Self::Err> {
         Ok(reader.read_u32::<LittleEndian>()?.into())
     }
 }
 
 impl WriteAttribute for ElementIdx {
+    type Err = io::Error;
     fn write_attribute(&self, writer: &mut impl io::Write) -> Result<(), Self::Err> {
         writer.write_u32::<LittleEndian>((*self).into())
     }
 }
 
 impl ReadAttribute for i32 {
+    type Err = io::Error;
     fn read_attribute(reader: &mut impl io::BufRead) -> Result<Self, Self::Err> {
         reader.read_i32::<LittleEndian>()
     }
 }
 
 impl WriteAttribute for i32 {
+    type Err = io::Error;
     fn write_attribute(&self, writer: &mut impl io::Write) -> Result<(), Self::Err> {
         writer.write_i32::<LittleEndian>(*self)
     }
 }
 
-impl ReadAttribute for OrderedFloat<f32> {
+impl ReadAttribute for Float {
+    type Err = io::Error;
     fn read_attribute(reader: &mut impl io::BufRead) -> Result<Self, Self::Err> {
         Ok(Self::from(reader.read_f32::<LittleEndian>()?))
     }
 }
 
-impl WriteAttribute for OrderedFloat<f32> {
+impl WriteAttribute for Float {
+    type Err = io::Error;
     fn write_attribute(&self, writer: &mut impl io::Write) -> Result<(), Self::Err> {
         writer.write_f32::<LittleEndian>(self.into_inner())
     }
 }
 
 impl ReadAttribute for Bool8 {
+    type Err = io::Error;
     fn read_attribute(reader: &mut impl io::BufRead) -> Result<Self, Self::Err> {
         Ok(Self::from(reader.read_u8()?))
     }
 }
 
 impl WriteAttribute for Bool8 {
+    type Err = io::Error;
     fn write_attribute(&self, writer: &mut impl io::Write) -> Result<(), Self::Err> {
         writer.write_u8(self.0)
     }
@@ -171,22 +282,14 @@ impl ReadAttribute for CString {
 }
 
 impl WriteAttribute for CString {
+    type Err = io::Error;
     fn write_attribute(&self, writer: &mut impl io::Write) -> Result<(), Self::Err> {
         writer.write_all(self.as_bytes_with_nul())
     }
 }
 
-impl ReadAttribute for Box<[u8]> {
-    fn read_attribute(reader: &mut impl io::BufRead) -> Result<Self, Self::Err> {
-        let count = reader.read_u32::<LittleEndian>()? as usize;
-
-        let mut buf = vec![0; count].into_boxed_slice();
-        reader.read_exact(&mut buf)?;
-        Ok(buf)
-    }
-}
-
-impl WriteAttribute for Box<[u8]> {
+impl WriteAttribute for Blob {
+    type Err = io::Error;
     fn write_attribute(&self, writer: &mut impl io::Write) -> Result<(), Self::Err> {
         writer.write_u32::<LittleEndian>(self.len() as u32)?;
         writer.write_all(self)
@@ -194,6 +297,7 @@ impl WriteAttribute for Box<[u8]> {
 }
 
 impl ReadAttribute for Color {
+    type Err = io::Error;
     fn read_attribute(reader: &mut impl io::BufRead) -> Result<Self, Self::Err> {
         Ok(Self(
             reader.read_u8()?,
@@ -205,6 +309,7 @@ impl ReadAttribute for Color {
 }
 
 impl WriteAttribute for Color {
+    type Err = io::Error;
     fn write_attribute(&self, writer: &mut impl io::Write) -> Result<(), Self::Err> {
         writer.write_u8(self.0)?;
         writer.write_u8(self.1)?;
@@ -215,6 +320,7 @@ impl WriteAttribute for Color {
 }
 
 impl ReadAttribute for Vector2 {
+    type Err = io::Error;
     fn read_attribute(reader: &mut impl io::BufRead) -> Result<Self, Self::Err> {
         Ok(Self(
             reader.read_f32::<LittleEndian>()?.into(),
@@ -224,6 +330,7 @@ impl ReadAttribute for Vector2 {
 }
 
 impl WriteAttribute for Vector2 {
+    type Err = io::Error;
     fn write_attribute(&self, writer: &mut impl io::Write) -> Result<(), Self::Err> {
         writer.write_f32::<LittleEndian>(self.0.into_inner())?;
         writer.write_f32::<LittleEndian>(self.1.into_inner())?;
@@ -232,6 +339,7 @@ impl WriteAttribute for Vector2 {
 }
 
 impl ReadAttribute for Vector3 {
+    type Err = io::Error;
     fn read_attribute(reader: &mut impl io::BufRead) -> Result<Self, Self::Err> {
         Ok(Self(
             reader.read_f32::<LittleEndian>()?.into(),
@@ -242,6 +350,7 @@ impl ReadAttribute for Vector3 {
 }
 
 impl WriteAttribute for Vector3 {
+    type Err = io::Error;
     fn write_attribute(&self, writer: &mut impl io::Write) -> Result<(), Self::Err> {
         writer.write_f32::<LittleEndian>(self.0.into_inner())?;
         writer.write_f32::<LittleEndian>(self.1.into_inner())?;
@@ -251,6 +360,7 @@ impl WriteAttribute for Vector3 {
 }
 
 impl ReadAttribute for Vector4 {
+    type Err = io::Error;
     fn read_attribute(reader: &mut impl io::BufRead) -> Result<Self, Self::Err> {
         Ok(Self(
             reader.read_f32::<LittleEndian>()?.into(),
@@ -262,6 +372,7 @@ impl ReadAttribute for Vector4 {
 }
 
 impl WriteAttribute for Vector4 {
+    type Err = io::Error;
     fn write_attribute(&self, writer: &mut impl io::Write) -> Result<(), Self::Err> {
         writer.write_f32::<LittleEndian>(self.0.into_inner())?;
         writer.write_f32::<LittleEndian>(self.1.into_inner())?;
@@ -272,6 +383,7 @@ impl WriteAttribute for Vector4 {
 }
 
 impl ReadAttribute for Matrix {
+    type Err = io::Error;
     fn read_attribute(reader: &mut impl io::BufRead) -> Result<Self, Self::Err> {
         Ok(Self(
             Vector4::read_attribute(reader)?,
@@ -283,6 +395,7 @@ impl ReadAttribute for Matrix {
 }
 
 impl WriteAttribute for Matrix {
+    type Err = io::Error;
     fn write_attribute(&self, writer: &mut impl io::Write) -> Result<(), Self::Err> {
         self.0.write_attribute(writer)?;
         self.1.write_attribute(writer)?;
@@ -292,13 +405,25 @@ impl WriteAttribute for Matrix {
     }
 }
 
-pub(crate) struct AttributeReader<'a, R: std::io::BufRead> {
+pub(crate) struct AttributeReader<'a, R: ReadBlob> {
     element_count: usize,
     first_attribute_count: usize,
     reader: &'a mut R,
+    encoding: Encoding,
+
+    /// the DMX's strings, for encodings which store string attributes as symbols
+    strings: &'a Symbols,
+
+    /// the elements whose attributes are being read, to resolve references which are encoded by signature
+    elements: &'a [Element],
+
+    /// the index of every element, by signature. Most DMXs never encode a reference by signature, so it's only built
+    /// once one is read.
+    element_indices: Option<HashMap<Signature, usize>>,
+    signature_references: SignatureReferences,
 }
 
-pub(crate) struct AttributeIterator<'a, R: std::io::BufRead> {
+pub(crate) struct AttributeIterator<'a, R: ReadBlob> {
     element_count: usize,
     current_element: usize,
     current_attribute_count: usize,
@@ -308,15 +433,21 @@ pub(crate) struct AttributeIterator<'a, R: std::io::BufRead> {
 
 pub(crate) struct AttributeWriter<'a, W: io::Write> {
     writer: &'a mut W,
-}
+    encoding: Encoding,
 
-impl<'a, W: io::Write> From<&'a mut W> for AttributeWriter<'a, W> {
-    fn from(writer: &'a mut W) -> Self {
-        Self { writer }
-    }
+    /// the DMX's strings, for encodings which store string attributes as symbols
+    strings: &'a Symbols,
 }
 
 impl<'a, W: io::Write> AttributeWriter<'a, W> {
+    pub fn new(writer: &'a mut W, encoding: Encoding, strings: &'a Symbols) -> Self {
+        Self {
+            writer,
+            encoding,
+            strings,
+        }
+    }
+
     fn write<T: WriteAttribute>(&mut self, value: &T) -> Result<(), T::Err> {
         value.write_attribute(&mut self.writer)
     }
@@ -357,13 +488,79 @@ impl<'a, W: io::Write> AttributeWriter<'a, W> {
         }
     }
 
-    pub fn write_attributes(&mut self, elements: &Vec<Element>) -> Result<(), io::Error> {
-        for element in elements {
-            self.writer.write_u32::<LittleEndian>(element.attributes.len() as u32)?;
+    /// Writes a reference to `target`, by signature if the reference at `position` was decoded from one.
+    fn write_reference(
+        &mut self,
+        elements: &[Element],
+        references: &SignatureReferences,
+        position: ReferencePosition,
+        target: ElementIdx,
+    ) -> Result<(), io::Error> {
+        let Some(signature) = references.get(&position) else {
+            return self.write(&target);
+        };
+
+        // the reference may have been changed since it was decoded, so the signature of its target takes precedence
+        let signature = elements
+            .get(usize::from(target))
+            .map_or(signature, |element| &element.signature);
+        self.writer.write_u32::<LittleEndian>(SIGNATURE_REFERENCE)?;
+        self.write(&reference::format_signature(signature))
+    }
+
+    /// Writes the attributes of every element in `elements`.
+    ///
+    /// # Errors
+    ///
+    /// Returns [`EncodeErrorKind::Limit`] before writing an element's attributes if it has too many, or before writing
+    /// an array attribute with too many values. See [`LimitError`](dmx::LimitError).
+    pub fn write_attributes(
+        &mut self,
+        elements: &[Element],
+        references: &SignatureReferences,
+    ) -> Result<(), EncodeErrorKind> {
+        for (element_idx, element) in elements.iter().enumerate() {
+            let element_idx = ElementIdx::try_from(element_idx)?;
+            let attribute_count =
+                dmx::encoded_count(element.attributes.len(), u32::MAX, || Table::Attributes(element_idx))?;
+            self.writer.write_u32::<LittleEndian>(attribute_count)?;
             for (name_idx, attribute) in &element.attributes {
-                self.writer.write_u16::<LittleEndian>(*name_idx)?;
+                if let Some(len) = attribute.array_len() {
+                    dmx::encoded_count(len, u32::MAX, || Table::Array {
+                        element: element_idx,
+                        name: self
+                            .strings
+                            .get_index(usize::from(*name_idx))
+                            .map_or_else(|| format!("#{name_idx}"), |name| name.to_string_lossy().into_owned()),
+                    })?;
+                }
+
+                self.encoding.write_symbol(&mut self.writer, *name_idx)?;
                 self.writer.write_u8(attribute.as_type())?;
-                self.write_attribute(attribute)?;
+
+                let position = |item| ReferencePosition {
+                    element: element_idx,
+                    name_idx: *name_idx,
+                    item,
+                };
+
+                match attribute {
+                    Attribute::Element(target) => {
+                        self.write_reference(elements, references, position(None), *target)?;
+                    }
+                    Attribute::ElementArray(targets) => {
+                        // the length was checked above
+                        self.writer.write_u32::<LittleEndian>(targets.len() as u32)?;
+                        for (item, target) in targets.iter().enumerate() {
+                            self.write_reference(elements, references, position(Some(item)), *target)?;
+                        }
+                    }
+                    Attribute::String(value) if self.encoding.strings_are_symbols => {
+                        let symbol = dmx::symbol_of(self.strings, value)?;
+                        self.encoding.write_symbol(&mut self.writer, symbol)?;
+                    }
+                    _ => self.write_attribute(attribute)?,
+                }
             }
         }
         Ok(())
@@ -372,27 +569,32 @@ impl<'a, W: io::Write> AttributeWriter<'a, W> {
 
 #[derive(Debug, Error)]
 pub enum ReadError {
-    #[error("{source}")]
-    Io {
-        #[from]
-        source: io::Error,
-        backtrace: Backtrace,
-    },
+    #[error(transparent)]
+    Io(#[from] io::Error),
 
     #[error(transparent)]
     CStringFromVec(#[from] std::ffi::FromVecWithNulError),
 
     #[error("the attribute type {0} is unsupported or invalid")]
     InvalidAttributeType(u8),
+
+    #[error("the element reference '{0}' isn't a valid GUID")]
+    InvalidSignature(String),
+
+    #[error("the symbol index {0} is out of range")]
+    SymbolOutOfRange(u32),
+
+    #[error(transparent)]
+    ElementIdx(#[from] ElementIdxError),
 }
 
-impl<'a, R: std::io::BufRead> Iterator for AttributeIterator<'a, R> {
+impl<'a, R: ReadBlob> Iterator for AttributeIterator<'a, R> {
     type Item = Result<(usize, NameIndex, Attribute), ReadError>;
 
     fn next(&mut self) -> Option<Self::Item> {
         if self.current_attribute < self.current_attribute_count {
             self.current_attribute += 1;
-            match self.reader.read_attribute() {
+            match self.reader.read_attribute(self.current_element) {
                 Ok((name_idx, attribute)) => Some(Ok((self.current_element, name_idx, attribute))),
                 Err(err) => Some(Err(err)),
             }
@@ -414,7 +616,14 @@ impl<'a, R: std::io::BufRead> Iterator for AttributeIterator<'a, R> {
     }
 }
 
-impl<'a, R: std::io::BufRead> IntoIterator for AttributeReader<'a, R> {
+impl<'a, R: ReadBlob> AttributeIterator<'a, R> {
+    /// The element references which were read by signature. See [`SignatureReferences`].
+    pub fn into_signature_references(self) -> SignatureReferences {
+        self.reader.signature_references
+    }
+}
+
+impl<'a, R: ReadBlob> IntoIterator for AttributeReader<'a, R> {
     type Item = Result<(usize, NameIndex, Attribute), ReadError>;
 
     type IntoIter = AttributeIterator<'a, R>;
@@ -430,8 +639,16 @@ impl<'a, R: std::io::BufRead> IntoIterator for AttributeReader<'a, R> {
     }
 }
 
-impl<'a, R: std::io::BufRead> AttributeReader<'a, R> {
-    pub fn try_from(reader: &'a mut R, element_count: usize) -> Result<Self, ReadError> {
+impl<'a, R: ReadBlob> AttributeReader<'a, R> {
+    /// Reads the attributes of `elements`.
+    pub fn try_from(
+        reader: &'a mut R,
+        encoding: Encoding,
+        strings: &'a Symbols,
+        elements: &'a [Element],
+    ) -> Result<Self, ReadError> {
+        let element_count = elements.len();
+
         // we always read the first attribute count; next() expects that the element_count and
         // current_attribute_count have both been set when applicable.
         let current_attribute_count = if element_count > 0 {
@@ -442,8 +659,13 @@ impl<'a, R: std::io::BufRead> AttributeReader<'a, R> {
 
         Ok(Self {
             reader,
+            encoding,
+            strings,
             element_count,
             first_attribute_count: current_attribute_count,
+            elements,
+            element_indices: None,
+            signature_references: SignatureReferences::new(),
         })
     }
 
@@ -462,28 +684,84 @@ impl<'a, R: std::io::BufRead> AttributeReader<'a, R> {
         Ok(buf.into_boxed_slice())
     }
 
-    pub fn read_attribute(&mut self) -> Result<(NameIndex, Attribute), ReadError> {
-        let name_idx = self.reader.read_u16::<LittleEndian>()?;
+    /// Reads a binary value, sharing the decoded buffer if there is one. See [`Blob`].
+    fn read_blob(&mut self) -> io::Result<Blob> {
+        let len = self.read::<u32>()? as usize;
+        self.reader.read_blob(len)
+    }
+
+    /// Reads an element reference, resolving it to an index if it's encoded by signature.
+    fn read_reference(&mut self, position: ReferencePosition) -> Result<ElementIdx, ReadError> {
+        let idx = self.read::<u32>()?;
+        if idx != SIGNATURE_REFERENCE {
+            return Ok(idx.into());
+        }
+
+        let guid = self.read::<CString>()?;
+        let guid = guid.to_string_lossy();
+        let signature = reference::parse_signature(&guid).ok_or_else(|| ReadError::InvalidSignature(guid.into()))?;
+        self.signature_references.insert(position, signature);
+
+        let elements = self.elements;
+        let element_indices = self.element_indices.get_or_insert_with(|| {
+            elements
+                .iter()
+                .enumerate()
+                .map(|(idx, element)| (element.signature, idx))
+                .collect()
+        });
+
+        // the referenced element may be in another file, so it can't be resolved
+        match element_indices.get(&signature) {
+            Some(&idx) => Ok(ElementIdx::try_from(idx)?),
+            None => Ok(ElementIdx::INVALID),
+        }
+    }
+
+    /// Reads one attribute of the element at `element`.
+    pub fn read_attribute(&mut self, element: usize) -> Result<(NameIndex, Attribute), ReadError> {
+        let name_idx = self.encoding.read_symbol(&mut self.reader)?;
         let type_idx = self.reader.read_u8()?;
+        let element = ElementIdx::try_from(element)?;
+        let position = |item| ReferencePosition {
+            element,
+            name_idx,
+            item,
+        };
 
         match type_idx {
-            1 => Ok(self.read::<ElementIdx>()?.into()),
+            1 => Ok(self.read_reference(position(None))?.into()),
             2 => Ok(self.read::<i32>()?.into()),
             3 => Ok(self.read::<Float>()?.into()),
             4 => Ok(self.read::<Bool8>()?.into()),
+            5 if self.encoding.strings_are_symbols => {
+                let idx = self.encoding.read_symbol(&mut self.reader)?;
+                let value = self.strings.get_index(usize::from(idx));
+                Ok(value.cloned().ok_or(ReadError::SymbolOutOfRange(idx.into()))?.into())
+            }
             5 => Ok(self.read::<CString>()?.into()),
-            6 => Ok(self.read::<Box<[u8]>>()?.into()),
+            6 => Ok(self.read_blob()?.into()),
             8 => Ok(self.read::<Color>()?.into()),
             9 => Ok(self.read::<Vector2>()?.into()),
             10 => Ok(self.read::<Vector3>()?.into()),
             11 => Ok(self.read::<Vector4>()?.into()),
             14 => Ok(self.read::<Matrix>()?.into()),
-            15 => Ok(self.read_array::<ElementIdx>()?.into()),
+            15 => {
+                let count = self.read::<u32>()? as usize;
+                let references: Result<Vec<_>, _> = (0..count)
+                    .map(|item| self.read_reference(position(Some(item))))
+                    .collect();
+                Ok(references?.into_boxed_slice().into())
+            }
             16 => Ok(self.read_array::<i32>()?.into()),
             17 => Ok(self.read_array::<Float>()?.into()),
             18 => Ok(self.read_array::<Bool8>()?.into()),
             19 => Ok(self.read_array::<CString>()?.into()),
-            20 => Ok(self.read_array::<Box<[u8]>>()?.into()),
+            20 => {
+                let count = self.read::<u32>()? as usize;
+                let blobs: Result<Vec<_>, _> = (0..count).map(|_| self.read_blob()).collect();
+                Ok(blobs?.into_boxed_slice().into())
+            }
             22 => Ok(self.read_array::<Color>()?.into()),
             23 => Ok(self.read_array::<Vector2>()?.into()),
             24 => Ok(self.read_array::<Vector3>()?.into()),
@@ -495,7 +773,7 @@ impl<'a, R: std::io::BufRead> AttributeReader<'a, R> {
     }
 }
 
-#[derive(Debug, From, Into, Clone, Copy, Hash, PartialEq, Eq)]
+#[derive(Debug, From, Into, Clone, Copy, Hash, PartialEq, Eq, PartialOrd, Ord)]
 /// An 8-bit boolean value. 0 is false, all other values are truthy.
 pub struct Bool8(u8);
 
@@ -521,24 +799,141 @@ impl Display for Bool8 {
     }
 }
 
+/// Every float in a DMX is wrapped in [`OrderedFloat`], which gives floats a total ordering and consistent hashing -
+/// NaN is equal to itself, and -0.0 is equal to 0.0 - so attributes can be compared, sorted and used as map keys.
 pub type Float = OrderedFloat<f32>;
 
-#[derive(Debug, Clone, Copy, Hash, PartialEq, Eq, derive_more::Display)]
+#[derive(Debug, Clone, Copy, Hash, PartialEq, Eq, PartialOrd, Ord, derive_more::Display)]
 #[display("Color({_0}, {_1}, {_2}, {_3})")]
 pub struct Color(pub u8, pub u8, pub u8, pub u8);
 
-#[derive(Debug, Clone, Copy, Hash, PartialEq, Eq, derive_more::Display)]
-#[display("Vector2({_0:.2}, {_1:.2})")]
+/// Displayed with 2 decimals, or with every float in full with the alternate flag, e.g. `{:#}`.
+#[derive(Debug, Clone, Copy, Hash, PartialEq, Eq, PartialOrd, Ord)]
 pub struct Vector2(pub Float, pub Float);
 
-#[derive(Debug, Default, Clone, Copy, Hash, PartialEq, Eq, derive_more::Display)]
-#[display("Vector3({_0:.2}, {_1:.2}, {_2:.2})")]
+/// Displayed like [`Vector2`].
+#[derive(Debug, Default, Clone, Copy, Hash, PartialEq, Eq, PartialOrd, Ord)]
 pub struct Vector3(pub Float, pub Float, pub Float);
 
-#[derive(Debug, Clone, Copy, Hash, PartialEq, Eq, derive_more::Display)]
-#[display("Vector4({_0:.2}, {_1:.2}, {_2:.2}, {_3:.2})")]
+/// Displayed like [`Vector2`].
+#[derive(Debug, Clone, Copy, Hash, PartialEq, Eq, PartialOrd, Ord)]
 pub struct Vector4(pub Float, pub Float, pub Float, pub Float);
 
-#[derive(Debug, Clone, Copy, Hash, PartialEq, Eq, derive_more::Display)]
-#[display("Matrix(...)")]
+/// Displayed as `Matrix(...)`, or with every row in full with the alternate flag, e.g. `{:#}`.
+#[derive(Debug, Clone, Copy, Hash, PartialEq, Eq, PartialOrd, Ord)]
 pub struct Matrix(pub Vector4, pub Vector4, pub Vector4, pub Vector4);
+
+/// Writes `name(a, b, ...)`, rounding each float to 2 decimals unless the alternate flag is set.
+fn fmt_floats(f: &mut std::fmt::Formatter<'_>, name: &str, floats: &[Float]) -> std::fmt::Result {
+    write!(f, "{name}(")?;
+    for (idx, float) in floats.iter().enumerate() {
+        if idx > 0 {
+            f.write_str(", ")?;
+        }
+
+        if f.alternate() {
+            write!(f, "{float}")?;
+        } else {
+            write!(f, "{float:.2}")?;
+        }
+    }
+    f.write_str(")")
+}
+
+impl Display for Vector2 {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        fmt_floats(f, "Vector2", &[self.0, self.1])
+    }
+}
+
+impl Display for Vector3 {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        fmt_floats(f, "Vector3", &[self.0, self.1, self.2])
+    }
+}
+
+impl Display for Vector4 {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        fmt_floats(f, "Vector4", &[self.0, self.1, self.2, self.3])
+    }
+}
+
+impl Display for Matrix {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        if f.alternate() {
+            write!(f, "Matrix({:#}, {:#}, {:#}, {:#})", self.0, self.1, self.2, self.3)
+        } else {
+            f.write_str("Matrix(...)")
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::{fmt::Debug, io};
+
+    use crate::{
+        Blob, Color, ElementIdx, FixedEncodedSize, Float, Matrix, Vector2, Vector3, Vector4,
+        attribute::{Bool8, ReadAttribute, WriteAttribute},
+    };
+
+    /// Checks that `value` encodes to `expected`, whatever the platform's endianness, and decodes back to itself.
+    fn assert_encoding<T>(value: T, expected: &[u8])
+    where
+        T: ReadAttribute<Err = io::Error> + WriteAttribute<Err = io::Error> + FixedEncodedSize + PartialEq + Debug,
+    {
+        let mut bytes = Vec::new();
+        value.write_attribute(&mut bytes).unwrap();
+        assert_eq!(bytes, expected);
+        assert_eq!(bytes.len(), T::ENCODED_SIZE);
+        assert_eq!(T::read_attribute(&mut &bytes[..]).unwrap(), value);
+    }
+
+    #[test]
+    fn values_are_always_little_endian() {
+        assert_encoding(ElementIdx::from(0x0102_0304u32), &[4, 3, 2, 1]);
+        assert_encoding(-2i32, &[0xfe, 0xff, 0xff, 0xff]);
+        assert_encoding(Float::from(1.0), &[0, 0, 0x80, 0x3f]);
+        assert_encoding(Bool8::from(true), &[1]);
+        assert_encoding(Color(1, 2, 3, 4), &[1, 2, 3, 4]);
+        assert_encoding(Vector2(1.0.into(), (-2.0).into()), &[0, 0, 0x80, 0x3f, 0, 0, 0, 0xc0]);
+        assert_encoding(
+            Vector3(0.0.into(), 1.0.into(), 0.5.into()),
+            &[0, 0, 0, 0, 0, 0, 0x80, 0x3f, 0, 0, 0, 0x3f],
+        );
+
+        let row = Vector4(1.0.into(), 0.0.into(), 0.0.into(), 0.0.into());
+        let row_bytes = [0, 0, 0x80, 0x3f, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0];
+        assert_encoding(row, &row_bytes);
+        assert_encoding(Matrix(row, row, row, row), &row_bytes.repeat(4));
+    }
+
+    #[test]
+    fn vectors_are_rounded_unless_alternate() {
+        let vector = Vector2(1.23456.into(), (-0.5).into());
+
+        assert_eq!(vector.to_string(), "Vector2(1.23, -0.50)");
+        assert_eq!(format!("{vector:#}"), "Vector2(1.23456, -0.5)");
+    }
+
+    #[test]
+    fn matrices_show_their_rows_when_alternate() {
+        let row = Vector4(1.0.into(), 0.0.into(), 0.0.into(), 0.125.into());
+        let matrix = Matrix(row, row, row, row);
+
+        assert_eq!(matrix.to_string(), "Matrix(...)");
+        let shown = "Vector4(1, 0, 0, 0.125)";
+        assert_eq!(
+            format!("{matrix:#}"),
+            format!("Matrix({shown}, {shown}, {shown}, {shown})")
+        );
+    }
+
+    #[test]
+    fn blobs_are_hex_when_alternate() {
+        let blob = Blob::from([0x0a, 0xff, 0x00]);
+
+        assert_eq!(blob.to_string(), "<3 bytes>");
+        assert_eq!(format!("{blob:#}"), "0aff00");
+    }
+}
@@ -0,0 +1,93 @@
+//! Binary attribute values.
+//!
+//! Some PCFs embed large binary attributes, like baked curves, which most tools never look at. When a DMX is decoded
+//! with [`Dmx::decode_bytes`](crate::Dmx::decode_bytes), each [`Blob`] shares the decoded buffer - storing only where
+//! its bytes are in it - so they aren't copied unless they're changed or [owned](Blob::into_owned). The buffer can be
+//! backed by anything [`Bytes`] supports, e.g. a memory-mapped file, in which case blobs that are never read are never
+//! paged in either.
+
+use std::{fmt, ops::Deref};
+
+use bytes::Bytes;
+
+/// The bytes of a binary attribute. Cloning a blob is cheap, since clones share the same bytes.
+///
+/// A blob decoded with [`Dmx::decode_bytes`](crate::Dmx::decode_bytes) keeps the whole decoded buffer alive, so one
+/// that outlives the rest of the DMX should be [owned](Blob::into_owned) first.
+#[derive(Clone, Default, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub struct Blob(Bytes);
+
+impl Blob {
+    pub const fn from_static(bytes: &'static [u8]) -> Self {
+        Self(Bytes::from_static(bytes))
+    }
+
+    pub fn as_bytes(&self) -> &[u8] {
+        &self.0
+    }
+
+    /// Copies the blob out of the buffer it was decoded from, so that it doesn't keep the rest of the buffer alive.
+    pub fn into_owned(self) -> Self {
+        Self(Bytes::copy_from_slice(&self.0))
+    }
+
+    pub fn into_bytes(self) -> Bytes {
+        self.0
+    }
+}
+
+/// Blobs can be large, so only their length is shown.
+impl fmt::Debug for Blob {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "Blob(<{} bytes>)", self.0.len())
+    }
+}
+
+/// Displayed as `<N bytes>`, or as lowercase hex with the alternate flag, e.g. `{:#}`.
+impl fmt::Display for Blob {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        if f.alternate() {
+            self.0.iter().try_for_each(|byte| write!(f, "{byte:02x}"))
+        } else {
+            write!(f, "<{} bytes>", self.0.len())
+        }
+    }
+}
+
+impl Deref for Blob {
+    type Target = [u8];
+
+    fn deref(&self) -> &Self::Target {
+        &self.0
+    }
+}
+
+impl AsRef<[u8]> for Blob {
+    fn as_ref(&self) -> &[u8] {
+        &self.0
+    }
+}
+
+impl From<Bytes> for Blob {
+    fn from(bytes: Bytes) -> Self {
+        Self(bytes)
+    }
+}
+
+impl From<Vec<u8>> for Blob {
+    fn from(bytes: Vec<u8>) -> Self {
+        Self(bytes.into())
+    }
+}
+
+impl From<Box<[u8]>> for Blob {
+    fn from(bytes: Box<[u8]>) -> Self {
+        Self(bytes.into())
+    }
+}
+
+impl<const N: usize> From<[u8; N]> for Blob {
+    fn from(bytes: [u8; N]) -> Self {
+        Self(Bytes::copy_from_slice(&bytes))
+    }
+}
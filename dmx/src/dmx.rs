@@ -1,17 +1,23 @@
 use std::{
+    borrow::Cow,
     ffi::{CStr, CString},
     fmt::Display,
+    io::{self, BufRead, Read, Write},
     str::FromStr,
 };
 
 use byteorder::{LittleEndian, ReadBytesExt, WriteBytesExt};
+use bytes::{Buf, Bytes};
 use itertools::Itertools;
 use ordermap::OrderMap;
 use thiserror::Error;
 
 use crate::{
-    Signature, SymbolIdx, Symbols,
-    attribute::{Attribute, AttributeReader, AttributeWriter},
+    ElementIdx, ElementIdxError, Signature, SymbolIdx, Symbols,
+    attribute::{Attribute, AttributeReader, AttributeWriter, ReadError},
+    blob::Blob,
+    profile::{Encoding, PcfProfile, Profile},
+    reference::{GUID_STRING_LEN, SIGNATURE_REFERENCE, SignatureReferences},
 };
 
 #[derive(Debug, Clone, Default, PartialEq)]
@@ -23,6 +29,9 @@ pub struct Dmx {
     pub version: Version,
     pub strings: Symbols,
     pub elements: Vec<Element>,
+
+    /// the element references which are encoded by signature rather than by index. See [`crate::reference`].
+    pub signature_references: SignatureReferences,
 }
 
 #[derive(Debug, Clone, PartialEq)]
@@ -39,21 +48,30 @@ pub enum Version {
     #[default]
     Binary2Pcf1,
     Binary3Pcf1,
+
+    /// A DMX which isn't a PCF, e.g. an SFM session or a model. See [`crate::profile`].
+    #[cfg(feature = "dialects")]
+    Dialect(crate::profile::DialectVersion),
 }
 
 impl Version {
-    pub fn as_cstr_with_nul_terminator(&self) -> &'static CStr {
+    pub fn as_cstr_with_nul_terminator(&self) -> Cow<'static, CStr> {
         match self {
-            Version::Binary2Dmx1 => c"<!-- dmx encoding binary 2 format dmx 1 -->\x0A",
-            Version::Binary2Pcf1 => c"<!-- dmx encoding binary 2 format pcf 1 -->\x0A",
-            Version::Binary3Pcf1 => c"<!-- dmx encoding binary 3 format pcf 1 -->\x0A",
+            Version::Binary2Dmx1 => Cow::Borrowed(c"<!-- dmx encoding binary 2 format dmx 1 -->\x0A"),
+            Version::Binary2Pcf1 => Cow::Borrowed(c"<!-- dmx encoding binary 2 format pcf 1 -->\x0A"),
+            Version::Binary3Pcf1 => Cow::Borrowed(c"<!-- dmx encoding binary 3 format pcf 1 -->\x0A"),
+            #[cfg(feature = "dialects")]
+            Version::Dialect(version) => Cow::Owned(version.header()),
         }
     }
-}
 
-impl From<Version> for &CStr {
-    fn from(value: Version) -> Self {
-        value.as_cstr_with_nul_terminator()
+    /// The profile of the dialect this version belongs to, which a DMX with this version is encoded with.
+    pub fn profile(&self) -> &'static dyn Profile {
+        match self {
+            #[cfg(feature = "dialects")]
+            Version::Dialect(_) => &crate::profile::DialectProfile,
+            _ => &PcfProfile,
+        }
     }
 }
 
@@ -63,6 +81,8 @@ impl Display for Version {
             Version::Binary2Dmx1 => "Binary2Dmx1",
             Version::Binary2Pcf1 => "Binary2Pcf1",
             Version::Binary3Pcf1 => "Binary3Pcf1",
+            #[cfg(feature = "dialects")]
+            Version::Dialect(version) => return version.fmt(f),
         })
     }
 }
@@ -71,6 +91,10 @@ impl Display for Version {
 pub enum ParseVersionError {
     #[error("the version string was invalid: '{0}'")]
     Invalid(String),
+
+    #[cfg(feature = "dialects")]
+    #[error("binary encoding {0} isn't supported")]
+    UnsupportedEncoding(u32),
 }
 
 impl FromStr for Version {
@@ -92,10 +116,42 @@ impl FromStr for Version {
     }
 }
 
+/// A part of an encoded DMX, in the order they're encoded.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Section {
+    Header,
+    Symbols,
+    Elements,
+    Attributes,
+}
+
+impl Display for Section {
+    fn fmt(&self, f: &mut std::fmt::Formatter) -> std::fmt::Result {
+        f.write_str(match self {
+            Section::Header => "header",
+            Section::Symbols => "symbols",
+            Section::Elements => "elements",
+            Section::Attributes => "attributes",
+        })
+    }
+}
+
 #[derive(Debug, Error)]
-pub enum Error {
+#[error("couldn't decode the DMX's {section}, at byte {offset}")]
+pub struct DecodeError {
+    pub section: Section,
+
+    /// the number of bytes read before the error occurred
+    pub offset: u64,
+
+    #[source]
+    pub kind: DecodeErrorKind,
+}
+
+#[derive(Debug, Error)]
+pub enum DecodeErrorKind {
     #[error(transparent)]
-    Io(#[from] std::io::Error),
+    Io(#[from] io::Error),
 
     #[error(transparent)]
     CStringFromVec(#[from] std::ffi::FromVecWithNulError),
@@ -108,6 +164,170 @@ pub enum Error {
 
     #[error(transparent)]
     AttributeReadError(#[from] crate::attribute::ReadError),
+
+    #[error(transparent)]
+    ElementIdx(#[from] ElementIdxError),
+}
+
+#[derive(Debug, Error)]
+#[error("couldn't encode the DMX's {section}, at byte {offset}")]
+pub struct EncodeError {
+    pub section: Section,
+
+    /// the number of bytes written before the error occurred
+    pub offset: u64,
+
+    #[source]
+    pub kind: EncodeErrorKind,
+}
+
+#[derive(Debug, Error)]
+pub enum EncodeErrorKind {
+    #[error(transparent)]
+    Io(#[from] io::Error),
+
+    #[error(transparent)]
+    Limit(#[from] LimitError),
+
+    #[error(transparent)]
+    ElementIdx(#[from] ElementIdxError),
+}
+
+/// A part of an encoded DMX whose length is written as a fixed-size count.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum Table {
+    Symbols,
+    Elements,
+
+    /// the attributes of an element
+    Attributes(ElementIdx),
+
+    /// the values of an array attribute, or the bytes of a binary attribute
+    Array {
+        element: ElementIdx,
+        name: String,
+    },
+}
+
+impl Display for Table {
+    fn fmt(&self, f: &mut std::fmt::Formatter) -> std::fmt::Result {
+        match self {
+            Table::Symbols => f.write_str("string table"),
+            Table::Elements => f.write_str("element table"),
+            Table::Attributes(element) => write!(f, "attribute table of element {element}"),
+            Table::Array { element, name } => write!(f, "'{name}' attribute of element {element}"),
+        }
+    }
+}
+
+/// A table is too long for its count to be encoded. Encoding stops before the count is written, rather than writing a
+/// truncated count & producing a corrupt file.
+#[derive(Debug, Error)]
+#[error("the {table} has {len} entries, but at most {max} can be encoded")]
+pub struct LimitError {
+    pub table: Table,
+    pub len: usize,
+    pub max: u32,
+}
+
+/// `len` as the count of `table`, if it's at most `max`.
+pub(crate) fn encoded_count(len: usize, max: u32, table: impl FnOnce() -> Table) -> Result<u32, LimitError> {
+    u32::try_from(len)
+        .ok()
+        .filter(|count| *count <= max)
+        .ok_or_else(|| LimitError {
+            table: table(),
+            len,
+            max,
+        })
+}
+
+/// Counts the bytes read from or written to `inner`, so errors can report where they occurred.
+struct Counting<T> {
+    inner: T,
+    count: u64,
+
+    /// the whole buffer being decoded, when it's shared with the decoded blobs. See [`Dmx::decode_bytes`].
+    shared: Option<Bytes>,
+}
+
+impl<T> Counting<T> {
+    fn new(inner: T) -> Self {
+        Self {
+            inner,
+            count: 0,
+            shared: None,
+        }
+    }
+}
+
+impl<R: Read> Read for Counting<R> {
+    fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+        let read = self.inner.read(buf)?;
+        self.count += read as u64;
+        Ok(read)
+    }
+}
+
+impl<R: BufRead> BufRead for Counting<R> {
+    fn fill_buf(&mut self) -> io::Result<&[u8]> {
+        self.inner.fill_buf()
+    }
+
+    fn consume(&mut self, amount: usize) {
+        self.count += amount as u64;
+        self.inner.consume(amount);
+    }
+}
+
+/// Reads the values of binary attributes. See [`Blob`].
+pub(crate) trait ReadBlob: BufRead {
+    /// Reads the next `len` bytes as a blob.
+    fn read_blob(&mut self, len: usize) -> io::Result<Blob>;
+}
+
+impl<R: BufRead> ReadBlob for Counting<R> {
+    fn read_blob(&mut self, len: usize) -> io::Result<Blob> {
+        let Some(shared) = &self.shared else {
+            let mut buf = vec![0; len];
+            self.read_exact(&mut buf)?;
+            return Ok(buf.into());
+        };
+
+        // the count is the offset into the shared buffer, since decoding always starts at its beginning
+        let start = usize::try_from(self.count).unwrap_or(usize::MAX);
+        let blob = start
+            .checked_add(len)
+            .filter(|end| *end <= shared.len())
+            .map(|end| shared.slice(start..end))
+            .ok_or(io::ErrorKind::UnexpectedEof)?;
+
+        // the bytes are already in the blob, so they're skipped rather than read
+        let mut remaining = len;
+        while remaining > 0 {
+            let skipped = self.fill_buf()?.len().min(remaining);
+            if skipped == 0 {
+                return Err(io::ErrorKind::UnexpectedEof.into());
+            }
+
+            self.consume(skipped);
+            remaining -= skipped;
+        }
+
+        Ok(blob.into())
+    }
+}
+
+impl<W: Write> Write for Counting<W> {
+    fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+        let written = self.inner.write(buf)?;
+        self.count += written as u64;
+        Ok(written)
+    }
+
+    fn flush(&mut self) -> io::Result<()> {
+        self.inner.flush()
+    }
 }
 
 impl Dmx {
@@ -194,55 +414,127 @@ impl Dmx {
 }
 
 impl Dmx {
-    pub fn decode(buf: &mut impl std::io::BufRead) -> Result<Dmx, Error> {
-        let version = Self::read_magic_version(buf)?;
-        let strings = Self::read_strings(buf)?;
-        let elements = Self::read_elements(buf)?;
+    /// Decodes a PCF, or another DMX in the same dialect, from `buf`.
+    ///
+    /// # Errors
+    ///
+    /// Returns [`DecodeError`] with the section & offset where decoding failed, if `buf` couldn't be read or isn't a
+    /// valid DMX.
+    pub fn decode(buf: &mut impl BufRead) -> Result<Dmx, DecodeError> {
+        Self::decode_with(&PcfProfile, buf)
+    }
 
-        Ok(Self {
-            version,
-            strings,
-            elements,
+    /// Decodes a DMX in any dialect `profile` supports from `buf`. See [`crate::profile`].
+    ///
+    /// # Errors
+    ///
+    /// See [`Dmx::decode`].
+    pub fn decode_with(profile: &impl Profile, buf: &mut impl BufRead) -> Result<Dmx, DecodeError> {
+        Self::decode_counting(profile, Counting::new(buf))
+    }
+
+    /// Decodes a PCF like [`Dmx::decode`], but without copying its binary attributes out of `bytes`; each [`Blob`]
+    /// shares `bytes` instead. See [`crate::blob`].
+    ///
+    /// # Errors
+    ///
+    /// See [`Dmx::decode`].
+    pub fn decode_bytes(bytes: Bytes) -> Result<Dmx, DecodeError> {
+        Self::decode_bytes_with(&PcfProfile, bytes)
+    }
+
+    /// Decodes a DMX in any dialect `profile` supports from `bytes`, like [`Dmx::decode_bytes`].
+    ///
+    /// # Errors
+    ///
+    /// See [`Dmx::decode`].
+    pub fn decode_bytes_with(profile: &impl Profile, bytes: Bytes) -> Result<Dmx, DecodeError> {
+        let mut reader = bytes.clone().reader();
+        Self::decode_counting(
+            profile,
+            Counting {
+                shared: Some(bytes),
+                ..Counting::new(&mut reader)
+            },
+        )
+    }
+
+    fn decode_counting(profile: &impl Profile, mut buf: Counting<impl BufRead>) -> Result<Dmx, DecodeError> {
+        let mut section = Section::Header;
+        let mut decode = || {
+            let version = Self::read_magic_version(profile, &mut buf)?;
+            let encoding = profile.encoding(version);
+            section = Section::Symbols;
+            let strings = Self::read_strings(encoding, &mut buf)?;
+            section = Section::Elements;
+            let (elements, signature_references) = Self::read_elements(encoding, &strings, &mut buf, &mut section)?;
+
+            Ok(Self {
+                version,
+                strings,
+                elements,
+                signature_references,
+            })
+        };
+
+        decode().map_err(|kind| DecodeError {
+            section,
+            offset: buf.count,
+            kind,
         })
     }
 
-    fn read_terminated_string(file: &mut impl std::io::BufRead) -> Result<CString, Error> {
+    fn read_terminated_string(file: &mut impl BufRead) -> Result<CString, DecodeErrorKind> {
         let mut header_buf = Vec::new();
         file.read_until(0, &mut header_buf)?;
 
         Ok(CString::from_vec_with_nul(header_buf)?)
     }
 
-    fn read_magic_version(file: &mut impl std::io::BufRead) -> Result<Version, Error> {
+    fn read_magic_version(profile: &impl Profile, file: &mut impl BufRead) -> Result<Version, DecodeErrorKind> {
         let mut header_buf = Vec::new();
         file.read_until(0, &mut header_buf)?;
 
-        let version = CStr::from_bytes_with_nul(&header_buf)?
-            .to_string_lossy()
-            .parse::<Version>()?;
+        let version = profile.parse_version(&CStr::from_bytes_with_nul(&header_buf)?.to_string_lossy())?;
 
         Ok(version)
     }
 
-    fn read_strings(file: &mut impl std::io::BufRead) -> Result<Symbols, Error> {
-        let symbol_count = file.read_u16::<LittleEndian>()? as usize;
+    fn read_strings(encoding: Encoding, file: &mut impl BufRead) -> Result<Symbols, DecodeErrorKind> {
+        let symbol_count = encoding.read_symbol_count(file)?;
 
         let mut symbols = Symbols::with_capacity(symbol_count);
         for _ in 0..symbol_count {
-            symbols.insert(Self::read_terminated_string(file)?);
+            // duplicates are kept, since every string after them is referenced by its encoded index
+            symbols.push(Self::read_terminated_string(file)?);
         }
 
         Ok(symbols)
     }
 
-    fn read_elements(file: &mut impl std::io::BufRead) -> Result<Vec<Element>, Error> {
+    /// Reads every element, followed by their attributes. `section` is updated once the attributes are being read.
+    fn read_elements(
+        encoding: Encoding,
+        strings: &Symbols,
+        file: &mut impl ReadBlob,
+        section: &mut Section,
+    ) -> Result<(Vec<Element>, SignatureReferences), DecodeErrorKind> {
         let element_count = file.read_u32::<LittleEndian>()? as usize;
 
         let mut elements = Vec::with_capacity(element_count);
         for _idx in 0..element_count {
-            let type_idx = file.read_u16::<LittleEndian>()?;
-            let name = Self::read_terminated_string(file)?;
-            let signature = file.read_array::<16>()?;
+            let type_idx = encoding.read_symbol(file)?;
+            let name = if encoding.strings_are_symbols {
+                let name_idx = encoding.read_symbol(file)?;
+                strings
+                    .get_index(usize::from(name_idx))
+                    .cloned()
+                    .ok_or(ReadError::SymbolOutOfRange(name_idx.into()))?
+            } else {
+                Self::read_terminated_string(file)?
+            };
+            let mut signature = [0; 16];
+            file.read_exact(&mut signature)?;
 
             elements.push(Element {
                 type_idx,
@@ -252,8 +544,11 @@ impl Dmx {
             });
         }
 
-        // we add one to element_count since AttributeReader will read root's attributes + elements' attributes
-        let attributes: Result<Vec<_>, _> = AttributeReader::try_from(file, element_count)?.into_iter().collect();
+        *section = Section::Attributes;
+
+        let mut reader = AttributeReader::try_from(file, encoding, strings, &elements)?.into_iter();
+        let attributes: Result<Vec<_>, _> = reader.by_ref().collect();
+        let signature_references = reader.into_signature_references();
         let attributes = attributes?.into_iter().chunk_by(|el| el.0);
 
         for (element_idx, group) in attributes.into_iter() {
@@ -263,29 +558,139 @@ impl Dmx {
             element.attributes = group.map(|el| (el.1, el.2)).collect();
         }
 
-        Ok(elements)
+        Ok((elements, signature_references))
     }
 }
 
 impl Dmx {
-    pub fn encode(&self, file: &mut impl std::io::Write) -> anyhow::Result<()> {
-        self.write_magic_version(file)?;
-        self.write_strings(file)?;
-        self.write_elements(file)?;
-        self.write_element_attributes(file)?;
+    /// Encodes this DMX into `file`.
+    ///
+    /// # Errors
+    ///
+    /// Returns [`EncodeError`] with the section & offset where encoding failed, if `file` couldn't be written to, or if
+    /// a table is too long to be encoded. See [`LimitError`].
+    pub fn encode(&self, file: &mut impl Write) -> Result<(), EncodeError> {
+        let mut file = Counting::new(file);
+        let mut section = Section::Header;
+        let mut encode = || -> Result<(), EncodeErrorKind> {
+            self.write_magic_version(&mut file)?;
+            section = Section::Symbols;
+            self.write_strings(&mut file)?;
+            section = Section::Elements;
+            self.write_elements(&mut file)?;
+            section = Section::Attributes;
+            self.write_element_attributes(&mut file)
+        };
+
+        encode().map_err(|kind| EncodeError {
+            section,
+            offset: file.count,
+            kind,
+        })
+    }
+
+    /// Encodes this DMX into `file`, like [`Dmx::encode`], when the encoded size is already known - e.g. from
+    /// [`Dmx::encoded_size`], or a size computed while building the DMX.
+    ///
+    /// # Panics
+    ///
+    /// In debug builds, panics if the number of bytes written isn't `expected_size`, since the size calculation has
+    /// drifted from the encoder.
+    ///
+    /// # Errors
+    ///
+    /// See [`Dmx::encode`].
+    pub fn encode_sized(&self, file: &mut impl Write, expected_size: usize) -> Result<(), EncodeError> {
+        let mut file = Counting::new(file);
+        self.encode(&mut file)?;
+
+        debug_assert_eq!(
+            file.count, expected_size as u64,
+            "the DMX's encoded size differs from its expected size"
+        );
 
         Ok(())
     }
 
-    fn write_magic_version(&self, file: &mut impl std::io::Write) -> anyhow::Result<()> {
-        let version: &CStr = self.version.into();
-        file.write_all(version.to_bytes_with_nul())?;
+    /// Encodes this DMX into a new buffer, allocated up front with [`Dmx::encoded_size`].
+    pub fn encode_to_vec(&self) -> Vec<u8> {
+        let size = self.encoded_size();
+        let mut buffer = Vec::with_capacity(size);
+        self.encode_sized(&mut buffer, size)
+            .expect("writing to a Vec can't fail");
+
+        buffer
+    }
+
+    /// The number of bytes [`Dmx::encode`] will write.
+    pub fn encoded_size(&self) -> usize {
+        let encoding = self.encoding();
+        let version_size = self.version.as_cstr_with_nul_terminator().to_bytes_with_nul().len();
+
+        // symbol counter + strings with nul terminators
+        let symbols_size = encoding.symbol_count_size()
+            + self
+                .strings
+                .iter()
+                .map(|string| string.to_bytes_with_nul().len())
+                .sum::<usize>();
+
+        // 32-bit element counter + each element's type index, name & signature
+        let elements_size = size_of::<u32>()
+            + self
+                .elements
+                .iter()
+                .map(|element| {
+                    let name_size = if encoding.strings_are_symbols {
+                        encoding.symbol_index_size()
+                    } else {
+                        element.name.to_bytes_with_nul().len()
+                    };
+
+                    encoding.symbol_index_size() + name_size + size_of::<Signature>()
+                })
+                .sum::<usize>();
+
+        // each element's 32-bit attribute counter + each attribute's name index, type & value
+        let attributes_size = self
+            .elements
+            .iter()
+            .map(|element| {
+                size_of::<u32>()
+                    + element
+                        .attributes
+                        .values()
+                        .map(|attribute| {
+                            let value_size = match attribute {
+                                Attribute::String(_) if encoding.strings_are_symbols => encoding.symbol_index_size(),
+                                _ => attribute.encoded_size(),
+                            };
+
+                            encoding.symbol_index_size() + size_of::<u8>() + value_size
+                        })
+                        .sum::<usize>()
+            })
+            .sum::<usize>();
+
+        // each reference encoded by signature is followed by a GUID string with a nul terminator
+        let signature_references_size = self.signature_references.len() * (GUID_STRING_LEN + 1);
+
+        version_size + symbols_size + elements_size + attributes_size + signature_references_size
+    }
+
+    /// How this DMX's symbols are encoded, per its version's [`Profile`].
+    fn encoding(&self) -> Encoding {
+        self.version.profile().encoding(self.version)
+    }
+
+    fn write_magic_version(&self, file: &mut impl Write) -> io::Result<()> {
+        file.write_all(self.version.as_cstr_with_nul_terminator().to_bytes_with_nul())?;
 
         Ok(())
     }
 
-    fn write_strings(&self, file: &mut impl std::io::Write) -> anyhow::Result<()> {
-        file.write_u16::<LittleEndian>(self.strings.len() as u16)?;
+    fn write_strings(&self, file: &mut impl Write) -> Result<(), EncodeErrorKind> {
+        self.encoding().write_symbol_count(file, self.strings.len())?;
 
         for string in &self.strings {
             file.write_all(string.to_bytes_with_nul())?;
@@ -294,29 +699,55 @@ impl Dmx {
         Ok(())
     }
 
-    fn write_elements(&self, file: &mut impl std::io::Write) -> anyhow::Result<()> {
-        file.write_u32::<LittleEndian>(self.elements.len() as u32)?;
+    fn write_elements(&self, file: &mut impl Write) -> Result<(), EncodeErrorKind> {
+        let encoding = self.encoding();
+
+        // the largest indices are reserved for null & signature references, so they can't be element indices
+        let element_count = encoded_count(self.elements.len(), SIGNATURE_REFERENCE, || Table::Elements)?;
+        file.write_u32::<LittleEndian>(element_count)?;
         for element in &self.elements {
-            file.write_u16::<LittleEndian>(element.type_idx)?;
-            file.write_all(element.name.to_bytes_with_nul())?;
+            encoding.write_symbol(file, element.type_idx)?;
+            if encoding.strings_are_symbols {
+                encoding.write_symbol(file, symbol_of(&self.strings, &element.name)?)?;
+            } else {
+                file.write_all(element.name.to_bytes_with_nul())?;
+            }
+
             file.write_all(&element.signature)?;
         }
 
         Ok(())
     }
 
-    fn write_element_attributes(&self, file: &mut impl std::io::Write) -> anyhow::Result<()> {
-        AttributeWriter::from(file).write_attributes(&self.elements)?;
-
-        Ok(())
+    fn write_element_attributes(&self, file: &mut impl Write) -> Result<(), EncodeErrorKind> {
+        AttributeWriter::new(file, self.encoding(), &self.strings)
+            .write_attributes(&self.elements, &self.signature_references)
     }
 }
 
+/// The index of `string` in `strings`, for encodings which store strings as symbols.
+///
+/// # Errors
+///
+/// Returns [`io::ErrorKind::InvalidInput`] if `string` isn't one of the `strings`.
+pub(crate) fn symbol_of(strings: &Symbols, string: &CStr) -> io::Result<SymbolIdx> {
+    strings
+        .get_index_of(string)
+        .and_then(|idx| SymbolIdx::try_from(idx).ok())
+        .ok_or_else(|| {
+            io::Error::new(
+                io::ErrorKind::InvalidInput,
+                format!("'{}' isn't one of the DMX's strings", string.to_string_lossy()),
+            )
+        })
+}
+
 #[cfg(test)]
 mod tests {
     use bytes::{Buf, BufMut, Bytes, BytesMut};
 
     use super::*;
+    use crate::reference::ReferencePosition;
 
     const TEST_PCF: &[u8] = include_bytes!("test/medicgun_beam.pcf");
 
@@ -361,4 +792,150 @@ mod tests {
             "expected decoded buf and encoded buf to be identical."
         );
     }
+
+    #[test]
+    fn encoded_size_matches_encoded_buffer() {
+        let mut strings = Symbols::new();
+        strings.insert(c"DmElement".to_owned());
+        strings.insert(c"names".to_owned());
+        strings.insert(c"data".to_owned());
+
+        let dmx = Dmx {
+            version: Version::Binary2Pcf1,
+            strings,
+            elements: vec![Element {
+                type_idx: 0,
+                name: c"root".to_owned(),
+                signature: [0; 16],
+                attributes: OrderMap::from([
+                    (
+                        1,
+                        Attribute::StringArray(Box::from([c"first".to_owned(), c"second".to_owned()])),
+                    ),
+                    (2, Attribute::BinaryArray(Box::from([Blob::from([1u8, 2, 3])]))),
+                    (
+                        0,
+                        Attribute::Vector3(crate::Vector3(1.0.into(), 2.0.into(), 3.0.into())),
+                    ),
+                ]),
+            }],
+            signature_references: SignatureReferences::new(),
+        };
+
+        let mut writer = BytesMut::new().writer();
+        dmx.encode(&mut writer).expect("writing failed");
+
+        assert_eq!(dmx.encoded_size(), writer.get_ref().len());
+        assert_eq!(dmx.encode_to_vec(), &writer.get_ref()[..]);
+    }
+
+    #[test]
+    fn decoding_bytes_shares_binary_attributes() {
+        let dmx = Dmx {
+            version: Version::Binary2Pcf1,
+            strings: Symbols::from([c"DmElement".to_owned(), c"curve".to_owned(), c"curves".to_owned()]),
+            elements: vec![Element {
+                type_idx: 0,
+                name: c"root".to_owned(),
+                signature: [0; 16],
+                attributes: OrderMap::from([
+                    (1, Attribute::Binary(Blob::from(vec![7; 1024]))),
+                    (
+                        2,
+                        Attribute::BinaryArray(Box::from([Blob::from([1, 2, 3]), Blob::default()])),
+                    ),
+                ]),
+            }],
+            signature_references: SignatureReferences::new(),
+        };
+
+        let encoded = Bytes::from(dmx.encode_to_vec());
+        let decoded = Dmx::decode_bytes(encoded.clone()).unwrap();
+        assert_eq!(decoded, dmx);
+        assert_eq!(decoded, Dmx::decode(&mut encoded.clone().reader()).unwrap());
+
+        let Some(Attribute::Binary(blob)) = decoded.elements[0].attributes.get(&1) else {
+            panic!("expected a binary attribute");
+        };
+        assert!(encoded.as_ptr_range().contains(&blob.as_ptr()));
+    }
+
+    #[test]
+    fn too_many_symbols_fail_before_the_count_is_written() {
+        let dmx = Dmx {
+            strings: (0..=u16::MAX)
+                .map(|idx| CString::new(idx.to_string()).unwrap())
+                .collect(),
+            ..Dmx::default()
+        };
+
+        let err = dmx.encode(&mut Vec::new()).unwrap_err();
+        assert_eq!(err.section, Section::Symbols);
+        assert_eq!(
+            err.offset as usize,
+            dmx.version.as_cstr_with_nul_terminator().count_bytes() + 1
+        );
+
+        let EncodeErrorKind::Limit(limit) = err.kind else {
+            panic!("expected a limit error, got {:?}", err.kind);
+        };
+        assert_eq!(limit.table, Table::Symbols);
+        assert_eq!(limit.len, 65536);
+        assert_eq!(limit.max, 65535);
+    }
+
+    #[test]
+    fn signature_references_round_trip() {
+        let element = |name: &CStr, signature, attributes| Element {
+            type_idx: 0,
+            name: name.to_owned(),
+            signature,
+            attributes,
+        };
+
+        let missing_signature = [9; 16];
+        let dmx = Dmx {
+            version: Version::Binary2Pcf1,
+            strings: Symbols::from([c"DmElement".to_owned(), c"child".to_owned(), c"children".to_owned()]),
+            elements: vec![
+                element(
+                    c"root",
+                    [0; 16],
+                    OrderMap::from([
+                        (1, Attribute::Element(ElementIdx::from(1u32))),
+                        (
+                            2,
+                            Attribute::ElementArray(Box::from([ElementIdx::from(1u32), ElementIdx::INVALID])),
+                        ),
+                    ]),
+                ),
+                element(c"child", [1; 16], OrderMap::new()),
+            ],
+            signature_references: SignatureReferences::from([
+                (
+                    ReferencePosition {
+                        element: 0u32.into(),
+                        name_idx: 1,
+                        item: None,
+                    },
+                    [1; 16],
+                ),
+                (
+                    ReferencePosition {
+                        element: 0u32.into(),
+                        name_idx: 2,
+                        item: Some(1),
+                    },
+                    missing_signature,
+                ),
+            ]),
+        };
+
+        let encoded = dmx.encode_to_vec();
+        assert_eq!(dmx.encoded_size(), encoded.len());
+
+        let decoded = Dmx::decode(&mut Bytes::from(encoded.clone()).reader()).unwrap();
+        assert_eq!(decoded, dmx);
+        assert_eq!(decoded.encode_to_vec(), encoded);
+    }
 }
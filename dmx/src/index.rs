@@ -1,10 +1,23 @@
-use std::ops::{Add, AddAssign};
-
 use derive_more::{Display, From, Into};
+use thiserror::Error;
 
+/// The index of an element in a [`Dmx`](crate::Dmx)'s element list, or [`ElementIdx::INVALID`] for a reference to no
+/// element.
+///
+/// Indices are only made from a `usize` through [`TryFrom`], and only moved by the checked helpers below, so an index
+/// can't silently wrap around into another element or into [`ElementIdx::INVALID`].
 #[derive(From, Debug, Clone, Copy, Into, Hash, PartialEq, Eq, PartialOrd, Ord, Display)]
 pub struct ElementIdx(u32);
 
+#[derive(Debug, Error, Clone, PartialEq, Eq)]
+pub enum ElementIdxError {
+    #[error("{0} is too large to be an element index")]
+    OutOfRange(usize),
+
+    #[error("element {idx} can't be rebased from {from}, since it comes before it")]
+    BeforeBase { idx: ElementIdx, from: usize },
+}
+
 impl ElementIdx {
     pub const INVALID: ElementIdx = ElementIdx(u32::MAX);
 
@@ -15,11 +28,51 @@ impl ElementIdx {
     pub fn inner(&self) -> u32 {
         self.0
     }
+
+    /// The index as a `usize`, or `None` if it's [`ElementIdx::INVALID`].
+    pub fn index(self) -> Option<usize> {
+        self.is_valid().then_some(self.0 as usize)
+    }
+
+    /// The index `offset` elements further on, e.g. once the elements it indexes are appended after `offset` others.
+    /// [`ElementIdx::INVALID`] is returned as is.
+    ///
+    /// # Errors
+    ///
+    /// Returns [`ElementIdxError::OutOfRange`] if the offset index is too large.
+    pub fn checked_offset(self, offset: usize) -> Result<Self, ElementIdxError> {
+        match self.index() {
+            Some(idx) => Self::try_from(idx.checked_add(offset).ok_or(ElementIdxError::OutOfRange(usize::MAX))?),
+            None => Ok(self),
+        }
+    }
+
+    /// Moves the index of an element in a list starting at `from` into the same list starting at `to` instead, e.g.
+    /// when an element before it is removed, or when a list is moved after another. [`ElementIdx::INVALID`] is
+    /// returned as is.
+    ///
+    /// # Errors
+    ///
+    /// Returns [`ElementIdxError::BeforeBase`] if the index comes before `from`, and
+    /// [`ElementIdxError::OutOfRange`] if the rebased index is too large.
+    pub fn rebased(self, from: usize, to: usize) -> Result<Self, ElementIdxError> {
+        match self.index() {
+            Some(idx) if idx < from => Err(ElementIdxError::BeforeBase { idx: self, from }),
+            Some(idx) => Self::try_from(idx - from).and_then(|relative| relative.checked_offset(to)),
+            None => Ok(self),
+        }
+    }
 }
 
-impl From<usize> for ElementIdx {
-    fn from(value: usize) -> Self {
-        ElementIdx(value as u32)
+impl TryFrom<usize> for ElementIdx {
+    type Error = ElementIdxError;
+
+    fn try_from(value: usize) -> Result<Self, Self::Error> {
+        u32::try_from(value)
+            .ok()
+            .filter(|idx| *idx != u32::MAX)
+            .map(ElementIdx)
+            .ok_or(ElementIdxError::OutOfRange(value))
     }
 }
 
@@ -29,16 +82,54 @@ impl From<ElementIdx> for usize {
     }
 }
 
-impl Add<usize> for ElementIdx {
-    type Output = Self;
+#[cfg(test)]
+mod tests {
+    use super::*;
 
-    fn add(self, rhs: usize) -> Self::Output {
-        ElementIdx(self.0 + rhs as u32)
+    #[test]
+    fn only_indices_below_invalid_convert() {
+        assert_eq!(ElementIdx::try_from(0usize), Ok(ElementIdx(0)));
+        assert_eq!(
+            ElementIdx::try_from(u32::MAX as usize - 1),
+            Ok(ElementIdx(u32::MAX - 1))
+        );
+        assert_eq!(
+            ElementIdx::try_from(u32::MAX as usize),
+            Err(ElementIdxError::OutOfRange(u32::MAX as usize))
+        );
+        assert_eq!(
+            ElementIdx::try_from(u32::MAX as usize + 1),
+            Err(ElementIdxError::OutOfRange(u32::MAX as usize + 1))
+        );
+    }
+
+    #[test]
+    fn invalid_indices_are_kept_invalid() {
+        assert_eq!(ElementIdx::INVALID.index(), None);
+        assert_eq!(ElementIdx::INVALID.checked_offset(3), Ok(ElementIdx::INVALID));
+        assert_eq!(ElementIdx::INVALID.rebased(1, 0), Ok(ElementIdx::INVALID));
+    }
+
+    #[test]
+    fn offsets_are_checked() {
+        assert_eq!(ElementIdx(2).checked_offset(3), Ok(ElementIdx(5)));
+        assert_eq!(
+            ElementIdx(u32::MAX - 2).checked_offset(2),
+            Err(ElementIdxError::OutOfRange(u32::MAX as usize))
+        );
+        assert!(ElementIdx(1).checked_offset(usize::MAX).is_err());
     }
-}
 
-impl AddAssign<usize> for ElementIdx {
-    fn add_assign(&mut self, rhs: usize) {
-        self.0 += rhs as u32
+    #[test]
+    fn rebasing_moves_between_lists() {
+        assert_eq!(ElementIdx(0).rebased(0, 1), Ok(ElementIdx(1)));
+        assert_eq!(ElementIdx(4).rebased(3, 2), Ok(ElementIdx(3)));
+        assert_eq!(
+            ElementIdx(1).rebased(2, 1),
+            Err(ElementIdxError::BeforeBase {
+                idx: ElementIdx(1),
+                from: 2
+            })
+        );
     }
 }
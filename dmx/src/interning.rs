@@ -0,0 +1,157 @@
+//! Estimates how much smaller a [`Dmx`] would be if its string attribute values were interned into the string table.
+//!
+//! Binary 2 & 3 DMX files only use the string table for element types and attribute names, so every string value -
+//! e.g. a material path - is written inline each time it appears. Binary 4 & 5 files also store each string value
+//! once in the string table, and write a 4 byte string table index in its place. This crate only reads & writes
+//! binary 2 & 3, so [`Dmx::interning_estimate`] reports what upgrading the encoding would save.
+
+use std::ffi::CStr;
+
+use crate::{Dmx, Symbols, attribute::Attribute};
+
+/// The size of a string table index in a binary 4 or 5 DMX.
+pub const STRING_INDEX_SIZE: usize = 4;
+
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct InterningEstimate {
+    /// The number of string attribute values in the DMX, including duplicates. String arrays aren't counted, since
+    /// their values are always written inline.
+    pub value_count: usize,
+
+    /// The number of distinct string attribute values which aren't already in the string table.
+    pub new_symbol_count: usize,
+
+    /// The size, in bytes, of every string attribute value when each one is written inline.
+    pub inline_size: usize,
+
+    /// The size, in bytes, of every string attribute value when each one is written as a string table index, plus
+    /// the size of the new symbols added to the string table.
+    pub interned_size: usize,
+}
+
+impl InterningEstimate {
+    /// The number of bytes interning would save. This is 0 if interning would make the DMX larger, which happens
+    /// when most values are unique & shorter than a string table index.
+    pub fn savings(&self) -> usize {
+        self.inline_size.saturating_sub(self.interned_size)
+    }
+}
+
+impl Dmx {
+    /// Returns every distinct string attribute value which isn't already in the string table, in the order they first
+    /// appear. These are the symbols which would be appended to the string table if the values were interned.
+    pub fn interned_values(&self) -> Symbols {
+        let mut values = Symbols::new();
+        for value in self.string_values() {
+            if !self.strings.contains(value) && !values.contains(value) {
+                values.insert(value.to_owned());
+            }
+        }
+
+        values
+    }
+
+    /// Estimates how many bytes interning string attribute values into the string table would save. See the
+    /// [module docs](crate::interning).
+    pub fn interning_estimate(&self) -> InterningEstimate {
+        let mut estimate = InterningEstimate::default();
+        for value in self.string_values() {
+            estimate.value_count += 1;
+            estimate.inline_size += value.count_bytes() + 1;
+            estimate.interned_size += STRING_INDEX_SIZE;
+        }
+
+        let new_symbols = self.interned_values();
+        estimate.new_symbol_count = new_symbols.len();
+        estimate.interned_size += new_symbols.iter().map(|symbol| symbol.count_bytes() + 1).sum::<usize>();
+
+        estimate
+    }
+
+    fn string_values(&self) -> impl Iterator<Item = &CStr> {
+        self.elements
+            .iter()
+            .flat_map(|element| element.attributes.values())
+            .filter_map(|attribute| match attribute {
+                Attribute::String(value) => Some(value.as_c_str()),
+                _ => None,
+            })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::ffi::CString;
+
+    use ordermap::OrderMap;
+
+    use super::{InterningEstimate, STRING_INDEX_SIZE};
+    use crate::{
+        Dmx, Symbols,
+        attribute::Attribute,
+        dmx::{Element, Version},
+        reference::SignatureReferences,
+    };
+
+    fn element(attributes: impl IntoIterator<Item = (u16, Attribute)>) -> Element {
+        Element {
+            type_idx: 0,
+            name: CString::default(),
+            signature: [0; 16],
+            attributes: attributes.into_iter().collect::<OrderMap<_, _>>(),
+        }
+    }
+
+    fn string(value: &str) -> Attribute {
+        Attribute::String(CString::new(value).unwrap())
+    }
+
+    fn test_dmx() -> Dmx {
+        Dmx {
+            version: Version::Binary2Pcf1,
+            strings: Symbols::from([c"DmElement".to_owned(), c"material".to_owned()]),
+            elements: vec![
+                element([(0, string("effects/fire.vmt")), (1, string("material"))]),
+                element([(1, string("effects/fire.vmt"))]),
+                element([(1, Attribute::StringArray(Box::from([c"effects/fire.vmt".to_owned()])))]),
+            ],
+            signature_references: SignatureReferences::new(),
+        }
+    }
+
+    #[test]
+    fn interned_values_skip_existing_symbols_and_duplicates() {
+        let dmx = test_dmx();
+        assert_eq!(dmx.interned_values(), Symbols::from([c"effects/fire.vmt".to_owned()]));
+    }
+
+    #[test]
+    fn estimate_counts_each_new_symbol_once() {
+        let estimate = test_dmx().interning_estimate();
+        let fire_len = "effects/fire.vmt".len() + 1;
+
+        assert_eq!(
+            estimate,
+            InterningEstimate {
+                value_count: 3,
+                new_symbol_count: 1,
+                inline_size: fire_len * 2 + "material".len() + 1,
+                interned_size: STRING_INDEX_SIZE * 3 + fire_len,
+            }
+        );
+        assert_eq!(
+            estimate.savings(),
+            fire_len + "material".len() + 1 - STRING_INDEX_SIZE * 3
+        );
+    }
+
+    #[test]
+    fn unique_short_values_save_nothing() {
+        let dmx = Dmx {
+            elements: vec![element([(0, string("a")), (1, string("b"))])],
+            ..Dmx::default()
+        };
+
+        assert_eq!(dmx.interning_estimate().savings(), 0);
+    }
+}
@@ -1,24 +1,25 @@
-#![feature(buf_read_has_data_left)]
-#![feature(read_array)]
-#![feature(trim_prefix_suffix)]
-#![feature(associated_type_defaults)]
-#![feature(error_generic_member_access)]
-
 pub mod attribute;
+pub mod blob;
 pub mod dmx;
 pub mod index;
-
-use std::ffi::CString;
-
-use ordermap::OrderSet;
+pub mod interning;
+pub mod profile;
+pub mod reference;
+pub mod symbols;
 
 pub type Signature = [u8; 16];
 pub type SymbolIdx = u16;
-pub type Symbols = OrderSet<CString>;
-pub use attribute::{Color, Float, Matrix, Vector2, Vector3, Vector4};
+pub use attribute::{Color, FixedEncodedSize, Float, Matrix, Vector2, Vector3, Vector4};
+pub use blob::Blob;
 pub use dmx::Dmx;
-pub use index::ElementIdx;
+pub use index::{ElementIdx, ElementIdxError};
+pub use interning::InterningEstimate;
+pub use symbols::Symbols;
 
-pub fn decode(buf: &mut impl std::io::BufRead) -> Result<Dmx, dmx::Error> {
+pub fn decode(buf: &mut impl std::io::BufRead) -> Result<Dmx, dmx::DecodeError> {
     Dmx::decode(buf)
 }
+
+pub fn decode_bytes(bytes: bytes::Bytes) -> Result<Dmx, dmx::DecodeError> {
+    Dmx::decode_bytes(bytes)
+}
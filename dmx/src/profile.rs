@@ -0,0 +1,346 @@
+//! Format profiles, which describe the parts of binary DMX that differ between the dialects written by different tools.
+//!
+//! TF2's particles are always encoded like [`PcfProfile`] - binary encoding 2 or 3, with a 16-bit symbol count &
+//! indices, and element names & string attributes stored inline - which [`Dmx::decode`](crate::Dmx::decode) assumes.
+//! With the `dialects` feature, [`DialectProfile`] also reads & writes binary encodings 4 and 5, which Source Filmmaker
+//! & the model tools use for e.g. SFM sessions & model DMX. Later encodings, like Source 2's, aren't supported.
+
+use std::io::{self, Read, Write};
+
+use byteorder::{LittleEndian, ReadBytesExt, WriteBytesExt};
+
+use crate::{
+    SymbolIdx,
+    attribute::ReadError,
+    dmx::{self, EncodeErrorKind, ParseVersionError, Table, Version},
+};
+
+/// A dialect of binary DMX.
+pub trait Profile {
+    /// Parses the header at the start of a DMX, e.g. `<!-- dmx encoding binary 2 format pcf 1 -->\n`.
+    ///
+    /// # Errors
+    ///
+    /// Returns [`ParseVersionError`] if the header isn't valid, or isn't a version this profile supports.
+    fn parse_version(&self, header: &str) -> Result<Version, ParseVersionError>;
+
+    /// How a DMX with `version` encodes its symbols.
+    fn encoding(&self, version: Version) -> Encoding;
+}
+
+/// How a DMX's symbols are encoded, which depends on its binary encoding version.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Encoding {
+    /// whether the symbol count is 32 bits wide, rather than 16
+    pub wide_symbol_count: bool,
+
+    /// whether each symbol index - an element's type, or an attribute's name - is 32 bits wide, rather than 16
+    pub wide_symbol_indices: bool,
+
+    /// whether element names & string attributes are symbol indices, rather than inline strings
+    pub strings_are_symbols: bool,
+}
+
+impl Encoding {
+    /// Binary encodings 2 & 3, which every PCF uses.
+    pub const PCF: Self = Self {
+        wide_symbol_count: false,
+        wide_symbol_indices: false,
+        strings_are_symbols: false,
+    };
+
+    /// The encoding of binary `version`. Encoding 4 widened the symbol count and moved strings into the symbols, and
+    /// encoding 5 widened the symbol indices.
+    pub const fn binary(version: u32) -> Self {
+        Self {
+            wide_symbol_count: version >= 4,
+            wide_symbol_indices: version >= 5,
+            strings_are_symbols: version >= 4,
+        }
+    }
+
+    pub(crate) fn symbol_count_size(&self) -> usize {
+        if self.wide_symbol_count {
+            size_of::<u32>()
+        } else {
+            size_of::<u16>()
+        }
+    }
+
+    pub(crate) fn symbol_index_size(&self) -> usize {
+        if self.wide_symbol_indices {
+            size_of::<u32>()
+        } else {
+            size_of::<u16>()
+        }
+    }
+
+    pub(crate) fn read_symbol_count(&self, reader: &mut impl Read) -> io::Result<usize> {
+        if self.wide_symbol_count {
+            Ok(reader.read_u32::<LittleEndian>()? as usize)
+        } else {
+            Ok(reader.read_u16::<LittleEndian>()? as usize)
+        }
+    }
+
+    /// Writes the number of symbols.
+    ///
+    /// # Errors
+    ///
+    /// Returns [`EncodeErrorKind::Limit`], without writing anything, if `count` doesn't fit in the symbol count.
+    pub(crate) fn write_symbol_count(&self, writer: &mut impl Write, count: usize) -> Result<(), EncodeErrorKind> {
+        if self.wide_symbol_count {
+            let count = dmx::encoded_count(count, u32::MAX, || Table::Symbols)?;
+            writer.write_u32::<LittleEndian>(count)?;
+        } else {
+            let count = dmx::encoded_count(count, u16::MAX.into(), || Table::Symbols)?;
+            writer.write_u16::<LittleEndian>(count as u16)?;
+        }
+
+        Ok(())
+    }
+
+    /// Reads a symbol index.
+    ///
+    /// # Errors
+    ///
+    /// Returns [`ReadError::SymbolOutOfRange`] if a wide index doesn't fit in a [`SymbolIdx`].
+    pub(crate) fn read_symbol(&self, reader: &mut impl Read) -> Result<SymbolIdx, ReadError> {
+        if self.wide_symbol_indices {
+            let idx = reader.read_u32::<LittleEndian>()?;
+            SymbolIdx::try_from(idx).map_err(|_| ReadError::SymbolOutOfRange(idx))
+        } else {
+            Ok(reader.read_u16::<LittleEndian>()?)
+        }
+    }
+
+    pub(crate) fn write_symbol(&self, writer: &mut impl Write, idx: SymbolIdx) -> io::Result<()> {
+        if self.wide_symbol_indices {
+            writer.write_u32::<LittleEndian>(idx.into())
+        } else {
+            writer.write_u16::<LittleEndian>(idx)
+        }
+    }
+}
+
+/// The dialect of TF2's particles, and the default for [`Dmx`](crate::Dmx).
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct PcfProfile;
+
+impl Profile for PcfProfile {
+    fn parse_version(&self, header: &str) -> Result<Version, ParseVersionError> {
+        header.parse()
+    }
+
+    fn encoding(&self, _version: Version) -> Encoding {
+        Encoding::PCF
+    }
+}
+
+#[cfg(feature = "dialects")]
+pub use dialects::{DialectProfile, DialectVersion, FormatName};
+
+#[cfg(feature = "dialects")]
+mod dialects {
+    use std::{ffi::CString, fmt::Display};
+
+    use super::{Encoding, PcfProfile, Profile};
+    use crate::dmx::{ParseVersionError, Version};
+
+    /// The binary encodings [`DialectProfile`] supports.
+    const ENCODINGS: std::ops::RangeInclusive<u32> = 2..=5;
+
+    /// Any binary DMX with an encoding from 2 to 5, in any format - e.g. `sfm_session`, `model` or `pcf`. PCF headers
+    /// are still parsed as their [`Version`], so PCFs decode the same way as with [`PcfProfile`].
+    #[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+    pub struct DialectProfile;
+
+    impl Profile for DialectProfile {
+        fn parse_version(&self, header: &str) -> Result<Version, ParseVersionError> {
+            PcfProfile
+                .parse_version(header)
+                .or_else(|_| header.parse().map(Version::Dialect))
+        }
+
+        fn encoding(&self, version: Version) -> Encoding {
+            match version {
+                Version::Dialect(version) => Encoding::binary(version.encoding),
+                _ => Encoding::PCF,
+            }
+        }
+    }
+
+    /// The version of a DMX which isn't a PCF, from a header like
+    /// `<!-- dmx encoding binary 5 format sfm_session 22 -->\n`.
+    #[derive(Debug, Clone, Copy, PartialEq, Eq)]
+    pub struct DialectVersion {
+        pub encoding: u32,
+        pub format: FormatName,
+        pub format_version: u32,
+    }
+
+    impl DialectVersion {
+        /// The header this version is encoded as, with a nul terminator.
+        pub fn header(&self) -> CString {
+            CString::new(format!(
+                "<!-- dmx encoding binary {} format {} {} -->\x0A",
+                self.encoding,
+                self.format.as_str(),
+                self.format_version
+            ))
+            .expect("format names never contain nul")
+        }
+    }
+
+    impl Display for DialectVersion {
+        fn fmt(&self, f: &mut std::fmt::Formatter) -> std::fmt::Result {
+            write!(
+                f,
+                "Binary{}({} {})",
+                self.encoding,
+                self.format.as_str(),
+                self.format_version
+            )
+        }
+    }
+
+    impl std::str::FromStr for DialectVersion {
+        type Err = ParseVersionError;
+
+        fn from_str(s: &str) -> Result<Self, Self::Err> {
+            let invalid = || ParseVersionError::Invalid(s.to_string());
+            let fields = s
+                .strip_prefix("<!-- dmx encoding binary ")
+                .and_then(|s| s.strip_suffix(" -->\x0A"))
+                .ok_or_else(invalid)?;
+
+            let [encoding, "format", format, format_version] = *fields.split(' ').collect::<Vec<_>>() else {
+                return Err(invalid());
+            };
+
+            let encoding: u32 = encoding.parse().map_err(|_| invalid())?;
+            if !ENCODINGS.contains(&encoding) {
+                return Err(ParseVersionError::UnsupportedEncoding(encoding));
+            }
+
+            Ok(Self {
+                encoding,
+                format: FormatName::new(format).ok_or_else(invalid)?,
+                format_version: format_version.parse().map_err(|_| invalid())?,
+            })
+        }
+    }
+
+    /// The format name in a [`DialectVersion`]. It's stored inline so that [`Version`] stays [`Copy`].
+    #[derive(Debug, Clone, Copy, PartialEq, Eq)]
+    pub struct FormatName([u8; FormatName::CAPACITY]);
+
+    impl FormatName {
+        /// The length of the longest format name.
+        pub const CAPACITY: usize = 32;
+
+        /// Returns [`None`] if `name` is empty, longer than [`FormatName::CAPACITY`], or has characters other than
+        /// ASCII letters, digits & underscores.
+        pub fn new(name: &str) -> Option<Self> {
+            if name.is_empty()
+                || name.len() > Self::CAPACITY
+                || !name.bytes().all(|byte| byte.is_ascii_alphanumeric() || byte == b'_')
+            {
+                return None;
+            }
+
+            let mut bytes = [0; Self::CAPACITY];
+            bytes[..name.len()].copy_from_slice(name.as_bytes());
+            Some(Self(bytes))
+        }
+
+        pub fn as_str(&self) -> &str {
+            let len = self.0.iter().position(|&byte| byte == 0).unwrap_or(Self::CAPACITY);
+            str::from_utf8(&self.0[..len]).expect("format names are always ASCII")
+        }
+    }
+}
+
+#[cfg(all(test, feature = "dialects"))]
+mod tests {
+    use bytes::{Buf, Bytes};
+    use ordermap::OrderMap;
+
+    use super::{DialectProfile, DialectVersion, FormatName, PcfProfile, Profile};
+    use crate::{
+        Dmx, ElementIdx, Symbols,
+        attribute::Attribute,
+        dmx::{Element, ParseVersionError, Version},
+        reference::SignatureReferences,
+    };
+
+    const SESSION_HEADER: &str = "<!-- dmx encoding binary 5 format sfm_session 22 -->\x0A";
+
+    #[test]
+    fn dialect_headers_are_parsed() {
+        let version = DialectProfile.parse_version(SESSION_HEADER).unwrap();
+        let Version::Dialect(dialect) = version else {
+            panic!("expected a dialect version, got {version}");
+        };
+
+        assert_eq!(dialect.encoding, 5);
+        assert_eq!(dialect.format.as_str(), "sfm_session");
+        assert_eq!(dialect.format_version, 22);
+        assert_eq!(dialect.header().to_str().unwrap(), SESSION_HEADER);
+
+        assert!(PcfProfile.parse_version(SESSION_HEADER).is_err());
+        assert_eq!(
+            DialectProfile
+                .parse_version("<!-- dmx encoding binary 2 format pcf 1 -->\x0A")
+                .unwrap(),
+            Version::Binary2Pcf1
+        );
+        assert!(matches!(
+            DialectProfile.parse_version("<!-- dmx encoding binary 9 format model 22 -->\x0A"),
+            Err(ParseVersionError::UnsupportedEncoding(9))
+        ));
+    }
+
+    #[test]
+    fn dialects_round_trip() {
+        for encoding in [4, 5] {
+            let dmx = Dmx {
+                version: Version::Dialect(DialectVersion {
+                    encoding,
+                    format: FormatName::new("model").unwrap(),
+                    format_version: 18,
+                }),
+                strings: Symbols::from([
+                    c"DmElement".to_owned(),
+                    c"root".to_owned(),
+                    c"skeleton".to_owned(),
+                    c"DmeModel".to_owned(),
+                    c"body".to_owned(),
+                    c"name".to_owned(),
+                ]),
+                elements: vec![
+                    Element {
+                        type_idx: 0,
+                        name: c"root".to_owned(),
+                        signature: [0; 16],
+                        attributes: OrderMap::from([(2, Attribute::Element(ElementIdx::from(1u32)))]),
+                    },
+                    Element {
+                        type_idx: 3,
+                        name: c"body".to_owned(),
+                        signature: [1; 16],
+                        attributes: OrderMap::from([(5, Attribute::String(c"body".to_owned()))]),
+                    },
+                ],
+                signature_references: SignatureReferences::new(),
+            };
+
+            let encoded = dmx.encode_to_vec();
+            assert_eq!(dmx.encoded_size(), encoded.len());
+
+            let decoded = Dmx::decode_with(&DialectProfile, &mut Bytes::from(encoded.clone()).reader()).unwrap();
+            assert_eq!(decoded, dmx);
+            assert_eq!(decoded.encode_to_vec(), encoded);
+        }
+    }
+}
@@ -0,0 +1,112 @@
+//! Element references which are encoded by signature, rather than by index.
+//!
+//! An element attribute is usually encoded as the index of the element it references, or -1 when it's null. Some
+//! tools instead encode -2, followed by the referenced element's signature as a GUID string, e.g.
+//! `"0f8fad5b-d9cb-469f-a165-70867728950e"`. When decoding, these references are resolved to the index of the element
+//! with that signature, and their positions are kept in [`Dmx::signature_references`](crate::Dmx) so that they're
+//! encoded the same way again. The positions are only meaningful for the elements they were decoded with, so anything
+//! which rebuilds the elements, like converting to a `pcf::Pcf` and back, encodes every reference by index instead.
+
+use std::{collections::BTreeMap, ffi::CString};
+
+use crate::{ElementIdx, Signature, SymbolIdx};
+
+/// The encoded index of an element reference which is followed by the referenced element's signature.
+pub const SIGNATURE_REFERENCE: u32 = u32::MAX - 1;
+
+/// The length of a GUID string, excluding its nul terminator.
+pub const GUID_STRING_LEN: usize = 36;
+
+/// Where an element reference is, in a DMX's elements.
+#[derive(Debug, Clone, Copy, Hash, PartialEq, Eq, PartialOrd, Ord)]
+pub struct ReferencePosition {
+    /// the element which has the attribute
+    pub element: ElementIdx,
+
+    /// the name of the attribute
+    pub name_idx: SymbolIdx,
+
+    /// the position of the reference in an element array, or [`None`] if the attribute is a single element
+    pub item: Option<usize>,
+}
+
+/// Every element reference which was encoded by signature, along with that signature. References to elements which
+/// aren't in the DMX are decoded as [`ElementIdx::INVALID`], and are only kept here.
+pub type SignatureReferences = BTreeMap<ReferencePosition, Signature>;
+
+/// Parses a GUID string, with or without braces, into a signature. The first three groups of a GUID are stored
+/// little-endian, like Windows' `GUID` struct.
+pub fn parse_signature(guid: &str) -> Option<Signature> {
+    let guid = guid
+        .strip_prefix('{')
+        .and_then(|guid| guid.strip_suffix('}'))
+        .unwrap_or(guid);
+    if guid.len() != GUID_STRING_LEN || [8, 13, 18, 23].iter().any(|&idx| guid.as_bytes()[idx] != b'-') {
+        return None;
+    }
+
+    let digits: Vec<u8> = guid.bytes().filter(|&byte| byte != b'-').collect();
+    if !digits.iter().all(u8::is_ascii_hexdigit) {
+        return None;
+    }
+
+    let mut bytes = [0; 16];
+    for (byte, pair) in bytes.iter_mut().zip(digits.as_chunks::<2>().0) {
+        *byte = u8::from_str_radix(str::from_utf8(pair).ok()?, 16).ok()?;
+    }
+
+    Some(swap_guid_groups(bytes))
+}
+
+/// Formats `signature` as a GUID string. See [`parse_signature`].
+pub fn format_signature(signature: &Signature) -> CString {
+    let bytes = swap_guid_groups(*signature);
+    let hex = |range: std::ops::Range<usize>| {
+        bytes[range]
+            .iter()
+            .map(|byte| format!("{byte:02x}"))
+            .collect::<String>()
+    };
+    let guid = format!(
+        "{}-{}-{}-{}-{}",
+        hex(0..4),
+        hex(4..6),
+        hex(6..8),
+        hex(8..10),
+        hex(10..16)
+    );
+
+    CString::new(guid).expect("hex digits are never nul")
+}
+
+/// Converts between the byte order of a signature & of a GUID string, by reversing its first three groups.
+fn swap_guid_groups(mut bytes: [u8; 16]) -> [u8; 16] {
+    bytes[0..4].reverse();
+    bytes[4..6].reverse();
+    bytes[6..8].reverse();
+    bytes
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{format_signature, parse_signature};
+
+    #[test]
+    fn signatures_round_trip_through_guid_strings() {
+        let guid = "0f8fad5b-d9cb-469f-a165-70867728950e";
+        let signature = parse_signature(guid).unwrap();
+
+        assert_eq!(signature[..4], [0x5b, 0xad, 0x8f, 0x0f]);
+        assert_eq!(signature[8..], [0xa1, 0x65, 0x70, 0x86, 0x77, 0x28, 0x95, 0x0e]);
+        assert_eq!(format_signature(&signature).to_str().unwrap(), guid);
+        assert_eq!(parse_signature(&format!("{{{guid}}}")), Some(signature));
+    }
+
+    #[test]
+    fn malformed_guid_strings_are_rejected() {
+        assert_eq!(parse_signature(""), None);
+        assert_eq!(parse_signature("0f8fad5bd9cb469fa16570867728950e"), None);
+        assert_eq!(parse_signature("0f8fad5b-d9cb-469f-a165-70867728950g"), None);
+        assert_eq!(parse_signature("0f8fad5b-d9cb-469f-a165-+0867728950e"), None);
+    }
+}
@@ -0,0 +1,289 @@
+//! A DMX's string table.
+//!
+//! Element types & attribute names are encoded as indices into the string table, so each string has to stay at the
+//! position it was decoded at. Tools don't always write a string only once, so unlike a set, [`Symbols`] keeps
+//! duplicates where they are. Looking a string up finds its first occurrence, which is what new references are encoded
+//! with. [`Dmx::deduplicate_strings`] removes duplicates, for when every string must have a single index.
+
+use std::{
+    collections::HashMap,
+    ffi::{CStr, CString},
+    mem,
+    ops::Index,
+    slice,
+};
+
+use ordermap::OrderMap;
+
+use crate::{Dmx, SymbolIdx};
+
+#[derive(Debug, Clone, Default)]
+pub struct Symbols {
+    strings: Vec<CString>,
+
+    /// the index of each string's first occurrence in `strings`
+    first_indices: HashMap<CString, usize>,
+}
+
+impl Symbols {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn with_capacity(capacity: usize) -> Self {
+        Self {
+            strings: Vec::with_capacity(capacity),
+            first_indices: HashMap::with_capacity(capacity),
+        }
+    }
+
+    /// Appends `string`, even if it's already in the table, and returns its index. Used when decoding, so that every
+    /// string keeps its encoded position.
+    pub fn push(&mut self, string: CString) -> usize {
+        let idx = self.strings.len();
+        self.first_indices.entry(string.clone()).or_insert(idx);
+        self.strings.push(string);
+        idx
+    }
+
+    /// Appends `string` if it isn't already in the table. Returns the index of its first occurrence, and whether it was
+    /// appended.
+    pub fn insert_full(&mut self, string: CString) -> (usize, bool) {
+        match self.first_indices.get(string.as_c_str()) {
+            Some(&idx) => (idx, false),
+            None => (self.push(string), true),
+        }
+    }
+
+    /// Appends `string` if it isn't already in the table. Returns whether it was appended.
+    pub fn insert(&mut self, string: CString) -> bool {
+        self.insert_full(string).1
+    }
+
+    pub fn get_index(&self, idx: usize) -> Option<&CString> {
+        self.strings.get(idx)
+    }
+
+    /// The index of the first occurrence of `string`.
+    pub fn get_index_of(&self, string: &CStr) -> Option<usize> {
+        self.first_indices.get(string).copied()
+    }
+
+    pub fn contains(&self, string: &CStr) -> bool {
+        self.first_indices.contains_key(string)
+    }
+
+    pub fn len(&self) -> usize {
+        self.strings.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.strings.is_empty()
+    }
+
+    /// Every string, in the order they're encoded, including duplicates.
+    pub fn iter(&self) -> slice::Iter<'_, CString> {
+        self.strings.iter()
+    }
+
+    /// Whether any string is in the table more than once.
+    pub fn has_duplicates(&self) -> bool {
+        self.first_indices.len() != self.strings.len()
+    }
+
+    /// Every duplicate string's index, paired with the index of its first occurrence.
+    pub fn duplicates(&self) -> impl Iterator<Item = (usize, usize)> {
+        self.strings.iter().enumerate().filter_map(|(idx, string)| {
+            let first_idx = self.first_indices[string];
+            (first_idx != idx).then_some((idx, first_idx))
+        })
+    }
+}
+
+/// Tables are equal if they have the same strings in the same order.
+impl PartialEq for Symbols {
+    fn eq(&self, other: &Self) -> bool {
+        self.strings == other.strings
+    }
+}
+
+impl Eq for Symbols {}
+
+impl Index<usize> for Symbols {
+    type Output = CString;
+
+    fn index(&self, idx: usize) -> &Self::Output {
+        &self.strings[idx]
+    }
+}
+
+/// Collects strings like [`Symbols::insert`], so duplicates are skipped.
+impl FromIterator<CString> for Symbols {
+    fn from_iter<T: IntoIterator<Item = CString>>(iter: T) -> Self {
+        let mut symbols = Self::new();
+        symbols.extend(iter);
+        symbols
+    }
+}
+
+impl Extend<CString> for Symbols {
+    fn extend<T: IntoIterator<Item = CString>>(&mut self, iter: T) {
+        for string in iter {
+            self.insert(string);
+        }
+    }
+}
+
+impl<const N: usize> From<[CString; N]> for Symbols {
+    fn from(strings: [CString; N]) -> Self {
+        strings.into_iter().collect()
+    }
+}
+
+impl IntoIterator for Symbols {
+    type Item = CString;
+    type IntoIter = std::vec::IntoIter<CString>;
+
+    fn into_iter(self) -> Self::IntoIter {
+        self.strings.into_iter()
+    }
+}
+
+impl<'a> IntoIterator for &'a Symbols {
+    type Item = &'a CString;
+    type IntoIter = slice::Iter<'a, CString>;
+
+    fn into_iter(self) -> Self::IntoIter {
+        self.strings.iter()
+    }
+}
+
+impl Dmx {
+    /// Removes every duplicate string from the string table, and re-indexes the element types, attribute names &
+    /// signature references which used them to the string's first occurrence. If an element has two attributes whose
+    /// names are the same string, the first is kept. Does nothing if there are no duplicates.
+    pub fn deduplicate_strings(&mut self) {
+        if !self.strings.has_duplicates() {
+            return;
+        }
+
+        let mut strings = Symbols::with_capacity(self.strings.first_indices.len());
+        let new_indices: Vec<SymbolIdx> = mem::take(&mut self.strings)
+            .into_iter()
+            .map(|string| strings.insert_full(string).0 as SymbolIdx)
+            .collect();
+        let reindex = |idx: SymbolIdx| new_indices.get(usize::from(idx)).copied().unwrap_or(idx);
+
+        self.strings = strings;
+        for element in &mut self.elements {
+            element.type_idx = reindex(element.type_idx);
+
+            let mut attributes = OrderMap::with_capacity(element.attributes.len());
+            for (name_idx, attribute) in mem::take(&mut element.attributes) {
+                attributes.entry(reindex(name_idx)).or_insert(attribute);
+            }
+
+            element.attributes = attributes;
+        }
+
+        self.signature_references = mem::take(&mut self.signature_references)
+            .into_iter()
+            .map(|(mut position, signature)| {
+                position.name_idx = reindex(position.name_idx);
+                (position, signature)
+            })
+            .collect();
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::io::Write;
+
+    use bytes::{Buf, BufMut, Bytes, BytesMut};
+    use ordermap::OrderMap;
+
+    use super::Symbols;
+    use crate::{
+        Dmx,
+        attribute::Attribute,
+        dmx::{Element, Version},
+    };
+
+    /// A binary 2 PCF whose string table has "name" twice, with an element using both copies & the string after them.
+    fn duplicate_symbol_pcf() -> Vec<u8> {
+        let mut writer = BytesMut::new().writer();
+        writer
+            .write_all(Version::Binary2Pcf1.as_cstr_with_nul_terminator().to_bytes_with_nul())
+            .unwrap();
+
+        writer.write_all(&4u16.to_le_bytes()).unwrap();
+        for string in [c"DmElement", c"name", c"name", c"value"] {
+            writer.write_all(string.to_bytes_with_nul()).unwrap();
+        }
+
+        writer.write_all(&1u32.to_le_bytes()).unwrap();
+        writer.write_all(&0u16.to_le_bytes()).unwrap();
+        writer.write_all(c"root".to_bytes_with_nul()).unwrap();
+        writer.write_all(&[0; 16]).unwrap();
+
+        writer.write_all(&3u32.to_le_bytes()).unwrap();
+        for (name_idx, value) in [(1u16, 1i32), (2, 2), (3, 3)] {
+            writer.write_all(&name_idx.to_le_bytes()).unwrap();
+            writer.write_all(&[2]).unwrap();
+            writer.write_all(&value.to_le_bytes()).unwrap();
+        }
+
+        writer.into_inner().to_vec()
+    }
+
+    #[test]
+    fn duplicate_symbols_keep_their_positions() {
+        let encoded = duplicate_symbol_pcf();
+        let dmx = Dmx::decode(&mut Bytes::from(encoded.clone()).reader()).unwrap();
+
+        assert_eq!(dmx.strings.len(), 4);
+        assert_eq!(dmx.strings[3], c"value");
+        assert_eq!(dmx.strings.get_index_of(c"name"), Some(1));
+        assert_eq!(dmx.strings.duplicates().collect::<Vec<_>>(), [(2, 1)]);
+
+        let attributes = &dmx.elements[0].attributes;
+        assert_eq!(attributes.get(&2), Some(&Attribute::Integer(2)));
+        assert_eq!(attributes.get(&3), Some(&Attribute::Integer(3)));
+
+        assert_eq!(dmx.encode_to_vec(), encoded);
+    }
+
+    #[test]
+    fn deduplicating_reindexes_elements() {
+        let mut dmx = Dmx::decode(&mut Bytes::from(duplicate_symbol_pcf()).reader()).unwrap();
+        dmx.deduplicate_strings();
+
+        assert_eq!(
+            dmx.strings,
+            Symbols::from([c"DmElement".to_owned(), c"name".to_owned(), c"value".to_owned()])
+        );
+        assert!(!dmx.strings.has_duplicates());
+        assert_eq!(
+            dmx.elements,
+            [Element {
+                type_idx: 0,
+                name: c"root".to_owned(),
+                signature: [0; 16],
+                attributes: OrderMap::from([(1, Attribute::Integer(1)), (2, Attribute::Integer(3))]),
+            }]
+        );
+    }
+
+    #[test]
+    fn inserting_finds_the_first_occurrence() {
+        let mut symbols = Symbols::new();
+        symbols.push(c"a".to_owned());
+        symbols.push(c"b".to_owned());
+        symbols.push(c"a".to_owned());
+
+        assert_eq!(symbols.insert_full(c"a".to_owned()), (0, false));
+        assert_eq!(symbols.insert_full(c"c".to_owned()), (3, true));
+        assert_eq!(symbols.len(), 4);
+    }
+}
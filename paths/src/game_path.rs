@@ -0,0 +1,146 @@
+use std::{borrow::Borrow, fmt};
+
+use typed_path::{StripPrefixError, Utf8Component, Utf8PlatformPath, Utf8PlatformPathBuf};
+
+/// A path in the game's filesystem, like an entry in a VPK, or a file relative to `tf/` or an addon's content. The
+/// engine looks these up case-insensitively with `/` separators, so they're always normalized: ASCII-lowercase,
+/// `/`-separated, without leading, trailing or repeated separators, and without `.` components.
+///
+/// Two paths the engine treats as the same file are always equal, so a [`GamePath`] can key maps & be compared to a
+/// VPK's entries directly.
+#[derive(Debug, Clone, Default, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub struct GamePath(String);
+
+impl GamePath {
+    /// Normalizes `path`, which may use either separator.
+    pub fn new(path: &str) -> Self {
+        let mut normalized = String::with_capacity(path.len());
+        for component in path
+            .split(['/', '\\'])
+            .filter(|component| !component.is_empty() && *component != ".")
+        {
+            if !normalized.is_empty() {
+                normalized.push('/');
+            }
+
+            normalized.push_str(component);
+        }
+
+        normalized.make_ascii_lowercase();
+        Self(normalized)
+    }
+
+    /// The game path of `path`, relative to `root`, e.g. a file in an addon's content relative to the content folder.
+    ///
+    /// # Errors
+    ///
+    /// Returns [`StripPrefixError`] if `path` isn't in `root`.
+    pub fn relative_to(path: &Utf8PlatformPath, root: &Utf8PlatformPath) -> Result<Self, StripPrefixError> {
+        Ok(Self::from_relative(path.strip_prefix(root)?))
+    }
+
+    /// The game path of the relative platform path `path`. Root & prefix components are ignored.
+    pub fn from_relative(path: &Utf8PlatformPath) -> Self {
+        let components: Vec<_> = path
+            .components()
+            .filter(|component| component.is_normal() || component.is_parent())
+            .map(|component| component.as_str())
+            .collect();
+
+        Self::new(&components.join("/"))
+    }
+
+    pub fn as_str(&self) -> &str {
+        &self.0
+    }
+
+    pub fn into_string(self) -> String {
+        self.0
+    }
+
+    /// Appends `path`, which is normalized first.
+    pub fn join(&self, path: &str) -> Self {
+        Self::new(&format!("{}/{path}", self.0))
+    }
+
+    /// The directory containing the file, or [`None`] if it's in the root.
+    pub fn directory(&self) -> Option<&str> {
+        self.0.rsplit_once('/').map(|(directory, _)| directory)
+    }
+
+    pub fn file_name(&self) -> &str {
+        self.0.rsplit_once('/').map_or(&self.0, |(_, file_name)| file_name)
+    }
+
+    /// The file name without its extension. A leading `.` isn't treated as the start of an extension.
+    pub fn file_stem(&self) -> &str {
+        let file_name = self.file_name();
+        match file_name.rsplit_once('.') {
+            Some((stem, _)) if !stem.is_empty() => stem,
+            _ => file_name,
+        }
+    }
+
+    /// The file's extension, without the `.`.
+    pub fn extension(&self) -> Option<&str> {
+        match self.file_name().rsplit_once('.') {
+            Some((stem, extension)) if !stem.is_empty() => Some(extension),
+            _ => None,
+        }
+    }
+
+    /// Whether the path is in `directory`, or one of its subdirectories. `directory` is normalized first.
+    pub fn starts_with(&self, directory: &str) -> bool {
+        let directory = Self::new(directory);
+        directory.0.is_empty()
+            || self
+                .0
+                .strip_prefix(&directory.0)
+                .is_some_and(|rest| rest.is_empty() || rest.starts_with('/'))
+    }
+
+    /// The platform path of this file under `root`, e.g. to find it in an extracted addon. On case-sensitive
+    /// filesystems, the file must have been written with this path's lowercase name.
+    pub fn to_platform_path(&self, root: &Utf8PlatformPath) -> Utf8PlatformPathBuf {
+        let mut path = root.to_path_buf();
+        path.extend(self.0.split('/').filter(|component| !component.is_empty()));
+        path
+    }
+}
+
+impl fmt::Display for GamePath {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(&self.0)
+    }
+}
+
+impl From<&str> for GamePath {
+    fn from(path: &str) -> Self {
+        Self::new(path)
+    }
+}
+
+impl From<String> for GamePath {
+    fn from(path: String) -> Self {
+        Self::new(&path)
+    }
+}
+
+impl From<GamePath> for String {
+    fn from(path: GamePath) -> Self {
+        path.0
+    }
+}
+
+impl AsRef<str> for GamePath {
+    fn as_ref(&self) -> &str {
+        &self.0
+    }
+}
+
+/// Lets maps keyed by [`GamePath`] be queried with a `&str`, which must already be normalized to match.
+impl Borrow<str> for GamePath {
+    fn borrow(&self) -> &str {
+        &self.0
+    }
+}
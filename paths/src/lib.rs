@@ -1,11 +1,25 @@
 use std::{
     borrow::Cow,
+    io,
     path::{Path, PathBuf},
     str::Utf8Error,
 };
 
+use thiserror::Error;
 use typed_path::{PlatformPath, Utf8PlatformPath, Utf8PlatformPathBuf};
 
+mod game_path;
+
+pub use game_path::GamePath;
+
+#[derive(Debug, Error)]
+pub enum PathError {
+    #[error("the path '{}' isn't valid UTF-8", .0.display())]
+    NonUtf8(PathBuf),
+}
+
+/// Converts `path` into a typed path, replacing any invalid UTF-8 with `U+FFFD`. Prefer [`try_to_typed`] when the
+/// path will be used to access the filesystem, since a lossy path won't point to the same file.
 pub fn to_typed(path: &Path) -> Cow<'_, Utf8PlatformPath> {
     match path.as_os_str().to_string_lossy() {
         Cow::Borrowed(path) => Cow::Borrowed(Utf8PlatformPath::from_bytes_path(PlatformPath::new(path)).unwrap()),
@@ -13,6 +27,8 @@ pub fn to_typed(path: &Path) -> Cow<'_, Utf8PlatformPath> {
     }
 }
 
+/// Converts `path` into a typed path, replacing any invalid UTF-8 with `U+FFFD`. Prefer [`try_buf_to_typed`] when the
+/// path will be used to access the filesystem, since a lossy path won't point to the same file.
 pub fn std_buf_to_typed(path: PathBuf) -> Utf8PlatformPathBuf {
     let string = path.into_os_string().to_string_lossy().into_owned();
     Utf8PlatformPathBuf::from(string)
@@ -21,3 +37,59 @@ pub fn std_buf_to_typed(path: PathBuf) -> Utf8PlatformPathBuf {
 pub fn std_to_typed(path: &Path) -> Result<&Utf8PlatformPath, Utf8Error> {
     Utf8PlatformPath::from_bytes_path(PlatformPath::new(path.as_os_str().as_encoded_bytes()))
 }
+
+/// Converts `path` into a typed path.
+///
+/// # Errors
+///
+/// Returns [`PathError::NonUtf8`] if `path` isn't valid UTF-8.
+pub fn try_to_typed(path: &Path) -> Result<&Utf8PlatformPath, PathError> {
+    std_to_typed(path).map_err(|_| PathError::NonUtf8(path.to_path_buf()))
+}
+
+/// Converts `path` into a typed path.
+///
+/// # Errors
+///
+/// Returns [`PathError::NonUtf8`] if `path` isn't valid UTF-8.
+pub fn try_buf_to_typed(path: PathBuf) -> Result<Utf8PlatformPathBuf, PathError> {
+    path.into_os_string()
+        .into_string()
+        .map(Utf8PlatformPathBuf::from)
+        .map_err(|path| PathError::NonUtf8(PathBuf::from(path)))
+}
+
+/// Returns `path` in a form which can be passed to filesystem APIs & external tools regardless of its length.
+///
+/// On Windows, absolute paths longer than `MAX_PATH` are normalized and given the `\\?\` verbatim prefix, which lifts
+/// the 260 character limit. Everywhere else, and for shorter or relative paths, `path` is returned as-is.
+pub fn long_path(path: &Utf8PlatformPath) -> Cow<'_, Utf8PlatformPath> {
+    #[cfg(windows)]
+    {
+        const MAX_PATH: usize = 260;
+        const VERBATIM_PREFIX: &str = r"\\?\";
+
+        let as_str = path.as_str();
+        if as_str.len() >= MAX_PATH && path.is_absolute() && !as_str.starts_with(VERBATIM_PREFIX) {
+            // verbatim paths aren't normalized by windows, so separators & `..` components have to be resolved here
+            let normalized = path.normalize().into_string().replace('/', r"\");
+            let verbatim = match normalized.strip_prefix(r"\\") {
+                Some(unc) => format!(r"{VERBATIM_PREFIX}UNC\{unc}"),
+                None => format!("{VERBATIM_PREFIX}{normalized}"),
+            };
+
+            return Cow::Owned(Utf8PlatformPathBuf::from(verbatim));
+        }
+    }
+
+    Cow::Borrowed(path)
+}
+
+/// Returns the number of bytes available to the current user on the filesystem containing `path`.
+///
+/// # Errors
+///
+/// Returns [`io::Error`] if `path` doesn't exist, or the filesystem couldn't be queried.
+pub fn available_space(path: impl AsRef<Path>) -> io::Result<u64> {
+    fs4::available_space(path)
+}
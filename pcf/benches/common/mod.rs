@@ -0,0 +1,108 @@
+//! Synthetic particles shared by the benchmarks, shaped like the vanilla particles: every graph has a few systems with a
+//! handful of operators each, and most graphs share the same symbol table.
+
+// each benchmark only uses some of these
+#![allow(dead_code)]
+
+use std::{env, fs};
+
+use dmx::{Dmx, dmx::Version};
+use pcf::{Attribute, AttributeMap, Operator, ParticleSystem, Pcf, Root, Symbol, Symbols};
+
+pub const SYSTEMS_PER_GRAPH: usize = 4;
+pub const OPERATORS_PER_SYSTEM: usize = 6;
+pub const ATTRIBUTES: [&str; 8] = [
+    "radius",
+    "alpha",
+    "color",
+    "lifetime",
+    "rate",
+    "scale",
+    "max_particles",
+    "material",
+];
+
+/// Set to the path of a PCF to benchmark decoding it instead of the synthetic corpus, e.g. a vanilla particle.
+pub const CORPUS_VAR: &str = "PCF_BENCH_CORPUS";
+
+/// A graph of `SYSTEMS_PER_GRAPH` systems. Every 4th graph has its attribute names in reverse order, so its symbol
+/// table doesn't line up with the others'.
+pub fn graph(idx: usize) -> Pcf {
+    let mut symbols = Symbols::new_with_all_special();
+    let reversed = idx % 4 == 3;
+    let names: Vec<_> = if reversed {
+        ATTRIBUTES.iter().rev().collect()
+    } else {
+        ATTRIBUTES.iter().collect()
+    };
+
+    for name in names {
+        symbols.base.insert((*name).into());
+    }
+
+    let attributes = || -> AttributeMap {
+        ATTRIBUTES
+            .iter()
+            .enumerate()
+            .map(|(value, name)| {
+                let name_idx = symbols.base.get_index_of(*name).unwrap() as u16;
+                (name_idx, Attribute::Integer(value as i32))
+            })
+            .collect()
+    };
+
+    let systems = (0..SYSTEMS_PER_GRAPH)
+        .map(|system| ParticleSystem {
+            name: format!("graph_{idx}_system_{system}"),
+            operators: (0..OPERATORS_PER_SYSTEM)
+                .map(|operator| Operator {
+                    name: format!("operator_{operator}"),
+                    function_name: "Alpha Fade In Random".to_string(),
+                    signature: [0; 16],
+                    attributes: attributes(),
+                })
+                .collect(),
+            attributes: attributes(),
+            ..ParticleSystem::default()
+        })
+        .collect();
+
+    let root = Root::new("untitled".to_string(), [0; 16], systems, AttributeMap::new());
+    Pcf::new(Version::Binary2Pcf1, symbols, root)
+}
+
+/// `count` graphs, see [`graph`].
+pub fn graphs(count: usize) -> Vec<Pcf> {
+    (0..count).map(graph).collect()
+}
+
+/// Like [`graph`], but with `unused` more symbols which none of its elements reference.
+pub fn graph_with_unused_symbols(idx: usize, unused: usize) -> Pcf {
+    let graph = graph(idx);
+    let mut symbols = graph.symbols().clone();
+    symbols
+        .base
+        .extend((0..unused).map(|symbol| Symbol::from(format!("unused_{idx}_{symbol}"))));
+    Pcf::new(graph.version(), symbols, graph.root().clone())
+}
+
+/// Merges `graphs` in order, like packing them into one bin does.
+pub fn merge_all(graphs: Vec<Pcf>) -> Pcf {
+    let mut graphs = graphs.into_iter();
+    let mut merged = graphs.next().expect("there should be at least one graph");
+    for mut graph in graphs {
+        merged.merged_in(&mut graph).expect("the graphs should merge");
+    }
+
+    merged
+}
+
+/// The encoded PCF to decode: the file named by [`CORPUS_VAR`] if it's set, and 400 merged graphs otherwise.
+pub fn corpus() -> Vec<u8> {
+    if let Ok(path) = env::var(CORPUS_VAR) {
+        return fs::read(&path)
+            .unwrap_or_else(|err| panic!("{CORPUS_VAR} should name a readable PCF, '{path}': {err}"));
+    }
+
+    Dmx::from(merge_all(graphs(400))).encode_to_vec()
+}
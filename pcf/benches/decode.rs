@@ -0,0 +1,40 @@
+//! Decodes & encodes a large PCF: the file named by `PCF_BENCH_CORPUS`, or 400 merged synthetic graphs.
+
+mod common;
+
+use std::hint::black_box;
+
+use bytes::Bytes;
+use criterion::{BatchSize, Criterion, Throughput, criterion_group, criterion_main};
+use dmx::Dmx;
+use pcf::Pcf;
+
+use crate::common::corpus;
+
+fn decode(c: &mut Criterion) {
+    let bytes = Bytes::from(corpus());
+    let pcf = Pcf::try_from(dmx::decode_bytes(bytes.clone()).unwrap()).unwrap();
+
+    let mut group = c.benchmark_group("decode");
+    group.throughput(Throughput::Bytes(bytes.len() as u64));
+    group.bench_function("dmx", |b| b.iter(|| dmx::decode(&mut black_box(&bytes[..])).unwrap()));
+    group.bench_function("dmx from bytes", |b| {
+        b.iter(|| dmx::decode_bytes(black_box(bytes.clone())).unwrap());
+    });
+    group.bench_function("pcf", |b| b.iter(|| pcf::decode(&mut black_box(&bytes[..])).unwrap()));
+    group.finish();
+
+    let mut group = c.benchmark_group("encode");
+    group.throughput(Throughput::Bytes(bytes.len() as u64));
+    group.bench_function("pcf", |b| {
+        b.iter_batched(
+            || pcf.clone(),
+            |pcf| Dmx::from(pcf).encode_to_vec(),
+            BatchSize::LargeInput,
+        );
+    });
+    group.finish();
+}
+
+criterion_group!(benches, decode);
+criterion_main!(benches);
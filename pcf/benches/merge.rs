@@ -0,0 +1,68 @@
+//! Merges particle graphs into one PCF, like packing every vanilla particle does. Most of the graphs are split from the
+//! same file and so share its symbol table, while the rest have their symbols in another order and have to be
+//! reindexed.
+//!
+//! The number of allocations made by merging 400 graphs is printed before anything is benchmarked.
+
+mod common;
+
+use std::{
+    alloc::{GlobalAlloc, Layout, System},
+    hint::black_box,
+    sync::atomic::{AtomicUsize, Ordering},
+};
+
+use criterion::{BatchSize, BenchmarkId, Criterion, criterion_group, criterion_main};
+
+use crate::common::{graphs, merge_all};
+
+const GRAPHS: usize = 400;
+
+struct CountingAlloc;
+
+static ALLOCATIONS: AtomicUsize = AtomicUsize::new(0);
+
+unsafe impl GlobalAlloc for CountingAlloc {
+    unsafe fn alloc(&self, layout: Layout) -> *mut u8 {
+        ALLOCATIONS.fetch_add(1, Ordering::Relaxed);
+        unsafe { System.alloc(layout) }
+    }
+
+    unsafe fn dealloc(&self, ptr: *mut u8, layout: Layout) {
+        unsafe { System.dealloc(ptr, layout) }
+    }
+
+    unsafe fn realloc(&self, ptr: *mut u8, layout: Layout, new_size: usize) -> *mut u8 {
+        ALLOCATIONS.fetch_add(1, Ordering::Relaxed);
+        unsafe { System.realloc(ptr, layout, new_size) }
+    }
+}
+
+#[global_allocator]
+static GLOBAL: CountingAlloc = CountingAlloc;
+
+fn merge(c: &mut Criterion) {
+    let input = graphs(GRAPHS);
+    let before = ALLOCATIONS.load(Ordering::Relaxed);
+    let merged = black_box(merge_all(input));
+    let allocations = ALLOCATIONS.load(Ordering::Relaxed) - before;
+    println!(
+        "merging {GRAPHS} graphs into {} systems made {allocations} allocations",
+        merged.particle_systems().len()
+    );
+
+    // merging is quadratic in the number of graphs, so the larger merges take a while
+    let mut group = c.benchmark_group("merge");
+    group.sample_size(10);
+    for count in [100, 200, GRAPHS] {
+        let graphs = graphs(count);
+        group.bench_with_input(BenchmarkId::from_parameter(count), &graphs, |b, graphs| {
+            b.iter_batched(|| graphs.clone(), merge_all, BatchSize::LargeInput);
+        });
+    }
+
+    group.finish();
+}
+
+criterion_group!(benches, merge);
+criterion_main!(benches);
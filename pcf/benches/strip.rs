@@ -0,0 +1,25 @@
+//! Strips the unused symbols from a large PCF: the file named by `PCF_BENCH_CORPUS`, or 400 merged synthetic graphs
+//! which each carry symbols nothing references.
+
+mod common;
+
+use std::env;
+
+use criterion::{BatchSize, Criterion, criterion_group, criterion_main};
+
+use crate::common::{CORPUS_VAR, corpus, graph_with_unused_symbols, merge_all};
+
+fn strip(c: &mut Criterion) {
+    let pcf = if env::var_os(CORPUS_VAR).is_some() {
+        pcf::decode(&mut &corpus()[..]).unwrap()
+    } else {
+        merge_all((0..400).map(|idx| graph_with_unused_symbols(idx, 16)).collect())
+    };
+
+    c.bench_function("unused_symbols_stripped", |b| {
+        b.iter_batched(|| pcf.clone(), pcf::Pcf::unused_symbols_stripped, BatchSize::LargeInput);
+    });
+}
+
+criterion_group!(benches, strip);
+criterion_main!(benches);
@@ -0,0 +1,17 @@
+//! Generates the typed operator schema in [`pcf::schema`] when the `schema` feature is enabled, see `build/schema.rs`.
+//! Without the feature there's nothing to generate, so the build script's dependencies are only needed with it.
+
+#[cfg(feature = "schema")]
+#[path = "build/schema.rs"]
+mod schema;
+
+#[cfg(feature = "schema")]
+fn main() -> anyhow::Result<()> {
+    println!("cargo:rerun-if-changed=build.rs");
+    schema::write_schema()
+}
+
+#[cfg(not(feature = "schema"))]
+fn main() {
+    println!("cargo:rerun-if-changed=build.rs");
+}
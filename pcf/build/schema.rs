@@ -0,0 +1,459 @@
+//! Generates the typed operator schema in [`pcf::schema`] from `default_values.pcf`.
+//!
+//! `default_values.pcf` contains one particle system per operator, with every attribute set to its default value. Each
+//! distinct `functionName` becomes a struct, and each of its attributes becomes a field.
+
+use std::{
+    collections::HashSet,
+    env,
+    fmt::Write as _,
+    fs::{self, File},
+    io::BufReader,
+    path::PathBuf,
+};
+
+use dmx::{Dmx, Float, Matrix, Vector2, Vector3, Vector4, attribute::Attribute};
+
+const DEFAULT_VALUES_PCF: &str = "default_values.pcf";
+
+const KEYWORDS: &[&str] = &[
+    "abstract", "as", "async", "await", "become", "box", "break", "const", "continue", "crate", "do", "dyn", "else",
+    "enum", "extern", "false", "final", "fn", "for", "gen", "if", "impl", "in", "let", "loop", "macro", "match", "mod",
+    "move", "mut", "override", "priv", "pub", "ref", "return", "self", "static", "struct", "super", "trait", "true",
+    "try", "type", "typeof", "unsafe", "unsized", "use", "virtual", "where", "while", "yield",
+];
+
+struct Schema {
+    function_name: String,
+    ident: String,
+    fields: Vec<Field>,
+}
+
+struct Field {
+    attribute_name: String,
+    ident: String,
+    kind: Kind,
+    value: String,
+    description: String,
+}
+
+/// How an attribute type is spelled in the generated code.
+struct Kind {
+    variant: &'static str,
+    ty: &'static str,
+    type_name: &'static str,
+    is_copy: bool,
+}
+
+impl Kind {
+    const fn new(variant: &'static str, ty: &'static str, type_name: &'static str, is_copy: bool) -> Self {
+        Self {
+            variant,
+            ty,
+            type_name,
+            is_copy,
+        }
+    }
+
+    /// Operators never reference other elements, so element attributes have no [`Kind`].
+    fn of(attribute: &Attribute) -> Option<Self> {
+        Some(match attribute {
+            Attribute::Element(_) | Attribute::ElementArray(_) => return None,
+            Attribute::Integer(_) => Self::new("Integer", "i32", "int", true),
+            Attribute::Float(_) => Self::new("Float", "dmx::Float", "float", true),
+            Attribute::Bool(_) => Self::new("Bool", "bool", "bool", true),
+            Attribute::String(_) => Self::new("String", "String", "string", false),
+            Attribute::Binary(_) => Self::new("Binary", "dmx::Blob", "binary", false),
+            Attribute::Color(_) => Self::new("Color", "dmx::Color", "color", true),
+            Attribute::Vector2(_) => Self::new("Vector2", "dmx::Vector2", "vector2", true),
+            Attribute::Vector3(_) => Self::new("Vector3", "dmx::Vector3", "vector3", true),
+            Attribute::Vector4(_) => Self::new("Vector4", "dmx::Vector4", "vector4", true),
+            Attribute::Matrix(_) => Self::new("Matrix", "dmx::Matrix", "matrix", true),
+            Attribute::IntegerArray(_) => Self::new("IntegerArray", "Box<[i32]>", "int_array", false),
+            Attribute::FloatArray(_) => Self::new("FloatArray", "Box<[dmx::Float]>", "float_array", false),
+            Attribute::BoolArray(_) => Self::new("BoolArray", "Box<[dmx::attribute::Bool8]>", "bool_array", false),
+            Attribute::StringArray(_) => Self::new("StringArray", "Box<[String]>", "string_array", false),
+            Attribute::BinaryArray(_) => Self::new("BinaryArray", "Box<[dmx::Blob]>", "binary_array", false),
+            Attribute::ColorArray(_) => Self::new("ColorArray", "Box<[dmx::Color]>", "color_array", false),
+            Attribute::Vector2Array(_) => Self::new("Vector2Array", "Box<[dmx::Vector2]>", "vector2_array", false),
+            Attribute::Vector3Array(_) => Self::new("Vector3Array", "Box<[dmx::Vector3]>", "vector3_array", false),
+            Attribute::Vector4Array(_) => Self::new("Vector4Array", "Box<[dmx::Vector4]>", "vector4_array", false),
+            Attribute::MatrixArray(_) => Self::new("MatrixArray", "Box<[dmx::Matrix]>", "matrix_array", false),
+        })
+    }
+}
+
+/// Generates `schema.rs` in `OUT_DIR`.
+pub(super) fn write_schema() -> anyhow::Result<()> {
+    println!("cargo:rerun-if-changed=build/schema.rs");
+    println!("cargo:rerun-if-changed={DEFAULT_VALUES_PCF}");
+
+    let mut reader = BufReader::new(File::open(DEFAULT_VALUES_PCF)?);
+    let dmx = dmx::decode(&mut reader)?;
+
+    let out_path = PathBuf::from(env::var("OUT_DIR")?).join("schema.rs");
+    fs::write(out_path, generate(&collect_schemas(&dmx))?)?;
+
+    Ok(())
+}
+
+fn collect_schemas(dmx: &Dmx) -> Vec<Schema> {
+    let operator_type_idx = dmx.strings.get_index_of(c"DmeParticleOperator");
+    let function_name_idx = dmx.strings.get_index_of(c"functionName");
+
+    let mut schemas = Vec::new();
+    let mut function_names = HashSet::new();
+    let mut idents = HashSet::new();
+    for element in &dmx.elements {
+        if operator_type_idx != Some(element.type_idx as usize) {
+            continue;
+        }
+
+        let Some(Attribute::String(function_name)) =
+            function_name_idx.and_then(|idx| element.attributes.get(&(idx as u16)))
+        else {
+            continue;
+        };
+
+        // the same operator may be used by several particle systems; the first one wins
+        let function_name = function_name.to_string_lossy().into_owned();
+        if !function_names.insert(function_name.clone()) {
+            continue;
+        }
+
+        let mut fields = Vec::new();
+        let mut field_idents = HashSet::new();
+        for (name_idx, attribute) in &element.attributes {
+            if function_name_idx == Some(*name_idx as usize) {
+                continue;
+            }
+
+            let Some(kind) = Kind::of(attribute) else {
+                continue;
+            };
+
+            let attribute_name = dmx.strings[*name_idx as usize].to_string_lossy().into_owned();
+            fields.push(Field {
+                ident: unique(field_ident(&attribute_name), "_", &mut field_idents),
+                attribute_name,
+                kind,
+                value: literal(attribute),
+                description: describe(attribute),
+            });
+        }
+
+        schemas.push(Schema {
+            ident: unique(type_ident(&function_name), "", &mut idents),
+            function_name,
+            fields,
+        });
+    }
+
+    schemas
+}
+
+fn generate(schemas: &[Schema]) -> Result<String, std::fmt::Error> {
+    let mut out = String::new();
+    for schema in schemas {
+        generate_struct(&mut out, schema)?;
+    }
+
+    writeln!(out, "/// Any operator with a known schema.")?;
+    writeln!(out, "#[derive(Debug, Clone, PartialEq)]")?;
+    writeln!(out, "#[allow(clippy::large_enum_variant)]")?;
+    writeln!(out, "pub enum KnownOperator {{")?;
+    for Schema { ident, .. } in schemas {
+        writeln!(out, "    {ident}({ident}),")?;
+    }
+    writeln!(out, "}}\n")?;
+
+    writeln!(out, "impl KnownOperator {{")?;
+    writeln!(out, "    /// The `functionName` of every known operator.")?;
+    writeln!(out, "    pub const FUNCTION_NAMES: &[&str] = &[")?;
+    for Schema { ident, .. } in schemas {
+        writeln!(out, "        {ident}::FUNCTION_NAME,")?;
+    }
+    writeln!(out, "    ];\n")?;
+
+    writeln!(
+        out,
+        "    /// The operator with this `functionName`, with every field set to its default value."
+    )?;
+    writeln!(out, "    pub fn default_for(function_name: &str) -> Option<Self> {{")?;
+    writeln!(out, "        match function_name {{")?;
+    for Schema {
+        function_name, ident, ..
+    } in schemas
+    {
+        writeln!(
+            out,
+            "            {function_name:?} => Some(Self::{ident}({ident}::default())),"
+        )?;
+    }
+    writeln!(out, "            _ => None,")?;
+    writeln!(out, "        }}")?;
+    writeln!(out, "    }}\n")?;
+
+    for (signature, call) in [
+        ("pub fn function_name(&self) -> &'static str", "FUNCTION_NAME"),
+        (
+            "pub fn attributes(&self) -> Vec<(&'static str, Attribute)>",
+            "attributes()",
+        ),
+        (
+            "pub fn set_attribute(&mut self, name: &str, attribute: &Attribute) -> Result<bool, TypeMismatch>",
+            "set_attribute(name, attribute)",
+        ),
+    ] {
+        writeln!(out, "    {signature} {{")?;
+        writeln!(out, "        match self {{")?;
+        for Schema { ident, .. } in schemas {
+            if call == "FUNCTION_NAME" {
+                writeln!(out, "            Self::{ident}(_) => {ident}::FUNCTION_NAME,")?;
+            } else {
+                writeln!(out, "            Self::{ident}(operator) => operator.{call},")?;
+            }
+        }
+        writeln!(out, "        }}")?;
+        writeln!(out, "    }}\n")?;
+    }
+    writeln!(out, "}}")?;
+
+    Ok(out)
+}
+
+fn generate_struct(out: &mut String, schema: &Schema) -> std::fmt::Result {
+    let Schema {
+        function_name,
+        ident,
+        fields,
+    } = schema;
+
+    writeln!(out, "/// The `{function_name}` operator.")?;
+    writeln!(out, "#[derive(Debug, Clone, PartialEq)]")?;
+    writeln!(out, "pub struct {ident} {{")?;
+    for field in fields {
+        writeln!(
+            out,
+            "    /// `{}`, which defaults to `{}`.",
+            field.attribute_name, field.description
+        )?;
+        writeln!(out, "    pub {}: {},", field.ident, field.kind.ty)?;
+    }
+    writeln!(out, "}}\n")?;
+
+    // some operators have no attributes, or only attributes which happen to match their type's default
+    writeln!(out, "#[allow(clippy::derivable_impls)]")?;
+    writeln!(out, "impl Default for {ident} {{")?;
+    writeln!(out, "    fn default() -> Self {{")?;
+    writeln!(out, "        Self {{")?;
+    for field in fields {
+        writeln!(out, "            {}: {},", field.ident, field.value)?;
+    }
+    writeln!(out, "        }}")?;
+    writeln!(out, "    }}")?;
+    writeln!(out, "}}\n")?;
+
+    writeln!(out, "impl OperatorSchema for {ident} {{")?;
+    writeln!(out, "    const FUNCTION_NAME: &'static str = {function_name:?};\n")?;
+
+    writeln!(out, "    fn attributes(&self) -> Vec<(&'static str, Attribute)> {{")?;
+    writeln!(out, "        vec![")?;
+    for field in fields {
+        let value = if field.kind.is_copy {
+            format!("self.{}", field.ident)
+        } else {
+            format!("self.{}.clone()", field.ident)
+        };
+        writeln!(
+            out,
+            "            ({:?}, Attribute::{}({value})),",
+            field.attribute_name, field.kind.variant
+        )?;
+    }
+    writeln!(out, "        ]")?;
+    writeln!(out, "    }}\n")?;
+
+    if fields.is_empty() {
+        writeln!(
+            out,
+            "    fn set_attribute(&mut self, _name: &str, _attribute: &Attribute) -> Result<bool, TypeMismatch> {{"
+        )?;
+        writeln!(out, "        Ok(false)")?;
+        writeln!(out, "    }}")?;
+        writeln!(out, "}}\n")?;
+        return Ok(());
+    }
+
+    writeln!(
+        out,
+        "    fn set_attribute(&mut self, name: &str, attribute: &Attribute) -> Result<bool, TypeMismatch> {{"
+    )?;
+    writeln!(out, "        match name {{")?;
+    for field in fields {
+        let value = if field.kind.is_copy { "*value" } else { "value.clone()" };
+        writeln!(out, "            {:?} => {{", field.attribute_name)?;
+        writeln!(
+            out,
+            "                let Attribute::{}(value) = attribute else {{",
+            field.kind.variant
+        )?;
+        writeln!(
+            out,
+            "                    return Err(TypeMismatch {{ expected: {:?}, actual: attribute.type_name() }});",
+            field.kind.type_name
+        )?;
+        writeln!(out, "                }};")?;
+        writeln!(out, "                self.{} = {value};", field.ident)?;
+        writeln!(out, "                Ok(true)")?;
+        writeln!(out, "            }}")?;
+    }
+    writeln!(out, "            _ => Ok(false),")?;
+    writeln!(out, "        }}")?;
+    writeln!(out, "    }}")?;
+    writeln!(out, "}}\n")?;
+
+    Ok(())
+}
+
+/// A Rust expression which evaluates to the value of `attribute`, as the type given by [`Kind::of`].
+fn literal(attribute: &Attribute) -> String {
+    fn float(value: &Float) -> String {
+        if value.0.is_finite() {
+            format!("dmx::Float::from({:?}_f32)", value.0)
+        } else {
+            format!("dmx::Float::from(f32::from_bits({:#x}))", value.0.to_bits())
+        }
+    }
+
+    fn vector2(Vector2(x, y): &Vector2) -> String {
+        format!("dmx::Vector2({}, {})", float(x), float(y))
+    }
+
+    fn vector3(Vector3(x, y, z): &Vector3) -> String {
+        format!("dmx::Vector3({}, {}, {})", float(x), float(y), float(z))
+    }
+
+    fn vector4(Vector4(x, y, z, w): &Vector4) -> String {
+        format!("dmx::Vector4({}, {}, {}, {})", float(x), float(y), float(z), float(w))
+    }
+
+    fn matrix(Matrix(a, b, c, d): &Matrix) -> String {
+        format!(
+            "dmx::Matrix({}, {}, {}, {})",
+            vector4(a),
+            vector4(b),
+            vector4(c),
+            vector4(d)
+        )
+    }
+
+    fn bytes(value: &[u8]) -> String {
+        let bytes: Vec<_> = value.iter().map(u8::to_string).collect();
+        format!("dmx::Blob::from_static(&[{}])", bytes.join(", "))
+    }
+
+    fn array<T>(values: &[T], element: impl Fn(&T) -> String) -> String {
+        let elements: Vec<_> = values.iter().map(element).collect();
+        format!("Box::from([{}])", elements.join(", "))
+    }
+
+    match attribute {
+        Attribute::Element(_) | Attribute::ElementArray(_) => unreachable!("element attributes are never generated"),
+        Attribute::Integer(value) => value.to_string(),
+        Attribute::Float(value) => float(value),
+        Attribute::Bool(value) => bool::from(*value).to_string(),
+        Attribute::String(value) => format!("{:?}.to_string()", value.to_string_lossy()),
+        Attribute::Binary(value) => bytes(value),
+        Attribute::Color(dmx::Color(r, g, b, a)) => format!("dmx::Color({r}, {g}, {b}, {a})"),
+        Attribute::Vector2(value) => vector2(value),
+        Attribute::Vector3(value) => vector3(value),
+        Attribute::Vector4(value) => vector4(value),
+        Attribute::Matrix(value) => matrix(value),
+        Attribute::IntegerArray(values) => array(values, i32::to_string),
+        Attribute::FloatArray(values) => array(values, float),
+        Attribute::BoolArray(values) => array(values, |value| {
+            format!("dmx::attribute::Bool8::from({})", bool::from(*value))
+        }),
+        Attribute::StringArray(values) => array(values, |value| format!("{:?}.to_string()", value.to_string_lossy())),
+        Attribute::BinaryArray(values) => array(values, |value| bytes(value)),
+        Attribute::ColorArray(values) => array(values, |dmx::Color(r, g, b, a)| {
+            format!("dmx::Color({r}, {g}, {b}, {a})")
+        }),
+        Attribute::Vector2Array(values) => array(values, vector2),
+        Attribute::Vector3Array(values) => array(values, vector3),
+        Attribute::Vector4Array(values) => array(values, vector4),
+        Attribute::MatrixArray(values) => array(values, matrix),
+    }
+}
+
+/// A short, human-readable description of `attribute`'s value for the generated docs.
+fn describe(attribute: &Attribute) -> String {
+    match attribute {
+        Attribute::Integer(value) => value.to_string(),
+        Attribute::Float(value) => format!("{:?}", value.0),
+        Attribute::Bool(value) => value.to_string(),
+        Attribute::String(value) => format!("{:?}", value.to_string_lossy()),
+        Attribute::Color(value) => value.to_string(),
+        Attribute::Vector2(value) => value.to_string(),
+        Attribute::Vector3(value) => value.to_string(),
+        Attribute::Vector4(value) => value.to_string(),
+        Attribute::Matrix(value) => value.to_string(),
+        Attribute::Binary(value) => format!("{} bytes", value.len()),
+        _ => "[]".to_string(),
+    }
+}
+
+/// Converts a `functionName` like `"Movement Basic"` or `"render_animated_sprites"` into an UpperCamelCase identifier.
+fn type_ident(name: &str) -> String {
+    let mut ident: String = name
+        .split(|c: char| !c.is_ascii_alphanumeric())
+        .filter(|word| !word.is_empty())
+        .map(|word| {
+            let mut chars = word.chars();
+            let first = chars.next().expect("empty words are filtered out");
+            first.to_ascii_uppercase().to_string() + chars.as_str()
+        })
+        .collect();
+
+    if !ident.starts_with(|c: char| c.is_ascii_alphabetic()) {
+        ident.insert_str(0, "Operator");
+    }
+
+    ident
+}
+
+/// Converts an attribute name like `"operator start fadein"` into a snake_case identifier.
+fn field_ident(name: &str) -> String {
+    let mut ident = String::new();
+    for c in name.chars() {
+        if c.is_ascii_alphanumeric() {
+            ident.push(c.to_ascii_lowercase());
+        } else if !ident.is_empty() && !ident.ends_with('_') {
+            ident.push('_');
+        }
+    }
+
+    let mut ident = ident.trim_end_matches('_').to_string();
+    if !ident.starts_with(|c: char| c.is_ascii_alphabetic()) {
+        ident.insert_str(0, "field_");
+    }
+
+    if KEYWORDS.contains(&ident.as_str()) {
+        ident.push('_');
+    }
+
+    ident
+}
+
+/// Appends a number to `ident` if it's already been taken, e.g. two attributes named `"radius"` & `"Radius"`.
+fn unique(ident: String, separator: &str, taken: &mut HashSet<String>) -> String {
+    let mut candidate = ident.clone();
+    let mut suffix = 2;
+    while !taken.insert(candidate.clone()) {
+        candidate = format!("{ident}{separator}{suffix}");
+        suffix += 1;
+    }
+
+    candidate
+}
@@ -1,17 +1,24 @@
-use std::ffi::CString;
+use std::{
+    ffi::CString,
+    fmt::{self, Display},
+};
 
 use derive_more::From;
-use dmx::attribute::{Bool8, Color, Float, Matrix, Vector2, Vector3, Vector4};
+use dmx::{
+    Blob,
+    attribute::{Bool8, Color, FixedEncodedSize, Float, Matrix, Vector2, Vector3, Vector4},
+};
+use thiserror::Error;
 
 use crate::{new::Error, strings::string_to_cstring};
 
-#[derive(Debug, From, Clone, PartialEq)]
+#[derive(Debug, From, Clone, Hash, PartialEq, Eq, PartialOrd, Ord)]
 pub enum Attribute {
     Integer(i32),
     Float(Float),
     Bool(bool),
     String(String),
-    Binary(Box<[u8]>),
+    Binary(Blob),
     Color(Color),
     Vector2(Vector2),
     Vector3(Vector3),
@@ -21,7 +28,7 @@ pub enum Attribute {
     FloatArray(Box<[Float]>),
     BoolArray(Box<[Bool8]>),
     StringArray(Box<[String]>),
-    BinaryArray(Box<[Box<[u8]>]>),
+    BinaryArray(Box<[Blob]>),
     ColorArray(Box<[Color]>),
     Vector2Array(Box<[Vector2]>),
     Vector3Array(Box<[Vector3]>),
@@ -37,31 +44,286 @@ impl From<f32> for Attribute {
 
 impl Attribute {
     pub(crate) fn get_encoded_size(&self) -> usize {
+        fn array<T: FixedEncodedSize>(values: &[T]) -> usize {
+            size_of::<u32>() + values.len() * T::ENCODED_SIZE
+        }
+
         match self {
-            Attribute::Integer(_) => size_of::<i32>(),
-            Attribute::Float(_) => size_of::<f32>(),
-            Attribute::Bool(_) => size_of::<Bool8>(),
+            Attribute::Integer(_) => i32::ENCODED_SIZE,
+            Attribute::Float(_) => Float::ENCODED_SIZE,
+            Attribute::Bool(_) => Bool8::ENCODED_SIZE,
             Attribute::String(value) => 1 + value.len(),
             Attribute::Binary(value) => size_of::<u32>() + value.len(),
-            Attribute::Color(_) => size_of::<Color>(),
-            Attribute::Vector2(_) => size_of::<Vector2>(),
-            Attribute::Vector3(_) => size_of::<Vector3>(),
-            Attribute::Vector4(_) => size_of::<Vector4>(),
-            Attribute::Matrix(_) => size_of::<Matrix>(),
-            Attribute::IntegerArray(value) => size_of::<u32>() + (value.len() * size_of::<i32>()),
-            Attribute::FloatArray(value) => size_of::<u32>() + (value.len() * size_of::<f32>()),
-            Attribute::BoolArray(value) => size_of::<u32>() + (value.len() * size_of::<Bool8>()),
+            Attribute::Color(_) => Color::ENCODED_SIZE,
+            Attribute::Vector2(_) => Vector2::ENCODED_SIZE,
+            Attribute::Vector3(_) => Vector3::ENCODED_SIZE,
+            Attribute::Vector4(_) => Vector4::ENCODED_SIZE,
+            Attribute::Matrix(_) => Matrix::ENCODED_SIZE,
+            Attribute::IntegerArray(value) => array(value),
+            Attribute::FloatArray(value) => array(value),
+            Attribute::BoolArray(value) => array(value),
             Attribute::StringArray(value) => {
                 size_of::<u32>() + value.len() + value.iter().map(String::len).sum::<usize>()
             }
             Attribute::BinaryArray(value) => {
                 size_of::<u32>() + value.iter().map(|value| size_of::<u32>() + value.len()).sum::<usize>()
             }
-            Attribute::ColorArray(value) => size_of::<u32>() + (value.len() * size_of::<Color>()),
-            Attribute::Vector2Array(value) => size_of::<u32>() + (value.len() * size_of::<Vector2>()),
-            Attribute::Vector3Array(value) => size_of::<u32>() + (value.len() * size_of::<Vector3>()),
-            Attribute::Vector4Array(value) => size_of::<u32>() + (value.len() * size_of::<Vector4>()),
-            Attribute::MatrixArray(value) => size_of::<u32>() + (value.len() * size_of::<Matrix>()),
+            Attribute::ColorArray(value) => array(value),
+            Attribute::Vector2Array(value) => array(value),
+            Attribute::Vector3Array(value) => array(value),
+            Attribute::Vector4Array(value) => array(value),
+            Attribute::MatrixArray(value) => array(value),
+        }
+    }
+
+    /// Whether `self` and `other` are the same type of attribute, and every float they contain is within `epsilon` of
+    /// its counterpart. Non-float values must be exactly equal.
+    pub fn approx_eq(&self, other: &Self, epsilon: f32) -> bool {
+        fn float(a: Float, b: Float, epsilon: f32) -> bool {
+            a == b || (a.0 - b.0).abs() <= epsilon
+        }
+
+        fn vector2(a: &Vector2, b: &Vector2, epsilon: f32) -> bool {
+            float(a.0, b.0, epsilon) && float(a.1, b.1, epsilon)
+        }
+
+        fn vector3(a: &Vector3, b: &Vector3, epsilon: f32) -> bool {
+            float(a.0, b.0, epsilon) && float(a.1, b.1, epsilon) && float(a.2, b.2, epsilon)
+        }
+
+        fn vector4(a: &Vector4, b: &Vector4, epsilon: f32) -> bool {
+            float(a.0, b.0, epsilon) && float(a.1, b.1, epsilon) && float(a.2, b.2, epsilon) && float(a.3, b.3, epsilon)
+        }
+
+        fn matrix(a: &Matrix, b: &Matrix, epsilon: f32) -> bool {
+            vector4(&a.0, &b.0, epsilon)
+                && vector4(&a.1, &b.1, epsilon)
+                && vector4(&a.2, &b.2, epsilon)
+                && vector4(&a.3, &b.3, epsilon)
+        }
+
+        fn array<T>(a: &[T], b: &[T], epsilon: f32, eq: impl Fn(&T, &T, f32) -> bool) -> bool {
+            a.len() == b.len() && a.iter().zip(b).all(|(a, b)| eq(a, b, epsilon))
+        }
+
+        match (self, other) {
+            (Attribute::Float(a), Attribute::Float(b)) => float(*a, *b, epsilon),
+            (Attribute::Vector2(a), Attribute::Vector2(b)) => vector2(a, b, epsilon),
+            (Attribute::Vector3(a), Attribute::Vector3(b)) => vector3(a, b, epsilon),
+            (Attribute::Vector4(a), Attribute::Vector4(b)) => vector4(a, b, epsilon),
+            (Attribute::Matrix(a), Attribute::Matrix(b)) => matrix(a, b, epsilon),
+            (Attribute::FloatArray(a), Attribute::FloatArray(b)) => {
+                array(a, b, epsilon, |a, b, epsilon| float(*a, *b, epsilon))
+            }
+            (Attribute::Vector2Array(a), Attribute::Vector2Array(b)) => array(a, b, epsilon, vector2),
+            (Attribute::Vector3Array(a), Attribute::Vector3Array(b)) => array(a, b, epsilon, vector3),
+            (Attribute::Vector4Array(a), Attribute::Vector4Array(b)) => array(a, b, epsilon, vector4),
+            (Attribute::MatrixArray(a), Attribute::MatrixArray(b)) => array(a, b, epsilon, matrix),
+            _ => self == other,
+        }
+    }
+}
+
+/// How attribute values are compared against each other, e.g. when stripping attributes that match their defaults.
+#[derive(Debug, Clone, Copy, Default, PartialEq)]
+pub enum Comparison {
+    /// Attributes match only if they are exactly equal. NaN matches NaN, and -0.0 matches 0.0.
+    #[default]
+    Exact,
+
+    /// Attributes match if every float they contain is within `epsilon` of the other's. Non-float values must still
+    /// be exactly equal.
+    Approximate { epsilon: f32 },
+}
+
+impl Comparison {
+    pub fn matches(&self, a: &Attribute, b: &Attribute) -> bool {
+        match *self {
+            Comparison::Exact => a == b,
+            Comparison::Approximate { epsilon } => a.approx_eq(b, epsilon),
+        }
+    }
+}
+
+/// An [`Attribute`] was accessed as a type that it isn't.
+#[derive(Debug, Error, Clone, Copy, PartialEq, Eq)]
+#[error("expected a {expected} attribute, but found a {actual} attribute")]
+pub struct TypeMismatch {
+    pub expected: &'static str,
+    pub actual: &'static str,
+}
+
+impl Attribute {
+    /// The name of this attribute's type, as it appears in a [`TypeMismatch`].
+    pub fn type_name(&self) -> &'static str {
+        match self {
+            Attribute::Integer(_) => "int",
+            Attribute::Float(_) => "float",
+            Attribute::Bool(_) => "bool",
+            Attribute::String(_) => "string",
+            Attribute::Binary(_) => "binary",
+            Attribute::Color(_) => "color",
+            Attribute::Vector2(_) => "vector2",
+            Attribute::Vector3(_) => "vector3",
+            Attribute::Vector4(_) => "vector4",
+            Attribute::Matrix(_) => "matrix",
+            Attribute::IntegerArray(_) => "int_array",
+            Attribute::FloatArray(_) => "float_array",
+            Attribute::BoolArray(_) => "bool_array",
+            Attribute::StringArray(_) => "string_array",
+            Attribute::BinaryArray(_) => "binary_array",
+            Attribute::ColorArray(_) => "color_array",
+            Attribute::Vector2Array(_) => "vector2_array",
+            Attribute::Vector3Array(_) => "vector3_array",
+            Attribute::Vector4Array(_) => "vector4_array",
+            Attribute::MatrixArray(_) => "matrix_array",
+        }
+    }
+
+    fn mismatch(&self, expected: &'static str) -> TypeMismatch {
+        TypeMismatch {
+            expected,
+            actual: self.type_name(),
+        }
+    }
+
+    /// # Errors
+    ///
+    /// Returns [`TypeMismatch`] if this isn't an [`Attribute::Float`].
+    pub fn as_float(&self) -> Result<f32, TypeMismatch> {
+        match self {
+            Attribute::Float(value) => Ok(value.0),
+            _ => Err(self.mismatch("float")),
+        }
+    }
+
+    /// # Errors
+    ///
+    /// Returns [`TypeMismatch`] if this isn't an [`Attribute::Integer`].
+    pub fn as_int(&self) -> Result<i32, TypeMismatch> {
+        match self {
+            Attribute::Integer(value) => Ok(*value),
+            _ => Err(self.mismatch("int")),
+        }
+    }
+
+    /// # Errors
+    ///
+    /// Returns [`TypeMismatch`] if this isn't an [`Attribute::Vector3`].
+    pub fn as_vec3(&self) -> Result<Vector3, TypeMismatch> {
+        match self {
+            Attribute::Vector3(value) => Ok(*value),
+            _ => Err(self.mismatch("vector3")),
+        }
+    }
+
+    /// # Errors
+    ///
+    /// Returns [`TypeMismatch`] if this isn't an [`Attribute::String`].
+    pub fn as_string(&self) -> Result<&str, TypeMismatch> {
+        match self {
+            Attribute::String(value) => Ok(value),
+            _ => Err(self.mismatch("string")),
+        }
+    }
+
+    /// Replaces the value of an [`Attribute::Float`], leaving its type unchanged.
+    ///
+    /// # Errors
+    ///
+    /// Returns [`TypeMismatch`], without modifying the attribute, if this isn't an [`Attribute::Float`].
+    pub fn set_float(&mut self, value: f32) -> Result<(), TypeMismatch> {
+        match self {
+            Attribute::Float(current) => {
+                *current = value.into();
+                Ok(())
+            }
+            _ => Err(self.mismatch("float")),
+        }
+    }
+
+    /// Replaces the value of an [`Attribute::Integer`], leaving its type unchanged.
+    ///
+    /// # Errors
+    ///
+    /// Returns [`TypeMismatch`], without modifying the attribute, if this isn't an [`Attribute::Integer`].
+    pub fn set_int(&mut self, value: i32) -> Result<(), TypeMismatch> {
+        match self {
+            Attribute::Integer(current) => {
+                *current = value;
+                Ok(())
+            }
+            _ => Err(self.mismatch("int")),
+        }
+    }
+
+    /// Replaces the value of an [`Attribute::Vector3`], leaving its type unchanged.
+    ///
+    /// # Errors
+    ///
+    /// Returns [`TypeMismatch`], without modifying the attribute, if this isn't an [`Attribute::Vector3`].
+    pub fn set_vec3(&mut self, value: Vector3) -> Result<(), TypeMismatch> {
+        match self {
+            Attribute::Vector3(current) => {
+                *current = value;
+                Ok(())
+            }
+            _ => Err(self.mismatch("vector3")),
+        }
+    }
+
+    /// Replaces the value of an [`Attribute::String`], leaving its type unchanged.
+    ///
+    /// # Errors
+    ///
+    /// Returns [`TypeMismatch`], without modifying the attribute, if this isn't an [`Attribute::String`].
+    pub fn set_string(&mut self, value: impl Into<String>) -> Result<(), TypeMismatch> {
+        match self {
+            Attribute::String(current) => {
+                *current = value.into();
+                Ok(())
+            }
+            _ => Err(self.mismatch("string")),
+        }
+    }
+}
+
+impl Display for Attribute {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        // the alternate flag is passed on to each value, see [`dmx::attribute::Vector2`]
+        fn array<T: Display>(f: &mut fmt::Formatter<'_>, items: &[T]) -> fmt::Result {
+            f.write_str("[")?;
+            for (idx, item) in items.iter().enumerate() {
+                if idx > 0 {
+                    f.write_str(", ")?;
+                }
+
+                Display::fmt(item, f)?;
+            }
+            f.write_str("]")
+        }
+
+        match self {
+            Attribute::Integer(value) => write!(f, "{value}"),
+            Attribute::Float(value) => write!(f, "{value}"),
+            Attribute::Bool(value) => write!(f, "{value}"),
+            Attribute::String(value) => f.write_str(value),
+            Attribute::Binary(value) => Display::fmt(value, f),
+            Attribute::Color(value) => write!(f, "{value}"),
+            Attribute::Vector2(value) => Display::fmt(value, f),
+            Attribute::Vector3(value) => Display::fmt(value, f),
+            Attribute::Vector4(value) => Display::fmt(value, f),
+            Attribute::Matrix(value) => Display::fmt(value, f),
+            Attribute::IntegerArray(items) => array(f, items),
+            Attribute::FloatArray(items) => array(f, items),
+            Attribute::BoolArray(items) => array(f, items),
+            Attribute::StringArray(items) => array(f, items),
+            Attribute::BinaryArray(items) => array(f, items),
+            Attribute::ColorArray(items) => array(f, items),
+            Attribute::Vector2Array(items) => array(f, items),
+            Attribute::Vector3Array(items) => array(f, items),
+            Attribute::Vector4Array(items) => array(f, items),
+            Attribute::MatrixArray(items) => array(f, items),
         }
     }
 }
@@ -131,3 +393,117 @@ impl From<Attribute> for dmx::attribute::Attribute {
         }
     }
 }
+
+#[cfg(test)]
+mod accessor_tests {
+    use dmx::attribute::Vector3;
+
+    use super::{Attribute, TypeMismatch};
+
+    #[test]
+    fn accessors_return_values_of_matching_type() {
+        assert_eq!(Attribute::from(1.5).as_float(), Ok(1.5));
+        assert_eq!(Attribute::Integer(3).as_int(), Ok(3));
+        assert_eq!(Attribute::String("hello".into()).as_string(), Ok("hello"));
+
+        let vector = Vector3(1.0.into(), 2.0.into(), 3.0.into());
+        assert_eq!(Attribute::Vector3(vector).as_vec3(), Ok(vector));
+    }
+
+    #[test]
+    fn accessors_report_mismatched_types() {
+        assert_eq!(
+            Attribute::Integer(3).as_float(),
+            Err(TypeMismatch {
+                expected: "float",
+                actual: "int"
+            })
+        );
+
+        assert_eq!(
+            Attribute::from(1.5).as_string(),
+            Err(TypeMismatch {
+                expected: "string",
+                actual: "float"
+            })
+        );
+    }
+
+    #[test]
+    fn setters_preserve_the_variant() {
+        let mut attribute = Attribute::from(1.5);
+        attribute.set_float(2.5).unwrap();
+        assert_eq!(attribute, Attribute::from(2.5));
+
+        let mut attribute = Attribute::String("hello".into());
+        attribute.set_string("world").unwrap();
+        assert_eq!(attribute, Attribute::String("world".into()));
+    }
+
+    #[test]
+    fn setters_leave_mismatched_attributes_untouched() {
+        let mut attribute = Attribute::Integer(3);
+        assert_eq!(
+            attribute.set_float(2.5),
+            Err(TypeMismatch {
+                expected: "float",
+                actual: "int"
+            })
+        );
+        assert_eq!(attribute, Attribute::Integer(3));
+    }
+}
+
+#[cfg(test)]
+mod comparison_tests {
+    use std::{
+        collections::HashSet,
+        hash::{BuildHasher, RandomState},
+    };
+
+    use dmx::attribute::Vector3;
+
+    use super::{Attribute, Comparison};
+
+    #[test]
+    fn nan_and_signed_zero_compare_and_hash_equal() {
+        assert_eq!(Attribute::from(f32::NAN), Attribute::from(f32::NAN));
+        assert_eq!(Attribute::from(-0.0), Attribute::from(0.0));
+
+        let state = RandomState::new();
+        assert_eq!(
+            state.hash_one(Attribute::from(-0.0)),
+            state.hash_one(Attribute::from(0.0))
+        );
+
+        let set = HashSet::from([Attribute::from(f32::NAN), Attribute::from(f32::NAN)]);
+        assert_eq!(set.len(), 1);
+    }
+
+    #[test]
+    fn exact_comparison_rejects_nearby_floats() {
+        assert!(!Comparison::Exact.matches(&Attribute::from(1.0), &Attribute::from(1.000_01)));
+    }
+
+    #[test]
+    fn approximate_comparison_accepts_floats_within_epsilon() {
+        let comparison = Comparison::Approximate { epsilon: 0.001 };
+        assert!(comparison.matches(&Attribute::from(1.0), &Attribute::from(1.000_01)));
+        assert!(!comparison.matches(&Attribute::from(1.0), &Attribute::from(1.1)));
+        assert!(comparison.matches(&Attribute::from(f32::NAN), &Attribute::from(f32::NAN)));
+
+        let a = Attribute::Vector3(Vector3(1.0.into(), 2.0.into(), 3.0.into()));
+        let b = Attribute::Vector3(Vector3(1.0.into(), 2.000_1.into(), 3.0.into()));
+        assert!(comparison.matches(&a, &b));
+    }
+
+    #[test]
+    fn approximate_comparison_requires_matching_types() {
+        let comparison = Comparison::Approximate { epsilon: 1.0 };
+        assert!(!comparison.matches(&Attribute::from(1.0), &Attribute::Integer(1)));
+        assert!(!comparison.matches(
+            &Attribute::FloatArray(Box::new([1.0.into()])),
+            &Attribute::FloatArray(Box::new([1.0.into(), 1.0.into()]))
+        ));
+    }
+}
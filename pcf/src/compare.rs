@@ -0,0 +1,136 @@
+//! Compares particle systems against a reference set of PCFs - usually the vanilla particles - to tell which systems
+//! an addon actually changes, and which are straight copies.
+//!
+//! Systems are matched by name, since that's how the game finds the system an addon overrides; and compared by
+//! [`Pcf::system_hash`]. Element signatures are regenerated whenever a PCF is saved in the particle editor, so a copy
+//! with a different signature still counts as [`Change::Identical`].
+//!
+//! # Example
+//!
+//! Count the systems an addon overrides without changing.
+//! ```
+//! # use pcf::{Pcf, compare::{Change, Reference}};
+//! # fn example(vanilla: &[Pcf], addon: &Pcf) {
+//! let reference = Reference::new(vanilla);
+//! let unchanged = reference
+//!     .compare_all(addon)
+//!     .filter(|(_, change)| *change == Change::Identical)
+//!     .count();
+//! # }
+//! ```
+
+use std::collections::HashMap;
+
+use crate::new::{ParticleSystemIdx, Pcf};
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum Change {
+    /// The reference contains a system with the same name & content.
+    Identical,
+
+    /// The reference contains a system with the same name, but different content.
+    Modified,
+
+    /// The reference doesn't contain a system with the same name.
+    New,
+}
+
+/// The name & [`Pcf::system_hash`] of every particle system in a set of reference PCFs.
+#[derive(Debug, Clone, Default)]
+pub struct Reference {
+    systems: HashMap<String, u64>,
+}
+
+impl Reference {
+    /// Indexes every particle system in `pcfs`. If more than one system shares a name, the first one is used, just
+    /// like the game does.
+    pub fn new<'a>(pcfs: impl IntoIterator<Item = &'a Pcf>) -> Self {
+        let mut systems = HashMap::new();
+        for pcf in pcfs {
+            for (system_idx, system) in pcf.particle_systems().iter().enumerate() {
+                systems
+                    .entry(system.name.clone())
+                    .or_insert_with(|| pcf.system_hash(system_idx));
+            }
+        }
+
+        Self { systems }
+    }
+
+    /// Classifies the particle system at `system_idx` in `pcf`.
+    ///
+    /// # Panics
+    ///
+    /// Panics if `system_idx` is out of bounds.
+    pub fn compare(&self, pcf: &Pcf, system_idx: ParticleSystemIdx) -> Change {
+        let system = &pcf.particle_systems()[system_idx];
+        match self.systems.get(&system.name) {
+            None => Change::New,
+            Some(hash) if *hash == pcf.system_hash(system_idx) => Change::Identical,
+            Some(_) => Change::Modified,
+        }
+    }
+
+    /// Classifies every particle system in `pcf`, in order.
+    pub fn compare_all<'a>(&self, pcf: &'a Pcf) -> impl Iterator<Item = (&'a str, Change)> {
+        (0..pcf.particle_systems().len()).map(move |system_idx| {
+            (
+                pcf.particle_systems()[system_idx].name.as_str(),
+                self.compare(pcf, system_idx),
+            )
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use dmx::dmx::Version;
+    use ordermap::OrderMap;
+
+    use super::{Change, Reference};
+    use crate::{Attribute, Operator, ParticleSystem, Pcf, Root, Symbols, new::SymbolIdx};
+
+    fn test_pcf(systems: &[(&str, f32)], signature: u8) -> Pcf {
+        let mut symbols = Symbols::new_with_all_special();
+        let (radius, _) = symbols.base.insert_full("radius".into());
+
+        let particle_systems = systems
+            .iter()
+            .map(|(name, radius_value)| ParticleSystem {
+                name: name.to_string(),
+                signature: [signature; 16],
+                renderers: Box::from([Operator {
+                    name: "render".to_string(),
+                    function_name: "render_animated_sprites".to_string(),
+                    signature: [signature; 16],
+                    attributes: OrderMap::from([(radius as SymbolIdx, Attribute::from(*radius_value))]),
+                }]),
+                ..ParticleSystem::default()
+            })
+            .collect();
+
+        Pcf::new(
+            Version::Binary2Pcf1,
+            symbols,
+            Root::new("untitled".to_string(), [0; 16], particle_systems, OrderMap::new()),
+        )
+    }
+
+    #[test]
+    fn systems_are_classified_by_name_and_content() {
+        let vanilla = [test_pcf(&[("fire", 5.0), ("smoke", 5.0)], 1)];
+        let reference = Reference::new(&vanilla);
+
+        let addon = test_pcf(&[("fire", 5.0), ("smoke", 10.0), ("sparks", 5.0)], 2);
+        let changes: Vec<_> = reference.compare_all(&addon).collect();
+
+        assert_eq!(
+            changes,
+            vec![
+                ("fire", Change::Identical),
+                ("smoke", Change::Modified),
+                ("sparks", Change::New)
+            ]
+        );
+    }
+}
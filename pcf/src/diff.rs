@@ -0,0 +1,401 @@
+//! Lists the differences between two PCFs: the particle systems only one of them has, and for each system they share,
+//! the children, operators & attributes which differ.
+//!
+//! Like [`crate::compare`], systems are matched by name and element signatures are ignored, so a PCF re-saved in the
+//! particle editor has no differences from the original. Operators are matched by name within each
+//! [`OperatorList`], and children by the name of the system they reference.
+//!
+//! # Example
+//!
+//! Print every attribute an addon changes from vanilla.
+//! ```
+//! # use pcf::{Pcf, diff};
+//! # fn example(vanilla: &Pcf, addon: &Pcf) {
+//! for system in diff::diff(vanilla, addon).changed {
+//!     for change in system.attributes {
+//!         println!("{} > {}", system.name, change);
+//!     }
+//! }
+//! # }
+//! ```
+
+use std::{
+    collections::HashMap,
+    fmt::{self, Display},
+};
+
+use crate::{
+    Attribute,
+    new::{AttributeMap, Operator, OperatorList, ParticleSystem, ParticleSystemIdx, Pcf},
+};
+
+/// The differences from an old PCF to a new one, see [`diff`].
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct PcfDiff {
+    /// The systems only in the new PCF, in its order.
+    pub added: Vec<String>,
+
+    /// The systems only in the old PCF, in its order.
+    pub removed: Vec<String>,
+
+    /// The systems in both PCFs whose content differs, in the new PCF's order.
+    pub changed: Vec<SystemDiff>,
+}
+
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct SystemDiff {
+    pub name: String,
+
+    /// The names of the systems only the new system has as children.
+    pub children_added: Vec<String>,
+
+    /// The names of the systems only the old system has as children.
+    pub children_removed: Vec<String>,
+
+    pub operators_added: Vec<(OperatorList, String)>,
+    pub operators_removed: Vec<(OperatorList, String)>,
+
+    /// The lists whose shared operators run in a different order.
+    pub operators_reordered: Vec<OperatorList>,
+
+    /// Every attribute which was added, removed or changed in the system, its children & its shared operators.
+    pub attributes: Vec<AttributeChange>,
+}
+
+/// The element which holds a changed attribute.
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub enum Element {
+    System,
+
+    /// A child, by the name of the system it references.
+    Child(String),
+
+    /// An operator, by its name.
+    Operator(OperatorList, String),
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct AttributeChange {
+    pub element: Element,
+    pub name: String,
+
+    /// The attribute's old value, or `None` if it was added.
+    pub old: Option<Attribute>,
+
+    /// The attribute's new value, or `None` if it was removed.
+    pub new: Option<Attribute>,
+}
+
+impl PcfDiff {
+    pub fn is_empty(&self) -> bool {
+        self.added.is_empty() && self.removed.is_empty() && self.changed.is_empty()
+    }
+}
+
+impl Display for Element {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Element::System => f.write_str("system"),
+            Element::Child(name) => write!(f, "child '{name}'"),
+            Element::Operator(list, name) => write!(f, "{list} > '{name}'"),
+        }
+    }
+}
+
+impl Display for AttributeChange {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{} > {}: ", self.element, self.name)?;
+        match (&self.old, &self.new) {
+            (Some(old), Some(new)) => write!(f, "{old} -> {new}"),
+            (Some(old), None) => write!(f, "removed {old}"),
+            (None, Some(new)) => write!(f, "added {new}"),
+            (None, None) => f.write_str("unchanged"),
+        }
+    }
+}
+
+/// Lists the differences from `old` to `new`. If more than one system in a PCF shares a name, only the first is
+/// compared, just like the game only loads the first.
+pub fn diff(old: &Pcf, new: &Pcf) -> PcfDiff {
+    let old_systems = systems_by_name(old);
+    let new_systems = systems_by_name(new);
+
+    let mut diff = PcfDiff {
+        removed: old
+            .particle_systems()
+            .iter()
+            .filter(|system| !new_systems.contains_key(system.name.as_str()))
+            .map(|system| system.name.clone())
+            .collect(),
+        ..PcfDiff::default()
+    };
+
+    for (new_idx, system) in new.particle_systems().iter().enumerate() {
+        match old_systems.get(system.name.as_str()) {
+            None => diff.added.push(system.name.clone()),
+            Some(&old_idx) if old.system_hash(old_idx) != new.system_hash(new_idx) => {
+                diff.changed.push(diff_system(old, old_idx, new, new_idx));
+            }
+            Some(_) => {}
+        }
+    }
+
+    diff
+}
+
+/// The index of the first system with each name in `pcf`.
+fn systems_by_name(pcf: &Pcf) -> HashMap<&str, ParticleSystemIdx> {
+    let mut systems = HashMap::new();
+    for (system_idx, system) in pcf.particle_systems().iter().enumerate() {
+        systems.entry(system.name.as_str()).or_insert(system_idx);
+    }
+
+    systems
+}
+
+fn diff_system(old: &Pcf, old_idx: ParticleSystemIdx, new: &Pcf, new_idx: ParticleSystemIdx) -> SystemDiff {
+    let old_system = &old.particle_systems()[old_idx];
+    let new_system = &new.particle_systems()[new_idx];
+    let mut diff = SystemDiff {
+        name: new_system.name.clone(),
+        ..SystemDiff::default()
+    };
+
+    let old_children = child_names(old, old_system);
+    let new_children = child_names(new, new_system);
+    diff.children_added = difference(&new_children, &old_children);
+    diff.children_removed = difference(&old_children, &new_children);
+
+    for list in OperatorList::ALL {
+        let old_operators = operator_names(old_system.operator_list(list));
+        let new_operators = operator_names(new_system.operator_list(list));
+        diff.operators_added.extend(
+            difference(&new_operators, &old_operators)
+                .into_iter()
+                .map(|name| (list, name)),
+        );
+        diff.operators_removed.extend(
+            difference(&old_operators, &new_operators)
+                .into_iter()
+                .map(|name| (list, name)),
+        );
+
+        let old_shared: Vec<_> = old_operators
+            .iter()
+            .filter(|name| new_operators.contains(name))
+            .collect();
+        let new_shared: Vec<_> = new_operators
+            .iter()
+            .filter(|name| old_operators.contains(name))
+            .collect();
+        if old_shared != new_shared {
+            diff.operators_reordered.push(list);
+        }
+    }
+
+    // only the attributes of elements in both systems are compared, since the rest were added or removed whole
+    let new_elements: HashMap<_, _> = attributes_by_element(new, new_system).into_iter().collect();
+    for (element, old_attributes) in attributes_by_element(old, old_system) {
+        let Some(new_attributes) = new_elements.get(&element) else {
+            continue;
+        };
+
+        for (name, old_attribute) in &old_attributes {
+            let new_attribute = new_attributes
+                .iter()
+                .find(|(new_name, _)| new_name == name)
+                .map(|(_, value)| value);
+            if new_attribute != Some(old_attribute) {
+                diff.attributes.push(AttributeChange {
+                    element: element.clone(),
+                    name: name.to_string(),
+                    old: Some((*old_attribute).clone()),
+                    new: new_attribute.map(|attribute| (*attribute).clone()),
+                });
+            }
+        }
+
+        for (name, new_attribute) in new_attributes {
+            if !old_attributes.iter().any(|(old_name, _)| old_name == name) {
+                diff.attributes.push(AttributeChange {
+                    element: element.clone(),
+                    name: name.to_string(),
+                    old: None,
+                    new: Some((*new_attribute).clone()),
+                });
+            }
+        }
+    }
+
+    diff
+}
+
+fn child_names(pcf: &Pcf, system: &ParticleSystem) -> Vec<String> {
+    system
+        .children
+        .iter()
+        .map(|child| {
+            pcf.particle_systems()
+                .get(usize::from(child.child))
+                .map_or_else(|| child.name.clone(), |system| system.name.clone())
+        })
+        .collect()
+}
+
+/// The name of each operator, or its function name if it has none, with a `#n` suffix on the nth operator with the
+/// same name so that duplicates are matched in order.
+fn operator_names(operators: &[Operator]) -> Vec<String> {
+    let mut counts: HashMap<&str, usize> = HashMap::new();
+    operators
+        .iter()
+        .map(|operator| {
+            let name = operator_name(operator);
+            let count = counts.entry(name).or_default();
+            *count += 1;
+            if *count == 1 {
+                name.to_string()
+            } else {
+                format!("{name} #{count}")
+            }
+        })
+        .collect()
+}
+
+fn operator_name(operator: &Operator) -> &str {
+    if operator.name.is_empty() {
+        &operator.function_name
+    } else {
+        &operator.name
+    }
+}
+
+/// Every element of `system` - itself, its children & its operators - with its attributes by name.
+fn attributes_by_element<'a>(
+    pcf: &'a Pcf,
+    system: &'a ParticleSystem,
+) -> Vec<(Element, Vec<(&'a str, &'a Attribute)>)> {
+    let named = |attributes: &'a AttributeMap| -> Vec<(&'a str, &'a Attribute)> {
+        attributes
+            .iter()
+            .filter_map(|(name_idx, attribute)| {
+                let name = pcf.symbols().base.get_index(usize::from(*name_idx))?;
+                Some((name.as_str(), attribute))
+            })
+            .collect()
+    };
+
+    let mut elements = vec![(Element::System, named(&system.attributes))];
+    for (child, name) in system.children.iter().zip(child_names(pcf, system)) {
+        elements.push((Element::Child(name), named(&child.attributes)));
+    }
+
+    for list in OperatorList::ALL {
+        let operators = system.operator_list(list);
+        for (operator, name) in operators.iter().zip(operator_names(operators)) {
+            elements.push((Element::Operator(list, name), named(&operator.attributes)));
+        }
+    }
+
+    elements
+}
+
+/// The items of `a` which aren't in `b`, in `a`'s order.
+fn difference(a: &[String], b: &[String]) -> Vec<String> {
+    a.iter().filter(|item| !b.contains(item)).cloned().collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use dmx::dmx::Version;
+    use ordermap::OrderMap;
+
+    use super::{AttributeChange, Element, diff};
+    use crate::{Attribute, Operator, OperatorList, ParticleSystem, Pcf, Root, Symbols, new::SymbolIdx};
+
+    /// A PCF whose systems each have a single renderer, named after its function, with the given radius.
+    fn test_pcf(systems: &[(&str, &str, f32)], signature: u8) -> Pcf {
+        let mut symbols = Symbols::new_with_all_special();
+        let (radius, _) = symbols.base.insert_full("radius".into());
+
+        let particle_systems = systems
+            .iter()
+            .map(|(name, function_name, radius_value)| ParticleSystem {
+                name: name.to_string(),
+                signature: [signature; 16],
+                renderers: Box::from([Operator {
+                    name: function_name.to_string(),
+                    function_name: function_name.to_string(),
+                    signature: [signature; 16],
+                    attributes: OrderMap::from([(radius as SymbolIdx, Attribute::from(*radius_value))]),
+                }]),
+                ..ParticleSystem::default()
+            })
+            .collect();
+
+        Pcf::new(
+            Version::Binary2Pcf1,
+            symbols,
+            Root::new("untitled".to_string(), [0; 16], particle_systems, OrderMap::new()),
+        )
+    }
+
+    #[test]
+    fn resaved_pcfs_have_no_differences() {
+        let old = test_pcf(&[("fire", "render_animated_sprites", 5.0)], 1);
+        let new = test_pcf(&[("fire", "render_animated_sprites", 5.0)], 2);
+
+        assert!(diff(&old, &new).is_empty());
+    }
+
+    #[test]
+    fn lists_added_removed_and_changed_systems() {
+        let old = test_pcf(
+            &[
+                ("fire", "render_animated_sprites", 5.0),
+                ("smoke", "render_animated_sprites", 5.0),
+            ],
+            1,
+        );
+        let new = test_pcf(
+            &[
+                ("fire", "render_animated_sprites", 10.0),
+                ("sparks", "render_animated_sprites", 5.0),
+            ],
+            1,
+        );
+
+        let diff = diff(&old, &new);
+        assert_eq!(diff.added, ["sparks"]);
+        assert_eq!(diff.removed, ["smoke"]);
+        assert_eq!(diff.changed.len(), 1);
+        assert_eq!(
+            diff.changed[0].attributes,
+            [AttributeChange {
+                element: Element::Operator(OperatorList::Renderers, "render_animated_sprites".to_string()),
+                name: "radius".to_string(),
+                old: Some(Attribute::from(5.0_f32)),
+                new: Some(Attribute::from(10.0_f32)),
+            }]
+        );
+    }
+
+    #[test]
+    fn replaced_operators_are_added_and_removed() {
+        let old = test_pcf(&[("fire", "render_animated_sprites", 5.0)], 1);
+        let new = test_pcf(&[("fire", "render_sprite_trail", 5.0)], 1);
+
+        let diff = diff(&old, &new);
+        let system = &diff.changed[0];
+        assert_eq!(
+            system.operators_added,
+            [(OperatorList::Renderers, "render_sprite_trail".to_string())]
+        );
+        assert_eq!(
+            system.operators_removed,
+            [(OperatorList::Renderers, "render_animated_sprites".to_string())]
+        );
+
+        // the attributes of a replaced operator are part of the operator, rather than changes of their own
+        assert!(system.attributes.is_empty());
+    }
+}
@@ -0,0 +1,281 @@
+//! Export the child relationships between the particle systems in a [`Pcf`] as a graph, for visualization in tools
+//! like Graphviz or yEd.
+//!
+//! # Example
+//!
+//! Write a Graphviz graph with every operator, which can be rendered with `dot -Tsvg particles.dot`.
+//! ```
+//! # use pcf::{Pcf, export::ExportOptions};
+//! # fn example(pcf: &Pcf) -> std::io::Result<()> {
+//! let options = ExportOptions {
+//!     operators: true,
+//!     ..ExportOptions::default()
+//! };
+//!
+//! std::fs::write("particles.dot", pcf.to_dot(&options))?;
+//! # Ok(())
+//! # }
+//! ```
+
+use std::fmt::Write;
+
+use crate::new::{Operator, ParticleSystem, Pcf};
+
+#[derive(Debug, Clone, Copy, Default)]
+pub struct ExportOptions {
+    /// Include a node for every operator, connected to the particle system that owns it.
+    pub operators: bool,
+
+    /// Group the particle systems by the connected component they belong to, matching how [`Pcf::into_connected`]
+    /// would split them.
+    pub components: bool,
+}
+
+impl Pcf {
+    /// Formats the particle systems & their child references as a Graphviz DOT digraph. Each edge points from a
+    /// parent system to its child.
+    pub fn to_dot(&self, options: &ExportOptions) -> String {
+        let mut dot = String::from("digraph pcf {\n");
+
+        if options.components {
+            for (component_idx, component) in self.connected_components().into_iter().enumerate() {
+                writeln!(dot, "    subgraph cluster_{component_idx} {{").unwrap();
+                writeln!(dot, "        label=\"group {component_idx}\";").unwrap();
+                for system_idx in component {
+                    let system_idx = usize::from(system_idx);
+                    let system = &self.particle_systems()[system_idx];
+                    writeln!(
+                        dot,
+                        "        {} [label=\"{}\"];",
+                        system_id(system_idx),
+                        escape_dot(&system.name)
+                    )
+                    .unwrap();
+                }
+
+                writeln!(dot, "    }}").unwrap();
+            }
+        } else {
+            for (system_idx, system) in self.particle_systems().iter().enumerate() {
+                writeln!(
+                    dot,
+                    "    {} [label=\"{}\"];",
+                    system_id(system_idx),
+                    escape_dot(&system.name)
+                )
+                .unwrap();
+            }
+        }
+
+        for (system_idx, system) in self.particle_systems().iter().enumerate() {
+            for child in &system.children {
+                writeln!(
+                    dot,
+                    "    {} -> {};",
+                    system_id(system_idx),
+                    system_id(usize::from(child.child))
+                )
+                .unwrap();
+            }
+
+            if options.operators {
+                for (operator_idx, (category, operator)) in categorized_operators(system).enumerate() {
+                    let operator_id = operator_id(system_idx, operator_idx);
+                    writeln!(
+                        dot,
+                        "    {operator_id} [shape=box, label=\"{}\\n{category}\"];",
+                        escape_dot(&operator.function_name)
+                    )
+                    .unwrap();
+                    writeln!(dot, "    {} -> {operator_id} [style=dashed];", system_id(system_idx)).unwrap();
+                }
+            }
+        }
+
+        dot.push_str("}\n");
+        dot
+    }
+
+    /// Formats the particle systems & their child references as a GraphML document. Every node has a `name`, and a
+    /// `kind` of either `system` or one of the operator categories, e.g. `renderer`. If components are enabled, every
+    /// system node also has a `component`.
+    pub fn to_graphml(&self, options: &ExportOptions) -> String {
+        let mut xml = String::from(concat!(
+            "<?xml version=\"1.0\" encoding=\"UTF-8\"?>\n",
+            "<graphml xmlns=\"http://graphml.graphdrawing.org/xmlns\">\n",
+            "  <key id=\"name\" for=\"node\" attr.name=\"name\" attr.type=\"string\"/>\n",
+            "  <key id=\"kind\" for=\"node\" attr.name=\"kind\" attr.type=\"string\"/>\n",
+            "  <key id=\"component\" for=\"node\" attr.name=\"component\" attr.type=\"int\"/>\n",
+            "  <graph id=\"pcf\" edgedefault=\"directed\">\n",
+        ));
+
+        let mut components = vec![None; self.particle_systems().len()];
+        if options.components {
+            for (component_idx, component) in self.connected_components().into_iter().enumerate() {
+                for system_idx in component {
+                    components[usize::from(system_idx)] = Some(component_idx);
+                }
+            }
+        }
+
+        let mut edges = Vec::new();
+        for (system_idx, system) in self.particle_systems().iter().enumerate() {
+            let id = system_id(system_idx);
+            writeln!(xml, "    <node id=\"{id}\">").unwrap();
+            writeln!(xml, "      <data key=\"name\">{}</data>", escape_xml(&system.name)).unwrap();
+            writeln!(xml, "      <data key=\"kind\">system</data>").unwrap();
+            if let Some(component_idx) = components[system_idx] {
+                writeln!(xml, "      <data key=\"component\">{component_idx}</data>").unwrap();
+            }
+            writeln!(xml, "    </node>").unwrap();
+
+            for child in &system.children {
+                edges.push((id.clone(), system_id(usize::from(child.child))));
+            }
+
+            if options.operators {
+                for (operator_idx, (category, operator)) in categorized_operators(system).enumerate() {
+                    let operator_id = operator_id(system_idx, operator_idx);
+                    writeln!(xml, "    <node id=\"{operator_id}\">").unwrap();
+                    writeln!(
+                        xml,
+                        "      <data key=\"name\">{}</data>",
+                        escape_xml(&operator.function_name)
+                    )
+                    .unwrap();
+                    writeln!(xml, "      <data key=\"kind\">{category}</data>").unwrap();
+                    writeln!(xml, "    </node>").unwrap();
+
+                    edges.push((id.clone(), operator_id));
+                }
+            }
+        }
+
+        for (source, target) in edges {
+            writeln!(xml, "    <edge source=\"{source}\" target=\"{target}\"/>").unwrap();
+        }
+
+        xml.push_str("  </graph>\n</graphml>\n");
+        xml
+    }
+}
+
+fn system_id(system_idx: usize) -> String {
+    format!("system{system_idx}")
+}
+
+fn operator_id(system_idx: usize, operator_idx: usize) -> String {
+    format!("system{system_idx}_operator{operator_idx}")
+}
+
+/// Every operator in `system`, with the singular name of the category it belongs to.
+fn categorized_operators(system: &ParticleSystem) -> impl Iterator<Item = (&'static str, &Operator)> {
+    [
+        ("constraint", &system.constraints),
+        ("emitter", &system.emitters),
+        ("force", &system.forces),
+        ("initializer", &system.initializers),
+        ("operator", &system.operators),
+        ("renderer", &system.renderers),
+    ]
+    .into_iter()
+    .flat_map(|(category, operators)| operators.iter().map(move |operator| (category, operator)))
+}
+
+fn escape_dot(value: &str) -> String {
+    value.replace('\\', "\\\\").replace('"', "\\\"")
+}
+
+fn escape_xml(value: &str) -> String {
+    value
+        .replace('&', "&amp;")
+        .replace('<', "&lt;")
+        .replace('>', "&gt;")
+        .replace('"', "&quot;")
+        .replace('\'', "&apos;")
+}
+
+#[cfg(test)]
+mod tests {
+    use dmx::dmx::Version;
+    use ordermap::OrderMap;
+
+    use super::ExportOptions;
+    use crate::{Child, Operator, ParticleSystem, Pcf, Root, Symbols, new::element_idx};
+
+    fn test_pcf() -> Pcf {
+        let system = |name: &str, children: &[usize]| ParticleSystem {
+            name: name.to_string(),
+            children: children
+                .iter()
+                .map(|child| Child {
+                    name: String::new(),
+                    signature: [0; 16],
+                    child: element_idx(*child),
+                    attributes: OrderMap::new(),
+                })
+                .collect(),
+            renderers: Box::from([Operator {
+                name: "render".to_string(),
+                function_name: "render_animated_sprites".to_string(),
+                signature: [0; 16],
+                attributes: OrderMap::new(),
+            }]),
+            ..ParticleSystem::default()
+        };
+
+        Pcf::new(
+            Version::Binary2Pcf1,
+            Symbols::new_with_all_special(),
+            Root::new(
+                "untitled".to_string(),
+                [0; 16],
+                Box::from([
+                    system("parent", &[1]),
+                    system("child \"quoted\"", &[]),
+                    system("loner", &[]),
+                ]),
+                OrderMap::new(),
+            ),
+        )
+    }
+
+    #[test]
+    fn dot_contains_systems_and_child_edges() {
+        let dot = test_pcf().to_dot(&ExportOptions::default());
+
+        assert!(dot.starts_with("digraph pcf {\n"));
+        assert!(dot.contains("system0 [label=\"parent\"];"));
+        assert!(dot.contains("system1 [label=\"child \\\"quoted\\\"\"];"));
+        assert!(dot.contains("system0 -> system1;"));
+        assert!(!dot.contains("render_animated_sprites"));
+        assert!(!dot.contains("cluster"));
+    }
+
+    #[test]
+    fn dot_clusters_components_and_includes_operators() {
+        let options = ExportOptions {
+            operators: true,
+            components: true,
+        };
+        let dot = test_pcf().to_dot(&options);
+
+        assert_eq!(dot.matches("subgraph cluster_").count(), 2);
+        assert!(dot.contains("system2_operator0 [shape=box, label=\"render_animated_sprites\\nrenderer\"];"));
+        assert!(dot.contains("system2 -> system2_operator0 [style=dashed];"));
+    }
+
+    #[test]
+    fn graphml_escapes_names_and_records_components() {
+        let options = ExportOptions {
+            operators: false,
+            components: true,
+        };
+        let graphml = test_pcf().to_graphml(&options);
+
+        assert!(graphml.contains("<data key=\"name\">child &quot;quoted&quot;</data>"));
+        assert!(graphml.contains("<edge source=\"system0\" target=\"system1\"/>"));
+        assert_eq!(graphml.matches("<data key=\"component\">").count(), 3);
+        assert_eq!(graphml.matches("<node ").count(), 3);
+    }
+}
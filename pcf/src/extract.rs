@@ -0,0 +1,60 @@
+//! Find which of a set of PCFs defines a particle system, so that the system can be [extracted](Pcf::extracted) into a
+//! standalone PCF.
+//!
+//! # Example
+//!
+//! Extract `flamethrower_fire` from whichever PCF defines it.
+//! ```
+//! # use pcf::{Pcf, extract::SystemMap};
+//! # fn example(pcfs: Vec<(String, Pcf)>) -> Option<Pcf> {
+//! let map = SystemMap::new(pcfs.iter().map(|(name, pcf)| (name.as_str(), pcf)));
+//! let pcf_name = map.find("flamethrower_fire")?;
+//! let (_, pcf) = pcfs.into_iter().find(|(name, _)| name == pcf_name)?;
+//! pcf.extracted("flamethrower_fire").ok().flatten()
+//! # }
+//! ```
+
+use std::collections::HashMap;
+
+use crate::new::Pcf;
+
+/// Maps the name of each particle system to the name of the PCF which defines it.
+#[derive(Debug, Clone, Default)]
+pub struct SystemMap {
+    pcfs: HashMap<String, String>,
+}
+
+impl SystemMap {
+    /// Maps every system in `pcfs`, which are `(name, pcf)` pairs. See [`SystemMap::insert`].
+    pub fn new<'a>(pcfs: impl IntoIterator<Item = (&'a str, &'a Pcf)>) -> Self {
+        let mut map = Self::default();
+        for (name, pcf) in pcfs {
+            map.insert(name, pcf);
+        }
+
+        map
+    }
+
+    /// Maps every system in `pcf` to `name`. Systems which are already mapped to another PCF keep their mapping, so
+    /// the first PCF to define a system wins.
+    pub fn insert(&mut self, name: &str, pcf: &Pcf) {
+        for system in pcf.particle_systems() {
+            if !self.pcfs.contains_key(&system.name) {
+                self.pcfs.insert(system.name.clone(), name.to_string());
+            }
+        }
+    }
+
+    /// The name of the PCF which defines `system`, if any of them do.
+    pub fn find(&self, system: &str) -> Option<&str> {
+        self.pcfs.get(system).map(String::as_str)
+    }
+
+    pub fn len(&self) -> usize {
+        self.pcfs.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.pcfs.is_empty()
+    }
+}
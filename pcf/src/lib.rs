@@ -6,13 +6,10 @@
 //!
 //! Parse a type-safe [`Pcf`].
 //! ```
-//! #![feature(file_buffered)]
 //! # use bytes::Buf;
 //! #
-//! # const EXAMPLE_PCF: &[u8] = include_bytes!("../../dazzle/src/static/default_values.pcf");
-//! #
 //! # fn main() -> anyhow::Result<()> {
-//!     # let mut reader = EXAMPLE_PCF.reader();
+//!     # let mut reader = pcf::DEFAULT_VALUES_PCF.reader();
 //!     let pcf = pcf::decode(&mut reader)?;
 //!     println!("particles.pcf has {} particle systems.", pcf.root().particle_systems().len());
 //!     // read/modify PCF data...
@@ -22,31 +19,43 @@
 //!
 //! See [`decode`] to decode a buffer into a [`Pcf`] directly.
 //!
-//! See [`dmx::Dmx::encode`] to encode a [`dmx::Dmx`] into a buffer. You can convert a [`Pcf`] into [`dmx::Dmx`] freely
-//! with [`Pcf::into`].
-
-#![feature(buf_read_has_data_left)]
-#![feature(read_array)]
-#![feature(trim_prefix_suffix)]
-#![feature(associated_type_defaults)]
-#![feature(error_generic_member_access)]
-#![feature(cstr_display)]
-#![feature(ascii_char)]
-#![feature(string_into_chars)]
+//! See [`encode`] to encode a [`Pcf`] into a buffer, or [`dmx::Dmx::encode`] to encode a [`dmx::Dmx`]. You can convert
+//! a [`Pcf`] into [`dmx::Dmx`] freely with [`Pcf::into`].
 
 pub mod attribute;
+pub mod compare;
+pub mod diff;
+pub mod export;
+pub mod extract;
 pub mod index;
 pub mod new;
+pub mod passthrough;
+pub mod patch;
+pub mod query;
+#[cfg(feature = "schema")]
+pub mod schema;
+pub mod stats;
 mod strings;
+pub mod symbol;
+#[cfg(test)]
+mod test_util;
 
-pub use attribute::Attribute;
-pub use new::{AttributeMap, Child, Operator, ParticleSystem, Pcf, Root, Symbols};
+pub use attribute::{Attribute, Comparison, TypeMismatch};
+pub use new::{
+    AttributeMap, AttributeOwner, AttributePath, Child, DecodeWarning, EditError, MergeOptions, MergePolicy,
+    MergeReport, Operator, OperatorList, ParticleSystem, Pcf, Root, Symbols,
+};
+pub use symbol::{Symbol, SymbolPool};
 use thiserror::Error;
 
+/// A PCF with one particle system per known operator, with every attribute set to its default value. The `schema`
+/// feature generates `pcf::schema` from it.
+pub const DEFAULT_VALUES_PCF: &[u8] = include_bytes!("../default_values.pcf");
+
 #[derive(Debug, Error)]
 pub enum DecodeError {
     #[error(transparent)]
-    Dmx(#[from] dmx::dmx::Error),
+    Dmx(#[from] dmx::dmx::DecodeError),
 
     #[error(transparent)]
     Pcf(#[from] new::Error),
@@ -56,3 +65,9 @@ pub fn decode(buf: &mut impl std::io::BufRead) -> Result<Pcf, DecodeError> {
     let dmx = dmx::decode(buf)?;
     Ok(Pcf::try_from(dmx)?)
 }
+
+/// Encodes `pcf` into `buf`. In debug builds, this checks that exactly [`Pcf::encoded_size`] bytes were written.
+pub fn encode(pcf: Pcf, buf: &mut impl std::io::Write) -> Result<(), dmx::dmx::EncodeError> {
+    let size = pcf.encoded_size();
+    dmx::Dmx::from(pcf).encode_sized(buf, size)
+}
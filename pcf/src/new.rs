@@ -1,27 +1,48 @@
 use std::{
-    collections::{HashMap, HashSet},
+    cmp::{Ordering, Reverse},
+    collections::{BTreeMap, BinaryHeap, HashMap, HashSet},
     ffi::{CStr, CString},
+    hash::{DefaultHasher, Hash, Hasher},
     mem,
 };
 
 use dmx::{
-    ElementIdx, Signature,
+    ElementIdx, ElementIdxError, FixedEncodedSize, Signature,
     dmx::{Dmx, Element, Version},
+    reference::SignatureReferences,
 };
 use itertools::Itertools;
 use ordermap::{OrderMap, OrderSet};
 use petgraph::{algo::tarjan_scc, prelude::UnGraphMap};
 use thiserror::Error;
+use uuid::Builder;
 
 use crate::{
-    attribute::Attribute,
+    attribute::{Attribute, Comparison, TypeMismatch},
+    passthrough::{Passthrough, PassthroughMap},
+    stats::{StripCounts, StripStats},
     strings::{str_to_cstring, string_to_cstring},
+    symbol::{Symbol, SymbolPool},
 };
 
 pub type SymbolIdx = u16;
 pub type ParticleSystemIdx = usize;
 pub type AttributeMap = OrderMap<SymbolIdx, Attribute>;
 
+/// The most symbols a PCF can have, since binary 2 & 3 encode the symbol count in 16 bits.
+pub const MAX_SYMBOLS: usize = u16::MAX as usize;
+
+/// The [`ElementIdx`] of the system or element at `idx`. Every element of a PCF is held in memory, and each takes far
+/// more than a byte, so a PCF can't have the `u32::MAX` elements needed for this to fail.
+pub(crate) fn element_idx(idx: usize) -> ElementIdx {
+    in_range(ElementIdx::try_from(idx))
+}
+
+/// The index moved by [`ElementIdx::checked_offset`] or [`ElementIdx::rebased`] within a PCF, see [`element_idx`].
+pub(crate) fn in_range(idx: Result<ElementIdx, ElementIdxError>) -> ElementIdx {
+    idx.expect("a PCF should have fewer than u32::MAX elements")
+}
+
 #[derive(Debug, Clone, Default, PartialEq)]
 pub struct Pcf {
     version: Version,
@@ -72,6 +93,42 @@ impl Root {
     pub fn attributes(&self) -> &AttributeMap {
         &self.attributes
     }
+
+    /// Reorders the particle systems, moving the system at `ordering[idx]` to `idx`. References to systems are
+    /// replaced with the new index for each system.
+    ///
+    /// # Errors
+    ///
+    /// Returns [`OrderError::NotAPermutation`], without reordering anything, unless `ordering` lists the index of every
+    /// system exactly once.
+    pub fn reorder_systems(&mut self, ordering: &[ParticleSystemIdx]) -> Result<(), OrderError> {
+        let system_count = self.particle_systems.len();
+        let mut old_to_new_idx = vec![ElementIdx::INVALID; system_count];
+        for (new_idx, old_idx) in ordering.iter().enumerate() {
+            match old_to_new_idx.get_mut(*old_idx) {
+                Some(idx) if !idx.is_valid() => *idx = element_idx(new_idx),
+                _ => return Err(OrderError::NotAPermutation(ordering.to_vec(), system_count)),
+            }
+        }
+
+        if ordering.len() != system_count {
+            return Err(OrderError::NotAPermutation(ordering.to_vec(), system_count));
+        }
+
+        let mut particle_systems: Vec<_> = ordering
+            .iter()
+            .map(|idx| mem::take(&mut self.particle_systems[*idx]))
+            .collect();
+
+        for system in &mut particle_systems {
+            for child in &mut system.children {
+                child.child = old_to_new_idx[usize::from(child.child)];
+            }
+        }
+
+        self.particle_systems = particle_systems.into_boxed_slice();
+        Ok(())
+    }
 }
 
 #[derive(Debug, Error)]
@@ -105,6 +162,9 @@ pub enum Error {
     #[error("The element contains an unexpected Element or ElementArray attribute")]
     UnexpectedElementReference,
 
+    #[error("A particle system's unknown element attribute references an element that doesn't exist")]
+    MissingPassthroughElement(ElementIdx),
+
     #[error("A particle system contains a reference to an operator that is not a valid DmeParticleOperator")]
     InvalidParticleOperator(ElementIdx),
 
@@ -121,10 +181,173 @@ pub enum Error {
     MissingSystemDefinitionString,
 }
 
+/// A part of a PCF which [`Pcf::try_from_dmx_lenient`] skipped, because it was invalid.
+#[derive(Debug)]
+pub struct DecodeWarning {
+    /// the name of the particle system the skipped part was in, or [`None`] if it was a particle system or part of
+    /// the root
+    pub system: Option<String>,
+
+    pub error: Error,
+}
+
+impl std::fmt::Display for DecodeWarning {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match &self.system {
+            Some(system) => write!(f, "skipped part of the particle system '{system}': {}", self.error),
+            None => write!(f, "skipped part of the PCF: {}", self.error),
+        }
+    }
+}
+
 #[derive(Debug, Error)]
 pub enum MergeError {
     #[error("can't merge DMX with version {0} into DMX with version {1}")]
     VersionMismatch(Version, Version),
+
+    #[error("both PCFs contain a particle system named '{0}'")]
+    DuplicateParticleSystem(String),
+
+    #[error("the merged PCF would have {0} symbols, but at most {MAX_SYMBOLS} can be encoded")]
+    TooManySymbols(usize),
+
+    #[error("particle system '{system}' has a child referencing system {child}, which isn't in the PCF")]
+    ChildOutOfRange { system: String, child: ElementIdx },
+
+    #[error("element '{element}' uses symbol {symbol}, which isn't in its PCF's symbols")]
+    UnknownSymbol { element: String, symbol: SymbolIdx },
+}
+
+#[derive(Debug, Error)]
+pub enum OrderError {
+    #[error("the particle systems {0:?} reference each other as children in a cycle")]
+    Cycle(Vec<String>),
+
+    #[error("{0:?} isn't an ordering of {1} particle systems, since it doesn't list every system exactly once")]
+    NotAPermutation(Vec<ParticleSystemIdx>, usize),
+
+    #[error("particle system '{system}' has a child referencing system {child}, which isn't in the PCF")]
+    ChildOutOfRange { system: String, child: ElementIdx },
+}
+
+#[derive(Debug, Error)]
+pub enum SignatureError {
+    #[error("{} signatures are shared by more than one element", .0.len())]
+    Duplicates(Vec<Signature>),
+}
+
+/// The location of an attribute in a [`Pcf`], by the names of the particle system & operator which own it. Names are
+/// used rather than indices, so that edits can be stored and re-applied after the PCF is decoded again.
+#[derive(Debug, Clone, PartialEq, Eq, Hash, PartialOrd, Ord)]
+pub struct AttributePath {
+    pub system: String,
+
+    /// The name of the operator which owns the attribute, or `None` if it's one of the particle system's own
+    /// attributes. Operators are matched by their name, or by their function name if none of them has that name.
+    pub operator: Option<String>,
+
+    pub attribute: String,
+}
+
+#[derive(Debug, Error)]
+pub enum EditError {
+    #[error("there is no particle system named '{0}'")]
+    UnknownSystem(String),
+
+    #[error("particle system '{system}' has no operator named '{operator}'")]
+    UnknownOperator { system: String, operator: String },
+
+    #[error("particle system '{system}' has more than one operator named '{operator}'")]
+    AmbiguousOperator { system: String, operator: String },
+
+    #[error("couldn't set '{attribute}'")]
+    TypeMismatch {
+        attribute: String,
+        #[source]
+        source: TypeMismatch,
+    },
+
+    #[error("particle system '{system}' has {len} {list}, so there's nothing at position {index}")]
+    OperatorOutOfRange {
+        system: String,
+        list: OperatorList,
+        index: usize,
+        len: usize,
+    },
+
+    #[error("there is already a particle system named '{0}'")]
+    DuplicateSystem(String),
+
+    #[error("particle system '{system}' has a child referencing system {child}, which isn't in the PCF")]
+    ChildOutOfRange { system: String, child: ElementIdx },
+
+    #[error("particle system '{system}' uses symbol {symbol}, which isn't in its symbols")]
+    UnknownSymbol { system: String, symbol: SymbolIdx },
+}
+
+/// Decides what happens when an incoming particle system has the same name as a particle system that's already in the
+/// PCF being merged into.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum MergePolicy {
+    /// Drop the incoming system. Incoming children which referenced it will reference the existing system instead.
+    KeepSelf,
+
+    /// Replace the existing system with the incoming one, in place.
+    KeepOther,
+
+    /// Rename the incoming system by appending `_1`, `_2`, etc. until its name is unique.
+    RenameIncoming,
+
+    /// Fail the merge with [`MergeError::DuplicateParticleSystem`].
+    Error,
+}
+
+/// The duplicately named particle systems that were resolved during a merge, by name.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct MergeReport {
+    /// Incoming systems that were dropped in favour of the existing system. See [`MergePolicy::KeepSelf`].
+    pub kept_self: Vec<String>,
+
+    /// Existing systems that were replaced by the incoming system. See [`MergePolicy::KeepOther`].
+    pub replaced: Vec<String>,
+
+    /// Incoming systems that were renamed, as `(old_name, new_name)`. See [`MergePolicy::RenameIncoming`].
+    pub renamed: Vec<(String, String)>,
+}
+
+impl MergeReport {
+    pub fn is_empty(&self) -> bool {
+        self.kept_self.is_empty() && self.replaced.is_empty() && self.renamed.is_empty()
+    }
+}
+
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct MergeOptions {
+    /// How incoming particle systems which share a name with an existing system are resolved. If `None`, every
+    /// incoming system is appended regardless.
+    pub policy: Option<MergePolicy>,
+
+    /// Keep the signatures of incoming elements as-is, instead of generating a new signature for each of them.
+    ///
+    /// Merging the same PCF twice while preserving signatures will produce duplicate signatures, which confuses the
+    /// particle editor. See [`Pcf::validate_signatures`].
+    pub preserve_signatures: bool,
+}
+
+/// Where an incoming particle system ends up in the merged PCF. Each variant holds the system's merged index.
+#[derive(Debug, Clone, Copy)]
+enum SystemPlacement {
+    Append(ParticleSystemIdx),
+    Replace(ParticleSystemIdx),
+    Drop(ParticleSystemIdx),
+}
+
+impl SystemPlacement {
+    fn idx(self) -> ParticleSystemIdx {
+        match self {
+            SystemPlacement::Append(idx) | SystemPlacement::Replace(idx) | SystemPlacement::Drop(idx) => idx,
+        }
+    }
 }
 
 impl Pcf {
@@ -147,6 +370,12 @@ impl Pcf {
         &self.symbols
     }
 
+    /// Replaces every symbol with `pool`'s copy of it, so that this [`Pcf`] shares its strings with every other [`Pcf`]
+    /// interned into the same pool. Symbol indices are unchanged. See [`crate::symbol`].
+    pub fn intern_symbols(&mut self, pool: &SymbolPool) {
+        self.symbols.base = self.symbols.base.iter().map(|symbol| pool.intern(symbol)).collect();
+    }
+
     pub fn root(&self) -> &Root {
         &self.root
     }
@@ -156,20 +385,41 @@ impl Pcf {
         Ok(())
     }
 
+    /// Merges the particle systems in `from` into `self`, appending every incoming system even if `self` already
+    /// contains a system with the same name.
+    ///
+    /// See [`Pcf::merged_with_policy`] to control what happens to duplicately named particle systems.
     pub fn merged(self, from: Self) -> Result<Self, MergeError> {
-        fn reindex_new_attributes(
-            old_to_new_string_idx: &HashMap<u16, u16>,
-            attributes: AttributeMap,
-        ) -> impl Iterator<Item = (u16, Attribute)> {
-            attributes.into_iter().map(|(name_idx, attribute)| {
-                let name_idx = old_to_new_string_idx
-                    .get(&name_idx)
-                    .copied()
-                    .expect("the attribute's name_idx should always match a value in the Pcf's string list");
-
-                (name_idx, attribute)
-            })
-        }
+        self.merged_with_options(from, MergeOptions::default())
+            .map(|(pcf, _)| pcf)
+    }
+
+    /// Merges the particle systems in `from` into `self`, resolving particle systems which share a name according to
+    /// `policy`. The report describes every duplicate that was resolved.
+    pub fn merged_with_policy(self, from: Self, policy: MergePolicy) -> Result<(Self, MergeReport), MergeError> {
+        self.merged_with_options(
+            from,
+            MergeOptions {
+                policy: Some(policy),
+                ..MergeOptions::default()
+            },
+        )
+    }
+
+    /// See [`Pcf::merged_with_policy`].
+    pub fn merged_in_with_policy(&mut self, from: &mut Self, policy: MergePolicy) -> Result<MergeReport, MergeError> {
+        let (merged, report) = mem::take(self).merged_with_policy(mem::take(from), policy)?;
+        *self = merged;
+        Ok(report)
+    }
+
+    /// Merges the particle systems in `from` into `self` according to `options`. Unless
+    /// [`MergeOptions::preserve_signatures`] is set, every incoming element is given a new signature.
+    pub fn merged_with_options(self, from: Self, options: MergeOptions) -> Result<(Self, MergeReport), MergeError> {
+        let MergeOptions {
+            policy,
+            preserve_signatures,
+        } = options;
 
         if self.version != from.version {
             return Err(MergeError::VersionMismatch(from.version, self.version));
@@ -181,18 +431,52 @@ impl Pcf {
         // two PCF files. Its possible to have new strings, or strings that have changed position. So, we create a
         // map here to convert from incoming string index to merged string index.
         //
-        // We also add any new strings from `other` into `self.strings` here.
-        let mut old_to_new_string_idx = HashMap::new();
+        // We also add any new strings from `other` into `self.strings` here. The strings are moved rather than cloned,
+        // and the map is indexed by the incoming string index.
+        let mut old_to_new_string_idx = Vec::with_capacity(from.symbols.base.len());
         for (from_idx, string) in from.symbols.base.into_iter().enumerate() {
-            let (mapped_idx, _) = symbols.base.insert_full(string);
-            old_to_new_string_idx.insert(from_idx as SymbolIdx, mapped_idx as SymbolIdx);
+            // PCFs interned into the same pool share each symbol's string, so a symbol at the same index in both is
+            // matched without hashing it
+            let mapped_idx = match symbols.base.get_index(from_idx) {
+                Some(existing) if existing.ptr_eq(&string) => from_idx,
+                _ => symbols.base.insert_full(string).0,
+            };
+            old_to_new_string_idx.push(mapped_idx as SymbolIdx);
+        }
+
+        // PCFs split from the same file, like the vanilla graphs, often share a symbol table; so their symbols keep
+        // their indices, and none of their attributes need to be reindexed.
+        let is_identity = old_to_new_string_idx
+            .iter()
+            .enumerate()
+            .all(|(from_idx, mapped_idx)| from_idx == usize::from(*mapped_idx));
+        let reindex = |idx: SymbolIdx| old_to_new_string_idx[usize::from(idx)];
+
+        // every incoming symbol is checked up front, so reindexing can't fail part-way through the merge
+        let is_unknown = |idx: SymbolIdx| usize::from(idx) >= old_to_new_string_idx.len();
+        if let Some(&symbol) = from.root.attributes.keys().find(|idx| is_unknown(**idx)) {
+            return Err(MergeError::UnknownSymbol {
+                element: from.root.name,
+                symbol,
+            });
+        }
+
+        for system in &from.root.particle_systems {
+            if let Some(symbol) = system.referenced_symbols().into_iter().find(|idx| is_unknown(*idx)) {
+                return Err(MergeError::UnknownSymbol {
+                    element: system.name.clone(),
+                    symbol,
+                });
+            }
+        }
+
+        // any index past the limit was truncated above, so the merge can't continue
+        if symbols.base.len() > MAX_SYMBOLS {
+            return Err(MergeError::TooManySymbols(symbols.base.len()));
         }
 
         fn find_idx(from: &Symbols, value: &str) -> Option<SymbolIdx> {
-            from.base
-                .iter()
-                .find_position(|el| *el == value)
-                .map(|(idx, _)| idx as SymbolIdx)
+            from.base.get_index_of(value).map(|idx| idx as SymbolIdx)
         }
 
         symbols.particle_child = find_idx(&symbols, "DmeParticleChild");
@@ -209,11 +493,7 @@ impl Pcf {
 
         let mut root_attributes = self.root.attributes;
         for (name_idx, attribute) in from.root.attributes {
-            let name_idx = old_to_new_string_idx
-                .get(&name_idx)
-                .copied()
-                .expect("the attribute's name_idx should always match a value in the Pcf's string list");
-
+            let name_idx = reindex(name_idx);
             if root_attributes.contains_key(&name_idx) {
                 continue;
             }
@@ -225,48 +505,91 @@ impl Pcf {
         // );
 
         let mut particle_systems = Vec::from(self.root.particle_systems);
-        let system_offset = particle_systems.len();
-
-        for mut new_system in from.root.particle_systems {
-            for child in &mut new_system.children {
-                child.child += system_offset;
-                child.attributes =
-                    reindex_new_attributes(&old_to_new_string_idx, mem::take(&mut child.attributes)).collect();
+        let mut report = MergeReport::default();
+
+        // we need to know where every incoming system will end up before we can reindex any of the incoming children,
+        // since children may reference systems that come after them.
+        let mut system_indices: HashMap<String, ParticleSystemIdx> = HashMap::new();
+        if policy.is_some() {
+            for (idx, system) in particle_systems.iter().enumerate() {
+                system_indices.entry(system.name.clone()).or_insert(idx);
             }
+        }
 
-            for operator in &mut new_system.constraints {
-                operator.attributes =
-                    reindex_new_attributes(&old_to_new_string_idx, mem::take(&mut operator.attributes)).collect();
-            }
+        let mut incoming_systems = Vec::from(from.root.particle_systems);
+        let mut placements = Vec::with_capacity(incoming_systems.len());
+        let mut next_idx = particle_systems.len();
+        for new_system in &mut incoming_systems {
+            let existing_idx = system_indices.get(&new_system.name).copied();
+            let placement = match (policy, existing_idx) {
+                (None, _) | (Some(_), None) => {
+                    let placement = SystemPlacement::Append(next_idx);
+                    next_idx += 1;
+                    placement
+                }
+                (Some(MergePolicy::KeepSelf), Some(existing_idx)) => {
+                    report.kept_self.push(new_system.name.clone());
+                    SystemPlacement::Drop(existing_idx)
+                }
+                (Some(MergePolicy::KeepOther), Some(existing_idx)) => {
+                    report.replaced.push(new_system.name.clone());
+                    SystemPlacement::Replace(existing_idx)
+                }
+                (Some(MergePolicy::RenameIncoming), Some(_)) => {
+                    let name = (1..)
+                        .map(|n| format!("{}_{n}", new_system.name))
+                        .find(|name| !system_indices.contains_key(name))
+                        .expect("there is always an unused suffix");
+
+                    report
+                        .renamed
+                        .push((mem::replace(&mut new_system.name, name), new_system.name.clone()));
+
+                    let placement = SystemPlacement::Append(next_idx);
+                    next_idx += 1;
+                    placement
+                }
+                (Some(MergePolicy::Error), Some(_)) => {
+                    return Err(MergeError::DuplicateParticleSystem(new_system.name.clone()));
+                }
+            };
 
-            for operator in &mut new_system.emitters {
-                operator.attributes =
-                    reindex_new_attributes(&old_to_new_string_idx, mem::take(&mut operator.attributes)).collect();
+            if policy.is_some() {
+                system_indices.entry(new_system.name.clone()).or_insert(placement.idx());
             }
 
-            for operator in &mut new_system.forces {
-                operator.attributes =
-                    reindex_new_attributes(&old_to_new_string_idx, mem::take(&mut operator.attributes)).collect();
-            }
+            placements.push(placement);
+        }
 
-            for operator in &mut new_system.initializers {
-                operator.attributes =
-                    reindex_new_attributes(&old_to_new_string_idx, mem::take(&mut operator.attributes)).collect();
+        for (mut new_system, placement) in incoming_systems.into_iter().zip(placements.iter()) {
+            if let SystemPlacement::Drop(_) = placement {
+                continue;
             }
 
-            for operator in &mut new_system.operators {
-                operator.attributes =
-                    reindex_new_attributes(&old_to_new_string_idx, mem::take(&mut operator.attributes)).collect();
+            for child in &mut new_system.children {
+                let Some(child_placement) = placements.get(usize::from(child.child)) else {
+                    return Err(MergeError::ChildOutOfRange {
+                        system: new_system.name.clone(),
+                        child: child.child,
+                    });
+                };
+
+                child.child = element_idx(child_placement.idx());
             }
 
-            for operator in &mut new_system.renderers {
-                operator.attributes =
-                    reindex_new_attributes(&old_to_new_string_idx, mem::take(&mut operator.attributes)).collect();
+            if !is_identity {
+                new_system.remap_symbols(reindex);
             }
 
-            new_system.attributes = reindex_new_attributes(&old_to_new_string_idx, new_system.attributes).collect();
+            if !preserve_signatures {
+                new_system.regenerate_signatures(self.root.signature, placement.idx());
+            }
 
-            particle_systems.push(new_system);
+            match *placement {
+                SystemPlacement::Append(_) => particle_systems.push(new_system),
+                SystemPlacement::Replace(idx) => particle_systems[idx] = new_system,
+                SystemPlacement::Drop(_) => unreachable!(),
+            }
         }
 
         let mut pcf = Self {
@@ -282,7 +605,7 @@ impl Pcf {
         };
 
         pcf.encoded_size = pcf.compute_encoded_size();
-        Ok(pcf)
+        Ok((pcf, report))
     }
 
     fn compute_encoded_size(&self) -> usize {
@@ -312,30 +635,12 @@ impl Pcf {
         elements_size += size_of::<u16>() + self.root.name.len() + 1 + size_of::<Signature>();
 
         // do the same for each element across all of our particle systems
-        for system in &self.root.particle_systems {
-            elements_size += size_of::<u16>() + system.name.len() + 1 + size_of::<Signature>();
-            for child in &system.children {
-                elements_size += size_of::<u16>() + child.name.len() + 1 + size_of::<Signature>();
-            }
-            for operator in &system.constraints {
-                elements_size += size_of::<u16>() + operator.name.len() + 1 + size_of::<Signature>();
-            }
-            for operator in &system.emitters {
-                elements_size += size_of::<u16>() + operator.name.len() + 1 + size_of::<Signature>();
-            }
-            for operator in &system.forces {
-                elements_size += size_of::<u16>() + operator.name.len() + 1 + size_of::<Signature>();
-            }
-            for operator in &system.initializers {
-                elements_size += size_of::<u16>() + operator.name.len() + 1 + size_of::<Signature>();
-            }
-            for operator in &system.operators {
-                elements_size += size_of::<u16>() + operator.name.len() + 1 + size_of::<Signature>();
-            }
-            for operator in &system.renderers {
-                elements_size += size_of::<u16>() + operator.name.len() + 1 + size_of::<Signature>();
-            }
-        }
+        elements_size += self
+            .root
+            .particle_systems
+            .iter()
+            .map(ParticleSystem::encoded_elements_size)
+            .sum::<usize>();
 
         elements_size
     }
@@ -350,7 +655,7 @@ impl Pcf {
         attributes_size += size_of::<SymbolIdx>()
             + size_of::<u8>()
             + size_of::<u32>()
-            + (self.root.particle_systems.len() * size_of::<ElementIdx>());
+            + (self.root.particle_systems.len() * ElementIdx::ENCODED_SIZE);
 
         for (_, attribute) in &self.root.attributes {
             // the 16-bit name index
@@ -365,120 +670,53 @@ impl Pcf {
     }
 
     pub fn compute_encoded_attributes_size(&self) -> usize {
-        let mut attributes_size = 0;
-
-        // do the same for each element across all of our particle systems
-        for system in &self.root.particle_systems {
-            attributes_size += size_of::<u32>();
-            for (_, attribute) in &system.attributes {
-                attributes_size += size_of::<SymbolIdx>();
-                attributes_size += size_of::<u8>();
-                attributes_size += attribute.get_encoded_size();
-            }
-
-            if !system.children.is_empty() {
-                attributes_size += size_of::<SymbolIdx>() + size_of::<u8>() + size_of::<u32>();
-                attributes_size += system.children.len() * size_of::<ElementIdx>();
-            }
-
-            if !system.constraints.is_empty() {
-                attributes_size += size_of::<SymbolIdx>() + size_of::<u8>() + size_of::<u32>();
-                attributes_size += system.constraints.len() * size_of::<ElementIdx>();
-            }
-
-            if !system.emitters.is_empty() {
-                attributes_size += size_of::<SymbolIdx>() + size_of::<u8>() + size_of::<u32>();
-                attributes_size += system.emitters.len() * size_of::<ElementIdx>();
-            }
+        self.root
+            .particle_systems
+            .iter()
+            .map(ParticleSystem::encoded_attributes_size)
+            .sum()
+    }
 
-            if !system.forces.is_empty() {
-                attributes_size += size_of::<SymbolIdx>() + size_of::<u8>() + size_of::<u32>();
-                attributes_size += system.forces.len() * size_of::<ElementIdx>();
+    /// The number of bytes each particle system contributes to the encoded size of this [`Pcf`], in the same order as
+    /// [`Pcf::particle_systems`].
+    ///
+    /// Each system is charged for its own elements & attributes. The cost of each symbol is split evenly between the
+    /// systems which reference it, and everything else - the header, the root element, and unreferenced symbols - is
+    /// split evenly between all systems. So, the sizes always add up to [`Pcf::encoded_size`].
+    pub fn system_sizes(&self) -> Vec<usize> {
+        fn share(sizes: &mut [usize], indices: impl ExactSizeIterator<Item = usize> + Clone, cost: usize) {
+            let count = indices.len();
+            if count == 0 {
+                return;
             }
 
-            if !system.initializers.is_empty() {
-                attributes_size += size_of::<SymbolIdx>() + size_of::<u8>() + size_of::<u32>();
-                attributes_size += system.initializers.len() * size_of::<ElementIdx>();
+            // any remainder goes to the first system so that nothing is lost to rounding
+            let (each, remainder) = (cost / count, cost % count);
+            for (n, idx) in indices.enumerate() {
+                sizes[idx] += each + if n == 0 { remainder } else { 0 };
             }
+        }
 
-            if !system.operators.is_empty() {
-                attributes_size += size_of::<SymbolIdx>() + size_of::<u8>() + size_of::<u32>();
-                attributes_size += system.operators.len() * size_of::<ElementIdx>();
-            }
+        let systems = &self.root.particle_systems;
+        let mut sizes: Vec<_> = systems.iter().map(ParticleSystem::encoded_own_size).collect();
 
-            if !system.renderers.is_empty() {
-                attributes_size += size_of::<SymbolIdx>() + size_of::<u8>() + size_of::<u32>();
-                attributes_size += system.renderers.len() * size_of::<ElementIdx>();
+        let mut symbol_users: HashMap<SymbolIdx, Vec<usize>> = HashMap::new();
+        for (system_idx, system) in systems.iter().enumerate() {
+            for symbol_idx in system.referenced_symbols() {
+                symbol_users.entry(symbol_idx).or_default().push(system_idx);
             }
+        }
 
-            for child in &system.children {
-                attributes_size += size_of::<u32>();
-                // child.child will also become an attribute
-                attributes_size += size_of::<SymbolIdx>() + size_of::<u8>() + size_of::<u32>();
-                for (_, attribute) in &child.attributes {
-                    attributes_size += size_of::<SymbolIdx>();
-                    attributes_size += size_of::<u8>();
-                    attributes_size += attribute.get_encoded_size();
-                }
-            }
-            for operator in &system.constraints {
-                attributes_size += size_of::<u32>();
-                // function name will also become an attribute
-                attributes_size += size_of::<SymbolIdx>() + size_of::<u8>() + 1 + operator.function_name.len();
-                for (_, attribute) in &operator.attributes {
-                    attributes_size += size_of::<SymbolIdx>();
-                    attributes_size += size_of::<u8>();
-                    attributes_size += attribute.get_encoded_size();
-                }
-            }
-            for operator in &system.emitters {
-                attributes_size += size_of::<u32>();
-                attributes_size += size_of::<SymbolIdx>() + size_of::<u8>() + 1 + operator.function_name.len();
-                for (_, attribute) in &operator.attributes {
-                    attributes_size += size_of::<SymbolIdx>();
-                    attributes_size += size_of::<u8>();
-                    attributes_size += attribute.get_encoded_size();
-                }
-            }
-            for operator in &system.forces {
-                attributes_size += size_of::<u32>();
-                attributes_size += size_of::<SymbolIdx>() + size_of::<u8>() + 1 + operator.function_name.len();
-                for (_, attribute) in &operator.attributes {
-                    attributes_size += size_of::<SymbolIdx>();
-                    attributes_size += size_of::<u8>();
-                    attributes_size += attribute.get_encoded_size();
-                }
-            }
-            for operator in &system.initializers {
-                attributes_size += size_of::<u32>();
-                attributes_size += size_of::<SymbolIdx>() + size_of::<u8>() + 1 + operator.function_name.len();
-                for (_, attribute) in &operator.attributes {
-                    attributes_size += size_of::<SymbolIdx>();
-                    attributes_size += size_of::<u8>();
-                    attributes_size += attribute.get_encoded_size();
-                }
-            }
-            for operator in &system.operators {
-                attributes_size += size_of::<u32>();
-                attributes_size += size_of::<SymbolIdx>() + size_of::<u8>() + 1 + operator.function_name.len();
-                for (_, attribute) in &operator.attributes {
-                    attributes_size += size_of::<SymbolIdx>();
-                    attributes_size += size_of::<u8>();
-                    attributes_size += attribute.get_encoded_size();
-                }
-            }
-            for operator in &system.renderers {
-                attributes_size += size_of::<u32>();
-                attributes_size += size_of::<SymbolIdx>() + size_of::<u8>() + 1 + operator.function_name.len();
-                for (_, attribute) in &operator.attributes {
-                    attributes_size += size_of::<SymbolIdx>();
-                    attributes_size += size_of::<u8>();
-                    attributes_size += attribute.get_encoded_size();
-                }
+        for (symbol_idx, users) in &symbol_users {
+            if let Some(symbol) = self.symbols.base.get_index(*symbol_idx as usize) {
+                share(&mut sizes, users.iter().copied(), symbol.len() + 1);
             }
         }
 
-        attributes_size
+        let overhead = self.compute_encoded_size() - sizes.iter().sum::<usize>();
+        share(&mut sizes, 0..systems.len(), overhead);
+
+        sizes
     }
 
     pub fn compute_merged_size(&self, from: &Self) -> usize {
@@ -532,6 +770,9 @@ impl Pcf {
             for operator in &system.renderers {
                 elements_size += size_of::<u16>() + operator.name.len() + 1 + size_of::<Signature>();
             }
+            for passthrough in system.passthrough.values() {
+                elements_size += passthrough.encoded_elements_size();
+            }
         }
 
         self.compute_encoded_elements_size() + elements_size
@@ -548,7 +789,7 @@ impl Pcf {
         let mut attributes_size = 0;
 
         // the root element's particle system definitions will become an attribute
-        attributes_size += from.root.particle_systems.len() * size_of::<ElementIdx>();
+        attributes_size += from.root.particle_systems.len() * ElementIdx::ENCODED_SIZE;
 
         for (name_idx, attribute) in &from.root.attributes {
             // only include attributes from `from` that don't already exist in `self`
@@ -583,37 +824,37 @@ impl Pcf {
 
             if !system.children.is_empty() {
                 attributes_size += size_of::<SymbolIdx>() + size_of::<u8>() + size_of::<u32>();
-                attributes_size += system.children.len() * size_of::<ElementIdx>();
+                attributes_size += system.children.len() * ElementIdx::ENCODED_SIZE;
             }
 
             if !system.constraints.is_empty() {
                 attributes_size += size_of::<SymbolIdx>() + size_of::<u8>() + size_of::<u32>();
-                attributes_size += system.constraints.len() * size_of::<ElementIdx>();
+                attributes_size += system.constraints.len() * ElementIdx::ENCODED_SIZE;
             }
 
             if !system.emitters.is_empty() {
                 attributes_size += size_of::<SymbolIdx>() + size_of::<u8>() + size_of::<u32>();
-                attributes_size += system.emitters.len() * size_of::<ElementIdx>();
+                attributes_size += system.emitters.len() * ElementIdx::ENCODED_SIZE;
             }
 
             if !system.forces.is_empty() {
                 attributes_size += size_of::<SymbolIdx>() + size_of::<u8>() + size_of::<u32>();
-                attributes_size += system.forces.len() * size_of::<ElementIdx>();
+                attributes_size += system.forces.len() * ElementIdx::ENCODED_SIZE;
             }
 
             if !system.initializers.is_empty() {
                 attributes_size += size_of::<SymbolIdx>() + size_of::<u8>() + size_of::<u32>();
-                attributes_size += system.initializers.len() * size_of::<ElementIdx>();
+                attributes_size += system.initializers.len() * ElementIdx::ENCODED_SIZE;
             }
 
             if !system.operators.is_empty() {
                 attributes_size += size_of::<SymbolIdx>() + size_of::<u8>() + size_of::<u32>();
-                attributes_size += system.operators.len() * size_of::<ElementIdx>();
+                attributes_size += system.operators.len() * ElementIdx::ENCODED_SIZE;
             }
 
             if !system.renderers.is_empty() {
                 attributes_size += size_of::<SymbolIdx>() + size_of::<u8>() + size_of::<u32>();
-                attributes_size += system.renderers.len() * size_of::<ElementIdx>();
+                attributes_size += system.renderers.len() * ElementIdx::ENCODED_SIZE;
             }
 
             for child in &system.children {
@@ -681,6 +922,9 @@ impl Pcf {
                     attributes_size += attribute.get_encoded_size();
                 }
             }
+            for passthrough in system.passthrough.values() {
+                attributes_size += passthrough.encoded_attributes_size();
+            }
         }
 
         self.compute_encoded_attributes_size() + attributes_size
@@ -721,31 +965,13 @@ impl Pcf {
         //     components
         // }
 
-        let mut graph: UnGraphMap<ElementIdx, ()> = UnGraphMap::new();
-        for (system_idx, _) in self.root.particle_systems.iter().enumerate() {
-            graph.add_node(system_idx.into());
-        }
-
-        for (system_idx, particle_system) in self.root.particle_systems.iter().enumerate() {
-            for child in particle_system.children.iter() {
-                graph.add_edge(system_idx.into(), child.child, ());
-            }
-        }
-
-        let mut components = tarjan_scc(&graph);
-
-        // tarjan_scc results in reversed element groups, so we unreverse it here
-        components.reverse();
-        let mut groups = Vec::new();
-        for mut component in components {
-            // tarjan_scc results in reversed element indices in each group, so we unreverse it here
-            component.reverse();
-
-            let old_to_new_idx: HashMap<_, _> = component
-                .iter()
-                .enumerate()
-                .map(|(new_idx, old_idx)| (*old_idx, ElementIdx::from(new_idx)))
-                .collect();
+        let mut groups = Vec::new();
+        for component in self.connected_components() {
+            let old_to_new_idx: HashMap<_, _> = component
+                .iter()
+                .enumerate()
+                .map(|(new_idx, old_idx)| (*old_idx, element_idx(new_idx)))
+                .collect();
 
             let mut group = Vec::new();
             for system_idx in component {
@@ -760,17 +986,28 @@ impl Pcf {
             groups.push(group);
         }
 
+        // every group but the last gets a copy of the symbols & root attributes, and the last group takes them
+        let last_group = groups.len().saturating_sub(1);
+        let mut shared = Some((self.symbols, self.root.name, self.root.attributes));
         groups
             .into_iter()
-            .map(|group| {
+            .enumerate()
+            .map(|(group_idx, group)| {
+                let (symbols, name, attributes) = if group_idx == last_group {
+                    shared.take().expect("only the last group takes the shared parts")
+                } else {
+                    let (symbols, name, attributes) = shared.as_ref().expect("the last group comes last");
+                    (symbols.clone(), name.clone(), attributes.clone())
+                };
+
                 Self {
                     version: self.version,
-                    symbols: self.symbols.clone(),
+                    symbols,
                     root: Root {
-                        name: self.root.name.clone(),
+                        name,
                         signature: self.root.signature,
                         particle_systems: group.into_iter().collect(),
-                        attributes: self.root.attributes.clone(),
+                        attributes,
                     },
                     encoded_size: 0,
                 }
@@ -779,9 +1016,582 @@ impl Pcf {
             .collect()
     }
 
+    /// Consumes the [`Pcf`], returning a new [`Pcf`] whose particle systems are ordered such that every system comes
+    /// before any system it references as a child. Systems that are already in a valid order keep their relative
+    /// order. References to systems are replaced with the new index for each system.
+    ///
+    /// If `deny_cycles` is set, this fails with [`OrderError::Cycle`] when the child references contain a cycle.
+    /// Otherwise, systems that are part of a cycle - or are only referenced by systems in a cycle - are placed after
+    /// every other system, in their original order.
+    ///
+    /// # Errors
+    ///
+    /// Returns [`OrderError::ChildOutOfRange`] if a child references a system that isn't in this [`Pcf`].
+    pub fn topologically_sorted(mut self, deny_cycles: bool) -> Result<Self, OrderError> {
+        self.check_children()?;
+        let system_count = self.root.particle_systems.len();
+
+        let mut in_degrees = vec![0usize; system_count];
+        for system in &self.root.particle_systems {
+            for child in &system.children {
+                in_degrees[usize::from(child.child)] += 1;
+            }
+        }
+
+        // Kahn's algorithm, always picking the lowest original index available so that the sort is stable.
+        let mut ready: BinaryHeap<_> = in_degrees
+            .iter()
+            .enumerate()
+            .filter(|(_, in_degree)| **in_degree == 0)
+            .map(|(idx, _)| Reverse(idx))
+            .collect();
+
+        let mut order = Vec::with_capacity(system_count);
+        while let Some(Reverse(idx)) = ready.pop() {
+            order.push(idx);
+            for child in &self.root.particle_systems[idx].children {
+                let child_idx = usize::from(child.child);
+                in_degrees[child_idx] -= 1;
+                if in_degrees[child_idx] == 0 {
+                    ready.push(Reverse(child_idx));
+                }
+            }
+        }
+
+        if order.len() < system_count {
+            let cyclic: Vec<_> = (0..system_count).filter(|idx| in_degrees[*idx] > 0).collect();
+            if deny_cycles {
+                return Err(OrderError::Cycle(
+                    cyclic
+                        .into_iter()
+                        .map(|idx| self.root.particle_systems[idx].name.clone())
+                        .collect(),
+                ));
+            }
+
+            order.extend(cyclic);
+        }
+
+        self.root
+            .reorder_systems(&order)
+            .expect("every system should be ordered exactly once");
+        Ok(self)
+    }
+
+    /// Sorts the particle systems with `compare`, like [`slice::sort_by`]. The sort is stable, so systems which compare
+    /// equal keep their relative order. References to systems are replaced with the new index for each system.
+    ///
+    /// Nothing is encoded differently, besides the order of the systems, so the encoded size is unchanged.
+    pub fn sort_systems_by(&mut self, mut compare: impl FnMut(&ParticleSystem, &ParticleSystem) -> Ordering) {
+        let systems = &self.root.particle_systems;
+        let mut ordering: Vec<_> = (0..systems.len()).collect();
+        ordering.sort_by(|a, b| compare(&systems[*a], &systems[*b]));
+
+        self.root
+            .reorder_systems(&ordering)
+            .expect("a sorted ordering should list every system exactly once");
+    }
+
+    /// The indices of every root particle system, i.e. every system which isn't a child of another system.
+    ///
+    /// # Errors
+    ///
+    /// Returns [`OrderError::ChildOutOfRange`] if a child references a system that isn't in this [`Pcf`].
+    pub fn root_systems(&self) -> Result<Vec<ParticleSystemIdx>, OrderError> {
+        self.check_children()?;
+
+        let mut is_child = vec![false; self.root.particle_systems.len()];
+        for system in &self.root.particle_systems {
+            for child in &system.children {
+                is_child[usize::from(child.child)] = true;
+            }
+        }
+
+        Ok((0..is_child.len()).filter(|idx| !is_child[*idx]).collect())
+    }
+
+    /// Checks that every child references a system in this [`Pcf`], so the systems can be indexed by child.
+    fn check_children(&self) -> Result<(), OrderError> {
+        let system_count = self.root.particle_systems.len();
+        for system in &self.root.particle_systems {
+            if let Some(child) = system
+                .children
+                .iter()
+                .find(|child| usize::from(child.child) >= system_count)
+            {
+                return Err(OrderError::ChildOutOfRange {
+                    system: system.name.clone(),
+                    child: child.child,
+                });
+            }
+        }
+
+        Ok(())
+    }
+
+    /// Consumes the [`Pcf`], returning a new [`Pcf`] without the root systems for which `exclude` returns true. Their
+    /// descendants are removed too, unless they're also a descendant of a root system that's kept. References to
+    /// systems are replaced with the new index for each system.
+    ///
+    /// See [`Pcf::root_systems`].
+    ///
+    /// # Errors
+    ///
+    /// Returns [`OrderError::ChildOutOfRange`] if a child references a system that isn't in this [`Pcf`].
+    pub fn without_root_systems(self, mut exclude: impl FnMut(&ParticleSystem) -> bool) -> Result<Self, OrderError> {
+        let (excluded_roots, kept_roots): (Vec<_>, Vec<_>) = self
+            .root_systems()?
+            .into_iter()
+            .partition(|idx| exclude(&self.root.particle_systems[*idx]));
+
+        if excluded_roots.is_empty() {
+            return Ok(self);
+        }
+
+        let excluded = self.descendants(excluded_roots);
+        let kept = self.descendants(kept_roots);
+        let retain: Vec<_> = excluded
+            .into_iter()
+            .zip(kept)
+            .map(|(excluded, kept)| !excluded || kept)
+            .collect();
+
+        Ok(self.retained_systems(&retain))
+    }
+
+    /// Consumes the [`Pcf`], returning a new [`Pcf`] with only the system named `name` and its descendants, or `None`
+    /// if there is no system with that name. This is the usual starting point for modding a single particle effect.
+    ///
+    /// # Errors
+    ///
+    /// Returns [`OrderError::ChildOutOfRange`] if a child references a system that isn't in this [`Pcf`].
+    pub fn extracted(self, name: &str) -> Result<Option<Self>, OrderError> {
+        let Some(system_idx) = self.root.particle_systems.iter().position(|system| system.name == name) else {
+            return Ok(None);
+        };
+
+        self.check_children()?;
+        let retain = self.descendants(vec![system_idx]);
+        Ok(Some(self.retained_systems(&retain)))
+    }
+
+    /// Marks `roots` and every system they reference as a child, directly or indirectly. Every child must reference a
+    /// system in this [`Pcf`], see [`Pcf::check_children`].
+    fn descendants(&self, roots: Vec<ParticleSystemIdx>) -> Vec<bool> {
+        let mut visited = vec![false; self.root.particle_systems.len()];
+        let mut stack = roots;
+        while let Some(idx) = stack.pop() {
+            if mem::replace(&mut visited[idx], true) {
+                continue;
+            }
+
+            stack.extend(
+                self.root.particle_systems[idx]
+                    .children
+                    .iter()
+                    .map(|child| usize::from(child.child)),
+            );
+        }
+
+        visited
+    }
+
+    /// Consumes the [`Pcf`], keeping only the systems marked in `retain`. References to systems are replaced with the
+    /// new index for each system, so no retained system may reference a removed one.
+    fn retained_systems(mut self, retain: &[bool]) -> Self {
+        let system_count = self.root.particle_systems.len();
+        let mut old_to_new_idx = vec![ElementIdx::INVALID; system_count];
+        let mut particle_systems = Vec::with_capacity(system_count);
+        for idx in 0..system_count {
+            if !retain[idx] {
+                continue;
+            }
+
+            old_to_new_idx[idx] = element_idx(particle_systems.len());
+            particle_systems.push(mem::take(&mut self.root.particle_systems[idx]));
+        }
+
+        for system in &mut particle_systems {
+            for child in &mut system.children {
+                child.child = old_to_new_idx[usize::from(child.child)];
+            }
+        }
+
+        self.root.particle_systems = particle_systems.into_boxed_slice();
+        self.unused_symbols_stripped()
+    }
+
+    /// Groups the indices of particle systems which are connected by child references, directly or indirectly. The
+    /// groups & their systems are in the same order [`Pcf::into_connected`] splits the [`Pcf`] into.
+    pub fn connected_components(&self) -> Vec<Vec<ElementIdx>> {
+        let mut graph: UnGraphMap<ElementIdx, ()> = UnGraphMap::new();
+        for (system_idx, _) in self.root.particle_systems.iter().enumerate() {
+            graph.add_node(element_idx(system_idx));
+        }
+
+        for (system_idx, particle_system) in self.root.particle_systems.iter().enumerate() {
+            for child in particle_system.children.iter() {
+                graph.add_edge(element_idx(system_idx), child.child, ());
+            }
+        }
+
+        let mut components = tarjan_scc(&graph);
+
+        // tarjan_scc results in reversed element groups, so we unreverse it here
+        components.reverse();
+        for component in &mut components {
+            // tarjan_scc results in reversed element indices in each group, so we unreverse it here
+            component.reverse();
+        }
+
+        components
+    }
+
+    /// Consumes the [`Pcf`], returning a new [`Pcf`] where every child & operator element has an empty name.
+    ///
+    /// The game finds operators by their `functionName` and children by their `child` reference; the element names
+    /// are only shown in the particle editor, so they can be dropped to save space.
+    pub fn editor_names_stripped(mut self) -> Self {
+        for system in &mut self.root.particle_systems {
+            for child in &mut system.children {
+                child.name.clear();
+            }
+
+            let operators = system
+                .constraints
+                .iter_mut()
+                .chain(system.emitters.iter_mut())
+                .chain(system.forces.iter_mut())
+                .chain(system.initializers.iter_mut())
+                .chain(system.operators.iter_mut())
+                .chain(system.renderers.iter_mut());
+
+            for operator in operators {
+                operator.name.clear();
+            }
+        }
+
+        self.encoded_size = self.compute_encoded_size();
+        self
+    }
+
+    /// Consumes the [`Pcf`], returning a new [`Pcf`] where every particle system's `material` is replaced by the
+    /// result of `rename`, if it returns [`Some`].
+    ///
+    /// Used when the materials a PCF references are moved, e.g. when an addon's materials are namespaced.
+    pub fn materials_renamed(mut self, mut rename: impl FnMut(&str) -> Option<String>) -> Self {
+        let Some(material_idx) = self.symbols.base.get_index_of("material") else {
+            return self;
+        };
+
+        for system in &mut self.root.particle_systems {
+            if let Some(Attribute::String(material)) = system.attributes.get_mut(&(material_idx as SymbolIdx))
+                && let Some(renamed) = rename(material)
+            {
+                *material = renamed;
+            }
+        }
+
+        self.encoded_size = self.compute_encoded_size();
+        self
+    }
+
+    /// Inserts `operator` into the `list` of the particle system named `system`, at `index`, shifting the operators
+    /// after it back. The symbols for `list` are added if they're missing, but `operator`'s attributes must already
+    /// use this PCF's symbols.
+    ///
+    /// # Errors
+    ///
+    /// Returns [`Err`], without modifying the [`Pcf`], if the particle system couldn't be found, or if `index` is past
+    /// the end of the list.
+    pub fn insert_operator(
+        &mut self,
+        system: &str,
+        list: OperatorList,
+        index: usize,
+        operator: Operator,
+    ) -> Result<(), EditError> {
+        self.system_mut(system)?.insert_operator(list, index, operator)?;
+        self.symbols.insert_operator_list(list);
+        self.encoded_size = self.compute_encoded_size();
+        Ok(())
+    }
+
+    /// Removes the operator at `index` in the `list` of the particle system named `system`, shifting the operators
+    /// after it forward.
+    ///
+    /// # Errors
+    ///
+    /// Returns [`Err`], without modifying the [`Pcf`], if the particle system or operator couldn't be found.
+    pub fn remove_operator(&mut self, system: &str, list: OperatorList, index: usize) -> Result<Operator, EditError> {
+        let operator = self.system_mut(system)?.remove_operator(list, index)?;
+        self.encoded_size = self.compute_encoded_size();
+        Ok(operator)
+    }
+
+    /// Moves the operator at `from` in the `list` of the particle system named `system` to `to`. See
+    /// [`ParticleSystem::move_operator`].
+    ///
+    /// # Errors
+    ///
+    /// Returns [`Err`], without modifying the [`Pcf`], if the particle system couldn't be found, or if `from` or `to`
+    /// isn't in the list.
+    pub fn move_operator(&mut self, system: &str, list: OperatorList, from: usize, to: usize) -> Result<(), EditError> {
+        // moving an operator within its list doesn't change the encoded size
+        self.system_mut(system)?.move_operator(list, from, to)
+    }
+
+    /// The index of the particle system named `name`, e.g. to reference it from a [`Child`].
+    pub fn system_index(&self, name: &str) -> Option<ParticleSystemIdx> {
+        self.root.particle_systems.iter().position(|system| system.name == name)
+    }
+
+    /// Appends `system`, returning its index. `system`'s attributes use `symbols` - e.g. those of the [`Pcf`] it was
+    /// taken from - and are re-indexed into this PCF's symbols, adding any which are missing. Its children must already
+    /// reference systems in this PCF, or `system` itself at the returned index. Signatures are kept as-is.
+    ///
+    /// # Errors
+    ///
+    /// Returns [`Err`], without modifying the [`Pcf`], if there's already a system with the same name, if a child
+    /// references a system which isn't in this PCF, or if `system` uses a symbol which isn't in `symbols`.
+    pub fn push_system(&mut self, system: ParticleSystem, symbols: &Symbols) -> Result<ParticleSystemIdx, EditError> {
+        if self.system_index(&system.name).is_some() {
+            return Err(EditError::DuplicateSystem(system.name));
+        }
+
+        let system_idx = self.root.particle_systems.len();
+        let system = self.adopted_system(system, symbols, system_idx + 1)?;
+
+        let mut particle_systems = mem::take(&mut self.root.particle_systems).into_vec();
+        particle_systems.push(system);
+        self.root.particle_systems = particle_systems.into_boxed_slice();
+        self.encoded_size = self.compute_encoded_size();
+        Ok(system_idx)
+    }
+
+    /// Replaces the particle system named `name` with `system`, in place, returning the replaced system. Children which
+    /// referenced the replaced system reference `system` instead. See [`Pcf::push_system`] for how `system`'s
+    /// attributes & children are handled.
+    ///
+    /// # Errors
+    ///
+    /// Returns [`Err`], without modifying the [`Pcf`], if there's no system named `name`, if `system` is renamed to
+    /// the name of another system, or for any of the reasons [`Pcf::push_system`] fails.
+    pub fn replace_system(
+        &mut self,
+        name: &str,
+        system: ParticleSystem,
+        symbols: &Symbols,
+    ) -> Result<ParticleSystem, EditError> {
+        let system_idx = self
+            .system_index(name)
+            .ok_or_else(|| EditError::UnknownSystem(name.to_string()))?;
+        if system.name != name && self.system_index(&system.name).is_some() {
+            return Err(EditError::DuplicateSystem(system.name));
+        }
+
+        let system = self.adopted_system(system, symbols, self.root.particle_systems.len())?;
+        let replaced = mem::replace(&mut self.root.particle_systems[system_idx], system);
+        self.encoded_size = self.compute_encoded_size();
+        Ok(replaced)
+    }
+
+    /// Removes the particle system named `name`, returning it. Children which referenced it are removed from their
+    /// parents, and references to the systems after it are shifted forward. The removed system's children still
+    /// reference systems by their index before the removal, and symbols only it used are kept; see
+    /// [`Pcf::unused_symbols_stripped`].
+    ///
+    /// # Errors
+    ///
+    /// Returns [`EditError::UnknownSystem`], without modifying the [`Pcf`], if there's no system named `name`.
+    pub fn remove_system(&mut self, name: &str) -> Result<ParticleSystem, EditError> {
+        let removed_idx = self
+            .system_index(name)
+            .ok_or_else(|| EditError::UnknownSystem(name.to_string()))?;
+
+        let mut particle_systems = mem::take(&mut self.root.particle_systems).into_vec();
+        let removed = particle_systems.remove(removed_idx);
+        for system in &mut particle_systems {
+            system.children = mem::take(&mut system.children)
+                .into_iter()
+                .filter_map(|mut child| {
+                    match usize::from(child.child).cmp(&removed_idx) {
+                        Ordering::Less => {}
+                        Ordering::Equal => return None,
+                        Ordering::Greater => child.child = in_range(child.child.rebased(removed_idx + 1, removed_idx)),
+                    }
+
+                    Some(child)
+                })
+                .collect();
+        }
+
+        self.root.particle_systems = particle_systems.into_boxed_slice();
+        self.encoded_size = self.compute_encoded_size();
+        Ok(removed)
+    }
+
+    /// Re-indexes `system`'s attributes from `symbols` into this PCF's symbols, and adds the symbols needed to encode
+    /// its children & operators. `system_count` is the number of systems its children may reference.
+    fn adopted_system(
+        &mut self,
+        mut system: ParticleSystem,
+        symbols: &Symbols,
+        system_count: usize,
+    ) -> Result<ParticleSystem, EditError> {
+        if let Some(child) = system
+            .children
+            .iter()
+            .find(|child| usize::from(child.child) >= system_count)
+        {
+            return Err(EditError::ChildOutOfRange {
+                system: system.name,
+                child: child.child,
+            });
+        }
+
+        let mut referenced_symbols = Vec::new();
+        for symbol in system.referenced_symbols() {
+            let Some(string) = symbols.base.get_index(usize::from(symbol)) else {
+                return Err(EditError::UnknownSymbol {
+                    system: system.name,
+                    symbol,
+                });
+            };
+
+            referenced_symbols.push((symbol, string));
+        }
+
+        let old_to_new_idx: HashMap<SymbolIdx, SymbolIdx> = referenced_symbols
+            .into_iter()
+            .map(|(symbol, string)| (symbol, self.symbols.base.insert_full(string.clone()).0 as SymbolIdx))
+            .collect();
+        system.remap_symbols(|symbol| old_to_new_idx[&symbol]);
+
+        if !system.children.is_empty() {
+            self.symbols.insert_children();
+        }
+
+        for list in OperatorList::ALL {
+            if !system.operator_list(list).is_empty() {
+                self.symbols.insert_operator_list(list);
+            }
+        }
+
+        Ok(system)
+    }
+
+    fn system_mut(&mut self, name: &str) -> Result<&mut ParticleSystem, EditError> {
+        self.root
+            .particle_systems
+            .iter_mut()
+            .find(|system| system.name == name)
+            .ok_or_else(|| EditError::UnknownSystem(name.to_string()))
+    }
+
+    /// The value of the attribute at `path`, or `None` if it isn't set, e.g. because it was stripped as a default.
+    ///
+    /// # Errors
+    ///
+    /// Returns [`Err`] if the particle system or operator in `path` couldn't be found.
+    pub fn attribute(&self, path: &AttributePath) -> Result<Option<&Attribute>, EditError> {
+        let system = self
+            .root
+            .particle_systems
+            .iter()
+            .find(|system| system.name == path.system)
+            .ok_or_else(|| EditError::UnknownSystem(path.system.clone()))?;
+
+        let attributes = match &path.operator {
+            None => &system.attributes,
+            Some(operator) => {
+                let operators: Vec<_> = system.all_operators().collect();
+                &find_operator(operators, &path.system, operator, |operator| {
+                    (&operator.name, &operator.function_name)
+                })?
+                .attributes
+            }
+        };
+
+        Ok(self
+            .symbols
+            .base
+            .get_index_of(path.attribute.as_str())
+            .and_then(|name_idx| attributes.get(&(name_idx as SymbolIdx))))
+    }
+
+    /// Sets the attribute at `path` to `value`, adding it if it isn't set. Returns the attribute's previous value.
+    ///
+    /// # Errors
+    ///
+    /// Returns [`Err`], without modifying the [`Pcf`], if the particle system or operator in `path` couldn't be found,
+    /// or if the attribute is already set to a different type of value.
+    pub fn set_attribute(&mut self, path: &AttributePath, value: Attribute) -> Result<Option<Attribute>, EditError> {
+        let system = self
+            .root
+            .particle_systems
+            .iter_mut()
+            .find(|system| system.name == path.system)
+            .ok_or_else(|| EditError::UnknownSystem(path.system.clone()))?;
+
+        let attributes = match &path.operator {
+            None => &mut system.attributes,
+            Some(operator) => {
+                let operators: Vec<_> = system
+                    .constraints
+                    .iter_mut()
+                    .chain(system.emitters.iter_mut())
+                    .chain(system.forces.iter_mut())
+                    .chain(system.initializers.iter_mut())
+                    .chain(system.operators.iter_mut())
+                    .chain(system.renderers.iter_mut())
+                    .collect();
+
+                &mut find_operator(operators, &path.system, operator, |operator| {
+                    (&operator.name, &operator.function_name)
+                })?
+                .attributes
+            }
+        };
+
+        let current = self
+            .symbols
+            .base
+            .get_index_of(path.attribute.as_str())
+            .and_then(|name_idx| attributes.get_mut(&(name_idx as SymbolIdx)));
+
+        let previous = match current {
+            Some(current) if mem::discriminant(current) != mem::discriminant(&value) => {
+                return Err(EditError::TypeMismatch {
+                    attribute: path.attribute.clone(),
+                    source: TypeMismatch {
+                        expected: current.type_name(),
+                        actual: value.type_name(),
+                    },
+                });
+            }
+            Some(current) => Some(mem::replace(current, value)),
+            None => {
+                let (name_idx, _) = self.symbols.base.insert_full(path.attribute.as_str().into());
+                attributes.insert(name_idx as SymbolIdx, value);
+                None
+            }
+        };
+
+        self.encoded_size = self.compute_encoded_size();
+        Ok(previous)
+    }
+
     /// Consumes the [`Pcf`], returning a new [`Pcf`] with all unused symbols removed. References to symbols are
     /// replaced with the new index for each symbol.
-    pub fn unused_symbols_stripped(mut self) -> Self {
+    pub fn unused_symbols_stripped(self) -> Self {
+        self.unused_symbols_stripped_with_stats().0
+    }
+
+    /// Like [`Pcf::unused_symbols_stripped`], but also returns what was stripped, including every removed symbol.
+    pub fn unused_symbols_stripped_with_stats(mut self) -> (Self, StripStats) {
+        let before = StripCounts::of(&self);
+
         // these symbols are always required
         let mut used_symbols = HashSet::from([
             self.symbols.element,
@@ -806,6 +1616,11 @@ impl Pcf {
                 used_symbols.insert(*name_idx);
             }
 
+            for (name_idx, passthrough) in &system.passthrough {
+                used_symbols.insert(*name_idx);
+                used_symbols.extend(passthrough.referenced_symbols());
+            }
+
             if !system.children.is_empty() {
                 has_child = true;
                 for child in &system.children {
@@ -917,7 +1732,7 @@ impl Pcf {
             );
         }
 
-        if has_emitter {
+        if has_force {
             used_symbols.insert(
                 self.symbols
                     .forces
@@ -925,7 +1740,7 @@ impl Pcf {
             );
         }
 
-        if has_emitter {
+        if has_initializer {
             used_symbols.insert(
                 self.symbols
                     .initializers
@@ -933,7 +1748,7 @@ impl Pcf {
             );
         }
 
-        if has_emitter {
+        if has_operator {
             used_symbols.insert(
                 self.symbols
                     .operators
@@ -941,7 +1756,7 @@ impl Pcf {
             );
         }
 
-        if has_emitter {
+        if has_renderer {
             used_symbols.insert(
                 self.symbols
                     .renderers
@@ -951,12 +1766,14 @@ impl Pcf {
 
         let old_symbols = mem::replace(&mut self.symbols.base, OrderSet::new());
 
+        let mut removed_symbols = Vec::new();
         let mut old_to_new_idx: HashMap<SymbolIdx, SymbolIdx> = HashMap::new();
         let mut running_offset = 0;
         for (idx, symbol) in old_symbols.into_iter().enumerate() {
             let idx = idx as SymbolIdx;
             if !used_symbols.contains(&idx) {
                 running_offset += 1;
+                removed_symbols.push(symbol.to_string());
                 continue;
             }
 
@@ -992,6 +1809,19 @@ impl Pcf {
             .map(|mut particle_system| {
                 particle_system.attributes = remap_attributes(&old_to_new_idx, particle_system.attributes);
 
+                let remap = |idx: SymbolIdx| {
+                    *old_to_new_idx
+                        .get(&idx)
+                        .expect("old name indices should always be present in the map")
+                };
+                particle_system.passthrough = mem::take(&mut particle_system.passthrough)
+                    .into_iter()
+                    .map(|(name_idx, mut passthrough)| {
+                        passthrough.remap_symbols(remap);
+                        (remap(name_idx), passthrough)
+                    })
+                    .collect();
+
                 particle_system.children = particle_system
                     .children
                     .into_iter()
@@ -1049,30 +1879,62 @@ impl Pcf {
         self.symbols.child = self.symbols.child.and_then(|idx| old_to_new_idx.get(&idx).cloned());
 
         self.encoded_size = self.compute_encoded_size();
-        self
+        let stats = StripStats {
+            removed_symbols,
+            ..StripStats::new(before, &self)
+        };
+
+        (self, stats)
     }
 
     pub fn defaults_stripped_nth(
-        mut self,
+        self,
+        to: usize,
+        particle_defaults: &HashMap<&str, Attribute>,
+        operator_defaults: &HashMap<&str, Attribute>,
+    ) -> Self {
+        self.defaults_stripped_nth_with(to, particle_defaults, operator_defaults, Comparison::Exact)
+    }
+
+    /// Like [`Pcf::defaults_stripped_nth`], but attributes are compared against their defaults using `comparison`, so
+    /// that e.g. floats within some epsilon of their default can be stripped too.
+    pub fn defaults_stripped_nth_with(
+        self,
         to: usize,
         particle_defaults: &HashMap<&str, Attribute>,
         operator_defaults: &HashMap<&str, Attribute>,
+        comparison: Comparison,
     ) -> Self {
-        fn remove_operator_defaults(op: &mut Operator, defaults: &HashMap<SymbolIdx, &Attribute>) {
+        self.defaults_stripped_nth_with_stats(to, particle_defaults, operator_defaults, comparison)
+            .0
+    }
+
+    /// Like [`Pcf::defaults_stripped_nth_with`], but also returns what was stripped, including how many of each
+    /// attribute were removed.
+    pub fn defaults_stripped_nth_with_stats(
+        mut self,
+        to: usize,
+        particle_defaults: &HashMap<&str, Attribute>,
+        operator_defaults: &HashMap<&str, Attribute>,
+        comparison: Comparison,
+    ) -> (Self, StripStats) {
+        fn remove_operator_defaults(
+            op: &mut Operator,
+            defaults: &HashMap<SymbolIdx, &Attribute>,
+            comparison: Comparison,
+            removed: &mut HashMap<SymbolIdx, usize>,
+        ) {
             op.attributes = mem::take(&mut op.attributes)
                 .into_iter()
                 .filter(|(name_idx, attribute)| {
-                    if let Some(default) = defaults.get(name_idx)
-                        && attribute == *default
-                    {
-                        false
-                    } else {
-                        true
-                    }
+                    !is_removed_default(*name_idx, attribute, defaults, comparison, removed)
                 })
                 .collect();
         }
 
+        let before = StripCounts::of(&self);
+        let mut removed = HashMap::new();
+
         let particle_defaults: HashMap<_, _> = particle_defaults
             .iter()
             .filter_map(|(name, value)| {
@@ -1100,67 +1962,91 @@ impl Pcf {
             system.attributes = mem::take(&mut system.attributes)
                 .into_iter()
                 .filter(|(name_idx, attribute)| {
-                    if let Some(default) = particle_defaults.get(name_idx)
-                        && attribute == *default
-                    {
-                        false
-                    } else {
-                        true
-                    }
+                    !is_removed_default(*name_idx, attribute, &particle_defaults, comparison, &mut removed)
                 })
                 .collect();
 
             system
                 .constraints
                 .iter_mut()
-                .for_each(|op| remove_operator_defaults(op, &operator_defaults));
+                .for_each(|op| remove_operator_defaults(op, &operator_defaults, comparison, &mut removed));
             system
                 .emitters
                 .iter_mut()
-                .for_each(|op| remove_operator_defaults(op, &operator_defaults));
+                .for_each(|op| remove_operator_defaults(op, &operator_defaults, comparison, &mut removed));
             system
                 .forces
                 .iter_mut()
-                .for_each(|op| remove_operator_defaults(op, &operator_defaults));
+                .for_each(|op| remove_operator_defaults(op, &operator_defaults, comparison, &mut removed));
             system
                 .initializers
                 .iter_mut()
-                .for_each(|op| remove_operator_defaults(op, &operator_defaults));
+                .for_each(|op| remove_operator_defaults(op, &operator_defaults, comparison, &mut removed));
             system
                 .operators
                 .iter_mut()
-                .for_each(|op| remove_operator_defaults(op, &operator_defaults));
+                .for_each(|op| remove_operator_defaults(op, &operator_defaults, comparison, &mut removed));
             system
                 .renderers
                 .iter_mut()
-                .for_each(|op| remove_operator_defaults(op, &operator_defaults));
+                .for_each(|op| remove_operator_defaults(op, &operator_defaults, comparison, &mut removed));
         }
 
-        self
+        self.encoded_size = self.compute_encoded_size();
+        let stats = StripStats {
+            removed_attributes: self.removed_attribute_names(removed),
+            ..StripStats::new(before, &self)
+        };
+
+        (self, stats)
     }
 
     pub fn defaults_stripped(
-        mut self,
+        self,
         particle_defaults: &HashMap<&str, Attribute>,
         operator_defaults: &HashMap<String, HashMap<String, Attribute>>,
     ) -> Self {
-        fn remove_operator_defaults(op: &mut Operator, defaults: &HashMap<&String, HashMap<SymbolIdx, &Attribute>>) {
+        self.defaults_stripped_with(particle_defaults, operator_defaults, Comparison::Exact)
+    }
+
+    /// Like [`Pcf::defaults_stripped`], but attributes are compared against their defaults using `comparison`.
+    pub fn defaults_stripped_with(
+        self,
+        particle_defaults: &HashMap<&str, Attribute>,
+        operator_defaults: &HashMap<String, HashMap<String, Attribute>>,
+        comparison: Comparison,
+    ) -> Self {
+        self.defaults_stripped_with_stats(particle_defaults, operator_defaults, comparison)
+            .0
+    }
+
+    /// Like [`Pcf::defaults_stripped_with`], but also returns what was stripped, including how many of each attribute
+    /// were removed.
+    pub fn defaults_stripped_with_stats(
+        mut self,
+        particle_defaults: &HashMap<&str, Attribute>,
+        operator_defaults: &HashMap<String, HashMap<String, Attribute>>,
+        comparison: Comparison,
+    ) -> (Self, StripStats) {
+        fn remove_operator_defaults(
+            op: &mut Operator,
+            defaults: &HashMap<&String, HashMap<SymbolIdx, &Attribute>>,
+            comparison: Comparison,
+            removed: &mut HashMap<SymbolIdx, usize>,
+        ) {
             if let Some(defaults) = defaults.get(&op.function_name) {
                 op.attributes = mem::take(&mut op.attributes)
                     .into_iter()
                     .filter(|(name_idx, attribute)| {
-                        if let Some(default) = defaults.get(name_idx)
-                            && attribute == *default
-                        {
-                            false
-                        } else {
-                            true
-                        }
+                        !is_removed_default(*name_idx, attribute, defaults, comparison, removed)
                     })
                     .collect();
             }
         }
 
+        let before = StripCounts::of(&self);
+        let mut removed = HashMap::new();
+
         let particle_defaults: HashMap<_, _> = particle_defaults
             .iter()
             .filter_map(|(name, value)| {
@@ -1178,7 +2064,7 @@ impl Pcf {
                 let map: HashMap<_, _> = defaults
                     .iter()
                     .filter_map(|(attribute_name, attribute)| {
-                        let name_idx = self.symbols.base.get_index_of(attribute_name)? as SymbolIdx;
+                        let name_idx = self.symbols.base.get_index_of(attribute_name.as_str())? as SymbolIdx;
                         Some((name_idx, attribute))
                     })
                     .collect();
@@ -1191,49 +2077,185 @@ impl Pcf {
             system.attributes = mem::take(&mut system.attributes)
                 .into_iter()
                 .filter(|(name_idx, attribute)| {
-                    if let Some(default) = particle_defaults.get(name_idx)
-                        && attribute == *default
-                    {
-                        false
-                    } else {
-                        true
-                    }
+                    !is_removed_default(*name_idx, attribute, &particle_defaults, comparison, &mut removed)
                 })
                 .collect();
 
             system
                 .constraints
                 .iter_mut()
-                .for_each(|op| remove_operator_defaults(op, &operator_defaults));
+                .for_each(|op| remove_operator_defaults(op, &operator_defaults, comparison, &mut removed));
             system
                 .emitters
                 .iter_mut()
-                .for_each(|op| remove_operator_defaults(op, &operator_defaults));
+                .for_each(|op| remove_operator_defaults(op, &operator_defaults, comparison, &mut removed));
             system
                 .forces
                 .iter_mut()
-                .for_each(|op| remove_operator_defaults(op, &operator_defaults));
+                .for_each(|op| remove_operator_defaults(op, &operator_defaults, comparison, &mut removed));
             system
                 .initializers
                 .iter_mut()
-                .for_each(|op| remove_operator_defaults(op, &operator_defaults));
+                .for_each(|op| remove_operator_defaults(op, &operator_defaults, comparison, &mut removed));
             system
                 .operators
                 .iter_mut()
-                .for_each(|op| remove_operator_defaults(op, &operator_defaults));
+                .for_each(|op| remove_operator_defaults(op, &operator_defaults, comparison, &mut removed));
             system
                 .renderers
                 .iter_mut()
-                .for_each(|op| remove_operator_defaults(op, &operator_defaults));
+                .for_each(|op| remove_operator_defaults(op, &operator_defaults, comparison, &mut removed));
         }
 
-        self
+        self.encoded_size = self.compute_encoded_size();
+        let stats = StripStats {
+            removed_attributes: self.removed_attribute_names(removed),
+            ..StripStats::new(before, &self)
+        };
+
+        (self, stats)
+    }
+
+    /// Names the attributes counted in `removed`, by their name index.
+    fn removed_attribute_names(&self, removed: HashMap<SymbolIdx, usize>) -> BTreeMap<String, usize> {
+        removed
+            .into_iter()
+            .filter_map(|(name_idx, count)| {
+                let name = self.symbols.base.get_index(usize::from(name_idx))?;
+                Some((name.to_string(), count))
+            })
+            .collect()
+    }
+
+    /// Like [`Pcf::defaults_stripped`], but every operator's defaults come from [`crate::schema`] rather than a
+    /// hand-written map.
+    #[cfg(feature = "schema")]
+    pub fn schema_defaults_stripped(self, particle_defaults: &HashMap<&str, Attribute>) -> Self {
+        self.defaults_stripped(particle_defaults, &crate::schema::operator_defaults())
+    }
+
+    /// Checks that no two elements in this PCF share a signature.
+    ///
+    /// # Errors
+    ///
+    /// Returns [`SignatureError::Duplicates`] with every signature that is shared by more than one element.
+    pub fn validate_signatures(&self) -> Result<(), SignatureError> {
+        let mut seen = HashSet::new();
+        let mut duplicates = OrderSet::new();
+        let signatures = self
+            .root
+            .particle_systems
+            .iter()
+            .flat_map(ParticleSystem::signatures)
+            .chain([&self.root.signature]);
+
+        for signature in signatures {
+            if !seen.insert(signature) {
+                duplicates.insert(*signature);
+            }
+        }
+
+        if duplicates.is_empty() {
+            Ok(())
+        } else {
+            Err(SignatureError::Duplicates(duplicates.into_iter().collect()))
+        }
     }
 
     pub fn encoded_size(&self) -> usize {
         self.encoded_size
     }
 
+    /// Hashes the content of every particle system in this [`Pcf`], in order. Two PCFs with the same particle systems
+    /// have the same hash, even if their symbols are in a different order or their elements have different signatures.
+    ///
+    /// The root's attributes aren't hashed, since they're dropped when merging into a PCF which already has them.
+    pub fn content_hash(&self) -> u64 {
+        let mut hasher = DefaultHasher::new();
+        self.hash_content(&mut hasher);
+        hasher.finish()
+    }
+
+    /// Whether this [`Pcf`] has the same content as `other`, by the same measure as [`Pcf::content_hash`]. Unlike
+    /// comparing hashes, this can't be fooled by a collision.
+    pub fn same_content(&self, other: &Self) -> bool {
+        let mut content = ContentBytes::default();
+        self.hash_content(&mut content);
+
+        let mut other_content = ContentBytes::default();
+        other.hash_content(&mut other_content);
+
+        content.0 == other_content.0
+    }
+
+    fn hash_content<H: Hasher>(&self, hasher: &mut H) {
+        self.root.particle_systems.len().hash(hasher);
+        for system in &self.root.particle_systems {
+            self.hash_system(system, hasher);
+        }
+    }
+
+    /// Hashes the content of the particle system at `system_idx`, like [`Pcf::content_hash`]. Children are hashed by
+    /// the name of the system they reference, so the same system in two different PCFs has the same hash.
+    ///
+    /// # Panics
+    ///
+    /// Panics if `system_idx` is out of bounds.
+    pub fn system_hash(&self, system_idx: ParticleSystemIdx) -> u64 {
+        let mut hasher = DefaultHasher::new();
+        self.hash_system(&self.root.particle_systems[system_idx], &mut hasher);
+        hasher.finish()
+    }
+
+    fn hash_system<H: Hasher>(&self, system: &ParticleSystem, hasher: &mut H) {
+        let hash_attributes = |hasher: &mut H, attributes: &AttributeMap| {
+            attributes.len().hash(hasher);
+            for (name_idx, attribute) in attributes {
+                self.symbols.base.get_index(*name_idx as usize).hash(hasher);
+                attribute.hash(hasher);
+            }
+        };
+
+        system.name.hash(hasher);
+        hash_attributes(hasher, &system.attributes);
+
+        system.children.len().hash(hasher);
+        for child in &system.children {
+            child.name.hash(hasher);
+            self.root
+                .particle_systems
+                .get(usize::from(child.child))
+                .map(|child| &child.name)
+                .hash(hasher);
+            hash_attributes(hasher, &child.attributes);
+        }
+
+        let operator_lists = [
+            &system.constraints,
+            &system.emitters,
+            &system.forces,
+            &system.initializers,
+            &system.operators,
+            &system.renderers,
+        ];
+
+        for operators in operator_lists {
+            operators.len().hash(hasher);
+            for operator in operators {
+                operator.name.hash(hasher);
+                operator.function_name.hash(hasher);
+                hash_attributes(hasher, &operator.attributes);
+            }
+        }
+
+        let symbol = |idx: SymbolIdx| self.symbols.base.get_index(usize::from(idx)).map(Symbol::as_str);
+        system.passthrough.len().hash(hasher);
+        for (name_idx, passthrough) in &system.passthrough {
+            symbol(*name_idx).hash(hasher);
+            passthrough.hash_with(symbol, hasher);
+        }
+    }
+
     pub fn into_parts(self) -> (Version, Symbols, Root) {
         (self.version, self.symbols, self.root)
     }
@@ -1258,29 +2280,106 @@ impl Pcf {
     pub fn particle_systems(&self) -> &[ParticleSystem] {
         &self.root.particle_systems
     }
+
+    /// Every attribute of every particle system, with the name of its system, what owns it, and its name. See
+    /// [`ParticleSystem::iter_attributes`] for the order they're in. The root's own attributes belong to no system, so
+    /// they aren't included.
+    pub fn iter_attributes(&self) -> impl Iterator<Item = (&str, AttributeOwner<'_>, &str, &Attribute)> {
+        self.particle_systems().iter().flat_map(|system| {
+            system
+                .iter_attributes(&self.symbols)
+                .map(|(owner, name, attribute)| (system.name.as_str(), owner, name, attribute))
+        })
+    }
+}
+
+/// A [`Hasher`] which keeps every byte written to it, so [`Pcf::same_content`] can compare exactly what
+/// [`Pcf::content_hash`] hashes.
+#[derive(Default)]
+struct ContentBytes(Vec<u8>);
+
+impl Hasher for ContentBytes {
+    fn finish(&self) -> u64 {
+        unimplemented!("the content is compared, rather than hashed")
+    }
+
+    fn write(&mut self, bytes: &[u8]) {
+        self.0.extend_from_slice(bytes);
+    }
 }
 
+/// The conversion doesn't keep how element references were encoded. References which were encoded by signature (see
+/// [`dmx::reference`]) are resolved to indices like any other, and [`Dmx::signature_references`] is dropped; so a
+/// [`Pcf`] is always encoded with index references, even if it was decoded from a DMX which used signatures.
 impl TryFrom<Dmx> for Pcf {
     type Error = Error;
 
     fn try_from(value: Dmx) -> Result<Self, Self::Error> {
+        Self::from_dmx(value, None)
+    }
+}
+
+impl Pcf {
+    /// Converts `dmx` into a [`Pcf`] like [`Pcf::try_from`], except that any particle system, child, operator, or
+    /// attribute which is invalid is skipped rather than failing the whole conversion. A [`DecodeWarning`] is returned
+    /// for each part that was skipped.
+    ///
+    /// # Errors
+    ///
+    /// Returns [`Err`] if `dmx` can't be a PCF at all - e.g. it has no root element, its root has no particle system
+    /// definitions, or its string list is missing a symbol every PCF needs.
+    pub fn try_from_dmx_lenient(dmx: Dmx) -> Result<(Self, Vec<DecodeWarning>), Error> {
+        let mut warnings = Vec::new();
+        let pcf = Self::from_dmx(dmx, Some(&mut warnings))?;
+        Ok((pcf, warnings))
+    }
+
+    /// Converts `value` into a [`Pcf`]. If `warnings` is [`Some`], invalid parts of the PCF are skipped and recorded
+    /// there, rather than returned as [`Err`].
+    fn from_dmx(mut value: Dmx, mut warnings: Option<&mut Vec<DecodeWarning>>) -> Result<Self, Error> {
+        let mut skip = |system: Option<&str>, error: Error| match warnings.as_deref_mut() {
+            Some(warnings) => {
+                warnings.push(DecodeWarning {
+                    system: system.map(str::to_string),
+                    error,
+                });
+                Ok(())
+            }
+            None => Err(error),
+        };
+
+        // a PCF's symbols are a set, so any duplicates in the DMX's string table are merged first
+        value.deduplicate_strings();
         let symbols: Symbols = value.strings.try_into()?;
 
         let root_element = value.elements.first().ok_or(Error::NoElements)?;
-        let Some(dmx::attribute::Attribute::ElementArray(system_indices)) =
+        let Some(dmx::attribute::Attribute::ElementArray(definitions)) =
             root_element.attributes.get(&symbols.particle_system_definitions)
         else {
             return Err(Error::MissingRootDefintions);
         };
 
-        // `particle_systems` will contain each particle system in the same order defined in `system_indices`; if we
+        // `particle_systems` will contain each particle system in the same order defined in `definitions`; if we
         // didn't map old indices to new indices, we'd have to do a second pass over each particle system after each
         // index is known in order to map the old child element indices. This lets us avoid the second pass entirely.
-        let system_indices: OrderMap<_, _> = system_indices
-            .iter()
-            .enumerate()
-            .map(|(new_idx, old_idx)| (*old_idx, ElementIdx::from(new_idx)))
-            .collect();
+        //
+        // invalid systems are skipped here, before any children are read, so that children only map to valid systems
+        let mut system_indices: OrderMap<ElementIdx, ElementIdx> = OrderMap::new();
+        let mut system_elements = Vec::new();
+        for system_idx in definitions {
+            if system_indices.contains_key(system_idx) {
+                continue;
+            }
+
+            match value.elements.get(usize::from(*system_idx)) {
+                Some(element) if element.type_idx == symbols.particle_system_definition => {
+                    system_indices.insert(*system_idx, element_idx(system_elements.len()));
+                    system_elements.push(element);
+                }
+                Some(_) => skip(None, Error::InvalidParticleSystem(*system_idx))?,
+                None => skip(None, Error::MissingParticleSystem(*system_idx))?,
+            }
+        }
 
         // the elements list is an association list for a Directed Acyclic Graph.
         // there is always at least a root element, usually named "untitled", which always has an attribute named
@@ -1297,16 +2396,10 @@ impl TryFrom<Dmx> for Pcf {
 
         let mut particle_systems: Vec<ParticleSystem> = Vec::new();
 
-        for system_idx in system_indices.keys() {
-            let element = value
-                .elements
-                .get(usize::from(*system_idx))
-                .ok_or(Error::MissingParticleSystem(*system_idx))?;
-
-            if element.type_idx != symbols.particle_system_definition {
-                return Err(Error::InvalidParticleSystem(*system_idx));
-            }
+        // the root & particle systems can't be copied into a passthrough attribute without duplicating them
+        let is_shared = |idx: ElementIdx| usize::from(idx) == 0 || system_indices.contains_key(&idx);
 
+        for element in system_elements {
             let name = element.name.to_string_lossy().into_owned();
             let signature = element.signature;
 
@@ -1318,52 +2411,17 @@ impl TryFrom<Dmx> for Pcf {
             let mut operators: Vec<Operator> = Vec::new();
             let mut renderers: Vec<Operator> = Vec::new();
             let mut attributes = OrderMap::new();
+            let mut passthrough = PassthroughMap::new();
 
             for (name_idx, attribute) in &element.attributes {
                 if let dmx::attribute::Attribute::ElementArray(element_indices) = attribute {
                     if symbols.children.is_some_and(|idx| *name_idx == idx) {
                         for child_element_idx in element_indices {
-                            let child_element = value
-                                .elements
-                                .get(usize::from(*child_element_idx))
-                                .ok_or(Error::MissingParticleChild(*child_element_idx))?;
-
-                            if symbols.particle_child.is_none_or(|idx| child_element.type_idx != idx) {
-                                return Err(Error::InvalidParticleChild(*child_element_idx));
-                            }
-
-                            let child_attribute = symbols
-                                .child
-                                .and_then(|idx| child_element.attributes.get(&idx))
-                                .ok_or(Error::MissingChild)?;
-                            let dmx::attribute::Attribute::Element(child_system_idx) = child_attribute else {
-                                return Err(Error::MissingChild);
-                            };
-
-                            if !child_system_idx.is_valid() {
-                                continue;
-                            }
-
-                            let mut attributes = OrderMap::new();
-                            for (name_idx, attribute) in &child_element.attributes {
-                                if symbols.child.is_some_and(|idx| *name_idx == idx) {
-                                    continue;
-                                }
-
-                                attributes.insert(*name_idx, attribute.clone().try_into()?);
+                            match Child::try_from(&value.elements, *child_element_idx, &symbols, &system_indices) {
+                                Ok(Some(child)) => children.push(child),
+                                Ok(None) => {}
+                                Err(err) => skip(Some(&name), err)?,
                             }
-
-                            let name = child_element.name.to_string_lossy().into_owned();
-                            let signature = child_element.signature;
-                            let child = *system_indices
-                                .get(child_system_idx)
-                                .expect("this relationship should always be valid");
-                            children.push(Child {
-                                name,
-                                signature,
-                                attributes,
-                                child,
-                            });
                         }
                         continue;
                     }
@@ -1381,23 +2439,37 @@ impl TryFrom<Dmx> for Pcf {
                     } else if symbols.renderers.is_some_and(|idx| *name_idx == idx) {
                         &mut renderers
                     } else {
-                        return Err(Error::UnexpectedElementReference);
+                        match Passthrough::collect(attribute, &value.elements, is_shared) {
+                            Ok(value) => _ = passthrough.insert(*name_idx, value),
+                            Err(err) => skip(Some(&name), err)?,
+                        }
+                        continue;
                     };
 
                     for element_idx in element_indices {
-                        let element = value
-                            .elements
-                            .get(usize::from(*element_idx))
-                            .ok_or(Error::MissingOperator(*element_idx))?;
+                        let operator = match value.elements.get(usize::from(*element_idx)) {
+                            Some(element) if symbols.particle_operator.is_some_and(|idx| element.type_idx == idx) => {
+                                Operator::try_from(element, &symbols)
+                            }
+                            Some(_) => Err(Error::InvalidParticleOperator(*element_idx)),
+                            None => Err(Error::MissingOperator(*element_idx)),
+                        };
 
-                        if symbols.particle_operator.is_none_or(|idx| element.type_idx != idx) {
-                            return Err(Error::InvalidParticleOperator(*element_idx));
+                        match operator {
+                            Ok(operator) => dme_operators.push(operator),
+                            Err(err) => skip(Some(&name), err)?,
                         }
-
-                        dme_operators.push(Operator::try_from(element, &symbols)?);
+                    }
+                } else if let dmx::attribute::Attribute::Element(_) = attribute {
+                    match Passthrough::collect(attribute, &value.elements, is_shared) {
+                        Ok(value) => _ = passthrough.insert(*name_idx, value),
+                        Err(err) => skip(Some(&name), err)?,
                     }
                 } else {
-                    attributes.insert(*name_idx, attribute.clone().try_into()?);
+                    match attribute.clone().try_into() {
+                        Ok(attribute) => _ = attributes.insert(*name_idx, attribute),
+                        Err(err) => skip(Some(&name), err)?,
+                    }
                 }
             }
 
@@ -1412,6 +2484,7 @@ impl TryFrom<Dmx> for Pcf {
                 operators: operators.into_boxed_slice(),
                 renderers: renderers.into_boxed_slice(),
                 attributes,
+                passthrough,
             });
         }
 
@@ -1421,7 +2494,10 @@ impl TryFrom<Dmx> for Pcf {
                 continue;
             }
 
-            attributes.insert(*name_idx, attribute.clone().try_into()?);
+            match attribute.clone().try_into() {
+                Ok(attribute) => _ = attributes.insert(*name_idx, attribute),
+                Err(err) => skip(None, err)?,
+            }
         }
 
         let root = Root {
@@ -1443,6 +2519,8 @@ impl TryFrom<Dmx> for Pcf {
     }
 }
 
+/// Every element reference is encoded by index, since the elements are rebuilt from scratch. See
+/// `Pcf`'s `TryFrom<Dmx>` implementation for why references encoded by signature aren't kept.
 impl From<Pcf> for Dmx {
     fn from(pcf: Pcf) -> Self {
         fn push_operators(
@@ -1462,7 +2540,7 @@ impl From<Pcf> for Dmx {
                     let mut attributes = attribute_map_to_dmx_map(operator.attributes);
                     attributes.insert(function_name_idx, string_to_cstring(operator.function_name).into());
 
-                    indices.push(ElementIdx::from(elements.len()));
+                    indices.push(element_idx(elements.len()));
                     elements.push(Element {
                         type_idx: particle_operator_idx,
                         name: string_to_cstring(operator.name),
@@ -1487,7 +2565,7 @@ impl From<Pcf> for Dmx {
         }
 
         let mut root_attributes = attribute_map_to_dmx_map(pcf.root.attributes);
-        let particle_system_definitions: Box<_> = (1..=pcf.root.particle_systems.len()).map(ElementIdx::from).collect();
+        let particle_system_definitions: Box<_> = (1..=pcf.root.particle_systems.len()).map(element_idx).collect();
 
         root_attributes.insert(
             pcf.symbols.particle_system_definitions,
@@ -1530,14 +2608,18 @@ impl From<Pcf> for Dmx {
                     .particle_child
                     .expect("particle child symbol idx not set despite having children in dmx");
                 for child in particle_system.children {
-                    child_indices.push(ElementIdx::from(elements.len()));
+                    child_indices.push(element_idx(elements.len()));
 
                     let mut attributes = attribute_map_to_dmx_map(child.attributes);
 
                     // child.child indexes into value.root.particle_systems, but we insert particle system defintiions
                     // into `elements` before any others, so this index is still correct in our new elements list
-                    // except we have to offset by 1 to account for the root element we add earlier.
-                    attributes.insert(child_idx, dmx::attribute::Attribute::Element(child.child + 1));
+                    // except we have to offset by 1 to account for the root element we add earlier. An invalid child
+                    // stays invalid.
+                    attributes.insert(
+                        child_idx,
+                        dmx::attribute::Attribute::Element(in_range(child.child.checked_offset(1))),
+                    );
 
                     elements.push(Element {
                         type_idx: particle_child_idx,
@@ -1590,13 +2672,19 @@ impl From<Pcf> for Dmx {
             push_index_attribute(operator_indices, pcf.symbols.operators, &mut new_attributes);
             push_index_attribute(renderer_indices, pcf.symbols.renderers, &mut new_attributes);
 
+            for (name_idx, passthrough) in particle_system.passthrough {
+                new_attributes.insert(name_idx, passthrough.push_into(&mut elements));
+            }
+
             elements[system_idx + 1].attributes = new_attributes;
         }
 
+        // every reference is re-encoded as an index, since the elements are rebuilt from scratch
         Self {
             version: pcf.version,
             strings: pcf.symbols.into(),
             elements,
+            signature_references: SignatureReferences::new(),
         }
     }
 }
@@ -1615,461 +2703,2258 @@ pub struct Child {
     pub attributes: AttributeMap,
 }
 
-#[derive(Debug, Clone, Default, PartialEq)]
-pub struct ParticleSystem {
-    pub name: String,
-    pub signature: Signature,
-    pub children: Box<[Child]>,
-    pub constraints: Box<[Operator]>,
-    pub emitters: Box<[Operator]>,
-    pub forces: Box<[Operator]>,
-    pub initializers: Box<[Operator]>,
-    pub operators: Box<[Operator]>,
-    pub renderers: Box<[Operator]>,
-    pub attributes: AttributeMap,
-}
-
-#[derive(Debug, Clone, PartialEq)]
-pub struct Operator {
-    pub name: String,
-    pub function_name: String,
-    pub signature: Signature,
-    pub attributes: AttributeMap,
-}
+impl Child {
+    /// Converts the DmeParticleChild at `element_idx`. `system_indices` maps each particle system's element index to
+    /// its index in the [`Pcf`]. Returns [`None`] if the child doesn't reference a system.
+    fn try_from(
+        elements: &[Element],
+        element_idx: ElementIdx,
+        symbols: &Symbols,
+        system_indices: &OrderMap<ElementIdx, ElementIdx>,
+    ) -> Result<Option<Self>, Error> {
+        let element = elements
+            .get(usize::from(element_idx))
+            .ok_or(Error::MissingParticleChild(element_idx))?;
+
+        if symbols.particle_child.is_none_or(|idx| element.type_idx != idx) {
+            return Err(Error::InvalidParticleChild(element_idx));
+        }
 
-impl Operator {
-    fn try_from(element: &Element, symbols: &Symbols) -> Result<Self, Error> {
-        let function_name = symbols
-            .function_name
+        let child_attribute = symbols
+            .child
             .and_then(|idx| element.attributes.get(&idx))
-            .ok_or(Error::MissingFunctionName)?;
-
-        let dmx::attribute::Attribute::String(function_name) = function_name else {
-            return Err(Error::MissingFunctionName);
+            .ok_or(Error::MissingChild)?;
+        let dmx::attribute::Attribute::Element(child_system_idx) = child_attribute else {
+            return Err(Error::MissingChild);
         };
 
-        let mut attributes: AttributeMap = OrderMap::new();
+        if !child_system_idx.is_valid() {
+            return Ok(None);
+        }
+
+        let child = *system_indices
+            .get(child_system_idx)
+            .ok_or(Error::MissingParticleSystem(*child_system_idx))?;
+
+        let mut attributes = OrderMap::new();
         for (name_idx, attribute) in &element.attributes {
-            if symbols.function_name.is_some_and(|idx| *name_idx == idx) {
+            if symbols.child.is_some_and(|idx| *name_idx == idx) {
                 continue;
             }
 
             attributes.insert(*name_idx, attribute.clone().try_into()?);
         }
 
-        Ok(Self {
+        Ok(Some(Self {
             name: element.name.to_string_lossy().into_owned(),
-            function_name: function_name.to_string_lossy().into_owned(),
             signature: element.signature,
+            child,
             attributes,
-        })
+        }))
     }
 }
 
-#[derive(Debug, Clone, PartialEq)]
-pub struct Symbols {
-    pub element: SymbolIdx,
-    pub particle_system_definitions: SymbolIdx,
-    pub particle_system_definition: SymbolIdx,
-    pub particle_child: Option<SymbolIdx>,
-    pub particle_operator: Option<SymbolIdx>,
-    pub function_name: Option<SymbolIdx>,
-    pub children: Option<SymbolIdx>,
-    pub constraints: Option<SymbolIdx>,
-    pub emitters: Option<SymbolIdx>,
-    pub forces: Option<SymbolIdx>,
-    pub initializers: Option<SymbolIdx>,
-    pub operators: Option<SymbolIdx>,
-    pub renderers: Option<SymbolIdx>,
-    pub child: Option<SymbolIdx>,
-    pub base: OrderSet<String>,
+/// One of a particle system's lists of operators. The engine runs the operators in each list in order, so their order
+/// within a list is significant & is kept through decoding, merging, stripping and encoding.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum OperatorList {
+    Constraints,
+    Emitters,
+    Forces,
+    Initializers,
+    Operators,
+    Renderers,
 }
 
-impl Symbols {
-    pub fn new_with_all_special() -> Self {
-        Self {
-            element: 0,
-            particle_system_definitions: 1,
-            particle_system_definition: 2,
-            particle_child: Some(3),
-            particle_operator: Some(4),
-            function_name: Some(5),
-            children: Some(6),
-            constraints: Some(7),
-            emitters: Some(8),
-            forces: Some(9),
-            initializers: Some(10),
-            operators: Some(11),
-            renderers: Some(12),
-            child: Some(13),
-            base: OrderSet::from([
-                "DmElement".to_string(),
-                "particleSystemDefinitions".to_string(),
-                "DmeParticleSystemDefinition".to_string(),
-                "DmeParticleChild".to_string(),
-                "DmeParticleOperator".to_string(),
-                "functionName".to_string(),
-                "children".to_string(),
-                "constraints".to_string(),
-                "emitters".to_string(),
-                "forces".to_string(),
-                "initializers".to_string(),
-                "operators".to_string(),
-                "renderers".to_string(),
-                "child".to_string(),
-            ]),
+impl OperatorList {
+    /// Every list, in the order they're encoded.
+    pub const ALL: [Self; 6] = [
+        Self::Constraints,
+        Self::Emitters,
+        Self::Forces,
+        Self::Initializers,
+        Self::Operators,
+        Self::Renderers,
+    ];
+
+    /// The name of the particle system attribute which holds this list.
+    pub fn attribute_name(self) -> &'static str {
+        match self {
+            OperatorList::Constraints => "constraints",
+            OperatorList::Emitters => "emitters",
+            OperatorList::Forces => "forces",
+            OperatorList::Initializers => "initializers",
+            OperatorList::Operators => "operators",
+            OperatorList::Renderers => "renderers",
         }
     }
 }
 
-impl Default for Symbols {
-    fn default() -> Self {
-        Self {
-            element: 0,
-            particle_system_definitions: 1,
-            particle_system_definition: 2,
-            particle_child: None,
-            particle_operator: None,
-            function_name: None,
-            children: None,
-            constraints: None,
-            emitters: None,
-            forces: None,
-            initializers: None,
-            operators: None,
-            renderers: None,
-            child: None,
-            base: OrderSet::from([
-                "DmElement".to_string(),
-                "particleSystemDefinitions".to_string(),
-                "DmeParticleSystemDefinition".to_string(),
-            ]),
-        }
+impl std::fmt::Display for OperatorList {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.write_str(self.attribute_name())
     }
 }
 
-impl TryFrom<dmx::Symbols> for Symbols {
-    type Error = Error;
+/// The element which holds an attribute of a particle system, see [`ParticleSystem::iter_attributes`].
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum AttributeOwner<'a> {
+    System,
+    Child(&'a Child),
+    Operator { list: OperatorList, operator: &'a Operator },
+}
 
-    fn try_from(base: dmx::Symbols) -> Result<Self, Self::Error> {
-        fn find_idx(from: &dmx::Symbols, value: &CStr) -> Option<SymbolIdx> {
-            from.iter()
-                .find_position(|el| *el == value)
-                .map(|(idx, _)| idx as SymbolIdx)
+impl<'a> AttributeOwner<'a> {
+    /// The operator which holds the attribute, or [`None`] if it's the system's or a child's.
+    pub fn operator(self) -> Option<&'a Operator> {
+        match self {
+            AttributeOwner::Operator { operator, .. } => Some(operator),
+            AttributeOwner::System | AttributeOwner::Child(_) => None,
         }
+    }
+}
 
-        let element = base
-            .iter()
-            .find_position(|el| *el == c"DmElement" || *el == c"DmeElement")
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct ParticleSystem {
+    pub name: String,
+    pub signature: Signature,
+    pub children: Box<[Child]>,
+    pub constraints: Box<[Operator]>,
+    pub emitters: Box<[Operator]>,
+    pub forces: Box<[Operator]>,
+    pub initializers: Box<[Operator]>,
+    pub operators: Box<[Operator]>,
+    pub renderers: Box<[Operator]>,
+    pub attributes: AttributeMap,
+
+    /// element attributes which aren't children or operators, kept verbatim. See [`crate::passthrough`].
+    pub passthrough: PassthroughMap,
+}
+
+impl ParticleSystem {
+    /// The operators in `list`, in the order they run.
+    pub fn operator_list(&self, list: OperatorList) -> &[Operator] {
+        match list {
+            OperatorList::Constraints => &self.constraints,
+            OperatorList::Emitters => &self.emitters,
+            OperatorList::Forces => &self.forces,
+            OperatorList::Initializers => &self.initializers,
+            OperatorList::Operators => &self.operators,
+            OperatorList::Renderers => &self.renderers,
+        }
+    }
+
+    fn operator_list_mut(&mut self, list: OperatorList) -> &mut Box<[Operator]> {
+        match list {
+            OperatorList::Constraints => &mut self.constraints,
+            OperatorList::Emitters => &mut self.emitters,
+            OperatorList::Forces => &mut self.forces,
+            OperatorList::Initializers => &mut self.initializers,
+            OperatorList::Operators => &mut self.operators,
+            OperatorList::Renderers => &mut self.renderers,
+        }
+    }
+
+    fn out_of_range(&self, list: OperatorList, index: usize) -> EditError {
+        EditError::OperatorOutOfRange {
+            system: self.name.clone(),
+            list,
+            index,
+            len: self.operator_list(list).len(),
+        }
+    }
+
+    /// Inserts `operator` into `list` at `index`, shifting the operators after it back. An `index` equal to the
+    /// list's length appends it.
+    ///
+    /// # Errors
+    ///
+    /// Returns [`EditError::OperatorOutOfRange`] if `index` is past the end of the list.
+    pub fn insert_operator(&mut self, list: OperatorList, index: usize, operator: Operator) -> Result<(), EditError> {
+        if index > self.operator_list(list).len() {
+            return Err(self.out_of_range(list, index));
+        }
+
+        let operators = self.operator_list_mut(list);
+        let mut vec = mem::take(operators).into_vec();
+        vec.insert(index, operator);
+        *operators = vec.into_boxed_slice();
+        Ok(())
+    }
+
+    /// Removes the operator at `index` in `list`, shifting the operators after it forward.
+    ///
+    /// # Errors
+    ///
+    /// Returns [`EditError::OperatorOutOfRange`] if there's no operator at `index`.
+    pub fn remove_operator(&mut self, list: OperatorList, index: usize) -> Result<Operator, EditError> {
+        if index >= self.operator_list(list).len() {
+            return Err(self.out_of_range(list, index));
+        }
+
+        let operators = self.operator_list_mut(list);
+        let mut vec = mem::take(operators).into_vec();
+        let operator = vec.remove(index);
+        *operators = vec.into_boxed_slice();
+        Ok(operator)
+    }
+
+    /// Moves the operator at `from` in `list` to `to`, keeping the order of the other operators. `to` is the operator's
+    /// position after the move, so moving the first of three operators to 2 makes it the last.
+    ///
+    /// # Errors
+    ///
+    /// Returns [`EditError::OperatorOutOfRange`] if `from` or `to` isn't in the list.
+    pub fn move_operator(&mut self, list: OperatorList, from: usize, to: usize) -> Result<(), EditError> {
+        let len = self.operator_list(list).len();
+        if let Some(index) = [from, to].into_iter().find(|index| *index >= len) {
+            return Err(self.out_of_range(list, index));
+        }
+
+        let operators = self.operator_list_mut(list);
+        if from < to {
+            operators[from..=to].rotate_left(1);
+        } else {
+            operators[to..=from].rotate_right(1);
+        }
+
+        Ok(())
+    }
+
+    /// Every attribute of this system, its children, and its operators, with what owns it and its name in `symbols`.
+    /// The system's own attributes come first, then its children's, then its operators' in the order of
+    /// [`OperatorList::ALL`]. Attributes whose name isn't in `symbols` are skipped.
+    pub fn iter_attributes<'a>(
+        &'a self,
+        symbols: &'a Symbols,
+    ) -> impl Iterator<Item = (AttributeOwner<'a>, &'a str, &'a Attribute)> {
+        let children = self
+            .children
+            .iter()
+            .map(|child| (AttributeOwner::Child(child), &child.attributes));
+        let operators = OperatorList::ALL.into_iter().flat_map(move |list| {
+            self.operator_list(list)
+                .iter()
+                .map(move |operator| (AttributeOwner::Operator { list, operator }, &operator.attributes))
+        });
+
+        // each name is looked up once, here, rather than by every caller
+        [(AttributeOwner::System, &self.attributes)]
+            .into_iter()
+            .chain(children)
+            .chain(operators)
+            .flat_map(move |(owner, attributes)| {
+                attributes.iter().filter_map(move |(name_idx, attribute)| {
+                    let name = symbols.base.get_index(usize::from(*name_idx))?;
+                    Some((owner, name.as_str(), attribute))
+                })
+            })
+    }
+
+    /// Every operator in this system, across all of its operator lists.
+    pub fn all_operators(&self) -> impl Iterator<Item = &Operator> {
+        self.constraints
+            .iter()
+            .chain(&self.emitters)
+            .chain(&self.forces)
+            .chain(&self.initializers)
+            .chain(&self.operators)
+            .chain(&self.renderers)
+    }
+
+    /// The number of bytes this system would add to an encoded [`Pcf`] containing it, including its children,
+    /// operators, and the definition of every symbol it references.
+    ///
+    /// Symbols are usually shared with other systems in the same [`Pcf`], so the sum of this across all systems will be
+    /// larger than the [`Pcf`]'s encoded size. See [`Pcf::system_sizes`] for sizes which share that cost.
+    pub fn encoded_size(&self, symbols: &Symbols) -> usize {
+        let symbols_size: usize = self
+            .referenced_symbols()
+            .into_iter()
+            .filter_map(|idx| symbols.base.get_index(idx as usize))
+            .map(|symbol| symbol.len() + 1)
+            .sum();
+
+        self.encoded_own_size() + symbols_size
+    }
+
+    /// The number of bytes this system's elements & attributes take up when encoded, including its entry in the root's
+    /// particle system definitions.
+    fn encoded_own_size(&self) -> usize {
+        self.encoded_elements_size() + self.encoded_attributes_size() + ElementIdx::ENCODED_SIZE
+    }
+
+    /// The size of the type idx, name, and signature of this system's element and each of its child & operator
+    /// elements.
+    fn encoded_elements_size(&self) -> usize {
+        let mut size = size_of::<u16>() + self.name.len() + 1 + size_of::<Signature>();
+        for child in &self.children {
+            size += size_of::<u16>() + child.name.len() + 1 + size_of::<Signature>();
+        }
+        for operator in &self.constraints {
+            size += size_of::<u16>() + operator.name.len() + 1 + size_of::<Signature>();
+        }
+        for operator in &self.emitters {
+            size += size_of::<u16>() + operator.name.len() + 1 + size_of::<Signature>();
+        }
+        for operator in &self.forces {
+            size += size_of::<u16>() + operator.name.len() + 1 + size_of::<Signature>();
+        }
+        for operator in &self.initializers {
+            size += size_of::<u16>() + operator.name.len() + 1 + size_of::<Signature>();
+        }
+        for operator in &self.operators {
+            size += size_of::<u16>() + operator.name.len() + 1 + size_of::<Signature>();
+        }
+        for operator in &self.renderers {
+            size += size_of::<u16>() + operator.name.len() + 1 + size_of::<Signature>();
+        }
+        for passthrough in self.passthrough.values() {
+            size += passthrough.encoded_elements_size();
+        }
+
+        size
+    }
+
+    /// The size of the attributes of this system's element and each of its child & operator elements.
+    fn encoded_attributes_size(&self) -> usize {
+        let mut size = size_of::<u32>();
+        for (_, attribute) in &self.attributes {
+            size += size_of::<SymbolIdx>();
+            size += size_of::<u8>();
+            size += attribute.get_encoded_size();
+        }
+
+        if !self.children.is_empty() {
+            size += size_of::<SymbolIdx>() + size_of::<u8>() + size_of::<u32>();
+            size += self.children.len() * ElementIdx::ENCODED_SIZE;
+        }
+
+        if !self.constraints.is_empty() {
+            size += size_of::<SymbolIdx>() + size_of::<u8>() + size_of::<u32>();
+            size += self.constraints.len() * ElementIdx::ENCODED_SIZE;
+        }
+
+        if !self.emitters.is_empty() {
+            size += size_of::<SymbolIdx>() + size_of::<u8>() + size_of::<u32>();
+            size += self.emitters.len() * ElementIdx::ENCODED_SIZE;
+        }
+
+        if !self.forces.is_empty() {
+            size += size_of::<SymbolIdx>() + size_of::<u8>() + size_of::<u32>();
+            size += self.forces.len() * ElementIdx::ENCODED_SIZE;
+        }
+
+        if !self.initializers.is_empty() {
+            size += size_of::<SymbolIdx>() + size_of::<u8>() + size_of::<u32>();
+            size += self.initializers.len() * ElementIdx::ENCODED_SIZE;
+        }
+
+        if !self.operators.is_empty() {
+            size += size_of::<SymbolIdx>() + size_of::<u8>() + size_of::<u32>();
+            size += self.operators.len() * ElementIdx::ENCODED_SIZE;
+        }
+
+        if !self.renderers.is_empty() {
+            size += size_of::<SymbolIdx>() + size_of::<u8>() + size_of::<u32>();
+            size += self.renderers.len() * ElementIdx::ENCODED_SIZE;
+        }
+
+        for child in &self.children {
+            size += size_of::<u32>();
+            // child.child will also become an attribute
+            size += size_of::<SymbolIdx>() + size_of::<u8>() + size_of::<u32>();
+            for (_, attribute) in &child.attributes {
+                size += size_of::<SymbolIdx>();
+                size += size_of::<u8>();
+                size += attribute.get_encoded_size();
+            }
+        }
+        for operator in &self.constraints {
+            size += size_of::<u32>();
+            // function name will also become an attribute
+            size += size_of::<SymbolIdx>() + size_of::<u8>() + 1 + operator.function_name.len();
+            for (_, attribute) in &operator.attributes {
+                size += size_of::<SymbolIdx>();
+                size += size_of::<u8>();
+                size += attribute.get_encoded_size();
+            }
+        }
+        for operator in &self.emitters {
+            size += size_of::<u32>();
+            size += size_of::<SymbolIdx>() + size_of::<u8>() + 1 + operator.function_name.len();
+            for (_, attribute) in &operator.attributes {
+                size += size_of::<SymbolIdx>();
+                size += size_of::<u8>();
+                size += attribute.get_encoded_size();
+            }
+        }
+        for operator in &self.forces {
+            size += size_of::<u32>();
+            size += size_of::<SymbolIdx>() + size_of::<u8>() + 1 + operator.function_name.len();
+            for (_, attribute) in &operator.attributes {
+                size += size_of::<SymbolIdx>();
+                size += size_of::<u8>();
+                size += attribute.get_encoded_size();
+            }
+        }
+        for operator in &self.initializers {
+            size += size_of::<u32>();
+            size += size_of::<SymbolIdx>() + size_of::<u8>() + 1 + operator.function_name.len();
+            for (_, attribute) in &operator.attributes {
+                size += size_of::<SymbolIdx>();
+                size += size_of::<u8>();
+                size += attribute.get_encoded_size();
+            }
+        }
+        for operator in &self.operators {
+            size += size_of::<u32>();
+            size += size_of::<SymbolIdx>() + size_of::<u8>() + 1 + operator.function_name.len();
+            for (_, attribute) in &operator.attributes {
+                size += size_of::<SymbolIdx>();
+                size += size_of::<u8>();
+                size += attribute.get_encoded_size();
+            }
+        }
+        for operator in &self.renderers {
+            size += size_of::<u32>();
+            size += size_of::<SymbolIdx>() + size_of::<u8>() + 1 + operator.function_name.len();
+            for (_, attribute) in &operator.attributes {
+                size += size_of::<SymbolIdx>();
+                size += size_of::<u8>();
+                size += attribute.get_encoded_size();
+            }
+        }
+        for passthrough in self.passthrough.values() {
+            size += passthrough.encoded_attributes_size();
+        }
+
+        size
+    }
+
+    /// The signatures of this system, its children, and its operators.
+    fn signatures(&self) -> impl Iterator<Item = &Signature> {
+        [&self.signature]
+            .into_iter()
+            .chain(self.children.iter().map(|child| &child.signature))
+            .chain(self.all_operators().map(|operator| &operator.signature))
+            .chain(self.passthrough.values().flat_map(Passthrough::signatures))
+    }
+
+    /// Gives this system, its children, and its operators new signatures, derived from the signature of the PCF it's
+    /// merged into and its index in that PCF. See [`derived_signature`].
+    fn regenerate_signatures(&mut self, root_signature: Signature, system_idx: ParticleSystemIdx) {
+        let mut hasher = DefaultHasher::new();
+        (root_signature, system_idx).hash(&mut hasher);
+        let seed = hasher.finish();
+
+        let mut ordinal = 0;
+        let mut next_signature = || {
+            ordinal += 1;
+            derived_signature(seed, ordinal)
+        };
+
+        self.signature = next_signature();
+        for child in &mut self.children {
+            child.signature = next_signature();
+        }
+
+        let operators = self
+            .constraints
+            .iter_mut()
+            .chain(self.emitters.iter_mut())
+            .chain(self.forces.iter_mut())
+            .chain(self.initializers.iter_mut())
+            .chain(self.operators.iter_mut())
+            .chain(self.renderers.iter_mut());
+
+        for operator in operators {
+            operator.signature = next_signature();
+        }
+
+        for signature in self.passthrough.values_mut().flat_map(Passthrough::signatures_mut) {
+            *signature = next_signature();
+        }
+    }
+
+    /// Replaces every symbol used as an attribute name by this system, its children, or its operators, and every
+    /// symbol used by its passthrough attributes, with `remap(symbol)`.
+    fn remap_symbols(&mut self, remap: impl Fn(SymbolIdx) -> SymbolIdx) {
+        let remap_attributes = |attributes: &mut AttributeMap| {
+            *attributes = mem::take(attributes)
+                .into_iter()
+                .map(|(name_idx, attribute)| (remap(name_idx), attribute))
+                .collect();
+        };
+
+        remap_attributes(&mut self.attributes);
+        for child in &mut self.children {
+            remap_attributes(&mut child.attributes);
+        }
+
+        let operators = self
+            .constraints
+            .iter_mut()
+            .chain(self.emitters.iter_mut())
+            .chain(self.forces.iter_mut())
+            .chain(self.initializers.iter_mut())
+            .chain(self.operators.iter_mut())
+            .chain(self.renderers.iter_mut());
+
+        for operator in operators {
+            remap_attributes(&mut operator.attributes);
+        }
+
+        self.passthrough = mem::take(&mut self.passthrough)
+            .into_iter()
+            .map(|(name_idx, mut passthrough)| {
+                passthrough.remap_symbols(&remap);
+                (remap(name_idx), passthrough)
+            })
+            .collect();
+    }
+
+    /// Every symbol used as an attribute name by this system, its children, or its operators, and every symbol used
+    /// by its passthrough attributes.
+    fn referenced_symbols(&self) -> HashSet<SymbolIdx> {
+        self.attributes
+            .keys()
+            .chain(self.children.iter().flat_map(|child| child.attributes.keys()))
+            .chain(self.all_operators().flat_map(|operator| operator.attributes.keys()))
+            .chain(self.passthrough.keys())
+            .copied()
+            .chain(self.passthrough.values().flat_map(Passthrough::referenced_symbols))
+            .collect()
+    }
+}
+
+/// Whether the attribute `name_idx` matches its default in `defaults`, and so is stripped. Stripped attributes are
+/// counted in `removed`.
+fn is_removed_default(
+    name_idx: SymbolIdx,
+    attribute: &Attribute,
+    defaults: &HashMap<SymbolIdx, &Attribute>,
+    comparison: Comparison,
+    removed: &mut HashMap<SymbolIdx, usize>,
+) -> bool {
+    if let Some(default) = defaults.get(&name_idx)
+        && comparison.matches(attribute, default)
+    {
+        *removed.entry(name_idx).or_default() += 1;
+        true
+    } else {
+        false
+    }
+}
+
+/// Finds the only operator in `operators` named `name`, or failing that, the only operator whose function name is
+/// `name`. `names` gets an operator's `(name, function_name)`.
+fn find_operator<T>(
+    operators: Vec<T>,
+    system: &str,
+    name: &str,
+    names: impl Fn(&T) -> (&String, &String),
+) -> Result<T, EditError> {
+    let (by_name, others): (Vec<_>, Vec<_>) = operators.into_iter().partition(|operator| names(operator).0 == name);
+    let mut matches = if by_name.is_empty() {
+        others
+            .into_iter()
+            .filter(|operator| names(operator).1 == name)
+            .collect()
+    } else {
+        by_name
+    };
+
+    match matches.len() {
+        0 => Err(EditError::UnknownOperator {
+            system: system.to_string(),
+            operator: name.to_string(),
+        }),
+        1 => Ok(matches.pop().unwrap()),
+        _ => Err(EditError::AmbiguousOperator {
+            system: system.to_string(),
+            operator: name.to_string(),
+        }),
+    }
+}
+
+/// A version 8 UUID derived from `seed` & `ordinal`. Valve's tools use random UUIDs for element signatures, but merged
+/// elements are given derived ones so that merging the same PCFs always produces the same bytes.
+fn derived_signature(seed: u64, ordinal: usize) -> Signature {
+    let [low, high] = [0u8, 1].map(|half| {
+        let mut hasher = DefaultHasher::new();
+        (seed, ordinal, half).hash(&mut hasher);
+        hasher.finish()
+    });
+
+    let bytes = (u128::from(high) << 64 | u128::from(low)).to_le_bytes();
+    *Builder::from_custom_bytes(bytes).into_uuid().as_bytes()
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct Operator {
+    pub name: String,
+    pub function_name: String,
+    pub signature: Signature,
+    pub attributes: AttributeMap,
+}
+
+impl Operator {
+    fn try_from(element: &Element, symbols: &Symbols) -> Result<Self, Error> {
+        let function_name = symbols
+            .function_name
+            .and_then(|idx| element.attributes.get(&idx))
+            .ok_or(Error::MissingFunctionName)?;
+
+        let dmx::attribute::Attribute::String(function_name) = function_name else {
+            return Err(Error::MissingFunctionName);
+        };
+
+        let mut attributes: AttributeMap = OrderMap::new();
+        for (name_idx, attribute) in &element.attributes {
+            if symbols.function_name.is_some_and(|idx| *name_idx == idx) {
+                continue;
+            }
+
+            attributes.insert(*name_idx, attribute.clone().try_into()?);
+        }
+
+        Ok(Self {
+            name: element.name.to_string_lossy().into_owned(),
+            function_name: function_name.to_string_lossy().into_owned(),
+            signature: element.signature,
+            attributes,
+        })
+    }
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct Symbols {
+    pub element: SymbolIdx,
+    pub particle_system_definitions: SymbolIdx,
+    pub particle_system_definition: SymbolIdx,
+    pub particle_child: Option<SymbolIdx>,
+    pub particle_operator: Option<SymbolIdx>,
+    pub function_name: Option<SymbolIdx>,
+    pub children: Option<SymbolIdx>,
+    pub constraints: Option<SymbolIdx>,
+    pub emitters: Option<SymbolIdx>,
+    pub forces: Option<SymbolIdx>,
+    pub initializers: Option<SymbolIdx>,
+    pub operators: Option<SymbolIdx>,
+    pub renderers: Option<SymbolIdx>,
+    pub child: Option<SymbolIdx>,
+    pub base: OrderSet<Symbol>,
+}
+
+impl Symbols {
+    /// Adds the symbols needed to encode operators in `list`, if they're missing.
+    fn insert_operator_list(&mut self, list: OperatorList) {
+        fn insert(base: &mut OrderSet<Symbol>, symbol: &mut Option<SymbolIdx>, value: &str) {
+            if symbol.is_none() {
+                *symbol = Some(base.insert_full(value.into()).0 as SymbolIdx);
+            }
+        }
+
+        insert(&mut self.base, &mut self.particle_operator, "DmeParticleOperator");
+        insert(&mut self.base, &mut self.function_name, "functionName");
+        let symbol = match list {
+            OperatorList::Constraints => &mut self.constraints,
+            OperatorList::Emitters => &mut self.emitters,
+            OperatorList::Forces => &mut self.forces,
+            OperatorList::Initializers => &mut self.initializers,
+            OperatorList::Operators => &mut self.operators,
+            OperatorList::Renderers => &mut self.renderers,
+        };
+        insert(&mut self.base, symbol, list.attribute_name());
+    }
+
+    /// Adds the symbols needed to encode children, if they're missing.
+    fn insert_children(&mut self) {
+        for (symbol, value) in [
+            (&mut self.particle_child, "DmeParticleChild"),
+            (&mut self.children, "children"),
+            (&mut self.child, "child"),
+        ] {
+            if symbol.is_none() {
+                *symbol = Some(self.base.insert_full(value.into()).0 as SymbolIdx);
+            }
+        }
+    }
+
+    pub fn new_with_all_special() -> Self {
+        Self {
+            element: 0,
+            particle_system_definitions: 1,
+            particle_system_definition: 2,
+            particle_child: Some(3),
+            particle_operator: Some(4),
+            function_name: Some(5),
+            children: Some(6),
+            constraints: Some(7),
+            emitters: Some(8),
+            forces: Some(9),
+            initializers: Some(10),
+            operators: Some(11),
+            renderers: Some(12),
+            child: Some(13),
+            base: OrderSet::from([
+                "DmElement".into(),
+                "particleSystemDefinitions".into(),
+                "DmeParticleSystemDefinition".into(),
+                "DmeParticleChild".into(),
+                "DmeParticleOperator".into(),
+                "functionName".into(),
+                "children".into(),
+                "constraints".into(),
+                "emitters".into(),
+                "forces".into(),
+                "initializers".into(),
+                "operators".into(),
+                "renderers".into(),
+                "child".into(),
+            ]),
+        }
+    }
+}
+
+impl Default for Symbols {
+    fn default() -> Self {
+        Self {
+            element: 0,
+            particle_system_definitions: 1,
+            particle_system_definition: 2,
+            particle_child: None,
+            particle_operator: None,
+            function_name: None,
+            children: None,
+            constraints: None,
+            emitters: None,
+            forces: None,
+            initializers: None,
+            operators: None,
+            renderers: None,
+            child: None,
+            base: OrderSet::from([
+                "DmElement".into(),
+                "particleSystemDefinitions".into(),
+                "DmeParticleSystemDefinition".into(),
+            ]),
+        }
+    }
+}
+
+impl TryFrom<dmx::Symbols> for Symbols {
+    type Error = Error;
+
+    fn try_from(base: dmx::Symbols) -> Result<Self, Self::Error> {
+        fn find_idx(from: &dmx::Symbols, value: &CStr) -> Option<SymbolIdx> {
+            from.iter()
+                .find_position(|el| *el == value)
+                .map(|(idx, _)| idx as SymbolIdx)
+        }
+
+        let element = base
+            .iter()
+            .find_position(|el| *el == c"DmElement" || *el == c"DmeElement")
             .ok_or(Error::MissingDatamodelElementString)?
             .0 as SymbolIdx;
 
-        let particle_system_definitions = base
-            .iter()
-            .find_position(|el| *el == c"particleSystemDefinitions")
-            .ok_or(Error::MissingRootDefinitionString)?
-            .0 as SymbolIdx;
+        let particle_system_definitions = base
+            .iter()
+            .find_position(|el| *el == c"particleSystemDefinitions")
+            .ok_or(Error::MissingRootDefinitionString)?
+            .0 as SymbolIdx;
+
+        let particle_system_definition = base
+            .iter()
+            .find_position(|el| *el == c"DmeParticleSystemDefinition")
+            .ok_or(Error::MissingSystemDefinitionString)?
+            .0 as SymbolIdx;
+
+        let particle_child = find_idx(&base, c"DmeParticleChild");
+        let particle_operator = find_idx(&base, c"DmeParticleOperator");
+        let function_name = find_idx(&base, c"functionName");
+        let children = find_idx(&base, c"children");
+        let constraints = find_idx(&base, c"constraints");
+        let emitters = find_idx(&base, c"emitters");
+        let forces = find_idx(&base, c"forces");
+        let initializers = find_idx(&base, c"initializers");
+        let operators = find_idx(&base, c"operators");
+        let renderers = find_idx(&base, c"renderers");
+        let child = find_idx(&base, c"child");
+
+        let base = base
+            .into_iter()
+            .map(|string| Symbol::from(&*string.to_string_lossy()))
+            .collect();
+
+        Ok(Self {
+            element,
+            particle_system_definitions,
+            particle_system_definition,
+            particle_child,
+            particle_operator,
+            function_name,
+            children,
+            constraints,
+            emitters,
+            forces,
+            initializers,
+            operators,
+            renderers,
+            child,
+            base,
+        })
+    }
+}
+
+impl From<Symbols> for dmx::Symbols {
+    fn from(value: Symbols) -> Self {
+        value
+            .base
+            .into_iter()
+            .map(|string| {
+                let mut vec = string.as_bytes().to_vec();
+                vec.push(0);
+                CString::from_vec_with_nul(vec).expect("this should never fail")
+            })
+            .collect()
+    }
+}
+
+#[cfg(test)]
+mod symbol_stripping_tests {
+    use dmx::dmx::Version;
+    use ordermap::OrderMap;
+
+    use crate::{
+        ParticleSystem, Pcf, Root,
+        new::{Child, Operator, Symbols},
+        passthrough::PassthroughMap,
+    };
+
+    #[test]
+    fn never_strips_required_symbols() {
+        let pcf = Pcf {
+            version: Version::Binary2Pcf1,
+            symbols: Symbols::default(),
+            root: Root::default(),
+            encoded_size: 0,
+        }
+        .unused_symbols_stripped();
+
+        assert_eq!(
+            Symbols::default().base,
+            pcf.symbols.base,
+            "the required symbols (left) were stripped in the resulting pcf (right)"
+        );
+    }
+
+    #[test]
+    fn strips_all_unused_symbols() {
+        let mut symbols = Symbols::new_with_all_special();
+        symbols.base.insert("test1".into());
+        symbols.base.insert("test2".into());
+        symbols.base.insert("test3".into());
+
+        let pcf = Pcf {
+            version: Version::Binary2Pcf1,
+            symbols,
+            root: Root::default(),
+            encoded_size: 0,
+        }
+        .unused_symbols_stripped();
+
+        assert!(
+            !pcf.symbols.base.contains("test1"),
+            "the unused symbols were not stripped from the pcf"
+        );
+        assert!(
+            !pcf.symbols.base.contains("test2"),
+            "the unused symbols were not stripped from the pcf"
+        );
+        assert!(
+            !pcf.symbols.base.contains("test3"),
+            "the unused symbols were not stripped from the pcf"
+        );
+        assert_eq!(Symbols::default(), pcf.symbols);
+    }
+
+    #[test]
+    fn doesnt_strip_used_symbols() {
+        fn empty_operator(name: &str, function_name: &str) -> Operator {
+            Operator {
+                name: name.to_string(),
+                function_name: function_name.to_string(),
+                signature: [2; 16],
+                attributes: OrderMap::new(),
+            }
+        }
+
+        let pcf = Pcf {
+            version: Version::Binary2Pcf1,
+            symbols: Symbols::new_with_all_special(),
+            root: Root {
+                name: "untitled".to_string(),
+                signature: [0; 16],
+                particle_systems: Box::from([ParticleSystem {
+                    name: "parent".to_string(),
+                    signature: [1; 16],
+                    children: Vec::new().into_boxed_slice(),
+                    constraints: Box::from([empty_operator("constraint", "constrain function")]) as Box<[Operator]>,
+                    emitters: Box::from([empty_operator("emitter", "emit function")]) as Box<[Operator]>,
+                    forces: Box::from([empty_operator("force", "force function")]) as Box<[Operator]>,
+                    initializers: Box::from([empty_operator("initializer", "initialize function")]) as Box<[Operator]>,
+                    operators: Box::from([empty_operator("operator", "operate function")]) as Box<[Operator]>,
+                    renderers: Box::from([empty_operator("renderer", "render function")]) as Box<[Operator]>,
+                    attributes: OrderMap::new(),
+                    passthrough: PassthroughMap::new(),
+                }]) as Box<[ParticleSystem]>,
+                attributes: OrderMap::new(),
+            },
+            encoded_size: 0,
+        }
+        .unused_symbols_stripped();
+
+        assert!(!pcf.symbols.base.contains("DmeParticleChild"));
+        assert!(!pcf.symbols.base.contains("child"));
+        assert!(!pcf.symbols.base.contains("children"));
+    }
+}
+
+#[cfg(test)]
+mod graph_tests {
+    use bytes::Buf;
+    use dmx::{Dmx, dmx::Version};
+    use ordermap::OrderMap;
+
+    use crate::{
+        ParticleSystem, Pcf, Root,
+        new::{Child, Symbols},
+    };
+
+    #[test]
+    fn creates_single_connected_component() {
+        let pcf = Pcf {
+            version: Version::Binary2Pcf1,
+            symbols: Symbols::new_with_all_special(),
+            root: Root {
+                name: "untitled".to_string(),
+                signature: [0; 16],
+                particle_systems: Box::from([
+                    ParticleSystem {
+                        name: "parent".to_string(),
+                        signature: [0; 16],
+                        children: Box::from([Child {
+                            name: "parent_child".to_string(),
+                            signature: [0; 16],
+                            child: 1u32.into(),
+                            attributes: OrderMap::new(),
+                        }]) as Box<[Child]>,
+                        ..ParticleSystem::default()
+                    },
+                    ParticleSystem {
+                        name: "daughter".to_string(),
+                        signature: [0; 16],
+                        ..ParticleSystem::default()
+                    },
+                    ParticleSystem {
+                        name: "step parent".to_string(),
+                        signature: [0; 16],
+                        children: Box::from([
+                            Child {
+                                name: "parent_child".to_string(),
+                                signature: [0; 16],
+                                child: 1u32.into(),
+                                attributes: OrderMap::new(),
+                            },
+                            Child {
+                                name: "parent_child".to_string(),
+                                signature: [0; 16],
+                                child: 3u32.into(),
+                                attributes: OrderMap::new(),
+                            },
+                        ]) as Box<[Child]>,
+                        ..ParticleSystem::default()
+                    },
+                    ParticleSystem {
+                        name: "step daughter".to_string(),
+                        signature: [0; 16],
+                        ..ParticleSystem::default()
+                    },
+                ]) as Box<[ParticleSystem]>,
+                attributes: OrderMap::new(),
+            },
+            encoded_size: 0,
+        };
+
+        let graph = pcf.into_connected();
+        assert_eq!(1, graph.len());
+    }
+
+    #[test]
+    fn creates_two_connected_components() {
+        let pcf = Pcf {
+            version: Version::Binary2Pcf1,
+            symbols: Symbols::new_with_all_special(),
+            root: Root {
+                name: "untitled".to_string(),
+                signature: [0; 16],
+                particle_systems: Box::from([
+                    ParticleSystem {
+                        name: "parent".to_string(),
+                        signature: [0; 16],
+                        children: Box::from([Child {
+                            name: "child daughter".to_string(),
+                            signature: [0; 16],
+                            child: 1u32.into(),
+                            attributes: OrderMap::new(),
+                        }]) as Box<[Child]>,
+                        ..ParticleSystem::default()
+                    },
+                    ParticleSystem {
+                        name: "daughter".to_string(),
+                        signature: [0; 16],
+                        ..ParticleSystem::default()
+                    },
+                    ParticleSystem {
+                        name: "parent's sister".to_string(),
+                        signature: [0; 16],
+                        children: Box::from([Child {
+                            name: "child cousin".to_string(),
+                            signature: [0; 16],
+                            child: 3u32.into(),
+                            attributes: OrderMap::new(),
+                        }]) as Box<[Child]>,
+                        ..ParticleSystem::default()
+                    },
+                    ParticleSystem {
+                        name: "daughter's cousin".to_string(),
+                        signature: [0; 16],
+                        ..ParticleSystem::default()
+                    },
+                ]) as Box<[ParticleSystem]>,
+                attributes: OrderMap::new(),
+            },
+            encoded_size: 0,
+        };
+
+        let mut graph = pcf.into_connected();
+        assert_eq!(2, graph.len());
+
+        let parent = graph.pop().unwrap();
+        let step_parent = graph.pop().unwrap();
+
+        assert_eq!("untitled", parent.root.name);
+        assert_eq!(2, parent.root.particle_systems.len());
+        assert_eq!("parent", &parent.root.particle_systems[0].name);
+        assert_eq!("daughter", &parent.root.particle_systems[1].name);
+
+        assert_eq!("untitled", step_parent.root.name);
+        assert_eq!(2, step_parent.root.particle_systems.len());
+        assert_eq!("parent's sister", &step_parent.root.particle_systems[0].name);
+        assert_eq!("daughter's cousin", &step_parent.root.particle_systems[1].name);
+    }
+
+    const TEST_PCF_DATA: &[u8] = include_bytes!("test/medicgun_beam.pcf");
+
+    #[test]
+    fn connected_components_can_be_reparsed_as_valid_pcf() {
+        let mut reader = TEST_PCF_DATA.reader();
+        let dmx = dmx::decode(&mut reader).unwrap();
+        let pcf: Pcf = dmx.try_into().unwrap();
+        for (idx, pcf) in pcf.into_connected().into_iter().enumerate() {
+            let dmx: Dmx = pcf.into();
+            let _: Pcf = dmx.try_into().unwrap();
+        }
+    }
+}
+
+#[cfg(test)]
+mod merge_policy_tests {
+    use ordermap::OrderMap;
+
+    use crate::{
+        Attribute, Pcf,
+        new::{MAX_SYMBOLS, MergeError, MergePolicy, MergeReport, SymbolIdx},
+        symbol::{Symbol, SymbolPool},
+        test_util::pcf_with_systems,
+    };
+
+    fn names(pcf: &Pcf) -> Vec<&str> {
+        pcf.particle_systems()
+            .iter()
+            .map(|system| system.name.as_str())
+            .collect()
+    }
+
+    fn child_indices(pcf: &Pcf, system_idx: usize) -> Vec<usize> {
+        pcf.particle_systems()[system_idx]
+            .children
+            .iter()
+            .map(|child| child.child.into())
+            .collect()
+    }
+
+    #[test]
+    fn keep_self_drops_incoming_duplicate_and_redirects_children() {
+        let into = pcf_with_systems(&[("shared", &[]), ("into_only", &[])]);
+        let from = pcf_with_systems(&[("from_parent", &[1]), ("shared", &[])]);
+
+        let (pcf, report) = into.merged_with_policy(from, MergePolicy::KeepSelf).unwrap();
+
+        assert_eq!(names(&pcf), ["shared", "into_only", "from_parent"]);
+        assert_eq!(child_indices(&pcf, 2), [0]);
+        assert_eq!(report.kept_self, ["shared"]);
+        assert!(report.replaced.is_empty() && report.renamed.is_empty());
+    }
+
+    #[test]
+    fn keep_other_replaces_existing_system_in_place() {
+        let into = pcf_with_systems(&[("into_parent", &[1]), ("shared", &[])]);
+        let from = pcf_with_systems(&[("shared", &[1]), ("from_only", &[])]);
+
+        let (pcf, report) = into.merged_with_policy(from, MergePolicy::KeepOther).unwrap();
+
+        assert_eq!(names(&pcf), ["into_parent", "shared", "from_only"]);
+        assert_eq!(child_indices(&pcf, 0), [1]);
+        assert_eq!(child_indices(&pcf, 1), [2]);
+        assert_eq!(report.replaced, ["shared"]);
+    }
+
+    #[test]
+    fn rename_incoming_appends_unique_names() {
+        let into = pcf_with_systems(&[("shared", &[]), ("shared_1", &[])]);
+        let from = pcf_with_systems(&[("shared", &[])]);
+
+        let (pcf, report) = into.merged_with_policy(from, MergePolicy::RenameIncoming).unwrap();
+
+        assert_eq!(names(&pcf), ["shared", "shared_1", "shared_2"]);
+        assert_eq!(report.renamed, [("shared".to_string(), "shared_2".to_string())]);
+    }
+
+    #[test]
+    fn error_policy_fails_on_duplicate() {
+        let into = pcf_with_systems(&[("shared", &[])]);
+        let from = pcf_with_systems(&[("shared", &[])]);
+
+        let result = into.merged_with_policy(from, MergePolicy::Error);
+        assert!(matches!(result, Err(MergeError::DuplicateParticleSystem(name)) if name == "shared"));
+    }
+
+    #[test]
+    fn merged_without_policy_keeps_duplicates() {
+        let into = pcf_with_systems(&[("shared", &[])]);
+        let from = pcf_with_systems(&[("shared", &[0])]);
+
+        let (pcf, report) = into
+            .clone()
+            .merged_with_policy(pcf_with_systems(&[("other", &[])]), MergePolicy::Error)
+            .unwrap();
+        assert_eq!(report, MergeReport::default());
+        assert_eq!(names(&pcf), ["shared", "other"]);
+
+        let pcf = into.merged(from).unwrap();
+        assert_eq!(names(&pcf), ["shared", "shared"]);
+        assert_eq!(child_indices(&pcf, 1), [1]);
+    }
+
+    #[test]
+    fn merging_fails_if_a_child_is_out_of_range() {
+        let result = pcf_with_systems(&[("into", &[])]).merged(pcf_with_systems(&[("from", &[1])]));
+        assert!(matches!(
+            result,
+            Err(MergeError::ChildOutOfRange { system, child }) if system == "from" && usize::from(child) == 1
+        ));
+    }
+
+    #[test]
+    fn merging_fails_if_a_symbol_is_unknown() {
+        let mut from = pcf_with_systems(&[("from", &[])]);
+        let unknown = from.symbols.base.len() as SymbolIdx;
+        from.root.particle_systems[0]
+            .attributes
+            .insert(unknown, Attribute::from(1.0));
+
+        let result = pcf_with_systems(&[("into", &[])]).merged(from);
+        assert!(matches!(
+            result,
+            Err(MergeError::UnknownSymbol { element, symbol }) if element == "from" && symbol == unknown
+        ));
+    }
+
+    #[test]
+    fn merging_fails_if_the_symbols_overflow() {
+        let with_symbols = |prefix: &str| {
+            let mut pcf = pcf_with_systems(&[]);
+            pcf.symbols
+                .base
+                .extend((0..MAX_SYMBOLS / 2).map(|idx| Symbol::from(format!("{prefix}{idx}"))));
+            pcf
+        };
+
+        let result = with_symbols("a").merged(with_symbols("b"));
+        assert!(matches!(result, Err(MergeError::TooManySymbols(len)) if len > MAX_SYMBOLS));
+    }
+
+    #[test]
+    fn merging_reindexes_attributes_of_differently_ordered_symbols() {
+        let with_attributes = |system: &str, names: &[&str]| {
+            let mut pcf = pcf_with_systems(&[(system, &[])]);
+            pcf.symbols.base.extend(names.iter().map(|&name| Symbol::from(name)));
+            let attributes = ["radius", "alpha"]
+                .into_iter()
+                .enumerate()
+                .map(|(value, name)| {
+                    let name_idx = pcf.symbols.base.get_index_of(name).unwrap() as SymbolIdx;
+                    (name_idx, Attribute::Integer(value as i32))
+                })
+                .collect();
+            pcf.root.particle_systems[0].attributes = attributes;
+            pcf
+        };
+
+        let into = with_attributes("into", &["radius", "alpha"]);
+        let from = with_attributes("from", &["alpha", "radius"]);
+        let pcf = into.merged(from).unwrap();
+
+        for system in pcf.particle_systems() {
+            let attributes: Vec<_> = system
+                .attributes
+                .iter()
+                .map(|(name_idx, value)| (pcf.symbols.base[usize::from(*name_idx)].as_str(), value.clone()))
+                .collect();
+            assert_eq!(
+                attributes,
+                [("radius", Attribute::Integer(0)), ("alpha", Attribute::Integer(1))],
+                "{}'s attributes should keep their names",
+                system.name
+            );
+        }
+    }
+
+    #[test]
+    fn merging_interned_pcfs_keeps_their_shared_symbols() {
+        let pool = SymbolPool::new();
+        let with_symbols = |system: &str, names: &[&str]| {
+            let mut pcf = pcf_with_systems(&[(system, &[])]);
+            pcf.symbols.base.extend(names.iter().map(|&name| Symbol::from(name)));
+            let radius = pcf.symbols.base.get_index_of("radius").unwrap() as SymbolIdx;
+            pcf.root.particle_systems[0].attributes = OrderMap::from([(radius, Attribute::Integer(1))]);
+            pcf.intern_symbols(&pool);
+            pcf
+        };
+
+        let pcf = with_symbols("first", &["radius", "alpha"])
+            .merged(with_symbols("second", &["radius", "alpha"]))
+            .unwrap()
+            .merged(with_symbols("third", &["alpha", "radius"]))
+            .unwrap();
+
+        let radius = pcf.symbols.base.get_index_of("radius").unwrap();
+        assert!(pcf.symbols.base[radius].ptr_eq(&pool.intern(&"radius".into())));
+        for system in pcf.particle_systems() {
+            assert_eq!(
+                system.attributes.keys().collect::<Vec<_>>(),
+                [&(radius as SymbolIdx)],
+                "{}'s attributes should keep their names",
+                system.name
+            );
+        }
+    }
+}
+
+#[cfg(test)]
+mod ordering_tests {
+    use crate::{Pcf, new::OrderError, test_util::pcf_with_systems};
+
+    fn names_and_children(pcf: &Pcf) -> Vec<(&str, Vec<usize>)> {
+        pcf.particle_systems()
+            .iter()
+            .map(|system| {
+                (
+                    system.name.as_str(),
+                    system.children.iter().map(|child| child.child.into()).collect(),
+                )
+            })
+            .collect()
+    }
+
+    #[test]
+    fn parents_come_before_children() {
+        let pcf = pcf_with_systems(&[
+            ("grandchild", &[]),
+            ("child", &[0]),
+            ("unrelated", &[]),
+            ("parent", &[1]),
+        ]);
+
+        let pcf = pcf.topologically_sorted(true).unwrap();
+
+        assert_eq!(
+            names_and_children(&pcf),
+            [
+                ("unrelated", vec![]),
+                ("parent", vec![2]),
+                ("child", vec![3]),
+                ("grandchild", vec![]),
+            ]
+        );
+    }
+
+    #[test]
+    fn already_sorted_systems_keep_their_order() {
+        let pcf = pcf_with_systems(&[("parent", &[2]), ("other", &[]), ("child", &[])]);
+
+        let sorted = pcf.clone().topologically_sorted(true).unwrap();
+
+        assert_eq!(pcf, sorted);
+    }
+
+    #[test]
+    fn cycles_are_denied_when_validating() {
+        let pcf = pcf_with_systems(&[("a", &[1]), ("b", &[0]), ("c", &[])]);
+
+        let result = pcf.topologically_sorted(true);
+
+        assert!(matches!(result, Err(OrderError::Cycle(names)) if names == ["a", "b"]));
+    }
+
+    #[test]
+    fn cycles_are_placed_last_when_not_validating() {
+        let pcf = pcf_with_systems(&[("a", &[1]), ("b", &[0]), ("c", &[])]);
+
+        let pcf = pcf.topologically_sorted(false).unwrap();
+
+        assert_eq!(
+            names_and_children(&pcf),
+            [("c", vec![]), ("a", vec![2]), ("b", vec![1])]
+        );
+    }
+
+    #[test]
+    fn reordering_systems_updates_their_children() {
+        let (version, symbols, mut root) = pcf_with_systems(&[("a", &[2]), ("b", &[0]), ("c", &[])]).into_parts();
+
+        root.reorder_systems(&[2, 0, 1]).unwrap();
+
+        let pcf = Pcf::new(version, symbols, root);
+        assert_eq!(
+            names_and_children(&pcf),
+            [("c", vec![]), ("a", vec![0]), ("b", vec![1])]
+        );
+    }
+
+    #[test]
+    fn reordering_needs_every_system_once() {
+        let (_, _, mut root) = pcf_with_systems(&[("a", &[]), ("b", &[])]).into_parts();
+        let unchanged = root.clone();
+
+        for ordering in [&[0][..], &[0, 0], &[0, 1, 2], &[1, 2]] {
+            let result = root.reorder_systems(ordering);
+            assert!(
+                matches!(result, Err(OrderError::NotAPermutation(_, 2))),
+                "{ordering:?} should be rejected"
+            );
+            assert_eq!(root, unchanged);
+        }
+    }
+
+    #[test]
+    fn sorting_systems_is_stable() {
+        let mut pcf = pcf_with_systems(&[("b_first", &[2]), ("a", &[]), ("b_second", &[1])]);
+        let encoded_size = pcf.encoded_size();
+
+        pcf.sort_systems_by(|a, b| a.name[..1].cmp(&b.name[..1]));
+
+        assert_eq!(
+            names_and_children(&pcf),
+            [("a", vec![]), ("b_first", vec![2]), ("b_second", vec![0])]
+        );
+        assert_eq!(pcf.encoded_size(), encoded_size);
+    }
+}
+
+#[cfg(test)]
+mod size_tests {
+    use bytes::{BufMut, BytesMut};
+    use dmx::Dmx;
+    use ordermap::{OrderMap, OrderSet};
+
+    use crate::{
+        Attribute, ParticleSystem, Pcf,
+        new::{Operator, Symbols},
+        test_util::{PcfBuilder, operator, system},
+    };
+
+    fn test_pcf() -> Pcf {
+        let mut builder = PcfBuilder::default();
+        let radius = builder.symbol("radius");
+        let color = builder.symbol("color");
+        builder.symbol("never referenced");
+
+        let mut parent = system("parent", &[1]);
+        parent.children[0].attributes = OrderMap::from([(color, Attribute::Float(1.0.into()))]);
+        parent.renderers = Box::from([Operator {
+            attributes: OrderMap::from([(radius, Attribute::Float(2.0.into()))]),
+            ..operator("render", "render_animated_sprites")
+        }]);
+        parent.attributes = OrderMap::from([(radius, Attribute::Integer(3))]);
+
+        builder
+            .system(parent)
+            .system(ParticleSystem {
+                name: "child".to_string(),
+                attributes: OrderMap::from([(radius, Attribute::Bool(true))]),
+                ..ParticleSystem::default()
+            })
+            .system(system("empty", &[]))
+            .build()
+    }
+
+    #[test]
+    fn stripping_editor_names_shrinks_encoded_size() {
+        let pcf = test_pcf();
+        let size = pcf.encoded_size();
+
+        let pcf = pcf.editor_names_stripped();
+        let system = &pcf.particle_systems()[0];
+        assert!(system.children[0].name.is_empty());
+        assert!(system.renderers[0].name.is_empty());
+        assert_eq!(system.name, "parent");
+        assert_eq!(pcf.encoded_size(), size - "parent_child".len() - "render".len());
+    }
+
+    #[test]
+    fn system_sizes_add_up_to_encoded_size() {
+        let pcf = test_pcf();
+
+        let sizes = pcf.system_sizes();
+
+        assert_eq!(sizes.len(), 3);
+        assert_eq!(sizes.iter().sum::<usize>(), pcf.encoded_size());
+    }
+
+    #[test]
+    fn system_sizes_add_up_to_size_of_encoded_dmx() {
+        let pcf = test_pcf();
+        let sizes = pcf.system_sizes();
+
+        let dmx: Dmx = pcf.into();
+        let mut writer = BytesMut::new().writer();
+        dmx.encode(&mut writer).expect("writing failed");
+
+        assert_eq!(sizes.iter().sum::<usize>(), writer.get_ref().len());
+    }
+
+    #[test]
+    fn encoded_size_matches_encoded_size_of_dmx() {
+        let pcf = test_pcf();
+        let size = pcf.encoded_size();
+
+        let dmx: Dmx = pcf.into();
+        assert_eq!(dmx.encoded_size(), size);
+        assert_eq!(dmx.encode_to_vec().len(), size);
+    }
+
+    #[test]
+    fn larger_systems_have_larger_sizes() {
+        let pcf = test_pcf();
+
+        let sizes = pcf.system_sizes();
+
+        assert!(sizes[0] > sizes[1]);
+        assert!(sizes[1] > sizes[2]);
+    }
+
+    #[test]
+    fn standalone_size_includes_referenced_symbols() {
+        let pcf = test_pcf();
+        let systems = pcf.particle_systems();
+
+        // "child" only references "radius", which costs 7 bytes including its nul terminator
+        let empty_symbols = Symbols {
+            base: OrderSet::new(),
+            ..Symbols::new_with_all_special()
+        };
+        assert_eq!(
+            systems[1].encoded_size(pcf.symbols()),
+            systems[1].encoded_size(&empty_symbols) + "radius".len() + 1
+        );
+    }
+
+    #[test]
+    fn empty_pcf_has_no_system_sizes() {
+        assert!(Pcf::default().system_sizes().is_empty());
+    }
+}
+
+#[cfg(test)]
+mod defaults_stripping_tests {
+    use std::collections::HashMap;
 
-        let particle_system_definition = base
-            .iter()
-            .find_position(|el| *el == c"DmeParticleSystemDefinition")
-            .ok_or(Error::MissingSystemDefinitionString)?
-            .0 as SymbolIdx;
+    use ordermap::OrderMap;
 
-        let particle_child = find_idx(&base, c"DmeParticleChild");
-        let particle_operator = find_idx(&base, c"DmeParticleOperator");
-        let function_name = find_idx(&base, c"functionName");
-        let children = find_idx(&base, c"children");
-        let constraints = find_idx(&base, c"constraints");
-        let emitters = find_idx(&base, c"emitters");
-        let forces = find_idx(&base, c"forces");
-        let initializers = find_idx(&base, c"initializers");
-        let operators = find_idx(&base, c"operators");
-        let renderers = find_idx(&base, c"renderers");
-        let child = find_idx(&base, c"child");
+    use crate::{
+        Attribute, Comparison, ParticleSystem, Pcf,
+        test_util::{PcfBuilder, system},
+    };
 
-        let base = base
-            .into_iter()
-            .map(|string| string.to_string_lossy().into_owned())
-            .collect();
+    fn test_pcf(radius: f32) -> Pcf {
+        let mut builder = PcfBuilder::default();
+        let radius_idx = builder.symbol("radius");
 
-        Ok(Self {
-            element,
-            particle_system_definitions,
-            particle_system_definition,
-            particle_child,
-            particle_operator,
-            function_name,
-            children,
-            constraints,
-            emitters,
-            forces,
-            initializers,
-            operators,
-            renderers,
-            child,
-            base,
-        })
+        builder
+            .system(ParticleSystem {
+                attributes: OrderMap::from([(radius_idx, Attribute::from(radius))]),
+                ..system("system", &[])
+            })
+            .build()
+    }
+
+    #[test]
+    fn exact_stripping_keeps_nearby_floats() {
+        let particle_defaults = HashMap::from([("radius", Attribute::from(5.0))]);
+        let pcf = test_pcf(5.000_01).defaults_stripped_nth(1000, &particle_defaults, &HashMap::new());
+        assert_eq!(pcf.root().particle_systems()[0].attributes.len(), 1);
+    }
+
+    #[test]
+    fn approximate_stripping_removes_nearby_floats() {
+        let particle_defaults = HashMap::from([("radius", Attribute::from(5.0))]);
+        let comparison = Comparison::Approximate { epsilon: 0.001 };
+
+        let pcf = test_pcf(5.000_01).defaults_stripped_nth_with(1000, &particle_defaults, &HashMap::new(), comparison);
+        assert!(pcf.root().particle_systems()[0].attributes.is_empty());
+
+        let pcf = test_pcf(5.1).defaults_stripped_nth_with(1000, &particle_defaults, &HashMap::new(), comparison);
+        assert_eq!(pcf.root().particle_systems()[0].attributes.len(), 1);
+    }
+
+    #[test]
+    fn exact_stripping_removes_negative_zero() {
+        let particle_defaults = HashMap::from([("radius", Attribute::from(0.0))]);
+        let pcf = test_pcf(-0.0).defaults_stripped_nth(1000, &particle_defaults, &HashMap::new());
+        assert!(pcf.root().particle_systems()[0].attributes.is_empty());
+    }
+
+    #[test]
+    fn stripping_updates_encoded_size() {
+        let particle_defaults = HashMap::from([("radius", Attribute::from(5.0))]);
+        let pcf = test_pcf(5.0);
+        let size = pcf.encoded_size();
+
+        let pcf = pcf.defaults_stripped_nth(1000, &particle_defaults, &HashMap::new());
+        assert!(pcf.encoded_size() < size);
+        assert_eq!(pcf.encoded_size(), pcf.compute_encoded_size());
     }
 }
 
-impl From<Symbols> for dmx::Symbols {
-    fn from(value: Symbols) -> Self {
-        value
-            .base
-            .into_iter()
-            .map(|string| {
-                let mut vec: Vec<u8> = string.into_bytes();
-                vec.push(0);
-                CString::from_vec_with_nul(vec).expect("this should never fail")
-            })
-            .collect()
+#[cfg(test)]
+mod signature_tests {
+    use crate::{
+        Pcf,
+        new::{MergeOptions, Operator, SignatureError},
+        test_util::{PcfBuilder, operator, system},
+    };
+
+    fn test_pcf(name: &str, signature: u8) -> Pcf {
+        let mut system = system(name, &[0]);
+        system.signature = [signature; 16];
+        system.children[0].signature = [signature + 1; 16];
+        system.renderers = Box::from([Operator {
+            signature: [signature + 2; 16],
+            ..operator("render", "render_animated_sprites")
+        }]);
+
+        PcfBuilder::default().system(system).build()
+    }
+
+    #[test]
+    fn merging_regenerates_incoming_signatures() {
+        let pcf = test_pcf("a", 1).merged(test_pcf("a", 1)).unwrap();
+
+        assert_eq!(pcf.particle_systems()[0].signature, [1; 16]);
+        assert_ne!(pcf.particle_systems()[1].signature, [1; 16]);
+        assert_ne!(pcf.particle_systems()[1].children[0].signature, [2; 16]);
+        assert_ne!(pcf.particle_systems()[1].renderers[0].signature, [3; 16]);
+        assert!(pcf.validate_signatures().is_ok());
+    }
+
+    #[test]
+    fn merging_is_deterministic() {
+        let first = test_pcf("a", 1).merged(test_pcf("a", 1)).unwrap();
+        let second = test_pcf("a", 1).merged(test_pcf("a", 1)).unwrap();
+
+        assert_eq!(first.particle_systems(), second.particle_systems());
+    }
+
+    #[test]
+    fn merging_can_preserve_signatures() {
+        let options = MergeOptions {
+            preserve_signatures: true,
+            ..MergeOptions::default()
+        };
+
+        let (pcf, _) = test_pcf("a", 1).merged_with_options(test_pcf("b", 1), options).unwrap();
+
+        assert_eq!(pcf.particle_systems()[1].signature, [1; 16]);
+        assert_eq!(pcf.particle_systems()[1].children[0].signature, [2; 16]);
+        assert_eq!(pcf.particle_systems()[1].renderers[0].signature, [3; 16]);
+
+        let Err(SignatureError::Duplicates(duplicates)) = pcf.validate_signatures() else {
+            panic!("expected duplicate signatures");
+        };
+
+        assert_eq!(duplicates, vec![[1; 16], [2; 16], [3; 16]]);
+    }
+
+    #[test]
+    fn distinct_signatures_are_valid() {
+        let options = MergeOptions {
+            preserve_signatures: true,
+            ..MergeOptions::default()
+        };
+
+        let (pcf, _) = test_pcf("a", 1).merged_with_options(test_pcf("b", 4), options).unwrap();
+
+        assert!(pcf.validate_signatures().is_ok());
     }
 }
 
 #[cfg(test)]
-mod symbol_stripping_tests {
-    use dmx::dmx::Version;
+mod material_renaming_tests {
+    use ordermap::OrderMap;
+
+    use crate::{Attribute, ParticleSystem, Pcf, test_util::PcfBuilder};
+
+    fn test_pcf(materials: &[&str]) -> Pcf {
+        let mut builder = PcfBuilder::default();
+        let material = builder.symbol("material");
+
+        builder
+            .systems(materials.iter().enumerate().map(|(idx, name)| ParticleSystem {
+                name: format!("system {idx}"),
+                attributes: OrderMap::from([(material, Attribute::String((*name).to_string()))]),
+                ..ParticleSystem::default()
+            }))
+            .build()
+    }
+
+    #[test]
+    fn only_renamed_materials_change() {
+        let pcf = test_pcf(&["effects/fire.vmt", "effects/smoke.vmt"]).materials_renamed(|material| {
+            (material == "effects/fire.vmt").then(|| "dazzle/fire_addon/effects/fire.vmt".to_string())
+        });
+
+        let expected = test_pcf(&["dazzle/fire_addon/effects/fire.vmt", "effects/smoke.vmt"]);
+        let materials = |pcf: &Pcf| {
+            pcf.particle_systems()
+                .iter()
+                .map(|system| system.attributes.values().next().unwrap().clone())
+                .collect::<Vec<_>>()
+        };
+
+        assert_eq!(materials(&pcf), materials(&expected));
+        assert_eq!(pcf.encoded_size(), expected.encoded_size());
+    }
+}
+
+#[cfg(test)]
+mod attribute_editing_tests {
     use ordermap::OrderMap;
 
     use crate::{
-        ParticleSystem, Pcf, Root,
-        new::{Child, Operator, Symbols},
+        Attribute, ParticleSystem, Pcf,
+        new::{AttributePath, EditError, Operator},
+        test_util::{self, PcfBuilder},
     };
 
-    #[test]
-    fn never_strips_required_symbols() {
-        let pcf = Pcf {
-            version: Version::Binary2Pcf1,
-            symbols: Symbols::default(),
-            root: Root::default(),
-            encoded_size: 0,
+    fn test_pcf() -> Pcf {
+        let mut builder = PcfBuilder::default();
+        let radius = builder.symbol("radius");
+
+        let operator = |name: &str, function_name: &str| Operator {
+            attributes: OrderMap::from([(radius, Attribute::Float(1.0.into()))]),
+            ..test_util::operator(name, function_name)
+        };
+
+        builder
+            .system(ParticleSystem {
+                name: "fire".to_string(),
+                renderers: Box::from([operator("render", "render_animated_sprites")]),
+                initializers: Box::from([
+                    operator("", "Alpha Random"),
+                    operator("", "Radius Random"),
+                    operator("", "Radius Random"),
+                ]),
+                attributes: OrderMap::from([(radius, Attribute::Float(2.0.into()))]),
+                ..ParticleSystem::default()
+            })
+            .build()
+    }
+
+    fn path(operator: Option<&str>, attribute: &str) -> AttributePath {
+        AttributePath {
+            system: "fire".to_string(),
+            operator: operator.map(str::to_string),
+            attribute: attribute.to_string(),
         }
-        .unused_symbols_stripped();
+    }
+
+    #[test]
+    fn sets_existing_attributes() {
+        let mut pcf = test_pcf();
 
+        let previous = pcf.set_attribute(&path(None, "radius"), 3.0.into()).unwrap();
+        assert_eq!(previous, Some(2.0.into()));
+        assert_eq!(pcf.attribute(&path(None, "radius")).unwrap(), Some(&3.0.into()));
+
+        pcf.set_attribute(&path(Some("render"), "radius"), 4.0.into()).unwrap();
         assert_eq!(
-            Symbols::default().base,
-            pcf.symbols.base,
-            "the required symbols (left) were stripped in the resulting pcf (right)"
+            pcf.attribute(&path(Some("render"), "radius")).unwrap(),
+            Some(&4.0.into())
         );
     }
 
     #[test]
-    fn strips_all_unused_symbols() {
-        let mut symbols = Symbols::new_with_all_special();
-        symbols.base.insert("test1".to_string());
-        symbols.base.insert("test2".to_string());
-        symbols.base.insert("test3".to_string());
+    fn adds_missing_attributes() {
+        let mut pcf = test_pcf();
+        let size = pcf.encoded_size();
 
-        let pcf = Pcf {
-            version: Version::Binary2Pcf1,
-            symbols,
-            root: Root::default(),
-            encoded_size: 0,
-        }
-        .unused_symbols_stripped();
+        let previous = pcf.set_attribute(&path(None, "color"), Attribute::Integer(1)).unwrap();
+        assert_eq!(previous, None);
+        assert_eq!(
+            pcf.attribute(&path(None, "color")).unwrap(),
+            Some(&Attribute::Integer(1))
+        );
+        assert!(pcf.encoded_size() > size);
+    }
 
-        assert!(
-            !pcf.symbols.base.contains("test1"),
-            "the unused symbols were not stripped from the pcf"
+    #[test]
+    fn operators_are_matched_by_function_name_if_unnamed() {
+        let mut pcf = test_pcf();
+        pcf.set_attribute(&path(Some("Alpha Random"), "radius"), 5.0.into())
+            .unwrap();
+        assert_eq!(
+            pcf.attribute(&path(Some("Alpha Random"), "radius")).unwrap(),
+            Some(&5.0.into())
         );
+
+        assert!(matches!(
+            pcf.set_attribute(&path(Some("Radius Random"), "radius"), 5.0.into()),
+            Err(EditError::AmbiguousOperator { .. })
+        ));
+        assert!(matches!(
+            pcf.attribute(&path(Some("Lifetime Random"), "radius")),
+            Err(EditError::UnknownOperator { .. })
+        ));
+    }
+
+    #[test]
+    fn type_mismatches_leave_the_pcf_unchanged() {
+        let mut pcf = test_pcf();
+        let expected = pcf.clone();
+
+        assert!(matches!(
+            pcf.set_attribute(&path(None, "radius"), Attribute::Integer(3)),
+            Err(EditError::TypeMismatch { .. })
+        ));
+        assert_eq!(pcf, expected);
+    }
+}
+
+#[cfg(test)]
+mod operator_order_tests {
+    use std::collections::HashMap;
+
+    use dmx::Dmx;
+    use ordermap::OrderMap;
+
+    use crate::{
+        Attribute, ParticleSystem, Pcf,
+        new::{EditError, Operator, OperatorList, Symbols},
+        test_util::PcfBuilder,
+    };
+
+    fn operator(name: &str, signature: u8) -> Operator {
+        Operator {
+            name: name.to_string(),
+            function_name: name.to_string(),
+            signature: [signature; 16],
+            attributes: OrderMap::new(),
+        }
+    }
+
+    fn system(name: &str, signature: u8, initializers: &[&str]) -> ParticleSystem {
+        ParticleSystem {
+            name: name.to_string(),
+            signature: [signature; 16],
+            initializers: initializers
+                .iter()
+                .enumerate()
+                .map(|(idx, name)| operator(name, signature + 1 + idx as u8))
+                .collect(),
+            ..ParticleSystem::default()
+        }
+    }
+
+    fn names(pcf: &Pcf, system: usize, list: OperatorList) -> Vec<&str> {
+        pcf.particle_systems()[system]
+            .operator_list(list)
+            .iter()
+            .map(|operator| operator.name.as_str())
+            .collect()
+    }
+
+    #[test]
+    fn operators_are_inserted_moved_and_removed_in_place() {
+        let mut pcf = PcfBuilder::default()
+            .system(system("fire", 1, &["a", "b", "c"]))
+            .build();
+
+        pcf.insert_operator("fire", OperatorList::Initializers, 1, operator("d", 9))
+            .unwrap();
+        assert_eq!(names(&pcf, 0, OperatorList::Initializers), ["a", "d", "b", "c"]);
+
+        pcf.move_operator("fire", OperatorList::Initializers, 0, 3).unwrap();
+        assert_eq!(names(&pcf, 0, OperatorList::Initializers), ["d", "b", "c", "a"]);
+
+        pcf.move_operator("fire", OperatorList::Initializers, 2, 0).unwrap();
+        assert_eq!(names(&pcf, 0, OperatorList::Initializers), ["c", "d", "b", "a"]);
+
+        let removed = pcf.remove_operator("fire", OperatorList::Initializers, 1).unwrap();
+        assert_eq!(removed.name, "d");
+        assert_eq!(names(&pcf, 0, OperatorList::Initializers), ["c", "b", "a"]);
+        assert_eq!(pcf.encoded_size(), Dmx::from(pcf.clone()).encode_to_vec().len());
+    }
+
+    #[test]
+    fn out_of_range_positions_leave_the_pcf_unchanged() {
+        let mut pcf = PcfBuilder::default().system(system("fire", 1, &["a", "b"])).build();
+        let expected = pcf.clone();
+
+        assert!(matches!(
+            pcf.insert_operator("fire", OperatorList::Initializers, 3, operator("c", 9)),
+            Err(EditError::OperatorOutOfRange { index: 3, len: 2, .. })
+        ));
+        assert!(matches!(
+            pcf.move_operator("fire", OperatorList::Initializers, 0, 2),
+            Err(EditError::OperatorOutOfRange { index: 2, .. })
+        ));
+        assert!(matches!(
+            pcf.remove_operator("fire", OperatorList::Renderers, 0),
+            Err(EditError::OperatorOutOfRange { len: 0, .. })
+        ));
+        assert!(matches!(
+            pcf.remove_operator("smoke", OperatorList::Initializers, 0),
+            Err(EditError::UnknownSystem(_))
+        ));
+        assert_eq!(pcf, expected);
+    }
+
+    #[test]
+    fn inserting_into_a_new_list_adds_its_symbols() {
+        let mut pcf = PcfBuilder::with_symbols(Symbols::default())
+            .system(system("fire", 1, &[]))
+            .build();
+
+        pcf.insert_operator("fire", OperatorList::Renderers, 0, operator("render", 9))
+            .unwrap();
+        assert!(pcf.symbols().renderers.is_some());
+        assert!(pcf.symbols().function_name.is_some());
+
+        let pcf = pcf.unused_symbols_stripped();
+        let decoded = Pcf::try_from(Dmx::from(pcf.clone())).unwrap();
+        assert_eq!(names(&decoded, 0, OperatorList::Renderers), ["render"]);
+    }
+
+    #[test]
+    fn operator_order_survives_merging_stripping_and_encoding() {
+        let mut builder = PcfBuilder::default();
+        let radius = builder.symbol("radius");
+
+        let mut fire = system("fire", 1, &["c", "a", "b"]);
+        for operator in &mut fire.initializers {
+            operator.attributes.insert(radius, Attribute::Float(1.0.into()));
+        }
+
+        let pcf = builder.system(fire).build();
+        let other = PcfBuilder::default()
+            .system(system("smoke", 10, &["z", "x", "y"]))
+            .build();
+
+        let operator_defaults = ["a", "b", "c"]
+            .into_iter()
+            .map(|name| {
+                (
+                    name.to_string(),
+                    HashMap::from([("radius".to_string(), Attribute::Float(1.0.into()))]),
+                )
+            })
+            .collect();
+
+        let pcf = pcf
+            .merged(other)
+            .unwrap()
+            .defaults_stripped(&HashMap::new(), &operator_defaults)
+            .unused_symbols_stripped();
+        let pcf = Pcf::try_from(Dmx::from(pcf)).unwrap();
+
+        assert_eq!(names(&pcf, 0, OperatorList::Initializers), ["c", "a", "b"]);
+        assert_eq!(names(&pcf, 1, OperatorList::Initializers), ["z", "x", "y"]);
         assert!(
-            !pcf.symbols.base.contains("test2"),
-            "the unused symbols were not stripped from the pcf"
+            pcf.particle_systems()[0]
+                .initializers
+                .iter()
+                .all(|operator| operator.attributes.is_empty())
         );
-        assert!(
-            !pcf.symbols.base.contains("test3"),
-            "the unused symbols were not stripped from the pcf"
+    }
+}
+
+#[cfg(test)]
+mod root_system_tests {
+    use crate::{Pcf, new::OrderError, test_util::pcf_with_systems};
+
+    fn names(pcf: &Pcf) -> Vec<&str> {
+        pcf.particle_systems()
+            .iter()
+            .map(|system| system.name.as_str())
+            .collect()
+    }
+
+    #[test]
+    fn roots_are_never_children() {
+        let pcf = pcf_with_systems(&[("fire", &[1]), ("smoke", &[]), ("sparks", &[1])]);
+
+        assert_eq!(pcf.root_systems().unwrap(), vec![0, 2]);
+    }
+
+    #[test]
+    fn shared_descendants_are_kept() {
+        let pcf = pcf_with_systems(&[("fire", &[1, 3]), ("smoke", &[]), ("sparks", &[1]), ("embers", &[])]);
+        let pcf = pcf.without_root_systems(|system| system.name == "fire").unwrap();
+
+        assert_eq!(names(&pcf), vec!["smoke", "sparks"]);
+        assert_eq!(usize::from(pcf.particle_systems()[1].children[0].child), 0);
+    }
+
+    #[test]
+    fn extracts_a_system_with_its_descendants() {
+        let pcf = pcf_with_systems(&[("fire", &[2]), ("smoke", &[]), ("sparks", &[3]), ("embers", &[])]);
+
+        let sparks = pcf.clone().extracted("sparks").unwrap().unwrap();
+        assert_eq!(names(&sparks), vec!["sparks", "embers"]);
+        assert_eq!(usize::from(sparks.particle_systems()[0].children[0].child), 1);
+
+        let fire = pcf.clone().extracted("fire").unwrap().unwrap();
+        assert_eq!(names(&fire), vec!["fire", "sparks", "embers"]);
+        assert!(pcf.extracted("missing").unwrap().is_none());
+    }
+
+    #[test]
+    fn out_of_range_children_are_errors() {
+        let pcf = pcf_with_systems(&[("fire", &[1]), ("smoke", &[2])]);
+
+        assert!(matches!(
+            pcf.root_systems(),
+            Err(OrderError::ChildOutOfRange { system, child }) if system == "smoke" && usize::from(child) == 2
+        ));
+        assert!(pcf.clone().without_root_systems(|_| true).is_err());
+        assert!(pcf.clone().extracted("fire").is_err());
+        assert!(matches!(
+            pcf.topologically_sorted(true),
+            Err(OrderError::ChildOutOfRange { .. })
+        ));
+    }
+}
+
+#[cfg(test)]
+mod system_editing_tests {
+    use dmx::ElementIdx;
+    use ordermap::OrderMap;
+
+    use crate::{
+        Attribute, ParticleSystem, Pcf,
+        new::{EditError, Operator, SymbolIdx, Symbols},
+        test_util::{PcfBuilder, system},
+    };
+
+    fn test_pcf(systems: impl IntoIterator<Item = ParticleSystem>) -> Pcf {
+        PcfBuilder::with_symbols(Symbols::default()).systems(systems).build()
+    }
+
+    fn children(pcf: &Pcf, name: &str) -> Vec<usize> {
+        let system_idx = pcf.system_index(name).unwrap();
+        pcf.particle_systems()[system_idx]
+            .children
+            .iter()
+            .map(|child| usize::from(child.child))
+            .collect()
+    }
+
+    /// A system from another PCF, whose "radius" & "color" symbols are at different indices to `pcf`'s.
+    fn foreign_system(name: &str) -> (ParticleSystem, Symbols) {
+        let mut symbols = Symbols::default();
+        let color = symbols.base.insert_full("color".into()).0 as SymbolIdx;
+        let radius = symbols.base.insert_full("radius".into()).0 as SymbolIdx;
+
+        let system = ParticleSystem {
+            name: name.to_string(),
+            renderers: Box::from([Operator {
+                name: String::new(),
+                function_name: "render_animated_sprites".to_string(),
+                signature: [0; 16],
+                attributes: OrderMap::from([(radius, Attribute::Float(1.0.into()))]),
+            }]),
+            attributes: OrderMap::from([(color, Attribute::Integer(2))]),
+            ..ParticleSystem::default()
+        };
+
+        (system, symbols)
+    }
+
+    #[test]
+    fn pushed_systems_use_the_pcfs_symbols() {
+        let mut pcf = test_pcf([system("fire", &[])]);
+        pcf.symbols.base.insert_full("radius".into());
+
+        let (system, symbols) = foreign_system("smoke");
+        assert_eq!(pcf.push_system(system, &symbols).unwrap(), 1);
+
+        let base = &pcf.symbols().base;
+        let symbol = |name: &str| base.get_index_of(name).unwrap() as SymbolIdx;
+        let smoke = &pcf.particle_systems()[1];
+        assert_eq!(smoke.attributes.get(&symbol("color")), Some(&Attribute::Integer(2)));
+        assert_eq!(
+            smoke.renderers[0].attributes.get(&symbol("radius")),
+            Some(&Attribute::Float(1.0.into()))
         );
-        assert_eq!(Symbols::default(), pcf.symbols);
+        assert!(pcf.symbols().renderers.is_some());
+        assert!(pcf.symbols().function_name.is_some());
+        assert_eq!(pcf.encoded_size(), pcf.compute_encoded_size());
     }
 
     #[test]
-    fn doesnt_strip_used_symbols() {
-        fn empty_operator(name: &str, function_name: &str) -> Operator {
-            Operator {
-                name: name.to_string(),
-                function_name: function_name.to_string(),
-                signature: [2; 16],
-                attributes: OrderMap::new(),
-            }
-        }
+    fn pushing_checks_names_and_children() {
+        let mut pcf = test_pcf([system("fire", &[])]);
+        let symbols = pcf.symbols().clone();
+
+        assert!(matches!(
+            pcf.push_system(system("fire", &[]), &symbols),
+            Err(EditError::DuplicateSystem(name)) if name == "fire"
+        ));
+        assert!(matches!(
+            pcf.push_system(system("smoke", &[2]), &symbols),
+            Err(EditError::ChildOutOfRange { child, .. }) if child == ElementIdx::from(2u32)
+        ));
+        assert_eq!(pcf.particle_systems().len(), 1);
+
+        assert_eq!(pcf.push_system(system("smoke", &[0, 1]), &symbols).unwrap(), 1);
+        assert_eq!(children(&pcf, "smoke"), [0, 1]);
+        assert!(pcf.symbols().child.is_some());
+    }
 
-        let pcf = Pcf {
-            version: Version::Binary2Pcf1,
-            symbols: Symbols::new_with_all_special(),
-            root: Root {
-                name: "untitled".to_string(),
-                signature: [0; 16],
-                particle_systems: Box::from([ParticleSystem {
-                    name: "parent".to_string(),
-                    signature: [1; 16],
-                    children: Vec::new().into_boxed_slice(),
-                    constraints: Box::from([empty_operator("constraint", "constrain function")]) as Box<[Operator]>,
-                    emitters: Box::from([empty_operator("emitter", "emit function")]) as Box<[Operator]>,
-                    forces: Box::from([empty_operator("force", "force function")]) as Box<[Operator]>,
-                    initializers: Box::from([empty_operator("initializer", "initialize function")]) as Box<[Operator]>,
-                    operators: Box::from([empty_operator("operator", "operate function")]) as Box<[Operator]>,
-                    renderers: Box::from([empty_operator("renderer", "render function")]) as Box<[Operator]>,
-                    attributes: OrderMap::new(),
-                }]) as Box<[ParticleSystem]>,
-                attributes: OrderMap::new(),
-            },
-            encoded_size: 0,
-        }
-        .unused_symbols_stripped();
+    #[test]
+    fn replaced_systems_keep_their_references() {
+        let mut pcf = test_pcf([system("fire", &[1]), system("smoke", &[]), system("sparks", &[])]);
+        let (replacement, symbols) = foreign_system("smoke");
+
+        let replaced = pcf.replace_system("smoke", replacement, &symbols).unwrap();
+        assert!(replaced.renderers.is_empty());
+        assert_eq!(pcf.particle_systems()[1].renderers.len(), 1);
+        assert_eq!(children(&pcf, "fire"), [1]);
+
+        let (renamed, symbols) = foreign_system("sparks");
+        assert!(matches!(
+            pcf.replace_system("smoke", renamed, &symbols),
+            Err(EditError::DuplicateSystem(_))
+        ));
+        assert!(matches!(
+            pcf.replace_system("missing", system("missing", &[]), &symbols),
+            Err(EditError::UnknownSystem(_))
+        ));
+    }
 
-        assert!(!pcf.symbols.base.contains("DmeParticleChild"));
-        assert!(!pcf.symbols.base.contains("child"));
-        assert!(!pcf.symbols.base.contains("children"));
+    #[test]
+    fn removing_a_system_reindexes_children() {
+        let mut pcf = test_pcf([
+            system("fire", &[1, 2]),
+            system("smoke", &[]),
+            system("sparks", &[3]),
+            system("embers", &[]),
+        ]);
+
+        let smoke = pcf.remove_system("smoke").unwrap();
+        assert_eq!(smoke.name, "smoke");
+        assert_eq!(children(&pcf, "fire"), [1]);
+        assert_eq!(children(&pcf, "sparks"), [2]);
+        assert_eq!(pcf.system_index("embers"), Some(2));
+        assert_eq!(pcf.encoded_size(), pcf.compute_encoded_size());
+
+        assert!(matches!(pcf.remove_system("smoke"), Err(EditError::UnknownSystem(_))));
     }
 }
 
 #[cfg(test)]
-mod graph_tests {
-    use bytes::Buf;
-    use dmx::{Dmx, dmx::Version};
+mod content_hash_tests {
     use ordermap::OrderMap;
 
     use crate::{
-        ParticleSystem, Pcf, Root,
-        new::{Child, Symbols},
+        Attribute, ParticleSystem, Pcf,
+        new::Operator,
+        test_util::{PcfBuilder, operator},
     };
 
+    fn test_pcf(extra_symbols: &[&str], radius_value: f32, signature: u8) -> Pcf {
+        let mut builder = PcfBuilder::default();
+        for symbol in extra_symbols {
+            builder.symbol(symbol);
+        }
+
+        let radius = builder.symbol("radius");
+        builder
+            .system(ParticleSystem {
+                name: "smoke".to_string(),
+                signature: [signature; 16],
+                renderers: Box::from([Operator {
+                    signature: [signature + 1; 16],
+                    attributes: OrderMap::from([(radius, Attribute::from(radius_value))]),
+                    ..operator("render", "render_animated_sprites")
+                }]),
+                ..ParticleSystem::default()
+            })
+            .build()
+    }
+
     #[test]
-    fn creates_single_connected_component() {
-        let pcf = Pcf {
-            version: Version::Binary2Pcf1,
-            symbols: Symbols::new_with_all_special(),
-            root: Root {
-                name: "untitled".to_string(),
-                signature: [0; 16],
-                particle_systems: Box::from([
-                    ParticleSystem {
-                        name: "parent".to_string(),
-                        signature: [0; 16],
-                        children: Box::from([Child {
-                            name: "parent_child".to_string(),
-                            signature: [0; 16],
-                            child: 1usize.into(),
-                            attributes: OrderMap::new(),
-                        }]) as Box<[Child]>,
-                        ..ParticleSystem::default()
-                    },
-                    ParticleSystem {
-                        name: "daughter".to_string(),
-                        signature: [0; 16],
-                        ..ParticleSystem::default()
-                    },
-                    ParticleSystem {
-                        name: "step parent".to_string(),
-                        signature: [0; 16],
-                        children: Box::from([
-                            Child {
-                                name: "parent_child".to_string(),
-                                signature: [0; 16],
-                                child: 1usize.into(),
-                                attributes: OrderMap::new(),
-                            },
-                            Child {
-                                name: "parent_child".to_string(),
-                                signature: [0; 16],
-                                child: 3usize.into(),
-                                attributes: OrderMap::new(),
-                            },
-                        ]) as Box<[Child]>,
-                        ..ParticleSystem::default()
-                    },
-                    ParticleSystem {
-                        name: "step daughter".to_string(),
-                        signature: [0; 16],
-                        ..ParticleSystem::default()
-                    },
-                ]) as Box<[ParticleSystem]>,
-                attributes: OrderMap::new(),
-            },
-            encoded_size: 0,
-        };
+    fn identical_systems_hash_equally() {
+        let pcf = test_pcf(&[], 5.0, 1);
+        let reordered = test_pcf(&["material", "color"], 5.0, 7);
 
-        let graph = pcf.into_connected();
-        assert_eq!(1, graph.len());
+        assert_eq!(pcf.content_hash(), reordered.content_hash());
+        assert!(pcf.same_content(&reordered));
     }
 
     #[test]
-    fn creates_two_connected_components() {
-        let pcf = Pcf {
-            version: Version::Binary2Pcf1,
-            symbols: Symbols::new_with_all_special(),
-            root: Root {
-                name: "untitled".to_string(),
-                signature: [0; 16],
-                particle_systems: Box::from([
-                    ParticleSystem {
-                        name: "parent".to_string(),
-                        signature: [0; 16],
-                        children: Box::from([Child {
-                            name: "child daughter".to_string(),
-                            signature: [0; 16],
-                            child: 1usize.into(),
-                            attributes: OrderMap::new(),
-                        }]) as Box<[Child]>,
-                        ..ParticleSystem::default()
-                    },
-                    ParticleSystem {
-                        name: "daughter".to_string(),
-                        signature: [0; 16],
-                        ..ParticleSystem::default()
-                    },
-                    ParticleSystem {
-                        name: "parent's sister".to_string(),
-                        signature: [0; 16],
-                        children: Box::from([Child {
-                            name: "child cousin".to_string(),
-                            signature: [0; 16],
-                            child: 3usize.into(),
-                            attributes: OrderMap::new(),
-                        }]) as Box<[Child]>,
-                        ..ParticleSystem::default()
-                    },
-                    ParticleSystem {
-                        name: "daughter's cousin".to_string(),
-                        signature: [0; 16],
-                        ..ParticleSystem::default()
-                    },
-                ]) as Box<[ParticleSystem]>,
-                attributes: OrderMap::new(),
-            },
-            encoded_size: 0,
+    fn different_attributes_hash_differently() {
+        let pcf = test_pcf(&[], 5.0, 1);
+        let changed = test_pcf(&[], 6.0, 1);
+
+        assert_ne!(pcf.content_hash(), changed.content_hash());
+        assert!(!pcf.same_content(&changed));
+    }
+}
+
+#[cfg(test)]
+mod attribute_iteration_tests {
+    use ordermap::OrderMap;
+
+    use crate::{
+        Attribute, ParticleSystem, Pcf,
+        new::{AttributeOwner, Child, Operator, OperatorList, SymbolIdx},
+        test_util::{self, PcfBuilder},
+    };
+
+    fn test_pcf() -> (Pcf, SymbolIdx) {
+        let mut builder = PcfBuilder::default();
+        let radius = builder.symbol("radius");
+        let alpha = builder.symbol("alpha");
+
+        // one past the last symbol, so it's not a valid name
+        let unknown = alpha + 1;
+
+        let operator = |function_name: &str, name_idx: SymbolIdx, value: i32| Operator {
+            attributes: OrderMap::from([(name_idx, Attribute::Integer(value))]),
+            ..test_util::operator("", function_name)
         };
 
-        let mut graph = pcf.into_connected();
-        assert_eq!(2, graph.len());
+        let system = |name: &str, children: Box<[Child]>| ParticleSystem {
+            name: name.to_string(),
+            children,
+            renderers: Box::from([operator("render_animated_sprites", alpha, 1)]),
+            initializers: Box::from([operator("Radius Random", radius, 2)]),
+            attributes: OrderMap::from([(radius, Attribute::Integer(3)), (unknown, Attribute::Integer(4))]),
+            ..ParticleSystem::default()
+        };
 
-        let parent = graph.pop().unwrap();
-        let step_parent = graph.pop().unwrap();
+        let child = Child {
+            name: String::new(),
+            signature: [0; 16],
+            child: 1u32.into(),
+            attributes: OrderMap::from([(alpha, Attribute::Integer(5))]),
+        };
 
-        assert_eq!("untitled", parent.root.name);
-        assert_eq!(2, parent.root.particle_systems.len());
-        assert_eq!("parent", &parent.root.particle_systems[0].name);
-        assert_eq!("daughter", &parent.root.particle_systems[1].name);
+        let pcf = builder
+            .systems([system("fire", Box::from([child])), system("smoke", Box::default())])
+            .attributes(OrderMap::from([(radius, Attribute::Integer(6))]))
+            .build();
 
-        assert_eq!("untitled", step_parent.root.name);
-        assert_eq!(2, step_parent.root.particle_systems.len());
-        assert_eq!("parent's sister", &step_parent.root.particle_systems[0].name);
-        assert_eq!("daughter's cousin", &step_parent.root.particle_systems[1].name);
+        (pcf, unknown)
     }
 
-    const TEST_PCF_DATA: &[u8] = include_bytes!("test/medicgun_beam.pcf");
+    #[test]
+    fn yields_every_system_attribute_in_order() {
+        let (pcf, _) = test_pcf();
+        let fire = &pcf.particle_systems()[0];
+
+        let attributes: Vec<_> = pcf
+            .iter_attributes()
+            .map(|(system, owner, name, attribute)| (system, owner, name, attribute.clone()))
+            .collect();
+
+        assert_eq!(attributes.len(), 7);
+        assert_eq!(
+            attributes[..4],
+            [
+                ("fire", AttributeOwner::System, "radius", Attribute::Integer(3)),
+                (
+                    "fire",
+                    AttributeOwner::Child(&fire.children[0]),
+                    "alpha",
+                    Attribute::Integer(5)
+                ),
+                (
+                    "fire",
+                    AttributeOwner::Operator {
+                        list: OperatorList::Initializers,
+                        operator: &fire.initializers[0]
+                    },
+                    "radius",
+                    Attribute::Integer(2)
+                ),
+                (
+                    "fire",
+                    AttributeOwner::Operator {
+                        list: OperatorList::Renderers,
+                        operator: &fire.renderers[0]
+                    },
+                    "alpha",
+                    Attribute::Integer(1)
+                ),
+            ]
+        );
+        assert!(attributes[4..].iter().all(|(system, ..)| *system == "smoke"));
+    }
 
     #[test]
-    fn connected_components_can_be_reparsed_as_valid_pcf() {
-        let mut reader = TEST_PCF_DATA.reader();
-        let dmx = dmx::decode(&mut reader).unwrap();
-        let pcf: Pcf = dmx.try_into().unwrap();
-        for (idx, pcf) in pcf.into_connected().into_iter().enumerate() {
-            let dmx: Dmx = pcf.into();
-            let _: Pcf = dmx.try_into().unwrap();
-        }
+    fn skips_attributes_without_a_name() {
+        let (pcf, unknown) = test_pcf();
+        let fire = &pcf.particle_systems()[0];
+
+        assert!(fire.attributes.contains_key(&unknown));
+        assert!(
+            fire.iter_attributes(pcf.symbols())
+                .all(|(owner, name, _)| owner != AttributeOwner::System || name == "radius")
+        );
+        assert_eq!(
+            fire.iter_attributes(pcf.symbols())
+                .filter_map(|(owner, ..)| owner.operator())
+                .map(|operator| operator.function_name.as_str())
+                .collect::<Vec<_>>(),
+            ["Radius Random", "render_animated_sprites"]
+        );
     }
 }
 
@@ -2082,7 +4967,7 @@ mod tests {
     };
 
     use bytes::{Buf, BufMut, BytesMut};
-    use dmx::{Dmx, ElementIdx, SymbolIdx, dmx::Element};
+    use dmx::{Dmx, ElementIdx, SymbolIdx, Symbols, dmx::Element, reference::SignatureReferences};
     use ordermap::{OrderMap, OrderSet};
 
     use crate::new::Pcf;
@@ -2097,7 +4982,7 @@ mod tests {
     fn converts_children() {
         let dmx = Dmx {
             version: dmx::dmx::Version::Binary2Pcf1,
-            strings: OrderSet::from([
+            strings: Symbols::from([
                 c"DmElement".to_owned(),
                 c"particleSystemDefinitions".to_owned(),
                 c"DmeParticleSystemDefinition".to_owned(),
@@ -2116,7 +5001,7 @@ mod tests {
                     signature: [0; 16],
                     attributes: OrderMap::from([
                         (6, c"root attribute value".to_owned().into()),
-                        (1, [ElementIdx::from(1usize), ElementIdx::from(2usize)].into()),
+                        (1, [ElementIdx::from(1u32), ElementIdx::from(2u32)].into()),
                     ]),
                 },
                 Element {
@@ -2125,7 +5010,7 @@ mod tests {
                     signature: [1; 16],
                     attributes: OrderMap::from([
                         (7, c"system1 attribute value".to_owned().into()),
-                        (4, [ElementIdx::from(3usize)].into()),
+                        (4, [ElementIdx::from(3u32)].into()),
                     ]),
                 },
                 Element {
@@ -2140,10 +5025,11 @@ mod tests {
                     signature: [3; 16],
                     attributes: OrderMap::from([
                         (9, c"child attribute value".to_owned().into()),
-                        (5 as SymbolIdx, ElementIdx::from(2usize).into()),
+                        (5 as SymbolIdx, ElementIdx::from(2u32).into()),
                     ]),
                 },
             ],
+            signature_references: SignatureReferences::new(),
         };
 
         let expected_dmx = dmx.clone();
@@ -2171,7 +5057,7 @@ mod tests {
     fn converts_operator() {
         let dmx = Dmx {
             version: dmx::dmx::Version::Binary2Pcf1,
-            strings: OrderSet::from([
+            strings: Symbols::from([
                 c"DmElement".to_owned(),
                 c"particleSystemDefinitions".to_owned(),
                 c"DmeParticleSystemDefinition".to_owned(),
@@ -2190,7 +5076,7 @@ mod tests {
                     signature: [0; 16],
                     attributes: OrderMap::from([
                         (6, c"root attribute value".to_owned().into()),
-                        (1, [ElementIdx::from(1usize), ElementIdx::from(2usize)].into()),
+                        (1, [ElementIdx::from(1u32), ElementIdx::from(2u32)].into()),
                     ]),
                 },
                 Element {
@@ -2199,7 +5085,7 @@ mod tests {
                     signature: [1; 16],
                     attributes: OrderMap::from([
                         (7, c"system1 attribute value".to_owned().into()),
-                        (4, [ElementIdx::from(3usize)].into()),
+                        (4, [ElementIdx::from(3u32)].into()),
                     ]),
                 },
                 Element {
@@ -2215,6 +5101,7 @@ mod tests {
                     attributes: OrderMap::from([(5, c"test function name".to_owned().into())]),
                 },
             ],
+            signature_references: SignatureReferences::new(),
         };
 
         let expected_dmx = dmx.clone();
@@ -2238,6 +5125,86 @@ mod tests {
         );
     }
 
+    #[test]
+    fn lenient_conversion_skips_invalid_parts() {
+        let dmx = Dmx {
+            version: dmx::dmx::Version::Binary2Pcf1,
+            strings: Symbols::from([
+                c"DmElement".to_owned(),
+                c"particleSystemDefinitions".to_owned(),
+                c"DmeParticleSystemDefinition".to_owned(),
+                c"DmeParticleOperator".to_owned(),
+                c"operators".to_owned(),
+                c"functionName".to_owned(),
+                c"DmeParticleChild".to_owned(),
+                c"children".to_owned(),
+                c"child".to_owned(),
+            ]),
+            elements: vec![
+                Element {
+                    type_idx: 0,
+                    name: c"untitled".to_owned(),
+                    signature: [0; 16],
+                    attributes: OrderMap::from([(1, [ElementIdx::from(1u32), ElementIdx::from(5u32)].into())]),
+                },
+                Element {
+                    type_idx: 2,
+                    name: c"system1".to_owned(),
+                    signature: [1; 16],
+                    attributes: OrderMap::from([
+                        (4, [ElementIdx::from(2u32), ElementIdx::from(3u32)].into()),
+                        (7, [ElementIdx::from(4u32)].into()),
+                    ]),
+                },
+                Element {
+                    type_idx: 3,
+                    name: c"no function name".to_owned(),
+                    signature: [2; 16],
+                    attributes: OrderMap::new(),
+                },
+                Element {
+                    type_idx: 3,
+                    name: c"operator1".to_owned(),
+                    signature: [3; 16],
+                    attributes: OrderMap::from([(5, c"test function name".to_owned().into())]),
+                },
+                Element {
+                    type_idx: 6,
+                    name: c"child of a missing system".to_owned(),
+                    signature: [4; 16],
+                    attributes: OrderMap::from([(8 as SymbolIdx, ElementIdx::from(5u32).into())]),
+                },
+                Element {
+                    type_idx: 0,
+                    name: c"not a system".to_owned(),
+                    signature: [5; 16],
+                    attributes: OrderMap::new(),
+                },
+            ],
+            signature_references: SignatureReferences::new(),
+        };
+
+        assert!(Pcf::try_from(dmx.clone()).is_err());
+
+        let (pcf, warnings) = Pcf::try_from_dmx_lenient(dmx).unwrap();
+        assert_eq!(warnings.len(), 3);
+        assert_eq!(warnings[0].system, None);
+        assert_eq!(warnings[1].system.as_deref(), Some("system1"));
+
+        let [system] = pcf.particle_systems() else {
+            panic!("expected exactly one particle system");
+        };
+        assert_eq!(system.name, "system1");
+        assert!(system.children.is_empty());
+        assert_eq!(system.operators.len(), 1);
+        assert_eq!(system.operators[0].name, "operator1");
+
+        let computed_size = pcf.compute_encoded_size();
+        let mut writer = BytesMut::new().writer();
+        Dmx::from(pcf).encode(&mut writer).unwrap();
+        assert_eq!(writer.get_ref().len(), computed_size);
+    }
+
     #[test]
     fn computes_correct_size_of_encoded_pcf() {
         let mut reader = TEST_PCF_DATA.reader();
@@ -2287,7 +5254,7 @@ mod tests {
                 original_element.name,
                 new_element.name,
                 "new is missing {}",
-                original_element.name.display()
+                original_element.name.to_string_lossy()
             );
 
             for (name_idx, attribute) in &original_element.attributes {
@@ -2305,8 +5272,8 @@ mod tests {
                         attribute,
                         new_value,
                         "new {}.{} (#{:x?}) mismatched",
-                        original_element.name.display(),
-                        name.display(),
+                        original_element.name.to_string_lossy(),
+                        name.to_string_lossy(),
                         original_element.signature
                     ),
                 }
@@ -0,0 +1,282 @@
+//! Element attributes of a particle system which aren't its children or one of its operator lists, like editor
+//! metadata or newer categories of operators. They're kept verbatim, along with every element they reference, so that
+//! they survive a round trip through a [`Pcf`](crate::Pcf) unchanged.
+
+use std::{
+    collections::{HashMap, HashSet},
+    hash::{Hash, Hasher},
+    slice,
+};
+
+use dmx::{ElementIdx, Signature, attribute::Attribute, dmx::Element};
+use ordermap::OrderMap;
+
+use crate::new::{Error, SymbolIdx, element_idx, in_range};
+
+/// Passthrough attributes by name.
+pub type PassthroughMap = OrderMap<SymbolIdx, Passthrough>;
+
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct Passthrough {
+    /// the attribute's value - an [`Attribute::Element`] or [`Attribute::ElementArray`], whose references are indices
+    /// into [`Passthrough::elements`]
+    pub value: Attribute,
+
+    /// every element the attribute references, directly or indirectly, in the order they were decoded. Their
+    /// references are also indices into this list.
+    pub elements: Box<[Element]>,
+}
+
+impl Passthrough {
+    /// Copies the element attribute `value` out of `elements`, along with every element it references. If an element
+    /// is referenced by more than one passthrough attribute, each gets its own copy.
+    ///
+    /// # Errors
+    ///
+    /// Returns [`Error::UnexpectedElementReference`] if any referenced element is `excluded` - i.e. the root or a
+    /// particle system, which can't be copied without duplicating it - or [`Error::MissingPassthroughElement`] if a
+    /// referenced element doesn't exist.
+    pub(crate) fn collect(
+        value: &Attribute,
+        elements: &[Element],
+        excluded: impl Fn(ElementIdx) -> bool,
+    ) -> Result<Self, Error> {
+        let mut referenced = HashSet::new();
+        let mut pending: Vec<ElementIdx> = references(value).collect();
+        while let Some(idx) = pending.pop() {
+            if !idx.is_valid() || referenced.contains(&idx) {
+                continue;
+            }
+
+            if excluded(idx) {
+                return Err(Error::UnexpectedElementReference);
+            }
+
+            let element = elements
+                .get(usize::from(idx))
+                .ok_or(Error::MissingPassthroughElement(idx))?;
+            referenced.insert(idx);
+            pending.extend(element.attributes.values().flat_map(references));
+        }
+
+        let mut referenced: Vec<_> = referenced.into_iter().collect();
+        referenced.sort();
+
+        let local_indices: HashMap<ElementIdx, ElementIdx> = referenced
+            .iter()
+            .enumerate()
+            .map(|(local_idx, idx)| (*idx, element_idx(local_idx)))
+            .collect();
+        let relocate = |idx: ElementIdx| local_indices.get(&idx).copied().unwrap_or(idx);
+
+        Ok(Self {
+            value: relocated(value, relocate),
+            elements: referenced
+                .into_iter()
+                .map(|idx| relocated_element(&elements[usize::from(idx)], relocate))
+                .collect(),
+        })
+    }
+
+    /// Appends this attribute's elements to `elements`, and returns its value with references to their new indices.
+    pub(crate) fn push_into(self, elements: &mut Vec<Element>) -> Attribute {
+        let offset = elements.len();
+        let relocate = |idx: ElementIdx| in_range(idx.checked_offset(offset));
+
+        elements.extend(self.elements.iter().map(|element| relocated_element(element, relocate)));
+
+        relocated(&self.value, relocate)
+    }
+
+    /// The size of the type idx, name, and signature of each element.
+    pub(crate) fn encoded_elements_size(&self) -> usize {
+        self.elements
+            .iter()
+            .map(|element| size_of::<u16>() + element.name.as_bytes_with_nul().len() + size_of::<Signature>())
+            .sum()
+    }
+
+    /// The size of the attribute itself, and of the attributes of each element.
+    pub(crate) fn encoded_attributes_size(&self) -> usize {
+        let attribute_size =
+            |attribute: &Attribute| size_of::<SymbolIdx>() + size_of::<u8>() + attribute.encoded_size();
+
+        attribute_size(&self.value)
+            + self
+                .elements
+                .iter()
+                .map(|element| size_of::<u32>() + element.attributes.values().map(attribute_size).sum::<usize>())
+                .sum::<usize>()
+    }
+
+    /// Every symbol used as an element type or attribute name by the elements.
+    pub(crate) fn referenced_symbols(&self) -> impl Iterator<Item = SymbolIdx> {
+        self.elements
+            .iter()
+            .flat_map(|element| [element.type_idx].into_iter().chain(element.attributes.keys().copied()))
+    }
+
+    /// Replaces every symbol used by the elements with `remap(symbol)`.
+    pub(crate) fn remap_symbols(&mut self, remap: impl Fn(SymbolIdx) -> SymbolIdx) {
+        for element in &mut self.elements {
+            element.type_idx = remap(element.type_idx);
+            element.attributes = std::mem::take(&mut element.attributes)
+                .into_iter()
+                .map(|(name_idx, attribute)| (remap(name_idx), attribute))
+                .collect();
+        }
+    }
+
+    pub(crate) fn signatures(&self) -> impl Iterator<Item = &Signature> {
+        self.elements.iter().map(|element| &element.signature)
+    }
+
+    pub(crate) fn signatures_mut(&mut self) -> impl Iterator<Item = &mut Signature> {
+        self.elements.iter_mut().map(|element| &mut element.signature)
+    }
+
+    /// Hashes the content of this attribute, with each symbol hashed as `symbol(idx)`.
+    pub(crate) fn hash_with<'a>(&self, symbol: impl Fn(SymbolIdx) -> Option<&'a str>, hasher: &mut impl Hasher) {
+        self.value.hash(hasher);
+        self.elements.len().hash(hasher);
+        for element in &self.elements {
+            symbol(element.type_idx).hash(hasher);
+            element.name.hash(hasher);
+            element.attributes.len().hash(hasher);
+            for (name_idx, attribute) in &element.attributes {
+                symbol(*name_idx).hash(hasher);
+                attribute.hash(hasher);
+            }
+        }
+    }
+}
+
+/// The element indices `attribute` references, if it's an element attribute.
+fn references(attribute: &Attribute) -> impl Iterator<Item = ElementIdx> {
+    let indices = match attribute {
+        Attribute::Element(idx) => slice::from_ref(idx),
+        Attribute::ElementArray(indices) => indices,
+        _ => &[],
+    };
+
+    indices.iter().copied()
+}
+
+fn relocated(attribute: &Attribute, relocate: impl Fn(ElementIdx) -> ElementIdx) -> Attribute {
+    match attribute {
+        Attribute::Element(idx) => Attribute::Element(relocate(*idx)),
+        Attribute::ElementArray(indices) => Attribute::ElementArray(indices.iter().map(|idx| relocate(*idx)).collect()),
+        attribute => attribute.clone(),
+    }
+}
+
+fn relocated_element(element: &Element, relocate: impl Fn(ElementIdx) -> ElementIdx) -> Element {
+    Element {
+        type_idx: element.type_idx,
+        name: element.name.clone(),
+        signature: element.signature,
+        attributes: element
+            .attributes
+            .iter()
+            .map(|(name_idx, attribute)| (*name_idx, relocated(attribute, &relocate)))
+            .collect(),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use dmx::{
+        Dmx, ElementIdx, Symbols,
+        attribute::Attribute,
+        dmx::{Element, Version},
+        reference::SignatureReferences,
+    };
+    use ordermap::OrderMap;
+
+    use crate::{Pcf, new::Error};
+
+    fn element(type_idx: u16, name: &str, signature: u8, attributes: OrderMap<u16, Attribute>) -> Element {
+        Element {
+            type_idx,
+            name: std::ffi::CString::new(name).unwrap(),
+            signature: [signature; 16],
+            attributes,
+        }
+    }
+
+    /// A PCF with one system, which has an unknown element array & an unknown element attribute.
+    fn test_dmx(metadata_target: u32) -> Dmx {
+        Dmx {
+            version: Version::Binary2Pcf1,
+            strings: Symbols::from([
+                c"DmElement".to_owned(),
+                c"particleSystemDefinitions".to_owned(),
+                c"DmeParticleSystemDefinition".to_owned(),
+                c"editorMetadata".to_owned(),
+                c"selection".to_owned(),
+                c"note".to_owned(),
+                c"radius".to_owned(),
+                c"DmeEditorInfo".to_owned(),
+            ]),
+            elements: vec![
+                element(
+                    0,
+                    "untitled",
+                    0,
+                    OrderMap::from([(1, Attribute::from([ElementIdx::from(1u32)]))]),
+                ),
+                element(
+                    2,
+                    "fire",
+                    1,
+                    OrderMap::from([
+                        (6, Attribute::from(5.0)),
+                        (3, Attribute::from([ElementIdx::from(metadata_target)])),
+                        (4, Attribute::Element(ElementIdx::from(4u32))),
+                    ]),
+                ),
+                element(
+                    7,
+                    "info",
+                    2,
+                    OrderMap::from([
+                        (5, Attribute::String(c"hand tuned".to_owned())),
+                        (4, Attribute::Element(ElementIdx::from(3u32))),
+                    ]),
+                ),
+                element(
+                    7,
+                    "nested",
+                    3,
+                    OrderMap::from([(4, Attribute::Element(ElementIdx::INVALID))]),
+                ),
+                element(7, "selected", 4, OrderMap::new()),
+            ],
+            signature_references: SignatureReferences::new(),
+        }
+    }
+
+    #[test]
+    fn unknown_element_attributes_round_trip() {
+        let dmx = test_dmx(2);
+        let pcf = Pcf::try_from(dmx.clone()).unwrap();
+
+        let passthrough = &pcf.particle_systems()[0].passthrough;
+        assert_eq!(passthrough.len(), 2);
+        assert_eq!(passthrough[&3].elements.len(), 2);
+        assert_eq!(passthrough[&4].elements.len(), 1);
+
+        let size = pcf.encoded_size();
+        let round_tripped = Dmx::from(pcf);
+        assert_eq!(round_tripped, dmx);
+        assert_eq!(round_tripped.encode_to_vec().len(), size);
+    }
+
+    #[test]
+    fn references_to_particle_systems_are_rejected() {
+        assert!(matches!(
+            Pcf::try_from(test_dmx(1)),
+            Err(Error::UnexpectedElementReference)
+        ));
+    }
+}
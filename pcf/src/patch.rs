@@ -0,0 +1,283 @@
+//! Declarative patches, which set the attributes of particle systems & operators by name. Since they don't depend on
+//! where a system is, or which PCF it's in, a [`PcfPatch`] can be shared & re-applied to any PCF containing the systems
+//! it edits, rather than shipping the whole PCF.
+//!
+//! With the `serde` feature, patches can be read from JSON or TOML. Integer values are accepted for float attributes.
+//! ```json
+//! {
+//!     "name": "Smaller flames",
+//!     "edits": [
+//!         { "system": "flamethrower_fire", "attribute": "radius", "value": 3.0 },
+//!         { "system": "flamethrower_fire", "operator": "Alpha Fade and Decay", "attribute": "end_alpha", "value": 0 }
+//!     ]
+//! }
+//! ```
+//!
+//! # Example
+//!
+//! Apply a patch, reporting the edits which couldn't be applied.
+//! ```
+//! # use pcf::{Pcf, patch::{PatchEdit, PatchValue, PcfPatch}};
+//! # fn example(pcf: &mut Pcf) {
+//! let patch = PcfPatch {
+//!     name: "Smaller flames".to_string(),
+//!     edits: vec![PatchEdit {
+//!         system: "flamethrower_fire".to_string(),
+//!         operator: None,
+//!         attribute: "radius".to_string(),
+//!         value: PatchValue::Float(3.0),
+//!     }],
+//! };
+//!
+//! for err in pcf.apply_patch(&patch).errors {
+//!     println!("{err}");
+//! }
+//! # }
+//! ```
+
+use std::mem;
+
+use dmx::Color;
+use thiserror::Error;
+
+use crate::{
+    Attribute, TypeMismatch,
+    new::{AttributePath, EditError, Pcf},
+};
+
+#[derive(Debug, Clone, Default, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct PcfPatch {
+    /// A short description of what the patch changes.
+    #[cfg_attr(feature = "serde", serde(default))]
+    pub name: String,
+
+    pub edits: Vec<PatchEdit>,
+}
+
+/// Sets a single attribute. See [`AttributePath`] for how the system & operator are found.
+#[derive(Debug, Clone, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct PatchEdit {
+    pub system: String,
+
+    #[cfg_attr(feature = "serde", serde(default, skip_serializing_if = "Option::is_none"))]
+    pub operator: Option<String>,
+
+    pub attribute: String,
+    pub value: PatchValue,
+}
+
+/// The value set by a [`PatchEdit`]. Only scalar & color attributes can be patched.
+#[derive(Debug, Clone, Copy, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize), serde(untagged))]
+pub enum PatchValue {
+    // N.B. integers must come before floats, so that untagged integers aren't read as floats
+    Integer(i32),
+    Float(f32),
+    Bool(bool),
+    Color([u8; 4]),
+}
+
+#[derive(Debug, Error)]
+#[error("edit {} can't be applied: {error}", .edit + 1)]
+pub struct PatchError {
+    /// The index of the edit in [`PcfPatch::edits`].
+    pub edit: usize,
+    pub error: EditError,
+}
+
+#[derive(Debug, Default)]
+pub struct PatchReport {
+    /// The indices of the edits which were applied.
+    pub applied: Vec<usize>,
+
+    /// The edits which target a particle system in the PCF, but couldn't be applied.
+    pub errors: Vec<PatchError>,
+}
+
+impl PatchEdit {
+    pub fn path(&self) -> AttributePath {
+        AttributePath {
+            system: self.system.clone(),
+            operator: self.operator.clone(),
+            attribute: self.attribute.clone(),
+        }
+    }
+}
+
+impl PatchValue {
+    /// This value as an [`Attribute`] which replaces `current`. Integers become floats when replacing a float, since
+    /// hand-written patches often leave out the `.0`.
+    pub fn to_attribute(self, current: Option<&Attribute>) -> Attribute {
+        match (self, current) {
+            (PatchValue::Integer(value), Some(Attribute::Float(_))) => (value as f32).into(),
+            (PatchValue::Integer(value), _) => Attribute::Integer(value),
+            (PatchValue::Float(value), _) => value.into(),
+            (PatchValue::Bool(value), _) => Attribute::Bool(value),
+            (PatchValue::Color([r, g, b, a]), _) => Attribute::Color(Color(r, g, b, a)),
+        }
+    }
+}
+
+impl Pcf {
+    /// Checks that every edit in `patch` which targets a particle system in this [`Pcf`] could be applied, without
+    /// applying any of them.
+    pub fn validate_patch(&self, patch: &PcfPatch) -> Vec<PatchError> {
+        patch
+            .edits
+            .iter()
+            .enumerate()
+            .filter_map(|(edit_idx, edit)| {
+                let error = self.patched_value(edit).err()?;
+                Some(PatchError { edit: edit_idx, error })
+            })
+            .collect()
+    }
+
+    /// Applies every edit in `patch` which targets a particle system in this [`Pcf`]. Edits to other systems are
+    /// skipped, since a patch usually edits systems from many PCFs. An edit which can't be applied doesn't stop the
+    /// others.
+    pub fn apply_patch(&mut self, patch: &PcfPatch) -> PatchReport {
+        let mut report = PatchReport::default();
+        for (edit_idx, edit) in patch.edits.iter().enumerate() {
+            let result = match self.patched_value(edit) {
+                Ok(Some(value)) => self.set_attribute(&edit.path(), value),
+                Ok(None) => continue,
+                Err(error) => Err(error),
+            };
+
+            match result {
+                Ok(_) => report.applied.push(edit_idx),
+                Err(error) => report.errors.push(PatchError { edit: edit_idx, error }),
+            }
+        }
+
+        report
+    }
+
+    /// The attribute `edit` would set, or `None` if its particle system isn't in this [`Pcf`].
+    fn patched_value(&self, edit: &PatchEdit) -> Result<Option<Attribute>, EditError> {
+        if !self.particle_systems().iter().any(|system| system.name == edit.system) {
+            return Ok(None);
+        }
+
+        let current = self.attribute(&edit.path())?;
+        let value = edit.value.to_attribute(current);
+        if let Some(current) = current
+            && mem::discriminant(current) != mem::discriminant(&value)
+        {
+            return Err(EditError::TypeMismatch {
+                attribute: edit.attribute.clone(),
+                source: TypeMismatch {
+                    expected: current.type_name(),
+                    actual: value.type_name(),
+                },
+            });
+        }
+
+        Ok(Some(value))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use dmx::dmx::Version;
+    use ordermap::OrderMap;
+
+    use super::{PatchEdit, PatchValue, PcfPatch};
+    use crate::{
+        Attribute, ParticleSystem, Pcf, Root,
+        new::{AttributePath, EditError, Operator, SymbolIdx, Symbols},
+    };
+
+    fn test_pcf() -> Pcf {
+        let mut symbols = Symbols::new_with_all_special();
+        let (radius, _) = symbols.base.insert_full("radius".into());
+        let radius = radius as SymbolIdx;
+
+        Pcf::new(
+            Version::Binary2Pcf1,
+            symbols,
+            Root::new(
+                "untitled".to_string(),
+                [0; 16],
+                Box::from([ParticleSystem {
+                    name: "fire".to_string(),
+                    renderers: Box::from([Operator {
+                        name: "render".to_string(),
+                        function_name: "render_animated_sprites".to_string(),
+                        signature: [0; 16],
+                        attributes: OrderMap::new(),
+                    }]),
+                    attributes: OrderMap::from([(radius, Attribute::Float(2.0.into()))]),
+                    ..ParticleSystem::default()
+                }]),
+                OrderMap::new(),
+            ),
+        )
+    }
+
+    fn edit(system: &str, operator: Option<&str>, attribute: &str, value: PatchValue) -> PatchEdit {
+        PatchEdit {
+            system: system.to_string(),
+            operator: operator.map(str::to_string),
+            attribute: attribute.to_string(),
+            value,
+        }
+    }
+
+    fn path(operator: Option<&str>, attribute: &str) -> AttributePath {
+        AttributePath {
+            system: "fire".to_string(),
+            operator: operator.map(str::to_string),
+            attribute: attribute.to_string(),
+        }
+    }
+
+    #[test]
+    fn applies_edits_to_systems_in_the_pcf() {
+        let mut pcf = test_pcf();
+        let patch = PcfPatch {
+            name: String::new(),
+            edits: vec![
+                edit("fire", None, "radius", PatchValue::Integer(3)),
+                edit("smoke", None, "radius", PatchValue::Float(4.0)),
+                edit("fire", Some("render"), "visible", PatchValue::Bool(false)),
+            ],
+        };
+
+        let report = pcf.apply_patch(&patch);
+        assert_eq!(report.applied, vec![0, 2]);
+        assert!(report.errors.is_empty());
+        assert_eq!(pcf.attribute(&path(None, "radius")).unwrap(), Some(&3.0.into()));
+        assert_eq!(
+            pcf.attribute(&path(Some("render"), "visible")).unwrap(),
+            Some(&Attribute::Bool(false))
+        );
+    }
+
+    #[test]
+    fn reports_edits_which_cant_be_applied() {
+        let mut pcf = test_pcf();
+        let patch = PcfPatch {
+            name: String::new(),
+            edits: vec![
+                edit("fire", None, "radius", PatchValue::Bool(true)),
+                edit("fire", Some("missing"), "radius", PatchValue::Float(1.0)),
+                edit("fire", None, "radius", PatchValue::Float(5.0)),
+            ],
+        };
+
+        let errors = pcf.validate_patch(&patch);
+        assert_eq!(errors.len(), 2);
+        assert!(matches!(errors[0].error, EditError::TypeMismatch { .. }));
+        assert!(matches!(errors[1].error, EditError::UnknownOperator { .. }));
+        assert_eq!(pcf.attribute(&path(None, "radius")).unwrap(), Some(&2.0.into()));
+
+        let report = pcf.apply_patch(&patch);
+        assert_eq!(report.applied, vec![2]);
+        assert_eq!(report.errors.len(), 2);
+        assert_eq!(pcf.attribute(&path(None, "radius")).unwrap(), Some(&5.0.into()));
+    }
+}
@@ -0,0 +1,246 @@
+//! Search the particle systems in a [`Pcf`] for operators & attributes matching a [`Query`].
+//!
+//! # Example
+//!
+//! Find every renderer that sets an `orientation_type`.
+//! ```
+//! # use pcf::{Pcf, query::Query};
+//! # fn example(pcf: &Pcf) {
+//! let query = Query {
+//!     attribute_name: Some("orientation_type".to_string()),
+//!     function_name: Some("render_animated_sprites".to_string()),
+//!     ..Query::default()
+//! };
+//!
+//! for found in query.find_in(pcf) {
+//!     println!("{}", found.system.name);
+//! }
+//! # }
+//! ```
+
+use regex::Regex;
+
+use crate::{
+    Attribute,
+    new::{AttributeOwner, Operator, ParticleSystem, ParticleSystemIdx, Pcf},
+};
+
+/// Every predicate that is set must match. A query with no predicates matches every particle system.
+#[derive(Debug, Clone, Default)]
+pub struct Query {
+    /// Only match attributes with exactly this name.
+    pub attribute_name: Option<String>,
+
+    /// Only match attributes whose value, formatted with [`Attribute`]'s `Display` impl, matches this pattern.
+    pub value: Option<Regex>,
+
+    /// Only match operators with exactly this function name. The particle system's own attributes are never matched.
+    pub function_name: Option<String>,
+
+    /// Only match within particle systems whose `material` attribute matches this pattern.
+    pub material: Option<Regex>,
+}
+
+/// A particle system, operator, or attribute which matched a [`Query`].
+#[derive(Debug, Clone, Copy)]
+pub struct Match<'a> {
+    pub system_idx: ParticleSystemIdx,
+    pub system: &'a ParticleSystem,
+
+    /// The operator which matched, or which owns the matched attribute. `None` if the match is the particle system
+    /// itself, or one of its own attributes.
+    pub operator: Option<&'a Operator>,
+
+    /// The name and value of the matched attribute. `None` if the query doesn't filter on attributes.
+    pub attribute: Option<(&'a str, &'a Attribute)>,
+}
+
+impl Query {
+    /// Whether this query matches individual attributes, rather than whole systems or operators.
+    pub fn filters_attributes(&self) -> bool {
+        self.attribute_name.is_some() || self.value.is_some()
+    }
+
+    /// Finds every match in `pcf`, in the order the particle systems, operators, and attributes appear.
+    pub fn find_in<'a>(&self, pcf: &'a Pcf) -> Vec<Match<'a>> {
+        let symbols = &pcf.symbols().base;
+        let material_idx = symbols.get_index_of("material");
+
+        let mut matches = Vec::new();
+        for (system_idx, system) in pcf.particle_systems().iter().enumerate() {
+            if let Some(material) = &self.material {
+                let system_material = material_idx
+                    .and_then(|idx| system.attributes.get(&(idx as u16)))
+                    .and_then(|attribute| attribute.as_string().ok());
+
+                if !system_material.is_some_and(|system_material| material.is_match(system_material)) {
+                    continue;
+                }
+            }
+
+            if !self.filters_attributes() {
+                if self.function_name.is_none() {
+                    matches.push(Match {
+                        system_idx,
+                        system,
+                        operator: None,
+                        attribute: None,
+                    });
+                }
+
+                for operator in system.all_operators() {
+                    if self.function_matches(operator) {
+                        matches.push(Match {
+                            system_idx,
+                            system,
+                            operator: Some(operator),
+                            attribute: None,
+                        });
+                    }
+                }
+
+                continue;
+            }
+
+            for (owner, name, attribute) in system.iter_attributes(pcf.symbols()) {
+                let operator = match owner {
+                    AttributeOwner::System if self.function_name.is_none() => None,
+                    AttributeOwner::Operator { operator, .. } if self.function_matches(operator) => Some(operator),
+                    _ => continue,
+                };
+
+                if self.attribute_matches(name, attribute) {
+                    matches.push(Match {
+                        system_idx,
+                        system,
+                        operator,
+                        attribute: Some((name, attribute)),
+                    });
+                }
+            }
+        }
+
+        matches
+    }
+
+    fn function_matches(&self, operator: &Operator) -> bool {
+        self.function_name
+            .as_ref()
+            .is_none_or(|function_name| *function_name == operator.function_name)
+    }
+
+    fn attribute_matches(&self, name: &str, attribute: &Attribute) -> bool {
+        if let Some(attribute_name) = &self.attribute_name
+            && attribute_name != name
+        {
+            return false;
+        }
+
+        if let Some(value) = &self.value
+            && !value.is_match(&attribute.to_string())
+        {
+            return false;
+        }
+
+        true
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use dmx::dmx::Version;
+    use ordermap::OrderMap;
+    use regex::Regex;
+
+    use super::Query;
+    use crate::{Attribute, Operator, ParticleSystem, Pcf, Root, Symbols};
+
+    fn test_pcf() -> Pcf {
+        let mut symbols = Symbols::new_with_all_special();
+        let (material, _) = symbols.base.insert_full("material".into());
+        let (radius, _) = symbols.base.insert_full("radius".into());
+        let material = material as u16;
+        let radius = radius as u16;
+
+        let system = |name: &str, material_name: &str, radius_value: f32| ParticleSystem {
+            name: name.to_string(),
+            renderers: Box::from([Operator {
+                name: "render".to_string(),
+                function_name: "render_animated_sprites".to_string(),
+                signature: [0; 16],
+                attributes: OrderMap::from([(radius, Attribute::from(radius_value))]),
+            }]),
+            attributes: OrderMap::from([
+                (material, Attribute::String(material_name.to_string())),
+                (radius, Attribute::from(radius_value * 2.0)),
+            ]),
+            ..ParticleSystem::default()
+        };
+
+        Pcf::new(
+            Version::Binary2Pcf1,
+            symbols,
+            Root::new(
+                "untitled".to_string(),
+                [0; 16],
+                Box::from([
+                    system("fire", "effects/fire", 1.0),
+                    system("smoke", "effects/smoke", 3.0),
+                ]),
+                OrderMap::new(),
+            ),
+        )
+    }
+
+    #[test]
+    fn empty_query_matches_every_system_and_operator() {
+        let pcf = test_pcf();
+        let matches = Query::default().find_in(&pcf);
+
+        assert_eq!(matches.len(), 4);
+        assert!(matches.iter().all(|found| found.attribute.is_none()));
+    }
+
+    #[test]
+    fn attribute_name_matches_systems_and_operators() {
+        let pcf = test_pcf();
+        let query = Query {
+            attribute_name: Some("radius".to_string()),
+            ..Query::default()
+        };
+
+        let matches = query.find_in(&pcf);
+        assert_eq!(matches.len(), 4);
+        assert_eq!(matches[0].attribute, Some(("radius", &Attribute::from(2.0))));
+        assert_eq!(matches[1].operator.unwrap().name, "render");
+    }
+
+    #[test]
+    fn function_name_excludes_system_attributes() {
+        let pcf = test_pcf();
+        let query = Query {
+            attribute_name: Some("radius".to_string()),
+            function_name: Some("render_animated_sprites".to_string()),
+            ..Query::default()
+        };
+
+        let matches = query.find_in(&pcf);
+        assert_eq!(matches.len(), 2);
+        assert!(matches.iter().all(|found| found.operator.is_some()));
+    }
+
+    #[test]
+    fn value_and_material_patterns_filter_matches() {
+        let pcf = test_pcf();
+        let query = Query {
+            value: Some(Regex::new("^3$").unwrap()),
+            material: Some(Regex::new("smoke").unwrap()),
+            ..Query::default()
+        };
+
+        let matches = query.find_in(&pcf);
+        assert_eq!(matches.len(), 1);
+        assert_eq!(matches[0].system.name, "smoke");
+        assert_eq!(matches[0].attribute, Some(("radius", &Attribute::from(3.0))));
+    }
+}
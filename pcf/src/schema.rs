@@ -0,0 +1,221 @@
+//! Typed structs for every known particle operator, generated from `default_values.pcf` by this crate's build script.
+//!
+//! Each operator's `functionName` maps to a struct implementing [`OperatorSchema`], whose fields are named after the
+//! operator's attributes and default to the values in `default_values.pcf`. [`KnownOperator`] can hold any of them.
+//!
+//! This module is only available with the `schema` feature.
+//!
+//! # Example
+//!
+//! Print the non-default attributes of every known operator.
+//! ```
+//! # use pcf::{Pcf, schema::KnownOperator};
+//! # fn example(pcf: &Pcf) -> Result<(), pcf::schema::SchemaError> {
+//! for system in pcf.particle_systems() {
+//!     for operator in system.all_operators() {
+//!         if let Some(known) = KnownOperator::from_operator(operator, pcf.symbols())? {
+//!             println!("{}: {known:?}", system.name);
+//!         }
+//!     }
+//! }
+//! # Ok(())
+//! # }
+//! ```
+
+use std::collections::HashMap;
+
+use thiserror::Error;
+
+use crate::{Attribute, Operator, Symbols, TypeMismatch};
+
+include!(concat!(env!("OUT_DIR"), "/schema.rs"));
+
+#[derive(Debug, Error)]
+pub enum SchemaError {
+    #[error("expected a '{expected}' operator, but found '{actual}'")]
+    FunctionName { expected: &'static str, actual: String },
+
+    #[error("the '{name}' attribute has the wrong type")]
+    Type {
+        name: String,
+        #[source]
+        source: TypeMismatch,
+    },
+}
+
+/// A particle operator with a known set of attributes.
+pub trait OperatorSchema: Default {
+    /// The `functionName` which identifies this operator.
+    const FUNCTION_NAME: &'static str;
+
+    /// Every field, as its attribute name & value.
+    fn attributes(&self) -> Vec<(&'static str, Attribute)>;
+
+    /// Sets the field for the attribute called `name`, returning `Ok(false)` if there is no such field.
+    ///
+    /// # Errors
+    ///
+    /// Returns [`TypeMismatch`] if `attribute` isn't the same type as the field.
+    fn set_attribute(&mut self, name: &str, attribute: &Attribute) -> Result<bool, TypeMismatch>;
+
+    /// Reads `operator`'s attributes into a new schema. Fields whose attribute isn't set on the operator keep their
+    /// default value, and attributes without a field are ignored.
+    ///
+    /// # Errors
+    ///
+    /// See [`SchemaError`].
+    fn from_operator(operator: &Operator, symbols: &Symbols) -> Result<Self, SchemaError> {
+        if operator.function_name != Self::FUNCTION_NAME {
+            return Err(SchemaError::FunctionName {
+                expected: Self::FUNCTION_NAME,
+                actual: operator.function_name.clone(),
+            });
+        }
+
+        let mut schema = Self::default();
+        read_attributes(operator, symbols, |name, attribute| {
+            schema.set_attribute(name, attribute)
+        })?;
+        Ok(schema)
+    }
+
+    /// Like [`OperatorSchema::attributes`], but only the fields which differ from their default value.
+    fn non_default_attributes(&self) -> Vec<(&'static str, Attribute)> {
+        self.attributes()
+            .into_iter()
+            .zip(Self::default().attributes())
+            .filter(|(attribute, default)| attribute != default)
+            .map(|(attribute, _)| attribute)
+            .collect()
+    }
+}
+
+impl KnownOperator {
+    /// Reads `operator` into the schema matching its `functionName`. Returns `Ok(None)` if the operator isn't known.
+    ///
+    /// # Errors
+    ///
+    /// See [`OperatorSchema::from_operator`].
+    pub fn from_operator(operator: &Operator, symbols: &Symbols) -> Result<Option<Self>, SchemaError> {
+        let Some(mut known) = Self::default_for(&operator.function_name) else {
+            return Ok(None);
+        };
+
+        read_attributes(operator, symbols, |name, attribute| {
+            known.set_attribute(name, attribute)
+        })?;
+        Ok(Some(known))
+    }
+}
+
+/// The default value of every attribute of every known operator, keyed by `functionName` then attribute name. This is
+/// the shape [`crate::Pcf::defaults_stripped`] expects.
+pub fn operator_defaults() -> HashMap<String, HashMap<String, Attribute>> {
+    KnownOperator::FUNCTION_NAMES
+        .iter()
+        .copied()
+        .filter_map(KnownOperator::default_for)
+        .map(|known| {
+            let attributes = known
+                .attributes()
+                .into_iter()
+                .map(|(name, attribute)| (name.to_string(), attribute))
+                .collect();
+
+            (known.function_name().to_string(), attributes)
+        })
+        .collect()
+}
+
+fn read_attributes(
+    operator: &Operator,
+    symbols: &Symbols,
+    mut set_attribute: impl FnMut(&str, &Attribute) -> Result<bool, TypeMismatch>,
+) -> Result<(), SchemaError> {
+    for (name_idx, attribute) in &operator.attributes {
+        let Some(name) = symbols.base.get_index(*name_idx as usize) else {
+            continue;
+        };
+
+        set_attribute(name, attribute).map_err(|source| SchemaError::Type {
+            name: name.to_string(),
+            source,
+        })?;
+    }
+
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use ordermap::OrderMap;
+
+    use super::{KnownOperator, MovementBasic, OperatorSchema, SchemaError, operator_defaults};
+    use crate::{Attribute, Operator, Symbols};
+
+    fn operator(function_name: &str, attributes: impl IntoIterator<Item = (u16, Attribute)>) -> Operator {
+        Operator {
+            name: String::new(),
+            function_name: function_name.to_string(),
+            signature: [0; 16],
+            attributes: attributes.into_iter().collect::<OrderMap<_, _>>(),
+        }
+    }
+
+    #[test]
+    fn every_known_operator_has_defaults() {
+        let defaults = operator_defaults();
+        assert_eq!(defaults.len(), KnownOperator::FUNCTION_NAMES.len());
+        assert!(defaults.contains_key("Movement Basic"));
+        assert!(defaults.contains_key("render_animated_sprites"));
+    }
+
+    #[test]
+    fn from_operator_reads_set_attributes() {
+        let Some(KnownOperator::MovementBasic(defaults)) = KnownOperator::default_for("Movement Basic") else {
+            panic!("expected Movement Basic to be known");
+        };
+
+        let (name, attribute) = defaults.attributes().remove(0);
+        let changed = match attribute {
+            Attribute::Float(value) => Attribute::from(value.0 + 1.0),
+            Attribute::Integer(value) => Attribute::Integer(value + 1),
+            Attribute::Vector3(dmx::Vector3(x, y, z)) => Attribute::Vector3(dmx::Vector3(x + 1.0, y, z)),
+            attribute => panic!("unexpected attribute type {}", attribute.type_name()),
+        };
+
+        let mut symbols = Symbols::new_with_all_special();
+        let (name_idx, _) = symbols.base.insert_full(name.into());
+        let operator = operator("Movement Basic", [(name_idx as u16, changed.clone())]);
+
+        let Some(known) = KnownOperator::from_operator(&operator, &symbols).unwrap() else {
+            panic!("expected Movement Basic to be known");
+        };
+
+        let KnownOperator::MovementBasic(schema) = known else {
+            panic!("expected a Movement Basic schema");
+        };
+        assert_eq!(schema.non_default_attributes(), vec![(name, changed)]);
+    }
+
+    #[test]
+    fn from_operator_rejects_mismatched_types() {
+        let mut symbols = Symbols::new_with_all_special();
+        let (name, _) = MovementBasic::default().attributes().remove(0);
+        let (name_idx, _) = symbols.base.insert_full(name.into());
+
+        let operator = operator(
+            "Movement Basic",
+            [(name_idx as u16, Attribute::StringArray(Box::from([])))],
+        );
+        let result = MovementBasic::from_operator(&operator, &symbols);
+        assert!(matches!(result, Err(SchemaError::Type { .. })));
+    }
+
+    #[test]
+    fn unknown_operators_are_skipped() {
+        let operator = operator("not a real operator", []);
+        let result = KnownOperator::from_operator(&operator, &Symbols::new_with_all_special()).unwrap();
+        assert!(result.is_none());
+    }
+}
@@ -0,0 +1,439 @@
+//! Counts & encoded byte totals for the contents of a [`Pcf`], to show where its size goes. Stats from many PCFs can be
+//! [added](PcfStats::add) together, to find which attributes & operators are worth stripping. [`StripStats`] describe
+//! what stripping actually removed, and are returned by each stripping function's `_with_stats` variant.
+//!
+//! # Example
+//!
+//! Find which attribute type takes up the most space across a set of PCFs.
+//! ```
+//! # use pcf::{Pcf, stats::PcfStats};
+//! # fn example(pcfs: &[Pcf]) -> Option<&'static str> {
+//! let mut stats = PcfStats::default();
+//! for pcf in pcfs {
+//!     stats.add(&pcf.stats());
+//! }
+//!
+//! let (type_name, _) = stats.attribute_types.iter().max_by_key(|(_, tally)| tally.bytes)?;
+//! Some(*type_name)
+//! # }
+//! ```
+
+use std::{collections::BTreeMap, ops::AddAssign};
+
+use dmx::{ElementIdx, FixedEncodedSize, Signature};
+
+use crate::{
+    attribute::Attribute,
+    new::{AttributeMap, Operator, Pcf, SymbolIdx},
+    symbol::Symbol,
+};
+
+/// How many of something there are, and how many bytes they take up when encoded.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct Tally {
+    pub count: usize,
+    pub bytes: usize,
+}
+
+impl AddAssign for Tally {
+    fn add_assign(&mut self, other: Self) {
+        self.count += other.count;
+        self.bytes += other.bytes;
+    }
+}
+
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct SystemStats {
+    pub name: String,
+    pub children: usize,
+    pub operators: usize,
+
+    /// the attributes of the system, its children, and its operators
+    pub attributes: usize,
+
+    /// see [`Pcf::system_sizes`]
+    pub bytes: usize,
+}
+
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct PcfStats {
+    /// the number of PCFs these stats were gathered from
+    pub pcfs: usize,
+
+    /// the sum of each PCF's [`Pcf::encoded_size`]
+    pub encoded_size: usize,
+
+    pub symbols: Tally,
+
+    /// every attribute of the root, systems, children & operators, by [`Attribute::type_name`]. Each attribute's bytes
+    /// include its name index & type.
+    pub attribute_types: BTreeMap<&'static str, Tally>,
+
+    /// the same attributes as [`PcfStats::attribute_types`], by name
+    pub attribute_names: BTreeMap<String, Tally>,
+
+    /// operators by function name. Each operator's bytes include its element, its attributes, and its reference from
+    /// its system.
+    pub operator_functions: BTreeMap<String, Tally>,
+
+    /// every particle system, in order
+    pub systems: Vec<SystemStats>,
+}
+
+impl PcfStats {
+    /// Adds `other` to these stats. `other`'s systems are appended.
+    pub fn add(&mut self, other: &Self) {
+        self.pcfs += other.pcfs;
+        self.encoded_size += other.encoded_size;
+        self.symbols += other.symbols;
+
+        for (type_name, tally) in &other.attribute_types {
+            *self.attribute_types.entry(type_name).or_default() += *tally;
+        }
+
+        for (name, tally) in &other.attribute_names {
+            *self.attribute_names.entry(name.clone()).or_default() += *tally;
+        }
+
+        for (function_name, tally) in &other.operator_functions {
+            *self.operator_functions.entry(function_name.clone()).or_default() += *tally;
+        }
+
+        self.systems.extend(other.systems.iter().cloned());
+    }
+
+    fn add_attributes(&mut self, pcf: &Pcf, attributes: &AttributeMap) -> usize {
+        let mut bytes = 0;
+        for (name_idx, attribute) in attributes {
+            let tally = Tally {
+                count: 1,
+                bytes: encoded_attribute_size(attribute),
+            };
+
+            bytes += tally.bytes;
+            *self.attribute_types.entry(attribute.type_name()).or_default() += tally;
+
+            let name = pcf.symbols().base.get_index(usize::from(*name_idx));
+            let name = name.map_or("<unknown>", Symbol::as_str);
+            *self.attribute_names.entry(name.to_string()).or_default() += tally;
+        }
+
+        bytes
+    }
+
+    fn add_operator(&mut self, pcf: &Pcf, operator: &Operator) {
+        let element_size = size_of::<u16>() + operator.name.len() + 1 + size_of::<Signature>();
+
+        // the attribute counter, the function name attribute, and the operator's entry in its system's list
+        let overhead = size_of::<u32>()
+            + size_of::<SymbolIdx>()
+            + size_of::<u8>()
+            + operator.function_name.len()
+            + 1
+            + ElementIdx::ENCODED_SIZE;
+
+        let attributes_size = self.add_attributes(pcf, &operator.attributes);
+        *self
+            .operator_functions
+            .entry(operator.function_name.clone())
+            .or_default() += Tally {
+            count: 1,
+            bytes: element_size + overhead + attributes_size,
+        };
+    }
+}
+
+/// The parts of a [`Pcf`] which stripping shrinks.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct StripCounts {
+    /// the attributes of the root, systems, children & operators
+    pub attributes: usize,
+
+    pub symbols: usize,
+
+    /// see [`Pcf::encoded_size`]
+    pub encoded_size: usize,
+}
+
+impl StripCounts {
+    pub fn of(pcf: &Pcf) -> Self {
+        let attributes = pcf.root().attributes().len()
+            + pcf
+                .particle_systems()
+                .iter()
+                .map(|system| {
+                    system.attributes.len()
+                        + system
+                            .children
+                            .iter()
+                            .map(|child| child.attributes.len())
+                            .sum::<usize>()
+                        + system
+                            .all_operators()
+                            .map(|operator| operator.attributes.len())
+                            .sum::<usize>()
+                })
+                .sum::<usize>();
+
+        Self {
+            attributes,
+            symbols: pcf.symbols().base.len(),
+            encoded_size: pcf.encoded_size(),
+        }
+    }
+}
+
+impl AddAssign for StripCounts {
+    fn add_assign(&mut self, other: Self) {
+        self.attributes += other.attributes;
+        self.symbols += other.symbols;
+        self.encoded_size += other.encoded_size;
+    }
+}
+
+/// What stripping a [`Pcf`] removed from it, see [`Pcf::stripped_with_stats`]. Stats from many PCFs can be
+/// [added](StripStats::add) together.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct StripStats {
+    pub before: StripCounts,
+    pub after: StripCounts,
+
+    /// how many of each attribute were removed, by name. Only strips which remove attributes by name fill this in, like
+    /// [`Pcf::defaults_stripped_with_stats`].
+    pub removed_attributes: BTreeMap<String, usize>,
+
+    /// every symbol removed by [`Pcf::unused_symbols_stripped_with_stats`]
+    pub removed_symbols: Vec<String>,
+}
+
+impl StripStats {
+    /// The stats of a strip which started with `before`, and left `pcf`.
+    pub fn new(before: StripCounts, pcf: &Pcf) -> Self {
+        Self {
+            before,
+            after: StripCounts::of(pcf),
+            ..Self::default()
+        }
+    }
+
+    /// Combines these stats with those of a strip made after this one, as though both were a single strip.
+    #[must_use]
+    pub fn then(mut self, next: Self) -> Self {
+        self.after = next.after;
+        for (name, count) in next.removed_attributes {
+            *self.removed_attributes.entry(name).or_default() += count;
+        }
+
+        self.removed_symbols.extend(next.removed_symbols);
+        self
+    }
+
+    /// Adds `other`, the stats of another PCF, to these stats.
+    pub fn add(&mut self, other: &Self) {
+        self.before += other.before;
+        self.after += other.after;
+        for (name, count) in &other.removed_attributes {
+            *self.removed_attributes.entry(name.clone()).or_default() += count;
+        }
+
+        self.removed_symbols.extend(other.removed_symbols.iter().cloned());
+    }
+
+    pub fn attributes_removed(&self) -> usize {
+        self.before.attributes.saturating_sub(self.after.attributes)
+    }
+
+    pub fn symbols_removed(&self) -> usize {
+        self.before.symbols.saturating_sub(self.after.symbols)
+    }
+
+    pub fn bytes_saved(&self) -> usize {
+        self.before.encoded_size.saturating_sub(self.after.encoded_size)
+    }
+}
+
+/// The size of an encoded attribute, including its name index & type.
+fn encoded_attribute_size(attribute: &Attribute) -> usize {
+    size_of::<SymbolIdx>() + size_of::<u8>() + attribute.get_encoded_size()
+}
+
+impl Pcf {
+    /// Gathers [`PcfStats`] about this PCF.
+    pub fn stats(&self) -> PcfStats {
+        let mut stats = PcfStats {
+            pcfs: 1,
+            encoded_size: self.encoded_size(),
+            symbols: Tally {
+                count: self.symbols().base.len(),
+                bytes: self.compute_encoded_symbols_size(),
+            },
+            ..PcfStats::default()
+        };
+
+        stats.add_attributes(self, self.root().attributes());
+
+        let sizes = self.system_sizes();
+        for (system, bytes) in self.particle_systems().iter().zip(sizes) {
+            stats.add_attributes(self, &system.attributes);
+            for child in &system.children {
+                stats.add_attributes(self, &child.attributes);
+            }
+
+            for operator in system.all_operators() {
+                stats.add_operator(self, operator);
+            }
+
+            stats.systems.push(SystemStats {
+                name: system.name.clone(),
+                children: system.children.len(),
+                operators: system.all_operators().count(),
+                attributes: system.attributes.len()
+                    + system
+                        .children
+                        .iter()
+                        .map(|child| child.attributes.len())
+                        .sum::<usize>()
+                    + system
+                        .all_operators()
+                        .map(|operator| operator.attributes.len())
+                        .sum::<usize>(),
+                bytes,
+            });
+        }
+
+        stats
+    }
+
+    /// Strips this PCF with `strip`, returning the stripped PCF along with what was removed. Only the counts are known,
+    /// so prefer a stripping function's own `_with_stats` variant where there is one.
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// # use pcf::Pcf;
+    /// # fn example(pcf: Pcf) -> Pcf {
+    /// let (pcf, stats) = pcf.stripped_with_stats(Pcf::editor_names_stripped);
+    /// println!("stripping editor names saved {} bytes", stats.bytes_saved());
+    /// # pcf
+    /// # }
+    /// ```
+    pub fn stripped_with_stats(self, strip: impl FnOnce(Self) -> Self) -> (Self, StripStats) {
+        let before = StripCounts::of(&self);
+        let pcf = strip(self);
+        let stats = StripStats::new(before, &pcf);
+        (pcf, stats)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use dmx::dmx::Version;
+    use ordermap::OrderMap;
+
+    use std::collections::{BTreeMap, HashMap};
+
+    use super::{PcfStats, StripCounts, StripStats, Tally};
+    use crate::{Attribute, Comparison, Operator, ParticleSystem, Pcf, Root, Symbols, new::SymbolIdx};
+
+    fn test_pcf() -> Pcf {
+        let mut symbols = Symbols::new_with_all_special();
+        let (radius, _) = symbols.base.insert_full("radius".into());
+        let (material, _) = symbols.base.insert_full("material".into());
+
+        let system = ParticleSystem {
+            name: "fire".to_string(),
+            attributes: OrderMap::from([
+                (radius as SymbolIdx, Attribute::Float(5.0.into())),
+                (material as SymbolIdx, Attribute::String("effects/flame".to_string())),
+            ]),
+            renderers: Box::from([Operator {
+                name: "render".to_string(),
+                function_name: "render_animated_sprites".to_string(),
+                signature: [1; 16],
+                attributes: OrderMap::from([(radius as SymbolIdx, Attribute::Float(2.0.into()))]),
+            }]),
+            ..ParticleSystem::default()
+        };
+
+        Pcf::new(
+            Version::Binary2Pcf1,
+            symbols,
+            Root::new("untitled".to_string(), [0; 16], Box::from([system]), OrderMap::new()),
+        )
+    }
+
+    #[test]
+    fn attributes_and_operators_are_tallied() {
+        let stats = test_pcf().stats();
+
+        assert_eq!(stats.attribute_types["float"], Tally { count: 2, bytes: 14 });
+        assert_eq!(stats.attribute_types["string"], Tally { count: 1, bytes: 17 });
+        assert_eq!(stats.attribute_names["radius"].count, 2);
+        assert_eq!(stats.operator_functions["render_animated_sprites"].count, 1);
+        assert_eq!(stats.systems[0].operators, 1);
+        assert_eq!(stats.systems[0].attributes, 3);
+        assert_eq!(stats.systems[0].bytes, stats.encoded_size);
+    }
+
+    #[test]
+    fn stats_can_be_added() {
+        let mut stats = PcfStats::default();
+        stats.add(&test_pcf().stats());
+        stats.add(&test_pcf().stats());
+
+        assert_eq!(stats.pcfs, 2);
+        assert_eq!(stats.attribute_types["float"].count, 4);
+        assert_eq!(stats.systems.len(), 2);
+        assert_eq!(stats.encoded_size, test_pcf().encoded_size() * 2);
+    }
+
+    #[test]
+    fn stripping_reports_what_was_removed() {
+        let pcf = test_pcf();
+        let before = StripCounts::of(&pcf);
+        assert_eq!(before.attributes, 3);
+
+        let particle_defaults = HashMap::from([("material", Attribute::String("effects/flame".to_string()))]);
+        let (pcf, stats) = pcf.defaults_stripped_with_stats(&particle_defaults, &HashMap::new(), Comparison::Exact);
+        assert_eq!(stats.before, before);
+        assert_eq!(stats.attributes_removed(), 1);
+        assert_eq!(stats.removed_attributes, BTreeMap::from([("material".to_string(), 1)]));
+        assert_eq!(stats.symbols_removed(), 0);
+        assert_eq!(stats.after.encoded_size, pcf.encoded_size());
+        assert!(stats.bytes_saved() > 0);
+
+        // nothing uses "material" anymore, nor the special symbols for lists the PCF doesn't have
+        let (pcf, stats) = pcf.unused_symbols_stripped_with_stats();
+        assert_eq!(stats.attributes_removed(), 0);
+        assert_eq!(stats.after.symbols, pcf.symbols().base.len());
+        assert_eq!(stats.removed_symbols.len(), stats.symbols_removed());
+        assert!(stats.removed_symbols.contains(&"material".to_string()));
+        assert_eq!(pcf.symbols().base.get_index_of("material"), None);
+    }
+
+    #[test]
+    fn sequential_strips_combine() {
+        let particle_defaults = HashMap::from([("radius", Attribute::Float(5.0.into()))]);
+        let operator_defaults = HashMap::from([("radius", Attribute::Float(2.0.into()))]);
+        let (pcf, defaults) = test_pcf().defaults_stripped_nth_with_stats(
+            usize::MAX,
+            &particle_defaults,
+            &operator_defaults,
+            Comparison::Exact,
+        );
+        assert_eq!(defaults.removed_attributes, BTreeMap::from([("radius".to_string(), 2)]));
+
+        let (pcf, symbols) = pcf.unused_symbols_stripped_with_stats();
+        let stats = defaults.clone().then(symbols);
+        assert_eq!(stats.before, defaults.before);
+        assert_eq!(stats.after, StripCounts::of(&pcf));
+        assert_eq!(stats.attributes_removed(), 2);
+        assert!(stats.removed_symbols.contains(&"radius".to_string()));
+
+        let mut total = StripStats::default();
+        total.add(&stats);
+        total.add(&stats);
+        assert_eq!(total.removed_attributes["radius"], 4);
+        assert_eq!(total.bytes_saved(), stats.bytes_saved() * 2);
+    }
+}
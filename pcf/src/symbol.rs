@@ -0,0 +1,209 @@
+//! The strings in a [`Pcf`]'s symbol table, and a [`SymbolPool`] to share them between PCFs.
+//!
+//! Nearly every PCF has the same couple thousand attribute names & element types in its symbol table, so holding
+//! hundreds of PCFs at once holds hundreds of copies of each. A [`Symbol`] is reference counted, and
+//! [`Pcf::intern_symbols`] swaps every symbol for the pool's copy of it. Each PCF's [`Symbols`] is then only a map from
+//! its own symbol indices to the shared strings.
+//!
+//! Pooling is optional; a PCF which was never interned works the same, it just doesn't share its strings. Merging PCFs
+//! which were interned into the same pool is faster, since a symbol both have at the same index is matched without
+//! hashing it.
+//!
+//! # Example
+//!
+//! Decode many PCFs, sharing their symbols.
+//! ```
+//! # use pcf::{Pcf, SymbolPool};
+//! # fn example(decoded: Vec<Pcf>) {
+//! let pool = SymbolPool::new();
+//! let pcfs: Vec<_> = decoded
+//!     .into_iter()
+//!     .map(|mut pcf| {
+//!         pcf.intern_symbols(&pool);
+//!         pcf
+//!     })
+//!     .collect();
+//! # }
+//! ```
+//!
+//! [`Symbols`]: crate::Symbols
+
+use std::{
+    borrow::Borrow,
+    collections::HashSet,
+    fmt::{self, Debug, Display},
+    hash::{Hash, Hasher},
+    ops::Deref,
+    sync::{Arc, RwLock},
+};
+
+#[cfg(doc)]
+use crate::Pcf;
+
+/// A string in a symbol table. Cloning a symbol shares its string rather than copying it.
+#[derive(Clone, PartialOrd, Ord)]
+pub struct Symbol(Arc<str>);
+
+impl Symbol {
+    pub fn as_str(&self) -> &str {
+        &self.0
+    }
+
+    /// Whether both symbols share the same string, which is always the case for equal symbols interned into the same
+    /// [`SymbolPool`].
+    pub fn ptr_eq(&self, other: &Self) -> bool {
+        Arc::ptr_eq(&self.0, &other.0)
+    }
+}
+
+impl Deref for Symbol {
+    type Target = str;
+
+    fn deref(&self) -> &str {
+        &self.0
+    }
+}
+
+impl AsRef<str> for Symbol {
+    fn as_ref(&self) -> &str {
+        &self.0
+    }
+}
+
+// N.B. symbols are looked up by `&str`, so they must hash & compare exactly like their strings do
+impl Borrow<str> for Symbol {
+    fn borrow(&self) -> &str {
+        &self.0
+    }
+}
+
+impl Hash for Symbol {
+    fn hash<H: Hasher>(&self, state: &mut H) {
+        self.0.hash(state);
+    }
+}
+
+impl PartialEq for Symbol {
+    fn eq(&self, other: &Self) -> bool {
+        self.ptr_eq(other) || self.0 == other.0
+    }
+}
+
+impl Eq for Symbol {}
+
+impl PartialEq<str> for Symbol {
+    fn eq(&self, other: &str) -> bool {
+        &*self.0 == other
+    }
+}
+
+impl PartialEq<&str> for Symbol {
+    fn eq(&self, other: &&str) -> bool {
+        &*self.0 == *other
+    }
+}
+
+impl PartialEq<String> for Symbol {
+    fn eq(&self, other: &String) -> bool {
+        &*self.0 == other.as_str()
+    }
+}
+
+impl From<&str> for Symbol {
+    fn from(value: &str) -> Self {
+        Self(Arc::from(value))
+    }
+}
+
+impl From<String> for Symbol {
+    fn from(value: String) -> Self {
+        Self(Arc::from(value))
+    }
+}
+
+impl Display for Symbol {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        Display::fmt(&*self.0, f)
+    }
+}
+
+impl Debug for Symbol {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        Debug::fmt(&*self.0, f)
+    }
+}
+
+/// A set of symbols shared by many PCFs, see the [module docs](self). Clones share the same pool, so one can be handed
+/// to each thread decoding PCFs.
+#[derive(Debug, Clone, Default)]
+pub struct SymbolPool {
+    symbols: Arc<RwLock<HashSet<Symbol>>>,
+}
+
+impl SymbolPool {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// The pool's copy of `symbol`, which is added to the pool if it isn't there yet.
+    pub fn intern(&self, symbol: &Symbol) -> Symbol {
+        if let Some(pooled) = self.symbols.read().unwrap().get(symbol.as_str()) {
+            return pooled.clone();
+        }
+
+        // another thread may have added the symbol between the locks, in which case its copy is kept
+        let mut symbols = self.symbols.write().unwrap();
+        if let Some(pooled) = symbols.get(symbol.as_str()) {
+            return pooled.clone();
+        }
+
+        symbols.insert(symbol.clone());
+        symbol.clone()
+    }
+
+    /// The number of distinct symbols in the pool.
+    pub fn len(&self) -> usize {
+        self.symbols.read().unwrap().len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+
+    /// Removes the symbols which only the pool still holds, e.g. once the PCFs which used them were dropped.
+    pub fn prune(&self) {
+        self.symbols
+            .write()
+            .unwrap()
+            .retain(|symbol| Arc::strong_count(&symbol.0) > 1);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::collections::HashMap;
+
+    use super::{Symbol, SymbolPool};
+
+    #[test]
+    fn symbols_are_looked_up_by_their_strings() {
+        let symbols = HashMap::from([(Symbol::from("radius"), 1)]);
+
+        assert_eq!(symbols.get("radius"), Some(&1));
+        assert_eq!(Symbol::from("radius"), "radius");
+    }
+
+    #[test]
+    fn interned_symbols_share_their_strings() {
+        let pool = SymbolPool::new();
+        let first = pool.intern(&Symbol::from("radius"));
+        let second = pool.intern(&Symbol::from("radius".to_string()));
+
+        assert!(first.ptr_eq(&second));
+        assert_eq!(pool.len(), 1);
+
+        drop((first, second));
+        pool.prune();
+        assert!(pool.is_empty());
+    }
+}
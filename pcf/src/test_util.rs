@@ -0,0 +1,100 @@
+//! Fixtures shared by this crate's tests.
+
+use dmx::dmx::Version;
+
+use crate::{
+    Pcf, Root,
+    new::{AttributeMap, Child, Operator, ParticleSystem, SymbolIdx, Symbols, element_idx},
+};
+
+/// Builds a [`Pcf`] out of particle systems. Its symbols include every special element & attribute name, unless it's
+/// given others with [`PcfBuilder::with_symbols`].
+pub(crate) struct PcfBuilder {
+    symbols: Symbols,
+    particle_systems: Vec<ParticleSystem>,
+    attributes: AttributeMap,
+}
+
+impl Default for PcfBuilder {
+    fn default() -> Self {
+        Self::with_symbols(Symbols::new_with_all_special())
+    }
+}
+
+impl PcfBuilder {
+    pub(crate) fn with_symbols(symbols: Symbols) -> Self {
+        Self {
+            symbols,
+            particle_systems: Vec::new(),
+            attributes: AttributeMap::new(),
+        }
+    }
+
+    /// Adds `name` to the symbols if it isn't one already, and returns its index.
+    pub(crate) fn symbol(&mut self, name: &str) -> SymbolIdx {
+        self.symbols.base.insert_full(name.into()).0 as SymbolIdx
+    }
+
+    pub(crate) fn system(mut self, system: ParticleSystem) -> Self {
+        self.particle_systems.push(system);
+        self
+    }
+
+    pub(crate) fn systems(mut self, systems: impl IntoIterator<Item = ParticleSystem>) -> Self {
+        self.particle_systems.extend(systems);
+        self
+    }
+
+    /// Sets the root element's attributes.
+    pub(crate) fn attributes(mut self, attributes: AttributeMap) -> Self {
+        self.attributes = attributes;
+        self
+    }
+
+    pub(crate) fn build(self) -> Pcf {
+        Pcf::new(
+            Version::Binary2Pcf1,
+            self.symbols,
+            Root::new(
+                "untitled".to_string(),
+                [0; 16],
+                self.particle_systems.into_boxed_slice(),
+                self.attributes,
+            ),
+        )
+    }
+}
+
+/// A system whose children are the systems at `children`. Each child is named after the system.
+pub(crate) fn system(name: &str, children: &[usize]) -> ParticleSystem {
+    ParticleSystem {
+        name: name.to_string(),
+        children: children
+            .iter()
+            .map(|child| Child {
+                name: format!("{name}_child"),
+                signature: [0; 16],
+                child: element_idx(*child),
+                attributes: AttributeMap::new(),
+            })
+            .collect(),
+        ..ParticleSystem::default()
+    }
+}
+
+/// Builds a PCF where each system is `(name, child indices)`.
+pub(crate) fn pcf_with_systems(systems: &[(&str, &[usize])]) -> Pcf {
+    PcfBuilder::default()
+        .systems(systems.iter().map(|(name, children)| system(name, children)))
+        .build()
+}
+
+/// An operator without any attributes.
+pub(crate) fn operator(name: &str, function_name: &str) -> Operator {
+    Operator {
+        name: name.to_string(),
+        function_name: function_name.to_string(),
+        signature: [0; 16],
+        attributes: AttributeMap::new(),
+    }
+}
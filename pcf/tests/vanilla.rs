@@ -0,0 +1,202 @@
+//! Decodes, re-encodes, strips & merges every particle shipped with TF2, as a regression gate for changes to the
+//! decoder, encoder & stripping that synthetic particles wouldn't catch.
+//!
+//! These need a real TF2 install, so they're ignored by default. Point [`TF_DIR_VAR`] at its `tf/` folder and run them
+//! with e.g. `TF_DIR=".../Team Fortress 2/tf" cargo test -p pcf --test vanilla -- --ignored`.
+
+use std::{
+    collections::HashSet,
+    env, fs,
+    io::Read,
+    path::{Path, PathBuf},
+};
+
+use pcf::{MergePolicy, Pcf};
+use vpk::VPK;
+
+/// The path of the `tf/` folder to read the vanilla particles from.
+const TF_DIR_VAR: &str = "TF_DIR";
+
+const TF2_VPK_NAME: &str = "tf2_misc_dir.vpk";
+
+/// Only every `MERGE_SAMPLE_STEP`th vanilla PCF is merged by [`a_sample_of_vanilla_pcfs_merge`], so the merged graph
+/// stays well within the symbol limit.
+const MERGE_SAMPLE_STEP: usize = 8;
+
+fn tf_dir() -> PathBuf {
+    let Some(tf_dir) = env::var_os(TF_DIR_VAR) else {
+        panic!("{TF_DIR_VAR} should be set to the 'Team Fortress 2/tf' folder of a TF2 install");
+    };
+
+    PathBuf::from(tf_dir)
+}
+
+/// Every vanilla PCF in the install, by its path, e.g. `particles/explosion.pcf`. They're read from the install's VPK
+/// if it has one, and from its loose `particles/` folder otherwise.
+fn vanilla_pcfs() -> Vec<(String, Vec<u8>)> {
+    let tf_dir = tf_dir();
+    let vpk_path = tf_dir.join(TF2_VPK_NAME);
+    let mut pcfs = if vpk_path.is_file() {
+        read_vpk_pcfs(&vpk_path)
+    } else {
+        read_loose_pcfs(&tf_dir)
+    };
+
+    assert!(!pcfs.is_empty(), "'{}' should have vanilla particles", tf_dir.display());
+    pcfs.sort_by(|(a, _), (b, _)| a.cmp(b));
+    pcfs
+}
+
+fn read_vpk_pcfs(vpk_path: &Path) -> Vec<(String, Vec<u8>)> {
+    let vpk = VPK::read(vpk_path).expect("the install's VPK should be readable");
+    vpk.tree
+        .iter()
+        .filter(|(path, _)| path.starts_with("particles/") && path.ends_with(".pcf"))
+        .map(|(path, entry)| {
+            let mut data = Vec::new();
+            entry
+                .reader()
+                .and_then(|mut reader| reader.read_to_end(&mut data))
+                .unwrap_or_else(|err| panic!("{path} should be readable from the VPK: {err}"));
+
+            (path.clone(), data)
+        })
+        .collect()
+}
+
+fn read_loose_pcfs(tf_dir: &Path) -> Vec<(String, Vec<u8>)> {
+    fs::read_dir(tf_dir.join("particles"))
+        .expect("the install should have a particles/ folder")
+        .map(|entry| entry.expect("the particles/ folder should be readable").path())
+        .filter(|path| path.extension().is_some_and(|extension| extension == "pcf"))
+        .map(|path| {
+            let name = format!("particles/{}", path.file_name().unwrap().to_string_lossy());
+            let data = fs::read(&path).unwrap_or_else(|err| panic!("{name} should be readable: {err}"));
+            (name, data)
+        })
+        .collect()
+}
+
+fn decode(name: &str, data: &[u8]) -> Pcf {
+    pcf::decode(&mut &data[..]).unwrap_or_else(|err| panic!("{name} should decode: {err}"))
+}
+
+fn encode(name: &str, pcf: Pcf) -> Vec<u8> {
+    let size = pcf.encoded_size();
+    let mut data = Vec::with_capacity(size);
+    pcf::encode(pcf, &mut data).unwrap_or_else(|err| panic!("{name} should encode: {err}"));
+    assert_eq!(data.len(), size, "{name} should be encoded in exactly its encoded size");
+    data
+}
+
+/// Encodes `pcf` and decodes it again, checking that nothing was lost on the way.
+fn round_trip(name: &str, pcf: Pcf) -> Pcf {
+    let content_hash = pcf.content_hash();
+    let encoded = encode(name, pcf);
+    let decoded = decode(name, &encoded);
+    assert_eq!(
+        decoded.content_hash(),
+        content_hash,
+        "{name} should decode to what was encoded"
+    );
+
+    // the encoder only depends on the PCF, so encoding the decoded PCF again must produce the same bytes
+    assert!(
+        encode(name, decoded.clone()) == encoded,
+        "{name} should encode the same way twice"
+    );
+    decoded
+}
+
+#[test]
+#[ignore = "needs a TF2 install, see the module docs"]
+fn every_vanilla_pcf_round_trips() {
+    for (name, data) in vanilla_pcfs() {
+        let pcf = decode(&name, &data);
+        pcf.validate_signatures()
+            .unwrap_or_else(|err| panic!("{name} should have unique signatures: {err}"));
+
+        let system_count = pcf.particle_systems().len();
+        let decoded = round_trip(&name, pcf);
+        assert_eq!(
+            decoded.particle_systems().len(),
+            system_count,
+            "{name} should keep all of its systems"
+        );
+        decoded
+            .validate_signatures()
+            .unwrap_or_else(|err| panic!("{name} should have unique signatures after a round trip: {err}"));
+    }
+}
+
+#[test]
+#[ignore = "needs a TF2 install, see the module docs"]
+fn every_vanilla_pcf_can_be_stripped() {
+    for (name, data) in vanilla_pcfs() {
+        let pcf = decode(&name, &data);
+        let size = pcf.encoded_size();
+        let system_count = pcf.particle_systems().len();
+
+        let (stripped, stats) = pcf.editor_names_stripped().unused_symbols_stripped_with_stats();
+        assert!(
+            stripped.encoded_size() <= size,
+            "stripping {name} shouldn't make it bigger"
+        );
+        assert_eq!(
+            stats.after.encoded_size,
+            stripped.encoded_size(),
+            "{name}'s strip stats should be accurate"
+        );
+
+        let stripped = round_trip(&name, stripped);
+        assert_eq!(
+            stripped.particle_systems().len(),
+            system_count,
+            "stripping {name} shouldn't remove systems"
+        );
+    }
+}
+
+#[test]
+#[ignore = "needs a TF2 install, see the module docs"]
+fn a_sample_of_vanilla_pcfs_merge() {
+    let mut pcfs = vanilla_pcfs()
+        .into_iter()
+        .step_by(MERGE_SAMPLE_STEP)
+        .map(|(name, data)| decode(&name, &data).unused_symbols_stripped());
+
+    let mut merged = pcfs.next().expect("there should be at least one vanilla PCF");
+    let mut names: HashSet<String> = merged
+        .particle_systems()
+        .iter()
+        .map(|system| system.name.clone())
+        .collect();
+    for mut pcf in pcfs {
+        names.extend(pcf.particle_systems().iter().map(|system| system.name.clone()));
+
+        // some vanilla systems are defined in more than one PCF, and the game keeps whichever it loaded first
+        merged
+            .merged_in_with_policy(&mut pcf, MergePolicy::KeepSelf)
+            .unwrap_or_else(|err| panic!("the sampled vanilla particles should merge: {err}"));
+    }
+
+    let merged = round_trip("the merged sample", merged);
+    merged
+        .validate_signatures()
+        .expect("the merged sample should have unique signatures");
+
+    let merged_names: HashSet<_> = merged
+        .particle_systems()
+        .iter()
+        .map(|system| system.name.clone())
+        .collect();
+    assert_eq!(
+        merged_names, names,
+        "the merged sample should have every sampled system"
+    );
+    assert_eq!(
+        merged.particle_systems().len(),
+        names.len(),
+        "the merged sample should have each system once"
+    );
+}
@@ -0,0 +1,142 @@
+//! Packs 400 particle graphs of varying size into 60 bins, shaped like packing an install into the vanilla particles:
+//! the bins start with a little data and have capacities ranging from a few to a few hundred kilobytes.
+
+use std::{cmp::Reverse, hint::black_box};
+
+use criterion::{BatchSize, Criterion, criterion_group, criterion_main};
+use dmx::dmx::Version;
+use pcf::{Attribute, AttributeMap, Operator, ParticleSystem, Pcf, Root, Symbols};
+use pcfpack::{Bin, BinPack, Packer};
+
+const BINS: usize = 60;
+const GRAPHS: usize = 400;
+const ATTRIBUTES: [&str; 4] = ["radius", "alpha", "lifetime", "rate"];
+
+/// A graph of `systems` systems, each with a handful of operators.
+fn graph(name: &str, systems: usize) -> Pcf {
+    let mut symbols = Symbols::new_with_all_special();
+    for attribute in ATTRIBUTES {
+        symbols.base.insert(attribute.into());
+    }
+
+    let attributes = || -> AttributeMap {
+        ATTRIBUTES
+            .iter()
+            .enumerate()
+            .map(|(value, attribute)| {
+                let name_idx = symbols.base.get_index_of(*attribute).unwrap() as u16;
+                (name_idx, Attribute::Integer(value as i32))
+            })
+            .collect()
+    };
+
+    let systems = (0..systems)
+        .map(|system| ParticleSystem {
+            name: format!("{name}_{system}"),
+            operators: (0..6)
+                .map(|operator| Operator {
+                    name: format!("operator_{operator}"),
+                    function_name: "Alpha Fade In Random".to_string(),
+                    signature: [0; 16],
+                    attributes: attributes(),
+                })
+                .collect(),
+            attributes: attributes(),
+            ..ParticleSystem::default()
+        })
+        .collect();
+
+    let root = Root::new("untitled".to_string(), [0; 16], systems, AttributeMap::new());
+    Pcf::new(Version::Binary2Pcf1, symbols, root)
+}
+
+/// Every bin, with capacities varying so that some fill up long before others.
+fn bins() -> Vec<Bin> {
+    (0..BINS)
+        .map(|idx| {
+            let data = graph(&format!("bin_{idx}"), 1);
+            let capacity = data.encoded_size() as u64 + 4_000 * (idx as u64 % 12 + 1);
+            Bin::new(capacity, format!("particles/bin_{idx}.pcf"), data)
+        })
+        .collect()
+}
+
+/// Graphs of between 1 & 8 systems, largest first, like split addon & vanilla particles.
+fn graphs() -> Vec<Pcf> {
+    let mut graphs: Vec<_> = (0..GRAPHS)
+        .map(|idx| graph(&format!("graph_{idx}"), idx * 7 % 8 + 1))
+        .collect();
+    graphs.sort_by_key(|graph| Reverse(graph.encoded_size()));
+    graphs
+}
+
+fn pack(c: &mut Criterion) {
+    let graphs = graphs();
+
+    let mut packer = Packer::new(bins());
+    for (idx, mut graph) in graphs.clone().into_iter().enumerate() {
+        let _ = packer.pack(format!("graph_{idx}"), &mut graph);
+    }
+
+    let report = packer.report();
+    println!(
+        "packed {} of {GRAPHS} graphs into {} of {} bytes",
+        report.placements.len(),
+        report.used(),
+        report.capacity()
+    );
+
+    let mut group = c.benchmark_group("pack");
+    group.bench_function("serial", |b| {
+        b.iter_batched(
+            || (bins(), graphs.clone()),
+            |(mut bins, graphs)| {
+                for mut graph in graphs {
+                    let _ = bins.pack(&mut graph);
+                }
+
+                bins
+            },
+            BatchSize::LargeInput,
+        );
+    });
+    group.bench_function("parallel", |b| {
+        b.iter_batched(
+            || (bins(), graphs.clone()),
+            |(mut bins, graphs)| {
+                for mut graph in graphs {
+                    let _ = bins.pack_parallel(&mut graph);
+                }
+
+                bins
+            },
+            BatchSize::LargeInput,
+        );
+    });
+    group.bench_function("packer", |b| {
+        b.iter_batched(
+            || (Packer::new(bins()), graphs.clone()),
+            |(mut packer, graphs)| {
+                for (idx, mut graph) in graphs.into_iter().enumerate() {
+                    let _ = packer.pack(format!("graph_{idx}"), &mut graph);
+                }
+
+                packer.report()
+            },
+            BatchSize::LargeInput,
+        );
+    });
+    group.bench_function("merged size", |b| {
+        let bin = graph("bin", 40);
+        b.iter(|| {
+            graphs
+                .iter()
+                .map(|graph| black_box(&bin).compute_merged_size(graph))
+                .sum::<usize>()
+        });
+    });
+    group.finish();
+}
+
+criterion_group!(benches, pack);
+criterion_main!(benches);
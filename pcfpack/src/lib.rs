@@ -1,11 +1,19 @@
 pub mod old;
+mod packer;
+#[cfg(test)]
+mod test_util;
+
+use std::cmp::Reverse;
 
 use pcf::Pcf;
+use rayon::iter::{IndexedParallelIterator, IntoParallelRefIterator, ParallelIterator};
 use thiserror::Error;
 
+pub use packer::{BinUsage, Failure, PackReport, Packer, Placement};
+
 pub type Bins = Vec<Bin>;
 
-#[derive(Debug)]
+#[derive(Debug, Clone)]
 pub struct Bin {
     capacity: u64,
     name: String,
@@ -46,50 +54,141 @@ pub trait BinPack {
     ///
     /// If there is an error when merging, then [`Error::CantMerge`] is returned.
     fn pack(&mut self, from: &mut Pcf) -> Result<(), Error>;
+
+    /// Like [`BinPack::pack`], but the merged size of `from` is estimated for every bin concurrently.
+    ///
+    /// The chosen bin is always the same one [`BinPack::pack`] would choose, so packing the same elements in the same
+    /// order into bins with the same capacities produces identical bins regardless of thread scheduling.
+    ///
+    /// ## Errors
+    ///
+    /// See [`BinPack::pack`].
+    fn pack_parallel(&mut self, from: &mut Pcf) -> Result<(), Error>;
 }
 
 impl BinPack for [Bin] {
     fn pack(&mut self, from: &mut Pcf) -> Result<(), Error> {
-        let mut packed = false;
         // we assume that the bins are always sorted heaviest to lightest.
-        for bin in self.iter_mut() {
+        let fit = self.iter().enumerate().find_map(|(idx, bin)| {
             let estimated_size = bin.data.compute_merged_size(from);
-            if estimated_size as u64 > bin.capacity {
-                continue;
-            }
-
-            // let estimated_symbols_size = bin.data.compute_encoded_symbols_size_after_merge(from);
-            // let estimated_elements_size = bin.data.compute_encoded_elements_size_after_merge(from);
-            // let estimated_root_size = bin.data.compute_encoded_root_attributes_size_after_merge(from);
-            // let estimated_attributes_size = bin.data.compute_encoded_attributes_size_after_merge(from);
+            (estimated_size as u64 <= bin.capacity).then_some((idx, estimated_size))
+        });
 
-            bin.data.merged_in(from)?;
+        let Some((idx, estimated_size)) = fit else {
+            return Err(no_fit(self, from));
+        };
 
-            // assert_eq!(bin.data.compute_encoded_symbols_size(), estimated_symbols_size);
-            // assert_eq!(bin.data.compute_encoded_elements_size(), estimated_elements_size);
-            // assert_eq!(bin.data.compute_encoded_root_attributes_size(), estimated_root_size);
-            // assert_eq!(bin.data.compute_encoded_attributes_size(), estimated_attributes_size);
-            assert_eq!(bin.data.encoded_size(), estimated_size);
+        merge_into_bin(self, idx, estimated_size, from).map(|_| ())
+    }
 
-            packed = true;
-            break;
-        }
+    fn pack_parallel(&mut self, from: &mut Pcf) -> Result<(), Error> {
+        let Some((idx, estimated_size)) = find_fit_parallel(self, from) else {
+            return Err(no_fit(self, from));
+        };
 
-        if packed {
-            // make sure the bins are always sorted by encoded size by descending order
-            self.sort_by(|a, b| b.data.encoded_size().cmp(&a.data.encoded_size()));
-            Ok(())
-        } else {
-            Err(Error::NoFit)
-        }
+        merge_into_bin(self, idx, estimated_size, from).map(|_| ())
     }
 }
 
+/// Finds the index of the heaviest bin that `from` fits in, along with the bin's estimated size after merging.
+fn find_fit_parallel(bins: &[Bin], from: &Pcf) -> Option<(usize, usize)> {
+    // find_first guarantees we pick the heaviest bin that fits, just like the serial search in `pack`
+    bins.par_iter()
+        .enumerate()
+        .map(|(idx, bin)| (idx, bin.capacity, bin.data.compute_merged_size(from)))
+        .find_first(|(_, capacity, estimated_size)| *estimated_size as u64 <= *capacity)
+        .map(|(idx, _, estimated_size)| (idx, estimated_size))
+}
+
+/// Merges `from` into the bin at `idx`, returning the name of the bin and how many bytes the merge added to it.
+fn merge_into_bin(bins: &mut [Bin], idx: usize, estimated_size: usize, from: &mut Pcf) -> Result<(String, u64), Error> {
+    let bin = &mut bins[idx];
+    let name = bin.name.clone();
+    let size = bin.data.encoded_size();
+
+    // let estimated_symbols_size = bin.data.compute_encoded_symbols_size_after_merge(from);
+    // let estimated_elements_size = bin.data.compute_encoded_elements_size_after_merge(from);
+    // let estimated_root_size = bin.data.compute_encoded_root_attributes_size_after_merge(from);
+    // let estimated_attributes_size = bin.data.compute_encoded_attributes_size_after_merge(from);
+
+    bin.data.merged_in(from)?;
+
+    // assert_eq!(bin.data.compute_encoded_symbols_size(), estimated_symbols_size);
+    // assert_eq!(bin.data.compute_encoded_elements_size(), estimated_elements_size);
+    // assert_eq!(bin.data.compute_encoded_root_attributes_size(), estimated_root_size);
+    // assert_eq!(bin.data.compute_encoded_attributes_size(), estimated_attributes_size);
+    assert_eq!(bin.data.encoded_size(), estimated_size);
+
+    // make sure the bins are always sorted by encoded size by descending order. The sort is stable, so bins of equal
+    // size keep their relative order and packing stays deterministic.
+    bins.sort_by_key(|bin| Reverse(bin.data.encoded_size()));
+    Ok((name, estimated_size.saturating_sub(size) as u64))
+}
+
+/// Builds an [`Error::NoFit`] for `from`, which didn't fit into any of `bins`.
+fn no_fit(bins: &[Bin], from: &Pcf) -> Error {
+    let shortfall = bins
+        .par_iter()
+        .map(|bin| (bin.data.compute_merged_size(from) as u64).saturating_sub(bin.capacity))
+        .min()
+        .unwrap_or(from.encoded_size() as u64);
+
+    Error::NoFit { shortfall }
+}
+
 #[derive(Debug, Error)]
 pub enum Error {
-    #[error("The item cannot fit into any bin in the bin map")]
-    NoFit,
+    /// `shortfall` is the smallest capacity increase, in bytes, which would have let any one bin fit the item.
+    #[error("The item cannot fit into any bin in the bin map; it needs {shortfall} more bytes than any bin has free")]
+    NoFit { shortfall: u64 },
 
     #[error(transparent)]
     CantMerge(#[from] pcf::new::MergeError),
 }
+
+#[cfg(test)]
+mod tests {
+    use crate::{Bin, BinPack, test_util::pcf_with_system};
+
+    fn bins() -> Vec<Bin> {
+        (0..8)
+            .map(|idx| {
+                let data = pcf_with_system(&format!("bin_{idx}"));
+                let capacity = data.encoded_size() as u64 + 200;
+                Bin::new(capacity, format!("bin_{idx}.pcf"), data)
+            })
+            .collect()
+    }
+
+    fn contents(bins: &[Bin]) -> Vec<(String, Vec<String>)> {
+        bins.iter()
+            .map(|bin| {
+                let systems = bin.as_pcf().particle_systems().iter().map(|system| system.name.clone());
+                (bin.name().to_string(), systems.collect())
+            })
+            .collect()
+    }
+
+    #[test]
+    fn parallel_packing_matches_serial_packing() {
+        let mut serial = bins();
+        let mut parallel = bins();
+
+        for idx in 0..24 {
+            serial.pack(&mut pcf_with_system(&format!("system_{idx}"))).unwrap();
+            parallel
+                .pack_parallel(&mut pcf_with_system(&format!("system_{idx}")))
+                .unwrap();
+        }
+
+        assert_eq!(contents(&serial), contents(&parallel));
+    }
+
+    #[test]
+    fn parallel_packing_fails_when_nothing_fits() {
+        let mut bins = bins();
+        let mut huge = pcf_with_system(&"x".repeat(1000));
+
+        assert!(matches!(bins.pack_parallel(&mut huge), Err(crate::Error::NoFit { .. })));
+    }
+}
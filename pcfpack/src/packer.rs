@@ -0,0 +1,280 @@
+use std::cmp::Reverse;
+
+use pcf::Pcf;
+
+use crate::{Bin, Error, find_fit_parallel, merge_into_bin, no_fit};
+
+/// Packs [`Pcf`]s into a set of bins like [`crate::BinPack::pack_parallel`], while keeping track of where each item
+/// went and which items didn't fit, so the result can be explained to the user.
+#[derive(Debug)]
+pub struct Packer {
+    bins: Box<[Bin]>,
+    placements: Vec<Placement>,
+    failures: Vec<Failure>,
+}
+
+/// An item which was packed into a bin.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Placement {
+    pub item: String,
+    pub bin: String,
+
+    /// The number of bytes the bin grew by when the item was merged into it.
+    pub added_size: u64,
+}
+
+/// An item which couldn't be packed into any bin.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Failure {
+    pub item: String,
+
+    /// The smallest capacity increase, in bytes, that would have let the item fit into any one bin.
+    pub shortfall: u64,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct BinUsage {
+    pub name: String,
+    pub capacity: u64,
+    pub used: u64,
+}
+
+impl BinUsage {
+    /// How full the bin is, from `0.0` to `1.0`.
+    pub fn utilization(&self) -> f64 {
+        if self.capacity == 0 {
+            1.0
+        } else {
+            self.used as f64 / self.capacity as f64
+        }
+    }
+
+    pub fn free(&self) -> u64 {
+        self.capacity.saturating_sub(self.used)
+    }
+}
+
+/// Describes the state of every bin, which items went where, and which items didn't fit.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct PackReport {
+    /// Every bin, sorted by name.
+    pub bins: Vec<BinUsage>,
+
+    /// Every item that was packed, in the order they were packed.
+    pub placements: Vec<Placement>,
+
+    /// Every item that couldn't be packed, in the order they were attempted.
+    pub failures: Vec<Failure>,
+}
+
+impl PackReport {
+    pub fn is_success(&self) -> bool {
+        self.failures.is_empty()
+    }
+
+    /// The total of every failure's shortfall; i.e. roughly how many bytes over budget the packed items are.
+    ///
+    /// This is a lower bound, since each failure's shortfall assumes it gets a bin to itself.
+    pub fn total_shortfall(&self) -> u64 {
+        self.failures.iter().map(|failure| failure.shortfall).sum()
+    }
+
+    pub fn used(&self) -> u64 {
+        self.bins.iter().map(|bin| bin.used).sum()
+    }
+
+    pub fn capacity(&self) -> u64 {
+        self.bins.iter().map(|bin| bin.capacity).sum()
+    }
+}
+
+impl Packer {
+    /// `bins` are sorted heaviest to lightest, to maintain [`crate::BinPack`]'s invariant.
+    pub fn new(bins: impl Into<Box<[Bin]>>) -> Self {
+        let mut bins = bins.into();
+        bins.sort_by_key(|bin| Reverse(bin.data.encoded_size()));
+
+        Self {
+            bins,
+            placements: Vec::new(),
+            failures: Vec::new(),
+        }
+    }
+
+    /// Packs `from` into the heaviest bin it fits in, recording the result under the name `item`.
+    ///
+    /// ## Errors
+    ///
+    /// See [`crate::BinPack::pack`]. If `from` doesn't fit, it's also recorded as a [`Failure`] in the report, so
+    /// callers may keep packing to find out how far over budget they are in total.
+    pub fn pack(&mut self, item: impl Into<String>, from: &mut Pcf) -> Result<(), Error> {
+        let item = item.into();
+        let Some((idx, estimated_size)) = find_fit_parallel(&self.bins, from) else {
+            let err = no_fit(&self.bins, from);
+            if let Error::NoFit { shortfall } = err {
+                self.failures.push(Failure { item, shortfall });
+            }
+
+            return Err(err);
+        };
+
+        let (bin, added_size) = merge_into_bin(&mut self.bins, idx, estimated_size, from)?;
+        self.placements.push(Placement { item, bin, added_size });
+
+        Ok(())
+    }
+
+    /// Packs `from` into the bin named `preferred` if it fits there, like [`Packer::pack`] otherwise. Used to keep
+    /// items in the bins they were packed into before, so that packing a few new items doesn't move every other one.
+    ///
+    /// ## Errors
+    ///
+    /// See [`Packer::pack`].
+    pub fn pack_preferring(
+        &mut self,
+        item: impl Into<String>,
+        from: &mut Pcf,
+        preferred: Option<&str>,
+    ) -> Result<(), Error> {
+        let fit = preferred
+            .and_then(|preferred| self.bins.iter().position(|bin| bin.name == preferred))
+            .map(|idx| (idx, self.bins[idx].data.compute_merged_size(from)))
+            .filter(|(idx, estimated_size)| *estimated_size as u64 <= self.bins[*idx].capacity);
+
+        let Some((idx, estimated_size)) = fit else {
+            return self.pack(item, from);
+        };
+
+        let (bin, added_size) = merge_into_bin(&mut self.bins, idx, estimated_size, from)?;
+        self.placements.push(Placement {
+            item: item.into(),
+            bin,
+            added_size,
+        });
+
+        Ok(())
+    }
+
+    pub fn report(&self) -> PackReport {
+        let mut bins: Vec<_> = self
+            .bins
+            .iter()
+            .map(|bin| BinUsage {
+                name: bin.name.clone(),
+                capacity: bin.capacity,
+                used: bin.data.encoded_size() as u64,
+            })
+            .collect();
+
+        bins.sort_by(|a, b| a.name.cmp(&b.name));
+
+        PackReport {
+            bins,
+            placements: self.placements.clone(),
+            failures: self.failures.clone(),
+        }
+    }
+
+    pub fn bins(&self) -> &[Bin] {
+        &self.bins
+    }
+
+    pub fn into_bins(self) -> Box<[Bin]> {
+        self.bins
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::Packer;
+    use crate::{Bin, Error, test_util::pcf_with_system};
+
+    fn packer(headroom: u64) -> Packer {
+        let bins: Vec<_> = ["a.pcf", "b.pcf"]
+            .into_iter()
+            .map(|name| {
+                let data = pcf_with_system(name);
+                Bin::new(data.encoded_size() as u64 + headroom, name.to_string(), data)
+            })
+            .collect();
+
+        Packer::new(bins)
+    }
+
+    #[test]
+    fn report_records_placements_and_utilization() {
+        let mut packer = packer(200);
+        packer.pack("first", &mut pcf_with_system("first")).unwrap();
+
+        let report = packer.report();
+        assert!(report.is_success());
+        assert_eq!(report.placements.len(), 1);
+        assert_eq!(report.placements[0].item, "first");
+
+        let bin = report
+            .bins
+            .iter()
+            .find(|bin| bin.name == report.placements[0].bin)
+            .unwrap();
+        assert_eq!(bin.free(), 200 - report.placements[0].added_size);
+        assert!(bin.utilization() > 0.0 && bin.utilization() <= 1.0);
+    }
+
+    #[test]
+    fn report_records_smallest_shortfall() {
+        let mut packer = packer(10);
+        let mut item = pcf_with_system("an item that is too large to fit");
+
+        let Err(Error::NoFit { shortfall }) = packer.pack("too large", &mut item) else {
+            panic!("expected the item not to fit");
+        };
+
+        let expected = packer
+            .bins()
+            .iter()
+            .map(|bin| bin.as_pcf().compute_merged_size(&item) as u64 - bin.capacity())
+            .min()
+            .unwrap();
+        assert_eq!(shortfall, expected);
+
+        let report = packer.report();
+        assert!(!report.is_success());
+        assert_eq!(report.total_shortfall(), shortfall);
+        assert_eq!(report.failures[0].item, "too large");
+    }
+
+    #[test]
+    fn packing_prefers_the_given_bin() {
+        let mut packer = packer(200);
+        packer
+            .pack_preferring("first", &mut pcf_with_system("first"), Some("b.pcf"))
+            .unwrap();
+        packer
+            .pack_preferring("second", &mut pcf_with_system("second"), Some("a.pcf"))
+            .unwrap();
+        packer
+            .pack_preferring("third", &mut pcf_with_system("third"), Some("c.pcf"))
+            .unwrap();
+
+        let report = packer.report();
+        assert!(report.is_success());
+        assert_eq!(report.placements[0].bin, "b.pcf");
+        assert_eq!(report.placements[1].bin, "a.pcf");
+    }
+
+    #[test]
+    fn packing_falls_back_when_the_preferred_bin_is_full() {
+        let mut packer = packer(200);
+        packer
+            .pack_preferring("large", &mut pcf_with_system(&"x".repeat(150)), Some("a.pcf"))
+            .unwrap();
+        packer
+            .pack_preferring("other", &mut pcf_with_system(&"y".repeat(150)), Some("a.pcf"))
+            .unwrap();
+
+        let report = packer.report();
+        assert!(report.is_success());
+        assert_eq!(report.placements[0].bin, "a.pcf");
+        assert_eq!(report.placements[1].bin, "b.pcf");
+    }
+}
@@ -0,0 +1,21 @@
+//! Fixtures shared by this crate's tests.
+
+use dmx::dmx::Version;
+use pcf::{AttributeMap, ParticleSystem, Pcf, Root, Symbols};
+
+/// A PCF with a single, empty particle system named `name`.
+pub(crate) fn pcf_with_system(name: &str) -> Pcf {
+    Pcf::new(
+        Version::Binary2Pcf1,
+        Symbols::new_with_all_special(),
+        Root::new(
+            "untitled".to_string(),
+            [0; 16],
+            Box::from([ParticleSystem {
+                name: name.to_string(),
+                ..ParticleSystem::default()
+            }]),
+            AttributeMap::new(),
+        ),
+    )
+}
@@ -0,0 +1,176 @@
+#![feature(file_buffered)]
+
+use std::{
+    env,
+    fs::File,
+    io::{BufReader, Read},
+    path::Path,
+    process,
+};
+
+use anyhow::{Context, bail};
+use pcf::{
+    Pcf,
+    diff::{AttributeChange, PcfDiff, SystemDiff},
+};
+use serde_json::{Value, json};
+
+const USAGE: &str = "\
+usage: pcfdiff [options] <old> <new>
+
+Compares two PCFs, printing the particle systems only one of them has, and the children, operators & attributes which
+differ in the systems they share. Systems are matched by name, and element signatures are ignored. Each PCF may be a
+.pcf file, or an entry in a _dir.vpk given as <vpk>:<entry>, e.g. tf2_misc_dir.vpk:particles/explosion.pcf.
+
+options:
+    --json          print the differences as JSON";
+
+fn main() {
+    if let Err(err) = run() {
+        eprintln!("pcfdiff: {err:#}");
+        process::exit(1);
+    }
+}
+
+fn run() -> anyhow::Result<()> {
+    let mut json = false;
+    let mut paths = Vec::new();
+
+    for arg in env::args().skip(1) {
+        match arg.as_str() {
+            "--json" => json = true,
+            "-h" | "--help" => {
+                println!("{USAGE}");
+                return Ok(());
+            }
+            _ if arg.starts_with("--") => bail!("unknown option '{arg}'\n\n{USAGE}"),
+            _ => paths.push(arg),
+        }
+    }
+
+    let [old, new] = paths.as_slice() else {
+        bail!("expected exactly two PCFs\n\n{USAGE}");
+    };
+
+    let diff = pcf::diff::diff(&read_pcf(old)?, &read_pcf(new)?);
+    if json {
+        println!("{}", serde_json::to_string_pretty(&diff_json(&diff))?);
+    } else {
+        print_diff(&diff);
+    }
+
+    Ok(())
+}
+
+/// Reads the PCF at `path`, which is either a file or a `<vpk>:<entry>` in a VPK.
+fn read_pcf(path: &str) -> anyhow::Result<Pcf> {
+    if let Some((vpk_path, entry_path)) = split_vpk_entry(path) {
+        let vpk = vpk::from_path(vpk_path)?;
+        let entry = vpk
+            .tree
+            .get(entry_path)
+            .with_context(|| format!("'{vpk_path}' has no entry '{entry_path}'"))?;
+
+        return decode(path, &mut BufReader::new(entry.reader()?));
+    }
+
+    let mut file = File::open_buffered(path).with_context(|| format!("couldn't open {path}"))?;
+    decode(path, &mut file)
+}
+
+/// Splits `<vpk>:<entry>` into the VPK's path and the entry's path, or returns `None` if `path` isn't in a VPK.
+fn split_vpk_entry(path: &str) -> Option<(&str, &str)> {
+    let vpk_end = path.find(".vpk:")? + ".vpk".len();
+    let (vpk_path, entry_path) = (&path[..vpk_end], &path[vpk_end + 1..]);
+    Path::new(vpk_path)
+        .is_file()
+        .then_some((vpk_path, entry_path.trim_start_matches('/')))
+}
+
+fn decode(name: &str, reader: &mut BufReader<impl Read>) -> anyhow::Result<Pcf> {
+    pcf::decode(reader).with_context(|| format!("couldn't decode {name}"))
+}
+
+fn print_diff(diff: &PcfDiff) {
+    if diff.is_empty() {
+        println!("no differences");
+        return;
+    }
+
+    for name in &diff.added {
+        println!("+ {name}");
+    }
+
+    for name in &diff.removed {
+        println!("- {name}");
+    }
+
+    for system in &diff.changed {
+        println!("~ {}", system.name);
+        for name in &system.children_added {
+            println!("    + child '{name}'");
+        }
+
+        for name in &system.children_removed {
+            println!("    - child '{name}'");
+        }
+
+        for (list, name) in &system.operators_added {
+            println!("    + {list} > '{name}'");
+        }
+
+        for (list, name) in &system.operators_removed {
+            println!("    - {list} > '{name}'");
+        }
+
+        for list in &system.operators_reordered {
+            println!("    ~ {list} reordered");
+        }
+
+        for change in &system.attributes {
+            println!("    ~ {change}");
+        }
+    }
+}
+
+fn diff_json(diff: &PcfDiff) -> Value {
+    json!({
+        "added": diff.added,
+        "removed": diff.removed,
+        "changed": diff.changed.iter().map(system_json).collect::<Vec<_>>(),
+    })
+}
+
+fn system_json(system: &SystemDiff) -> Value {
+    let operators = |operators: &[(pcf::OperatorList, String)]| -> Vec<Value> {
+        operators
+            .iter()
+            .map(|(list, name)| json!({ "list": list.attribute_name(), "name": name }))
+            .collect()
+    };
+
+    let reordered: Vec<_> = system
+        .operators_reordered
+        .iter()
+        .map(|list| list.attribute_name())
+        .collect();
+    json!({
+        "name": system.name,
+        "children_added": system.children_added,
+        "children_removed": system.children_removed,
+        "operators_added": operators(&system.operators_added),
+        "operators_removed": operators(&system.operators_removed),
+        "operators_reordered": reordered,
+        "attributes": system.attributes.iter().map(attribute_json).collect::<Vec<_>>(),
+    })
+}
+
+/// Values are written as they're displayed, since attributes can be of types JSON has no equivalent for.
+fn attribute_json(change: &AttributeChange) -> Value {
+    json!({
+        "element": change.element.to_string(),
+        "name": change.name,
+        "old": change.old.as_ref().map(ToString::to_string),
+        "new": change.new.as_ref().map(ToString::to_string),
+    })
+}
@@ -0,0 +1,61 @@
+//! Extract a vanilla particle system, along with every system it uses as a child, into a standalone PCF. This is the
+//! usual starting point for a particle mod.
+
+use std::{io::BufReader, path::Path};
+
+use anyhow::{Context, bail};
+use pcf::{Pcf, extract::SystemMap};
+
+/// The VPK which holds TF2's particles, relative to the tf/ directory.
+pub const TF2_MISC_VPK: &str = "tf2_misc_dir.vpk";
+
+/// Decodes every PCF in `tf_dir`'s [`TF2_MISC_VPK`], along with its path in the VPK. They're ordered by path, except
+/// that the `_dx80` & `_dx90_slow` fallbacks come last, so that a [`SystemMap`] prefers the PCFs most players load.
+pub fn vanilla_pcfs(tf_dir: &Path) -> anyhow::Result<Vec<(String, Pcf)>> {
+    let vpk_path = tf_dir.join(TF2_MISC_VPK);
+    let vpk = vpk::from_path(&vpk_path).with_context(|| format!("couldn't open {}", vpk_path.display()))?;
+
+    let mut entries: Vec<_> = vpk
+        .tree
+        .iter()
+        .filter(|(entry_path, _)| entry_path.starts_with("particles/") && entry_path.ends_with(".pcf"))
+        .collect();
+    entries.sort_by_key(|(entry_path, _)| (is_fallback(entry_path), *entry_path));
+
+    entries
+        .into_iter()
+        .map(|(entry_path, entry)| {
+            let mut reader = BufReader::new(entry.reader()?);
+            let pcf = pcf::decode(&mut reader).with_context(|| format!("couldn't decode {entry_path}"))?;
+            Ok((entry_path.clone(), pcf))
+        })
+        .collect()
+}
+
+/// Finds the vanilla PCF in `tf_dir` which defines `system`, and extracts the system & its descendants from it. Returns
+/// the path of the PCF in [`TF2_MISC_VPK`], along with the extracted PCF.
+pub fn extract_vanilla(tf_dir: &Path, system: &str) -> anyhow::Result<(String, Pcf)> {
+    let pcfs = vanilla_pcfs(tf_dir)?;
+    let map = SystemMap::new(pcfs.iter().map(|(name, pcf)| (name.as_str(), pcf)));
+    let Some(pcf_name) = map.find(system) else {
+        bail!(
+            "none of the {} vanilla PCFs have a particle system named '{system}'",
+            pcfs.len()
+        );
+    };
+
+    let pcf_name = pcf_name.to_string();
+    let (_, pcf) = pcfs
+        .into_iter()
+        .find(|(name, _)| *name == pcf_name)
+        .expect("the system map only contains names from pcfs");
+
+    let extracted = pcf
+        .extracted(system)?
+        .expect("the system map only maps systems in their pcf");
+    Ok((pcf_name, extracted))
+}
+
+fn is_fallback(entry_path: &str) -> bool {
+    entry_path.ends_with("_dx80.pcf") || entry_path.ends_with("_dx90_slow.pcf")
+}
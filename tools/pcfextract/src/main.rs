@@ -0,0 +1,71 @@
+#![feature(file_buffered)]
+
+use std::{
+    env,
+    fs::File,
+    io::Write,
+    path::{Path, PathBuf},
+    process,
+};
+
+use anyhow::{Context, bail};
+
+const USAGE: &str = "\
+usage: pcfextract [options] <tf dir> <system>
+
+Finds the vanilla PCF in tf/tf2_misc_dir.vpk which defines the particle system, and writes the system along with every
+system it uses as a child to a standalone PCF. If dazzle has installed particles, tf2_misc_dir.vpk contains them
+instead of the vanilla particles; uninstall them first.
+
+options:
+    -o, --output <path>     where to write the PCF, defaults to <system>.pcf";
+
+fn main() {
+    if let Err(err) = run() {
+        eprintln!("pcfextract: {err:#}");
+        process::exit(1);
+    }
+}
+
+fn run() -> anyhow::Result<()> {
+    let mut output = None;
+    let mut positional = Vec::new();
+
+    let mut args = env::args().skip(1);
+    while let Some(arg) = args.next() {
+        match arg.as_str() {
+            "-o" | "--output" => {
+                output = Some(PathBuf::from(
+                    args.next().with_context(|| format!("{arg} requires a value"))?,
+                ));
+            }
+            "-h" | "--help" => {
+                println!("{USAGE}");
+                return Ok(());
+            }
+            _ if arg.starts_with('-') => bail!("unknown option '{arg}'\n\n{USAGE}"),
+            _ => positional.push(arg),
+        }
+    }
+
+    let [tf_dir, system] = positional.as_slice() else {
+        bail!("expected a tf/ directory and a particle system\n\n{USAGE}");
+    };
+
+    let output = output.unwrap_or_else(|| PathBuf::from(format!("{system}.pcf")));
+
+    let (pcf_name, pcf) = pcfextract::extract_vanilla(Path::new(tf_dir), system)?;
+    let system_count = pcf.particle_systems().len();
+
+    let mut file = File::create_buffered(&output)?;
+    pcf::encode(pcf, &mut file)?;
+    file.flush()?;
+
+    println!(
+        "extracted '{system}' and {} descendants from {pcf_name} to {}",
+        system_count - 1,
+        output.display()
+    );
+
+    Ok(())
+}
@@ -0,0 +1,124 @@
+#![feature(file_buffered)]
+
+use std::{
+    env,
+    fs::File,
+    io::{BufReader, Read},
+    path::Path,
+    process,
+};
+
+use anyhow::{Context, bail};
+use pcf::{
+    Pcf,
+    query::{Match, Query},
+};
+use regex::Regex;
+
+const USAGE: &str = "\
+usage: pcfgrep [options] <path>...
+
+Searches PCFs for particle systems, operators, and attributes. Each path may be a .pcf file, a directory which is
+searched recursively for .pcf files, or a _dir.vpk whose particles/*.pcf entries are searched.
+
+options:
+    --name <attribute>      only match attributes with this exact name
+    --value <regex>         only match attributes whose value matches this pattern
+    --function <name>       only match operators with this exact functionName
+    --material <regex>      only match within particle systems whose material matches this pattern";
+
+fn main() {
+    if let Err(err) = run() {
+        eprintln!("pcfgrep: {err:#}");
+        process::exit(1);
+    }
+}
+
+fn run() -> anyhow::Result<()> {
+    let mut query = Query::default();
+    let mut paths = Vec::new();
+
+    let mut args = env::args().skip(1);
+    while let Some(arg) = args.next() {
+        let mut value = || args.next().with_context(|| format!("{arg} requires a value"));
+        match arg.as_str() {
+            "--name" => query.attribute_name = Some(value()?),
+            "--value" => query.value = Some(Regex::new(&value()?)?),
+            "--function" => query.function_name = Some(value()?),
+            "--material" => query.material = Some(Regex::new(&value()?)?),
+            "-h" | "--help" => {
+                println!("{USAGE}");
+                return Ok(());
+            }
+            _ if arg.starts_with("--") => bail!("unknown option '{arg}'\n\n{USAGE}"),
+            _ => paths.push(arg),
+        }
+    }
+
+    if paths.is_empty() {
+        bail!("no paths given\n\n{USAGE}");
+    }
+
+    for path in paths {
+        search_path(&query, Path::new(&path))?;
+    }
+
+    Ok(())
+}
+
+fn search_path(query: &Query, path: &Path) -> anyhow::Result<()> {
+    if path.is_dir() {
+        let pattern = path.join("**").join("*.pcf");
+        let pattern = pattern.to_str().context("directory path isn't valid UTF-8")?;
+        for entry in glob::glob(pattern)? {
+            let entry = entry?;
+            let mut file = File::open_buffered(&entry)?;
+            search_pcf(query, &entry.display().to_string(), &mut file)?;
+        }
+
+        return Ok(());
+    }
+
+    if path.extension().is_some_and(|extension| extension == "vpk") {
+        let vpk = vpk::from_path(path)?;
+        let mut entries: Vec<_> = vpk
+            .tree
+            .iter()
+            .filter(|(entry_path, _)| entry_path.starts_with("particles/") && entry_path.ends_with(".pcf"))
+            .collect();
+        entries.sort_by_key(|(entry_path, _)| *entry_path);
+
+        for (entry_path, entry) in entries {
+            let mut reader = BufReader::new(entry.reader()?);
+            search_pcf(query, &format!("{}:{entry_path}", path.display()), &mut reader)?;
+        }
+
+        return Ok(());
+    }
+
+    let mut file = File::open_buffered(path)?;
+    search_pcf(query, &path.display().to_string(), &mut file)
+}
+
+fn search_pcf(query: &Query, name: &str, reader: &mut BufReader<impl Read>) -> anyhow::Result<()> {
+    let pcf: Pcf = pcf::decode(reader).with_context(|| format!("couldn't decode {name}"))?;
+
+    for found in query.find_in(&pcf) {
+        println!("{name}: {}", describe(&found));
+    }
+
+    Ok(())
+}
+
+fn describe(found: &Match) -> String {
+    let mut description = format!("#{} {}", found.system_idx, found.system.name);
+    if let Some(operator) = found.operator {
+        description += &format!(" > {} ({})", operator.name, operator.function_name);
+    }
+
+    if let Some((name, attribute)) = found.attribute {
+        description += &format!(" > {name} = {attribute}");
+    }
+
+    description
+}
@@ -0,0 +1,148 @@
+#![feature(file_buffered)]
+
+use std::{
+    collections::BTreeMap,
+    env,
+    fs::File,
+    io::{BufReader, Read},
+    path::Path,
+    process,
+};
+
+use anyhow::{Context, bail};
+use pcf::{
+    Pcf,
+    stats::{PcfStats, Tally},
+};
+
+const USAGE: &str = "\
+usage: pcfstats [options] <path>...
+
+Counts the attributes, operators, and particle systems in PCFs, along with how many bytes they take up, summed across
+every PCF. Each path may be a .pcf file, a directory which is searched recursively for .pcf files, or a _dir.vpk whose
+particles/*.pcf entries are counted.
+
+options:
+    --top <n>       only show the <n> largest rows of each table (default 20)";
+
+const DEFAULT_TOP: usize = 20;
+
+fn main() {
+    if let Err(err) = run() {
+        eprintln!("pcfstats: {err:#}");
+        process::exit(1);
+    }
+}
+
+fn run() -> anyhow::Result<()> {
+    let mut top = DEFAULT_TOP;
+    let mut paths = Vec::new();
+
+    let mut args = env::args().skip(1);
+    while let Some(arg) = args.next() {
+        let mut value = || args.next().with_context(|| format!("{arg} requires a value"));
+        match arg.as_str() {
+            "--top" => top = value()?.parse().context("--top must be a number")?,
+            "-h" | "--help" => {
+                println!("{USAGE}");
+                return Ok(());
+            }
+            _ if arg.starts_with("--") => bail!("unknown option '{arg}'\n\n{USAGE}"),
+            _ => paths.push(arg),
+        }
+    }
+
+    if paths.is_empty() {
+        bail!("no paths given\n\n{USAGE}");
+    }
+
+    let mut stats = PcfStats::default();
+    for path in paths {
+        add_path(&mut stats, Path::new(&path))?;
+    }
+
+    print_stats(&stats, top);
+    Ok(())
+}
+
+fn add_path(stats: &mut PcfStats, path: &Path) -> anyhow::Result<()> {
+    if path.is_dir() {
+        let pattern = path.join("**").join("*.pcf");
+        let pattern = pattern.to_str().context("directory path isn't valid UTF-8")?;
+        for entry in glob::glob(pattern)? {
+            let entry = entry?;
+            let mut file = File::open_buffered(&entry)?;
+            add_pcf(stats, &entry.display().to_string(), &mut file)?;
+        }
+
+        return Ok(());
+    }
+
+    if path.extension().is_some_and(|extension| extension == "vpk") {
+        let vpk = vpk::from_path(path)?;
+        let mut entries: Vec<_> = vpk
+            .tree
+            .iter()
+            .filter(|(entry_path, _)| entry_path.starts_with("particles/") && entry_path.ends_with(".pcf"))
+            .collect();
+        entries.sort_by_key(|(entry_path, _)| *entry_path);
+
+        for (entry_path, entry) in entries {
+            let mut reader = BufReader::new(entry.reader()?);
+            add_pcf(stats, &format!("{}:{entry_path}", path.display()), &mut reader)?;
+        }
+
+        return Ok(());
+    }
+
+    let mut file = File::open_buffered(path)?;
+    add_pcf(stats, &path.display().to_string(), &mut file)
+}
+
+fn add_pcf(stats: &mut PcfStats, name: &str, reader: &mut BufReader<impl Read>) -> anyhow::Result<()> {
+    let pcf: Pcf = pcf::decode(reader).with_context(|| format!("couldn't decode {name}"))?;
+    stats.add(&pcf.stats());
+    Ok(())
+}
+
+fn print_stats(stats: &PcfStats, top: usize) {
+    println!(
+        "{} PCFs, {} bytes, {} particle systems, {} symbols ({} bytes)",
+        stats.pcfs,
+        stats.encoded_size,
+        stats.systems.len(),
+        stats.symbols.count,
+        stats.symbols.bytes
+    );
+
+    print_table("attribute type", &stats.attribute_types, top);
+    print_table("attribute", &stats.attribute_names, top);
+    print_table("operator function", &stats.operator_functions, top);
+
+    let mut systems: Vec<_> = stats.systems.iter().collect();
+    systems.sort_by(|a, b| b.bytes.cmp(&a.bytes).then_with(|| a.name.cmp(&b.name)));
+
+    println!();
+    println!(
+        "{:>10}  {:>8}  {:>9}  {:>10}  particle system",
+        "bytes", "children", "operators", "attributes"
+    );
+    for system in systems.into_iter().take(top) {
+        println!(
+            "{:>10}  {:>8}  {:>9}  {:>10}  {}",
+            system.bytes, system.children, system.operators, system.attributes, system.name
+        );
+    }
+}
+
+/// Prints the `top` rows of `tallies` which take up the most bytes.
+fn print_table<K: AsRef<str>>(heading: &str, tallies: &BTreeMap<K, Tally>, top: usize) {
+    let mut rows: Vec<_> = tallies.iter().collect();
+    rows.sort_by(|(a_key, a), (b_key, b)| b.bytes.cmp(&a.bytes).then_with(|| a_key.as_ref().cmp(b_key.as_ref())));
+
+    println!();
+    println!("{:>10}  {:>8}  {heading}", "bytes", "count");
+    for (key, tally) in rows.into_iter().take(top) {
+        println!("{:>10}  {:>8}  {}", tally.bytes, tally.count, key.as_ref());
+    }
+}
@@ -13,11 +13,10 @@ use std::{
     str::FromStr,
 };
 
-use bytes::{Buf, BufMut, BytesMut};
 use dmx::{
     Dmx,
 };
-use pcf::{Attribute, Pcf};
+use pcf::{Attribute, Comparison, Pcf, stats::StripStats};
 
 use crate::patch::PatchVpkExt;
 
@@ -70,16 +69,21 @@ fn main() -> anyhow::Result<()> {
 
     print!("stripping PCFs... ");
     stdout().flush()?;
+    let mut strip_stats = StripStats::default();
     let input_pcfs: Vec<_> = input_pcfs
         .into_iter()
         .map(|(pcf, output)| {
-            (
-                pcf.defaults_stripped_nth(1000, &particle_defaults, &operator_defaults),
-                output,
-            )
+            let (pcf, stats) =
+                pcf.defaults_stripped_nth_with_stats(1000, &particle_defaults, &operator_defaults, Comparison::Exact);
+            strip_stats.add(&stats);
+            (pcf, output)
         })
         .collect();
-    println!("done");
+    println!(
+        "done, removed {} attributes and saved {} bytes",
+        strip_stats.attributes_removed(),
+        strip_stats.bytes_saved()
+    );
 
     // whats goin on with the 431st particle system? (0-indexed)
     // let pcf = pcf.defaults_stripped_nth(611, &operator_defaults, &particle_defaults);
@@ -110,14 +114,11 @@ fn main() -> anyhow::Result<()> {
         print!("  {output}: encoding... ");
         stdout().flush()?;
         let dmx: Dmx = pcf.into();
-        let buf = BytesMut::new();
-        let mut writer = buf.writer();
-        dmx.encode(&mut writer)?;
+        let buffer = dmx.encode_to_vec();
 
-        let mut reader = writer.into_inner().reader();
-        print!("patching {} bytes... ", reader.get_ref().len());
+        print!("patching {} bytes... ", buffer.len());
         stdout().flush()?;
-        vpk.patch_file(&output, reader.get_ref().len() as u64, &mut reader)?;
+        vpk.patch_file(&output, buffer.len() as u64, &mut buffer.as_slice())?;
 
         println!("done");
     }
@@ -3,19 +3,44 @@
 
 use std::{env, fmt::Display, fs::File, process};
 
-use dmx::SymbolIdx;
+use dmx::{Float, SymbolIdx};
 use pcf::Attribute;
 use pcf::new::{Operator, ParticleSystem, Pcf};
 use ptree::{TreeBuilder, print_tree};
 
+const USAGE: &str = "\
+usage: pcftree [--raw] <path>
+
+Prints every symbol & particle system in a PCF as a tree.
+
+options:
+    --raw       print every float in full, every matrix's rows, and binary attributes as hex, rather than rounding
+                floats to 2 decimals & summarizing matrices and binary attributes";
+
 fn main() {
-    let args: Vec<String> = env::args().collect();
-    if args.len() != 2 {
-        process::exit(1);
+    let mut raw = false;
+    let mut path = None;
+    for arg in env::args().skip(1) {
+        match arg.as_str() {
+            "--raw" => raw = true,
+            "-h" | "--help" => {
+                println!("{USAGE}");
+                return;
+            }
+            _ if arg.starts_with("--") || path.is_some() => {
+                eprintln!("pcftree: unexpected argument '{arg}'\n\n{USAGE}");
+                process::exit(1);
+            }
+            _ => path = Some(arg),
+        }
     }
 
-    let path = &args[1];
-    let mut file = File::open_buffered(path).unwrap();
+    let Some(path) = path else {
+        eprintln!("pcftree: no path given\n\n{USAGE}");
+        process::exit(1);
+    };
+
+    let mut file = File::open_buffered(&path).unwrap();
     let dmx = dmx::decode(&mut file).unwrap();
     let pcf = Pcf::try_from(dmx).unwrap();
 
@@ -29,7 +54,7 @@ fn main() {
         strings.sort();
 
         for string in strings {
-            symbols.add_empty_child(string.clone());
+            symbols.add_empty_child(string.to_string());
         }
 
         symbols.end_child();
@@ -42,7 +67,7 @@ fn main() {
         // particle_systems.sort_by(|a, b| a.1.name.cmp(&b.1.name));
 
         let root = tree.begin_child(root_text);
-        create_element_children(root, &pcf, particle_systems.into_iter());
+        create_element_children(root, &pcf, particle_systems.into_iter(), raw);
         root.end_child();
     }
 
@@ -55,6 +80,7 @@ fn create_element_children<'a, 't>(
     node: &'t mut TreeBuilder,
     pcf: &'a Pcf,
     particle_systems: impl Iterator<Item = (usize, &'a ParticleSystem)>,
+    raw: bool,
 ) -> &'t mut TreeBuilder {
     for (system_idx, particle_system) in particle_systems {
         let label = format!(
@@ -74,7 +100,7 @@ fn create_element_children<'a, 't>(
             attributes.sort_by_key(|(name_idx, _)| pcf.symbols().base.get_index(**name_idx as usize).unwrap());
 
             for (name_idx, attribute) in attributes {
-                create_attribute_child(node, pcf, *name_idx, attribute);
+                create_attribute_child(node, pcf, *name_idx, attribute, raw);
             }
         }
 
@@ -97,7 +123,7 @@ fn create_element_children<'a, 't>(
                 let node = node.begin_child(label);
                 node.add_empty_child(format!("child: {}", child.child));
                 for (name_idx, attribute) in &child.attributes {
-                    create_attribute_child(node, pcf, *name_idx, attribute);
+                    create_attribute_child(node, pcf, *name_idx, attribute, raw);
                 }
                 node.end_child();
             }
@@ -105,12 +131,12 @@ fn create_element_children<'a, 't>(
             node.end_child();
         }
 
-        create_operator_children(node, pcf, &particle_system.constraints, "constraints");
-        create_operator_children(node, pcf, &particle_system.emitters, "emitters");
-        create_operator_children(node, pcf, &particle_system.forces, "forces");
-        create_operator_children(node, pcf, &particle_system.initializers, "initializers");
-        create_operator_children(node, pcf, &particle_system.operators, "operators");
-        create_operator_children(node, pcf, &particle_system.renderers, "renderers");
+        create_operator_children(node, pcf, &particle_system.constraints, "constraints", raw);
+        create_operator_children(node, pcf, &particle_system.emitters, "emitters", raw);
+        create_operator_children(node, pcf, &particle_system.forces, "forces", raw);
+        create_operator_children(node, pcf, &particle_system.initializers, "initializers", raw);
+        create_operator_children(node, pcf, &particle_system.operators, "operators", raw);
+        create_operator_children(node, pcf, &particle_system.renderers, "renderers", raw);
 
         node.end_child();
     }
@@ -118,7 +144,7 @@ fn create_element_children<'a, 't>(
     node
 }
 
-fn create_operator_children(node: &mut TreeBuilder, pcf: &Pcf, operators: &[Operator], name: &str) {
+fn create_operator_children(node: &mut TreeBuilder, pcf: &Pcf, operators: &[Operator], name: &str, raw: bool) {
     if !operators.is_empty() {
         let mut operators: Vec<_> = operators.iter().collect();
         operators.sort_by(|a, b| a.name.cmp(&b.name));
@@ -138,7 +164,7 @@ fn create_operator_children(node: &mut TreeBuilder, pcf: &Pcf, operators: &[Oper
             let node = node.begin_child(label);
             node.add_empty_child(format!("function_name: {}", operator.function_name.clone()));
             for (name_idx, attribute) in &operator.attributes {
-                create_attribute_child(node, pcf, *name_idx, attribute);
+                create_attribute_child(node, pcf, *name_idx, attribute, raw);
             }
             node.end_child();
         }
@@ -146,11 +172,25 @@ fn create_operator_children(node: &mut TreeBuilder, pcf: &Pcf, operators: &[Oper
     }
 }
 
-fn create_attribute_child(node: &mut TreeBuilder, pcf: &Pcf, name_idx: SymbolIdx, attribute: &Attribute) {
-    fn add_array<'a>(name: &str, node: &'a mut TreeBuilder, items: &[impl Display]) -> &'a mut TreeBuilder {
+/// Formats `value` with the alternate flag in raw mode, see [`dmx::attribute::Vector2`].
+fn format_value(value: &dyn Display, raw: bool) -> String {
+    if raw { format!("{value:#}") } else { format!("{value}") }
+}
+
+fn format_float(value: Float, raw: bool) -> String {
+    if raw { format!("{value}") } else { format!("{value:.2}") }
+}
+
+fn create_attribute_child(node: &mut TreeBuilder, pcf: &Pcf, name_idx: SymbolIdx, attribute: &Attribute, raw: bool) {
+    fn add_array<'a, T>(
+        name: &str,
+        node: &'a mut TreeBuilder,
+        items: &[T],
+        format: impl Fn(&T) -> String,
+    ) -> &'a mut TreeBuilder {
         let child = node.begin_child(name.to_owned());
         for item in items {
-            child.add_empty_child(item.to_string());
+            child.add_empty_child(format(item));
         }
         child.end_child()
     }
@@ -158,42 +198,24 @@ fn create_attribute_child(node: &mut TreeBuilder, pcf: &Pcf, name_idx: SymbolIdx
     let name = pcf.symbols().base.get_index(name_idx as usize).unwrap();
     match attribute {
         Attribute::Integer(value) => node.add_empty_child(format!("{name}: {value}")),
-        Attribute::Float(value) => node.add_empty_child(format!("{name}: {value:.2}")),
+        Attribute::Float(value) => node.add_empty_child(format!("{name}: {}", format_float(*value, raw))),
         Attribute::Bool(value) => node.add_empty_child(format!("{name}: {value}")),
         Attribute::String(value) => node.add_empty_child(format!("{name}: {value}")),
-        Attribute::Binary(value) => node.add_empty_child(format!("{name}: {value:#?}")),
+        Attribute::Binary(blob) => node.add_empty_child(format!("{name}: {}", format_value(blob, raw))),
         Attribute::Color(value) => node.add_empty_child(format!("{name}: {value}")),
-        Attribute::Vector2(value) => node.add_empty_child(format!("{name}: {value}")),
-        Attribute::Vector3(value) => node.add_empty_child(format!("{name}: {value}")),
-        Attribute::Vector4(value) => node.add_empty_child(format!("{name}: {value}")),
-        Attribute::Matrix(value) => node.add_empty_child(format!("{name}: {value}")),
-        Attribute::IntegerArray(items) => add_array(name, node, items),
-        Attribute::FloatArray(items) => {
-            let child = node.begin_child(name.clone());
-            for item in items {
-                child.add_empty_child(format!("{item:.2}"));
-            }
-            child.end_child()
-        }
-        Attribute::BoolArray(items) => add_array(name, node, items),
-        Attribute::StringArray(items) => {
-            let child = node.begin_child(name.clone());
-            for item in items {
-                child.add_empty_child(item.clone());
-            }
-            child.end_child()
-        }
-        Attribute::BinaryArray(items) => {
-            let child = node.begin_child(name.clone());
-            for item in items {
-                child.add_empty_child(format!("{item:#?}"));
-            }
-            child.end_child()
-        }
-        Attribute::ColorArray(items) => add_array(name, node, items),
-        Attribute::Vector2Array(items) => add_array(name, node, items),
-        Attribute::Vector3Array(items) => add_array(name, node, items),
-        Attribute::Vector4Array(items) => add_array(name, node, items),
-        Attribute::MatrixArray(items) => add_array(name, node, items),
+        Attribute::Vector2(vector) => node.add_empty_child(format!("{name}: {}", format_value(vector, raw))),
+        Attribute::Vector3(vector) => node.add_empty_child(format!("{name}: {}", format_value(vector, raw))),
+        Attribute::Vector4(vector) => node.add_empty_child(format!("{name}: {}", format_value(vector, raw))),
+        Attribute::Matrix(matrix) => node.add_empty_child(format!("{name}: {}", format_value(matrix, raw))),
+        Attribute::IntegerArray(items) => add_array(name, node, items, ToString::to_string),
+        Attribute::FloatArray(items) => add_array(name, node, items, |item| format_float(*item, raw)),
+        Attribute::BoolArray(items) => add_array(name, node, items, ToString::to_string),
+        Attribute::StringArray(items) => add_array(name, node, items, Clone::clone),
+        Attribute::BinaryArray(items) => add_array(name, node, items, |item| format_value(item, raw)),
+        Attribute::ColorArray(items) => add_array(name, node, items, ToString::to_string),
+        Attribute::Vector2Array(items) => add_array(name, node, items, |item| format_value(item, raw)),
+        Attribute::Vector3Array(items) => add_array(name, node, items, |item| format_value(item, raw)),
+        Attribute::Vector4Array(items) => add_array(name, node, items, |item| format_value(item, raw)),
+        Attribute::MatrixArray(items) => add_array(name, node, items, |item| format_value(item, raw)),
     };
 }
@@ -0,0 +1,41 @@
+use std::{
+    env,
+    io::{self, Write},
+    process,
+};
+
+use anyhow::bail;
+use writevpk::browse::BrowseVpkExt;
+
+const USAGE: &str = "\
+usage: vpkcat <vpk> <path>...
+
+Writes the contents of each file in a _dir.vpk to stdout, in order.";
+
+fn main() {
+    if let Err(err) = run() {
+        eprintln!("vpkcat: {err:#}");
+        process::exit(1);
+    }
+}
+
+fn run() -> anyhow::Result<()> {
+    let args: Vec<String> = env::args().skip(1).collect();
+    let [vpk_path, paths @ ..] = args.as_slice() else {
+        bail!("{USAGE}");
+    };
+
+    if paths.is_empty() {
+        bail!("{USAGE}");
+    }
+
+    let vpk = vpk::from_path(vpk_path)?;
+    let mut stdout = io::stdout().lock();
+    for path in paths {
+        let mut reader = vpk.open(path)?;
+        io::copy(&mut reader, &mut stdout)?;
+    }
+
+    stdout.flush()?;
+    Ok(())
+}
@@ -0,0 +1,41 @@
+use std::{env, process};
+
+use anyhow::bail;
+use writevpk::browse::BrowseVpkExt;
+
+const USAGE: &str = "\
+usage: vpkls <vpk> [pattern]
+
+Lists the files in a _dir.vpk which match the glob pattern, or every file if no pattern is given, along with their
+size and the archive they're stored in. Use vpkcat to print a file's contents.";
+
+fn main() {
+    if let Err(err) = run() {
+        eprintln!("vpkls: {err:#}");
+        process::exit(1);
+    }
+}
+
+fn run() -> anyhow::Result<()> {
+    let args: Vec<String> = env::args().skip(1).collect();
+    let (vpk_path, pattern) = match args.as_slice() {
+        [vpk_path] => (vpk_path, "**/*"),
+        [vpk_path, pattern] => (vpk_path, pattern.as_str()),
+        _ => bail!("{USAGE}"),
+    };
+
+    let vpk = vpk::from_path(vpk_path)?;
+    for path in vpk.glob(pattern)? {
+        let stat = vpk.stat(path).expect("globbed paths are always in the vpk");
+        // files stored in the directory VPK itself use this archive index
+        let archive = if stat.archive_index == 0x7fff {
+            "dir".to_string()
+        } else {
+            format!("{:03}", stat.archive_index)
+        };
+
+        println!("{:>12} {archive:>4} {path}", stat.size);
+    }
+
+    Ok(())
+}
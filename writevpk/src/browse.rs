@@ -0,0 +1,99 @@
+use std::io::{self, Read};
+
+use glob::{MatchOptions, Pattern};
+use thiserror::Error;
+
+#[derive(Debug, Error)]
+pub enum BrowseError {
+    #[error("file '{0}' not found in vpk")]
+    NotFound(String),
+
+    #[error(transparent)]
+    Pattern(#[from] glob::PatternError),
+
+    #[error(transparent)]
+    Vpk(#[from] vpk::Error),
+
+    #[error(transparent)]
+    Io(#[from] io::Error),
+}
+
+/// Metadata about a single file in a VPK.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct EntryStat {
+    /// The size of the entire file, including its preload data.
+    pub size: u64,
+
+    /// The number of bytes of the file that are stored in the directory VPK itself, rather than in an archive.
+    pub preload_size: u16,
+
+    /// The index of the archive (e.g. `tf2_misc_017.vpk`) that contains the rest of the file.
+    pub archive_index: u16,
+
+    pub crc: u32,
+}
+
+pub trait BrowseVpkExt {
+    /// Every path in the vpk which matches the glob `pattern`, sorted.
+    ///
+    /// `*` doesn't match across `/`, so `particles/*.pcf` won't match PCFs in subdirectories of `particles/`. Use `**`
+    /// to match any number of directories.
+    ///
+    /// ## Errors
+    ///
+    /// Returns [`Err`] if `pattern` isn't a valid glob.
+    fn glob(&self, pattern: &str) -> Result<Vec<&str>, BrowseError>;
+
+    /// Metadata about the file at `path_in_vpk`, or [`None`] if it doesn't exist in the vpk.
+    fn stat(&self, path_in_vpk: &str) -> Option<EntryStat>;
+
+    /// Opens the file at `path_in_vpk` for streaming, without reading the entire file into memory.
+    ///
+    /// ## Errors
+    ///
+    /// Returns [`Err`] if the file doesn't exist in the vpk, or if its archive couldn't be opened.
+    fn open(&self, path_in_vpk: &str) -> Result<impl Read, BrowseError>;
+}
+
+impl BrowseVpkExt for vpk::VPK {
+    fn glob(&self, pattern: &str) -> Result<Vec<&str>, BrowseError> {
+        let pattern = Pattern::new(pattern.trim_start_matches('/'))?;
+        let options = MatchOptions {
+            require_literal_separator: true,
+            ..MatchOptions::new()
+        };
+
+        let mut paths: Vec<_> = self
+            .tree
+            .keys()
+            .map(String::as_str)
+            .filter(|path| pattern.matches_with(path.trim_start_matches('/'), options))
+            .collect();
+
+        paths.sort_unstable();
+        Ok(paths)
+    }
+
+    fn stat(&self, path_in_vpk: &str) -> Option<EntryStat> {
+        let entry = self
+            .tree
+            .get(path_in_vpk)
+            .or_else(|| self.tree.get(crate::matching_key(self.tree.keys(), path_in_vpk)?))?;
+        Some(EntryStat {
+            size: u64::from(entry.dir_entry.file_length) + u64::from(entry.dir_entry.preload_length),
+            preload_size: entry.dir_entry.preload_length,
+            archive_index: entry.dir_entry.archive_index,
+            crc: entry.dir_entry.crc,
+        })
+    }
+
+    fn open(&self, path_in_vpk: &str) -> Result<impl Read, BrowseError> {
+        let entry = self
+            .tree
+            .get(path_in_vpk)
+            .or_else(|| self.tree.get(crate::matching_key(self.tree.keys(), path_in_vpk)?))
+            .ok_or_else(|| BrowseError::NotFound(path_in_vpk.to_string()))?;
+
+        Ok(entry.reader()?)
+    }
+}
@@ -1,4 +1,32 @@
-#![feature(file_buffered)]
+use std::io;
 
+use paths::GamePath;
+use typed_path::Utf8PlatformPath;
+
+pub mod browse;
 pub mod pack;
 pub mod patch;
+
+/// The key among a VPK tree's `keys` with the same [`GamePath`] as `path_in_vpk`. The engine looks entries up
+/// case-insensitively & with either separator, so this finds the entry it would when there's no exact match.
+pub(crate) fn matching_key<'a>(mut keys: impl Iterator<Item = &'a String>, path_in_vpk: &str) -> Option<&'a String> {
+    let path_in_vpk = GamePath::new(path_in_vpk);
+    keys.find(|key| GamePath::new(key) == path_in_vpk)
+}
+
+/// Flushes the entries of `dir` to disk, so that files created, renamed or removed in it aren't lost along with it on a
+/// power loss. Syncing a file only makes its contents durable; on Linux its name in the directory is only durable once
+/// the directory is synced too. Elsewhere this does nothing, since directories can't be opened like files.
+///
+/// # Errors
+///
+/// Returns [`Err`] if `dir` couldn't be opened or synced.
+pub fn sync_dir(dir: &Utf8PlatformPath) -> io::Result<()> {
+    #[cfg(unix)]
+    std::fs::File::open(dir)?.sync_all()?;
+
+    #[cfg(not(unix))]
+    let _ = dir;
+
+    Ok(())
+}
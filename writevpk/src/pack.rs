@@ -1,12 +1,13 @@
 use std::{
-    collections::HashMap,
+    collections::{BTreeMap, HashMap, HashSet, hash_map},
     fs::{self, File, OpenOptions},
-    io::{self, BufWriter, Read, Seek, Write},
+    io::{self, BufRead, BufReader, BufWriter, Read, Seek, Write},
 };
 
 use buf_read_write::BufStream;
-use byteorder::{LittleEndian, WriteBytesExt};
-use md5::{Digest, Md5};
+use byteorder::{LittleEndian, ReadBytesExt, WriteBytesExt};
+use md5::{Digest, Md5, digest::Output};
+use paths::GamePath;
 use thiserror::Error;
 use typed_path::{Utf8PlatformPath, Utf8PlatformPathBuf};
 
@@ -56,12 +57,154 @@ struct EntryInfo {
     crc: u32,
 }
 
+/// The size Valve's `vpk.exe` splits archives at by default, in bytes.
+pub const DEFAULT_SPLIT_SIZE: u32 = 200 * 1024 * 1024;
+
+/// Archives are hashed in fragments of this size for the dir file's archive MD5 section, like Valve's `vpk.exe`.
+const ARCHIVE_MD5_FRAGMENT_SIZE: u32 = 1024 * 1024;
+
+const VPK_SIGNATURE: u32 = 0x55AA1234;
+const VPK_VERSION: u32 = 2;
+const VPK_ARCHIVE_MD5_LENGTH: u32 = 28;
+const VPK_SELF_HASHES_LENGTH: u32 = 48;
+const VPK_SIGNATURE_LENGTH: u32 = 0;
+
+/// The archive index of entries whose contents are embedded in the dir file.
+const EMBEDDED_ARCHIVE_IDX: u16 = u16::MAX >> 1;
+
+/// An entry in the dir file's archive MD5 section, which lets tools verify a fragment of a numbered archive.
+#[derive(Debug, Clone)]
+struct ArchiveMd5 {
+    archive_idx: u16,
+    offset: u32,
+    size: u32,
+    checksum: Output<Md5>,
+}
+
+/// Statistics about a build by [`pack_directory`] or [`pack_directory_incremental`].
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct PackStats {
+    /// the number of files which were unchanged since the previous build, and weren't written again
+    pub reused_files: usize,
+    pub reused_bytes: u64,
+
+    /// the number of files which were new, changed, or in a previous archive that wasn't kept
+    pub written_files: usize,
+    pub written_bytes: u64,
+
+    /// the number of files whose contents are identical to an earlier file's, and which share its packed copy instead
+    /// of being written. See [`find_duplicates`].
+    pub deduplicated_files: usize,
+    pub deduplicated_bytes: u64,
+}
+
+/// A file whose contents are identical to an earlier file's, so that only one copy of them is packed.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct DuplicateFile {
+    /// the file's path, relative to the packed directory
+    pub path: String,
+
+    /// the path of the first file with the same contents, whose packed copy is shared
+    pub original: String,
+
+    pub size: u32,
+}
+
+/// Identifies the contents of a file, so that files with the same contents are only packed once.
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+struct ContentKey {
+    size: u32,
+    crc: u32,
+    md5: Output<Md5>,
+}
+
+impl ContentKey {
+    fn new(contents: &[u8]) -> Self {
+        let mut hasher = ContentHasher::default();
+        hasher.update(contents);
+        hasher.finish()
+    }
+
+    /// Hashes the file at `path` as it's read, rather than reading all of it into memory first.
+    fn read(path: &Utf8PlatformPath) -> Result<Self, Error> {
+        let mut hasher = ContentHasher::default();
+        File::open(path)
+            .and_then(|mut file| io::copy(&mut file, &mut hasher))
+            .map_err(|err| Error::CantOpenEntrySource(path.to_path_buf(), err))?;
+
+        Ok(hasher.finish())
+    }
+}
+
+/// Builds a [`ContentKey`] from contents which are read a chunk at a time, so they're never all in memory at once.
+#[derive(Default)]
+struct ContentHasher {
+    size: u64,
+    crc: crc32fast::Hasher,
+    md5: Md5,
+}
+
+impl ContentHasher {
+    fn update(&mut self, chunk: &[u8]) {
+        self.size += chunk.len() as u64;
+        self.crc.update(chunk);
+        self.md5.update(chunk);
+    }
+
+    fn finish(self) -> ContentKey {
+        ContentKey {
+            size: self.size as u32,
+            crc: self.crc.finalize(),
+            md5: self.md5.finalize(),
+        }
+    }
+}
+
+impl Write for ContentHasher {
+    fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+        self.update(buf);
+        Ok(buf.len())
+    }
+
+    fn flush(&mut self) -> io::Result<()> {
+        Ok(())
+    }
+}
+
+/// A file which isn't in the packed directory, but is generated once everything in it has been packed, from what was
+/// packed. A file which records the duplicates, like a manifest, can't be in the directory; they're only known once
+/// every file has been hashed, which packing does as it writes each file.
+pub struct GeneratedFile<'a> {
+    /// the file's path in the VPK. It replaces any file in the packed directory at the same path.
+    pub path: &'a str,
+
+    /// generates the file's contents from every duplicate that was packed, see [`find_duplicates`]
+    pub generate: Box<dyn FnOnce(&[DuplicateFile]) -> Vec<u8> + 'a>,
+}
+
+/// Files are read & compared in chunks of this size.
+const CHUNK_SIZE: usize = 64 * 1024;
+
+/// Packs the contents of `source` into a VPK named `vpk_name` in `dest`. The contents are split into numbered archives
+/// of at most `split_size` bytes - see [`DEFAULT_SPLIT_SIZE`] - unless a single file is larger. If everything fits in
+/// one archive, it's embedded in a single `{vpk_name}.vpk` instead.
+///
+/// Files with identical contents are only written once, and share the same copy; see [`find_duplicates`]. Each file is
+/// only read once, and is hashed as it's written. `generated` is packed after every file in `source`.
+///
+/// Every file written, and `dest` itself, is synced to disk before this returns, so a finished VPK survives a power
+/// loss.
+///
+/// # Errors
+///
+/// Returns [`Err`] if `source` or `dest` aren't directories, or if any file couldn't be read or written.
 pub fn pack_directory(
     source: &Utf8PlatformPath,
     dest: &Utf8PlatformPath,
     vpk_name: &str,
     split_size: u32,
-) -> Result<(), Error> {
+    generated: Option<GeneratedFile>,
+) -> Result<PackStats, Error> {
     if !fs::metadata(source)?.is_dir() {
         return Err(Error::SourceNotADirectory);
     }
@@ -71,42 +214,482 @@ pub fn pack_directory(
     }
 
     let tree = get_vpk_tree(source)?;
-    let (last_archive_path, last_archive_idx, tree) = write_tree(tree, dest, vpk_name, split_size)?;
+    let (tree, written, stats) = write_tree(tree, dest, vpk_name, split_size, generated)?;
 
     let vpk_path = dest.join(format!("{vpk_name}_dir.vpk"));
-    write_index_archive(&last_archive_path, last_archive_idx, tree, &vpk_path)?;
+    if written.archives.len() > 1 {
+        write_index_archive(None, tree, &written.archive_md5s, &vpk_path)?;
+        crate::sync_dir(dest)?;
+        return Ok(stats);
+    }
 
-    if last_archive_idx == 0 {
-        // we copied our 0th archive into _dir, so we need to drop the "_dir" and remove the 0th archive.
-        fs::remove_file(last_archive_path).map_err(Error::CantRemoveArchive0)?;
+    // everything fit into the 0th archive, so we copy it into _dir, drop the "_dir", and remove the 0th archive.
+    let archive_path = written.archives.first().map(|(_, archive_path)| archive_path.as_path());
+    write_index_archive(archive_path, tree, &[], &vpk_path)?;
 
-        fs::rename(vpk_path, dest.join(vpk_name).with_extension("vpk")).map_err(Error::CantRenameDirArchive)?;
+    if let Some(archive_path) = archive_path {
+        fs::remove_file(archive_path).map_err(Error::CantRemoveArchive0)?;
+    }
+
+    fs::rename(vpk_path, dest.join(vpk_name).with_extension("vpk")).map_err(Error::CantRenameDirArchive)?;
+    crate::sync_dir(dest)?;
+
+    Ok(stats)
+}
+
+/// Finds every file in `source` whose contents are identical to an earlier file's, in the order they're packed. Packing
+/// `source` with [`pack_directory`] or [`pack_directory_incremental`] only stores one copy of each file's contents,
+/// which its duplicates share. Packing finds the same duplicates itself, see [`GeneratedFile`] to record them in the VPK.
+///
+/// # Errors
+///
+/// Returns [`Err`] if `source` isn't a directory, or if any file couldn't be read.
+pub fn find_duplicates(source: &Utf8PlatformPath) -> Result<Vec<DuplicateFile>, Error> {
+    if !fs::metadata(source)?.is_dir() {
+        return Err(Error::SourceNotADirectory);
+    }
+
+    let mut originals = HashMap::new();
+    let mut duplicates = Vec::new();
+    for (extension, directories) in get_vpk_tree(source)?.0 {
+        for (directory, entries) in directories.0 {
+            for entry in entries {
+                let content = ContentKey::read(&entry.source_path)?;
+                let path = entry_path(&extension, &directory, &entry.filename);
+                match originals.get(&content) {
+                    Some(original) => duplicates.push(DuplicateFile {
+                        path,
+                        original: String::clone(original),
+                        size: content.size,
+                    }),
+                    None => {
+                        originals.insert(content, path);
+                    }
+                }
+            }
+        }
+    }
+
+    Ok(duplicates)
+}
+
+/// The path of an entry relative to the packed directory, from how it's written in the dir file's tree.
+fn entry_path(extension: &str, directory: &str, filename: &str) -> String {
+    let mut path = String::new();
+    if directory != " " {
+        path += directory;
+        path.push('/');
+    }
+
+    path += filename;
+    if extension != " " {
+        path.push('.');
+        path += extension;
+    }
+
+    path
+}
+
+/// Like [`pack_directory`], but reuses the archives of a previous build of the same VPK in `dest`. Files with the same
+/// path & contents as in the previous build aren't written again; only new & changed files are written, into new
+/// archives, and the dir file is rewritten to reference both. Files with identical contents still share one copy.
+///
+/// A previous archive is only kept if at least half of it is still used, otherwise its files are written again; so
+/// archives don't fill up with stale files over many builds. Archives which are no longer used are removed. The
+/// contents are never embedded into a single `{vpk_name}.vpk`, since its archive couldn't be reused.
+///
+/// `generated` is always written again, rather than reused.
+///
+/// Files in `dest` are only ever replaced or removed, never written into, so the previous build in `dest` may be hard
+/// links to a build elsewhere which has to stay intact until this one is finished.
+///
+/// # Errors
+///
+/// See [`pack_directory`]. The previous dir file is removed before any archive is written, so if building fails
+/// part-way, the next build is a full rebuild.
+pub fn pack_directory_incremental(
+    source: &Utf8PlatformPath,
+    dest: &Utf8PlatformPath,
+    vpk_name: &str,
+    split_size: u32,
+    generated: Option<GeneratedFile>,
+) -> Result<PackStats, Error> {
+    if !fs::metadata(source)?.is_dir() {
+        return Err(Error::SourceNotADirectory);
+    }
+
+    if !fs::metadata(dest)?.is_dir() {
+        return Err(Error::DestinationNotADirectory);
+    }
+
+    let vpk_path = dest.join(format!("{vpk_name}_dir.vpk"));
+
+    // a previous build that can't be read is replaced entirely
+    let previous = read_previous_build(&vpk_path).unwrap_or_default();
+    if fs::exists(&vpk_path)? {
+        fs::remove_file(&vpk_path)?;
+    }
+
+    let mut tree = get_vpk_tree(source)?;
+    if let Some(generated) = &generated {
+        tree.remove(&entry_key(&GamePath::new(generated.path)));
+    }
+
+    // find every file that's unchanged since the previous build, and how much of each previous archive they use
+    let mut reusable = HashMap::new();
+    let mut live_sizes: HashMap<u16, u64> = HashMap::new();
+    let mut previous_archives = HashMap::new();
+    for (extension, directories) in &tree.0 {
+        for (directory, entries) in &directories.0 {
+            for entry in entries {
+                let key = (extension.clone(), directory.clone(), entry.filename.clone());
+                let Some(&previous_entry) = previous.entries.get(&key) else {
+                    continue;
+                };
+
+                if previous_entry.size != entry.size {
+                    continue;
+                }
+
+                // an archive which can't be opened can't be reused either
+                let archive = match previous_archives.entry(previous_entry.archive_idx) {
+                    hash_map::Entry::Occupied(archive) => archive.into_mut(),
+                    hash_map::Entry::Vacant(archive) => {
+                        let file = File::open(archive_path(dest, vpk_name, previous_entry.archive_idx)).ok();
+                        archive.insert(file.map(BufReader::new))
+                    }
+                };
+
+                let Some(archive) = archive else {
+                    continue;
+                };
+
+                if let Some(content) = unchanged_content(&entry.source_path, archive, &previous_entry)? {
+                    *live_sizes.entry(previous_entry.archive_idx).or_default() += u64::from(entry.size);
+                    reusable.insert(key, (previous_entry, content));
+                }
+            }
+        }
+    }
+
+    // unused archives are removed below, which Windows won't do while they're open
+    drop(previous_archives);
+
+    // mostly-stale archives aren't kept, so their unchanged files are written again along with the new ones
+    let mut kept_archives = HashSet::new();
+    for (archive_idx, live_size) in live_sizes {
+        let Ok(metadata) = fs::metadata(archive_path(dest, vpk_name, archive_idx)) else {
+            continue;
+        };
+
+        if live_size * 2 >= metadata.len() {
+            kept_archives.insert(archive_idx);
+        }
+    }
+
+    reusable.retain(|_, (previous_entry, _)| kept_archives.contains(&previous_entry.archive_idx));
+
+    let mut stats = PackStats::default();
+    let mut writer = ArchiveWriter::new(dest, vpk_name, split_size, kept_archives.clone());
+    let mut written_tree = VpkTree(BTreeMap::new());
+    for (extension, directories) in tree.0 {
+        for (directory, entries) in directories.0 {
+            for entry in entries {
+                let path = entry_path(&extension, &directory, &entry.filename);
+                let key = (extension.clone(), directory.clone(), entry.filename);
+                let info = if let Some((previous_entry, content)) = reusable.get(&key) {
+                    let (archive_idx, offset) = writer.reuse_entry(content, previous_entry, path, &mut stats);
+                    EntryInfo {
+                        filename: key.2,
+                        archive_idx,
+                        offset,
+                        size: previous_entry.size,
+                        crc: previous_entry.crc,
+                    }
+                } else {
+                    let (archive_idx, offset, content) =
+                        writer.write_unique_file(&entry.source_path, entry.size, path, &mut stats)?;
+
+                    EntryInfo {
+                        filename: key.2,
+                        archive_idx,
+                        offset,
+                        size: content.size,
+                        crc: content.crc,
+                    }
+                };
+
+                written_tree.insert(&extension, &directory, info);
+            }
+        }
+    }
+
+    if let Some(generated) = generated {
+        writer.write_generated(generated, &mut written_tree, &mut stats)?;
+    }
+
+    let written = writer.finish()?;
+
+    let mut archive_md5s = written.archive_md5s;
+    for &archive_idx in &kept_archives {
+        let previous_md5s: Vec<_> = previous
+            .archive_md5s
+            .iter()
+            .filter(|archive_md5| archive_md5.archive_idx == archive_idx)
+            .cloned()
+            .collect();
+
+        if previous_md5s.is_empty() {
+            archive_md5s.extend(hash_archive(&archive_path(dest, vpk_name, archive_idx), archive_idx)?);
+        } else {
+            archive_md5s.extend(previous_md5s);
+        }
+    }
+
+    archive_md5s.sort_by_key(|archive_md5| (archive_md5.archive_idx, archive_md5.offset));
+    write_index_archive(None, written_tree, &archive_md5s, &vpk_path)?;
+
+    let used_archives = kept_archives
+        .into_iter()
+        .chain(written.archives.into_iter().map(|(archive_idx, _)| archive_idx))
+        .collect();
+    remove_unused_archives(dest, vpk_name, &used_archives)?;
+    crate::sync_dir(dest)?;
+
+    Ok(stats)
+}
+
+/// Compares the file at `source` to `previous_entry`'s copy in `archive`, and returns the key of its contents if they're
+/// identical. The dir file only records each entry's size & CRC, and a CRC can collide; so the contents themselves are
+/// compared, rather than risk packing stale contents which would still match the dir file's CRC.
+fn unchanged_content(
+    source: &Utf8PlatformPath,
+    archive: &mut BufReader<File>,
+    previous_entry: &PreviousEntry,
+) -> Result<Option<ContentKey>, Error> {
+    let mut source_file = File::open(source).map_err(|err| Error::CantOpenEntrySource(source.to_path_buf(), err))?;
+    archive.seek(io::SeekFrom::Start(previous_entry.offset.into()))?;
+    let mut previous = Read::by_ref(archive).take(previous_entry.size.into());
+
+    let mut hasher = ContentHasher::default();
+    let mut chunk = vec![0; CHUNK_SIZE];
+    let mut previous_chunk = vec![0; CHUNK_SIZE];
+    loop {
+        let size = match source_file.read(&mut chunk) {
+            Ok(0) => break,
+            Ok(size) => size,
+            Err(err) if err.kind() == io::ErrorKind::Interrupted => continue,
+            Err(err) => return Err(Error::CantOpenEntrySource(source.to_path_buf(), err)),
+        };
+
+        // the file is longer than the previous copy, or the archive was truncated
+        match previous.read_exact(&mut previous_chunk[..size]) {
+            Ok(()) => {}
+            Err(err) if err.kind() == io::ErrorKind::UnexpectedEof => return Ok(None),
+            Err(err) => return Err(err.into()),
+        }
+
+        if chunk[..size] != previous_chunk[..size] {
+            return Ok(None);
+        }
+
+        hasher.update(&chunk[..size]);
+    }
+
+    let content = hasher.finish();
+    Ok((content.size == previous_entry.size).then_some(content))
+}
+
+fn archive_path(dest: &Utf8PlatformPath, vpk_name: &str, archive_idx: u16) -> Utf8PlatformPathBuf {
+    dest.join(format!("{vpk_name}_{archive_idx:03}.vpk"))
+}
+
+/// Removes every numbered archive of `vpk_name` in `dest` which isn't in `used_archives`, and any single-file
+/// `{vpk_name}.vpk`.
+fn remove_unused_archives(dest: &Utf8PlatformPath, vpk_name: &str, used_archives: &HashSet<u16>) -> Result<(), Error> {
+    let single_file_name = format!("{vpk_name}.vpk");
+    for entry in fs::read_dir(dest)? {
+        let entry = entry?;
+        let Ok(file_name) = entry.file_name().into_string() else {
+            continue;
+        };
+
+        let archive_idx = file_name
+            .strip_prefix(vpk_name)
+            .and_then(|rest| rest.strip_prefix('_'))
+            .and_then(|rest| rest.strip_suffix(".vpk"))
+            .filter(|idx| idx.len() == 3)
+            .and_then(|idx| idx.parse::<u16>().ok());
+
+        let is_unused = match archive_idx {
+            Some(archive_idx) => !used_archives.contains(&archive_idx),
+            None => file_name == single_file_name,
+        };
+
+        if is_unused && entry.metadata()?.is_file() {
+            fs::remove_file(entry.path())?;
+        }
     }
 
     Ok(())
 }
 
+/// Hashes an existing archive in [`ARCHIVE_MD5_FRAGMENT_SIZE`] fragments, for the dir file's archive MD5 section.
+fn hash_archive(archive_path: &Utf8PlatformPath, archive_idx: u16) -> io::Result<Vec<ArchiveMd5>> {
+    let mut archive_file = BufReader::new(File::open(archive_path)?);
+
+    let mut archive_md5s = Vec::new();
+    let mut offset = 0;
+    loop {
+        let mut hasher = Md5::new();
+        let size = io::copy(
+            &mut Read::by_ref(&mut archive_file).take(ARCHIVE_MD5_FRAGMENT_SIZE.into()),
+            &mut hasher,
+        )? as u32;
+
+        if size == 0 {
+            return Ok(archive_md5s);
+        }
+
+        archive_md5s.push(ArchiveMd5 {
+            archive_idx,
+            offset,
+            size,
+            checksum: hasher.finalize(),
+        });
+
+        offset += size;
+    }
+}
+
+/// An entry's extension, directory & filename, as they're written in the dir file's tree.
+type EntryKey = (String, String, String);
+
+/// Where an entry of a previous build was written.
+#[derive(Debug, Clone, Copy)]
+struct PreviousEntry {
+    archive_idx: u16,
+    offset: u32,
+    size: u32,
+    crc: u32,
+}
+
+#[derive(Debug, Default)]
+struct PreviousBuild {
+    /// every entry stored in a numbered archive. Embedded entries can't be reused, so they're left out.
+    entries: HashMap<EntryKey, PreviousEntry>,
+    archive_md5s: Vec<ArchiveMd5>,
+}
+
+/// Reads the tree & archive MD5 section of a dir file at `vpk_path`. If there isn't one, the build is empty.
+fn read_previous_build(vpk_path: &Utf8PlatformPath) -> io::Result<PreviousBuild> {
+    fn read_string(reader: &mut impl BufRead) -> io::Result<String> {
+        let mut bytes = Vec::new();
+        reader.read_until(0, &mut bytes)?;
+        if bytes.pop() != Some(0) {
+            return Err(io::ErrorKind::UnexpectedEof.into());
+        }
+
+        String::from_utf8(bytes).map_err(|err| io::Error::new(io::ErrorKind::InvalidData, err))
+    }
+
+    let mut file = match File::open(vpk_path) {
+        Ok(file) => BufReader::new(file),
+        Err(err) if err.kind() == io::ErrorKind::NotFound => return Ok(PreviousBuild::default()),
+        Err(err) => return Err(err),
+    };
+
+    if file.read_u32::<LittleEndian>()? != VPK_SIGNATURE || file.read_u32::<LittleEndian>()? != VPK_VERSION {
+        return Err(io::Error::new(io::ErrorKind::InvalidData, "not a version 2 VPK"));
+    }
+
+    let _tree_size = file.read_u32::<LittleEndian>()?;
+    let embed_chunk_size = file.read_u32::<LittleEndian>()?;
+    let archive_md5s_size = file.read_u32::<LittleEndian>()?;
+    let _self_hashes_size = file.read_u32::<LittleEndian>()?;
+    let _signature_size = file.read_u32::<LittleEndian>()?;
+
+    let mut entries = HashMap::new();
+    loop {
+        let extension = read_string(&mut file)?;
+        if extension.is_empty() {
+            break;
+        }
+
+        loop {
+            let directory = read_string(&mut file)?;
+            if directory.is_empty() {
+                break;
+            }
+
+            loop {
+                let filename = read_string(&mut file)?;
+                if filename.is_empty() {
+                    break;
+                }
+
+                let crc = file.read_u32::<LittleEndian>()?;
+                let preload_size = file.read_u16::<LittleEndian>()?;
+                let archive_idx = file.read_u16::<LittleEndian>()?;
+                let offset = file.read_u32::<LittleEndian>()?;
+                let size = file.read_u32::<LittleEndian>()?;
+                let _terminator = file.read_u16::<LittleEndian>()?;
+                file.seek_relative(preload_size.into())?;
+
+                if preload_size == 0 && archive_idx != EMBEDDED_ARCHIVE_IDX {
+                    let previous_entry = PreviousEntry {
+                        archive_idx,
+                        offset,
+                        size,
+                        crc,
+                    };
+
+                    entries.insert((extension.clone(), directory.clone(), filename), previous_entry);
+                }
+            }
+        }
+    }
+
+    file.seek_relative(embed_chunk_size.into())?;
+
+    let mut archive_md5s = Vec::new();
+    for _ in 0..archive_md5s_size / VPK_ARCHIVE_MD5_LENGTH {
+        let archive_idx = file.read_u32::<LittleEndian>()? as u16;
+        let offset = file.read_u32::<LittleEndian>()?;
+        let size = file.read_u32::<LittleEndian>()?;
+
+        let mut checksum = Output::<Md5>::default();
+        file.read_exact(&mut checksum)?;
+
+        archive_md5s.push(ArchiveMd5 {
+            archive_idx,
+            offset,
+            size,
+            checksum,
+        });
+    }
+
+    Ok(PreviousBuild { entries, archive_md5s })
+}
+
+/// Writes the dir file, with `archive_md5s` describing the numbered archives. If `embedded_archive` is given, its
+/// contents are copied into the dir file, and every entry is expected to be in it.
 fn write_index_archive(
-    last_archive_path: &Utf8PlatformPath,
-    last_archive_idx: u16,
+    embedded_archive: Option<&Utf8PlatformPath>,
     tree: VpkTree<EntryInfo>,
+    archive_md5s: &[ArchiveMd5],
     vpk_path: &Utf8PlatformPath,
 ) -> Result<(), Error> {
-    let mut stream = BufStream::new(
-        OpenOptions::new()
-            .create(true)
-            .truncate(true)
-            .read(true)
-            .write(true)
-            .open(vpk_path)
-            .map_err(Error::CantOpenDirVpk)?,
-    );
+    let vpk_file = OpenOptions::new()
+        .create(true)
+        .truncate(true)
+        .read(true)
+        .write(true)
+        .open(vpk_path)
+        .map_err(Error::CantOpenDirVpk)?;
+    let mut stream = BufStream::new(vpk_file.try_clone()?);
 
-    const VPK_SIGNATURE: u32 = 0x55AA1234;
-    const VPK_VERSION: u32 = 2;
-    const VPK_CHUNK_HASHES_LENGTH: u32 = 0;
-    const VPK_SELF_HASHES_LENGTH: u32 = 48;
-    const VPK_SIGNATURE_LENGTH: u32 = 0;
+    let archive_md5s_size = archive_md5s.len() as u32 * VPK_ARCHIVE_MD5_LENGTH;
 
     stream.write_u32::<LittleEndian>(VPK_SIGNATURE)?;
     stream.write_u32::<LittleEndian>(VPK_VERSION)?;
@@ -117,7 +700,7 @@ fn write_index_archive(
     let embed_chunk_offset = stream.stream_position()?;
     stream.write_u32::<LittleEndian>(0)?;
 
-    stream.write_u32::<LittleEndian>(VPK_CHUNK_HASHES_LENGTH)?;
+    stream.write_u32::<LittleEndian>(archive_md5s_size)?;
     stream.write_u32::<LittleEndian>(VPK_SELF_HASHES_LENGTH)?;
     stream.write_u32::<LittleEndian>(VPK_SIGNATURE_LENGTH)?;
 
@@ -137,8 +720,8 @@ fn write_index_archive(
                 stream.write_u32::<LittleEndian>(entry.crc)?;
                 // this impl doesnt support writing preload data, so there is always 0
                 stream.write_u16::<LittleEndian>(0)?;
-                if last_archive_idx == 0 {
-                    stream.write_u16::<LittleEndian>(u16::MAX >> 1)?;
+                if embedded_archive.is_some() {
+                    stream.write_u16::<LittleEndian>(EMBEDDED_ARCHIVE_IDX)?;
                 } else {
                     stream.write_u16::<LittleEndian>(entry.archive_idx)?;
                 }
@@ -157,118 +740,475 @@ fn write_index_archive(
 
     let tree_size = (stream.stream_position()? - tree_start) as u32;
 
-    let embed_chunk_size = if last_archive_idx == 0 {
-        let mut last_archive_file = File::open_buffered(last_archive_path)?;
-        io::copy(&mut last_archive_file, &mut stream)? as u32
+    let embed_chunk_size = if let Some(embedded_archive) = embedded_archive {
+        let mut embedded_archive_file = BufReader::new(File::open(embedded_archive)?);
+        io::copy(&mut embedded_archive_file, &mut stream)? as u32
     } else {
         0
     };
 
-    stream.flush()?;
-
-    let chunk_hashes = Md5::new().finalize();
-    let tree_hash = {
-        stream.seek(io::SeekFrom::Start(tree_start))?;
-        let mut tree_reader = Read::by_ref(&mut stream).take(tree_size as u64);
-
-        let mut tree_hasher = Md5::new();
-        io::copy(&mut tree_reader, &mut tree_hasher)?;
-
-        tree_hasher.finalize()
-    };
-
-    let file_hash = {
-        stream.seek(io::SeekFrom::Start(0))?;
-        let mut header_reader = Read::by_ref(&mut stream).take(tree_start);
-
-        let mut file_hasher = Md5::new();
-        io::copy(&mut header_reader, &mut file_hasher)?;
-        file_hasher.write_all(&tree_hash)?;
-        file_hasher.write_all(&chunk_hashes)?;
-        file_hasher.finalize()
-    };
+    let archive_md5s_start = stream.stream_position()?;
+    for archive_md5 in archive_md5s {
+        stream.write_u32::<LittleEndian>(u32::from(archive_md5.archive_idx))?;
+        stream.write_u32::<LittleEndian>(archive_md5.offset)?;
+        stream.write_u32::<LittleEndian>(archive_md5.size)?;
+        stream.write_all(&archive_md5.checksum)?;
+    }
 
+    // the header has to be complete before the whole file is hashed
     stream.seek(io::SeekFrom::Start(tree_size_offset))?;
     stream.write_u32::<LittleEndian>(tree_size)?;
 
     stream.seek(io::SeekFrom::Start(embed_chunk_offset))?;
     stream.write_u32::<LittleEndian>(embed_chunk_size)?;
 
+    stream.flush()?;
+
+    let tree_hash = hash_range(&mut stream, tree_start, tree_size.into())?;
+    let archive_md5s_hash = hash_range(&mut stream, archive_md5s_start, archive_md5s_size.into())?;
+
     stream.seek(io::SeekFrom::End(0))?;
     stream.write_all(&tree_hash)?;
-    stream.write_all(&chunk_hashes)?;
+    stream.write_all(&archive_md5s_hash)?;
+    stream.flush()?;
+
+    // the file hash covers everything before it, including the tree & archive MD5 section hashes
+    let file_size = stream.stream_position()?;
+    let file_hash = hash_range(&mut stream, 0, file_size)?;
+
+    stream.seek(io::SeekFrom::End(0))?;
     stream.write_all(&file_hash)?;
 
     stream.flush()?;
+    vpk_file.sync_all()?;
 
     Ok(())
 }
 
+/// Hashes `size` bytes of `stream`, starting at `start`.
+fn hash_range(stream: &mut (impl Read + Seek), start: u64, size: u64) -> io::Result<Output<Md5>> {
+    stream.seek(io::SeekFrom::Start(start))?;
+
+    let mut hasher = Md5::new();
+    io::copy(&mut Read::by_ref(stream).take(size), &mut hasher)?;
+
+    Ok(hasher.finalize())
+}
+
 fn write_tree(
-    tree: VpkTree<Entry>,
+    mut tree: VpkTree<Entry>,
     dest: &Utf8PlatformPath,
     vpk_name: &str,
     split_size: u32,
-) -> Result<(Utf8PlatformPathBuf, u16, VpkTree<EntryInfo>), Error> {
-    let mut archive_path = dest.join(format!("{vpk_name}_000.vpk"));
-    let mut archive_file = BufWriter::new(
-        OpenOptions::new()
-            .create(true)
-            .truncate(true)
-            .write(true)
-            .open(&archive_path)
-            .map_err(Error::CantOpenVpk)?,
-    );
-
-    let mut current_archive_idx = 0;
-    let mut current_size = 0;
+    generated: Option<GeneratedFile>,
+) -> Result<(VpkTree<EntryInfo>, WrittenArchives, PackStats), Error> {
+    let mut writer = ArchiveWriter::new(dest, vpk_name, split_size, HashSet::new());
+    if let Some(generated) = &generated {
+        tree.remove(&entry_key(&GamePath::new(generated.path)));
+    }
 
-    let mut written_tree = VpkTree(HashMap::new());
+    let mut stats = PackStats::default();
+    let mut written_tree = VpkTree(BTreeMap::new());
     for (extension, directories) in tree.0 {
         for (dir_path, entries) in directories.0 {
             for entry in entries {
-                if current_size > 0 && current_size + entry.size > split_size {
-                    archive_file.flush()?;
-                    current_archive_idx += 1;
-                    current_size = 0;
-
-                    archive_path = dest.with_file_name(format!("{vpk_name}_{current_archive_idx:03}.vpk"));
-                    archive_file = BufWriter::new(
-                        OpenOptions::new()
-                            .create(true)
-                            .truncate(true)
-                            .write(true)
-                            .open(&archive_path)
-                            .map_err(Error::CantOpenVpk)?,
-                    );
-                }
-
-                let results =
-                    fs::read(&entry.source_path).map_err(|err| Error::CantOpenEntrySource(entry.source_path, err))?;
-                let checksum = crc32fast::hash(&results);
-
-                _ = archive_file.write(&results)?;
+                let path = entry_path(&extension, &dir_path, &entry.filename);
+                let (archive_idx, offset, content) =
+                    writer.write_unique_file(&entry.source_path, entry.size, path, &mut stats)?;
 
                 written_tree.insert(
                     &extension,
                     &dir_path,
                     EntryInfo {
                         filename: entry.filename,
-                        archive_idx: current_archive_idx,
-                        offset: current_size,
-                        size: entry.size,
-                        crc: checksum,
+                        archive_idx,
+                        offset,
+                        size: content.size,
+                        crc: content.crc,
                     },
                 );
+            }
+        }
+    }
+
+    if let Some(generated) = generated {
+        writer.write_generated(generated, &mut written_tree, &mut stats)?;
+    }
+
+    Ok((written_tree, writer.finish()?, stats))
+}
 
-                current_size += results.len() as u32;
+/// The archives written by an [`ArchiveWriter`].
+struct WrittenArchives {
+    /// the index & path of every archive, in the order they were written
+    archives: Vec<(u16, Utf8PlatformPathBuf)>,
+    archive_md5s: Vec<ArchiveMd5>,
+
+    /// every entry which shares an earlier entry's copy, in the order they were written
+    duplicates: Vec<DuplicateFile>,
+}
+
+/// Where the packed copy of some contents is, and the path of the entry it was first packed for.
+struct PackedCopy {
+    archive_idx: u16,
+    offset: u32,
+    path: String,
+}
+
+/// How far an [`ArchiveWriter`] had written before an entry, so that the entry can be taken back out.
+struct Checkpoint {
+    archive_size: u32,
+    fragment: Md5,
+    fragment_offset: u32,
+    fragment_size: u32,
+    archive_md5s: usize,
+}
+
+/// Writes entries into numbered archives, moving on to the next archive once an entry wouldn't fit in `split_size`,
+/// and hashes each archive in [`ARCHIVE_MD5_FRAGMENT_SIZE`] fragments as it's written. Archives are only created once
+/// something is written to them.
+struct ArchiveWriter<'a> {
+    dest: &'a Utf8PlatformPath,
+    vpk_name: &'a str,
+    split_size: u32,
+
+    /// indices of existing archives, which are skipped rather than overwritten
+    reserved: HashSet<u16>,
+
+    /// the copy of the contents of every entry written or reused so far
+    copies: HashMap<ContentKey, PackedCopy>,
+
+    archive: Option<(u16, BufWriter<File>)>,
+    archive_size: u32,
+
+    fragment: Md5,
+    fragment_offset: u32,
+    fragment_size: u32,
+
+    written: WrittenArchives,
+}
+
+impl<'a> ArchiveWriter<'a> {
+    fn new(dest: &'a Utf8PlatformPath, vpk_name: &'a str, split_size: u32, reserved: HashSet<u16>) -> Self {
+        Self {
+            dest,
+            vpk_name,
+            split_size,
+            reserved,
+            copies: HashMap::new(),
+            archive: None,
+            archive_size: 0,
+            fragment: Md5::new(),
+            fragment_offset: 0,
+            fragment_size: 0,
+            written: WrittenArchives {
+                archives: Vec::new(),
+                archive_md5s: Vec::new(),
+                duplicates: Vec::new(),
+            },
+        }
+    }
+
+    /// The location of an earlier copy of `content`, if there is one, in which case the entry at `path` is recorded as
+    /// its duplicate.
+    fn find_copy(&mut self, content: &ContentKey, path: &str, stats: &mut PackStats) -> Option<(u16, u32)> {
+        let copy = self.copies.get(content)?;
+        self.written.duplicates.push(DuplicateFile {
+            path: path.to_string(),
+            original: copy.path.clone(),
+            size: content.size,
+        });
+
+        stats.deduplicated_files += 1;
+        stats.deduplicated_bytes += u64::from(content.size);
+        Some((copy.archive_idx, copy.offset))
+    }
+
+    fn add_copy(&mut self, content: ContentKey, (archive_idx, offset): (u16, u32), path: String) {
+        self.copies.insert(
+            content,
+            PackedCopy {
+                archive_idx,
+                offset,
+                path,
+            },
+        );
+    }
+
+    /// Writes `data`, identified by `content`, like [`ArchiveWriter::write_entry`]. If the same contents were already
+    /// written or reused, they aren't written again, and the location of that copy is returned instead.
+    fn write_unique_entry(
+        &mut self,
+        content: ContentKey,
+        data: &[u8],
+        path: String,
+        stats: &mut PackStats,
+    ) -> Result<(u16, u32), Error> {
+        if let Some(location) = self.find_copy(&content, &path, stats) {
+            return Ok(location);
+        }
+
+        let location = self.write_entry(data)?;
+        self.add_copy(content, location, path);
+        stats.written_files += 1;
+        stats.written_bytes += data.len() as u64;
+
+        Ok(location)
+    }
+
+    /// Streams the file at `source` into the archives, hashing it as it's written, so it's only read once and never
+    /// held in memory. `size` is the file's expected size, which picks the archive it's written to. If the same
+    /// contents were already written or reused, the copy which was just written is taken back out, and the location of
+    /// the earlier copy is returned instead. Returns the location & key of the file's contents.
+    fn write_unique_file(
+        &mut self,
+        source: &Utf8PlatformPath,
+        size: u32,
+        path: String,
+        stats: &mut PackStats,
+    ) -> Result<(u16, u32, ContentKey), Error> {
+        let source_error = |err| Error::CantOpenEntrySource(source.to_path_buf(), err);
+        let mut source_file = File::open(source).map_err(source_error)?;
+
+        let (archive_idx, offset) = self.start_entry(size)?;
+        let checkpoint = self.checkpoint();
+
+        let mut hasher = ContentHasher::default();
+        let mut chunk = vec![0; CHUNK_SIZE];
+        loop {
+            let size = match source_file.read(&mut chunk) {
+                Ok(0) => break,
+                Ok(size) => size,
+                Err(err) if err.kind() == io::ErrorKind::Interrupted => continue,
+                Err(err) => return Err(source_error(err)),
+            };
+
+            hasher.update(&chunk[..size]);
+            self.append(&chunk[..size])?;
+        }
+
+        let content = hasher.finish();
+        if let Some((archive_idx, offset)) = self.find_copy(&content, &path, stats) {
+            self.rollback(checkpoint)?;
+            return Ok((archive_idx, offset, content));
+        }
+
+        self.add_copy(content.clone(), (archive_idx, offset), path);
+        stats.written_files += 1;
+        stats.written_bytes += u64::from(content.size);
+
+        Ok((archive_idx, offset, content))
+    }
+
+    /// Generates & writes `generated`, and adds it to `tree`.
+    fn write_generated(
+        &mut self,
+        generated: GeneratedFile,
+        tree: &mut VpkTree<EntryInfo>,
+        stats: &mut PackStats,
+    ) -> Result<(), Error> {
+        let data = (generated.generate)(&self.written.duplicates);
+        let content = ContentKey::new(&data);
+        let crc = content.crc;
+        let (archive_idx, offset) = self.write_unique_entry(content, &data, generated.path.to_string(), stats)?;
+
+        let (extension, directory, filename) = entry_key(&GamePath::new(generated.path));
+        tree.insert(
+            &extension,
+            &directory,
+            EntryInfo {
+                filename,
+                archive_idx,
+                offset,
+                size: data.len() as u32,
+                crc,
+            },
+        );
+
+        Ok(())
+    }
+
+    /// Reuses the copy of an entry's contents in a previous build's archive, unless the same contents were already
+    /// written or reused, in which case the location of that copy is returned instead.
+    fn reuse_entry(
+        &mut self,
+        content: &ContentKey,
+        previous_entry: &PreviousEntry,
+        path: String,
+        stats: &mut PackStats,
+    ) -> (u16, u32) {
+        if let Some(location) = self.find_copy(content, &path, stats) {
+            return location;
+        }
+
+        let location = (previous_entry.archive_idx, previous_entry.offset);
+        self.add_copy(content.clone(), location, path);
+        stats.reused_files += 1;
+        stats.reused_bytes += u64::from(previous_entry.size);
+
+        location
+    }
+
+    /// Writes `data` into the current archive, or the next one if it wouldn't fit. Returns the index of the archive
+    /// `data` was written to, and its offset in that archive.
+    fn write_entry(&mut self, data: &[u8]) -> Result<(u16, u32), Error> {
+        let location = self.start_entry(data.len() as u32)?;
+        self.append(data)?;
+        Ok(location)
+    }
+
+    /// Moves on to the next archive if an entry of `size` bytes wouldn't fit in the current one. Returns the index of
+    /// the archive the entry will be written to, and its offset in that archive.
+    fn start_entry(&mut self, size: u32) -> Result<(u16, u32), Error> {
+        let is_full = self.archive_size > 0 && self.archive_size.saturating_add(size) > self.split_size;
+        if self.archive.is_none() || is_full {
+            self.next_archive()?;
+        }
+
+        let (archive_idx, _) = self.archive.as_ref().expect("an archive was just opened");
+        Ok((*archive_idx, self.archive_size))
+    }
+
+    /// Appends `data` to the entry being written into the current archive.
+    fn append(&mut self, data: &[u8]) -> Result<(), Error> {
+        let (_, archive_file) = self.archive.as_mut().expect("an entry should have been started");
+        archive_file.write_all(data)?;
+        self.archive_size += data.len() as u32;
+        self.hash(data);
+
+        Ok(())
+    }
+
+    fn checkpoint(&self) -> Checkpoint {
+        Checkpoint {
+            archive_size: self.archive_size,
+            fragment: self.fragment.clone(),
+            fragment_offset: self.fragment_offset,
+            fragment_size: self.fragment_size,
+            archive_md5s: self.written.archive_md5s.len(),
+        }
+    }
+
+    /// Takes everything written to the current archive since `checkpoint` back out of it.
+    fn rollback(&mut self, checkpoint: Checkpoint) -> Result<(), Error> {
+        let (_, archive_file) = self
+            .archive
+            .as_mut()
+            .expect("the checkpoint's archive should still be open");
+        archive_file.flush()?;
+
+        let file = archive_file.get_mut();
+        file.set_len(checkpoint.archive_size.into())?;
+        file.seek(io::SeekFrom::Start(checkpoint.archive_size.into()))?;
+
+        // any fragments finished since the checkpoint were after it in the current archive
+        self.written.archive_md5s.truncate(checkpoint.archive_md5s);
+        self.archive_size = checkpoint.archive_size;
+        self.fragment = checkpoint.fragment;
+        self.fragment_offset = checkpoint.fragment_offset;
+        self.fragment_size = checkpoint.fragment_size;
+
+        Ok(())
+    }
+
+    fn next_archive(&mut self) -> Result<(), Error> {
+        let first_idx = match &self.archive {
+            Some((archive_idx, _)) => archive_idx + 1,
+            None => self
+                .written
+                .archives
+                .last()
+                .map_or(0, |(archive_idx, _)| archive_idx + 1),
+        };
+
+        self.finish_archive()?;
+
+        let archive_idx = (first_idx..)
+            .find(|archive_idx| !self.reserved.contains(archive_idx))
+            .expect("there should be a free archive index");
+
+        // an existing archive is replaced rather than truncated, since it may be a hard link to another build's archive
+        let archive_path = archive_path(self.dest, self.vpk_name, archive_idx);
+        if let Err(err) = fs::remove_file(&archive_path)
+            && err.kind() != io::ErrorKind::NotFound
+        {
+            return Err(Error::CantOpenVpk(err));
+        }
+
+        let archive_file = OpenOptions::new()
+            .create(true)
+            .truncate(true)
+            .write(true)
+            .open(&archive_path)
+            .map_err(Error::CantOpenVpk)?;
+
+        self.archive = Some((archive_idx, BufWriter::new(archive_file)));
+        self.written.archives.push((archive_idx, archive_path));
+
+        Ok(())
+    }
+
+    fn finish_archive(&mut self) -> Result<(), Error> {
+        self.finish_fragment();
+        if let Some((_, archive_file)) = self.archive.take() {
+            archive_file
+                .into_inner()
+                .map_err(io::IntoInnerError::into_error)?
+                .sync_all()?;
+
+            // an archive only ends up empty if every entry written to it was a duplicate, and was taken back out
+            if self.archive_size == 0
+                && let Some((_, archive_path)) = self.written.archives.pop()
+            {
+                fs::remove_file(archive_path)?;
+            }
+        }
+
+        self.archive_size = 0;
+        self.fragment_offset = 0;
+
+        Ok(())
+    }
+
+    fn hash(&mut self, mut data: &[u8]) {
+        while !data.is_empty() {
+            let remaining = (ARCHIVE_MD5_FRAGMENT_SIZE - self.fragment_size) as usize;
+            let (fragment, rest) = data.split_at(remaining.min(data.len()));
+            self.fragment.update(fragment);
+            self.fragment_size += fragment.len() as u32;
+            data = rest;
+
+            if self.fragment_size == ARCHIVE_MD5_FRAGMENT_SIZE {
+                self.finish_fragment();
             }
         }
     }
 
-    archive_file.flush()?;
+    fn finish_fragment(&mut self) {
+        let Some((archive_idx, _)) = &self.archive else {
+            return;
+        };
+
+        if self.fragment_size == 0 {
+            return;
+        }
+
+        self.written.archive_md5s.push(ArchiveMd5 {
+            archive_idx: *archive_idx,
+            offset: self.fragment_offset,
+            size: self.fragment_size,
+            checksum: self.fragment.finalize_reset(),
+        });
 
-    Ok((archive_path, current_archive_idx, written_tree))
+        self.fragment_offset += self.fragment_size;
+        self.fragment_size = 0;
+    }
+
+    /// Flushes & syncs the last archive, and returns every archive that was written.
+    fn finish(mut self) -> Result<WrittenArchives, Error> {
+        self.finish_archive()?;
+        Ok(self.written)
+    }
 }
 
 fn get_vpk_tree(source: &Utf8PlatformPath) -> Result<VpkTree<Entry>, Error> {
@@ -284,13 +1224,8 @@ fn get_vpk_tree(source: &Utf8PlatformPath) -> Result<VpkTree<Entry>, Error> {
             }
 
             let size = metadata.len() as u32;
-            let extension = source_path.extension().unwrap_or(" ").to_string();
-            let filename = source_path.file_stem().unwrap_or(" ").to_string();
-
-            let directory = source_path
-                .strip_prefix(source)?
-                .parent()
-                .map_or(" ".to_string(), |el| el.with_unix_encoding().to_string());
+            let game_path = GamePath::relative_to(&source_path, source)?;
+            let (extension, directory, filename) = entry_key(&game_path);
 
             tree.insert(
                 &extension,
@@ -306,24 +1241,46 @@ fn get_vpk_tree(source: &Utf8PlatformPath) -> Result<VpkTree<Entry>, Error> {
         Ok(())
     }
 
-    let mut tree = VpkTree(HashMap::new());
+    let mut tree = VpkTree(BTreeMap::new());
     visit(source, source, &mut tree)?;
 
+    // the order of a directory's entries depends on the filesystem, but the VPK should be the same on every machine
+    for directories in tree.0.values_mut() {
+        for entries in directories.0.values_mut() {
+            entries.sort_by(|a, b| (&a.filename, &a.source_path).cmp(&(&b.filename, &b.source_path)));
+
+            // the engine treats paths which only differ by case as the same file, so only the first is packed
+            entries.dedup_by(|b, a| a.filename == b.filename);
+        }
+    }
+
     Ok(tree)
 }
 
+/// The key of the entry for `game_path` in the dir file's tree.
+fn entry_key(game_path: &GamePath) -> EntryKey {
+    let extension = game_path.extension().unwrap_or(" ").to_string();
+    let filename = game_path.file_stem().to_string();
+
+    // files in the root are written with a " " directory, since an empty string ends the tree's directories
+    let directory = game_path.directory().unwrap_or(" ").to_string();
+    (extension, directory, filename)
+}
+
+/// Entries by extension, then by directory. The maps are sorted so that the same files are always written in the same
+/// order.
 #[derive(Debug, Default)]
-struct VpkTree<T: Sized>(HashMap<String, Directories<T>>);
+struct VpkTree<T: Sized>(BTreeMap<String, Directories<T>>);
 
 #[derive(Debug, Default)]
-struct Directories<T: Sized>(HashMap<String, Vec<T>>);
+struct Directories<T: Sized>(BTreeMap<String, Vec<T>>);
 
 impl<T: Sized> VpkTree<T> {
     pub fn insert(&mut self, extension: &str, directory: &str, entry: T) {
         let Some(directories) = self.0.get_mut(extension) else {
             self.0.insert(
                 extension.to_string(),
-                Directories(HashMap::from([(directory.to_string(), vec![entry])])),
+                Directories(BTreeMap::from([(directory.to_string(), vec![entry])])),
             );
             return;
         };
@@ -337,6 +1294,19 @@ impl<T: Sized> VpkTree<T> {
     }
 }
 
+impl VpkTree<Entry> {
+    /// Removes the entry with `key`, if there is one.
+    fn remove(&mut self, (extension, directory, filename): &EntryKey) {
+        if let Some(entries) = self
+            .0
+            .get_mut(extension)
+            .and_then(|directories| directories.0.get_mut(directory))
+        {
+            entries.retain(|entry| entry.filename != *filename);
+        }
+    }
+}
+
 // struct SizeChunk {
 
 // }
@@ -1,10 +1,11 @@
-use byteorder::WriteBytesExt;
 use std::{
+    collections::BTreeMap,
     fs::OpenOptions,
-    io::{self, Read, Seek, SeekFrom},
+    io::{self, Read, Seek, SeekFrom, Write},
 };
 
 use thiserror::Error;
+use vpk::entry::VPKEntry;
 
 #[derive(Debug, Error)]
 pub enum PatchError {
@@ -31,7 +32,10 @@ pub trait PrintVpkExt {
 pub trait PatchVpkExt {
     /// Patches data over an existing entry in the vpk's tree.
     ///
-    /// The file on disk must have the same size as the file in the VPK, and the file must not have any preload data.
+    /// The file on disk must be no larger than the file in the VPK, and the file must not have any preload data.
+    /// Whatever's left of the entry after the file is filled with 0s, like [`PatchVpkExt::patch_files`] with a single
+    /// patch.
+    /// The archive is synced to disk before this returns, so the patch survives a power loss.
     ///
     /// ## Errors
     ///
@@ -39,11 +43,23 @@ pub trait PatchVpkExt {
     ///
     /// - the file described by `path_in_vpk` does not exist in the vpk
     /// - the file in VPK has a preload data block.
-    /// - the file on disk and file in VPK have different sizes
+    /// - the file on disk is larger than the file in VPK
     /// - the function produced no IO error but wasn't able to write the entire file
     /// - there was an IO error when reading the file on disk
     /// - there was an IO error when writing the file on disk
     fn patch_file(&mut self, path_in_vpk: &str, size: u64, reader: &mut impl Read) -> Result<(), PatchError>;
+
+    /// Patches every `(path_in_vpk, data)` in `patches` over its existing entry, like [`PatchVpkExt::patch_file`].
+    /// Whatever's left of an entry after its data is filled with 0s.
+    ///
+    /// Every patch is checked before anything is written. Then each archive is opened once, its patches are written
+    /// in the order they appear in the archive, and it's synced to disk once they've all been written.
+    ///
+    /// ## Errors
+    ///
+    /// Returns [`Err`] for the same reasons as [`PatchVpkExt::patch_file`]. Nothing is written if any patch is invalid,
+    /// but an IO error can leave some of the patches written.
+    fn patch_files<'a>(&mut self, patches: impl IntoIterator<Item = (&'a str, &'a [u8])>) -> Result<(), PatchError>;
 }
 
 impl PrintVpkExt for vpk::VPK {
@@ -105,41 +121,73 @@ impl PrintVpkExt for vpk::VPK {
 
 impl PatchVpkExt for vpk::VPK {
     fn patch_file(&mut self, path_in_vpk: &str, size: u64, reader: &mut impl Read) -> Result<(), PatchError> {
-        let entry = self
-            .tree
-            .get(path_in_vpk)
-            .ok_or_else(|| PatchError::NotFound(path_in_vpk.to_string()))?;
-
-        if entry.dir_entry.preload_length > 0 {
-            return Err(PatchError::HasPreloadData);
-        }
-
-        let Some(archive_path) = &entry.archive_path else {
-            return Err(PatchError::HasPreloadData);
-        };
-
-        // TODO: what about preload_length? does patch_file need to ever handle preloaded files?
-        let entry_size = u64::from(entry.dir_entry.file_length);
+        // checked before reading, so nothing is read for an entry that can't be patched
+        patchable_entry(self, path_in_vpk, size)?;
 
-        if size > entry_size {
-            return Err(PatchError::InputTooBig(size, path_in_vpk.to_string(), entry_size));
+        let mut data = Vec::new();
+        let read = reader.take(size).read_to_end(&mut data)? as u64;
+        if read != size {
+            return Err(PatchError::PartialWrite(read, size));
         }
 
-        let mut archive_file = OpenOptions::new().write(true).open(archive_path.as_ref())?;
-        archive_file.seek(SeekFrom::Start(u64::from(entry.dir_entry.archive_offset)))?;
+        self.patch_files([(path_in_vpk, data.as_slice())])
+    }
 
-        let copied = io::copy(reader, &mut archive_file)?;
-        if copied != size {
-            return Err(PatchError::PartialWrite(copied, entry_size));
+    fn patch_files<'a>(&mut self, patches: impl IntoIterator<Item = (&'a str, &'a [u8])>) -> Result<(), PatchError> {
+        // every archive's path & patches, by its archive index. Each patch is its `(archive_offset, file_length, data)`
+        let mut archives = BTreeMap::new();
+        for (path_in_vpk, data) in patches {
+            let entry = patchable_entry(self, path_in_vpk, data.len() as u64)?;
+            let archive_path = entry
+                .archive_path
+                .as_ref()
+                .expect("patchable entries are in an archive");
+
+            let entry_size = u64::from(entry.dir_entry.file_length);
+            archives
+                .entry(entry.dir_entry.archive_index)
+                .or_insert_with(|| (archive_path, Vec::new()))
+                .1
+                .push((u64::from(entry.dir_entry.archive_offset), entry_size, data));
         }
 
-        // patched content needs to have the same size as the original, so we pad in 0s to make it fit snuggly.
-        if entry_size < copied {
-            for _ in entry_size..copied {
-                archive_file.write_u8(0)?;
+        for (archive_path, mut patches) in archives.into_values() {
+            patches.sort_by_key(|(offset, _, _)| *offset);
+
+            let mut archive_file = OpenOptions::new().write(true).open(archive_path.as_ref())?;
+            for (offset, entry_size, data) in patches {
+                archive_file.seek(SeekFrom::Start(offset))?;
+                archive_file.write_all(data)?;
+                io::copy(
+                    &mut io::repeat(0).take(entry_size - data.len() as u64),
+                    &mut archive_file,
+                )?;
             }
+
+            archive_file.sync_all()?;
         }
 
         Ok(())
     }
 }
+
+/// Finds the entry at `path_in_vpk`, and checks that `size` bytes can be patched over it.
+fn patchable_entry<'a>(vpk: &'a vpk::VPK, path_in_vpk: &str, size: u64) -> Result<&'a VPKEntry, PatchError> {
+    let entry = vpk
+        .tree
+        .get(path_in_vpk)
+        .or_else(|| vpk.tree.get(crate::matching_key(vpk.tree.keys(), path_in_vpk)?))
+        .ok_or_else(|| PatchError::NotFound(path_in_vpk.to_string()))?;
+
+    // TODO: what about preload_length? does patching ever need to handle preloaded files?
+    if entry.dir_entry.preload_length > 0 || entry.archive_path.is_none() {
+        return Err(PatchError::HasPreloadData);
+    }
+
+    let entry_size = u64::from(entry.dir_entry.file_length);
+    if size > entry_size {
+        return Err(PatchError::InputTooBig(size, path_in_vpk.to_string(), entry_size));
+    }
+
+    Ok(entry)
+}
@@ -0,0 +1,260 @@
+//! Files with identical contents, e.g. the same texture shipped by two addons under different names, are only packed
+//! once, and every copy's entry shares the same data.
+
+use std::{collections::HashMap, env, fs, io::Read, path::PathBuf, process};
+
+use typed_path::Utf8PlatformPathBuf;
+use writevpk::pack::{self, DEFAULT_SPLIT_SIZE, DuplicateFile, GeneratedFile, PackStats};
+
+const TEXTURE: &[u8] = &[0x56, 0x54, 0x46, 0x00, 0x07, 0x00, 0x00, 0x00, 0x02, 0x00, 0x00, 0x00];
+
+const FILES: [(&str, &[u8]); 4] = [
+    ("materials/effects/flame.vtf", TEXTURE),
+    ("materials/effects/flame.vmt", b"\"UnlitGeneric\" {}"),
+    ("materials/models/rocket.vtf", TEXTURE),
+    ("materials/models/rocket.vmt", b"\"VertexLitGeneric\" {}"),
+];
+
+/// A directory which is removed when it's dropped.
+struct TempDir(PathBuf);
+
+impl TempDir {
+    fn new(name: &str) -> Self {
+        let path = env::temp_dir().join(format!("writevpk-{name}-{}", process::id()));
+        let _ = fs::remove_dir_all(&path);
+        fs::create_dir_all(path.join("source")).unwrap();
+        fs::create_dir_all(path.join("dest")).unwrap();
+        Self(path)
+    }
+
+    fn join(&self, path: &str) -> Utf8PlatformPathBuf {
+        Utf8PlatformPathBuf::from(self.0.join(path).to_str().unwrap())
+    }
+
+    fn write_files(&self, files: &[(&str, &[u8])]) {
+        for (path, contents) in files {
+            let path = self.0.join("source").join(path);
+            fs::create_dir_all(path.parent().unwrap()).unwrap();
+            fs::write(path, contents).unwrap();
+        }
+    }
+}
+
+impl Drop for TempDir {
+    fn drop(&mut self) {
+        let _ = fs::remove_dir_all(&self.0);
+    }
+}
+
+#[test]
+fn duplicates_are_found_in_packing_order() {
+    let dir = TempDir::new("find-duplicates");
+    dir.write_files(&FILES);
+
+    assert_eq!(
+        pack::find_duplicates(&dir.join("source")).unwrap(),
+        [DuplicateFile {
+            path: "materials/models/rocket.vtf".to_string(),
+            original: "materials/effects/flame.vtf".to_string(),
+            size: TEXTURE.len() as u32,
+        }]
+    );
+}
+
+#[test]
+fn duplicates_are_only_written_once() {
+    let dir = TempDir::new("dedup-pack");
+    dir.write_files(&FILES);
+
+    let stats = pack::pack_directory(
+        &dir.join("source"),
+        &dir.join("dest"),
+        "addons",
+        DEFAULT_SPLIT_SIZE,
+        None,
+    )
+    .unwrap();
+    assert_eq!(
+        stats,
+        PackStats {
+            written_files: 3,
+            written_bytes: FILES[..2]
+                .iter()
+                .chain(&FILES[3..])
+                .map(|(_, data)| data.len() as u64)
+                .sum(),
+            deduplicated_files: 1,
+            deduplicated_bytes: TEXTURE.len() as u64,
+            ..PackStats::default()
+        }
+    );
+
+    // the same files with a different rocket.vtf have the same tree, but need a second copy of the texture
+    let distinct = TempDir::new("dedup-distinct");
+    distinct.write_files(&FILES);
+    distinct.write_files(&[("materials/models/rocket.vtf", &[0; TEXTURE.len()])]);
+    pack::pack_directory(
+        &distinct.join("source"),
+        &distinct.join("dest"),
+        "addons",
+        DEFAULT_SPLIT_SIZE,
+        None,
+    )
+    .unwrap();
+
+    let size = |dir: &TempDir| fs::metadata(dir.join("dest/addons.vpk")).unwrap().len();
+    assert_eq!(size(&dir) + TEXTURE.len() as u64, size(&distinct));
+}
+
+#[test]
+fn incremental_builds_deduplicate_reused_files() {
+    let dir = TempDir::new("dedup-incremental");
+    dir.write_files(&FILES[..2]);
+    pack::pack_directory_incremental(
+        &dir.join("source"),
+        &dir.join("dest"),
+        "addons",
+        DEFAULT_SPLIT_SIZE,
+        None,
+    )
+    .unwrap();
+
+    // the new rocket.vtf is identical to the flame.vtf reused from the previous build, so it shares its copy
+    dir.write_files(&FILES[2..]);
+    let stats = pack::pack_directory_incremental(
+        &dir.join("source"),
+        &dir.join("dest"),
+        "addons",
+        DEFAULT_SPLIT_SIZE,
+        None,
+    )
+    .unwrap();
+
+    assert_eq!((stats.reused_files, stats.written_files), (2, 1));
+    assert_eq!(
+        (stats.deduplicated_files, stats.deduplicated_bytes),
+        (1, TEXTURE.len() as u64)
+    );
+}
+
+/// Two different 8 byte files with the same CRC32, found by a birthday search.
+fn crc_collision() -> ([u8; 8], [u8; 8]) {
+    let mut seen = HashMap::new();
+    for idx in 0u64.. {
+        let contents = idx.wrapping_mul(0x9E37_79B9_7F4A_7C15).to_le_bytes();
+        if let Some(other) = seen.insert(crc32fast::hash(&contents), contents) {
+            return (other, contents);
+        }
+    }
+
+    unreachable!()
+}
+
+#[test]
+fn incremental_builds_rewrite_changed_files_with_the_same_crc() {
+    let (before, after) = crc_collision();
+    assert_ne!(before, after);
+
+    let dir = TempDir::new("crc-collision");
+    dir.write_files(&[("materials/effects/flame.vtf", &before)]);
+    pack::pack_directory_incremental(
+        &dir.join("source"),
+        &dir.join("dest"),
+        "addons",
+        DEFAULT_SPLIT_SIZE,
+        None,
+    )
+    .unwrap();
+
+    // the dir file only records the size & CRC, which both still match
+    dir.write_files(&[("materials/effects/flame.vtf", &after)]);
+    let stats = pack::pack_directory_incremental(
+        &dir.join("source"),
+        &dir.join("dest"),
+        "addons",
+        DEFAULT_SPLIT_SIZE,
+        None,
+    )
+    .unwrap();
+    assert_eq!((stats.reused_files, stats.written_files), (0, 1));
+
+    let vpk = vpk::from_path(dir.join("dest/addons_dir.vpk").as_str()).unwrap();
+    let mut packed = Vec::new();
+    vpk.tree["materials/effects/flame.vtf"]
+        .reader()
+        .unwrap()
+        .read_to_end(&mut packed)
+        .unwrap();
+    assert_eq!(packed, after);
+}
+
+#[test]
+fn incremental_builds_dont_write_into_hard_linked_archives() {
+    let dir = TempDir::new("hard-links");
+    dir.write_files(&[("materials/effects/flame.vtf", TEXTURE)]);
+    fs::create_dir_all(dir.join("previous")).unwrap();
+    pack::pack_directory_incremental(
+        &dir.join("source"),
+        &dir.join("previous"),
+        "addons",
+        DEFAULT_SPLIT_SIZE,
+        None,
+    )
+    .unwrap();
+
+    let previous_archive = fs::read(dir.join("previous/addons_000.vpk")).unwrap();
+    for file_name in ["addons_dir.vpk", "addons_000.vpk"] {
+        fs::hard_link(dir.join("previous").join(file_name), dir.join("dest").join(file_name)).unwrap();
+    }
+
+    // nothing in the linked archive is still used, so it's replaced by a new archive with the same name
+    dir.write_files(&[("materials/effects/flame.vtf", &[0; TEXTURE.len()])]);
+    let stats = pack::pack_directory_incremental(
+        &dir.join("source"),
+        &dir.join("dest"),
+        "addons",
+        DEFAULT_SPLIT_SIZE,
+        None,
+    )
+    .unwrap();
+    assert_eq!((stats.reused_files, stats.written_files), (0, 1));
+
+    assert_eq!(fs::read(dir.join("previous/addons_000.vpk")).unwrap(), previous_archive);
+    assert_ne!(fs::read(dir.join("dest/addons_000.vpk")).unwrap(), previous_archive);
+}
+
+#[test]
+fn generated_files_record_the_duplicates_found_while_packing() {
+    let dir = TempDir::new("dedup-generated");
+    dir.write_files(&FILES);
+    dir.write_files(&[("docs/manifest.txt", b"stale")]);
+
+    let generated = GeneratedFile {
+        path: "docs/manifest.txt",
+        generate: Box::new(|duplicates| {
+            duplicates
+                .iter()
+                .map(|duplicate| format!("{} {}\n", duplicate.path, duplicate.original))
+                .collect::<String>()
+                .into_bytes()
+        }),
+    };
+    pack::pack_directory(
+        &dir.join("source"),
+        &dir.join("dest"),
+        "addons",
+        DEFAULT_SPLIT_SIZE,
+        Some(generated),
+    )
+    .unwrap();
+
+    // the generated file replaces the stale one in the packed directory
+    let vpk = vpk::from_path(dir.join("dest/addons.vpk").as_str()).unwrap();
+    let mut packed = String::new();
+    vpk.tree["docs/manifest.txt"]
+        .reader()
+        .unwrap()
+        .read_to_string(&mut packed)
+        .unwrap();
+    assert_eq!(packed, "materials/models/rocket.vtf materials/effects/flame.vtf\n");
+}
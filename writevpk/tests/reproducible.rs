@@ -0,0 +1,106 @@
+//! Packing the same files must always produce the same bytes, no matter which order the filesystem lists them in, so
+//! that installs are reproducible across runs & machines.
+
+use std::{
+    env, fs,
+    path::{Path, PathBuf},
+    process,
+};
+
+use typed_path::Utf8PlatformPathBuf;
+use writevpk::pack::{self, DEFAULT_SPLIT_SIZE};
+
+const FILES: [(&str, &[u8]); 6] = [
+    ("materials/effects/flame.vmt", b"\"UnlitGeneric\" {}"),
+    (
+        "materials/effects/flame.vtf",
+        &[0x56, 0x54, 0x46, 0x00, 0x07, 0x00, 0x00, 0x00],
+    ),
+    ("materials/effects/smoke.vmt", b"\"SpriteCard\" {}"),
+    (
+        "particles/flamethrower.pcf",
+        b"<!-- dmx encoding binary 2 format pcf 1 -->",
+    ),
+    ("scripts/game_sounds_weapons.txt", b"\"Weapon_FlameThrower.Fire\" {}"),
+    ("readme.txt", b"not in a folder"),
+];
+
+/// A directory which is removed when it's dropped.
+struct TempDir(PathBuf);
+
+impl TempDir {
+    fn new(name: &str) -> Self {
+        let path = env::temp_dir().join(format!("writevpk-{name}-{}", process::id()));
+        let _ = fs::remove_dir_all(&path);
+        fs::create_dir_all(&path).unwrap();
+        Self(path)
+    }
+
+    fn join(&self, path: &str) -> PathBuf {
+        self.0.join(path)
+    }
+}
+
+impl Drop for TempDir {
+    fn drop(&mut self) {
+        let _ = fs::remove_dir_all(&self.0);
+    }
+}
+
+fn typed(path: &Path) -> Utf8PlatformPathBuf {
+    Utf8PlatformPathBuf::from(path.to_str().unwrap())
+}
+
+/// Writes `files` in the order given, packs them, and returns every file that was packed along with its contents.
+fn pack<'a>(
+    name: &str,
+    files: impl Iterator<Item = &'a (&'a str, &'a [u8])>,
+    split_size: u32,
+) -> Vec<(String, Vec<u8>)> {
+    let dir = TempDir::new(name);
+    for (path, contents) in files {
+        let path = dir.join(&format!("source/{path}"));
+        fs::create_dir_all(path.parent().unwrap()).unwrap();
+        fs::write(path, contents).unwrap();
+    }
+
+    fs::create_dir_all(dir.join("dest")).unwrap();
+    pack::pack_directory(
+        &typed(&dir.join("source")),
+        &typed(&dir.join("dest")),
+        "addons",
+        split_size,
+        None,
+    )
+    .unwrap();
+
+    let mut packed: Vec<_> = fs::read_dir(dir.join("dest"))
+        .unwrap()
+        .map(|entry| {
+            let entry = entry.unwrap();
+            let contents = fs::read(entry.path()).unwrap();
+            (entry.file_name().into_string().unwrap(), contents)
+        })
+        .collect();
+
+    packed.sort();
+    packed
+}
+
+#[test]
+fn single_file_vpks_are_reproducible() {
+    let forward = pack("single-forward", FILES.iter(), DEFAULT_SPLIT_SIZE);
+    let reverse = pack("single-reverse", FILES.iter().rev(), DEFAULT_SPLIT_SIZE);
+
+    assert_eq!(forward.len(), 1);
+    assert_eq!(forward, reverse);
+}
+
+#[test]
+fn split_vpks_are_reproducible() {
+    let forward = pack("split-forward", FILES.iter(), 32);
+    let reverse = pack("split-reverse", FILES.iter().rev(), 32);
+
+    assert!(forward.len() > 2);
+    assert_eq!(forward, reverse);
+}